@@ -17,6 +17,10 @@ def extract_data(filename):
     with open(filename, "r") as fp:
         data = json.load(fp)
 
+    # newer versions store the flows next to the per-step verdicts
+    if isinstance(data, dict):
+        data = data["flows"]
+
     # get the total number of packets
     num_success = 0
     num_fail = 0
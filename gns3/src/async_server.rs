@@ -0,0 +1,573 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Asynchronous GNS3 Server
+//!
+//! This module is only available with the feature `async`. In contrast to [`GNS3Server`], all
+//! requests are sent asynchronously (using tokio), such that many nodes and links can be created
+//! concurrently. All requests share the same pool of connections. The number of concurrent
+//! requests, and the time between two requests, are limited by the [`RequestPolicy`], and failed
+//! idempotent requests (GET, PUT and DELETE) are retried.
+//!
+//! [`GNS3Server`]: crate::GNS3Server
+
+use crate::server::check_response;
+use crate::types::*;
+use crate::{Error, Result};
+
+use futures::future::try_join_all;
+use futures::io::AsyncReadExt;
+use isahc::prelude::*;
+use isahc::HttpClient;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
+
+/// # Request Policy
+///
+/// Limits and retries of the requests sent by the [`AsyncGNS3Server`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestPolicy {
+    /// Maximum number of requests (and connections) at the same time
+    pub max_concurrent: usize,
+    /// Minimum time between the start of two requests
+    pub min_interval: Duration,
+    /// Number of times a failed idempotent request (GET, PUT or DELETE) is retried. POST requests
+    /// are never retried, since the server might have created the object before the request failed.
+    pub max_retries: usize,
+    /// Time to wait before the first retry. The time doubles for every further retry.
+    pub retry_delay: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 8,
+            min_interval: Duration::from_millis(10),
+            max_retries: 3,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RequestPolicy {
+    /// Time to wait before the given retry (starting at 1).
+    pub fn backoff(&self, retry: usize) -> Duration {
+        self.retry_delay * 2u32.pow(retry.saturating_sub(1).min(16) as u32)
+    }
+
+    /// Returns `true` if an idempotent request should be retried after this error. This is the case
+    /// for errors of the connection, for server errors (5xx), and for conflicts (409), for instance
+    /// if a node is still locked.
+    pub fn is_retryable(error: &Error) -> bool {
+        match error {
+            Error::HTTPError(_) => true,
+            Error::ResponseError(status, _) => *status >= 500 || *status == 409,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Method {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+impl Method {
+    /// Returns `true` if sending the request multiple times has the same effect as sending it once.
+    fn is_idempotent(&self) -> bool {
+        !matches!(self, Self::Post)
+    }
+}
+
+/// # Asynchronous GNS3 Server Handle
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone)]
+pub struct AsyncGNS3Server {
+    address: String,
+    version: String,
+    project: Option<String>,
+    client: Arc<HttpClient>,
+    limiter: Arc<Semaphore>,
+    last_request: Arc<Mutex<Instant>>,
+    policy: RequestPolicy,
+}
+
+impl AsyncGNS3Server {
+    /// Create a new instance of a server handler, with the default [`RequestPolicy`].
+    pub async fn new(address: impl AsRef<str>, port: u32) -> Result<Self> {
+        Self::with_policy(address, port, RequestPolicy::default()).await
+    }
+
+    /// Create a new instance of a server handler, with the given [`RequestPolicy`].
+    pub async fn with_policy(
+        address: impl AsRef<str>,
+        port: u32,
+        policy: RequestPolicy,
+    ) -> Result<Self> {
+        let client = HttpClient::builder()
+            .max_connections(policy.max_concurrent)
+            .build()?;
+        let mut server = Self {
+            address: format!("http://{}:{}", address.as_ref(), port),
+            version: String::new(),
+            project: None,
+            client: Arc::new(client),
+            limiter: Arc::new(Semaphore::new(policy.max_concurrent)),
+            last_request: Arc::new(Mutex::new(Instant::now())),
+            policy,
+        };
+        let v: GNS3ResponseVersion = serde_json::from_str(&server.request_get("version").await?)?;
+        server.version = v.version;
+        Ok(server)
+    }
+
+    /// Get the version
+    pub fn version(&self) -> &str {
+        self.version.as_ref()
+    }
+
+    /// Returns the ID of the opened project
+    pub fn project_id(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    /// Returns all project informations
+    pub async fn get_projects(&self) -> Result<Vec<GNS3Project>> {
+        Ok(serde_json::from_str(&self.request_get("projects").await?)?)
+    }
+
+    /// Open an existing project, without checking its status.
+    pub fn select_project(&mut self, project_id: impl Into<String>) {
+        self.project = Some(project_id.into());
+    }
+
+    /// Create a new project with the given name, and open it
+    pub async fn create_project(&mut self, project_name: impl AsRef<str>) -> Result<GNS3Project> {
+        let project: GNS3Project = serde_json::from_str(
+            &self
+                .request_post(
+                    "projects",
+                    format!("{{\"name\": \"{}\"}}", project_name.as_ref()),
+                )
+                .await?,
+        )?;
+        self.project = Some(project.id.clone());
+        Ok(project)
+    }
+
+    /// Delete an existing project
+    pub async fn delete_project(&mut self, project_id: impl AsRef<str>) -> Result<()> {
+        if self.project == Some(project_id.as_ref().to_string()) {
+            self.project = None;
+        }
+        match self
+            .request(
+                Method::Delete,
+                format!("projects/{}", project_id.as_ref()),
+                None,
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(Error::GNS3Error { id, .. }) if (200..300).contains(&id) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns all available templates
+    pub async fn get_templates(&self) -> Result<Vec<GNS3Template>> {
+        Ok(serde_json::from_str(&self.request_get("templates").await?)?)
+    }
+
+    /// Create a new node from a template
+    pub async fn create_node(
+        &self,
+        name: impl AsRef<str>,
+        template_id: impl AsRef<str>,
+    ) -> Result<GNS3Node> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        let node: GNS3Node = serde_json::from_str(
+            &self
+                .request_post(
+                    format!("projects/{}/templates/{}", project_id, template_id.as_ref()),
+                    format!("{{\"name\": \"{}\", \"x\": 0, \"y\": 0}}", name.as_ref()),
+                )
+                .await?,
+        )?;
+        self.modify_node(node.id, Some(name.as_ref().to_string()), None)
+            .await
+    }
+
+    /// Create many nodes concurrently. Every node is given by its name and the template ID. The
+    /// nodes are returned in the same order.
+    pub async fn create_nodes(&self, nodes: &[(String, String)]) -> Result<Vec<GNS3Node>> {
+        try_join_all(
+            nodes
+                .iter()
+                .map(|(name, template_id)| self.create_node(name, template_id)),
+        )
+        .await
+    }
+
+    /// Modify the name or the port ID of a node
+    pub async fn modify_node(
+        &self,
+        node_id: impl AsRef<str>,
+        name: Option<String>,
+        port: Option<u32>,
+    ) -> Result<GNS3Node> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        let mut options_vec: Vec<String> = Vec::new();
+        if let Some(name) = name {
+            options_vec.push(format!("\"name\": \"{}\"", name));
+        }
+        if let Some(port) = port {
+            options_vec.push(format!("\"console\": {}", port));
+        }
+        Ok(serde_json::from_str(
+            &self
+                .request(
+                    Method::Put,
+                    format!("projects/{}/nodes/{}", project_id, node_id.as_ref()),
+                    Some(format!("{{ {} }}", options_vec.join(", "))),
+                )
+                .await?,
+        )?)
+    }
+
+    /// Return all nodes in the project
+    pub async fn get_nodes(&self) -> Result<Vec<GNS3Node>> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        Ok(serde_json::from_str(
+            &self
+                .request_get(format!("projects/{}/nodes", project_id))
+                .await?,
+        )?)
+    }
+
+    /// Create a new link. The interface is used as an index into the interfaces of the
+    /// corresponding node.
+    pub async fn create_link(
+        &self,
+        node_a: &GNS3Node,
+        iface_a: usize,
+        node_b: &GNS3Node,
+        iface_b: usize,
+    ) -> Result<GNS3Link> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        Ok(serde_json::from_str(
+            &self
+                .request_post(
+                    format!("projects/{}/links", project_id),
+                    format!(
+                        "{{ \"nodes\": [ {}, {} ] }}",
+                        GNS3LinkEndpoint::from_node(node_a, iface_a),
+                        GNS3LinkEndpoint::from_node(node_b, iface_b)
+                    ),
+                )
+                .await?,
+        )?)
+    }
+
+    /// Create many links concurrently. Every link is given by both nodes and the index of their
+    /// interfaces. The links are returned in the same order.
+    pub async fn create_links(
+        &self,
+        links: &[(&GNS3Node, usize, &GNS3Node, usize)],
+    ) -> Result<Vec<GNS3Link>> {
+        try_join_all(
+            links
+                .iter()
+                .map(|(a, iface_a, b, iface_b)| self.create_link(a, *iface_a, b, *iface_b)),
+        )
+        .await
+    }
+
+    /// Return all links in the project
+    pub async fn get_links(&self) -> Result<Vec<GNS3Link>> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        Ok(serde_json::from_str(
+            &self
+                .request_get(format!("projects/{}/links", project_id))
+                .await?,
+        )?)
+    }
+
+    /// Start the capture on a specific link
+    pub async fn start_capture(&self, link: impl AsRef<str>) -> Result<GNS3Link> {
+        self.link_action(link, "start_capture").await
+    }
+
+    /// Stop the capture on a specific link
+    pub async fn stop_capture(&self, link: impl AsRef<str>) -> Result<GNS3Link> {
+        self.link_action(link, "stop_capture").await
+    }
+
+    /// Download the capture file of a link (in the pcap format) to `path`, and return the number of
+    /// bytes written (see [`GNS3Server::download_capture`](crate::GNS3Server::download_capture)).
+    pub async fn download_capture(
+        &self,
+        link: impl AsRef<str>,
+        path: impl AsRef<Path>,
+    ) -> Result<u64> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        let addr = format!(
+            "{}/v2/projects/{}/links/{}/pcap",
+            self.address,
+            project_id,
+            link.as_ref()
+        );
+        let mut response = {
+            let _permit = self
+                .limiter
+                .acquire()
+                .await
+                .expect("The semaphore is never closed");
+            self.throttle().await;
+            self.client.get_async(addr).await?
+        };
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::ResponseError(
+                status.as_u16(),
+                response.text_async().await?,
+            ));
+        }
+        let mut data = Vec::new();
+        response.body_mut().read_to_end(&mut data).await?;
+        std::fs::write(path, &data)?;
+        Ok(data.len() as u64)
+    }
+
+    /// Start all nodes in the project
+    pub async fn start_all_nodes(&self) -> Result<()> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        self.request_post(
+            format!("projects/{}/nodes/start", project_id),
+            String::from("{}"),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Stop all nodes in the project
+    pub async fn stop_all_nodes(&self) -> Result<()> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        self.request_post(
+            format!("projects/{}/nodes/stop", project_id),
+            String::from("{}"),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Start a specific node
+    pub async fn start_node(&self, node_id: impl AsRef<str>) -> Result<GNS3Node> {
+        self.node_action(node_id, "start").await
+    }
+
+    /// Stop a specific node
+    pub async fn stop_node(&self, node_id: impl AsRef<str>) -> Result<GNS3Node> {
+        self.node_action(node_id, "stop").await
+    }
+
+    async fn node_action(&self, node_id: impl AsRef<str>, action: &str) -> Result<GNS3Node> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        Ok(serde_json::from_str(
+            &self
+                .request_post(
+                    format!(
+                        "projects/{}/nodes/{}/{}",
+                        project_id,
+                        node_id.as_ref(),
+                        action
+                    ),
+                    String::from("{}"),
+                )
+                .await?,
+        )?)
+    }
+
+    async fn link_action(&self, link: impl AsRef<str>, action: &str) -> Result<GNS3Link> {
+        let project_id = self.project_id().ok_or(Error::NoProjectOpened)?;
+        Ok(serde_json::from_str(
+            &self
+                .request_post(
+                    format!("projects/{}/links/{}/{}", project_id, link.as_ref(), action),
+                    String::from("{}"),
+                )
+                .await?,
+        )?)
+    }
+
+    async fn request_get(&self, key: impl AsRef<str>) -> Result<String> {
+        self.request(Method::Get, key, None).await
+    }
+
+    async fn request_post(&self, key: impl AsRef<str>, data: String) -> Result<String> {
+        self.request(Method::Post, key, Some(data)).await
+    }
+
+    /// Send a request, respecting the limits of the policy, and retry it if it fails and is
+    /// idempotent.
+    async fn request(
+        &self,
+        method: Method,
+        key: impl AsRef<str>,
+        data: Option<String>,
+    ) -> Result<String> {
+        let addr = format!("{}/v2/{}", self.address, key.as_ref());
+        let mut retry = 0;
+        loop {
+            let result = {
+                let _permit = self
+                    .limiter
+                    .acquire()
+                    .await
+                    .expect("The semaphore is never closed");
+                self.throttle().await;
+                self.send(method, &addr, data.clone()).await
+            };
+            match result {
+                Err(e)
+                    if retry < self.policy.max_retries
+                        && method.is_idempotent()
+                        && RequestPolicy::is_retryable(&e) =>
+                {
+                    retry += 1;
+                    tokio::time::sleep(self.policy.backoff(retry)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Wait until the minimum interval since the last request has passed.
+    async fn throttle(&self) {
+        let mut last_request = self.last_request.lock().await;
+        let next = *last_request + self.policy.min_interval;
+        let now = Instant::now();
+        if next > now {
+            tokio::time::sleep(next - now).await;
+        }
+        *last_request = Instant::now();
+    }
+
+    async fn send(&self, method: Method, addr: &str, data: Option<String>) -> Result<String> {
+        let data = data.unwrap_or_default();
+        let mut response = match method {
+            Method::Get => self.client.get_async(addr).await?,
+            Method::Post => self.client.post_async(addr, data).await?,
+            Method::Put => self.client.put_async(addr, data).await?,
+            Method::Delete => self.client.delete_async(addr).await?,
+        };
+        let status = response.status().as_u16();
+        check_response(status, response.text_async().await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_PROJECT_NAME: &str = "AsyncTestProjectForTestPurpose";
+
+    #[test]
+    fn backoff() {
+        let policy = RequestPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn retryable() {
+        assert!(RequestPolicy::is_retryable(&Error::ResponseError(
+            503,
+            String::new()
+        )));
+        assert!(RequestPolicy::is_retryable(&Error::ResponseError(
+            409,
+            String::new()
+        )));
+        assert!(!RequestPolicy::is_retryable(&Error::ResponseError(
+            404,
+            String::new()
+        )));
+        assert!(!RequestPolicy::is_retryable(&Error::NoProjectOpened));
+    }
+
+    #[test]
+    fn idempotent() {
+        assert!(Method::Get.is_idempotent());
+        assert!(Method::Put.is_idempotent());
+        assert!(Method::Delete.is_idempotent());
+        assert!(!Method::Post.is_idempotent());
+    }
+
+    #[tokio::test]
+    async fn create_nodes_and_links() {
+        let mut server = match AsyncGNS3Server::new("localhost", 3080).await {
+            Ok(s) => s,
+            Err(_) => return, // skip the test
+        };
+        if let Some(project) = server
+            .get_projects()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|p| p.name == TEST_PROJECT_NAME)
+        {
+            server.delete_project(project.id).await.unwrap();
+        }
+        let project = server.create_project(TEST_PROJECT_NAME).await.unwrap();
+        let frr_id = server
+            .get_templates()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|t| t.name == "FRR 7.3.1")
+            .unwrap()
+            .id;
+        let names = (0..10).map(|i| format!("node_{}", i)).collect::<Vec<_>>();
+        let nodes = server
+            .create_nodes(
+                &names
+                    .iter()
+                    .map(|n| (n.clone(), frr_id.clone()))
+                    .collect::<Vec<_>>(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            nodes.iter().map(|n| &n.name).collect::<Vec<_>>(),
+            names.iter().collect::<Vec<_>>()
+        );
+        let links = nodes
+            .windows(2)
+            .map(|w| (&w[0], 1, &w[1], 0))
+            .collect::<Vec<_>>();
+        server.create_links(&links).await.unwrap();
+        assert_eq!(server.get_links().await.unwrap().len(), 9);
+        server.delete_project(&project.id).await.unwrap();
+    }
+}
@@ -60,10 +60,18 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! With the feature `async`, the [`AsyncGNS3Server`] sends all requests asynchronously (using
+//! tokio), such that many nodes and links can be created concurrently. The [`GNS3Server`] remains
+//! available as a blocking API.
 #![deny(missing_docs)]
 
+#[cfg(feature = "async")]
+mod async_server;
 mod server;
 mod types;
+#[cfg(feature = "async")]
+pub use async_server::{AsyncGNS3Server, RequestPolicy};
 pub use server::GNS3Server;
 pub use types::*;
 
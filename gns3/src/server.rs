@@ -22,6 +22,7 @@ use crate::{Error, Result};
 
 use isahc::prelude::*;
 use regex::Regex;
+use std::path::Path;
 
 /// # GNS3 Server Handle
 #[allow(clippy::upper_case_acronyms)]
@@ -217,6 +218,26 @@ impl GNS3Server {
         )?)?)
     }
 
+    /// Download the capture file of a link (in the pcap format) to `path`, and return the number of
+    /// bytes written. This also works while the capture is still running. Use this function if the
+    /// `capture_file_path` of the link is not accessible, because the server runs on a different
+    /// machine.
+    pub fn download_capture(&self, link: impl AsRef<str>, path: impl AsRef<Path>) -> Result<u64> {
+        let project_id: String = self.project.as_ref().ok_or(Error::NoProjectOpened)?.clone();
+        let addr = format!(
+            "{}/v2/projects/{}/links/{}/pcap",
+            self.address,
+            project_id,
+            link.as_ref()
+        );
+        let mut response = isahc::get(&addr)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::ResponseError(status.as_u16(), response.text()?));
+        }
+        Ok(response.copy_to_file(path)?)
+    }
+
     /// Stop the capture on a specific link
     pub fn clear_capture_file(&self, link: impl AsRef<str>) -> Result<GNS3Link> {
         self.stop_capture(link.as_ref())?;
@@ -261,6 +282,70 @@ impl GNS3Server {
         )?)?)
     }
 
+    /// Create a snapshot of the opened project. The project can later be reset to this snapshot
+    /// with [`GNS3Server::restore_snapshot`].
+    pub fn create_snapshot(&self, name: impl AsRef<str>) -> Result<GNS3Snapshot> {
+        let project_id: String = self.project.as_ref().ok_or(Error::NoProjectOpened)?.clone();
+        Ok(serde_json::from_str(&self.request_post(
+            format!("projects/{}/snapshots", project_id),
+            format!("{{\"name\": \"{}\"}}", name.as_ref()),
+        )?)?)
+    }
+
+    /// Returns all snapshots of the opened project
+    pub fn get_snapshots(&self) -> Result<Vec<GNS3Snapshot>> {
+        let project_id: String = self.project.as_ref().ok_or(Error::NoProjectOpened)?.clone();
+        Ok(serde_json::from_str(&self.request_get(format!(
+            "projects/{}/snapshots",
+            project_id
+        ))?)?)
+    }
+
+    /// Reset the opened project to a snapshot, and return the new project information. All nodes
+    /// are stopped after the snapshot is restored.
+    pub fn restore_snapshot(&self, snapshot_id: impl AsRef<str>) -> Result<GNS3Project> {
+        let project_id: String = self.project.as_ref().ok_or(Error::NoProjectOpened)?.clone();
+        Ok(serde_json::from_str(&self.request_post(
+            format!(
+                "projects/{}/snapshots/{}/restore",
+                project_id,
+                snapshot_id.as_ref()
+            ),
+            String::from("{}"),
+        )?)?)
+    }
+
+    /// Delete a snapshot of the opened project
+    pub fn delete_snapshot(&self, snapshot_id: impl AsRef<str>) -> Result<()> {
+        let project_id: String = self.project.as_ref().ok_or(Error::NoProjectOpened)?.clone();
+        self.request_delete(format!(
+            "projects/{}/snapshots/{}",
+            project_id,
+            snapshot_id.as_ref()
+        ))
+    }
+
+    /// Returns the console of a node in the opened project. The telnet console is expected on the
+    /// same host as the server.
+    pub fn node_console(&self, node: &GNS3Node) -> Result<GNS3Console> {
+        let project_id: String = self.project.as_ref().ok_or(Error::NoProjectOpened)?.clone();
+        let host = self.address.trim_start_matches("http://");
+        let host = match host.rfind(':') {
+            Some(pos) => &host[..pos],
+            None => host,
+        };
+        Ok(GNS3Console {
+            host: host.to_string(),
+            port: node.port,
+            websocket_url: format!(
+                "{}/v2/projects/{}/nodes/{}/console/ws",
+                self.address.replacen("http://", "ws://", 1),
+                project_id,
+                node.id
+            ),
+        })
+    }
+
     /// Delete an existing project
     pub fn delete_project(&mut self, project_id: impl AsRef<str>) -> Result<()> {
         if self.project == Some(project_id.as_ref().to_string()) {
@@ -298,26 +383,31 @@ impl GNS3Server {
     }
 
     fn handle_response(&self, mut response: Response<Body>) -> Result<String> {
-        let status = response.status();
-        if !status.is_success() {
-            return Err(Error::ResponseError(status.as_u16(), response.text()?));
-        }
-        let response = response.text()?;
-        let error_re = Regex::new(r"^(\d*): (.*)$").unwrap();
-        if let Some(captures) = error_re.captures(&response) {
-            if captures.len() == 3 {
-                let error_id: u32 = captures.get(1).unwrap().as_str().parse().unwrap();
-                let error_text: String = captures.get(2).unwrap().as_str().to_string();
-                Err(Error::GNS3Error {
-                    id: error_id,
-                    message: error_text,
-                })
-            } else {
-                panic!("Unexpected Error Received! {}", response)
-            }
+        let status = response.status().as_u16();
+        check_response(status, response.text()?)
+    }
+}
+
+/// Check the status and the body of a response. Returns an error if the status is not successful,
+/// or if the body contains a GNS3 error message.
+pub(crate) fn check_response(status: u16, response: String) -> Result<String> {
+    if !(200..300).contains(&status) {
+        return Err(Error::ResponseError(status, response));
+    }
+    let error_re = Regex::new(r"^(\d*): (.*)$").unwrap();
+    if let Some(captures) = error_re.captures(&response) {
+        if captures.len() == 3 {
+            let error_id: u32 = captures.get(1).unwrap().as_str().parse().unwrap();
+            let error_text: String = captures.get(2).unwrap().as_str().to_string();
+            Err(Error::GNS3Error {
+                id: error_id,
+                message: error_text,
+            })
         } else {
-            Ok(response)
+            panic!("Unexpected Error Received! {}", response)
         }
+    } else {
+        Ok(response)
     }
 }
 
@@ -329,6 +419,25 @@ mod test {
 
     const TEST_PROJECT_NAME: &str = "TestProjectForTestPurpose";
 
+    #[test]
+    fn response() {
+        assert_eq!(
+            check_response(200, String::from("{}")).unwrap(),
+            String::from("{}")
+        );
+        match check_response(404, String::from("not found")) {
+            Err(Error::ResponseError(404, msg)) => assert_eq!(msg, "not found"),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        match check_response(200, String::from("409: Node is locked")) {
+            Err(Error::GNS3Error { id, message }) => {
+                assert_eq!(id, 409);
+                assert_eq!(message, "Node is locked");
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
     #[test]
     fn new_server() {
         let server = match GNS3Server::new("localhost", 3080) {
@@ -425,6 +534,38 @@ mod test {
         server.delete_project(&project.id).unwrap();
     }
 
+    #[test]
+    fn capture_link() {
+        let mut server = match GNS3Server::new("localhost", 3080) {
+            Ok(s) => s,
+            Err(_) => return, // skip the test
+        };
+        delete_test_project(&mut server, TEST_PROJECT_NAME);
+        let project = server.create_project(TEST_PROJECT_NAME).unwrap();
+        let frr_id = server
+            .get_templates()
+            .unwrap()
+            .iter()
+            .find(|t| t.name == "FRR 7.3.1")
+            .unwrap()
+            .id
+            .clone();
+        let node_a = server.create_node("node_a", &frr_id).unwrap();
+        let node_b = server.create_node("node_b", &frr_id).unwrap();
+        let link = server.create_link(&node_a, 0, &node_b, 0).unwrap();
+        server.start_all_nodes().unwrap();
+        assert!(server.start_capture(&link.id).unwrap().capturing);
+        sleep(Duration::from_millis(1000));
+        assert!(!server.stop_capture(&link.id).unwrap().capturing);
+        let path = std::env::temp_dir().join("gns3_test_capture.pcap");
+        // every pcap file starts with a 24 byte global header
+        assert!(server.download_capture(&link.id, &path).unwrap() >= 24);
+        std::fs::remove_file(&path).unwrap();
+        server.stop_all_nodes().unwrap();
+        server.close_project().unwrap();
+        server.delete_project(&project.id).unwrap();
+    }
+
     #[test]
     fn start_stop_node() {
         let mut server = match GNS3Server::new("localhost", 3080) {
@@ -499,6 +640,65 @@ mod test {
         server.delete_project(&project.id).unwrap();
     }
 
+    #[test]
+    fn console() {
+        let mut server = GNS3Server {
+            address: String::from("http://localhost:3080"),
+            version: String::from("2.2.16"),
+            project: None,
+        };
+        let node = GNS3Node {
+            id: String::from("n1"),
+            name: String::from("r1"),
+            node_type: String::from("docker"),
+            port: 5000,
+            status: GNS3NodeStatus::Started,
+            interfaces: Vec::new(),
+        };
+        assert!(server.node_console(&node).is_err());
+        server.project = Some(String::from("p1"));
+        assert_eq!(
+            server.node_console(&node).unwrap(),
+            GNS3Console {
+                host: String::from("localhost"),
+                port: 5000,
+                websocket_url: String::from(
+                    "ws://localhost:3080/v2/projects/p1/nodes/n1/console/ws"
+                ),
+            }
+        );
+    }
+
+    #[test]
+    fn create_restore_snapshot() {
+        let mut server = match GNS3Server::new("localhost", 3080) {
+            Ok(s) => s,
+            Err(_) => return, // skip the test
+        };
+        delete_test_project(&mut server, TEST_PROJECT_NAME);
+        let project = server.create_project(TEST_PROJECT_NAME).unwrap();
+        let frr_id = server
+            .get_templates()
+            .unwrap()
+            .iter()
+            .find(|t| t.name == "FRR 7.3.1")
+            .unwrap()
+            .id
+            .clone();
+        server.create_node("node_a", &frr_id).unwrap();
+        let snapshot = server.create_snapshot("one_node").unwrap();
+        assert_eq!(snapshot.name, "one_node");
+        assert_eq!(server.get_snapshots().unwrap(), vec![snapshot.clone()]);
+        server.create_node("node_b", &frr_id).unwrap();
+        assert_eq!(server.get_nodes().unwrap().len(), 2);
+        server.restore_snapshot(&snapshot.id).unwrap();
+        assert_eq!(server.get_nodes().unwrap().len(), 1);
+        server.delete_snapshot(&snapshot.id).unwrap();
+        assert!(server.get_snapshots().unwrap().is_empty());
+        server.close_project().unwrap();
+        server.delete_project(&project.id).unwrap();
+    }
+
     fn delete_test_project(server: &mut GNS3Server, name: &'static str) {
         if let Some(project) = server
             .get_projects()
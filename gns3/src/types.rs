@@ -19,6 +19,7 @@
 
 use serde::Deserialize;
 use std::fmt;
+use std::net::TcpStream;
 
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Deserialize, Clone)]
@@ -189,3 +190,37 @@ impl fmt::Display for GNS3LinkEndpoint {
         )
     }
 }
+
+/// Snapshot of a project
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct GNS3Snapshot {
+    /// ID of the snapshot
+    #[serde(rename = "snapshot_id")]
+    pub id: String,
+    /// Name of the snapshot
+    pub name: String,
+    /// ID of the project
+    pub project_id: String,
+    /// Time of creation (seconds since the epoch)
+    pub created_at: u64,
+}
+
+/// Console of a node
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GNS3Console {
+    /// Host on which the telnet console is reachable
+    pub host: String,
+    /// Port of the telnet console
+    pub port: u16,
+    /// URL of the websocket, which is proxied by the GNS3 server to the console.
+    pub websocket_url: String,
+}
+
+impl GNS3Console {
+    /// Open a TCP connection to the telnet console.
+    pub fn connect(&self) -> crate::Result<TcpStream> {
+        Ok(TcpStream::connect((self.host.as_str(), self.port))?)
+    }
+}
@@ -22,7 +22,7 @@ use snowcap::netsim::config::{Config, ConfigModifier};
 use snowcap::netsim::Network;
 use snowcap::optimizers::*;
 use snowcap::soft_policies::*;
-use snowcap::Stopper;
+use snowcap::ControlHandle;
 
 use num_cpus;
 use rand::prelude::*;
@@ -219,7 +219,7 @@ fn single_run(
     let bar = ProgressBar::new(n_optim as u64);
     bar.tick();
     let (sender, receiver) = channel::<Option<f64>>();
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
     let _workers: Vec<JoinHandle<()>> = (0..num_threads)
         .map(|_| {
             let n = net.clone();
@@ -266,7 +266,7 @@ fn single_run(
     let bar = ProgressBar::new(num_iter as u64);
     bar.tick();
     let (sender, receiver) = channel::<Option<f64>>();
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
 
     // spawn all workers
     let _workers: Vec<JoinHandle<()>> = (0..num_threads)
@@ -327,7 +327,7 @@ fn random_permutations_parallel(
     net: Network,
     mut modifiers: Vec<ConfigModifier>,
     sender: Sender<Option<f64>>,
-    mut kill: Stopper,
+    mut kill: ControlHandle,
 ) {
     loop {
         if kill.try_is_stop().unwrap_or(false) {
@@ -354,7 +354,7 @@ fn optimizer_parallel(
     net: Network,
     config: Config,
     sender: Sender<Option<f64>>,
-    mut kill: Stopper,
+    mut kill: ControlHandle,
 ) {
     let soft_policy = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
     let hard_policy =
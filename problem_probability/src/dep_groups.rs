@@ -25,7 +25,7 @@ use snowcap::optimizers::*;
 use snowcap::permutators::*;
 use snowcap::soft_policies::*;
 use snowcap::strategies::*;
-use snowcap::Stopper;
+use snowcap::ControlHandle;
 
 use num_cpus;
 use statistical as stats;
@@ -90,7 +90,7 @@ fn single_run(num_iter: usize, c: &TopoConfig) -> Result<(), Box<dyn Error>> {
     // start the process
     let bar = ProgressBar::new(num_iter as u64);
     let (sender, receiver) = channel::<Option<f64>>();
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
     let num_threads = num_cpus::get();
 
     // spawn all workers
@@ -153,7 +153,7 @@ fn compute_parallel(
     net: Network,
     config_b: Config,
     sender: Sender<Option<f64>>,
-    mut kill: Stopper,
+    mut kill: ControlHandle,
 ) {
     let soft_policy = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
     let hard_policy =
@@ -19,7 +19,7 @@
 
 use rand::prelude::*;
 use snowcap::netsim::{config::ConfigModifier, Network};
-use snowcap::Stopper;
+use snowcap::ControlHandle;
 use std::error::Error;
 
 use crate::utils::*;
@@ -165,7 +165,7 @@ fn single_run(
     let mut permut_step_magnitudes: Vec<f64> = Vec::with_capacity(num_iter * modifiers.len());
     bar.tick();
     let (sender, receiver) = channel::<Option<(f64, Vec<f64>)>>();
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
     let num_threads = num_threads.unwrap_or_else(|| num_cpus::get());
 
     let _workers: Vec<JoinHandle<()>> = (0..num_threads)
@@ -213,7 +213,7 @@ fn single_run(
     let mut router_tot_magnitudes: Vec<f64> = Vec::with_capacity(num_iter);
     let mut router_step_magnitudes: Vec<f64> = Vec::with_capacity(num_iter * modifiers.len());
     let (sender, receiver) = channel::<Option<(f64, Vec<f64>)>>();
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
 
     let _workers: Vec<JoinHandle<()>> = (0..num_threads)
         .map(|_| {
@@ -260,7 +260,7 @@ fn single_run(
     let mut ibr_tot_magnitudes: Vec<f64> = Vec::with_capacity(num_iter);
     let mut ibr_step_magnitudes: Vec<f64> = Vec::with_capacity(num_iter * modifiers.len());
     let (sender, receiver) = channel::<Option<(f64, Vec<f64>)>>();
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
 
     let _workers: Vec<JoinHandle<()>> = (0..num_threads)
         .map(|_| {
@@ -339,7 +339,7 @@ fn random_permutations_parallel(
     net: Network,
     mut modifiers: Vec<ConfigModifier>,
     sender: Sender<Option<(f64, Vec<f64>)>>,
-    mut kill: Stopper,
+    mut kill: ControlHandle,
 ) {
     loop {
         if kill.try_is_stop().unwrap_or(false) {
@@ -368,7 +368,7 @@ fn router_based_random_permutations_parallel(
     net: Network,
     mut modifiers: Vec<ConfigModifier>,
     sender: Sender<Option<(f64, Vec<f64>)>>,
-    mut kill: Stopper,
+    mut kill: ControlHandle,
 ) {
     loop {
         if kill.try_is_stop().unwrap_or(false) {
@@ -396,7 +396,7 @@ fn ibr_based_random_permutations_parallel(
     net: Network,
     mut modifiers: Vec<ConfigModifier>,
     sender: Sender<Option<(f64, Vec<f64>)>>,
-    mut kill: Stopper,
+    mut kill: ControlHandle,
 ) {
     loop {
         if kill.try_is_stop().unwrap_or(false) {
@@ -210,12 +210,52 @@ pub fn do_router_based_random_reconfiguration_with_fail_magnitude(
                     .get_mut(source)
                     .and_then(|v| Some(v.push(m.clone())));
             }
+            (ConfigExpr::OspfArea { router_a, .. }, _, _) => {
+                router_modifiers
+                    .get_mut(router_a)
+                    .and_then(|v| Some(v.push(m.clone())));
+            }
+            (ConfigExpr::OspfStubArea { .. }, _, _) => {
+                // configured on all routers of the area at once
+                router_modifiers
+                    .get_mut(&router_order[0])
+                    .unwrap()
+                    .push(m.clone())
+            }
             (ConfigExpr::StaticRoute { router, .. }, _, _) => {
                 router_modifiers.get_mut(router).unwrap().push(m.clone())
             }
             (ConfigExpr::BgpRouteMap { router, .. }, _, _) => {
                 router_modifiers.get_mut(router).unwrap().push(m.clone())
             }
+            (ConfigExpr::BgpGracefulRestart { router, .. }, _, _) => {
+                router_modifiers.get_mut(router).unwrap().push(m.clone())
+            }
+            (ConfigExpr::BgpGracefulShutdown { router, .. }, _, _) => {
+                router_modifiers.get_mut(router).unwrap().push(m.clone())
+            }
+            (ConfigExpr::RedistributeStatic { router }, _, _) => {
+                router_modifiers.get_mut(router).unwrap().push(m.clone())
+            }
+            (ConfigExpr::BgpAlwaysCompareMed { router }, _, _) => {
+                router_modifiers.get_mut(router).unwrap().push(m.clone())
+            }
+            (ConfigExpr::BgpClusterId { router, .. }, _, _) => {
+                router_modifiers.get_mut(router).unwrap().push(m.clone())
+            }
+            (ConfigExpr::RovPolicy { router, .. }, _, _) => {
+                router_modifiers.get_mut(router).unwrap().push(m.clone())
+            }
+            (ConfigExpr::BgpRelationship { .. }, _, _) => {
+                // configured on all routers at once
+                router_modifiers
+                    .get_mut(&router_order[0])
+                    .unwrap()
+                    .push(m.clone())
+            }
+            (ConfigExpr::Roa { router, .. }, _, _) => {
+                router_modifiers.get_mut(router).unwrap().push(m.clone())
+            }
             (
                 ConfigExpr::BgpSession {
                     source,
@@ -270,7 +310,10 @@ pub fn do_router_based_random_reconfiguration_with_fail_magnitude(
     let num_patches = router_modifiers.len();
 
     for (_, mods) in router_modifiers {
-        let patch = ConfigPatch { modifiers: mods };
+        let patch = ConfigPatch {
+            modifiers: mods,
+            meta: HashMap::new(),
+        };
         match net.apply_patch(&patch) {
             Ok(_) => {}
             Err(NetworkError::NoConvergence) => is_valid = false,
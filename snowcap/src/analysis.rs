@@ -0,0 +1,184 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Analysis of the Search Space
+//!
+//! This module estimates the size of the search tree, before running a tree-based strategy like
+//! the [`TreeStrategy`](crate::strategies::TreeStrategy). Every node of the tree represents a
+//! valid partial ordering of the modifiers, and its children are all modifiers which can be
+//! applied next without violating the hard policy.
+//!
+//! The estimation uses random probing, as described by Knuth (*Estimating the Efficiency of
+//! Backtrack Programs*, 1975). A probe walks down the tree by choosing a random child at every
+//! node. If $d_1, d_2, \ldots, d_k$ are the number of children observed along this path, then
+//! $d_1 + d_1 d_2 + \ldots + d_1 d_2 \cdots d_k$ is an unbiased estimate of the number of nodes in
+//! the tree. Averaging over many probes reduces the variance of the estimate.
+
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{Network, NetworkError};
+use crate::Error;
+
+use rand::prelude::*;
+
+/// # Search Space Estimate
+/// Result of [`estimate_search_space`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSpaceEstimate {
+    /// Number of random probes used for the estimation.
+    pub num_probes: usize,
+    /// Expected number of states (valid partial orderings) in the search tree. This is the number
+    /// of states an exhaustive search would need to visit in the worst case.
+    pub expected_states: f64,
+    /// Standard deviation of the estimated number of states over all probes.
+    pub std_dev: f64,
+    /// Expected number of valid complete orderings.
+    pub expected_solutions: f64,
+}
+
+impl SearchSpaceEstimate {
+    /// Returns `true` if the expected number of states does not exceed `max_states`.
+    pub fn is_feasible(&self, max_states: f64) -> bool {
+        self.expected_states <= max_states
+    }
+}
+
+/// Estimate the size of the search tree by `num_probes` random probes. The network must be in the
+/// initial state. If `seed` is given, the probes are reproducible.
+pub fn estimate_search_space(
+    net: &Network,
+    modifiers: &[ConfigModifier],
+    hard_policy: &HardPolicy,
+    num_probes: usize,
+    seed: Option<u64>,
+) -> Result<SearchSpaceEstimate, Error> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let mut net = net.clone();
+    let mut hard_policy = hard_policy.clone();
+    hard_policy.reset();
+    hard_policy.set_num_mods_if_none(modifiers.len());
+
+    // check the initial state
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state)?;
+    if !hard_policy.check() {
+        return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+    }
+
+    let mut states: Vec<f64> = Vec::with_capacity(num_probes);
+    let mut solutions: f64 = 0.0;
+    for _ in 0..num_probes {
+        let (num_states, num_solutions) = probe(&mut net, modifiers, &mut hard_policy, &mut rng)?;
+        states.push(num_states);
+        solutions += num_solutions;
+    }
+
+    let n = num_probes.max(1) as f64;
+    let expected_states = states.iter().sum::<f64>() / n;
+    let variance = states.iter().map(|s| (s - expected_states).powi(2)).sum::<f64>() / n;
+
+    Ok(SearchSpaceEstimate {
+        num_probes,
+        expected_states,
+        std_dev: variance.sqrt(),
+        expected_solutions: solutions / n,
+    })
+}
+
+/// Perform a single random probe, and return the estimated number of states and the estimated
+/// number of solutions. The network and the hard policy are restored to the initial state
+/// afterwards.
+fn probe(
+    net: &mut Network,
+    modifiers: &[ConfigModifier],
+    hard_policy: &mut HardPolicy,
+    rng: &mut StdRng,
+) -> Result<(f64, f64), Error> {
+    let mut remaining: Vec<usize> = (0..modifiers.len()).collect();
+    let mut weight: f64 = 1.0;
+    let mut num_states: f64 = 0.0;
+    let mut num_solutions: f64 = 0.0;
+    let mut depth: usize = 0;
+
+    loop {
+        if remaining.is_empty() {
+            num_solutions = weight;
+            break;
+        }
+
+        // find all valid children
+        let mut children: Vec<usize> = Vec::new();
+        for (i, idx) in remaining.iter().enumerate() {
+            if try_modifier(net, &modifiers[*idx], hard_policy)? {
+                hard_policy.undo();
+                net.undo_action()?;
+                children.push(i);
+            }
+        }
+
+        if children.is_empty() {
+            break;
+        }
+
+        weight *= children.len() as f64;
+        num_states += weight;
+
+        // descend into a random child
+        let i = *children.choose(rng).unwrap();
+        let idx = remaining.remove(i);
+        try_modifier(net, &modifiers[idx], hard_policy)?;
+        depth += 1;
+    }
+
+    // restore the initial state
+    for _ in 0..depth {
+        hard_policy.undo();
+        net.undo_action()?;
+    }
+
+    Ok((num_states, num_solutions))
+}
+
+/// Apply the modifier and step the hard policy. Returns `true` if the modifier can be applied
+/// without violating the hard policy. In this case, the network and the hard policy remain in the
+/// new state. Otherwise, both are already restored.
+fn try_modifier(
+    net: &mut Network,
+    modifier: &ConfigModifier,
+    hard_policy: &mut HardPolicy,
+) -> Result<bool, Error> {
+    match net.apply_modifier(modifier) {
+        Ok(()) => {}
+        Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+            net.undo_action()?;
+            return Ok(false);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(net, &mut fw_state)?;
+    if hard_policy.check() {
+        Ok(true)
+    } else {
+        hard_policy.undo();
+        net.undo_action()?;
+        Ok(false)
+    }
+}
@@ -0,0 +1,169 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Regression Corpus
+//!
+//! The corpus stores synthesis problems, together with a known-valid plan and its expected cost.
+//! Checking a [`CorpusEntry`] verifies that the stored plan is still valid and still has the same
+//! cost, and that a new plan can be synthesized for the same problem. This guards against silent
+//! regressions in the strategies and in the simulator.
+//!
+//! The corpus of the example networks and of some TopologyZoo scenarios is stored in
+//! `tests/corpus` (one JSON file per problem). A missing entry makes the test fail. To add new
+//! entries, or to regenerate all entries after an intended change in behavior, run the tests with
+//! the environment variable `SNOWCAP_BLESS_CORPUS` set, and commit the generated files.
+
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::{Config, ConfigModifier};
+use crate::netsim::Network;
+use crate::plan_editor::PlanEditor;
+use crate::soft_policies::{compute_cost, MinimizeTrafficShift};
+use crate::{synthesize, Error};
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Absolute difference up to which two costs are considered to be equal.
+pub const COST_TOLERANCE: f64 = 1e-6;
+
+/// Single problem of the corpus, with a known-valid plan and its cost, computed using the
+/// [`MinimizeTrafficShift`] soft policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorpusEntry {
+    /// Name of the problem
+    pub name: String,
+    /// Network in the initial state
+    pub net: Network,
+    /// Final configuration
+    pub final_config: Config,
+    /// Known-valid plan
+    pub plan: Vec<ConfigModifier>,
+    /// Expected cost of the plan
+    pub cost: f64,
+}
+
+/// Difference between the stored entry and the current behavior.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CorpusMismatch {
+    /// The stored plan no longer satisfies the hard policy.
+    PlanInvalid,
+    /// The cost of the stored plan has changed.
+    CostChanged {
+        /// Cost stored in the corpus
+        expected: f64,
+        /// Cost computed now
+        actual: f64,
+    },
+    /// The newly synthesized plan does not apply every modifier exactly once, or it violates the
+    /// hard policy.
+    ResynthesisInvalid,
+}
+
+impl CorpusEntry {
+    /// Synthesize a plan for the problem and compute its cost, in order to create a new entry.
+    /// The network must be in the initial state.
+    pub fn generate(
+        name: impl Into<String>,
+        net: Network,
+        final_config: Config,
+        hard_policy: HardPolicy,
+        time_budget: Option<Duration>,
+    ) -> Result<Self, Error> {
+        let plan = synthesize(
+            net.clone(),
+            net.current_config().clone(),
+            final_config.clone(),
+            hard_policy,
+            time_budget,
+        )?;
+        let cost = compute_cost::<MinimizeTrafficShift>(&net, &plan)?;
+        Ok(Self { name: name.into(), net, final_config, plan, cost })
+    }
+
+    /// Returns `true` if the entry was created for the same problem, i.e., the initial and the
+    /// final configuration are equal.
+    pub fn same_problem(&self, net: &Network, final_config: &Config) -> bool {
+        self.net.current_config() == net.current_config() && &self.final_config == final_config
+    }
+
+    /// Check the stored plan, and synthesize a new plan for the stored problem. Returns all
+    /// mismatches, which is empty if the current behavior matches the stored entry. Errors of the
+    /// synthesis (like a timeout) are returned as `Err`.
+    pub fn check(
+        &self,
+        hard_policy: &HardPolicy,
+        time_budget: Option<Duration>,
+    ) -> Result<Vec<CorpusMismatch>, Error> {
+        let mut mismatches = Vec::new();
+
+        // check the stored plan
+        if !PlanEditor::new(&self.net, self.plan.clone(), hard_policy.clone())?.is_valid() {
+            mismatches.push(CorpusMismatch::PlanInvalid);
+        }
+        let cost = compute_cost::<MinimizeTrafficShift>(&self.net, &self.plan)?;
+        if (cost - self.cost).abs() > COST_TOLERANCE {
+            mismatches.push(CorpusMismatch::CostChanged { expected: self.cost, actual: cost });
+        }
+
+        // synthesize a new plan
+        let plan = synthesize(
+            self.net.clone(),
+            self.net.current_config().clone(),
+            self.final_config.clone(),
+            hard_policy.clone(),
+            time_budget,
+        )?;
+        let mut remaining = self.net.current_config().get_diff(&self.final_config).modifiers;
+        let complete = plan.len() == remaining.len()
+            && plan.iter().all(|m| match remaining.iter().position(|x| x == m) {
+                Some(pos) => {
+                    remaining.swap_remove(pos);
+                    true
+                }
+                None => false,
+            });
+        if !complete || !PlanEditor::new(&self.net, plan, hard_policy.clone())?.is_valid() {
+            mismatches.push(CorpusMismatch::ResynthesisInvalid);
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Load an entry from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CorpusError> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Store the entry as a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CorpusError> {
+        Ok(serde_json::to_writer_pretty(File::create(path)?, self)?)
+    }
+}
+
+/// Error while loading or storing a corpus entry
+#[derive(Debug, Error)]
+pub enum CorpusError {
+    /// Cannot read or write the file
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// Cannot parse the JSON file
+    #[error("JSON Error: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
@@ -21,6 +21,7 @@
 
 pub(crate) mod optimizer;
 pub(crate) mod optimizer_trta;
+mod parallel_trta;
 pub(crate) mod strategy;
 pub(crate) mod strategy_trta;
 mod utils;
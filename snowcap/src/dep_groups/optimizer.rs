@@ -28,14 +28,96 @@ use crate::optimizers::{Optimizer, TreeOptimizer};
 use crate::permutators::{Permutator, PermutatorItem, RandomTreePermutator};
 use crate::soft_policies::SoftPolicy;
 use crate::strategies::{GroupStrategy, PushBackTreeStrategy, Strategy};
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error, PartialResult};
 
 use log::*;
 use rand::prelude::*;
 use std::marker::PhantomData;
 use std::time::{Duration, SystemTime};
 
-const NUM_WORSE_SOLUTIONS_ALLOWED: usize = 9;
+/// # Stopping Criteria
+///
+/// Determines when the [`DepGroupsOptimizer`] stops searching for better solutions. All criteria
+/// only end the search once a valid solution was found, because the optimizer needs something to
+/// return. Use the time budget of [`Optimizer::new`] to limit the total time. The default criteria
+/// stop after 10 consecutive valid solutions that do not improve the best one.
+///
+/// ```
+/// use snowcap::optimizers::StoppingCriteria;
+/// use std::time::Duration;
+///
+/// let criteria = StoppingCriteria::default()
+///     .patience(50)
+///     .relative_tolerance(0.01)
+///     .max_search_time(Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoppingCriteria {
+    /// Number of consecutive valid solutions without improvement, after which the search stops.
+    pub patience: usize,
+    /// A solution only counts as an improvement if it reduces the best cost by at least this
+    /// fraction. Smaller improvements are still kept as the best solution.
+    pub relative_tolerance: f64,
+    /// Time after which the search stops, measured from the start of [`Optimizer::work`].
+    pub max_search_time: Option<Duration>,
+    /// Number of dependency groups that may be learned, before the search stops.
+    pub max_groups: Option<usize>,
+}
+
+impl Default for StoppingCriteria {
+    fn default() -> Self {
+        Self { patience: 10, relative_tolerance: 0.0, max_search_time: None, max_groups: None }
+    }
+}
+
+impl StoppingCriteria {
+    /// Set the number of consecutive valid solutions without improvement.
+    pub fn patience(mut self, patience: usize) -> Self {
+        self.patience = patience;
+        self
+    }
+
+    /// Set the minimal relative improvement of the cost.
+    pub fn relative_tolerance(mut self, tolerance: f64) -> Self {
+        self.relative_tolerance = tolerance;
+        self
+    }
+
+    /// Set the maximum search time.
+    pub fn max_search_time(mut self, time: Duration) -> Self {
+        self.max_search_time = Some(time);
+        self
+    }
+
+    /// Set the maximum number of learned dependency groups.
+    pub fn max_groups(mut self, max_groups: usize) -> Self {
+        self.max_groups = Some(max_groups);
+        self
+    }
+
+    /// Returns true if `cost` is an improvement over `best` according to the relative tolerance.
+    fn is_improvement(&self, cost: f64, best: f64) -> bool {
+        best.is_infinite() || cost < best - self.relative_tolerance * best.abs()
+    }
+
+    /// Returns a message describing why the search must stop, or `None` if it can go on.
+    fn reached(
+        &self,
+        num_no_improvement: usize,
+        num_groups: usize,
+        elapsed: Duration,
+    ) -> Option<String> {
+        if num_no_improvement >= self.patience {
+            Some(format!("The last {} valid solutions were no improvement!", num_no_improvement))
+        } else if self.max_groups.map(|max| num_groups >= max).unwrap_or(false) {
+            Some(format!("Learned {} dependency groups!", num_groups))
+        } else if self.max_search_time.map(|max| elapsed >= max).unwrap_or(false) {
+            Some(format!("Searched for {:.1}s!", elapsed.as_secs_f64()))
+        } else {
+            None
+        }
+    }
+}
 
 /// # DepGroupsOptimizer
 ///
@@ -46,8 +128,8 @@ const NUM_WORSE_SOLUTIONS_ALLOWED: usize = 9;
 /// using an optimizer. But we also use the state of the network where the group is applied. This
 /// way, we can get the best ordering for the sub groups for the valid solution. Once we have found
 /// a valid solution, we reset the permutator and try again. During this, we always store the best
-/// solution. If we have found 10 new solutions, where no one does improve the best score, we stop
-/// the algorithm and return the best one found.
+/// solution. The search stops according to the [`StoppingCriteria`], by default once we have found
+/// 10 new solutions, where no one does improve the best score. Then, the best one is returned.
 pub struct DepGroupsOptimizer<
     P,
     Perm = RandomTreePermutator<usize>,
@@ -68,6 +150,7 @@ pub struct DepGroupsOptimizer<
     rng: ThreadRng,
     stop_time: Option<SystemTime>,
     max_group_solve_time: Option<Duration>,
+    criteria: StoppingCriteria,
     phantom: PhantomData<(O, S)>,
     #[cfg(feature = "count-states")]
     num_states: usize,
@@ -82,56 +165,58 @@ where
     Perm::Item: PermutatorItem<usize>,
 {
     fn new(
-        mut net: Network,
+        net: Network,
         modifiers: Vec<ConfigModifier>,
-        mut hard_policy: HardPolicy,
+        hard_policy: HardPolicy,
         soft_policy: P,
         time_budget: Option<Duration>,
     ) -> Result<Box<Self>, Error> {
-        let num_modifiers = modifiers.len();
-        let mut groups: Vec<Vec<ConfigModifier>> = Vec::with_capacity(num_modifiers);
-        for modifier in modifiers {
-            groups.push(vec![modifier]);
-        }
-        let permutator = Perm::new((0..groups.len()).collect());
-        hard_policy.set_num_mods_if_none(num_modifiers);
-        let mut fw_state = net.get_forwarding_state();
-        hard_policy.step(&mut net, &mut fw_state)?;
-        if !hard_policy.check() {
-            return Err(Error::InvalidInitialState);
-        }
-        let max_group_solve_time: Option<Duration> =
-            time_budget.as_ref().map(|dur| *dur / super::TIME_FRACTION);
-        let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
-        Ok(Box::new(Self {
+        Self::with_criteria(
             net,
-            groups,
+            modifiers,
             hard_policy,
             soft_policy,
-            permutator,
-            rng: rand::thread_rng(),
-            stop_time,
-            max_group_solve_time,
-            phantom: PhantomData,
-            #[cfg(feature = "count-states")]
-            num_states: 0,
-        }))
+            time_budget,
+            StoppingCriteria::default(),
+        )
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<(Vec<ConfigModifier>, f64), Error> {
+    fn work(&mut self, mut abort: ControlHandle) -> Result<(Vec<ConfigModifier>, f64), Error> {
         let mut best_solution: Option<(Vec<ConfigModifier>, f64)> = None;
         let mut num_no_best_found: usize = 0;
+        let mut num_new_groups: usize = 0;
+
+        // longest valid prefix (in modifiers) found so far
+        let mut best_sequence: Vec<ConfigModifier> = Vec::new();
+        let start_time = SystemTime::now();
 
         'main_loop: loop {
+            // check the stopping criteria
+            if best_solution.is_some() {
+                let elapsed = start_time.elapsed().unwrap_or_default();
+                if let Some(reason) =
+                    self.criteria.reached(num_no_best_found, num_new_groups, elapsed)
+                {
+                    info!("{} Abort", reason);
+                    return Ok(best_solution.unwrap());
+                }
+            }
+
             // check for time budget
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 // time budget is used up!
                 if let Some(solution) = best_solution {
                     info!("Time budget is used up! Returning the best solution yet!");
                     return Ok(solution);
                 }
                 error!("Time budget is used up! No solution was found yet!");
-                break Err(Error::Timeout);
+                let modifiers: Vec<ConfigModifier> =
+                    self.groups.iter().flatten().cloned().collect();
+                let partial = PartialResult::new(best_sequence, &modifiers)
+                    .with_elapsed(start_time.elapsed().unwrap_or_default());
+                #[cfg(feature = "count-states")]
+                let partial = partial.with_num_states(self.num_states);
+                break Err(partial.into());
             }
 
             // check for abort criteria
@@ -196,20 +281,16 @@ where
                     };
                     // print the resulting groups
                     info!("Found a valid ordering with cost {}", cost);
-                    if cost < best_solution.as_ref().map(|s| s.1).unwrap_or(f64::INFINITY) {
+                    let best_cost = best_solution.as_ref().map(|s| s.1).unwrap_or(f64::INFINITY);
+                    if self.criteria.is_improvement(cost, best_cost) {
                         info!("NEW BEST SOLUTION");
                         num_no_best_found = 0;
-                        best_solution = Some((finalized_ordering, cost));
                     } else {
                         info!("Solution is not the best yet!");
                         num_no_best_found += 1;
-                        if num_no_best_found > NUM_WORSE_SOLUTIONS_ALLOWED {
-                            info!(
-                                "The last {} valid solutions were no improvement! Abort",
-                                num_no_best_found
-                            );
-                            return Ok(best_solution.unwrap());
-                        }
+                    }
+                    if cost < best_cost {
+                        best_solution = Some((finalized_ordering, cost));
                     }
                     // We want to take a different very much different permutation than before.
                     // Hence, we just restart the permutator. Since we use the random permutation,
@@ -225,6 +306,14 @@ where
                 Err((_, i, None)) => (i, (Vec::new(), vec![Some(PolicyError::NoConvergence)])),
             };
 
+            // remember the longest valid prefix
+            if problem_group_pos > 0 {
+                let prefix = utils::finalize_ordering(&self.groups, &ordering[..problem_group_pos]);
+                if prefix.len() > best_sequence.len() {
+                    best_sequence = prefix;
+                }
+            }
+
             // .--------.
             // | Step 3 | Find dependencies
             // '--------'
@@ -242,6 +331,7 @@ where
             ) {
                 Some((new_group, old_groups)) => {
                     info!("Found a new dependency group!");
+                    num_new_groups += 1;
                     // add the new ordering to the known groups
                     utils::add_minimal_ordering_as_new_gorup(
                         &mut self.groups,
@@ -282,6 +372,47 @@ where
     Perm: Permutator<usize>,
     Perm::Item: PermutatorItem<usize>,
 {
+    /// Create a new optimizer with the given [`StoppingCriteria`]. Otherwise, this is the same as
+    /// [`Optimizer::new`].
+    pub fn with_criteria(
+        mut net: Network,
+        modifiers: Vec<ConfigModifier>,
+        mut hard_policy: HardPolicy,
+        soft_policy: P,
+        time_budget: Option<Duration>,
+        criteria: StoppingCriteria,
+    ) -> Result<Box<Self>, Error> {
+        let num_modifiers = modifiers.len();
+        let mut groups: Vec<Vec<ConfigModifier>> = Vec::with_capacity(num_modifiers);
+        for modifier in modifiers {
+            groups.push(vec![modifier]);
+        }
+        let permutator = Perm::new((0..groups.len()).collect());
+        hard_policy.set_num_mods_if_none(num_modifiers);
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state)?;
+        if !hard_policy.check() {
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+        }
+        let max_group_solve_time: Option<Duration> =
+            time_budget.as_ref().map(|dur| *dur / super::TIME_FRACTION);
+        let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
+        Ok(Box::new(Self {
+            net,
+            groups,
+            hard_policy,
+            soft_policy,
+            permutator,
+            rng: rand::thread_rng(),
+            stop_time,
+            max_group_solve_time,
+            criteria,
+            phantom: PhantomData,
+            #[cfg(feature = "count-states")]
+            num_states: 0,
+        }))
+    }
+
     /// Returns the cost of the ordering, without checking its validity!
     fn get_cost_of_ordering(&self, sequence: &[usize]) -> f64 {
         let mut soft_policy = self.soft_policy.clone();
@@ -308,7 +439,7 @@ where
     fn optimize_groups_in_ordering(
         &mut self,
         sequence: &[usize],
-        abort: Stopper,
+        abort: ControlHandle,
     ) -> Result<(Vec<ConfigModifier>, f64), Error> {
         let mut net = self.net.clone();
         let mut hard_policy = self.hard_policy.clone();
@@ -361,7 +492,7 @@ where
                 }
                 // update the cost and the soft policy
                 cost += group_cost;
-                soft_policy = P::new(&mut net.get_forwarding_state(), &net);
+                soft_policy.reset(&mut net.get_forwarding_state(), &net);
             }
         }
         Ok((good_ordering, cost))
@@ -401,7 +532,7 @@ mod tests {
 
         let expected_cost = (R::get_count() as f64) / ((R::get_count() + 2) as f64);
 
-        let (_, cost) = o.work(Stopper::new()).unwrap();
+        let (_, cost) = o.work(ControlHandle::new()).unwrap();
         assert_approx_eq!(expected_cost, cost);
     }
 
@@ -426,7 +557,51 @@ mod tests {
 
         let expected_cost = 0.0;
 
-        let (_, cost) = o.work(Stopper::new()).unwrap();
+        let (_, cost) = o.work(ControlHandle::new()).unwrap();
         assert_approx_eq!(expected_cost, cost);
     }
+
+    #[test]
+    fn test_stopping_criteria() {
+        let criteria = StoppingCriteria::default().relative_tolerance(0.1);
+        assert!(criteria.is_improvement(100.0, f64::INFINITY));
+        assert!(criteria.is_improvement(80.0, 100.0));
+        assert!(!criteria.is_improvement(95.0, 100.0));
+        assert!(!criteria.is_improvement(0.0, 0.0));
+
+        let criteria = StoppingCriteria::default()
+            .patience(3)
+            .max_groups(5)
+            .max_search_time(Duration::from_secs(10));
+        assert!(criteria.reached(2, 4, Duration::from_secs(9)).is_none());
+        assert!(criteria.reached(3, 4, Duration::from_secs(9)).is_some());
+        assert!(criteria.reached(2, 5, Duration::from_secs(9)).is_some());
+        assert!(criteria.reached(2, 4, Duration::from_secs(10)).is_some());
+    }
+
+    #[test]
+    fn test_chain_gadget_with_criteria() {
+        type R = Repetition5;
+        type T = ChainGadget<R>;
+        let net = T::net(0);
+        let cf = T::final_config(&net, 0);
+        let patch = net.current_config().get_diff(&cf);
+        let hard_policy =
+            HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+        let soft_policy = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
+
+        let mut o = DepGroupsOptimizer::<_>::with_criteria(
+            net,
+            patch.modifiers,
+            hard_policy,
+            soft_policy,
+            None,
+            StoppingCriteria::default().patience(1).max_search_time(Duration::from_secs(0)),
+        )
+        .unwrap();
+
+        // the search stops after the first valid solution
+        let (sequence, _) = o.work(ControlHandle::new()).unwrap();
+        assert_eq!(sequence.len(), o.groups.iter().map(|g| g.len()).sum::<usize>());
+    }
 }
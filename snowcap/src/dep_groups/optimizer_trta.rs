@@ -23,9 +23,10 @@ use crate::modifier_ordering::RandomOrdering;
 use crate::netsim::config::ConfigModifier;
 use crate::netsim::Network;
 use crate::optimizers::Optimizer;
+use crate::plan::{migration_steps, MigrationStep};
 use crate::soft_policies::SoftPolicy;
 use crate::strategies::PushBackTreeStrategy;
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error, PartialResult};
 
 use log::*;
 use rand::prelude::*;
@@ -93,7 +94,7 @@ where
                 "Initial state errors::\n{}",
                 utils::fmt_err(&hard_policy.get_watch_errors(), &net)
             );
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         let max_group_solve_time: Option<Duration> =
             time_budget.as_ref().map(|dur| *dur / super::TIME_FRACTION);
@@ -111,7 +112,7 @@ where
         }))
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<(Vec<ConfigModifier>, f64), Error> {
+    fn work(&mut self, mut abort: ControlHandle) -> Result<(Vec<ConfigModifier>, f64), Error> {
         // clone the network and the hard policies to work with them for the tree exploration
         let mut net = self.net.clone();
         let mut hard_policy = self.hard_policy.clone();
@@ -130,13 +131,23 @@ where
         let mut stack =
             vec![StackFrame { num_undo: 0, valid_groups, invalid_groups, idx: 0, soft_policy }];
         let mut current_sequence: Vec<usize> = vec![];
+        // longest valid prefix (in modifiers) found so far, including its cost
+        let mut best_prefix: (Vec<ConfigModifier>, f64) = (vec![], 0.0);
+        let start_time = SystemTime::now();
 
         loop {
             // check for iter overflow
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 // time budget is used up!
                 error!("Time budget is used up! No solution was found yet!");
-                return Err(Error::Timeout);
+                let modifiers: Vec<ConfigModifier> =
+                    self.groups.iter().flatten().cloned().collect();
+                let partial = PartialResult::new(best_prefix.0, &modifiers)
+                    .with_cost(best_prefix.1)
+                    .with_elapsed(start_time.elapsed().unwrap_or_default());
+                #[cfg(feature = "count-states")]
+                let partial = partial.with_num_states(self.num_states);
+                return Err(partial.into());
             }
 
             // check for abort criteria
@@ -162,6 +173,11 @@ where
 
                 // push the next step to the sequence
                 current_sequence.push(next_group_idx);
+                let seq_len: usize = current_sequence.iter().map(|g| self.groups[*g].len()).sum();
+                if seq_len > best_prefix.0.len() {
+                    best_prefix =
+                        (utils::finalize_ordering(&self.groups, &current_sequence), current_cost);
+                }
 
                 // check if all groups have been added to the sequence
                 if current_sequence.len() == self.groups.len() {
@@ -311,6 +327,15 @@ impl<P> OptimizerTRTA<P>
 where
     P: SoftPolicy + Clone,
 {
+    /// Optimize the sequence in the same way as [`Optimizer::work`], but return it bundled into
+    /// [`MigrationStep`]s (see [`migration_steps`]), together with its cost. All learned dependency
+    /// groups become atomic steps, and independent modifiers are merged into parallel steps.
+    pub fn work_steps(&mut self, abort: ControlHandle) -> Result<(Vec<MigrationStep>, f64), Error> {
+        let (sequence, cost) = self.work(abort)?;
+        let steps = migration_steps(&self.net, &sequence, &self.groups, &self.hard_policy)?;
+        Ok((steps, cost))
+    }
+
     /// Check all remaining possible choices at the current position in the stack. For all options,
     /// we check if it is possible and what the cost is. Once finished, this function will return a
     /// tuple, where the first vector contains all the valid options, including the cost, already
@@ -400,7 +425,7 @@ where
         hard_policy: &mut HardPolicy,
         good_ordering: &[usize],
         bad_group: usize,
-        abort: Stopper,
+        abort: ControlHandle,
     ) -> Option<(Vec<ConfigModifier>, Vec<usize>)> {
         // apply the modifier to the network to get the errors
         let mut num_undo = 0;
@@ -527,7 +552,7 @@ mod tests {
 
         let expected_cost = (R::get_count() as f64) / ((R::get_count() + 2) as f64);
 
-        let (_, cost) = o.work(Stopper::new()).unwrap();
+        let (_, cost) = o.work(ControlHandle::new()).unwrap();
         assert_approx_eq!(expected_cost, cost);
     }
 
@@ -553,7 +578,7 @@ mod tests {
 
         let expected_cost = 0.0;
 
-        let (_, cost) = o.work(Stopper::new()).unwrap();
+        let (_, cost) = o.work(ControlHandle::new()).unwrap();
         assert_approx_eq!(expected_cost, cost);
     }
 }
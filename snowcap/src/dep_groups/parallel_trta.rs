@@ -0,0 +1,586 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Parallel One Strategy To Rule Them All
+//!
+//! This module contains the state shared by all worker threads of
+//! [`StrategyTRTA::work_parallel`](super::strategy_trta::StrategyTRTA::work_parallel).
+//!
+//! The search space is split into *tasks*. A task is a valid prefix of groups, together with the
+//! groups that should be tried next. Every worker explores the subtree of its task in the same way
+//! as [`StrategyTRTA`](super::strategy_trta::StrategyTRTA). As soon as some worker is idle, the
+//! busy workers donate the untried options of the frame closest to the root of their subtree as
+//! new tasks. When a worker learns a new dependency group, all workers abandon their tasks, and the
+//! exploration restarts at the root, with the new groups.
+//!
+//! All workers share a cache of *failed prefixes*. A prefix is failed if its entire subtree was
+//! explored without finding a valid solution. These prefixes are stored as sequences of modifiers
+//! (and not of groups), such that they remain valid once new groups are learned.
+
+use super::utils;
+use crate::hard_policies::{HardPolicy, PolicyError, WatchErrors};
+use crate::modifier_ordering::RandomOrdering;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::Network;
+use crate::strategies::PushBackTreeStrategy;
+use crate::{ControlHandle, Error, PartialResult};
+
+use log::*;
+use rand::prelude::*;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Time an idle worker waits for new tasks, before checking the stop criteria again.
+const IDLE_WAIT: Duration = Duration::from_millis(10);
+
+/// Subtree of the search space, explored by a single worker.
+#[derive(Debug, Clone)]
+struct Task {
+    /// Valid sequence of groups, leading to the root of the subtree.
+    prefix: Vec<usize>,
+    /// Groups to try after the prefix.
+    options: Vec<usize>,
+    /// Wether `options` contains all groups which are not part of the prefix. Only then, the
+    /// prefix can be marked as failed once all options are explored.
+    complete: bool,
+}
+
+impl Task {
+    fn root(num_groups: usize) -> Self {
+        Self { prefix: Vec::new(), options: (0..num_groups).collect(), complete: true }
+    }
+}
+
+/// Single stack frame of a worker.
+#[derive(Debug, Clone)]
+struct Frame {
+    /// Number of calls to undo, in order to undo this step
+    num_undo: usize,
+    /// Remaining groups to try at this position
+    rem_groups: Vec<usize>,
+    /// index into rem_groups to check next
+    idx: usize,
+    /// Wether the entire subtree of this frame is explored by this worker.
+    complete: bool,
+}
+
+/// Mutable state, shared by all workers.
+struct State {
+    /// Current groups of modifiers
+    groups: Vec<Vec<ConfigModifier>>,
+    /// Generation of the groups, incremented every time a new group is learned.
+    generation: usize,
+    /// Tasks which are not yet explored
+    tasks: Vec<Task>,
+    /// Number of workers waiting for a task
+    num_idle: usize,
+    /// Valid solution, found by any worker
+    solution: Option<Vec<ConfigModifier>>,
+    /// Longest valid prefix found so far
+    best_prefix: Vec<ConfigModifier>,
+}
+
+/// State shared by all threads of
+/// [`StrategyTRTA::work_parallel`](super::strategy_trta::StrategyTRTA::work_parallel).
+pub(super) struct SharedTRTA {
+    /// Network in the initial state
+    net: Network,
+    /// Hard policy, containing only the initial state. It is wrapped in a mutex, since the
+    /// operators of the LTL expression are not `Sync`.
+    hard_policy: Mutex<HardPolicy>,
+    /// All modifiers, used to represent the failed prefixes.
+    modifiers: Vec<ConfigModifier>,
+    stop_time: Option<SystemTime>,
+    max_group_solve_time: Option<Duration>,
+    num_threads: usize,
+    state: Mutex<State>,
+    wakeup: Condvar,
+    /// Copy of `state.generation`, which can be checked without locking the state.
+    generation: AtomicUsize,
+    /// Copy of `state.num_idle`, which can be checked without locking the state.
+    num_idle: AtomicUsize,
+    /// Length of `state.best_prefix`
+    best_len: AtomicUsize,
+    done: AtomicBool,
+    aborted: AtomicBool,
+    /// Prefixes (as indices into `modifiers`), whose subtree contains no valid solution.
+    failed: Mutex<HashSet<Vec<usize>>>,
+    num_states: AtomicUsize,
+}
+
+impl SharedTRTA {
+    /// Prepare the shared state. The network and the hard policy must be in the initial state.
+    pub(super) fn new(
+        net: Network,
+        hard_policy: HardPolicy,
+        groups: Vec<Vec<ConfigModifier>>,
+        stop_time: Option<SystemTime>,
+        max_group_solve_time: Option<Duration>,
+        num_threads: usize,
+    ) -> Self {
+        let modifiers = groups.iter().flatten().cloned().collect();
+        let tasks = vec![Task::root(groups.len())];
+        Self {
+            net,
+            hard_policy: Mutex::new(hard_policy),
+            modifiers,
+            stop_time,
+            max_group_solve_time,
+            num_threads,
+            state: Mutex::new(State {
+                groups,
+                generation: 0,
+                tasks,
+                num_idle: 0,
+                solution: None,
+                best_prefix: Vec::new(),
+            }),
+            wakeup: Condvar::new(),
+            generation: AtomicUsize::new(0),
+            num_idle: AtomicUsize::new(0),
+            best_len: AtomicUsize::new(0),
+            done: AtomicBool::new(false),
+            aborted: AtomicBool::new(false),
+            failed: Mutex::new(HashSet::new()),
+            num_states: AtomicUsize::new(0),
+        }
+    }
+
+    /// Spawn all workers, and wait until they are done.
+    pub(super) fn run(
+        self: &Arc<Self>,
+        abort: ControlHandle,
+    ) -> Result<Vec<ConfigModifier>, Error> {
+        let start_time = SystemTime::now();
+        info!("Exploring the search space with {} threads", self.num_threads);
+        let handles = (0..self.num_threads)
+            .map(|_| {
+                let shared = self.clone();
+                let abort = abort.clone();
+                thread::spawn(move || shared.worker(abort))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().expect("Worker thread panicked!");
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(solution) = state.solution.take() {
+            info!(
+                "Valid solution was found! Learned {} groups",
+                state.groups.iter().filter(|g| g.len() > 1).count()
+            );
+            Ok(solution)
+        } else if !self.aborted.load(Ordering::Relaxed) {
+            error!("Could not find any valid ordering!");
+            Err(Error::ProbablyNoSafeOrdering)
+        } else if abort.is_stop() {
+            info!("Operation was aborted!");
+            Err(Error::Abort)
+        } else {
+            error!("Time budget is used up! No solution was found yet!");
+            let best_prefix = std::mem::take(&mut state.best_prefix);
+            let partial = PartialResult::new(best_prefix, &self.modifiers)
+                .with_elapsed(start_time.elapsed().unwrap_or_default());
+            #[cfg(feature = "count-states")]
+            let partial = partial.with_num_states(self.num_states());
+            Err(partial.into())
+        }
+    }
+
+    /// Returns the current groups.
+    pub(super) fn groups(&self) -> Vec<Vec<ConfigModifier>> {
+        self.state.lock().unwrap().groups.clone()
+    }
+
+    /// Returns the number of states explored by all workers.
+    #[cfg(feature = "count-states")]
+    pub(super) fn num_states(&self) -> usize {
+        self.num_states.load(Ordering::Relaxed)
+    }
+
+    /// Main loop of a worker: take tasks until the search is done.
+    fn worker(&self, mut abort: ControlHandle) {
+        let mut rng = thread_rng();
+        while let Some((task, groups, generation)) = self.next_task(&mut abort) {
+            self.explore(task, &groups, generation, &mut rng, abort.clone());
+        }
+    }
+
+    /// Wait for the next task, and return it together with the current groups and their
+    /// generation. If no task is left, and all other workers are idle as well, the search space
+    /// is exhausted, and `None` is returned.
+    fn next_task(
+        &self,
+        abort: &mut ControlHandle,
+    ) -> Option<(Task, Vec<Vec<ConfigModifier>>, usize)> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if self.should_stop(abort) {
+                return None;
+            }
+            if let Some(task) = state.tasks.pop() {
+                return Some((task, state.groups.clone(), state.generation));
+            }
+            state.num_idle += 1;
+            self.num_idle.fetch_add(1, Ordering::Relaxed);
+            if state.num_idle == self.num_threads {
+                // nobody is working, and no task is left.
+                self.done.store(true, Ordering::Relaxed);
+                self.wakeup.notify_all();
+                return None;
+            }
+            state = self.wakeup.wait_timeout(state, IDLE_WAIT).unwrap().0;
+            state.num_idle -= 1;
+            self.num_idle.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Explore the subtree of a single task. The function returns as soon as the subtree is
+    /// explored, a solution or a new group is found, or the search is stopped.
+    fn explore(
+        &self,
+        task: Task,
+        groups: &[Vec<ConfigModifier>],
+        generation: usize,
+        rng: &mut ThreadRng,
+        mut abort: ControlHandle,
+    ) {
+        let group_mods = self.group_mods(groups);
+        let mut net = self.net.clone();
+        let mut hard_policy = self.initial_hard_policy();
+
+        // apply the prefix
+        for modifier in task.prefix.iter().flat_map(|g| groups[*g].iter()) {
+            net.apply_modifier(modifier).expect("The prefix of a task must be valid!");
+            let mut fw_state = net.get_forwarding_state();
+            hard_policy.step(&mut net, &mut fw_state).expect("cannot check policies!");
+        }
+
+        let prefix_len = task.prefix.len();
+        let mut current_sequence = task.prefix;
+        let mut rem_groups = task.options;
+        rem_groups.shuffle(rng);
+        let mut stack = vec![Frame { num_undo: 0, rem_groups, idx: 0, complete: task.complete }];
+
+        while let Some(frame) = stack.last_mut() {
+            if self.should_stop(&mut abort) || self.generation.load(Ordering::Relaxed) != generation
+            {
+                return;
+            }
+
+            if frame.idx >= frame.rem_groups.len() {
+                // The frame is explored completely. Go back
+                let frame = stack.pop().unwrap();
+                if frame.complete {
+                    let key = self.key(&group_mods, &current_sequence);
+                    self.failed.lock().unwrap().insert(key);
+                }
+                if !stack.is_empty() {
+                    current_sequence.pop();
+                    (0..frame.num_undo).for_each(|_| {
+                        net.undo_action().expect("Cannot perform undo!");
+                        hard_policy.undo();
+                    });
+                }
+                continue;
+            }
+
+            match self.next_option(
+                &mut net,
+                &mut hard_policy,
+                groups,
+                &group_mods,
+                &current_sequence,
+                frame,
+                rng,
+            ) {
+                Ok(pos) => {
+                    frame.idx = pos + 1;
+                    let next_group_idx = frame.rem_groups[pos];
+                    current_sequence.push(next_group_idx);
+                    self.update_best_prefix(groups, &current_sequence);
+
+                    // check if all groups have been added to the sequence
+                    if current_sequence.len() == groups.len() {
+                        let mut state = self.state.lock().unwrap();
+                        if state.solution.is_none() {
+                            state.solution =
+                                Some(utils::finalize_ordering(groups, &current_sequence));
+                        }
+                        self.done.store(true, Ordering::Relaxed);
+                        self.wakeup.notify_all();
+                        return;
+                    }
+
+                    let mut rem_groups: Vec<usize> =
+                        (0..groups.len()).filter(|g| !current_sequence.contains(g)).collect();
+                    rem_groups.shuffle(rng);
+                    stack.push(Frame {
+                        num_undo: groups[next_group_idx].len(),
+                        rem_groups,
+                        idx: 0,
+                        complete: true,
+                    });
+                    self.donate(&mut stack, &current_sequence, prefix_len, generation);
+                }
+                Err(Some(pos)) => {
+                    // There exists no option, that we can take. Try to find a dependency!
+                    frame.idx = frame.rem_groups.len();
+                    let bad_group = frame.rem_groups[pos];
+                    if let Some((new_group, old_groups)) = self.find_dependency(
+                        &mut net,
+                        &mut hard_policy,
+                        groups,
+                        &current_sequence,
+                        bad_group,
+                        abort.clone(),
+                    ) {
+                        info!("Found a new dependency group!");
+                        self.add_group(generation, new_group, old_groups);
+                        return;
+                    }
+                    info!("Could not find a new dependency group!");
+                }
+                Err(None) => {
+                    // all remaining options lead to failed prefixes.
+                    frame.idx = frame.rem_groups.len();
+                }
+            }
+        }
+    }
+
+    /// Check all remaining options of the frame, skipping those that lead to a failed prefix.
+    /// The first option that works is returned with `Ok(pos)`, and the network and the hard policy
+    /// remain in the state after applying it. If no option works, then one of the failed options
+    /// (chosen at random) is returned as `Err(Some(pos))`. If all remaining options lead to a
+    /// failed prefix, `Err(None)` is returned.
+    #[allow(clippy::too_many_arguments)]
+    fn next_option(
+        &self,
+        net: &mut Network,
+        hard_policy: &mut HardPolicy,
+        groups: &[Vec<ConfigModifier>],
+        group_mods: &[Vec<usize>],
+        sequence: &[usize],
+        frame: &Frame,
+        rng: &mut ThreadRng,
+    ) -> Result<usize, Option<usize>> {
+        let prefix = self.key(group_mods, sequence);
+        let mut bad_options: Vec<usize> = Vec::new();
+        for (pos, group_idx) in frame.rem_groups.iter().enumerate().skip(frame.idx) {
+            let key: Vec<usize> =
+                prefix.iter().chain(group_mods[*group_idx].iter()).cloned().collect();
+            if self.failed.lock().unwrap().contains(&key) {
+                continue;
+            }
+            match self.apply_group(net, hard_policy, &groups[*group_idx]) {
+                None => return Ok(pos),
+                Some(_) => bad_options.push(pos),
+            }
+        }
+        Err(bad_options.choose(rng).cloned())
+    }
+
+    /// Apply all modifiers of the group. If everything works, `None` is returned, and the network
+    /// and the hard policy remain in the new state. Otherwise, all changes are undone, and the
+    /// errors of the hard policy are returned.
+    fn apply_group(
+        &self,
+        net: &mut Network,
+        hard_policy: &mut HardPolicy,
+        group: &[ConfigModifier],
+    ) -> Option<WatchErrors> {
+        let mut num_undo: usize = 0;
+        let mut num_undo_policy: usize = 0;
+        let mut errors = None;
+        for modifier in group.iter() {
+            self.num_states.fetch_add(1, Ordering::Relaxed);
+            num_undo += 1;
+            if net.apply_modifier(modifier).is_ok() {
+                num_undo_policy += 1;
+                let mut fw_state = net.get_forwarding_state();
+                hard_policy.step(net, &mut fw_state).expect("cannot check policies!");
+                if !hard_policy.check() {
+                    errors = Some(hard_policy.get_watch_errors());
+                    break;
+                }
+            } else {
+                errors = Some((Vec::new(), vec![Some(PolicyError::NoConvergence)]));
+                break;
+            }
+        }
+        if errors.is_some() {
+            (0..num_undo_policy).for_each(|_| hard_policy.undo());
+            (0..num_undo).for_each(|_| {
+                net.undo_action().expect("Cannot perform undo!");
+            });
+        }
+        errors
+    }
+
+    /// Try to find a dependency group, which solves the problem of applying `bad_group` after the
+    /// `good_ordering`. See `StrategyTRTA::find_dependency`.
+    fn find_dependency(
+        &self,
+        net: &mut Network,
+        hard_policy: &mut HardPolicy,
+        groups: &[Vec<ConfigModifier>],
+        good_ordering: &[usize],
+        bad_group: usize,
+        abort: ControlHandle,
+    ) -> Option<(Vec<ConfigModifier>, Vec<usize>)> {
+        let errors = self
+            .apply_group(net, hard_policy, &groups[bad_group])
+            .expect("The bad group, passed into this function seems to be fine!");
+        let ordering =
+            good_ordering.iter().cloned().chain(std::iter::once(bad_group)).collect::<Vec<usize>>();
+        #[cfg(feature = "count-states")]
+        let mut num_states: usize = 0;
+        let result = utils::find_dependency::<PushBackTreeStrategy<RandomOrdering>>(
+            &self.net,
+            groups,
+            &self.initial_hard_policy(),
+            &ordering,
+            errors,
+            self.stop_time,
+            self.max_group_solve_time,
+            abort,
+            #[cfg(feature = "count-states")]
+            &mut num_states,
+        );
+        #[cfg(feature = "count-states")]
+        self.num_states.fetch_add(num_states, Ordering::Relaxed);
+        result
+    }
+
+    /// Add the new group, if no other worker has changed the groups in the meantime, and restart
+    /// the exploration at the root.
+    fn add_group(&self, generation: usize, new_group: Vec<ConfigModifier>, old_groups: Vec<usize>) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            debug!("Another worker has changed the groups in the meantime. Drop the new group");
+            return;
+        }
+        utils::add_minimal_ordering_as_new_gorup(&mut state.groups, old_groups, Some(new_group));
+        state.generation += 1;
+        state.tasks = vec![Task::root(state.groups.len())];
+        self.generation.store(state.generation, Ordering::Relaxed);
+        self.wakeup.notify_all();
+    }
+
+    /// If some worker is idle and there are no tasks left, donate the untried options of the frame
+    /// closest to the root as new tasks. If only the top frame has options left, half of them are
+    /// donated.
+    fn donate(
+        &self,
+        stack: &mut [Frame],
+        sequence: &[usize],
+        prefix_len: usize,
+        generation: usize,
+    ) {
+        let num_idle = self.num_idle.load(Ordering::Relaxed);
+        if num_idle == 0 {
+            return;
+        }
+        let top = stack.len() - 1;
+        let pos = match stack
+            .iter()
+            .enumerate()
+            .position(|(i, f)| f.rem_groups.len() - f.idx >= if i == top { 2 } else { 1 })
+        {
+            Some(pos) => pos,
+            None => return,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation || !state.tasks.is_empty() {
+            return;
+        }
+        let frame = &mut stack[pos];
+        let keep = if pos == top {
+            frame.idx + (frame.rem_groups.len() - frame.idx) / 2
+        } else {
+            frame.idx
+        };
+        let donated = frame.rem_groups.split_off(keep);
+        // the subtrees of this frame and all frames below are no longer explored completely.
+        stack[..=pos].iter_mut().for_each(|f| f.complete = false);
+
+        let chunk_size = (donated.len() + num_idle - 1) / num_idle;
+        let prefix = &sequence[..prefix_len + pos];
+        for options in donated.chunks(chunk_size) {
+            state.tasks.push(Task {
+                prefix: prefix.to_vec(),
+                options: options.to_vec(),
+                complete: false,
+            });
+        }
+        debug!("Donated {} options as {} tasks", donated.len(), state.tasks.len());
+        self.wakeup.notify_all();
+    }
+
+    /// Remember the sequence if it is the longest valid prefix found so far.
+    fn update_best_prefix(&self, groups: &[Vec<ConfigModifier>], sequence: &[usize]) {
+        let len: usize = sequence.iter().map(|g| groups[*g].len()).sum();
+        if len > self.best_len.load(Ordering::Relaxed) {
+            let mut state = self.state.lock().unwrap();
+            if len > state.best_prefix.len() {
+                state.best_prefix = utils::finalize_ordering(groups, sequence);
+                self.best_len.store(len, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Check if the search should stop, because a solution was found, the time budget is used up,
+    /// or the operation was aborted.
+    fn should_stop(&self, abort: &mut ControlHandle) -> bool {
+        if self.done.load(Ordering::Relaxed) {
+            return true;
+        }
+        if abort.time_is_up(self.stop_time) || abort.try_is_stop().unwrap_or(false) {
+            self.aborted.store(true, Ordering::Relaxed);
+            self.done.store(true, Ordering::Relaxed);
+            self.wakeup.notify_all();
+            return true;
+        }
+        false
+    }
+
+    /// Returns a copy of the hard policy in the initial state.
+    fn initial_hard_policy(&self) -> HardPolicy {
+        self.hard_policy.lock().unwrap().clone()
+    }
+
+    /// Map every group to the indices of its modifiers in `self.modifiers`.
+    fn group_mods(&self, groups: &[Vec<ConfigModifier>]) -> Vec<Vec<usize>> {
+        groups
+            .iter()
+            .map(|g| {
+                g.iter().map(|m| self.modifiers.iter().position(|x| x == m).unwrap()).collect()
+            })
+            .collect()
+    }
+
+    /// Returns the sequence of groups as a sequence of modifier indices.
+    fn key(&self, group_mods: &[Vec<usize>], sequence: &[usize]) -> Vec<usize> {
+        sequence.iter().flat_map(|g| group_mods[*g].iter()).cloned().collect()
+    }
+}
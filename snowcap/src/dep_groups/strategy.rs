@@ -25,8 +25,10 @@ use crate::modifier_ordering::SimpleOrdering;
 use crate::netsim::config::ConfigModifier;
 use crate::netsim::Network;
 use crate::permutators::{Permutator, PermutatorItem, RandomTreePermutator};
-use crate::strategies::{GroupStrategy, PushBackTreeStrategy, Strategy};
-use crate::{Error, Stopper};
+use crate::strategies::{
+    GroupStrategy, PushBackTreeStrategy, Strategy, DEFAULT_STATE_CACHE_CAPACITY,
+};
+use crate::{ControlHandle, Error, PartialResult};
 
 use log::*;
 use rand::prelude::*;
@@ -554,7 +556,11 @@ where
         hard_policy.set_num_mods_if_none(num_modifiers);
         hard_policy.step(&mut net, &mut fw_state)?;
         if !hard_policy.check() {
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+        }
+        // share a state cache between all orderings checked during the run
+        if hard_policy.state_cache().is_none() {
+            hard_policy.enable_state_cache(DEFAULT_STATE_CACHE_CAPACITY);
         }
         let max_group_solve_time: Option<Duration> =
             time_budget.as_ref().map(|dur| *dur / super::TIME_FRACTION);
@@ -573,13 +579,23 @@ where
         }))
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<Vec<ConfigModifier>, Error> {
+    fn work(&mut self, mut abort: ControlHandle) -> Result<Vec<ConfigModifier>, Error> {
+        // longest valid prefix (in modifiers) found so far
+        let mut best_sequence: Vec<ConfigModifier> = Vec::new();
+        let start_time = SystemTime::now();
+
         'main_loop: loop {
             // check for iter overflow
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 // time budget is used up!
                 error!("Time budget is used up! No solution was found yet!");
-                return Err(Error::Timeout);
+                let modifiers: Vec<ConfigModifier> =
+                    self.groups.iter().flatten().cloned().collect();
+                let partial = PartialResult::new(best_sequence, &modifiers)
+                    .with_elapsed(start_time.elapsed().unwrap_or_default());
+                #[cfg(feature = "count-states")]
+                let partial = partial.with_num_states(self.num_states);
+                return Err(partial.into());
             }
 
             // check for abort criteria
@@ -628,6 +644,14 @@ where
                 Err((_, i, None)) => (i, (Vec::new(), vec![Some(PolicyError::NoConvergence)])),
             };
 
+            // remember the longest valid prefix
+            if problem_group_pos > 0 {
+                let prefix = utils::finalize_ordering(&self.groups, &ordering[..problem_group_pos]);
+                if prefix.len() > best_sequence.len() {
+                    best_sequence = prefix;
+                }
+            }
+
             // .--------.
             // | Step 3 | Find dependencies
             // '--------'
@@ -17,16 +17,22 @@
 
 //! # One Strategy To Rule Them All
 
+use super::parallel_trta::SharedTRTA;
 use super::utils;
 use crate::hard_policies::{HardPolicy, PolicyError};
 use crate::modifier_ordering::RandomOrdering;
 use crate::netsim::config::ConfigModifier;
-use crate::netsim::Network;
-use crate::strategies::{PushBackTreeStrategy, Strategy};
-use crate::{Error, Stopper};
+use crate::netsim::{Network, NetworkError};
+use crate::plan::{migration_steps, MigrationStep};
+use crate::strategies::{
+    replay, CheckpointStrategy, FrameState, GroupStrategy, PushBackTreeStrategy, Strategy,
+    StrategyState, DEFAULT_STATE_CACHE_CAPACITY,
+};
+use crate::{ControlHandle, Error, PartialResult};
 
 use log::*;
 use rand::prelude::*;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use utils::fmt_err;
 
@@ -172,6 +178,11 @@ use utils::fmt_err;
 ///        return Err
 ///    }
 ///    ```
+///
+/// ## Checkpoints
+///
+/// The exploration can be interrupted and resumed (see [`CheckpointStrategy`]). The checkpoint
+/// contains all learned dependency groups, such that they need not be learned again.
 pub struct StrategyTRTA {
     net: Network,
     groups: Vec<Vec<ConfigModifier>>,
@@ -179,6 +190,9 @@ pub struct StrategyTRTA {
     rng: ThreadRng,
     stop_time: Option<SystemTime>,
     max_group_solve_time: Option<Duration>,
+    resume_point: Option<StrategyState>,
+    learned: Vec<(Vec<ConfigModifier>, Vec<PolicyError>)>,
+    unresolved: Vec<(Vec<ConfigModifier>, Vec<PolicyError>)>,
     #[cfg(feature = "count-states")]
     num_states: usize,
     #[cfg(feature = "count-states")]
@@ -187,67 +201,65 @@ pub struct StrategyTRTA {
 
 impl Strategy for StrategyTRTA {
     fn new(
-        mut net: Network,
+        net: Network,
         modifiers: Vec<ConfigModifier>,
-        mut hard_policy: HardPolicy,
+        hard_policy: HardPolicy,
         time_budget: Option<Duration>,
     ) -> Result<Box<Self>, Error> {
-        // clear the undo stack
-        net.clear_undo_stack();
-
-        // check the state
-        hard_policy.set_num_mods_if_none(modifiers.len());
-        let mut fw_state = net.get_forwarding_state();
-        hard_policy.step(&mut net, &mut fw_state)?;
-        if !hard_policy.check() {
-            error!("Initial state errors::\n{}", fmt_err(&hard_policy.get_watch_errors(), &net));
-            return Err(Error::InvalidInitialState);
-        }
-
         // prepare the groups
-        let mut groups: Vec<Vec<ConfigModifier>> = Vec::with_capacity(modifiers.len());
-        for modifier in modifiers {
-            groups.push(vec![modifier]);
-        }
-
-        // prepare the timings
-        let max_group_solve_time: Option<Duration> =
-            time_budget.as_ref().map(|dur| *dur / super::TIME_FRACTION);
-        let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
-        Ok(Box::new(Self {
-            net,
-            groups,
-            hard_policy,
-            rng: rand::thread_rng(),
-            stop_time,
-            max_group_solve_time,
-            #[cfg(feature = "count-states")]
-            num_states: 0,
-            #[cfg(feature = "count-states")]
-            seen_difficult_dependency: false,
-        }))
+        let groups = modifiers.into_iter().map(|m| vec![m]).collect();
+        Self::from_groups(net, groups, hard_policy, time_budget)
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<Vec<ConfigModifier>, Error> {
-        // setup the stack with a randomized frame
+    fn work(&mut self, mut abort: ControlHandle) -> Result<Vec<ConfigModifier>, Error> {
+        // setup the stack with a randomized frame, or continue where the last call stopped
         let mut stack = vec![StackFrame::new(0..self.groups.len(), 0, &mut self.rng)];
         let mut current_sequence: Vec<usize> = vec![];
+        // longest valid prefix (in modifiers) found so far
+        let mut best_sequence: Vec<ConfigModifier> = vec![];
+        let start_time = SystemTime::now();
 
         // clone the network and the hard policies to work with them for the tree exploration
         let mut net = self.net.clone();
         let mut hard_policy = self.hard_policy.clone();
 
+        if let Some(state) = self.resume_point.take() {
+            stack = state
+                .frames
+                .iter()
+                .enumerate()
+                .map(|(i, frame)| StackFrame {
+                    num_undo: if i == 0 { 0 } else { self.groups[state.sequence[i - 1]].len() },
+                    rem_groups: frame.remaining.clone(),
+                    idx: frame.next,
+                })
+                .collect();
+            replay(&mut net, &mut hard_policy, state.sequence_modifiers().iter())?;
+            current_sequence = state.sequence;
+            best_sequence = state.best_sequence;
+        }
+        // interrupt the convergence if the operation is aborted
+        net.set_abort(Some(abort.clone()));
+
         loop {
             // check for iter overflow
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 // time budget is used up!
                 error!("Time budget is used up! No solution was found yet!");
-                return Err(Error::Timeout);
+                self.resume_point = Some(self.state(&stack, &current_sequence, &best_sequence));
+                let modifiers: Vec<ConfigModifier> =
+                    self.groups.iter().flatten().cloned().collect();
+                let partial = PartialResult::new(best_sequence, &modifiers)
+                    .with_elapsed(start_time.elapsed().unwrap_or_default());
+                #[cfg(feature = "count-states")]
+                let partial = partial.with_num_states(self.num_states);
+                return Err(partial.into());
             }
 
             // check for abort criteria
             if abort.try_is_stop().unwrap_or(false) {
                 info!("Operation was aborted!");
+                self.resume_point = Some(self.state(&stack, &current_sequence, &best_sequence));
                 return Err(Error::Abort);
             }
 
@@ -261,14 +273,26 @@ impl Strategy for StrategyTRTA {
             };
 
             // search the current stack frame for the next
-            let action: StackAction = match self.get_next_option(&mut net, &mut hard_policy, frame)
-            {
+            let next_option = match self.get_next_option(&mut net, &mut hard_policy, frame) {
+                Some(next_option) => next_option,
+                None => {
+                    info!("Operation was aborted!");
+                    self.resume_point = Some(self.state(&stack, &current_sequence, &best_sequence));
+                    return Err(Error::Abort);
+                }
+            };
+            let action: StackAction = match next_option {
                 Ok(next_idx) => {
                     // update the current stack frame and prepare the next one
                     frame.idx = next_idx + 1;
                     // There exists a valid next step! Update the current sequence and the stack
                     let next_group_idx = frame.rem_groups[next_idx];
                     current_sequence.push(next_group_idx);
+                    let seq_len: usize =
+                        current_sequence.iter().map(|g| self.groups[*g].len()).sum();
+                    if seq_len > best_sequence.len() {
+                        best_sequence = utils::finalize_ordering(&self.groups, &current_sequence);
+                    }
 
                     // check if all groups have been added to the sequence
                     if current_sequence.len() == self.groups.len() {
@@ -365,20 +389,146 @@ impl Strategy for StrategyTRTA {
     }
 }
 
+impl GroupStrategy for StrategyTRTA {
+    fn from_groups(
+        mut net: Network,
+        groups: Vec<Vec<ConfigModifier>>,
+        mut hard_policy: HardPolicy,
+        time_budget: Option<Duration>,
+    ) -> Result<Box<Self>, Error> {
+        // clear the undo stack
+        net.clear_undo_stack();
+
+        // check the state
+        hard_policy.set_num_mods_if_none(groups.iter().map(|g| g.len()).sum());
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state)?;
+        if !hard_policy.check() {
+            error!("Initial state errors::\n{}", fmt_err(&hard_policy.get_watch_errors(), &net));
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+        }
+
+        // share a state cache between all clones of the hard policy used while exploring
+        if hard_policy.state_cache().is_none() {
+            hard_policy.enable_state_cache(DEFAULT_STATE_CACHE_CAPACITY);
+        }
+
+        // prepare the timings
+        let max_group_solve_time: Option<Duration> =
+            time_budget.as_ref().map(|dur| *dur / super::TIME_FRACTION);
+        let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
+        Ok(Box::new(Self {
+            net,
+            groups,
+            hard_policy,
+            rng: rand::thread_rng(),
+            stop_time,
+            max_group_solve_time,
+            resume_point: None,
+            learned: Vec::new(),
+            unresolved: Vec::new(),
+            #[cfg(feature = "count-states")]
+            num_states: 0,
+            #[cfg(feature = "count-states")]
+            seen_difficult_dependency: false,
+        }))
+    }
+}
+
 impl StrategyTRTA {
+    /// Returns the current groups of modifiers. Every group with more than one modifier is a
+    /// dependency group, learned while exploring the search space. The modifiers within a group
+    /// are stored in the order in which they must be applied.
+    pub fn groups(&self) -> &[Vec<ConfigModifier>] {
+        &self.groups
+    }
+
+    /// Explore the search space in the same way as [`Strategy::work`], but return the sequence
+    /// bundled into [`MigrationStep`]s (see [`migration_steps`]). All learned dependency groups
+    /// become atomic steps, and independent modifiers are merged into parallel steps.
+    pub fn work_steps(&mut self, abort: ControlHandle) -> Result<Vec<MigrationStep>, Error> {
+        let sequence = self.work(abort)?;
+        migration_steps(&self.net, &sequence, &self.groups, &self.hard_policy)
+    }
+
+    /// Returns every dependency group learned so far (in the order in which they were learned),
+    /// together with the errors of the hard policy which triggered the search for it.
+    pub(crate) fn learned_dependencies(&self) -> &[(Vec<ConfigModifier>, Vec<PolicyError>)] {
+        &self.learned
+    }
+
+    /// Returns every group for which no dependency could be found when the exploration got stuck
+    /// (since the last dependency group was learned), together with the errors of the hard policy
+    /// when applying it.
+    pub(crate) fn unresolved_dependencies(&self) -> &[(Vec<ConfigModifier>, Vec<PolicyError>)] {
+        &self.unresolved
+    }
+
+    /// Describe the current state of the exploration
+    fn state(
+        &self,
+        stack: &[StackFrame],
+        current_sequence: &[usize],
+        best_sequence: &[ConfigModifier],
+    ) -> StrategyState {
+        StrategyState {
+            strategy: "StrategyTRTA".to_string(),
+            groups: self.groups.clone(),
+            frames: stack
+                .iter()
+                .map(|f| FrameState { remaining: f.rem_groups.clone(), next: f.idx })
+                .collect(),
+            sequence: current_sequence.to_vec(),
+            best_sequence: best_sequence.to_vec(),
+        }
+    }
+
+    /// Explore the search space using multiple threads. If `num_threads` is `None`, then one
+    /// thread per CPU is spawned.
+    ///
+    /// Every thread explores a disjoint subtree of the search space in the same way as
+    /// [`Strategy::work`]. As soon as a thread is idle, the others donate parts of their subtree
+    /// to it. The threads share a cache of prefixes whose subtree contains no valid solution, such
+    /// that they are never explored twice. If a thread learns a new dependency group, all threads
+    /// restart the exploration with the new groups. The time budget and the `abort` handle apply
+    /// to all threads.
+    pub fn work_parallel(
+        &mut self,
+        abort: ControlHandle,
+        num_threads: Option<usize>,
+    ) -> Result<Vec<ConfigModifier>, Error> {
+        let num_threads = num_threads.unwrap_or_else(num_cpus::get).max(1);
+        let shared = Arc::new(SharedTRTA::new(
+            self.net.clone(),
+            self.hard_policy.clone(),
+            self.groups.clone(),
+            self.stop_time,
+            self.max_group_solve_time,
+            num_threads,
+        ));
+        let result = shared.run(abort);
+        self.groups = shared.groups();
+        #[cfg(feature = "count-states")]
+        {
+            self.num_states += shared.num_states();
+        }
+        result
+    }
+
     /// Check all remaining possible choices at the current position in the stack. The first option,
     /// that works is returned (with `Ok(idx)`). However, if none of them seem to work, then one of
     /// the checked and failed groups is returned at random, which should be used to find a
     /// dependency group. The returned index corresponds to the position in `frame.rem_groups`!
     ///
     /// In the OK case, the network and the hard policy will remain in the state of the modification
-    /// of which the index is returned
+    /// of which the index is returned. If the convergence was aborted, `None` is returned, and the
+    /// network and the hard policy remain in the same state as before.
     fn get_next_option(
         &mut self,
         net: &mut Network,
         hard_policy: &mut HardPolicy,
         frame: &StackFrame,
-    ) -> Result<usize, usize> {
+    ) -> Option<Result<usize, usize>> {
         assert!(frame.idx < frame.rem_groups.len());
         for group_pos in frame.idx..frame.rem_groups.len() {
             let group_idx = *frame.rem_groups.get(group_pos).unwrap();
@@ -386,41 +536,48 @@ impl StrategyTRTA {
             let mut mod_ok: bool = true;
             let mut num_undo: usize = 0;
             let mut num_undo_policy: usize = 0;
+            let mut aborted: bool = false;
             'apply_group: for modifier in self.groups[group_idx].iter() {
                 #[cfg(feature = "count-states")]
                 {
                     self.num_states += 1;
                 }
                 num_undo += 1;
-                if net.apply_modifier(modifier).is_ok() {
-                    num_undo_policy += 1;
-                    let mut fw_state = net.get_forwarding_state();
-                    hard_policy.step(net, &mut fw_state).expect("cannot check policies!");
-                    if !hard_policy.check() {
+                match net.apply_modifier(modifier) {
+                    Ok(()) => {
+                        num_undo_policy += 1;
+                        hard_policy.step_cached(net).expect("cannot check policies!");
+                        if !hard_policy.check() {
+                            mod_ok = false;
+                            break 'apply_group;
+                        }
+                    }
+                    Err(e) => {
+                        aborted = e == NetworkError::Aborted;
                         mod_ok = false;
                         break 'apply_group;
                     }
-                } else {
-                    mod_ok = false;
-                    break 'apply_group;
                 }
             }
 
             // check if the modifier is ok
             if mod_ok {
                 // everything fine, return the index
-                return Ok(group_pos);
+                return Some(Ok(group_pos));
             } else {
                 // undo the hard policy and the network
                 (0..num_undo_policy).for_each(|_| hard_policy.undo());
                 (0..num_undo).for_each(|_| {
                     net.undo_action().expect("Cannot perform undo!");
                 });
+                if aborted {
+                    return None;
+                }
             }
         }
 
         // if we reach this position, we know that every possible option is bad!
-        Err(self.rng.gen_range(frame.idx, frame.rem_groups.len()))
+        Some(Err(self.rng.gen_range(frame.idx, frame.rem_groups.len())))
     }
 
     /// This function tries to find a dependency based on the current position. The arguments
@@ -443,7 +600,7 @@ impl StrategyTRTA {
         hard_policy: &mut HardPolicy,
         good_ordering: &[usize],
         bad_group: usize,
-        abort: Stopper,
+        abort: ControlHandle,
     ) -> Option<(Vec<ConfigModifier>, Vec<usize>)> {
         // apply the modifier to the network to get the errors
         let mut num_undo = 0;
@@ -453,8 +610,7 @@ impl StrategyTRTA {
             num_undo += 1;
             if net.apply_modifier(modifier).is_ok() {
                 num_undo_policy += 1;
-                let mut fw_state = net.get_forwarding_state();
-                hard_policy.step(net, &mut fw_state).expect("cannot check policies!");
+                hard_policy.step_cached(net).expect("cannot check policies!");
                 if !hard_policy.check() {
                     errors = Some(hard_policy.get_watch_errors());
                     break 'apply_group;
@@ -473,12 +629,13 @@ impl StrategyTRTA {
 
         match errors {
             Some(errors) => {
+                let violation: Vec<PolicyError> = errors.1.iter().flatten().cloned().collect();
                 let ordering = good_ordering
                     .iter()
                     .cloned()
                     .chain(std::iter::once(bad_group))
                     .collect::<Vec<usize>>();
-                utils::find_dependency::<PushBackTreeStrategy<RandomOrdering>>(
+                let result = utils::find_dependency::<PushBackTreeStrategy<RandomOrdering>>(
                     &self.net,
                     &self.groups,
                     &self.hard_policy,
@@ -489,7 +646,17 @@ impl StrategyTRTA {
                     abort,
                     #[cfg(feature = "count-states")]
                     &mut self.num_states,
-                )
+                );
+                // remember the violation that caused the (failed) search for a dependency. Learning
+                // a new group restarts the exploration, which makes all unresolved groups obsolete.
+                match result.as_ref() {
+                    Some((new_group, _)) => {
+                        self.learned.push((new_group.clone(), violation));
+                        self.unresolved.clear();
+                    }
+                    None => self.unresolved.push((self.groups[bad_group].clone(), violation)),
+                }
+                result
             }
             None => panic!("The bad group, passed into this function seems to be fine!"),
         }
@@ -504,6 +671,23 @@ impl StrategyTRTA {
     }
 }
 
+impl CheckpointStrategy for StrategyTRTA {
+    fn checkpoint(&self) -> StrategyState {
+        match self.resume_point.as_ref() {
+            Some(state) => state.clone(),
+            None => StrategyState::root("StrategyTRTA", self.groups.clone()),
+        }
+    }
+
+    fn resume(&mut self, state: StrategyState) -> Result<(), Error> {
+        let modifiers: Vec<ConfigModifier> = self.groups.iter().flatten().cloned().collect();
+        state.validate("StrategyTRTA", &modifiers)?;
+        self.groups = state.groups.clone();
+        self.resume_point = Some(state);
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 enum StackAction {
     Pop,
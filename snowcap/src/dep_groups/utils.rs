@@ -25,7 +25,7 @@ use crate::hard_policies::{HardPolicy, PolicyError, WatchErrors};
 use crate::netsim::config::ConfigModifier;
 use crate::netsim::{printer, Network, NetworkError};
 use crate::strategies::{GroupStrategy, Strategy};
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error};
 
 use log::*;
 use std::time::{Duration, SystemTime};
@@ -64,7 +64,7 @@ pub(super) fn find_dependency<S>(
     errors: WatchErrors,
     stop_time: Option<SystemTime>,
     max_group_solve_time: Option<Duration>,
-    abort: Stopper,
+    abort: ControlHandle,
     #[cfg(feature = "count-states")] num_states: &mut usize,
 ) -> Option<(Vec<ConfigModifier>, Vec<usize>)>
 where
@@ -119,7 +119,7 @@ where
                 // Found a group which works!
                 return Some((new_group, reduced_ordering));
             }
-            Err(Error::Timeout) | Err(Error::Abort) => {
+            Err(Error::Timeout(_)) | Err(Error::Abort) => {
                 return None;
             }
             Err(_) => {
@@ -192,8 +192,7 @@ pub(super) fn check_group_ordering(
                 Err(e) => panic!("Unrecoverable network error: {}", e),
             }
             // check
-            let mut fw_state = net.get_forwarding_state();
-            if let Err(e) = hard_policy.step(&mut net, &mut fw_state) {
+            if let Err(e) = hard_policy.step_cached(&mut net) {
                 warn!("Error while checking hard policies: {}", e);
                 panic!("Error while checking hard policies: {}", e);
             }
@@ -541,7 +540,7 @@ pub(super) fn check_minimal_problem<S: Strategy + GroupStrategy>(
     hard_policy: &HardPolicy,
     minimal_problem_ordering: &[usize],
     time_budget: Option<std::time::Duration>,
-    abort: Stopper,
+    abort: ControlHandle,
     #[cfg(feature = "count-states")] num_states: &mut usize,
 ) -> Result<Vec<ConfigModifier>, Error> {
     let mut hard_policy = hard_policy.clone();
@@ -576,12 +575,12 @@ pub(super) fn check_minimal_problem<S: Strategy + GroupStrategy>(
             );
             Err(Error::NoSafeOrdering)
         }
-        Err(Error::Timeout) => {
+        Err(e @ Error::Timeout(_)) => {
             debug!(
                 "Time Budget did not suffice to solve the problem!\n{}",
                 fmt_group_ord(groups, minimal_problem_ordering, net),
             );
-            Err(Error::Timeout)
+            Err(e)
         }
         Err(Error::Abort) => {
             info!("Operation was aborted!");
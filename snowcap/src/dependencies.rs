@@ -0,0 +1,149 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Dependency Analysis
+//!
+//! While exploring the search space, [`StrategyTRTA`] learns *dependency groups*: sets of modifiers
+//! which must be applied together, in a specific order, such that the hard policy is satisfied.
+//! [`analyze_dependencies`] runs the exploration and returns these groups as
+//! [`DependencyGroup`]s, e.g., to show operators which commands must be bundled.
+//!
+//! Every group contains the evidence, i.e., the errors of the hard policy observed when the
+//! modifiers were applied in the wrong order, which caused the group to be learned. If no safe
+//! ordering exists, the groups for which no dependency could be found are reported as
+//! [`DependencyStatus::Unresolved`].
+
+use crate::hard_policies::{HardPolicy, PolicyError};
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{printer, Network};
+use crate::strategies::{Strategy, StrategyTRTA};
+use crate::{ControlHandle, Error};
+
+/// # Dependency Group
+/// Set of modifiers which must be applied together, computed by [`analyze_dependencies`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyGroup {
+    /// Modifiers of the group. If the group is [solved](DependencyStatus::Solved), the modifiers
+    /// are stored in the order in which they must be applied.
+    pub modifiers: Vec<ConfigModifier>,
+    /// Whether a safe ordering of the group was found
+    pub status: DependencyStatus,
+    /// Errors of the hard policy, which were observed when applying the modifiers of the group in
+    /// the wrong order. If the network does not converge, this contains
+    /// [`PolicyError::NoConvergence`].
+    pub evidence: Vec<PolicyError>,
+}
+
+/// Status of a [`DependencyGroup`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DependencyStatus {
+    /// The group has a safe ordering, in which the modifiers are stored.
+    Solved,
+    /// Applying the group violates the hard policy, but no dependency could be found which solves
+    /// the problem.
+    Unresolved,
+}
+
+impl DependencyGroup {
+    /// Get a string representing the group, where all router names are inserted.
+    pub fn repr_with_name(&self, net: &Network) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        lines.push(format!("{:?} group of {} modifiers:", self.status, self.modifiers.len()));
+        lines.extend(self.modifiers.iter().map(|m| {
+            format!(
+                "    {}",
+                printer::config_modifier(net, m).unwrap_or_else(|_| format!("{:?}", m))
+            )
+        }));
+        lines.push("Evidence:".to_string());
+        lines.extend(self.evidence.iter().map(|e| format!("    {}", e.repr_with_name(net))));
+        lines.join("\n")
+    }
+}
+
+/// Learn the dependency groups of the reconfiguration, by running [`StrategyTRTA`] until a safe
+/// ordering is found. The network must be in the initial state.
+///
+/// Every learned group with more than one modifier is returned as
+/// [solved](DependencyStatus::Solved). If no safe ordering exists, then all groups (including
+/// solved ones) which caused the exploration to get stuck, but for which no dependency could be
+/// found, are additionally returned as [unresolved](DependencyStatus::Unresolved). Any other error
+/// of the strategy is returned.
+pub fn analyze_dependencies(
+    net: &Network,
+    modifiers: &[ConfigModifier],
+    hard_policy: &HardPolicy,
+) -> Result<Vec<DependencyGroup>, Error> {
+    let mut strategy =
+        StrategyTRTA::new(net.clone(), modifiers.to_vec(), hard_policy.clone(), None)?;
+    let solved = match strategy.work(ControlHandle::new()) {
+        Ok(_) => true,
+        Err(Error::NoSafeOrdering) | Err(Error::ProbablyNoSafeOrdering) => false,
+        Err(e) => return Err(e),
+    };
+
+    let learned = strategy.learned_dependencies();
+    let mut result: Vec<DependencyGroup> = strategy
+        .groups()
+        .iter()
+        .filter(|g| g.len() > 1)
+        .map(|group| DependencyGroup {
+            modifiers: group.clone(),
+            status: DependencyStatus::Solved,
+            // the group may be the result of merging multiple groups learned earlier.
+            evidence: collect_evidence(learned.iter().filter(|(g, _)| is_subset(g, group))),
+        })
+        .collect();
+
+    if !solved {
+        let mut unresolved: Vec<DependencyGroup> = Vec::new();
+        for (group, _) in strategy.unresolved_dependencies() {
+            // the same group may get stuck at multiple positions in the exploration
+            if unresolved.iter().any(|g| &g.modifiers == group) {
+                continue;
+            }
+            unresolved.push(DependencyGroup {
+                modifiers: group.clone(),
+                status: DependencyStatus::Unresolved,
+                evidence: collect_evidence(
+                    strategy.unresolved_dependencies().iter().filter(|(g, _)| g == group),
+                ),
+            });
+        }
+        result.extend(unresolved);
+    }
+
+    Ok(result)
+}
+
+/// Returns true if all modifiers of `a` are part of `b`
+fn is_subset(a: &[ConfigModifier], b: &[ConfigModifier]) -> bool {
+    a.iter().all(|m| b.contains(m))
+}
+
+/// Collect all distinct errors of the given records
+fn collect_evidence<'a>(
+    records: impl Iterator<Item = &'a (Vec<ConfigModifier>, Vec<PolicyError>)>,
+) -> Vec<PolicyError> {
+    let mut evidence: Vec<PolicyError> = Vec::new();
+    for err in records.flat_map(|(_, errors)| errors.iter()) {
+        if !evidence.contains(err) {
+            evidence.push(err.clone());
+        }
+    }
+    evidence
+}
@@ -0,0 +1,344 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Diagnostics
+//!
+//! This module contains a small benchmark, which measures for a given problem how the time of the
+//! synthesis is split between the network simulation, checking the hard policy, and the overhead
+//! of the search itself (cloning the network and the hard policy to branch off). The benchmark
+//! plays out random orderings of the modifiers, similar to
+//! [`estimate_search_space`](crate::analysis::estimate_search_space), and times every phase
+//! separately. In addition, the time to check the hard policy is split up by the kind of the
+//! conditions, to find out which conditions are the most expensive ones.
+//!
+//! Based on the measurements, [`ProblemProfile::recommendations`] suggests how the problem can be
+//! tuned, e.g., by scoping the reliability conditions to fewer routers.
+
+use crate::hard_policies::{Condition, HardPolicy};
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{Network, NetworkError};
+use crate::Error;
+
+use rand::prelude::*;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Fraction of the total time, above which a phase is considered to dominate.
+const DOMINANCE_THRESHOLD: f64 = 0.5;
+
+/// Kind of a condition, used to split up the time to check the hard policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConditionKind {
+    /// Conditions checked on the forwarding state only (reachability, isolation, path length and
+    /// egress).
+    Forwarding,
+    /// Reliability conditions, which need to simulate every single link failure.
+    Reliability,
+    /// Transient conditions, which need to analyze the convergence process.
+    Transient,
+    /// Valley-free conditions, which need to check the BGP tables.
+    ValleyFree,
+    /// Latency conditions, which need to check the link weights of the paths.
+    Latency,
+}
+
+impl ConditionKind {
+    /// All kinds of conditions.
+    pub const ALL: [ConditionKind; 5] =
+        [Self::Forwarding, Self::Reliability, Self::Transient, Self::ValleyFree, Self::Latency];
+
+    /// Get the kind of a condition
+    pub fn of(condition: &Condition) -> Self {
+        match condition {
+            Condition::Reachable(_, _, _)
+            | Condition::NotReachable(_, _)
+            | Condition::PathLengthAtMost(_, _, _)
+            | Condition::EgressIs(_, _, _) => Self::Forwarding,
+            Condition::Reliable(_, _, _) => Self::Reliability,
+            Condition::TransientPath(_, _, _) => Self::Transient,
+            Condition::ValleyFree(_, _) => Self::ValleyFree,
+            Condition::LatencyAtMost(_, _, _) => Self::Latency,
+        }
+    }
+}
+
+impl fmt::Display for ConditionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Forwarding => write!(f, "forwarding"),
+            Self::Reliability => write!(f, "reliability"),
+            Self::Transient => write!(f, "transient"),
+            Self::ValleyFree => write!(f, "valley-free"),
+            Self::Latency => write!(f, "latency"),
+        }
+    }
+}
+
+/// Recommendation how to tune a problem, computed by [`ProblemProfile::recommendations`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recommendation {
+    /// The simulation of the network dominates.
+    SimulationDominates,
+    /// Checking the hard policy dominates, and most of the time is spent on conditions of the given
+    /// kind.
+    ConditionsDominate(ConditionKind),
+    /// The overhead of the search (cloning the network and the hard policy) dominates.
+    SearchDominates,
+    /// Most modifiers cannot be applied without violating the hard policy, which means that the
+    /// search will need to backtrack a lot.
+    ManyViolations,
+}
+
+impl fmt::Display for Recommendation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SimulationDominates => write!(
+                f,
+                "network simulation dominates; consider reducing the number of prefixes, or \
+                 splitting the reconfiguration into smaller independent parts"
+            ),
+            Self::ConditionsDominate(ConditionKind::Reliability) => write!(
+                f,
+                "reliability conditions dominate; consider scoping them to fewer routers or \
+                 prefixes, since every condition requires to simulate all link failures"
+            ),
+            Self::ConditionsDominate(ConditionKind::Transient) => write!(
+                f,
+                "transient conditions dominate; consider scoping them to the routers and \
+                 prefixes which are actually affected by the reconfiguration"
+            ),
+            Self::ConditionsDominate(kind) => write!(
+                f,
+                "{} conditions dominate; consider removing redundant conditions, e.g., for \
+                 prefixes which are not affected by the reconfiguration",
+                kind
+            ),
+            Self::SearchDominates => write!(
+                f,
+                "search overhead dominates; consider using a multi-threaded strategy, or a \
+                 strategy which learns dependency groups"
+            ),
+            Self::ManyViolations => write!(
+                f,
+                "most modifiers violate the hard policy when applied; consider a strategy which \
+                 learns dependency groups, or relaxing the hard policy"
+            ),
+        }
+    }
+}
+
+/// # Problem Profile
+/// Result of [`profile_problem`], containing the accumulated time of every phase of the synthesis.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProblemProfile {
+    /// Number of modifiers which were applied to the network during the benchmark.
+    pub num_samples: usize,
+    /// Number of applied modifiers which violated the hard policy (or did not converge).
+    pub num_violations: usize,
+    /// Time spent simulating the network, i.e., applying and undoing modifiers, and computing the
+    /// forwarding state.
+    pub simulation: Duration,
+    /// Time spent checking the hard policy.
+    pub policy_check: Duration,
+    /// Time spent cloning the network and the hard policy, which strategies do in order to branch
+    /// off in the search tree.
+    pub search_overhead: Duration,
+    /// Time spent checking the conditions of each kind separately. The sum of these times is
+    /// typically close to (but not exactly) `policy_check`.
+    pub conditions: Vec<(ConditionKind, Duration)>,
+}
+
+impl ProblemProfile {
+    /// Total time of all phases (excluding the separate check of each kind of condition).
+    pub fn total(&self) -> Duration {
+        self.simulation + self.policy_check + self.search_overhead
+    }
+
+    /// Fraction of the total time spent on the given duration.
+    fn fraction(&self, d: Duration) -> f64 {
+        let total = self.total().as_secs_f64();
+        if total == 0.0 {
+            0.0
+        } else {
+            d.as_secs_f64() / total
+        }
+    }
+
+    /// Compute recommendations how the problem can be tuned, based on the measurements.
+    pub fn recommendations(&self) -> Vec<Recommendation> {
+        let mut result = Vec::new();
+        if self.fraction(self.simulation) > DOMINANCE_THRESHOLD {
+            result.push(Recommendation::SimulationDominates);
+        }
+        if self.fraction(self.policy_check) > DOMINANCE_THRESHOLD {
+            let total = self.conditions.iter().map(|(_, d)| d.as_secs_f64()).sum::<f64>();
+            if let Some((kind, d)) = self.conditions.iter().max_by_key(|(_, d)| *d) {
+                if total > 0.0 && d.as_secs_f64() / total > DOMINANCE_THRESHOLD {
+                    result.push(Recommendation::ConditionsDominate(*kind));
+                }
+            }
+        }
+        if self.fraction(self.search_overhead) > DOMINANCE_THRESHOLD {
+            result.push(Recommendation::SearchDominates);
+        }
+        if self.num_samples > 0
+            && self.num_violations as f64 / self.num_samples as f64 > DOMINANCE_THRESHOLD
+        {
+            result.push(Recommendation::ManyViolations);
+        }
+        result
+    }
+}
+
+impl fmt::Display for ProblemProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |d: Duration| 100.0 * self.fraction(d);
+        writeln!(
+            f,
+            "{} modifiers applied ({} violations) in {:.3}s",
+            self.num_samples,
+            self.num_violations,
+            self.total().as_secs_f64()
+        )?;
+        writeln!(f, "  simulation:      {:5.1}%", percent(self.simulation))?;
+        writeln!(f, "  policy check:    {:5.1}%", percent(self.policy_check))?;
+        for (kind, d) in self.conditions.iter() {
+            writeln!(f, "    {:<13}  {:.3}s", format!("{}:", kind), d.as_secs_f64())?;
+        }
+        write!(f, "  search overhead: {:5.1}%", percent(self.search_overhead))
+    }
+}
+
+/// Measure how the time of the synthesis is split between network simulation, policy checking and
+/// search overhead, by playing out `num_playouts` random orderings of the modifiers. During a
+/// playout, modifiers which violate the hard policy are undone and skipped. The network must be in
+/// the initial state. If `seed` is given, the playouts are reproducible (but the measured times are
+/// of course not).
+pub fn profile_problem(
+    net: &Network,
+    modifiers: &[ConfigModifier],
+    hard_policy: &HardPolicy,
+    num_playouts: usize,
+    seed: Option<u64>,
+) -> Result<ProblemProfile, Error> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let mut net = net.clone();
+    let mut hard_policy = hard_policy.clone();
+    hard_policy.reset();
+    hard_policy.set_num_mods_if_none(modifiers.len());
+
+    // prepare one policy for each kind of condition, in order to measure them separately
+    let mut kind_policies: Vec<(ConditionKind, HardPolicy)> = ConditionKind::ALL
+        .iter()
+        .map(|kind| {
+            let conditions: Vec<Condition> = hard_policy
+                .prop_vars
+                .iter()
+                .filter(|c| ConditionKind::of(c) == *kind)
+                .cloned()
+                .collect();
+            (*kind, HardPolicy::globally(conditions))
+        })
+        .filter(|(_, p)| !p.prop_vars.is_empty())
+        .collect();
+
+    // check the initial state
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state)?;
+    if !hard_policy.check() {
+        return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+    }
+    for (_, p) in kind_policies.iter_mut() {
+        p.set_num_mods_if_none(modifiers.len());
+        p.step(&mut net, &mut fw_state)?;
+    }
+
+    let mut profile = ProblemProfile {
+        conditions: kind_policies.iter().map(|(k, _)| (*k, Duration::default())).collect(),
+        ..Default::default()
+    };
+
+    let mut order: Vec<usize> = (0..modifiers.len()).collect();
+    for _ in 0..num_playouts {
+        order.shuffle(&mut rng);
+        let mut depth: usize = 0;
+        for idx in order.iter() {
+            // branch off, as a strategy would do
+            let start = Instant::now();
+            let _branch = (net.clone(), hard_policy.clone());
+            profile.search_overhead += start.elapsed();
+
+            profile.num_samples += 1;
+            let start = Instant::now();
+            let converged = match net.apply_modifier(&modifiers[*idx]) {
+                Ok(()) => true,
+                Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                    false
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if !converged {
+                net.undo_action()?;
+                profile.simulation += start.elapsed();
+                profile.num_violations += 1;
+                continue;
+            }
+            let mut fw_state = net.get_forwarding_state();
+            profile.simulation += start.elapsed();
+
+            let start = Instant::now();
+            hard_policy.step(&mut net, &mut fw_state)?;
+            let valid = hard_policy.check();
+            profile.policy_check += start.elapsed();
+
+            for ((_, p), (_, d)) in kind_policies.iter_mut().zip(profile.conditions.iter_mut()) {
+                let start = Instant::now();
+                p.step(&mut net, &mut fw_state)?;
+                p.check();
+                *d += start.elapsed();
+            }
+
+            if valid {
+                depth += 1;
+            } else {
+                profile.num_violations += 1;
+                hard_policy.undo();
+                kind_policies.iter_mut().for_each(|(_, p)| p.undo());
+                let start = Instant::now();
+                net.undo_action()?;
+                profile.simulation += start.elapsed();
+            }
+        }
+
+        // restore the initial state
+        let start = Instant::now();
+        for _ in 0..depth {
+            net.undo_action()?;
+        }
+        profile.simulation += start.elapsed();
+        for _ in 0..depth {
+            hard_policy.undo();
+            kind_policies.iter_mut().for_each(|(_, p)| p.undo());
+        }
+    }
+
+    Ok(profile)
+}
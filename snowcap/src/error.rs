@@ -17,8 +17,15 @@
 
 //! Module containing all error types
 
-use crate::netsim::{config::ConfigModifier, ConfigError, NetworkError};
+use crate::corpus::CorpusError;
+use crate::hard_policies::{HardPolicy, ParseError};
+use crate::netsim::{
+    config::ConfigModifier, ConfigError, ForwardingState, Network, NetworkError, RouterId,
+};
+use crate::network_spec::NetworkSpecError;
 use crate::topology_zoo::ZooTopologyError;
+use crate::traffic_matrix::TrafficMatrixError;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Main error type
@@ -37,21 +44,56 @@ pub enum Error {
     /// Global Optimum was not found using the GlobalOptimizer.
     #[error("Global optimum was not found: Best solution yet has cost {1}")]
     GlobalOptimumNotFound(Vec<ConfigModifier>, f64),
-    /// The initial state of the network or the configuration is invalid
-    #[error("Invalid initial state or configuration")]
-    InvalidInitialState,
+    /// The initial state of the network or the configuration is invalid. The error contains the
+    /// violated conditions of the hard policy (which might be empty if they are not known).
+    #[error("Invalid initial state or configuration{}", fmt_violations(.0))]
+    InvalidInitialState(Vec<InitialStateViolation>),
     /// The maximum number of backtracks are reached
     #[error("The configured max backtrack level was reached!")]
     ReachedMaxBacktrack,
-    /// Used up all of the time budget
-    #[error("The time budget was used up without finding any solution")]
-    Timeout,
+    /// Used up all of the time budget. The error contains the best partial result found so far.
+    #[error(
+        "The time budget was used up without finding a complete solution (best valid prefix: {} \
+         of {} modifiers)",
+        .0.sequence.len(),
+        .0.num_modifiers()
+    )]
+    Timeout(Box<PartialResult>),
     /// On an operation abort
     #[error("The operation was aborted")]
     Abort,
+    /// A window of the hard policy references an anchor which does not exist
+    #[error("Unknown anchor: {0}")]
+    UnknownAnchor(String),
+    /// A hard policy with anchors cannot be extended with additional conditions
+    #[error("Cannot extend a hard policy which contains anchors")]
+    AnchoredPolicyExtension,
+    /// The hard policy cannot be parsed
+    #[error("Policy Parse Error: {0}")]
+    PolicyParseError(#[from] ParseError),
+    /// Network Specification Error
+    #[error("Network Specification Error: {0}")]
+    NetworkSpecError(#[from] NetworkSpecError),
     /// Topology Zoo Error
     #[error("Topology Zoo Error: {0}")]
     ZooTopologyError(#[from] ZooTopologyError),
+    /// Traffic Matrix Error
+    #[error("Traffic Matrix Error: {0}")]
+    TrafficMatrixError(#[from] TrafficMatrixError),
+    /// Regression Corpus Error
+    #[error("Corpus Error: {0}")]
+    CorpusError(#[from] CorpusError),
+    /// The checkpoint cannot be used to resume the strategy
+    #[error("Invalid checkpoint: {0}")]
+    InvalidCheckpoint(String),
+    /// The transient violation probability cannot be estimated without any sample
+    #[cfg(feature = "transient-violation")]
+    #[error("At least one transient sample is required!")]
+    NoTransientSamples,
+    /// The SAT solver failed
+    #[cfg(feature = "sat-strategy")]
+    #[error("SAT Solver Error: {0}")]
+    SatSolverError(String),
 }
 
 impl From<ConfigError> for Error {
@@ -59,3 +101,177 @@ impl From<ConfigError> for Error {
         Self::NetworkError(NetworkError::ConfigError(cause))
     }
 }
+
+impl Error {
+    /// Returns `true` if the time budget was used up.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Timeout(_))
+    }
+
+    /// Returns the partial result, if the error was caused by a timeout.
+    pub fn partial_result(&self) -> Option<&PartialResult> {
+        match self {
+            Self::Timeout(partial) => Some(partial.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Build a timeout error, without any partial solution. All modifiers are still remaining.
+    pub(crate) fn timeout(modifiers: &[ConfigModifier]) -> Self {
+        Self::Timeout(Box::new(PartialResult::new(Vec::new(), modifiers)))
+    }
+
+    /// Build the error for an invalid initial state, after the hard policy was checked on the
+    /// initial state of `net` (with the forwarding state `fw_state`). The error contains all
+    /// conditions that need to change for the policy to be satisfied, but are violated. If there
+    /// are no such conditions, all violated conditions are reported.
+    pub(crate) fn invalid_initial_state(
+        net: &Network,
+        hard_policy: &HardPolicy,
+        fw_state: &mut ForwardingState,
+    ) -> Self {
+        let num_vars = hard_policy.prop_vars.len();
+        let (watch, errors) = hard_policy.get_watch_errors();
+        let mut violated: Vec<(usize, _)> =
+            watch.into_iter().zip(errors).filter(|(i, e)| *i < num_vars && e.is_some()).collect();
+        if violated.is_empty() {
+            let all = (0..num_vars).collect::<Vec<_>>();
+            violated = all
+                .iter()
+                .cloned()
+                .zip(hard_policy.last_errors_of_watch(&all))
+                .filter(|(_, e)| e.is_some())
+                .collect();
+        }
+        Self::InvalidInitialState(
+            violated
+                .into_iter()
+                .map(|(i, e)| {
+                    let condition = &hard_policy.prop_vars[i];
+                    InitialStateViolation {
+                        condition: condition.repr_with_name(net),
+                        error: e.map(|e| e.repr_with_name(net)).unwrap_or_default(),
+                        paths: fw_state
+                            .get_all_routes(condition.router_id(), condition.prefix())
+                            .into_iter()
+                            .map(|route| fmt_route(net, route))
+                            .collect(),
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// # Initial State Violation
+///
+/// Condition of the hard policy which is violated in the initial state, as reported by
+/// [`Error::InvalidInitialState`]. All routers are referenced by their name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InitialStateViolation {
+    /// The violated condition
+    pub condition: String,
+    /// Error describing the violation
+    pub error: String,
+    /// Forwarding paths from the router of the condition towards its prefix in the initial state
+    pub paths: Vec<String>,
+}
+
+impl std::fmt::Display for InitialStateViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.condition, self.error)?;
+        for path in self.paths.iter() {
+            write!(f, "\n      {}", path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format the violations of an invalid initial state, one per line.
+fn fmt_violations(violations: &[InitialStateViolation]) -> String {
+    violations.iter().map(|v| format!("\n  - {}", v)).collect()
+}
+
+/// Format a route of the forwarding state with the names of the routers.
+fn fmt_route(net: &Network, route: Result<Vec<RouterId>, NetworkError>) -> String {
+    let names = |path: &[RouterId]| {
+        path.iter().map(|r| net.get_router_name(*r).unwrap_or("?")).collect::<Vec<_>>().join(" -> ")
+    };
+    match route {
+        Ok(path) => names(&path),
+        Err(NetworkError::ForwardingLoop(path)) => format!("{} (forwarding loop)", names(&path)),
+        Err(NetworkError::ForwardingBlackHole(path)) => format!("{} (black hole)", names(&path)),
+        Err(e) => format!("{}", e),
+    }
+}
+
+/// # Partial Result
+///
+/// Best result found by a strategy or an optimizer before its time budget was used up. The
+/// `sequence` is a valid prefix of the reconfiguration, i.e., applying these modifiers in order
+/// satisfies the hard policies at every step. The `remaining` modifiers still need to be applied
+/// after the `sequence` (but no valid ordering was found for them yet).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartialResult {
+    /// Longest valid prefix of the reconfiguration found before the timeout.
+    pub sequence: Vec<ConfigModifier>,
+    /// Modifiers that are not yet part of the `sequence`.
+    pub remaining: Vec<ConfigModifier>,
+    /// Cost of the `sequence`, if it was found by an optimizer.
+    pub cost: Option<f64>,
+    /// Number of states explored before the timeout (only counted with the feature
+    /// `count-states`, and `0` otherwise).
+    pub num_states: usize,
+    /// Time spent searching, if known.
+    pub elapsed: Option<Duration>,
+}
+
+impl PartialResult {
+    /// Create a new partial result from the valid prefix `sequence`, and the list of all
+    /// modifiers. All modifiers which are not part of the `sequence` are `remaining`.
+    pub fn new(sequence: Vec<ConfigModifier>, modifiers: &[ConfigModifier]) -> Self {
+        let mut remaining = modifiers.to_vec();
+        for m in sequence.iter() {
+            if let Some(pos) = remaining.iter().position(|x| x == m) {
+                remaining.remove(pos);
+            }
+        }
+        Self { sequence, remaining, cost: None, num_states: 0, elapsed: None }
+    }
+
+    /// Set the cost of the partial sequence.
+    pub fn with_cost(mut self, cost: f64) -> Self {
+        self.cost = Some(cost);
+        self
+    }
+
+    /// Set the number of explored states.
+    pub fn with_num_states(mut self, num_states: usize) -> Self {
+        self.num_states = num_states;
+        self
+    }
+
+    /// Set the time spent searching.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed = Some(elapsed);
+        self
+    }
+
+    /// Returns `true` if the partial result contains a complete solution, i.e., no modifiers
+    /// are remaining. This can happen for optimizers, which found a valid, but not optimal
+    /// sequence.
+    pub fn is_complete(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// Returns the total number of modifiers of the problem.
+    pub fn num_modifiers(&self) -> usize {
+        self.sequence.len() + self.remaining.len()
+    }
+}
+
+impl From<PartialResult> for Error {
+    fn from(partial: PartialResult) -> Self {
+        Self::Timeout(Box::new(partial))
+    }
+}
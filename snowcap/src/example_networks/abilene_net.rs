@@ -17,7 +17,7 @@
 
 //! [Abilene Network](http://topology-zoo.org/dataset.html)
 
-use super::ExampleNetwork;
+use super::{ExampleNetwork, VariantRange};
 use crate::hard_policies::Condition::*;
 use crate::hard_policies::PathCondition::*;
 use crate::hard_policies::Waypoint::*;
@@ -99,6 +99,14 @@ use itertools::iproduct;
 pub struct AbileneNetwork;
 
 impl ExampleNetwork for AbileneNetwork {
+    fn initial_variants() -> VariantRange {
+        VariantRange::new(0, 6)
+    }
+
+    fn final_variants() -> VariantRange {
+        VariantRange::new(0, 6)
+    }
+
     /// Get raw network without configuration
     fn net(initial_variant: usize) -> Network {
         let mut net = Network::new();
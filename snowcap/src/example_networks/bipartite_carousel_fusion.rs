@@ -20,7 +20,7 @@
 
 use super::{
     repetitions::{Repetition1, Repetitions},
-    ExampleNetwork,
+    ExampleNetwork, VariantRange,
 };
 use crate::hard_policies::HardPolicy;
 use crate::netsim::config::{Config, ConfigExpr::*};
@@ -47,6 +47,14 @@ where
     BipartiteR: Repetitions,
     CarouselR: Repetitions,
 {
+    fn initial_variants() -> VariantRange {
+        VariantRange::from(2)
+    }
+
+    fn final_variants() -> VariantRange {
+        VariantRange::from(2)
+    }
+
     fn net(initial_variant: usize) -> Network {
         assert!(initial_variant > 1);
 
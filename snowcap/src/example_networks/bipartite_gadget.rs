@@ -20,7 +20,7 @@
 
 use super::{
     repetitions::{Repetition1, Repetitions},
-    ExampleNetwork,
+    ExampleNetwork, VariantRange,
 };
 use crate::hard_policies::HardPolicy;
 use crate::netsim::config::{Config, ConfigExpr::*};
@@ -85,6 +85,14 @@ impl<R> ExampleNetwork for BipartiteGadget<R>
 where
     R: Repetitions,
 {
+    fn initial_variants() -> VariantRange {
+        VariantRange::from(2)
+    }
+
+    fn final_variants() -> VariantRange {
+        VariantRange::from(2)
+    }
+
     fn net(initial_variant: usize) -> Network {
         assert!(initial_variant > 1);
 
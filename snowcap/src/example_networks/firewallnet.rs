@@ -17,7 +17,7 @@
 
 //! # Firewall Network
 
-use super::ExampleNetwork;
+use super::{ExampleNetwork, VariantRange};
 use crate::hard_policies::*;
 use crate::netsim::config::{Config, ConfigExpr::*};
 use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix};
@@ -49,6 +49,14 @@ use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix};
 pub struct FirewallNet {}
 
 impl ExampleNetwork for FirewallNet {
+    fn initial_variants() -> VariantRange {
+        VariantRange::new(0, 1)
+    }
+
+    fn final_variants() -> VariantRange {
+        VariantRange::new(0, 1)
+    }
+
     fn net(initial_variant: usize) -> Network {
         let mut net = Network::new();
 
@@ -17,7 +17,7 @@
 
 //! # Mediumnet Network
 
-use super::ExampleNetwork;
+use super::{ExampleNetwork, VariantRange};
 use crate::hard_policies::HardPolicy;
 use crate::netsim::config::{Config, ConfigExpr::*};
 use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix};
@@ -58,6 +58,14 @@ use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix};
 pub struct MediumNet {}
 
 impl ExampleNetwork for MediumNet {
+    fn initial_variants() -> VariantRange {
+        VariantRange::new(0, 1)
+    }
+
+    fn final_variants() -> VariantRange {
+        VariantRange::new(0, 3)
+    }
+
     fn net(initial_variant: usize) -> Network {
         let mut net = Network::new();
 
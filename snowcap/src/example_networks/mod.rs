@@ -71,4 +71,56 @@ pub trait ExampleNetwork {
     fn final_config(net: &Network, variant: usize) -> Config;
     /// Get the hard policies.
     fn get_policy(net: &Network, variant: usize) -> HardPolicy;
+    /// Variants accepted by [`ExampleNetwork::net`] and [`ExampleNetwork::initial_config`]. By
+    /// default, only the variant 0 is available.
+    fn initial_variants() -> VariantRange {
+        VariantRange::single()
+    }
+    /// Variants accepted by [`ExampleNetwork::final_config`] and [`ExampleNetwork::get_policy`]. By
+    /// default, only the variant 0 is available.
+    fn final_variants() -> VariantRange {
+        VariantRange::single()
+    }
+}
+
+/// Range of variants that an [`ExampleNetwork`] accepts. If `max` is `None`, the range is
+/// unbounded, which is the case when the variant determines the size of the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariantRange {
+    /// Smallest valid variant
+    pub min: usize,
+    /// Largest valid variant, or `None` if there is no upper bound.
+    pub max: Option<usize>,
+}
+
+impl VariantRange {
+    /// Range containing only the variant 0.
+    pub const fn single() -> Self {
+        Self { min: 0, max: Some(0) }
+    }
+
+    /// Range of variants from `min` to `max` (both inclusive).
+    pub const fn new(min: usize, max: usize) -> Self {
+        Self { min, max: Some(max) }
+    }
+
+    /// Unbounded range of variants, starting at `min`.
+    pub const fn from(min: usize) -> Self {
+        Self { min, max: None }
+    }
+
+    /// Returns `true` if the variant is part of the range.
+    pub fn contains(&self, variant: usize) -> bool {
+        variant >= self.min && self.max.map(|max| variant <= max).unwrap_or(true)
+    }
+}
+
+impl std::fmt::Display for VariantRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{}..={}", self.min, max),
+            None => write!(f, "{}..", self.min),
+        }
+    }
 }
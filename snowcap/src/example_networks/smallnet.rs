@@ -17,7 +17,7 @@
 
 //! Smallnet
 
-use super::ExampleNetwork;
+use super::{ExampleNetwork, VariantRange};
 use crate::hard_policies::HardPolicy;
 use crate::netsim::config::{Config, ConfigExpr::*};
 use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix};
@@ -28,6 +28,14 @@ use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix};
 pub struct SmallNet;
 
 impl ExampleNetwork for SmallNet {
+    fn initial_variants() -> VariantRange {
+        VariantRange::new(0, 2)
+    }
+
+    fn final_variants() -> VariantRange {
+        VariantRange::new(0, 1)
+    }
+
     /// Get raw network without configuration
     fn net(initial_variant: usize) -> Network {
         let mut net = Network::new();
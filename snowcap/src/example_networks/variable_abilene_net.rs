@@ -17,7 +17,7 @@
 
 //! [Abilene Network](http://topology-zoo.org/dataset.html)
 
-use super::{repetitions::Repetitions, ExampleNetwork};
+use super::{repetitions::Repetitions, ExampleNetwork, VariantRange};
 use crate::hard_policies::*;
 use crate::netsim::config::{Config, ConfigExpr::*};
 use crate::netsim::route_map::*;
@@ -47,6 +47,10 @@ pub struct VariableAbileneNetwork<R: Repetitions> {
 }
 
 impl<R: Repetitions> ExampleNetwork for VariableAbileneNetwork<R> {
+    fn final_variants() -> VariantRange {
+        VariantRange::new(1, 65)
+    }
+
     /// Get raw network without configuration
     fn net(initial_variant: usize) -> Network {
         let mut net = Network::new();
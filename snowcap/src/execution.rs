@@ -0,0 +1,200 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Operator-in-the-Loop Execution
+//!
+//! While a plan is executed on the real network, a step may need to be postponed, e.g., because
+//! the device is currently unreachable. The [`ExecutionState`] keeps track of the steps that were
+//! already executed, and [`next_step_options`] ranks all remaining steps, which can safely be
+//! executed next. This way, the operator can continue with an alternative step, rather than
+//! aborting the entire plan.
+
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{Network, NetworkError};
+use crate::soft_policies::SoftPolicy;
+use crate::Error;
+
+use std::cmp::Ordering;
+
+/// # Execution State
+///
+/// State of a plan which is partially executed. The network and the hard policy are in the state
+/// after all executed steps, and the soft policy `P` is used to compute the cost of the steps.
+///
+/// ```
+/// use snowcap::example_networks::*;
+/// use snowcap::execution::{next_step_options, ExecutionState};
+/// use snowcap::hard_policies::HardPolicy;
+/// use snowcap::soft_policies::MinimizeTrafficShift;
+///
+/// let net = SimpleNet::net(0);
+/// let final_config = SimpleNet::final_config(&net, 0);
+/// let plan = net.current_config().get_diff(&final_config).modifiers;
+/// let hard_policy = HardPolicy::globally(vec![]);
+///
+/// let mut state = ExecutionState::<MinimizeTrafficShift>::new(&net, plan, hard_policy).unwrap();
+/// while !state.is_done() {
+///     // the first step of the plan is postponed, if there is an alternative
+///     let options = next_step_options(&state).unwrap();
+///     let postponed = &state.remaining()[0];
+///     let next = options.iter().find(|o| o.modifier != *postponed).unwrap_or(&options[0]);
+///     let index = state.remaining().iter().position(|m| *m == next.modifier).unwrap();
+///     assert!(state.execute(index).unwrap());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExecutionState<P> {
+    net: Network,
+    hard_policy: HardPolicy,
+    soft_policy: P,
+    executed: Vec<ConfigModifier>,
+    remaining: Vec<ConfigModifier>,
+    cost: f64,
+}
+
+impl<P> ExecutionState<P>
+where
+    P: SoftPolicy + Clone,
+{
+    /// Start the execution of the plan. The network must be in the initial state. Returns
+    /// `Err(InvalidInitialState)` if the initial state violates the hard policy.
+    pub fn new(
+        net: &Network,
+        plan: Vec<ConfigModifier>,
+        mut hard_policy: HardPolicy,
+    ) -> Result<Self, Error> {
+        let mut net = net.clone();
+        hard_policy.reset();
+        hard_policy.set_num_mods_if_none(plan.len());
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state)?;
+        if !hard_policy.check_overwrite_finish(plan.is_empty()) {
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+        }
+        let soft_policy = P::new(&mut fw_state, &net);
+        Ok(Self { net, hard_policy, soft_policy, executed: Vec::new(), remaining: plan, cost: 0.0 })
+    }
+
+    /// Returns the network in the state after all executed steps.
+    pub fn network(&self) -> &Network {
+        &self.net
+    }
+
+    /// Returns all steps executed so far, in order.
+    pub fn executed(&self) -> &[ConfigModifier] {
+        &self.executed
+    }
+
+    /// Returns all steps which are not yet executed, in the order of the plan.
+    pub fn remaining(&self) -> &[ConfigModifier] {
+        &self.remaining
+    }
+
+    /// Returns the cost of the soft policy, accumulated over all executed steps.
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+
+    /// Returns `true` if all steps are executed.
+    pub fn is_done(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// Execute the remaining step at position `index`. The step is executed even if it violates
+    /// the hard policy (since it was also executed on the real network), and the function returns
+    /// `false` in this case. If the network does not converge, the step is not executed, and the
+    /// error is returned.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn execute(&mut self, index: usize) -> Result<bool, Error> {
+        let modifier = self.remaining.remove(index);
+        if let Err(e) = self.net.apply_modifier(&modifier) {
+            if matches!(e, NetworkError::NoConvergence | NetworkError::ConvergenceLoop(_, _)) {
+                self.net.undo_action()?;
+            }
+            self.remaining.insert(index, modifier);
+            return Err(e.into());
+        }
+        let mut fw_state = self.net.get_forwarding_state();
+        self.hard_policy.step(&mut self.net, &mut fw_state)?;
+        self.soft_policy.update(&mut fw_state, &self.net);
+        self.cost += self.soft_policy.cost();
+        self.executed.push(modifier);
+        Ok(self.hard_policy.check_overwrite_finish(self.remaining.is_empty()))
+    }
+}
+
+/// # Option for the next step
+/// Remaining step, which can be executed next without violating the hard policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepOption {
+    /// The step to execute
+    pub modifier: ConfigModifier,
+    /// Cost of the soft policy caused by this step (see [`SoftPolicy::cost`]).
+    pub cost_delta: f64,
+    /// Risk score of the step, which is the number of BGP messages exchanged while the network
+    /// converges after applying it.
+    pub risk: usize,
+}
+
+/// Returns all remaining steps, which can be executed next without violating the hard policy,
+/// ranked first by their cost, then by their risk, and finally by their position in the plan.
+/// Steps after which the network does not converge are not returned. Note, that this only checks
+/// the next step, and not whether the remaining steps can still be ordered safely afterwards.
+pub fn next_step_options<P>(state: &ExecutionState<P>) -> Result<Vec<StepOption>, Error>
+where
+    P: SoftPolicy + Clone,
+{
+    let mut net = state.net.clone();
+    let mut hard_policy = state.hard_policy.clone();
+    let last_step = state.remaining.len() == 1;
+    let mut options = Vec::new();
+
+    for modifier in state.remaining.iter() {
+        let num_msg_before = net.num_msg_exchanged();
+        match net.apply_modifier(modifier) {
+            Ok(()) => {}
+            Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                net.undo_action()?;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let risk = net.num_msg_exchanged() - num_msg_before;
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state)?;
+        if hard_policy.check_overwrite_finish(last_step) {
+            let mut soft_policy = state.soft_policy.clone();
+            soft_policy.update(&mut fw_state, &net);
+            options.push(StepOption {
+                modifier: modifier.clone(),
+                cost_delta: soft_policy.cost(),
+                risk,
+            });
+        }
+        hard_policy.undo();
+        net.undo_action()?;
+    }
+
+    // the sort is stable, such that the order of the plan is kept on ties.
+    options.sort_by(|a, b| {
+        a.cost_delta.partial_cmp(&b.cost_delta).unwrap_or(Ordering::Equal).then(a.risk.cmp(&b.risk))
+    });
+    Ok(options)
+}
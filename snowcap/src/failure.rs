@@ -0,0 +1,225 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Explanation of a Failed Synthesis
+//!
+//! When no safe ordering can be found, [`explain_failure`] extracts a counterexample, which is
+//! reported as a [`SynthesisFailure`]. It consists of two parts:
+//!
+//! 1. **Blocking prefix**: Starting from the initial state, modifiers are applied greedily (in the
+//!    given order), as long as one of them keeps the hard policy satisfied. Once no remaining
+//!    modifier can be applied, the applied sequence is the prefix, and every remaining modifier is
+//!    reported together with the errors of the hard policy when trying to apply it.
+//! 2. **Minimal subset**: The set of modifiers is reduced one modifier at a time, as long as the
+//!    reduced set still has no safe ordering, until no modifier can be removed. The result is a
+//!    subset without a safe ordering, in which every modifier is necessary (i.e., removing any
+//!    single modifier makes it solvable, or its feasibility is unknown within the time budget).
+//!    Every check uses the exhaustive [`TreeStrategy`], such that the subset is proven to have no
+//!    safe ordering.
+//!
+//! Every pass of the reduction solves up to $n$ problems, where $n$ is the number of modifiers. Use
+//! the time budget to bound the time of each of them.
+
+use crate::hard_policies::{HardPolicy, PolicyError};
+use crate::modifier_ordering::NoOrdering;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{printer, Network, NetworkError};
+use crate::strategies::{Strategy, TreeStrategy};
+use crate::{ControlHandle, Error};
+
+use log::*;
+use std::time::Duration;
+
+/// # Synthesis Failure
+/// Counterexample explaining why no safe ordering could be found, computed by [`explain_failure`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthesisFailure {
+    /// Sequence of modifiers which satisfies the hard policy, but after which none of the remaining
+    /// modifiers can be applied.
+    pub prefix: Vec<ConfigModifier>,
+    /// All modifiers which cannot be applied after the prefix, together with the reason.
+    pub blocked: Vec<BlockedModifier>,
+    /// Minimal subset of modifiers without any safe ordering, or `None` if no subset could be
+    /// proven to have no safe ordering within the time budget.
+    pub minimal_subset: Option<Vec<ConfigModifier>>,
+}
+
+/// Modifier which violates the hard policy when applied after the prefix of a
+/// [`SynthesisFailure`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockedModifier {
+    /// The modifier which cannot be applied
+    pub modifier: ConfigModifier,
+    /// Errors of the hard policy after applying the modifier. If the network does not converge,
+    /// this contains [`PolicyError::NoConvergence`].
+    pub errors: Vec<PolicyError>,
+}
+
+impl SynthesisFailure {
+    /// Get a string representing the failure, where all router names are inserted.
+    pub fn repr_with_name(&self, net: &Network) -> String {
+        let fmt_mod = |m: &ConfigModifier| {
+            printer::config_modifier(net, m).unwrap_or_else(|_| format!("{:?}", m))
+        };
+        let mut lines: Vec<String> = Vec::new();
+        lines.push(format!("Blocked after a prefix of {} modifiers:", self.prefix.len()));
+        lines.extend(
+            self.prefix.iter().enumerate().map(|(i, m)| format!("{:>5}: {}", i, fmt_mod(m))),
+        );
+        lines.push(format!("{} modifiers cannot be applied after the prefix:", self.blocked.len()));
+        for b in self.blocked.iter() {
+            lines.push(format!("    {}", fmt_mod(&b.modifier)));
+            lines.extend(b.errors.iter().map(|e| format!("        {}", e.repr_with_name(net))));
+        }
+        match self.minimal_subset.as_ref() {
+            Some(subset) => {
+                lines.push(format!(
+                    "Minimal subset of {} modifiers without a safe ordering:",
+                    subset.len()
+                ));
+                lines.extend(subset.iter().map(|m| format!("    {}", fmt_mod(m))));
+            }
+            None => lines.push("No minimal subset could be found.".to_string()),
+        }
+        lines.join("\n")
+    }
+}
+
+/// Explain why no safe ordering exists for the modifiers, by computing a blocking prefix and a
+/// minimal subset of modifiers without a safe ordering (see the [module
+/// documentation](crate::failure)). The network must be in the initial state. The `time_budget`
+/// applies to every single problem solved while reducing the set of modifiers.
+///
+/// Returns `Ok(None)` if a safe ordering was found instead.
+pub fn explain_failure(
+    net: &Network,
+    modifiers: &[ConfigModifier],
+    hard_policy: &HardPolicy,
+    time_budget: Option<Duration>,
+) -> Result<Option<SynthesisFailure>, Error> {
+    let (prefix, blocked) = match blocking_prefix(net, modifiers, hard_policy)? {
+        Some(counterexample) => counterexample,
+        None => return Ok(None),
+    };
+
+    // reduce the set of modifiers, as long as it has no safe ordering
+    let mut subset: Vec<ConfigModifier> = modifiers.to_vec();
+    let mut proven = match solvable(net, &subset, hard_policy, time_budget)? {
+        Some(true) => return Ok(None),
+        Some(false) => true,
+        None => false,
+    };
+    // Removing a modifier may make another one unnecessary. Hence, repeat until no modifier can be
+    // removed anymore.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..subset.len()).rev() {
+            let mut candidate = subset.clone();
+            let removed = candidate.remove(i);
+            if solvable(net, &candidate, hard_policy, time_budget)? == Some(false) {
+                debug!("Modifier is not necessary for the failure: {:?}", removed);
+                subset = candidate;
+                proven = true;
+                changed = true;
+            }
+        }
+    }
+
+    Ok(Some(SynthesisFailure {
+        prefix,
+        blocked,
+        minimal_subset: if proven { Some(subset) } else { None },
+    }))
+}
+
+/// Apply the modifiers greedily, and return the prefix after which no modifier can be applied,
+/// together with the blocked modifiers. Returns `None` if all modifiers could be applied.
+#[allow(clippy::type_complexity)]
+fn blocking_prefix(
+    net: &Network,
+    modifiers: &[ConfigModifier],
+    hard_policy: &HardPolicy,
+) -> Result<Option<(Vec<ConfigModifier>, Vec<BlockedModifier>)>, Error> {
+    let mut net = net.clone();
+    let mut hard_policy = hard_policy.clone();
+    hard_policy.set_num_mods_if_none(modifiers.len());
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state)?;
+    if !hard_policy.check() {
+        return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+    }
+
+    let mut prefix: Vec<ConfigModifier> = Vec::new();
+    let mut remaining: Vec<ConfigModifier> = modifiers.to_vec();
+    while !remaining.is_empty() {
+        let mut blocked: Vec<BlockedModifier> = Vec::new();
+        let mut next: Option<usize> = None;
+        for (i, m) in remaining.iter().enumerate() {
+            match net.apply_modifier(m) {
+                Ok(()) => {}
+                Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                    net.undo_action()?;
+                    blocked.push(BlockedModifier {
+                        modifier: m.clone(),
+                        errors: vec![PolicyError::NoConvergence],
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            }
+            let mut fw_state = net.get_forwarding_state();
+            hard_policy.step(&mut net, &mut fw_state)?;
+            if hard_policy.check_overwrite_finish(remaining.len() == 1) {
+                next = Some(i);
+                break;
+            }
+            let mut errors = hard_policy.last_errors().into_iter().collect::<Vec<_>>();
+            errors.sort_by_key(|e| format!("{:?}", e));
+            hard_policy.undo();
+            net.undo_action()?;
+            blocked.push(BlockedModifier { modifier: m.clone(), errors });
+        }
+        match next {
+            Some(i) => prefix.push(remaining.remove(i)),
+            None => return Ok(Some((prefix, blocked))),
+        }
+    }
+    Ok(None)
+}
+
+/// Check if the modifiers have a safe ordering, using the exhaustive tree strategy. Returns `None`
+/// if the time budget was used up before the search space was explored.
+fn solvable(
+    net: &Network,
+    modifiers: &[ConfigModifier],
+    hard_policy: &HardPolicy,
+    time_budget: Option<Duration>,
+) -> Result<Option<bool>, Error> {
+    let mut strategy = TreeStrategy::<NoOrdering>::new(
+        net.clone(),
+        modifiers.to_vec(),
+        hard_policy.clone(),
+        time_budget,
+    )?;
+    match strategy.work(ControlHandle::new()) {
+        Ok(_) => Ok(Some(true)),
+        Err(Error::NoSafeOrdering) => Ok(Some(false)),
+        Err(Error::Timeout(_)) | Err(Error::ReachedMaxBacktrack) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
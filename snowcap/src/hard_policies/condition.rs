@@ -21,10 +21,11 @@
 //! false.
 
 use super::{prepare_loop_path, PolicyError};
-use crate::netsim::{ForwardingState, Network, NetworkError, Prefix, RouterId};
+use crate::netsim::{ForwardingState, LinkWeight, Network, NetworkError, Prefix, RouterId};
 
 use itertools::iproduct;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 /// Condition that can be checked for either being true or false.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -41,6 +42,59 @@ pub enum Condition {
     Reliable(RouterId, Prefix, Option<PathCondition>),
     /// Condition on the path during transient state
     TransientPath(RouterId, Prefix, PathCondition),
+    /// Condition that the router does not export a route for the prefix in violation of the
+    /// valley-free property, i.e., a route learned from a peer or a provider must not be exported
+    /// to a peer or a provider (see
+    /// [`BgpRelationship`](crate::netsim::bgp::BgpRelationship)). External neighbors without a
+    /// relationship are treated as non-customers.
+    ValleyFree(RouterId, Prefix),
+    /// Condition that the router can reach the prefix, and every path towards it traverses at most
+    /// the given number of links (hops).
+    PathLengthAtMost(RouterId, Prefix, usize),
+    /// Condition that the router can reach the prefix, and the latency (sum of the IGP link
+    /// weights) of every path towards it does not exceed the given bound.
+    LatencyAtMost(RouterId, Prefix, Latency),
+    /// Condition that the router can reach the prefix, and the traffic leaves the network at one of
+    /// the given border routers (see [`ForwardingState::get_egress`]).
+    EgressIs(RouterId, Prefix, Vec<RouterId>),
+}
+
+/// Latency of a path, computed as the sum of the IGP link weights along the path. Two latencies are
+/// equal if their bit representation is equal, such that it can be used inside a [`Condition`].
+#[derive(Debug, Clone, Copy)]
+pub struct Latency(pub LinkWeight);
+
+impl PartialEq for Latency {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Latency {}
+
+impl Hash for Latency {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state)
+    }
+}
+
+impl fmt::Display for Latency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Latency {
+    /// Compute the latency of a path, as the sum of the IGP link weights along the path. Links
+    /// which are not part of the IGP topology (e.g., towards external routers) have no latency.
+    pub fn of_path(net: &Network, path: &[RouterId]) -> Self {
+        let g = net.get_topology();
+        Self(
+            path.windows(2)
+                .filter_map(|w| g.find_edge(w[0], w[1]).and_then(|e| g.edge_weight(e)))
+                .sum(),
+        )
+    }
 }
 
 impl fmt::Display for Condition {
@@ -60,6 +114,20 @@ impl fmt::Display for Condition {
             Self::TransientPath(r, p, c) => {
                 write!(f, "Transient(r{}, prefix {}, condition {})", r.index(), p.0, c)
             }
+            Self::ValleyFree(r, p) => write!(f, "ValleyFree(r{}, prefix {})", r.index(), p.0),
+            Self::PathLengthAtMost(r, p, k) => {
+                write!(f, "PathLength(r{}, prefix {}, {})", r.index(), p.0, k)
+            }
+            Self::LatencyAtMost(r, p, l) => {
+                write!(f, "Latency(r{}, prefix {}, {})", r.index(), p.0, l)
+            }
+            Self::EgressIs(r, p, egresses) => write!(
+                f,
+                "Egress(r{}, prefix {}, [{}])",
+                r.index(),
+                p.0,
+                egresses.iter().map(|e| format!("r{}", e.index())).collect::<Vec<_>>().join(", ")
+            ),
         }
     }
 }
@@ -93,39 +161,133 @@ impl Condition {
                 "Transient({}, prefix {}, condition {})",
                 net.get_router_name(*r).unwrap(),
                 p.0,
-                c
+                c.repr_with_name(net)
+            ),
+            Self::ValleyFree(r, p) => {
+                format!("ValleyFree({}, prefix {})", net.get_router_name(*r).unwrap(), p.0)
+            }
+            Self::PathLengthAtMost(r, p, k) => {
+                format!("PathLength({}, prefix {}, {})", net.get_router_name(*r).unwrap(), p.0, k)
+            }
+            Self::LatencyAtMost(r, p, l) => {
+                format!("Latency({}, prefix {}, {})", net.get_router_name(*r).unwrap(), p.0, l)
+            }
+            Self::EgressIs(r, p, egresses) => format!(
+                "Egress({}, prefix {}, [{}])",
+                net.get_router_name(*r).unwrap(),
+                p.0,
+                egresses
+                    .iter()
+                    .map(|e| net.get_router_name(*e).unwrap())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
             ),
         }
     }
 
-    /// Check the the condition, returning a policy error if it is violated.
+    /// Check the the condition, returning a policy error if it is violated. If ECMP is enabled,
+    /// the condition is checked on every path, and the first violation is returned.
     ///
-    /// **Warning**: reliability or transient condition is not checked here, but will just return
-    /// `Ok`.
+    /// **Warning**: reliability, transient, valley-free or latency condition is not checked here,
+    /// but will just return `Ok`. Use [`Condition::check_latency`] for latency conditions.
     pub fn check(&self, fw_state: &mut ForwardingState) -> Result<(), PolicyError> {
         match self {
-            Self::Reachable(r, p, c) => match fw_state.get_route(*r, *p) {
-                Ok(path) => match c {
-                    None => Ok(()),
-                    Some(c) => c.check(&path, *p),
-                },
-                Err(NetworkError::ForwardingLoop(path)) => {
-                    Err(PolicyError::ForwardingLoop { path: prepare_loop_path(path), prefix: *p })
-                }
-                Err(NetworkError::ForwardingBlackHole(path)) => {
-                    Err(PolicyError::BlackHole { router: *path.last().unwrap(), prefix: *p })
+            // With ECMP, every path must satisfy the condition.
+            Self::Reachable(r, p, c) => check_all_paths(fw_state, *r, *p, |path| match c {
+                None => Ok(()),
+                Some(c) => c.check(path, *p),
+            }),
+            Self::PathLengthAtMost(r, p, k) => check_all_paths(fw_state, *r, *p, |path| {
+                if path.len() - 1 <= *k {
+                    Ok(())
+                } else {
+                    Err(PolicyError::PathTooLong {
+                        router: *r,
+                        prefix: *p,
+                        path: path.to_vec(),
+                        max_len: *k,
+                    })
                 }
-                Err(e) => panic!("Unrecoverable error detected: {}", e),
-            },
-            Self::NotReachable(r, p) => match fw_state.get_route(*r, *p) {
-                Err(NetworkError::ForwardingBlackHole(_)) => Ok(()),
-                Err(NetworkError::ForwardingLoop(_)) => Ok(()),
-                Err(e) => panic!("Unrecoverable error detected: {}", e),
-                Ok(path) => Err(PolicyError::UnallowedPathExists { router: *r, prefix: *p, path }),
-            },
+            }),
+            // With ECMP, no path may reach the prefix.
+            Self::EgressIs(r, p, egresses) => fw_state
+                .get_all_egresses(*r, *p)
+                .into_iter()
+                .map(|egress| match egress {
+                    Ok(egress) if egresses.contains(&egress) => Ok(()),
+                    Ok(egress) => Err(PolicyError::WrongEgress {
+                        router: *r,
+                        prefix: *p,
+                        egress,
+                        allowed: egresses.clone(),
+                    }),
+                    Err(e) => Err(route_error(e, *p)),
+                })
+                .collect(),
+            Self::NotReachable(r, p) => fw_state
+                .get_all_routes(*r, *p)
+                .into_iter()
+                .map(|route| match route {
+                    Err(NetworkError::ForwardingBlackHole(_)) => Ok(()),
+                    Err(NetworkError::ForwardingLoop(_)) => Ok(()),
+                    Err(e) => panic!("Unrecoverable error detected: {}", e),
+                    Ok(path) => {
+                        Err(PolicyError::UnallowedPathExists { router: *r, prefix: *p, path })
+                    }
+                })
+                .collect(),
             Self::Reliable(_, _, _) => Ok(()),
             Self::TransientPath(_, _, _) => Ok(()),
+            Self::ValleyFree(_, _) => Ok(()),
+            Self::LatencyAtMost(_, _, _) => Ok(()),
+        }
+    }
+
+    /// Check a latency condition, which requires the link weights of the network, returning a
+    /// policy error if it is violated. If ECMP is enabled, every path must satisfy the bound. For
+    /// any other condition, this function just returns `Ok`.
+    pub fn check_latency(
+        &self,
+        net: &Network,
+        fw_state: &mut ForwardingState,
+    ) -> Result<(), PolicyError> {
+        match self {
+            Self::LatencyAtMost(r, p, bound) => check_all_paths(fw_state, *r, *p, |path| {
+                let latency = Latency::of_path(net, path);
+                if latency.0 <= bound.0 {
+                    Ok(())
+                } else {
+                    Err(PolicyError::LatencyExceeded {
+                        router: *r,
+                        prefix: *p,
+                        path: path.to_vec(),
+                        latency,
+                        bound: *bound,
+                    })
+                }
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Create a latency condition, which allows the router to use paths towards the prefix of at
+    /// most `factor` times the latency of the path it currently uses in `net`. If the router
+    /// currently uses multiple paths (ECMP), the largest latency is used. This can be used to
+    /// express SLAs like "the path must not exceed twice the latency of the primary path". Returns
+    /// `None` if the router has currently no valid path towards the prefix.
+    pub fn latency_stretch(
+        net: &Network,
+        router: RouterId,
+        prefix: Prefix,
+        factor: LinkWeight,
+    ) -> Option<Self> {
+        let mut fw_state = net.get_forwarding_state();
+        let mut max_latency: Option<LinkWeight> = None;
+        for route in fw_state.get_all_routes(router, prefix) {
+            let latency = Latency::of_path(net, &route.ok()?).0;
+            max_latency = Some(max_latency.map_or(latency, |l| l.max(latency)));
         }
+        max_latency.map(|l| Self::LatencyAtMost(router, prefix, Latency(l * factor)))
     }
 
     /// Returns wether the condition is a reliability condition or not.
@@ -133,11 +295,21 @@ impl Condition {
         matches!(self, Self::Reliable(_, _, _))
     }
 
-    /// Returns wether the condition is a reliability condition or not.
+    /// Returns wether the condition is a transient condition or not.
     pub fn is_transient(&self) -> bool {
         matches!(self, Self::TransientPath(_, _, _))
     }
 
+    /// Returns wether the condition is a valley-free condition or not.
+    pub fn is_valley_free(&self) -> bool {
+        matches!(self, Self::ValleyFree(_, _))
+    }
+
+    /// Returns wether the condition is a latency condition or not.
+    pub fn is_latency(&self) -> bool {
+        matches!(self, Self::LatencyAtMost(_, _, _))
+    }
+
     /// Returns the router id of the condition
     pub fn router_id(&self) -> RouterId {
         match self {
@@ -145,6 +317,10 @@ impl Condition {
             Condition::NotReachable(r, _) => *r,
             Condition::Reliable(r, _, _) => *r,
             Condition::TransientPath(r, _, _) => *r,
+            Condition::ValleyFree(r, _) => *r,
+            Condition::PathLengthAtMost(r, _, _) => *r,
+            Condition::LatencyAtMost(r, _, _) => *r,
+            Condition::EgressIs(r, _, _) => *r,
         }
     }
 
@@ -155,7 +331,46 @@ impl Condition {
             Condition::NotReachable(_, p) => *p,
             Condition::Reliable(_, p, _) => *p,
             Condition::TransientPath(_, p, _) => *p,
+            Condition::ValleyFree(_, p) => *p,
+            Condition::PathLengthAtMost(_, p, _) => *p,
+            Condition::LatencyAtMost(_, p, _) => *p,
+            Condition::EgressIs(_, p, _) => *p,
+        }
+    }
+}
+
+/// Check `f` on every path from `router` towards `prefix`, and return the first violation.
+/// Forwarding loops and black holes are reported as such.
+fn check_all_paths<F>(
+    fw_state: &mut ForwardingState,
+    router: RouterId,
+    prefix: Prefix,
+    f: F,
+) -> Result<(), PolicyError>
+where
+    F: Fn(&[RouterId]) -> Result<(), PolicyError>,
+{
+    fw_state
+        .get_all_routes(router, prefix)
+        .into_iter()
+        .map(|route| match route {
+            Ok(path) => f(&path),
+            Err(e) => Err(route_error(e, prefix)),
+        })
+        .collect()
+}
+
+/// Transform the error of a route into a policy error. Only forwarding loops and black holes are
+/// expected.
+fn route_error(e: NetworkError, prefix: Prefix) -> PolicyError {
+    match e {
+        NetworkError::ForwardingLoop(path) => {
+            PolicyError::ForwardingLoop { path: prepare_loop_path(path), prefix }
+        }
+        NetworkError::ForwardingBlackHole(path) => {
+            PolicyError::BlackHole { router: *path.last().unwrap(), prefix }
         }
+        e => panic!("Unrecoverable error detected: {}", e),
     }
 }
 
@@ -18,17 +18,23 @@
 //! # Linear Temporal Logic
 
 use super::condition::Condition;
+use super::ltl_ast::LtlAst;
+use super::window::is_applied;
 use super::{PolicyError, TransientStateAnalyzer};
 use crate::netsim::{
     config::{ConfigExpr, ConfigModifier},
-    ForwardingState, Network, NetworkError, Prefix, RouterId,
+    ForwardingState, Network, NetworkDevice, NetworkError, Prefix, RouterId,
 };
+use crate::strategies::{CheckResults, StateCache};
+use crate::Error;
 
 use itertools::iproduct;
 use std::boxed::Box;
 use std::collections::HashSet;
 use std::fmt;
 use std::ops::{BitAnd, BitOr, BitXor, Not};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 /// Type alias for comfortable handling of the watch errors
 pub type WatchErrors = (Vec<usize>, Vec<Option<PolicyError>>);
@@ -49,6 +55,8 @@ pub struct HardPolicy {
     pub expr: LTLModal,
     num_mods: Option<usize>,
     tsa: Option<TransientStateAnalyzer>,
+    pub(super) anchors: Vec<(String, ConfigModifier)>,
+    state_cache: Option<Arc<Mutex<StateCache>>>,
 }
 
 impl HardPolicy {
@@ -112,9 +120,38 @@ impl HardPolicy {
             expr,
             num_mods: None,
             tsa,
+            anchors: Vec::new(),
+            state_cache: None,
         }
     }
 
+    /// Extend the hard policy with additional conditions, which must hold in every state (in
+    /// addition to the existing expression). The history of the hard policy is cleared. Policies
+    /// with anchors cannot be extended, since the propositional variables of the anchors are
+    /// placed after all conditions.
+    pub fn extend_globally(&mut self, conditions: Vec<Condition>) -> Result<(), Error> {
+        if !self.anchors.is_empty() {
+            return Err(Error::AnchoredPolicyExtension);
+        }
+        if conditions.is_empty() {
+            return Ok(());
+        }
+        let offset = self.prop_vars.len();
+        let mut prop_vars = self.prop_vars.clone();
+        prop_vars.extend(conditions);
+        let extension = LTLModal::Globally(Box::new(LTLBoolean::And(
+            (offset..prop_vars.len()).map(|i| Box::new(i) as Box<dyn LTLOperator>).collect(),
+        )));
+        let expr = LTLModal::Now(Box::new(LTLBoolean::And(vec![
+            Box::new(self.expr.clone()),
+            Box::new(extension),
+        ])));
+        let num_mods = self.num_mods;
+        *self = Self::new(prop_vars, expr);
+        self.num_mods = num_mods;
+        Ok(())
+    }
+
     /// Sets the total number of modifiers, if it was not yet set before. If it is already set, then
     /// nothing will change. This function returns `true` if there was no previous value.
     pub fn set_num_mods_if_none(&mut self, num_mods: usize) -> bool {
@@ -132,6 +169,143 @@ impl HardPolicy {
         net: &mut Network,
         state: &mut ForwardingState,
     ) -> Result<(), NetworkError> {
+        let (new_state, new_error) = self.check_all(net, state)?;
+        self.finish_step(net, new_state, new_error);
+        Ok(())
+    }
+
+    /// Applies a next step to the LTL model, reusing the forwarding state and the results of all
+    /// checks from the state cache (see [`HardPolicy::enable_state_cache`]) if the network was
+    /// already checked with the same configuration. Only the transient conditions and the anchors
+    /// are always evaluated, since they depend on the sequence of states. Without a state cache,
+    /// this function is equivalent to calling [`HardPolicy::step`] with the current forwarding
+    /// state of the network.
+    pub fn step_cached(&mut self, net: &mut Network) -> Result<(), NetworkError> {
+        let cache = match self.state_cache.clone() {
+            Some(cache) => cache,
+            None => {
+                let mut state = net.get_forwarding_state();
+                return self.step(net, &mut state);
+            }
+        };
+
+        // the lock is not held while checking the conditions, such that clones of the hard policy
+        // on other threads can use the cache in the meantime.
+        let cached =
+            cache.lock().expect("State cache is poisoned").checks(net, self.prop_vars.len());
+        let (new_state, new_error) = match cached {
+            Some(checks) => checks,
+            None => {
+                let mut state =
+                    cache.lock().expect("State cache is poisoned").forwarding_state(net);
+                let checks = self.check_all(net, &mut state)?;
+                cache.lock().expect("State cache is poisoned").set_checks(net, checks.clone());
+                checks
+            }
+        };
+
+        self.finish_step(net, new_state, new_error);
+        Ok(())
+    }
+
+    /// Use a [`StateCache`] storing at most `capacity` states in [`HardPolicy::step_cached`]. The
+    /// cache is shared with all clones of the hard policy created afterwards. It is removed when
+    /// the hard policy is extended (see [`HardPolicy::extend_globally`]). The cache must only be
+    /// used while solving a single problem, since it only distinguishes the states by their
+    /// configuration.
+    pub fn enable_state_cache(&mut self, capacity: usize) {
+        self.state_cache = Some(Arc::new(Mutex::new(StateCache::new(capacity))));
+    }
+
+    /// Stop using the state cache in [`HardPolicy::step_cached`]. Clones of the hard policy keep
+    /// using the cache.
+    pub fn disable_state_cache(&mut self) {
+        self.state_cache = None;
+    }
+
+    /// Returns the state cache used by this hard policy, if any.
+    pub fn state_cache(&self) -> Option<Arc<Mutex<StateCache>>> {
+        self.state_cache.clone()
+    }
+
+    /// Applies a next step to the LTL model, without modifying the network. Only the reliability
+    /// conditions need to modify the network (by failing links). They are checked in `num_threads`
+    /// worker threads, each working on its own clone of the network and failing a share of all
+    /// links. In the meantime, all other conditions are checked on the calling thread, using the
+    /// shared reference to the network. If the policy has no reliability conditions, the network is
+    /// never cloned.
+    pub fn step_shared(
+        &mut self,
+        net: &Network,
+        state: &mut ForwardingState,
+        num_threads: usize,
+    ) -> Result<(), NetworkError> {
+        // start the workers for the reliability conditions
+        let workers = if self.reliability.is_empty() {
+            Vec::new()
+        } else {
+            let links = net.links_symmetric().cloned().collect::<Vec<_>>();
+            let chunk_size = (links.len() + num_threads.max(1) - 1) / num_threads.max(1);
+            let prop_vars = Arc::new(self.prop_vars.clone());
+            let reliability = Arc::new(self.reliability.clone());
+            links
+                .chunks(chunk_size.max(1))
+                .map(|chunk| {
+                    let chunk = chunk.to_vec();
+                    let mut net = net.clone();
+                    let prop_vars = prop_vars.clone();
+                    let reliability = reliability.clone();
+                    thread::spawn(move || {
+                        reliability_errors(&prop_vars, &reliability, &mut net, &chunk)
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let (mut new_state, mut new_error) = self.check_state(net, state);
+
+        // collect the results of the workers in the order of the links, such that the result is
+        // identical to the one of `step`.
+        let mut results = Vec::with_capacity(workers.len());
+        for worker in workers {
+            results.push(worker.join().expect("Reliability worker panicked")?);
+        }
+        for (c_id, e) in results.into_iter().flatten() {
+            new_state[c_id] = false;
+            new_error[c_id] = Some(e);
+        }
+
+        self.finish_step(net, new_state, new_error);
+        Ok(())
+    }
+
+    /// Checks all conditions except the transient ones, including the reliability conditions.
+    fn check_all(
+        &self,
+        net: &mut Network,
+        state: &mut ForwardingState,
+    ) -> Result<CheckResults, NetworkError> {
+        let (mut new_state, mut new_error) = self.check_state(net, state);
+
+        // Next, we need to check the reliability, which fails every link of the network in place
+        if !self.reliability.is_empty() {
+            let links = net.links_symmetric().cloned().collect::<Vec<_>>();
+            for (c_id, e) in reliability_errors(&self.prop_vars, &self.reliability, net, &links)? {
+                new_state[c_id] = false;
+                new_error[c_id] = Some(e);
+            }
+        }
+
+        Ok((new_state, new_error))
+    }
+
+    /// Checks all conditions which do only read the network and the forwarding state, i.e., all
+    /// conditions except the reliability and the transient conditions.
+    fn check_state(
+        &self,
+        net: &Network,
+        state: &mut ForwardingState,
+    ) -> (Vec<bool>, Vec<Option<PolicyError>>) {
         // prepare new state
         let mut new_state = Vec::with_capacity(self.prop_vars.len());
         let mut new_error: Vec<Option<PolicyError>> = Vec::with_capacity(self.prop_vars.len());
@@ -150,94 +324,39 @@ impl HardPolicy {
             }
         }
 
-        // Next, we need to check the reliability
-        if !self.reliability.is_empty() {
-            // iterate over all links in the network, deactivating them ony by one
-            for (a, b) in net.links_symmetric().cloned().collect::<Vec<_>>() {
-                // let link a -- b fail
-                let mut num_undo = 0;
-                match net.apply_modifier(&ConfigModifier::Remove(ConfigExpr::IgpLinkWeight {
-                    source: a,
-                    target: b,
-                    weight: 1.0,
-                })) {
-                    Ok(_) => num_undo += 1,
-                    Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
-                        num_undo += 1
-                    }
-                    Err(NetworkError::ConfigError(_)) => {}
-                    Err(e) => return Err(e),
-                }
-                match net.apply_modifier(&ConfigModifier::Remove(ConfigExpr::IgpLinkWeight {
-                    source: b,
-                    target: a,
-                    weight: 1.0,
-                })) {
-                    Ok(_) => num_undo += 1,
-                    Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
-                        num_undo += 1
-                    }
-                    Err(NetworkError::ConfigError(_)) => {}
-                    Err(e) => return Err(e),
-                }
-
-                // perform the check
-                let mut fw_state = net.get_forwarding_state();
-                for c_id in self.reliability.iter() {
-                    let check_result = if let Some(Condition::Reliable(r, p, c)) =
-                        self.prop_vars.get(*c_id)
-                    {
-                        match fw_state.get_route(*r, *p) {
-                            Ok(path) => match c {
-                                None => Ok(()),
-                                Some(c) => match c.check(&path, *p) {
-                                    Ok(()) => Ok(()),
-                                    Err(PolicyError::PathCondition { path, condition, prefix }) => {
-                                        Err(PolicyError::ReliabilityCondition {
-                                            path,
-                                            condition,
-                                            prefix,
-                                            link_a: a,
-                                            link_b: b,
-                                        })
-                                    }
-                                    // Condition::check can only return either Ok or Err(PolicyError::PathCondition)
-                                    Err(_) => unreachable!(),
-                                },
-                            },
-                            Err(NetworkError::ForwardingLoop(_))
-                            | Err(NetworkError::ForwardingBlackHole(_)) => {
-                                Err(PolicyError::NotReliable {
-                                    router: *r,
-                                    prefix: *p,
-                                    link_a: a,
-                                    link_b: b,
-                                })
-                            }
-                            Err(e) => panic!("Unrecoverable error detected: {}", e),
-                        }
-                    } else {
-                        // this is the else statements from getting the prop_var. This obviously is
-                        // not reachable, becaues we prepare the reliability array internally, and
-                        // don't expose it to the outside.
-                        unreachable!();
-                    };
-                    match check_result {
-                        Ok(()) => {}
-                        Err(e) => {
-                            new_state[*c_id] = false;
-                            new_error[*c_id] = Some(e);
-                        }
-                    }
+        // Next, check the valley-free conditions, which require the BGP tables of the routers
+        for (c_id, c) in self.prop_vars.iter().enumerate().filter(|(_, c)| c.is_valley_free()) {
+            if let NetworkDevice::InternalRouter(router) = net.get_device(c.router_id()) {
+                if let Some(neighbor) = router.get_valley_exports(c.prefix()).first() {
+                    new_state[c_id] = false;
+                    new_error[c_id] = Some(PolicyError::ValleyViolation {
+                        router: c.router_id(),
+                        neighbor: *neighbor,
+                        prefix: c.prefix(),
+                    });
                 }
+            }
+        }
 
-                // undo the action
-                for _ in 0..num_undo {
-                    net.undo_action()?;
-                }
+        // Next, check the latency conditions, which require the link weights of the network
+        for (c_id, c) in self.prop_vars.iter().enumerate().filter(|(_, c)| c.is_latency()) {
+            if let Err(e) = c.check_latency(net, state) {
+                new_state[c_id] = false;
+                new_error[c_id] = Some(e);
             }
         }
 
+        (new_state, new_error)
+    }
+
+    /// Performs the step on the transient state analyzer, evaluates the anchors and pushes the new
+    /// state onto the history.
+    fn finish_step(
+        &mut self,
+        net: &Network,
+        mut new_state: Vec<bool>,
+        mut new_error: Vec<Option<PolicyError>>,
+    ) {
         // then, perform the step on the transient state analyzer, and do the check
         if self.tsa.is_some() {
             let tsa = self.tsa.as_mut().unwrap();
@@ -261,11 +380,27 @@ impl HardPolicy {
             }
         }
 
+        // the anchors are placed after all conditions
+        for (_, modifier) in self.anchors.iter() {
+            new_state.push(is_applied(net, modifier));
+            new_error.push(None);
+        }
+
         // finally, push the changes to the stack
         self.history.push(new_state);
         self.error_history.push(new_error);
+    }
 
-        Ok(())
+    /// Returns all named anchors of the hard policy (see
+    /// [`WindowedPolicyBuilder`](super::WindowedPolicyBuilder)). The propositional variable of the
+    /// anchor at position `i` has the index `prop_vars.len() + i`.
+    pub fn anchors(&self) -> &[(String, ConfigModifier)] {
+        &self.anchors
+    }
+
+    /// Returns the index of the propositional variable of the anchor with the given name.
+    pub fn anchor_var(&self, name: &str) -> Option<usize> {
+        self.anchors.iter().position(|(n, _)| n == name).map(|i| self.prop_vars.len() + i)
     }
 
     /// Undoes the last call to step
@@ -384,9 +519,14 @@ impl HardPolicy {
                 .iter()
                 .enumerate()
                 .map(|(i, c)| format!("{}: {}", i.repr(), c.repr_with_name(net)))
+                .chain(self.anchors.iter().enumerate().map(|(i, (name, _))| format!(
+                    "{}: Anchor({})",
+                    (self.prop_vars.len() + i).repr(),
+                    name
+                )))
                 .collect::<Vec<_>>()
                 .join("\n    "),
-            (0..self.prop_vars.len())
+            (0..self.prop_vars.len() + self.anchors.len())
                 .map(|i| format!(
                     "{}: {}",
                     i.repr(),
@@ -406,6 +546,103 @@ impl HardPolicy {
     }
 }
 
+/// Checks the reliability conditions (at the positions `reliability` of `prop_vars`) by failing
+/// each of the `links` one after the other. The network is restored to its original state
+/// afterwards. The function returns the violated conditions, together with the error. If a
+/// condition is violated for multiple links, then the error of each link is returned, in the order
+/// of the links.
+fn reliability_errors(
+    prop_vars: &[Condition],
+    reliability: &[usize],
+    net: &mut Network,
+    links: &[(RouterId, RouterId)],
+) -> Result<Vec<(usize, PolicyError)>, NetworkError> {
+    let mut errors = Vec::new();
+    // iterate over all links in the network, deactivating them ony by one
+    for (a, b) in links.iter().cloned() {
+        // let link a -- b fail
+        let mut num_undo = 0;
+        match net.apply_modifier(&ConfigModifier::Remove(ConfigExpr::IgpLinkWeight {
+            source: a,
+            target: b,
+            weight: 1.0,
+        })) {
+            Ok(_) => num_undo += 1,
+            Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                num_undo += 1
+            }
+            Err(NetworkError::ConfigError(_)) => {}
+            Err(e) => return Err(e),
+        }
+        match net.apply_modifier(&ConfigModifier::Remove(ConfigExpr::IgpLinkWeight {
+            source: b,
+            target: a,
+            weight: 1.0,
+        })) {
+            Ok(_) => num_undo += 1,
+            Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                num_undo += 1
+            }
+            Err(NetworkError::ConfigError(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        // perform the check
+        let mut fw_state = net.get_forwarding_state();
+        for c_id in reliability.iter() {
+            let check_result = if let Some(Condition::Reliable(r, p, c)) = prop_vars.get(*c_id) {
+                fw_state
+                    .get_all_routes(*r, *p)
+                    .into_iter()
+                    .map(|route| match route {
+                        Ok(path) => match c {
+                            None => Ok(()),
+                            Some(c) => match c.check(&path, *p) {
+                                Ok(()) => Ok(()),
+                                Err(PolicyError::PathCondition { path, condition, prefix }) => {
+                                    Err(PolicyError::ReliabilityCondition {
+                                        path,
+                                        condition,
+                                        prefix,
+                                        link_a: a,
+                                        link_b: b,
+                                    })
+                                }
+                                // Condition::check can only return either Ok or Err(PolicyError::PathCondition)
+                                Err(_) => unreachable!(),
+                            },
+                        },
+                        Err(NetworkError::ForwardingLoop(_))
+                        | Err(NetworkError::ForwardingBlackHole(_)) => {
+                            Err(PolicyError::NotReliable {
+                                router: *r,
+                                prefix: *p,
+                                link_a: a,
+                                link_b: b,
+                            })
+                        }
+                        Err(e) => panic!("Unrecoverable error detected: {}", e),
+                    })
+                    .collect::<Result<(), PolicyError>>()
+            } else {
+                // this is the else statements from getting the prop_var. This obviously is
+                // not reachable, becaues we prepare the reliability array internally, and
+                // don't expose it to the outside.
+                unreachable!();
+            };
+            if let Err(e) = check_result {
+                errors.push((*c_id, e));
+            }
+        }
+
+        // undo the action
+        for _ in 0..num_undo {
+            net.undo_action()?;
+        }
+    }
+    Ok(errors)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LTLResult {
     T,
@@ -576,6 +813,9 @@ pub trait LTLOperator:
 
     /// represent the operator as a string
     fn repr(&self) -> String;
+
+    /// Convert the operator into a plain, serializable [`LtlAst`].
+    fn ast(&self) -> LtlAst;
 }
 
 impl LTLOperator for bool {
@@ -602,6 +842,10 @@ impl LTLOperator for bool {
             String::from("false")
         }
     }
+
+    fn ast(&self) -> LtlAst {
+        LtlAst::Bool(*self)
+    }
 }
 
 impl LTLOperator for usize {
@@ -624,6 +868,10 @@ impl LTLOperator for usize {
     fn repr(&self) -> String {
         format!("x{:02}", self)
     }
+
+    fn ast(&self) -> LtlAst {
+        LtlAst::Var(*self)
+    }
 }
 
 fn partial_any<I, F>(iter: I, mut f: F) -> LTLResult
@@ -876,6 +1124,17 @@ impl LTLOperator for LTLBoolean {
             Self::Iff(a, b) => format!("({} <=> {})", a.repr(), b.repr()),
         }
     }
+
+    fn ast(&self) -> LtlAst {
+        match self {
+            Self::Not(a) => LtlAst::Not(Box::new(a.ast())),
+            Self::Or(v) => LtlAst::Or(v.iter().map(|x| x.ast()).collect()),
+            Self::And(v) => LtlAst::And(v.iter().map(|x| x.ast()).collect()),
+            Self::Xor(a, b) => LtlAst::Xor(Box::new(a.ast()), Box::new(b.ast())),
+            Self::Implies(a, b) => LtlAst::Implies(Box::new(a.ast()), Box::new(b.ast())),
+            Self::Iff(a, b) => LtlAst::Iff(Box::new(a.ast()), Box::new(b.ast())),
+        }
+    }
 }
 
 /// Temporal modal operators of LTL. For reconfiguration purpose, in the last state, we assume that
@@ -1338,6 +1597,21 @@ impl LTLOperator for LTLModal {
             LTLModal::StrongRelease(a, b) => format!("({} M {})", a.repr(), b.repr()),
         }
     }
+
+    fn ast(&self) -> LtlAst {
+        match self {
+            LTLModal::Now(a) => LtlAst::Now(Box::new(a.ast())),
+            LTLModal::Next(a) => LtlAst::Next(Box::new(a.ast())),
+            LTLModal::Finally(a) => LtlAst::Finally(Box::new(a.ast())),
+            LTLModal::Globally(a) => LtlAst::Globally(Box::new(a.ast())),
+            LTLModal::Until(a, b) => LtlAst::Until(Box::new(a.ast()), Box::new(b.ast())),
+            LTLModal::Release(a, b) => LtlAst::Release(Box::new(a.ast()), Box::new(b.ast())),
+            LTLModal::WeakUntil(a, b) => LtlAst::WeakUntil(Box::new(a.ast()), Box::new(b.ast())),
+            LTLModal::StrongRelease(a, b) => {
+                LtlAst::StrongRelease(Box::new(a.ast()), Box::new(b.ast()))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
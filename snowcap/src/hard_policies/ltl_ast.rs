@@ -0,0 +1,168 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Serializable LTL Abstract Syntax Tree
+//!
+//! The LTL formulas used by [`HardPolicy`](super::HardPolicy) are built from trait objects
+//! ([`LTLOperator`]), which cannot be serialized directly. [`LtlAst`] is a plain enum mirroring
+//! [`LTLModal`] and [`LTLBoolean`], which can be stored in problem files or sent over the wire,
+//! and converted back into the operator tree used for checking.
+
+use super::ltl::{LTLBoolean, LTLModal, LTLOperator};
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// # LTL Abstract Syntax Tree
+///
+/// Plain representation of an LTL formula without any trait objects. Use
+/// [`LTLOperator::ast`] to obtain the AST of an existing formula, and [`LtlAst::to_operator`] or
+/// [`LtlAst::to_modal`] to convert it back.
+///
+/// ```
+/// use snowcap::hard_policies::*;
+/// use snowcap_ltl_parser::ltl;
+///
+/// let expr = ltl!(U(0, G(And(1, 2))));
+/// let ast = expr.ast();
+/// assert_eq!(ast.to_string(), expr.repr());
+/// assert_eq!(ast.to_modal().ast(), ast);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LtlAst {
+    /// Constant boolean value
+    Bool(bool),
+    /// Propositional variable, referencing the condition at the given index
+    Var(usize),
+    /// Logical not
+    Not(Box<LtlAst>),
+    /// Logical or
+    Or(Vec<LtlAst>),
+    /// Logical and
+    And(Vec<LtlAst>),
+    /// Logical exclusive or
+    Xor(Box<LtlAst>, Box<LtlAst>),
+    /// Logical implication
+    Implies(Box<LtlAst>, Box<LtlAst>),
+    /// Logical if and only if
+    Iff(Box<LtlAst>, Box<LtlAst>),
+    /// Modal operator *now*
+    Now(Box<LtlAst>),
+    /// Modal operator *next*
+    Next(Box<LtlAst>),
+    /// Modal operator *finally*
+    Finally(Box<LtlAst>),
+    /// Modal operator *globally*
+    Globally(Box<LtlAst>),
+    /// Modal operator *until*
+    Until(Box<LtlAst>, Box<LtlAst>),
+    /// Modal operator *release*
+    Release(Box<LtlAst>, Box<LtlAst>),
+    /// Modal operator *weak until*
+    WeakUntil(Box<LtlAst>, Box<LtlAst>),
+    /// Modal operator *strong release*
+    StrongRelease(Box<LtlAst>, Box<LtlAst>),
+}
+
+impl LtlAst {
+    /// Returns `true` if the top-level node is a temporal modal operator.
+    pub fn is_modal(&self) -> bool {
+        matches!(
+            self,
+            Self::Now(_)
+                | Self::Next(_)
+                | Self::Finally(_)
+                | Self::Globally(_)
+                | Self::Until(_, _)
+                | Self::Release(_, _)
+                | Self::WeakUntil(_, _)
+                | Self::StrongRelease(_, _)
+        )
+    }
+
+    /// Convert the AST into an operator tree that can be checked.
+    pub fn to_operator(&self) -> Box<dyn LTLOperator> {
+        match self {
+            Self::Bool(b) => Box::new(*b),
+            Self::Var(x) => Box::new(*x),
+            Self::Not(_)
+            | Self::Or(_)
+            | Self::And(_)
+            | Self::Xor(_, _)
+            | Self::Implies(_, _)
+            | Self::Iff(_, _) => Box::new(self.to_boolean().unwrap()),
+            _ => Box::new(self.to_modal()),
+        }
+    }
+
+    /// Convert the AST into a [`LTLModal`] operator. If the top-level node is not a modal
+    /// operator, the expression is wrapped in [`LTLModal::Now`].
+    pub fn to_modal(&self) -> LTLModal {
+        let op = |a: &LtlAst| a.to_operator();
+        match self {
+            Self::Now(a) => LTLModal::Now(op(a)),
+            Self::Next(a) => LTLModal::Next(op(a)),
+            Self::Finally(a) => LTLModal::Finally(op(a)),
+            Self::Globally(a) => LTLModal::Globally(op(a)),
+            Self::Until(a, b) => LTLModal::Until(op(a), op(b)),
+            Self::Release(a, b) => LTLModal::Release(op(a), op(b)),
+            Self::WeakUntil(a, b) => LTLModal::WeakUntil(op(a), op(b)),
+            Self::StrongRelease(a, b) => LTLModal::StrongRelease(op(a), op(b)),
+            _ => LTLModal::Now(self.to_operator()),
+        }
+    }
+
+    /// Convert the AST into a [`LTLBoolean`] operator. Returns `None` if the top-level node is
+    /// not a boolean operator.
+    pub fn to_boolean(&self) -> Option<LTLBoolean> {
+        let op = |a: &LtlAst| a.to_operator();
+        Some(match self {
+            Self::Not(a) => LTLBoolean::Not(op(a)),
+            Self::Or(v) => LTLBoolean::Or(v.iter().map(op).collect()),
+            Self::And(v) => LTLBoolean::And(v.iter().map(op).collect()),
+            Self::Xor(a, b) => LTLBoolean::Xor(op(a), op(b)),
+            Self::Implies(a, b) => LTLBoolean::Implies(op(a), op(b)),
+            Self::Iff(a, b) => LTLBoolean::Iff(op(a), op(b)),
+            _ => return None,
+        })
+    }
+}
+
+impl From<&LTLModal> for LtlAst {
+    fn from(expr: &LTLModal) -> Self {
+        expr.ast()
+    }
+}
+
+impl From<&LTLBoolean> for LtlAst {
+    fn from(expr: &LTLBoolean) -> Self {
+        expr.ast()
+    }
+}
+
+impl From<&LtlAst> for LTLModal {
+    fn from(ast: &LtlAst) -> Self {
+        ast.to_modal()
+    }
+}
+
+impl fmt::Display for LtlAst {
+    /// Uses the same notation as [`LTLOperator::repr`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_operator().repr())
+    }
+}
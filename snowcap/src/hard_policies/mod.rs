@@ -79,6 +79,9 @@
 //!   every possible path, that router $r$ might choose to reach $p$ does satisfy the path condition
 //!   $c$. Note, that this condition cannot check, that during convergence, no forwarding loop or
 //!   black hole may appear. Only the path can be checked.
+//! - $\mathbf{VF}_{(r, p)}$ (Valley-free): Router $r$ does not export a route for prefix $p$, that
+//!   was learned from a peer or a provider, to another peer or provider. This condition is checked
+//!   on the BGP tables of the router, based on the relationships of the external neighbors.
 //!
 //! ## Path Condition
 //!
@@ -93,6 +96,30 @@
 //! other node, then $b$ must be traversed, immediately followed by $c$. This always matches on the
 //! entire path, and not just on a small part of it.
 //!
+//! # Parsing Policies at Runtime
+//!
+//! Besides the `ltl!` macro, policies can be parsed at runtime from text, using [`parse_ltl`] or
+//! [`HardPolicy::parse`]. See the module [`parser`] for the syntax.
+//!
+//! # Selecting Routers and Prefixes
+//!
+//! For large networks, routers can be selected by a regular expression on their name (see
+//! [`Network::routers_matching`]), and prefixes using a [`PrefixSet`]. The function [`for_all`]
+//! creates a condition for every selected router and prefix. See the module [`selectors`].
+//!
+//! # Policy Templates
+//!
+//! Commonly used policies (e.g., no transit through customers, or firewall traversal for a set of
+//! prefixes) are available as [`PolicyTemplate`], which expand to the set of conditions based on
+//! the roles of the routers, given as [`TemplateRoles`].
+//!
+//! # Activation Windows
+//!
+//! Conditions which only need to hold during a part of the reconfiguration can be built with the
+//! [`WindowedPolicyBuilder`]. Every condition is given an [`ActivationWindow`], either as a range
+//! of steps, or between two named anchor modifiers. The windows are compiled into the equivalent
+//! LTL expression.
+//!
 //! # Transient Behavior
 //!
 //! For transient behavior, we cannot guarantee the absence of black holes or forwarding loops. In
@@ -101,6 +128,10 @@
 //! This obviously makes no sence. Nevertheless, we are able to guarantee that if there exists a
 //! path, then this path will satisfy the specified conditions.
 //!
+//! The analysis is implemented in the module [`transient_behavior`]. Its [`TransientStateAnalyzer`]
+//! is used by the [`HardPolicy`] for every [`Condition::TransientPath`], but it can also be used on
+//! its own.
+//!
 //! ## Computation Complexity
 //!
 //! In the following, we use the notation $n = |\mathcal{V}|$ to be the number of routers in the
@@ -233,7 +264,8 @@
 //! loops in any transient state.
 //!
 //! So, our algorithm generates many different reconfiguraiton expressions to incrementally change
-//! the link weight until we reach the desired value, treating all of these as individual changes.
+//! the link weight until we reach the desired value, treating all of these as individual changes
+//! (see [`decompose_igp_weight_change`](crate::strategies::decompose_igp_weight_change)).
 //! While preparing the forwarding supergraph, we consider the next hop based on the IGP metric
 //! before and after the reconfiguration.
 //!
@@ -292,14 +324,23 @@
 
 mod condition;
 mod ltl;
-mod transient_behavior;
-
-pub use condition::{Condition, PathCondition, Waypoint};
+mod ltl_ast;
+pub mod parser;
+pub mod selectors;
+mod templates;
+pub mod transient_behavior;
+mod window;
+
+pub use condition::{Condition, Latency, PathCondition, Waypoint};
 pub use ltl::{HardPolicy, LTLBoolean, LTLModal, LTLOperator, WatchErrors};
-use transient_behavior::TransientStateAnalyzer;
+pub use ltl_ast::LtlAst;
+pub use parser::{parse_ltl, ParseError};
+pub use selectors::{for_all, PrefixSet};
+pub use templates::{PolicyTemplate, TemplateRoles};
+pub use transient_behavior::TransientStateAnalyzer;
+pub use window::{ActivationWindow, WindowedPolicyBuilder};
 
 use crate::netsim::{Network, Prefix, RouterId};
-//use crate::transient_behavior::TransientError;
 
 use std::collections::VecDeque;
 use thiserror::Error;
@@ -390,6 +431,60 @@ pub enum PolicyError {
         /// Path condition which may be violated in transient behavior
         condition: PathCondition,
     },
+
+    /// Valley-Free Violation
+    #[error("Router {router:?} exports a route for {prefix:?} to {neighbor:?}, violating the valley-free property")]
+    ValleyViolation {
+        /// Router which exports the route
+        router: RouterId,
+        /// External neighbor to which the route is exported
+        neighbor: RouterId,
+        /// Prefix of the route
+        prefix: Prefix,
+    },
+
+    /// Path Length Violation
+    #[error(
+        "Router {router:?} uses path {path:?} for {prefix:?}, which is longer than {max_len} hops"
+    )]
+    PathTooLong {
+        /// Router for which the path is too long
+        router: RouterId,
+        /// Prefix of the path
+        prefix: Prefix,
+        /// Path which is too long
+        path: Vec<RouterId>,
+        /// Maximum allowed number of hops
+        max_len: usize,
+    },
+
+    /// Latency Violation
+    #[error("Router {router:?} uses path {path:?} for {prefix:?} with latency {latency}, exceeding {bound}")]
+    LatencyExceeded {
+        /// Router for which the latency is exceeded
+        router: RouterId,
+        /// Prefix of the path
+        prefix: Prefix,
+        /// Path whose latency exceeds the bound
+        path: Vec<RouterId>,
+        /// Latency of the path
+        latency: Latency,
+        /// Maximum allowed latency
+        bound: Latency,
+    },
+
+    /// Egress Violation
+    #[error("Router {router:?} sends traffic for {prefix:?} to egress {egress:?}, which is not one of {allowed:?}")]
+    WrongEgress {
+        /// Router from which the traffic originates
+        router: RouterId,
+        /// Prefix of the traffic
+        prefix: Prefix,
+        /// Border router at which the traffic leaves the network
+        egress: RouterId,
+        /// Allowed border routers
+        allowed: Vec<RouterId>,
+    },
 }
 
 impl PolicyError {
@@ -452,7 +547,45 @@ impl PolicyError {
                 net.get_router_name(*router).unwrap(),
                 prefix.0,
                 condition.repr_with_name(net),
-            )
+            ),
+            PolicyError::ValleyViolation { router, neighbor, prefix } => format!(
+                "Router {} exports a route for prefix {} to {}, violating the valley-free property",
+                net.get_router_name(*router).unwrap(),
+                prefix.0,
+                net.get_router_name(*neighbor).unwrap(),
+            ),
+            PolicyError::PathTooLong { router, prefix, path, max_len } => format!(
+                "Router {} uses path {} for prefix {}, which is longer than {} hops",
+                net.get_router_name(*router).unwrap(),
+                path.iter()
+                    .map(|r| net.get_router_name(*r).unwrap())
+                    .collect::<Vec<&str>>()
+                    .join(" -> "),
+                prefix.0,
+                max_len,
+            ),
+            PolicyError::LatencyExceeded { router, prefix, path, latency, bound } => format!(
+                "Router {} uses path {} for prefix {} with latency {}, exceeding {}",
+                net.get_router_name(*router).unwrap(),
+                path.iter()
+                    .map(|r| net.get_router_name(*r).unwrap())
+                    .collect::<Vec<&str>>()
+                    .join(" -> "),
+                prefix.0,
+                latency,
+                bound,
+            ),
+            PolicyError::WrongEgress { router, prefix, egress, allowed } => format!(
+                "Traffic from {} to prefix {} leaves the network at {}, instead of one of {{{}}}",
+                net.get_router_name(*router).unwrap(),
+                prefix.0,
+                net.get_router_name(*egress).unwrap(),
+                allowed
+                    .iter()
+                    .map(|r| net.get_router_name(*r).unwrap())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            ),
         }
     }
 }
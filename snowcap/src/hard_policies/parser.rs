@@ -0,0 +1,633 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Runtime LTL Parser
+//!
+//! The `ltl!` macro of `snowcap_ltl_parser` only works at compile time. This module parses the same
+//! syntax at runtime, such that policies can be loaded from text files or from user input. Use
+//! [`parse_ltl`] to parse a plain LTL formula (where numbers reference propositional variables),
+//! or [`HardPolicy::parse`] to parse an entire hard policy, where the conditions are written
+//! directly into the formula, and router names are resolved using the [`Network`].
+//!
+//! ## Syntax
+//!
+//! The operators (and their precedence) are the same as for the `ltl!` macro:
+//!
+//! - `true`, `false`, and numbers to index propositional variables (only in [`parse_ltl`])
+//! - `!`, `-`, `Not(_)`: logical not
+//! - `+`, `||`, `|`, `Or(_, ..)`: logical or
+//! - `*`, `&&`, `&`, `And(_, ..)`: logical and
+//! - `^`, `Xor(_, _)`: logical exclusive or
+//! - `==`, `Iff(_, _)`: logical if and only if
+//! - `>>`, `>`, `Implies(_, _)`: logical implication, and `<<`, `<`, `<=` in reverse
+//! - `X(_)`, `N(_)`, `Next(_)`, `F(_)`, `Finally(_)`, `G(_)`, `Globally(_)`, `U(_, _)`,
+//!   `Until(_, _)`, `R(_, _)`, `Release(_, _)`, `W(_, _)`, `WeakUntil(_, _)`, `M(_, _)` and
+//!   `StrongRelease(_, _)`: temporal modal operators (also in lower case)
+//!
+//! In addition, [`HardPolicy::parse`] accepts the following conditions as propositional variables,
+//! in the same format as they are printed by [`Condition::repr_with_name`] (the keywords `prefix`
+//! and `condition` are optional):
+//!
+//! - `Reachability(router, prefix 0)` or `Reachability(router, prefix 0, condition path)`
+//! - `Isolation(router, prefix 0)`
+//! - `Reliability(router, prefix 0)` or `Reliability(router, prefix 0, condition path)`
+//! - `Transient(router, prefix 0, condition path)`
+//! - `ValleyFree(router, prefix 0)`
+//! - `PathLength(router, prefix 0, 3)`: at most 3 hops
+//! - `Latency(router, prefix 0, 20)`: latency of at most 20 (only integer bounds are supported)
+//! - `Egress(router, prefix 0, [border_1, border_2])`
+//!
+//! Instead of a single router, the first argument of a condition may select all routers whose name
+//! matches a regular expression, written as `matching("core-.*")` (see
+//! [`Network::routers_matching`]). Similarly, the prefix may be a list of prefixes `prefix [0, 1]`,
+//! or `prefix *` for all known prefixes. Such a condition expands to the conjunction of the
+//! conditions for every selected router and prefix, e.g., `G(Reachability(matching("r.*"), prefix
+//! *))` requires every router whose name starts with `r` to reach every prefix.
+//!
+//! Path conditions consist of router names, edges `[a -> b]`, positional conditions
+//! `[... -> a -> ? -> b -> ...]` (where `?` matches any router and `...` or `*` any sequence), and
+//! the operators `&&`, `||` and `!`. Router names containing special characters must be quoted,
+//! e.g., `"New York"`. Multiple formulas can be separated by `;`, in which case all of them must
+//! hold. Everything after a `#` until the end of the line is a comment.
+//!
+//! ```
+//! use snowcap::hard_policies::*;
+//! use snowcap::example_networks::{ExampleNetwork, SimpleNet};
+//!
+//! let net = SimpleNet::net(0);
+//! let policy = HardPolicy::parse(
+//!     "G(Reachability(r1, prefix 0) && Reachability(r2, prefix 0, condition [* -> r3 -> *]))",
+//!     &net,
+//! ).unwrap();
+//! assert_eq!(policy.prop_vars.len(), 2);
+//! ```
+
+use super::{
+    for_all, Condition, HardPolicy, LTLModal, Latency, LtlAst, PathCondition, PrefixSet, Waypoint,
+};
+use crate::netsim::{LinkWeight, Network, Prefix, RouterId};
+
+use thiserror::Error;
+
+/// # Parse Error
+/// Error while parsing an LTL formula or a hard policy at runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Cannot parse the policy at position {position}: {message}")]
+pub struct ParseError {
+    /// Position (in bytes) in the input, where the error occurred
+    pub position: usize,
+    /// Description of the error
+    pub message: String,
+}
+
+/// Parse an LTL formula at runtime, using the same syntax as the `ltl!` macro. Like the macro,
+/// the result is wrapped into `LTLModal::Now`.
+///
+/// ```
+/// use snowcap::hard_policies::*;
+/// use snowcap_ltl_parser::ltl;
+///
+/// let expr = parse_ltl("U(0, G(1 & !2))").unwrap();
+/// assert_eq!(expr.ast(), ltl!(U(0, G(1 & !2))).ast());
+/// ```
+pub fn parse_ltl(input: &str) -> Result<LTLModal, ParseError> {
+    let mut parser = Parser::new(input, None)?;
+    let ast = parser.parse_statements()?;
+    Ok(LTLModal::Now(ast.to_operator()))
+}
+
+impl HardPolicy {
+    /// Parse a hard policy at runtime. The conditions are written directly into the LTL formula,
+    /// and the router names are resolved using `net`. See the module
+    /// [`parser`](crate::hard_policies::parser) for the syntax. The propositional variables are
+    /// ordered by their first occurrence in the formula.
+    pub fn parse(input: &str, net: &Network) -> Result<HardPolicy, ParseError> {
+        let mut parser = Parser::new(input, Some(net))?;
+        let ast = parser.parse_statements()?;
+        Ok(HardPolicy::new(parser.prop_vars, LTLModal::Now(ast.to_operator())))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Semicolon,
+    Op(&'static str),
+    Ident(String),
+    Str(String),
+    Int(usize),
+}
+
+/// Operators, ordered such that longer operators are matched first.
+const OPERATORS: [&str; 17] =
+    ["...", "||", "&&", "==", ">>", "<<", "<=", "->", "!", "-", "+", "|", "*", "&", "^", ">", "<"];
+
+/// Binary operators with their precedence (higher binds stronger), following the rust precedence
+/// used by the `ltl!` macro.
+fn binary_precedence(op: &str) -> Option<usize> {
+    match op {
+        "||" => Some(1),
+        "&&" => Some(2),
+        "==" | ">" | "<" | "<=" => Some(3),
+        "|" => Some(4),
+        "^" => Some(5),
+        "&" => Some(6),
+        ">>" | "<<" => Some(7),
+        "+" => Some(8),
+        "*" => Some(9),
+        _ => None,
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(pos, c)) = chars.peek() {
+        let rest = &input[pos..];
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '#' {
+            while chars.peek().map(|(_, c)| *c != '\n').unwrap_or(false) {
+                chars.next();
+            }
+        } else if c.is_ascii_digit() {
+            let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let value = rest[..len].parse().map_err(|_| ParseError {
+                position: pos,
+                message: format!("Invalid number: {}", &rest[..len]),
+            })?;
+            tokens.push((Token::Int(value), pos));
+            (0..len).for_each(|_| {
+                chars.next();
+            });
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            tokens.push((Token::Ident(rest[..len].to_string()), pos));
+            rest[..len].chars().for_each(|_| {
+                chars.next();
+            });
+        } else if c == '"' {
+            let len = rest[1..].find('"').ok_or(ParseError {
+                position: pos,
+                message: String::from("Unterminated string"),
+            })?;
+            tokens.push((Token::Str(rest[1..len + 1].to_string()), pos));
+            rest[..len + 2].chars().for_each(|_| {
+                chars.next();
+            });
+        } else {
+            let (token, len) = match c {
+                '(' => (Token::LParen, 1),
+                ')' => (Token::RParen, 1),
+                '[' => (Token::LBracket, 1),
+                ']' => (Token::RBracket, 1),
+                ',' => (Token::Comma, 1),
+                ';' => (Token::Semicolon, 1),
+                '?' => (Token::Op("?"), 1),
+                _ => match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                    Some(op) => (Token::Op(op), op.len()),
+                    None => {
+                        return Err(ParseError {
+                            position: pos,
+                            message: format!("Unexpected character: {}", c),
+                        })
+                    }
+                },
+            };
+            tokens.push((token, pos));
+            (0..len).for_each(|_| {
+                chars.next();
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+    net: Option<&'a Network>,
+    prop_vars: Vec<Condition>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &str, net: Option<&'a Network>) -> Result<Self, ParseError> {
+        Ok(Self { tokens: tokenize(input)?, pos: 0, end: input.len(), net, prop_vars: Vec::new() })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        let position = self.tokens.get(self.pos).map(|(_, p)| *p).unwrap_or(self.end);
+        Err(ParseError { position, message: message.into() })
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), ParseError> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(format!("Expected {:?}, found {:?}", token, self.peek()))
+        }
+    }
+
+    /// Parse all formulas, separated by `;`, and combine them with a logical and.
+    fn parse_statements(&mut self) -> Result<LtlAst, ParseError> {
+        let mut statements = Vec::new();
+        loop {
+            while self.peek() == Some(&Token::Semicolon) {
+                self.pos += 1;
+            }
+            if self.peek().is_none() {
+                break;
+            }
+            statements.push(self.parse_expr(0)?);
+            if self.peek().is_some() && self.peek() != Some(&Token::Semicolon) {
+                return self.error(format!("Unexpected token: {:?}", self.peek().unwrap()));
+            }
+        }
+        match statements.len() {
+            0 => self.error("Empty policy"),
+            1 => Ok(statements.pop().unwrap()),
+            _ => Ok(LtlAst::And(statements)),
+        }
+    }
+
+    /// Parse an expression using precedence climbing.
+    fn parse_expr(&mut self, min_precedence: usize) -> Result<LtlAst, ParseError> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            let precedence = match binary_precedence(op) {
+                Some(p) if p >= min_precedence => p,
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.parse_expr(precedence + 1)?;
+            let (l, r) = (Box::new(lhs), Box::new(rhs));
+            lhs = match op {
+                "||" | "|" | "+" => LtlAst::Or(vec![*l, *r]),
+                "&&" | "&" | "*" => LtlAst::And(vec![*l, *r]),
+                "^" => LtlAst::Xor(l, r),
+                "==" => LtlAst::Iff(l, r),
+                ">>" | ">" => LtlAst::Implies(l, r),
+                _ => LtlAst::Implies(r, l),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<LtlAst, ParseError> {
+        match self.peek() {
+            Some(Token::Op("!")) | Some(Token::Op("-")) => {
+                self.pos += 1;
+                Ok(LtlAst::Not(Box::new(self.parse_unary()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<LtlAst, ParseError> {
+        match self.peek().cloned() {
+            Some(Token::Int(x)) => {
+                if self.net.is_some() {
+                    return self.error("Propositional variables must be given as conditions");
+                }
+                self.pos += 1;
+                Ok(LtlAst::Var(x))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let e = self.parse_expr(0)?;
+                self.expect(Token::RParen)?;
+                Ok(e)
+            }
+            Some(Token::Ident(name)) if name == "true" || name == "false" => {
+                self.pos += 1;
+                Ok(LtlAst::Bool(name == "true"))
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                self.expect(Token::LParen)?;
+                self.parse_call(&name)
+            }
+            Some(t) => self.error(format!("Unexpected token: {:?}", t)),
+            None => self.error("Unexpected end of input"),
+        }
+    }
+
+    /// Parse the arguments of a function (either an operator or a condition), after the opening
+    /// parenthesis.
+    fn parse_call(&mut self, name: &str) -> Result<LtlAst, ParseError> {
+        if let Some(mut conditions) = self.parse_condition(name)? {
+            let mut vars: Vec<LtlAst> = conditions
+                .drain(..)
+                .map(|condition| match self.prop_vars.iter().position(|c| c == &condition) {
+                    Some(idx) => LtlAst::Var(idx),
+                    None => {
+                        self.prop_vars.push(condition);
+                        LtlAst::Var(self.prop_vars.len() - 1)
+                    }
+                })
+                .collect();
+            return Ok(if vars.len() == 1 { vars.pop().unwrap() } else { LtlAst::And(vars) });
+        }
+
+        let mut args = Vec::new();
+        if self.peek() != Some(&Token::RParen) {
+            args.push(self.parse_expr(0)?);
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                args.push(self.parse_expr(0)?);
+            }
+        }
+        self.expect(Token::RParen)?;
+
+        let (op, num_args) = match name {
+            "X" | "x" | "N" | "n" | "Next" | "next" => ("Next", 1),
+            "F" | "f" | "Finally" | "finally" => ("Finally", 1),
+            "G" | "g" | "Globally" | "globally" => ("Globally", 1),
+            "U" | "u" | "Until" | "until" => ("Until", 2),
+            "R" | "r" | "Release" | "release" => ("Release", 2),
+            "W" | "w" | "WeakUntil" => ("WeakUntil", 2),
+            "M" | "m" | "StrongRelease" => ("StrongRelease", 2),
+            "Not" | "not" => ("Not", 1),
+            "And" | "and" => ("And", 0),
+            "Or" | "or" => ("Or", 0),
+            "Xor" | "xor" => ("Xor", 2),
+            "Implies" | "implies" => ("Implies", 2),
+            "Iff" | "iff" => ("Iff", 2),
+            _ => return self.error(format!("Invalid function name: {}", name)),
+        };
+        if args.is_empty() || (num_args > 0 && args.len() != num_args) {
+            return self.error(format!("Invalid number of arguments for \"{}\"", op));
+        }
+        match op {
+            "And" | "Or" if args.len() == 1 => return Ok(args.pop().unwrap()),
+            "And" => return Ok(LtlAst::And(args)),
+            "Or" => return Ok(LtlAst::Or(args)),
+            _ => {}
+        }
+
+        let mut args = args.into_iter().map(Box::new);
+        let mut arg = || args.next().unwrap();
+        Ok(match op {
+            "Next" => LtlAst::Next(arg()),
+            "Finally" => LtlAst::Finally(arg()),
+            "Globally" => LtlAst::Globally(arg()),
+            "Until" => LtlAst::Until(arg(), arg()),
+            "Release" => LtlAst::Release(arg(), arg()),
+            "WeakUntil" => LtlAst::WeakUntil(arg(), arg()),
+            "StrongRelease" => LtlAst::StrongRelease(arg(), arg()),
+            "Not" => LtlAst::Not(arg()),
+            "Xor" => LtlAst::Xor(arg(), arg()),
+            "Implies" => LtlAst::Implies(arg(), arg()),
+            _ => LtlAst::Iff(arg(), arg()),
+        })
+    }
+
+    /// Parse the arguments of a condition, after the opening parenthesis. Returns `None` if `name`
+    /// is not a condition. If routers or prefixes are selected, the condition is returned for
+    /// every combination of them.
+    fn parse_condition(&mut self, name: &str) -> Result<Option<Vec<Condition>>, ParseError> {
+        let (has_path, needs_path, has_bound) = match name {
+            "Reachability" | "Reachable" | "Reliability" | "Reliable" => (true, false, false),
+            "Transient" | "TransientPath" => (true, true, false),
+            "Isolation" | "NotReachable" | "ValleyFree" | "Egress" => (false, false, false),
+            "PathLength" | "Latency" => (false, false, true),
+            _ => return Ok(None),
+        };
+        if self.net.is_none() {
+            return self.error(format!("Condition {} requires a network", name));
+        }
+
+        let routers = self.parse_router_selection()?;
+        self.expect(Token::Comma)?;
+        if self.peek() == Some(&Token::Ident(String::from("prefix"))) {
+            self.pos += 1;
+        }
+        let prefixes = self.parse_prefix_selection()?;
+        let bound = if has_bound {
+            self.expect(Token::Comma)?;
+            match self.next() {
+                Some(Token::Int(x)) => x,
+                _ => {
+                    self.pos -= 1;
+                    return self.error(format!("Condition {} requires an integer bound", name));
+                }
+            }
+        } else {
+            0
+        };
+        let egresses = if name == "Egress" {
+            self.expect(Token::Comma)?;
+            self.expect(Token::LBracket)?;
+            let mut egresses = vec![self.parse_router()?];
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                egresses.push(self.parse_router()?);
+            }
+            self.expect(Token::RBracket)?;
+            egresses
+        } else {
+            Vec::new()
+        };
+        let path = if has_path && self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            if self.peek() == Some(&Token::Ident(String::from("condition"))) {
+                self.pos += 1;
+            }
+            Some(self.parse_path_or()?)
+        } else if needs_path {
+            return self.error(format!("Condition {} requires a path condition", name));
+        } else {
+            None
+        };
+        self.expect(Token::RParen)?;
+
+        Ok(Some(for_all(&routers, &prefixes, |router, prefix| match name {
+            "Reachability" | "Reachable" => Condition::Reachable(router, prefix, path.clone()),
+            "Reliability" | "Reliable" => Condition::Reliable(router, prefix, path.clone()),
+            "Transient" | "TransientPath" => {
+                Condition::TransientPath(router, prefix, path.clone().unwrap())
+            }
+            "Isolation" | "NotReachable" => Condition::NotReachable(router, prefix),
+            "PathLength" => Condition::PathLengthAtMost(router, prefix, bound),
+            "Latency" => Condition::LatencyAtMost(router, prefix, Latency(bound as LinkWeight)),
+            "Egress" => Condition::EgressIs(router, prefix, egresses.clone()),
+            _ => Condition::ValleyFree(router, prefix),
+        })))
+    }
+
+    /// Parse either a single router, or a selection of routers written as `matching("regex")`.
+    fn parse_router_selection(&mut self) -> Result<Vec<RouterId>, ParseError> {
+        let is_selection = self.peek() == Some(&Token::Ident(String::from("matching")))
+            && self.tokens.get(self.pos + 1).map(|(t, _)| t) == Some(&Token::LParen);
+        if !is_selection {
+            return Ok(vec![self.parse_router()?]);
+        }
+        self.pos += 1;
+        self.expect(Token::LParen)?;
+        let pattern = match self.peek() {
+            Some(Token::Str(pattern)) => pattern.clone(),
+            t => return self.error(format!("Expected a quoted pattern, found {:?}", t)),
+        };
+        let routers = match self.net.map(|net| net.routers_matching(&pattern)) {
+            Some(Ok(routers)) => routers,
+            Some(Err(e)) => return self.error(e.to_string()),
+            None => return self.error("Selecting routers requires a network"),
+        };
+        if routers.is_empty() {
+            return self.error(format!("No router matches the pattern: {}", pattern));
+        }
+        self.pos += 1;
+        self.expect(Token::RParen)?;
+        Ok(routers)
+    }
+
+    /// Parse either a single prefix, a list of prefixes `[0, 1]`, or all known prefixes `*`.
+    fn parse_prefix_selection(&mut self) -> Result<Vec<Prefix>, ParseError> {
+        match self.next() {
+            Some(Token::Int(p)) => Ok(vec![Prefix(p as u32)]),
+            Some(Token::Op("*")) => {
+                let prefixes = PrefixSet::All.prefixes(self.net.unwrap());
+                if prefixes.is_empty() {
+                    self.pos -= 1;
+                    return self.error("The network does not know any prefix");
+                }
+                Ok(prefixes)
+            }
+            Some(Token::LBracket) => {
+                let mut prefixes = Vec::new();
+                loop {
+                    match self.next() {
+                        Some(Token::Int(p)) => prefixes.push(Prefix(p as u32)),
+                        _ => {
+                            self.pos -= 1;
+                            return self.error("Expected a prefix");
+                        }
+                    }
+                    if self.peek() != Some(&Token::Comma) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                self.expect(Token::RBracket)?;
+                Ok(PrefixSet::List(prefixes).prefixes(self.net.unwrap()))
+            }
+            _ => {
+                self.pos -= 1;
+                self.error("Expected a prefix")
+            }
+        }
+    }
+
+    /// Parse a router name and resolve it using the network.
+    fn parse_router(&mut self) -> Result<RouterId, ParseError> {
+        let name = match self.peek() {
+            Some(Token::Ident(name)) | Some(Token::Str(name)) => name.clone(),
+            t => return self.error(format!("Expected a router name, found {:?}", t)),
+        };
+        match self.net.and_then(|net| net.get_router_id(&name).ok()) {
+            Some(router) => {
+                self.pos += 1;
+                Ok(router)
+            }
+            None => self.error(format!("Unknown router: {}", name)),
+        }
+    }
+
+    fn parse_path_or(&mut self) -> Result<PathCondition, ParseError> {
+        let mut conds = vec![self.parse_path_and()?];
+        while self.peek() == Some(&Token::Op("||")) {
+            self.pos += 1;
+            conds.push(self.parse_path_and()?);
+        }
+        Ok(if conds.len() == 1 { conds.pop().unwrap() } else { PathCondition::Or(conds) })
+    }
+
+    fn parse_path_and(&mut self) -> Result<PathCondition, ParseError> {
+        let mut conds = vec![self.parse_path_unary()?];
+        while self.peek() == Some(&Token::Op("&&")) {
+            self.pos += 1;
+            conds.push(self.parse_path_unary()?);
+        }
+        Ok(if conds.len() == 1 { conds.pop().unwrap() } else { PathCondition::And(conds) })
+    }
+
+    fn parse_path_unary(&mut self) -> Result<PathCondition, ParseError> {
+        match self.peek() {
+            Some(Token::Op("!")) => {
+                self.pos += 1;
+                Ok(PathCondition::Not(Box::new(self.parse_path_unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let cond = self.parse_path_or()?;
+                self.expect(Token::RParen)?;
+                Ok(cond)
+            }
+            Some(Token::LBracket) => {
+                self.pos += 1;
+                self.parse_path_positional()
+            }
+            _ => Ok(PathCondition::Node(self.parse_router()?)),
+        }
+    }
+
+    /// Parse a positional condition (or an edge) after the opening bracket. A sequence of exactly
+    /// two routers, written as `[a -> b]`, is parsed as an edge.
+    fn parse_path_positional(&mut self) -> Result<PathCondition, ParseError> {
+        let mut waypoints = Vec::new();
+        loop {
+            waypoints.push(match self.peek() {
+                Some(Token::Op("?")) => {
+                    self.pos += 1;
+                    Waypoint::Any
+                }
+                Some(Token::Op("...")) | Some(Token::Op("*")) => {
+                    self.pos += 1;
+                    Waypoint::Star
+                }
+                _ => Waypoint::Fix(self.parse_router()?),
+            });
+            match self.peek() {
+                Some(Token::Op("->")) | Some(Token::Comma) => self.pos += 1,
+                _ => break,
+            }
+        }
+        self.expect(Token::RBracket)?;
+        Ok(match waypoints.as_slice() {
+            [Waypoint::Fix(a), Waypoint::Fix(b)] => PathCondition::Edge(*a, *b),
+            _ => PathCondition::Positional(waypoints),
+        })
+    }
+}
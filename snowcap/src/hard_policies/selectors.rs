@@ -0,0 +1,83 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Selectors
+//!
+//! Helpers to write policies over large networks without collecting the `RouterId`s and prefixes
+//! manually. Routers are selected by their name using [`Network::routers_matching`], prefixes using
+//! a [`PrefixSet`], and [`for_all`] expands a condition for every combination of them.
+//!
+//! ```
+//! use snowcap::hard_policies::*;
+//! use snowcap::example_networks::{ExampleNetwork, SimpleNet};
+//!
+//! let net = SimpleNet::net(0);
+//! let routers = net.routers_matching("r[1-3]").unwrap();
+//! let prefixes = PrefixSet::All.prefixes(&net);
+//! let policy = HardPolicy::globally(for_all(&routers, &prefixes, |r, p| {
+//!     Condition::Reachable(r, p, None)
+//! }));
+//! assert_eq!(policy.prop_vars.len(), routers.len() * prefixes.len());
+//! ```
+
+use super::Condition;
+use crate::netsim::{Network, NetworkDevice, Prefix, RouterId};
+
+/// # Prefix Set
+/// Selects a set of prefixes of the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefixSet {
+    /// All prefixes currently known in the network
+    All,
+    /// An explicit list of prefixes
+    List(Vec<Prefix>),
+    /// All prefixes advertised by at least one of the given external routers
+    AdvertisedBy(Vec<RouterId>),
+}
+
+impl PrefixSet {
+    /// Returns the selected prefixes, sorted and without duplicates.
+    pub fn prefixes(&self, net: &Network) -> Vec<Prefix> {
+        let mut prefixes: Vec<Prefix> = match self {
+            Self::All => net.get_known_prefixes().iter().cloned().collect(),
+            Self::List(prefixes) => prefixes.clone(),
+            Self::AdvertisedBy(routers) => routers
+                .iter()
+                .flat_map(|r| match net.get_device(*r) {
+                    NetworkDevice::ExternalRouter(r) => r.advertised_prefixes(),
+                    _ => Default::default(),
+                })
+                .collect(),
+        };
+        prefixes.sort();
+        prefixes.dedup();
+        prefixes
+    }
+}
+
+/// Create a condition for every combination of router and prefix, using the function `f`. The
+/// conditions are ordered first by router, and then by prefix.
+pub fn for_all<F>(routers: &[RouterId], prefixes: &[Prefix], f: F) -> Vec<Condition>
+where
+    F: Fn(RouterId, Prefix) -> Condition,
+{
+    routers
+        .iter()
+        .flat_map(|r| prefixes.iter().map(move |p| (*r, *p)))
+        .map(|(r, p)| f(r, p))
+        .collect()
+}
@@ -0,0 +1,193 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Policy Templates
+//!
+//! This module contains a library of commonly used policies, which expand to a set of
+//! [`Condition`]s. The templates are instantiated on a [`Network`], using the roles of the routers
+//! given in [`TemplateRoles`], which can be derived from the router roles annotated in the network
+//! (see [`TemplateRoles::from_network`]). The resulting conditions can be combined into a
+//! [`HardPolicy`], either by using [`PolicyTemplate::build`], or by composing them manually.
+//!
+//! All templates, which restrict the path towards a prefix, use [`Condition::Reachable`], and
+//! hence also require that the prefix can be reached.
+
+use super::{Condition, HardPolicy, PathCondition};
+use crate::netsim::bgp::BgpRelationship;
+use crate::netsim::{Network, NetworkDevice, Prefix, RouterId, RouterRole};
+
+use itertools::iproduct;
+
+/// # Router roles used by templates
+/// The routers, grouped by the role they play in the policy templates. Customers, peers and
+/// providers are external routers, while firewalls are internal routers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateRoles {
+    /// External routers which are customers
+    pub customers: Vec<RouterId>,
+    /// External routers which are settlement-free peers
+    pub peers: Vec<RouterId>,
+    /// External routers which are providers
+    pub providers: Vec<RouterId>,
+    /// Internal routers which act as firewalls
+    pub firewalls: Vec<RouterId>,
+}
+
+impl TemplateRoles {
+    /// Create the roles based on the BGP relationships currently configured in the network. All
+    /// external routers without a relationship are ignored, and no firewalls are set.
+    pub fn from_relationships(net: &Network) -> Self {
+        let mut roles = Self::default();
+        let router = net.get_routers().into_iter().min().map(|r| net.get_device(r));
+        if let Some(NetworkDevice::InternalRouter(router)) = router {
+            for ext in sorted(net.get_external_routers()) {
+                match router.get_bgp_relationship(ext) {
+                    Some(BgpRelationship::Customer) => roles.customers.push(ext),
+                    Some(BgpRelationship::Peer) => roles.peers.push(ext),
+                    Some(BgpRelationship::Provider) => roles.providers.push(ext),
+                    None => {}
+                }
+            }
+        }
+        roles
+    }
+
+    /// Create the roles based on the BGP relationships currently configured in the network (see
+    /// [`TemplateRoles::from_relationships`]), and use all routers annotated with
+    /// [`RouterRole::Firewall`] as firewalls.
+    pub fn from_network(net: &Network) -> Self {
+        Self::from_relationships(net)
+            .with_firewalls(net.get_routers_with_role(RouterRole::Firewall))
+    }
+
+    /// Set the firewalls, and return the updated roles.
+    pub fn with_firewalls(mut self, firewalls: Vec<RouterId>) -> Self {
+        self.firewalls = firewalls;
+        self
+    }
+}
+
+/// # Policy Template
+/// Pre-built policies, which can be instantiated on a network using [`TemplateRoles`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyTemplate {
+    /// Traffic must never transit through a customer: every internal router must reach each known
+    /// prefix without leaving the network through a customer which does not advertise the prefix
+    /// itself. Prefixes advertised by all customers are skipped.
+    NoTransitThroughCustomer,
+    /// Peers are always preferred over providers: every prefix advertised both by a peer and by a
+    /// provider must be reached through one of the peers advertising it.
+    PreferPeerOverProvider,
+    /// Traffic from every internal router (except the firewalls themselves) towards the given
+    /// prefixes must traverse at least one firewall.
+    FirewallTraversal(Vec<Prefix>),
+    /// No router connected to an external neighbor may export a route in violation of the
+    /// valley-free property (see [`Condition::ValleyFree`]), for any known prefix.
+    ValleyFree,
+}
+
+impl PolicyTemplate {
+    /// Expand the template into the set of conditions for the given network and roles. The order
+    /// of the conditions is deterministic.
+    pub fn conditions(&self, net: &Network, roles: &TemplateRoles) -> Vec<Condition> {
+        let routers = sorted(net.get_routers());
+        let prefixes = sorted(net.get_known_prefixes().iter().cloned().collect());
+        match self {
+            Self::NoTransitThroughCustomer => prefixes
+                .iter()
+                .filter_map(|p| {
+                    let transit = roles
+                        .customers
+                        .iter()
+                        .filter(|c| !advertises(net, **c, *p))
+                        .map(|c| PathCondition::Node(*c))
+                        .collect::<Vec<_>>();
+                    if transit.is_empty() {
+                        None
+                    } else {
+                        Some((*p, PathCondition::Not(Box::new(PathCondition::Or(transit)))))
+                    }
+                })
+                .flat_map(|(p, c)| {
+                    routers.iter().map(move |r| Condition::Reachable(*r, p, Some(c.clone())))
+                })
+                .collect(),
+            Self::PreferPeerOverProvider => prefixes
+                .iter()
+                .filter(|p| roles.providers.iter().any(|e| advertises(net, *e, **p)))
+                .filter_map(|p| {
+                    let peers = roles
+                        .peers
+                        .iter()
+                        .filter(|e| advertises(net, **e, *p))
+                        .map(|e| PathCondition::Node(*e))
+                        .collect::<Vec<_>>();
+                    if peers.is_empty() {
+                        None
+                    } else {
+                        Some((*p, PathCondition::Or(peers)))
+                    }
+                })
+                .flat_map(|(p, c)| {
+                    routers.iter().map(move |r| Condition::Reachable(*r, p, Some(c.clone())))
+                })
+                .collect(),
+            Self::FirewallTraversal(fw_prefixes) => {
+                let condition = PathCondition::Or(
+                    roles.firewalls.iter().map(|r| PathCondition::Node(*r)).collect(),
+                );
+                iproduct!(
+                    routers.iter().filter(|r| !roles.firewalls.contains(r)),
+                    fw_prefixes.iter()
+                )
+                .map(|(r, p)| Condition::Reachable(*r, *p, Some(condition.clone())))
+                .collect()
+            }
+            Self::ValleyFree => {
+                iproduct!(routers.iter().filter(|r| is_border(net, **r)), prefixes.iter())
+                    .map(|(r, p)| Condition::ValleyFree(*r, *p))
+                    .collect()
+            }
+        }
+    }
+
+    /// Build a hard policy, where all conditions of all templates must be satisfied all the time.
+    pub fn build(templates: &[Self], net: &Network, roles: &TemplateRoles) -> HardPolicy {
+        HardPolicy::globally(templates.iter().flat_map(|t| t.conditions(net, roles)).collect())
+    }
+}
+
+/// Returns `true` if the router is an external router advertising the prefix.
+fn advertises(net: &Network, router: RouterId, prefix: Prefix) -> bool {
+    match net.get_device(router) {
+        NetworkDevice::ExternalRouter(r) => r.advertised_prefixes().contains(&prefix),
+        _ => false,
+    }
+}
+
+/// Returns `true` if the router is an internal router with at least one eBGP session.
+fn is_border(net: &Network, router: RouterId) -> bool {
+    match net.get_device(router) {
+        NetworkDevice::InternalRouter(r) => r.get_bgp_sessions().any(|(_, t)| t.is_ebgp()),
+        _ => false,
+    }
+}
+
+fn sorted<T: Ord>(mut v: Vec<T>) -> Vec<T> {
+    v.sort();
+    v
+}
@@ -28,8 +28,34 @@ use super::{
 use crate::netsim::{bgp::BgpRoute, Network, Prefix, RouterId};
 use std::collections::{HashMap, HashSet};
 
+/// # Transient State Analyzer
+///
 /// Structure to manage and check transient state. This module supports functions to push and pop
-/// from a stack, in order to maintain history (similar to `HardPolicy`).
+/// from a stack, in order to maintain history (similar to `HardPolicy`). Only
+/// [`Condition::TransientPath`] is checked, all other conditions are ignored. The analyzer is used
+/// by the [`HardPolicy`](crate::hard_policies::HardPolicy) for all transient conditions, but it can
+/// also be used on its own:
+///
+/// ```
+/// use snowcap::example_networks::*;
+/// use snowcap::hard_policies::transient_behavior::TransientStateAnalyzer;
+/// use snowcap::hard_policies::{Condition, PathCondition};
+///
+/// let mut net = SimpleNet::net(0);
+/// let final_config = SimpleNet::final_config(&net, 0);
+/// let r = net.get_router_id("r1").unwrap();
+/// let p = *net.get_known_prefixes().iter().next().unwrap();
+/// // every transient path of r1 must traverse r1 (which is trivially satisfied)
+/// let conditions = vec![Condition::TransientPath(r, p, PathCondition::Node(r))];
+///
+/// let mut tsa = TransientStateAnalyzer::new(net.get_known_prefixes(), &conditions);
+/// tsa.step(&net);
+/// // the conditions are checked only after the first modifier is applied
+/// assert!(tsa.check().is_empty());
+/// net.apply_modifier(&net.current_config().get_diff(&final_config).modifiers[0]).unwrap();
+/// tsa.step(&net);
+/// assert_eq!(tsa.check(), vec![(0, true)]);
+/// ```
 #[derive(Debug, Clone)]
 pub struct TransientStateAnalyzer {
     /// All analyzers for each prefix
@@ -65,6 +91,12 @@ impl TransientStateAnalyzer {
         self.analyzers.iter().map(|a| a.check().into_iter()).flatten().collect()
     }
 
+    /// Returns the forwarding supergraph of the prefix in the current state, or `None` if the
+    /// prefix is not analyzed, or if no step was performed yet.
+    pub fn forwarding_supergraph(&self, prefix: Prefix) -> Option<&ForwardingSupergraph> {
+        self.analyzers.iter().find(|a| a.prefix == prefix).and_then(|a| a.fwsg.last())
+    }
+
     /// Represent the transient state analyzer as a string
     pub fn repr_with_name(&self, net: &Network) -> String {
         self.analyzers.iter().map(|a| a.repr_with_name(net)).collect::<Vec<_>>().join("\n")
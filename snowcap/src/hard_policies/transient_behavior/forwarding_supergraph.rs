@@ -28,7 +28,12 @@ use crate::netsim::{bgp::BgpRoute, Network, NetworkDevice, Prefix, RouterId};
 use std::collections::{HashMap, HashSet};
 use std::iter::repeat;
 
-/// Forwarding Supergraph
+/// # Forwarding Supergraph
+///
+/// Graph of a single prefix, containing an edge from every internal router to all next hops it
+/// may choose during convergence, i.e., to the next hops of every route which may reach the router
+/// (see [`RouteReachability`]). The transient paths are all simple paths in the union of the
+/// supergraphs before and after a modifier is applied (see [`ForwardingSupergraph::simple_paths`]).
 #[derive(Clone, Debug)]
 pub struct ForwardingSupergraph {
     neighbors: Vec<Vec<RouterId>>,
@@ -37,10 +42,13 @@ pub struct ForwardingSupergraph {
 
 impl ForwardingSupergraph {
     /// Build the forwarding supergraph for a single prefix. The routes are filtered in this
-    /// funciton (i.e., only the routes with the given prefix are considered).
+    /// funciton (i.e., only the routes with the given prefix are considered). If ECMP is enabled
+    /// in the network, all equal-cost next hops towards the egress of a route are added. Static
+    /// routes replace all other next hops of the router.
     pub fn new(net: &Network, prefix: Prefix, rri: &HashMap<BgpRoute, RouteReachability>) -> Self {
         // build the graph
         let mut g: Vec<Vec<RouterId>> = repeat(Vec::new()).take(net.num_devices()).collect();
+        let ecmp = net.get_ecmp();
 
         // go through each route and update the graph accordingly
         for (route, routers) in rri {
@@ -50,11 +58,20 @@ impl ForwardingSupergraph {
             }
             let target = route.next_hop;
             for r_id in routers.iter() {
-                // compute the next hop for this potential route
+                // compute the next hops for this potential route
                 if let NetworkDevice::InternalRouter(r) = net.get_device(*r_id) {
-                    if let Some(nh) =
-                        r.igp_forwarding_table.get(&target).cloned().flatten().map(|(nh, _)| nh)
-                    {
+                    let next_hops = match r.igp_ecmp_table.get(&target) {
+                        Some(next_hops) if ecmp && !next_hops.is_empty() => next_hops.clone(),
+                        _ => r
+                            .igp_forwarding_table
+                            .get(&target)
+                            .cloned()
+                            .flatten()
+                            .map(|(nh, _)| nh)
+                            .into_iter()
+                            .collect(),
+                    };
+                    for nh in next_hops {
                         // check if this next hop is already stored in the graph
                         if !g[r_id.index()].contains(&nh) {
                             g[r_id.index()].push(nh);
@@ -81,8 +98,18 @@ impl ForwardingSupergraph {
         Self { neighbors: g, external }
     }
 
+    /// Returns all next hops of the router, which may be chosen in this state. External routers
+    /// have no next hops.
+    pub fn next_hops(&self, router: RouterId) -> &[RouterId] {
+        self.neighbors.get(router.index()).map(|n| n.as_slice()).unwrap_or_default()
+    }
+
     /// Check the transient condition on the current supergraph, including the old supergraph
-    pub fn check_condition<'n, 'o>(&'n self, old: &'o Self, cond: &TransientCondition) -> bool {
+    pub(crate) fn check_condition<'n, 'o>(
+        &'n self,
+        old: &'o Self,
+        cond: &TransientCondition,
+    ) -> bool {
         match cond {
             TransientCondition::FastMode { router_id, groups_pos, groups_neg, .. } => {
                 self.cond_algorithm(old, *router_id, groups_pos, groups_neg)
@@ -98,7 +125,9 @@ impl ForwardingSupergraph {
         }
     }
 
-    /// Returns an iterator over all simple paths starting from the given router.
+    /// Returns an iterator over all simple paths starting from the given router, where every
+    /// router may choose any next hop of either `old` or `self`. Only paths reaching an external
+    /// router are returned.
     pub fn simple_paths<'n, 'o>(
         &'n self,
         start: RouterId,
@@ -158,9 +187,21 @@ impl ForwardingSupergraph {
     /// let mut fw_state = net.get_forwarding_state();
     /// policy.step(&mut net, &mut fw_state)?;
     ///
-    /// // Apply the modifier while performing the transient check (simulation, ground thruth)
-    /// let num_correct = net.apply_modifier_check_transient(command, &transient_conds, 1000)?;
-    /// let ground_truth = num_correct == 1000;
+    /// // Perform the transient check (simulation, ground thruth), and apply the modifier
+    /// let mut rng = rand::thread_rng();
+    /// let fail_prob = snowcap::transient::estimate_violation_probability_with(
+    ///     &net,
+    ///     command,
+    ///     1000,
+    ///     &mut rng,
+    ///     |fw_state| {
+    ///         transient_conds.iter().any(|c| {
+    ///             matches!(c.check(fw_state), Err(PolicyError::PathCondition { .. }))
+    ///         })
+    ///     },
+    /// )?;
+    /// let ground_truth = fail_prob == 0.0;
+    /// net.apply_modifier(command)?;
     ///
     /// // Use the transient condition to check if everything is ok
     /// let mut fw_state = net.get_forwarding_state();
@@ -362,6 +403,8 @@ fn group_matches_node(
     }
 }
 
+/// Iterator over all simple paths in the union of two forwarding supergraphs, which end at an
+/// external router (see [`ForwardingSupergraph::simple_paths`]).
 pub struct ForwardingSupergraphPaths<'n, 'o> {
     old: &'o Vec<Vec<RouterId>>,
     new: &'n Vec<Vec<RouterId>>,
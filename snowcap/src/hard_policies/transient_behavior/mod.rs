@@ -18,17 +18,31 @@
 //! # Transient Behavior
 //!
 //! This module checks for necessary but insufficient conditions for wether any ordering of messages
-//! during convergence might violate chosen path conditions.
+//! during convergence might violate chosen path conditions. If the check succeeds, then every
+//! transient path satisfies the condition. However, the check might fail, even though no ordering
+//! of messages leads to a violation.
 //!
-//! ## Strucutre
+//! ## Structure
 //!
-//! **TODO** explain this
+//! The analysis of a single state is done in three steps:
 //!
+//! 1. The [`BgpGraph`] contains all BGP sessions and route maps of the network.
+//! 2. [`get_all_route_reachability`] propagates every route advertised by an external router
+//!    through the BGP graph (applying all route maps on the way), and computes the
+//!    [`RouteReachability`], i.e., the set of routers which may learn the route.
+//! 3. The [`ForwardingSupergraph`] of every prefix contains an edge from every router to the IGP
+//!    next hop (or to all equal-cost next hops, if ECMP is enabled) towards the egress of every
+//!    route, which may reach the router.
 //!
+//! When a modifier is applied, every router may choose any next hop of either the supergraph before
+//! or after the modifier. Hence, the [`TransientStateAnalyzer`] checks a
+//! [`Condition::TransientPath`](crate::hard_policies::Condition::TransientPath) on all simple paths
+//! in the union of both supergraphs (see [`ForwardingSupergraph::simple_paths`]).
 
 mod analysis;
 mod forwarding_supergraph;
 mod route_reachability_graph;
 
 pub use analysis::TransientStateAnalyzer;
+pub use forwarding_supergraph::{ForwardingSupergraph, ForwardingSupergraphPaths};
 pub use route_reachability_graph::*;
@@ -182,7 +182,7 @@ impl RouteReachability {
         (Self(reachability), new_routes_map.into_iter().collect())
     }
 
-    #[allow(dead_code)]
+    /// Returns `true` if the route may reach the router.
     pub fn contains(&self, router_id: RouterId) -> bool {
         self.0.contains(&router_id)
     }
@@ -201,13 +201,14 @@ impl RouteReachability {
     }
 }
 
-/// BGP Graph
+/// BGP Graph, containing all BGP sessions (as an adjacency matrix) and the route maps of every
+/// router.
 #[derive(Debug, Clone, PartialEq)]
 pub struct BgpGraph {
-    pub g: Vec<BgpEdge>,
-    pub rm_inc: Vec<Vec<RouteMap>>,
-    pub rm_out: Vec<Vec<RouteMap>>,
-    pub n: usize,
+    g: Vec<BgpEdge>,
+    rm_inc: Vec<Vec<RouteMap>>,
+    rm_out: Vec<Vec<RouteMap>>,
+    n: usize,
 }
 
 impl BgpGraph {
@@ -382,12 +383,14 @@ impl BgpGraph {
     }
 }
 
-pub enum RouteMapResult {
+/// Result of applying the route maps on a single BGP session
+pub(super) enum RouteMapResult {
     NoMatch,
     Deny,
     Allow(BgpRoute),
 }
 
+/// Iterator over all BGP neighbors of a router, along with the type of the session.
 pub struct BgpGraphNeighborIterator<'a> {
     data: &'a [BgpEdge],
     pos: usize,
@@ -417,11 +420,17 @@ fn edge_idx(from: RouterId, to: RouterId, n: usize) -> usize {
 /// Type of an edge (BGP Session), including optional route maps
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BgpEdge {
+    /// iBGP session from a route-reflector client to its route reflector
     Up,
+    /// eBGP session from an external router to an internal router
     UpExternal,
+    /// iBGP session between two peers
     Over,
+    /// iBGP session from a route reflector to its client
     Down,
+    /// eBGP session from an internal router to an external router
     DownExternal,
+    /// No BGP session
     None,
 }
 
@@ -493,6 +502,7 @@ mod test {
     use crate::netsim::route_map::{RouteMapBuilder, RouteMapDirection};
     use crate::netsim::{AsId, Prefix};
     use maplit::{hashmap, hashset};
+    use std::collections::BTreeSet;
 
     #[test]
     fn bgp_tree_simplenet() {
@@ -642,7 +652,9 @@ mod test {
             next_hop: e1,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route4 = BgpRoute {
             prefix: Prefix(0),
@@ -650,7 +662,9 @@ mod test {
             next_hop: e4,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
 
         let expected = hashmap![
@@ -694,7 +708,9 @@ mod test {
             next_hop: e1,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route2 = BgpRoute {
             prefix: Prefix(0),
@@ -702,7 +718,9 @@ mod test {
             next_hop: e1,
             local_pref: Some(200),
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route4 = BgpRoute {
             prefix: Prefix(0),
@@ -710,7 +728,9 @@ mod test {
             next_hop: e4,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
 
         let expected = hashmap![
@@ -755,7 +775,9 @@ mod test {
             next_hop: e1,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route2 = BgpRoute {
             prefix: Prefix(0),
@@ -763,7 +785,9 @@ mod test {
             next_hop: e1,
             local_pref: Some(200),
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route4 = BgpRoute {
             prefix: Prefix(0),
@@ -771,7 +795,9 @@ mod test {
             next_hop: e4,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
 
         let expected = hashmap![
@@ -827,7 +853,9 @@ mod test {
             next_hop: e1,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route2 = BgpRoute {
             prefix: Prefix(0),
@@ -835,7 +863,9 @@ mod test {
             next_hop: e1,
             local_pref: Some(200),
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route3 = BgpRoute {
             prefix: Prefix(0),
@@ -843,7 +873,9 @@ mod test {
             next_hop: e1,
             local_pref: Some(300),
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route4 = BgpRoute {
             prefix: Prefix(0),
@@ -851,7 +883,9 @@ mod test {
             next_hop: e4,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
 
         let expected = hashmap![
@@ -903,7 +937,9 @@ mod test {
             next_hop: e1,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route2 = BgpRoute {
             prefix: Prefix(0),
@@ -911,7 +947,9 @@ mod test {
             next_hop: e1,
             local_pref: Some(200),
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
         let route4 = BgpRoute {
             prefix: Prefix(0),
@@ -919,7 +957,9 @@ mod test {
             next_hop: e4,
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
 
         let expected = hashmap![
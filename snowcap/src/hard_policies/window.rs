@@ -0,0 +1,184 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Activation Windows
+//!
+//! Conditions which must only hold during a part of the reconfiguration can be expressed in LTL.
+//! However, writing such expressions manually, using the numeric indices of the propositional
+//! variables, is very error prone. The [`WindowedPolicyBuilder`] allows every condition to be
+//! paired with an [`ActivationWindow`], and compiles them into a single [`HardPolicy`].
+//!
+//! A window can either be a range of steps, or it can be placed between two named *anchors*. An
+//! anchor is a modifier of the reconfiguration, and it becomes a propositional variable of the
+//! hard policy, which is `true` as soon as the modifier is applied to the network.
+
+use super::{Condition, HardPolicy, LTLBoolean, LTLModal, LTLOperator};
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::Network;
+use crate::Error;
+
+/// # Activation Window
+/// Describes when a condition must be satisfied. States are numbered, where state 0 is the initial
+/// state, and state $i$ is the state after applying $i$ modifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActivationWindow {
+    /// The condition must always be satisfied.
+    Always,
+    /// The condition must be satisfied in all states from `from` up to (and including) `to`.
+    /// Compiles to $\mathbf{N}^{from}\ (\phi \land \mathbf{N}\ (\phi \land \ldots))$.
+    Steps {
+        /// First state in which the condition is active.
+        from: usize,
+        /// Last state in which the condition is active.
+        to: usize,
+    },
+    /// The condition must be satisfied in all states after the anchor `after` is applied, and
+    /// before the anchor `before` is applied. If `after` is `None`, then the window starts at the
+    /// initial state, and if `before` is `None`, the window never ends. Compiles to
+    /// $\mathbf{G}\ (a \Rightarrow (\phi\ \mathbf{W}\ b))$.
+    Anchors {
+        /// Name of the anchor, which opens the window.
+        after: Option<String>,
+        /// Name of the anchor, which closes the window.
+        before: Option<String>,
+    },
+}
+
+/// # Windowed Policy Builder
+///
+/// Builds a [`HardPolicy`] from conditions, each with its own [`ActivationWindow`]. All
+/// conditions are combined with a logical and.
+///
+/// ```
+/// use snowcap::hard_policies::*;
+/// use snowcap::example_networks::*;
+///
+/// let net = SimpleNet::net(0);
+/// let final_config = SimpleNet::final_config(&net, 0);
+/// let modifiers = net.current_config().get_diff(&final_config).modifiers;
+/// let r1 = net.get_router_id("r1").unwrap();
+/// let r4 = net.get_router_id("r4").unwrap();
+/// let prefix = net.get_known_prefixes()[0];
+///
+/// let hard_policy = WindowedPolicyBuilder::new()
+///     .anchor("start", modifiers[0].clone())
+///     .condition(Condition::Reachable(r4, prefix, None), ActivationWindow::Always)
+///     .condition(
+///         Condition::Reachable(r1, prefix, None),
+///         ActivationWindow::Anchors { after: Some("start".to_string()), before: None },
+///     )
+///     .build()
+///     .unwrap();
+/// assert_eq!(hard_policy.anchor_var("start"), Some(2));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WindowedPolicyBuilder {
+    conditions: Vec<(Condition, ActivationWindow)>,
+    anchors: Vec<(String, ConfigModifier)>,
+}
+
+impl WindowedPolicyBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a condition, which must be satisfied during the given window.
+    pub fn condition(mut self, condition: Condition, window: ActivationWindow) -> Self {
+        self.conditions.push((condition, window));
+        self
+    }
+
+    /// Add a named anchor. The anchor is considered to be reached as soon as the modifier is
+    /// applied to the network. If an anchor with the same name already exists, it is replaced.
+    pub fn anchor(mut self, name: impl Into<String>, modifier: ConfigModifier) -> Self {
+        let name = name.into();
+        self.anchors.retain(|(n, _)| n != &name);
+        self.anchors.push((name, modifier));
+        self
+    }
+
+    /// Compile all conditions and windows into a hard policy. Returns `Err(UnknownAnchor)` if a
+    /// window references an anchor which was not added.
+    pub fn build(self) -> Result<HardPolicy, Error> {
+        let num_vars = self.conditions.len();
+        let anchor_var = |name: &Option<String>| -> Result<Option<usize>, Error> {
+            match name {
+                None => Ok(None),
+                Some(name) => match self.anchors.iter().position(|(n, _)| n == name) {
+                    Some(i) => Ok(Some(num_vars + i)),
+                    None => Err(Error::UnknownAnchor(name.clone())),
+                },
+            }
+        };
+
+        let mut terms: Vec<Box<dyn LTLOperator>> = Vec::with_capacity(num_vars);
+        for (i, (_, window)) in self.conditions.iter().enumerate() {
+            terms.push(match window {
+                ActivationWindow::Always => Box::new(LTLModal::Globally(Box::new(i))),
+                ActivationWindow::Steps { from, to } => steps_window(i, *from, *to),
+                ActivationWindow::Anchors { after, before } => {
+                    anchor_window(i, anchor_var(after)?, anchor_var(before)?)
+                }
+            });
+        }
+
+        let expr = LTLModal::Now(Box::new(LTLBoolean::And(terms)));
+        let mut hard_policy =
+            HardPolicy::new(self.conditions.into_iter().map(|(c, _)| c).collect(), expr);
+        hard_policy.anchors = self.anchors;
+        Ok(hard_policy)
+    }
+}
+
+/// Compile the window, where `var` must hold in the states `from` up to `to`.
+fn steps_window(var: usize, from: usize, to: usize) -> Box<dyn LTLOperator> {
+    if to < from {
+        return Box::new(true);
+    }
+    let mut expr: Box<dyn LTLOperator> = Box::new(var);
+    for _ in from..to {
+        expr = Box::new(LTLBoolean::And(vec![Box::new(var), Box::new(LTLModal::Next(expr))]));
+    }
+    for _ in 0..from {
+        expr = Box::new(LTLModal::Next(expr));
+    }
+    expr
+}
+
+/// Compile the window, where `var` must hold after `after` is reached, and before `before` is
+/// reached.
+fn anchor_window(var: usize, after: Option<usize>, before: Option<usize>) -> Box<dyn LTLOperator> {
+    let inner: Box<dyn LTLOperator> = match before {
+        Some(b) => Box::new(LTLModal::WeakUntil(Box::new(var), Box::new(b))),
+        None => Box::new(LTLModal::Globally(Box::new(var))),
+    };
+    match after {
+        Some(a) => Box::new(LTLModal::Globally(Box::new(LTLBoolean::Implies(Box::new(a), inner)))),
+        None => inner,
+    }
+}
+
+/// Returns `true` if the modifier is applied in the current configuration of the network.
+pub(super) fn is_applied(net: &Network, modifier: &ConfigModifier) -> bool {
+    let config = net.current_config();
+    match modifier {
+        ConfigModifier::Insert(e) | ConfigModifier::Update { to: e, .. } => {
+            config.expr.get(&e.key()) == Some(e)
+        }
+        ConfigModifier::Remove(e) => !config.expr.contains_key(&e.key()),
+    }
+}
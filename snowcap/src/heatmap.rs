@@ -0,0 +1,220 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Policy Violation Heatmap
+//!
+//! This module estimates which parts of the network make a reconfiguration hard. Similar to the
+//! [`NaiveRandomStrategy`](crate::strategies::NaiveRandomStrategy), many random orderings of the
+//! modifiers are played out. Every time a playout violates the hard policy, the modifier that
+//! caused the violation, as well as every (router, prefix) pair mentioned in the policy errors, is
+//! counted. The playout stops at the first violation.
+//!
+//! The resulting [`ViolationHeatmap`] can be exported as CSV, using
+//! [`ViolationHeatmap::write_router_csv`] and [`ViolationHeatmap::write_modifier_csv`].
+
+use crate::hard_policies::{HardPolicy, PolicyError};
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{printer, Network, NetworkError, Prefix, RouterId};
+use crate::Error;
+
+use log::*;
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// # Policy Violation Heatmap
+/// Aggregated number of violations per (router, prefix) pair and per modifier, over a number of
+/// random playouts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViolationHeatmap {
+    /// Number of random orderings that were played out.
+    pub num_playouts: usize,
+    /// Number of playouts which violated the hard policy.
+    pub num_violations: usize,
+    /// Number of violating playouts in which the (router, prefix) pair was involved.
+    pub routers: HashMap<(RouterId, Prefix), usize>,
+    /// Number of violating playouts caused by the modifier at the given index.
+    pub modifiers: Vec<usize>,
+}
+
+impl ViolationHeatmap {
+    /// Play out `num_playouts` random orderings of `modifiers` on the network, and aggregate all
+    /// violations of the `hard_policy`. The network must be in the initial state. If `seed` is
+    /// given, the orderings are reproducible.
+    pub fn compute(
+        net: &Network,
+        modifiers: &[ConfigModifier],
+        hard_policy: &HardPolicy,
+        num_playouts: usize,
+        seed: Option<u64>,
+    ) -> Result<Self, Error> {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let mut hard_policy = hard_policy.clone();
+        hard_policy.reset();
+        hard_policy.set_num_mods_if_none(modifiers.len());
+
+        // check the initial state
+        let mut initial_net = net.clone();
+        let mut fw_state = initial_net.get_forwarding_state();
+        hard_policy.step(&mut initial_net, &mut fw_state)?;
+        if !hard_policy.check() {
+            return Err(Error::invalid_initial_state(&initial_net, &hard_policy, &mut fw_state));
+        }
+
+        let mut heatmap = Self {
+            num_playouts,
+            num_violations: 0,
+            routers: HashMap::new(),
+            modifiers: vec![0; modifiers.len()],
+        };
+
+        let mut ordering: Vec<usize> = (0..modifiers.len()).collect();
+        for _ in 0..num_playouts {
+            ordering.shuffle(&mut rng);
+            if let Some((idx, errors)) = playout(&initial_net, modifiers, &ordering, &hard_policy)?
+            {
+                debug!("Playout violated the hard policy at modifier {}", idx);
+                heatmap.num_violations += 1;
+                heatmap.modifiers[idx] += 1;
+                let mut locations = errors.iter().flat_map(error_locations).collect::<Vec<_>>();
+                locations.sort();
+                locations.dedup();
+                for location in locations {
+                    *heatmap.routers.entry(location).or_insert(0) += 1;
+                }
+            }
+        }
+
+        Ok(heatmap)
+    }
+
+    /// Returns the fraction of playouts in which the (router, prefix) pair was involved in a
+    /// violation.
+    pub fn router_frequency(&self, router: RouterId, prefix: Prefix) -> f64 {
+        self.frequency(self.routers.get(&(router, prefix)).copied().unwrap_or(0))
+    }
+
+    /// Returns the fraction of playouts in which the modifier at the given index caused a
+    /// violation.
+    pub fn modifier_frequency(&self, idx: usize) -> f64 {
+        self.frequency(self.modifiers.get(idx).copied().unwrap_or(0))
+    }
+
+    /// Export the (router, prefix) heatmap as CSV, with columns `router`, `prefix`, `violations`
+    /// and `frequency`. Rows are sorted by the number of violations (descending).
+    pub fn write_router_csv(&self, net: &Network, writer: impl Write) -> Result<(), csv::Error> {
+        let mut rows = self.routers.iter().collect::<Vec<_>>();
+        rows.sort_by(|(a_loc, a), (b_loc, b)| b.cmp(a).then(a_loc.cmp(b_loc)));
+        let mut wtr = csv::Writer::from_writer(writer);
+        wtr.write_record(&["router", "prefix", "violations", "frequency"])?;
+        for ((router, prefix), count) in rows {
+            wtr.write_record(&[
+                net.get_router_name(*router).unwrap_or("?").to_string(),
+                prefix.0.to_string(),
+                count.to_string(),
+                self.frequency(*count).to_string(),
+            ])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Export the per-modifier heatmap as CSV, with columns `index`, `modifier`, `violations` and
+    /// `frequency`. The `modifiers` must be the same as used to compute the heatmap.
+    pub fn write_modifier_csv(
+        &self,
+        net: &Network,
+        modifiers: &[ConfigModifier],
+        writer: impl Write,
+    ) -> Result<(), csv::Error> {
+        let mut wtr = csv::Writer::from_writer(writer);
+        wtr.write_record(&["index", "modifier", "violations", "frequency"])?;
+        for (idx, (modifier, count)) in modifiers.iter().zip(self.modifiers.iter()).enumerate() {
+            wtr.write_record(&[
+                idx.to_string(),
+                printer::config_modifier(net, modifier)
+                    .unwrap_or_else(|_| format!("{:?}", modifier)),
+                count.to_string(),
+                self.frequency(*count).to_string(),
+            ])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    fn frequency(&self, count: usize) -> f64 {
+        if self.num_playouts == 0 {
+            0.0
+        } else {
+            count as f64 / self.num_playouts as f64
+        }
+    }
+}
+
+/// Apply the modifiers in the given ordering on a clone of the network. If the hard policy is
+/// violated, the index of the modifier which caused the violation, and the errors are returned.
+fn playout(
+    net: &Network,
+    modifiers: &[ConfigModifier],
+    ordering: &[usize],
+    hard_policy: &HardPolicy,
+) -> Result<Option<(usize, Vec<PolicyError>)>, Error> {
+    let mut net = net.clone();
+    let mut hard_policy = hard_policy.clone();
+
+    for idx in ordering.iter() {
+        match net.apply_modifier(&modifiers[*idx]) {
+            Ok(()) => {}
+            Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                return Ok(Some((*idx, Vec::new())))
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state)?;
+        if !hard_policy.check() {
+            return Ok(Some((*idx, hard_policy.last_errors().into_iter().collect())));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Extract all (router, prefix) pairs involved in the policy error.
+fn error_locations(error: &PolicyError) -> Vec<(RouterId, Prefix)> {
+    match error {
+        PolicyError::BlackHole { router, prefix }
+        | PolicyError::UnallowedPathExists { router, prefix, .. }
+        | PolicyError::NotReliable { router, prefix, .. }
+        | PolicyError::TransientBehavior { router, prefix, .. }
+        | PolicyError::ValleyViolation { router, prefix, .. }
+        | PolicyError::PathTooLong { router, prefix, .. }
+        | PolicyError::LatencyExceeded { router, prefix, .. }
+        | PolicyError::WrongEgress { router, prefix, .. } => vec![(*router, *prefix)],
+        PolicyError::ForwardingLoop { path, prefix } => {
+            path.iter().map(|r| (*r, *prefix)).collect()
+        }
+        PolicyError::PathCondition { path, prefix, .. }
+        | PolicyError::ReliabilityCondition { path, prefix, .. } => {
+            path.first().map(|r| vec![(*r, *prefix)]).unwrap_or_default()
+        }
+        PolicyError::NoConvergence => Vec::new(),
+    }
+}
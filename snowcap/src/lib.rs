@@ -81,6 +81,40 @@
 //! - **[`SoftPolicy`](soft_policies)**: Collection of different soft policies which allows the
 //!   synthetisized configuration update to minimize a cost function.
 //!
+//! - **[`TrafficMatrix`](traffic_matrix::TrafficMatrix)**: Demand of traffic entering the network,
+//!   which can be imported from aggregated flow records, and is used to weight soft policies.
+//!
+//! - **[`Rationale`](rationale)**: Functions to explain a synthesized sequence, by computing for
+//!   every step on which earlier steps it depends, and what goes wrong if the order is changed.
+//!
+//! - **[`DependencyGroup`](dependencies::DependencyGroup)**: Dependency groups learned while
+//!   exploring the search space, i.e., modifiers which must be applied together, along with the
+//!   policy violation which caused each group to be learned.
+//!
+//! - **[`Analysis`](analysis)**: Estimates the size of the search space with random probing, to
+//!   decide whether an exhaustive search is feasible before running a strategy.
+//!
+//! - **[`Diagnostics`](diagnostics)**: Measures how the time is split between network simulation,
+//!   policy checking and search overhead for a given problem, and recommends how to tune it.
+//!
+//! - **[`ViolationHeatmap`](heatmap::ViolationHeatmap)**: Aggregates over many random orderings
+//!   which routers, prefixes and modifiers are involved in violations of the hard policy, and
+//!   exports the result as CSV.
+//!
+//! - **[`MigrationPlan`](plan::MigrationPlan)**: Synthesized sequence, annotated with the
+//!   convergence effort and the estimated execution time of every step.
+//!
+//! - **[`PlanEditor`](plan_editor::PlanEditor)**: Edit a verified sequence manually (e.g., swap
+//!   two steps), and verify only the part of the sequence which is affected by the edit.
+//!
+//! - **[`ExecutionState`](execution::ExecutionState)**: Track the execution of a plan on the
+//!   real network, and rank the remaining steps which can safely be executed next, if a step must
+//!   be postponed.
+//!
+//! - **[`SynthesisSession`](session::SynthesisSession)**: Repeated synthesis on the same
+//!   reconfiguration, where the hard policy can be replaced or extended between the attempts,
+//!   while reusing the prepared network and the learned dependencies.
+//!
 //! - **[`ExampleNetworks`](example_networks)**: Collection of prepared networks and reconfiguration
 //!   scenarios to test different strategies. Some of these networks can be scaled to arbitrary
 //!   size.
@@ -89,11 +123,19 @@
 //!   topology downloaded from [TopologyZoo](http://www.topology-zoo.org/dataset.html) (as `GML`
 //!   files). The configuration can be generated randomly.
 //!
+//! - **[`NetworkSpec`](network_spec::NetworkSpec)**: Declarative description of a network and a
+//!   reconfiguration scenario, loaded from JSON or YAML files.
+//!
 //! ## Features
 //!
 //! - *`count-states`*: If this feature is enabled, then [strategies](strategies::Strategy) and
 //!   [optimizers](optimizers::Optimizer) will contain the method `num_states`, to get the number
 //!   of network states that have been explored.
+//! - *`transient-violation`*: If this feature is enabled, then the module `transient` estimates
+//!   the probability of reachability violations during convergence, by simulating random orderings
+//!   of messages. Further, `Network::apply_modifier_fuzz_orderings` reports random orderings of
+//!   messages violating the hard policy, which the `PushBackTreeStrategy` can use as an optional
+//!   stronger check.
 //!
 //! ## Usage
 //!
@@ -138,57 +180,140 @@ pub mod example_networks;
 mod test;
 pub mod topology_zoo;
 
+pub mod analysis;
+pub mod corpus;
 mod dep_groups;
+pub mod dependencies;
+pub mod diagnostics;
 mod error;
+pub mod execution;
+pub mod failure;
 pub mod hard_policies;
+pub mod heatmap;
 pub mod modifier_ordering;
 pub mod netsim;
+pub mod network_spec;
 pub mod optimizers;
 pub mod permutators;
+pub mod plan;
+pub mod plan_editor;
+pub mod rationale;
+pub mod session;
 pub mod soft_policies;
 //pub mod static_analysis;
 pub mod strategies;
-// TODO needs fixing
-//pub mod transient_behavior;
+pub mod traffic_matrix;
+#[cfg(feature = "transient-violation")]
+pub mod transient;
 
 mod synthesize;
-pub use synthesize::{optimize, synthesize, synthesize_parallel};
+pub use synthesize::{optimize, optimize_early_completion, synthesize, synthesize_parallel};
 
-pub use error::Error;
+pub use error::{Error, InitialStateViolation, PartialResult};
 
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::SystemTime;
 
-/// Stopper, to check when to stop, or to send the stop command
+/// # Control Handle
+///
+/// Handle to control a running synthesis from a different thread, and to query its progress. All
+/// clones of the handle share the same state. The handle supports the following commands:
+///
+/// - **Stop**: The synthesis is aborted as soon as possible, returning [`Error::Abort`].
+/// - **Pause**: The synthesis blocks the next time it checks for the stop command (see
+///   [`ControlHandle::try_is_stop`]), until it is resumed or stopped.
+/// - **Soft deadline**: Once the deadline has passed, the synthesis behaves as if its time budget
+///   was used up, i.e., it returns the best result found so far (as a [`PartialResult`]).
+///
+/// The [`Progress`] is reported by the network (every modifier applied to a network with this
+/// handle attached, see [`Network::set_abort`](netsim::Network::set_abort), counts as an explored
+/// state), and by the optimizers (the cost of the best solution found so far).
+///
+/// ```
+/// use snowcap::ControlHandle;
+/// use std::time::{Duration, SystemTime};
+///
+/// let handle = ControlHandle::new();
+/// let worker = handle.clone();
+///
+/// handle.set_soft_deadline(Some(SystemTime::now() - Duration::from_secs(1)));
+/// assert!(worker.time_is_up(None));
+///
+/// worker.record_cost(3.0);
+/// worker.record_cost(5.0);
+/// assert_eq!(handle.progress().best_cost, Some(3.0));
+///
+/// handle.send_stop();
+/// assert!(worker.is_stop());
+/// ```
 #[derive(Clone, Debug)]
-pub struct Stopper {
-    b: Arc<RwLock<bool>>,
+pub struct ControlHandle {
+    state: Arc<ControlState>,
     c: usize,
 }
 
-impl Default for Stopper {
+/// State shared by all clones of a [`ControlHandle`]
+#[derive(Debug)]
+struct ControlState {
+    stop: RwLock<bool>,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+    soft_deadline: RwLock<Option<SystemTime>>,
+    num_states: AtomicUsize,
+    /// Cost of the best solution found so far, stored as the bits of a `f64`.
+    best_cost: AtomicU64,
+}
+
+/// # Progress
+/// Snapshot of the progress of a synthesis, see [`ControlHandle::progress`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// Number of states explored so far.
+    pub num_states: usize,
+    /// Cost of the best solution found so far, or `None` if no solution was found yet.
+    pub best_cost: Option<f64>,
+}
+
+impl Default for ControlHandle {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Stopper {
-    /// Create a new stopper
+impl ControlHandle {
+    /// Create a new control handle
     pub fn new() -> Self {
-        Self { b: Arc::new(RwLock::new(false)), c: 0 }
+        Self {
+            state: Arc::new(ControlState {
+                stop: RwLock::new(false),
+                paused: Mutex::new(false),
+                resumed: Condvar::new(),
+                soft_deadline: RwLock::new(None),
+                num_states: AtomicUsize::new(0),
+                best_cost: AtomicU64::new(f64::INFINITY.to_bits()),
+            }),
+            c: 0,
+        }
     }
 
-    /// Send the stop command. This function will block until the write lock can be acquired.
+    /// Send the stop command. This function will block until the write lock can be acquired. A
+    /// paused synthesis is woken up, such that it can stop.
     pub fn send_stop(&self) {
-        *self.b.write().unwrap() = true;
+        *self.state.stop.write().unwrap() = true;
+        let _paused = self.state.paused.lock().unwrap();
+        self.state.resumed.notify_all();
     }
 
     /// Checks if the stop flag is set. This funciton will not block, just continue if the
-    /// read-lock cannot be acquired.
+    /// read-lock cannot be acquired. If the synthesis is paused, this function blocks until it is
+    /// resumed or stopped.
     pub fn try_is_stop(&mut self) -> Option<bool> {
         self.c += 1;
         if self.c >= 9 {
             self.c = 0;
-            self.b.try_read().map(|x| *x).ok()
+            self.wait_while_paused();
+            self.state.stop.try_read().map(|x| *x).ok()
         } else {
             None
         }
@@ -197,6 +322,73 @@ impl Stopper {
     /// Checks if the stop flag is set. This funciton will block until the read lock can be
     /// acquired.
     pub fn is_stop(&self) -> bool {
-        *self.b.read().unwrap()
+        *self.state.stop.read().unwrap()
+    }
+
+    /// Pause the synthesis, until [`ControlHandle::resume`] or [`ControlHandle::send_stop`] is
+    /// called.
+    pub fn pause(&self) {
+        *self.state.paused.lock().unwrap() = true;
+    }
+
+    /// Resume the synthesis after it was paused.
+    pub fn resume(&self) {
+        *self.state.paused.lock().unwrap() = false;
+        self.state.resumed.notify_all();
+    }
+
+    /// Returns `true` if the synthesis is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.state.paused.lock().unwrap()
+    }
+
+    /// Block the current thread while the synthesis is paused (and not stopped).
+    pub fn wait_while_paused(&self) {
+        let mut paused = self.state.paused.lock().unwrap();
+        while *paused && !self.is_stop() {
+            paused = self.state.resumed.wait(paused).unwrap();
+        }
+    }
+
+    /// Set the soft deadline (or remove it, if `deadline` is `None`). In contrast to the stop
+    /// command, the synthesis returns the best result found so far once the deadline has passed.
+    pub fn set_soft_deadline(&self, deadline: Option<SystemTime>) {
+        *self.state.soft_deadline.write().unwrap() = deadline;
+    }
+
+    /// Returns the soft deadline, if any.
+    pub fn soft_deadline(&self) -> Option<SystemTime> {
+        *self.state.soft_deadline.read().unwrap()
+    }
+
+    /// Returns `true` if either the time budget of the strategy (given as the point in time
+    /// `stop_time` when it is used up) or the soft deadline has passed.
+    pub fn time_is_up(&self, stop_time: Option<SystemTime>) -> bool {
+        stop_time.into_iter().chain(self.soft_deadline()).any(|time| time.elapsed().is_ok())
+    }
+
+    /// Record that `n` more states were explored.
+    pub fn record_states(&self, n: usize) {
+        self.state.num_states.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Record the cost of a solution. The progress only keeps the lowest cost.
+    pub fn record_cost(&self, cost: f64) {
+        let _ = self.state.best_cost.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old| {
+            if cost < f64::from_bits(old) {
+                Some(cost.to_bits())
+            } else {
+                None
+            }
+        });
+    }
+
+    /// Returns a snapshot of the current progress.
+    pub fn progress(&self) -> Progress {
+        let best_cost = f64::from_bits(self.state.best_cost.load(Ordering::Relaxed));
+        Progress {
+            num_states: self.state.num_states.load(Ordering::Relaxed),
+            best_cost: if best_cost.is_finite() { Some(best_cost) } else { None },
+        }
     }
 }
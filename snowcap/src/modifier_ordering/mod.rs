@@ -35,6 +35,9 @@ pub use unordered::NoOrdering;
 mod random;
 pub use random::RandomOrdering;
 
+mod roles;
+pub use roles::sort_by_router_roles;
+
 use std::cmp::Ordering;
 
 /// # ModifierOrdering
@@ -0,0 +1,41 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Role-based Modifier Ordering
+
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{Network, RouterRole};
+
+/// Sort the modifiers based on the roles of the routers they configure, e.g., to reconfigure all
+/// core routers before the border routers. The rank of a modifier is the position (in `roles`) of
+/// the first role held by any of its routers. Modifiers on routers without any of the roles are
+/// placed at the end. The sort is stable, which means that modifiers with the same rank keep their
+/// relative ordering. Hence, this can be combined with any other
+/// [`ModifierOrdering`](super::ModifierOrdering), by sorting with that ordering first.
+pub fn sort_by_router_roles(
+    net: &Network,
+    modifiers: &mut Vec<ConfigModifier>,
+    roles: &[RouterRole],
+) {
+    modifiers.sort_by_key(|m| {
+        m.routers()
+            .into_iter()
+            .filter_map(|r| roles.iter().position(|role| net.has_router_role(r, *role)))
+            .min()
+            .unwrap_or(roles.len())
+    })
+}
@@ -19,7 +19,9 @@
 
 use super::{CompleteOrdering, ModifierOrdering};
 use crate::netsim::config::ConfigExpr::{
-    self, BgpRouteMap, BgpSession, IgpLinkWeight, StaticRoute,
+    self, BgpAlwaysCompareMed, BgpClusterId, BgpGracefulRestart, BgpGracefulShutdown,
+    BgpRelationship, BgpRouteMap, BgpSession, IgpLinkWeight, OspfArea, OspfStubArea,
+    RedistributeStatic, Roa, RovPolicy, StaticRoute,
 };
 use crate::netsim::config::ConfigModifier::{self, Insert, Remove, Update};
 use crate::netsim::BgpSessionType::*;
@@ -29,7 +31,9 @@ use std::cmp::Ordering;
 /// #Simple Ordering
 /// The following ordering is used:
 /// - Modification type: Insert < Update < Remove
-/// - Expression type: IgpLinkWeight < BgpSession < BgpLocalPref
+/// - Expression type: StaticRoute < IgpLinkWeight < BgpSession < BgpRouteMap < Roa < RovPolicy <
+///   BgpGracefulRestart < BgpGracefulShutdown < BgpRelationship < BgpAlwaysCompareMed < BgpClusterId <
+///   RedistributeStatic < OspfArea < OspfStubArea
 /// - Values of each expression.
 pub struct SimpleOrdering {}
 
@@ -98,6 +102,56 @@ fn order_expr(a: &ConfigExpr, b: &ConfigExpr) -> Ordering {
             Ordering::Equal => ma.order().cmp(&mb.order),
             o => o,
         },
+        (Roa { router: ra, prefix: pa, .. }, Roa { router: rb, prefix: pb, .. }) => {
+            match pa.0.cmp(&pb.0) {
+                Ordering::Equal => ra.cmp(rb),
+                o => o,
+            }
+        }
+        (OspfStubArea { area: aa }, OspfStubArea { area: ab }) => aa.cmp(ab),
+        (OspfStubArea { .. }, _) => Ordering::Greater,
+        (_, OspfStubArea { .. }) => Ordering::Less,
+        (
+            OspfArea { router_a: ra, router_b: rb, area: aa },
+            OspfArea { router_a: ta, router_b: tb, area: ab },
+        ) => match aa.cmp(ab) {
+            Ordering::Equal => order_two_routers(ra, ta, rb, tb),
+            o => o,
+        },
+        (OspfArea { .. }, _) => Ordering::Greater,
+        (_, OspfArea { .. }) => Ordering::Less,
+        (RedistributeStatic { router: ra }, RedistributeStatic { router: rb }) => ra.cmp(rb),
+        (RedistributeStatic { .. }, _) => Ordering::Greater,
+        (_, RedistributeStatic { .. }) => Ordering::Less,
+        (
+            BgpClusterId { router: ra, cluster_id: ca },
+            BgpClusterId { router: rb, cluster_id: cb },
+        ) => order_two_routers(ra, rb, ca, cb),
+        (BgpClusterId { .. }, _) => Ordering::Greater,
+        (_, BgpClusterId { .. }) => Ordering::Less,
+        (BgpAlwaysCompareMed { router: ra }, BgpAlwaysCompareMed { router: rb }) => ra.cmp(rb),
+        (BgpAlwaysCompareMed { .. }, _) => Ordering::Greater,
+        (_, BgpAlwaysCompareMed { .. }) => Ordering::Less,
+        (BgpRelationship { neighbor: na, .. }, BgpRelationship { neighbor: nb, .. }) => na.cmp(nb),
+        (BgpRelationship { .. }, _) => Ordering::Greater,
+        (_, BgpRelationship { .. }) => Ordering::Less,
+        (
+            BgpGracefulShutdown { router: ra, neighbor: na },
+            BgpGracefulShutdown { router: rb, neighbor: nb },
+        ) => order_two_routers(ra, rb, na, nb),
+        (BgpGracefulShutdown { .. }, _) => Ordering::Greater,
+        (_, BgpGracefulShutdown { .. }) => Ordering::Less,
+        (
+            BgpGracefulRestart { router: ra, neighbor: na },
+            BgpGracefulRestart { router: rb, neighbor: nb },
+        ) => order_two_routers(ra, rb, na, nb),
+        (BgpGracefulRestart { .. }, _) => Ordering::Greater,
+        (_, BgpGracefulRestart { .. }) => Ordering::Less,
+        (Roa { .. }, RovPolicy { .. }) => Ordering::Less,
+        (RovPolicy { .. }, Roa { .. }) => Ordering::Greater,
+        (RovPolicy { router: ra, .. }, RovPolicy { router: rb, .. }) => ra.cmp(rb),
+        (Roa { .. }, _) | (RovPolicy { .. }, _) => Ordering::Greater,
+        (_, Roa { .. }) | (_, RovPolicy { .. }) => Ordering::Less,
     }
 }
 
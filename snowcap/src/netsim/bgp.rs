@@ -17,8 +17,10 @@
 
 //! Module containing definitions for BGP
 
-use crate::netsim::{AsId, LinkWeight, Prefix, RouterId};
+use crate::netsim::{Afi, AsId, LinkWeight, Prefix, RouterId};
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
 
 /// Bgp Route
 /// The following attributes are omitted
@@ -37,11 +39,22 @@ pub struct BgpRoute {
     pub local_pref: Option<u32>,
     /// MED (Multi-Exit Discriminator)
     pub med: Option<u32>,
-    /// Community
-    pub community: Option<u32>,
+    /// Communities attached to the route
+    pub communities: BTreeSet<u32>,
+    /// ORIGINATOR_ID, set by the first route reflector to the router from which it learned the
+    /// route. It is only present on reflected routes.
+    pub originator_id: Option<RouterId>,
+    /// CLUSTER_LIST, containing the cluster IDs of all route reflectors that have reflected the
+    /// route, where the most recent one is first.
+    pub cluster_list: Vec<RouterId>,
 }
 
 impl BgpRoute {
+    /// Returns the address family of the route, which is given by its prefix.
+    pub fn afi(&self) -> Afi {
+        self.prefix.afi()
+    }
+
     /// Applies the default values for any non-mandatory field
     #[allow(dead_code)]
     pub(crate) fn apply_default(&mut self) {
@@ -57,7 +70,9 @@ impl BgpRoute {
             next_hop: self.next_hop,
             local_pref: Some(self.local_pref.unwrap_or(100)),
             med: Some(self.med.unwrap_or(0)),
-            community: Some(self.community.unwrap_or(0)),
+            communities: self.communities.clone(),
+            originator_id: self.originator_id,
+            cluster_list: self.cluster_list.clone(),
         }
     }
 }
@@ -71,7 +86,9 @@ impl PartialEq for BgpRoute {
             && s.next_hop == o.next_hop
             && s.local_pref == o.local_pref
             && s.med == o.med
-            && s.community == o.community
+            && s.communities == o.communities
+            && s.originator_id == o.originator_id
+            && s.cluster_list == o.cluster_list
     }
 }
 
@@ -83,12 +100,14 @@ impl std::hash::Hash for BgpRoute {
         s.next_hop.hash(state);
         s.local_pref.hash(state);
         s.med.hash(state);
-        s.community.hash(state);
+        s.communities.hash(state);
+        s.originator_id.hash(state);
+        s.cluster_list.hash(state);
     }
 }
 
 /// Type of a BGP session
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum BgpSessionType {
     /// iBGP session with a peer (or from a coient with a Route Reflector)
     IBgpPeer,
@@ -110,6 +129,50 @@ impl BgpSessionType {
     }
 }
 
+/// Business relationship with an eBGP neighbor (Gao-Rexford model), seen from the perspective of
+/// the own AS. The relationship determines the default export policy: routes learned from a
+/// customer are exported to everyone, while routes learned from a peer or a provider are only
+/// exported to customers. Neighbors without a relationship are not restricted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BgpRelationship {
+    /// The neighbor is a customer, paying for transit.
+    Customer,
+    /// The neighbor is a settlement-free peer.
+    Peer,
+    /// The neighbor is a provider, from which transit is bought.
+    Provider,
+}
+
+impl BgpRelationship {
+    /// returns true if the neighbor is a customer
+    pub fn is_customer(&self) -> bool {
+        matches!(self, Self::Customer)
+    }
+}
+
+/// Route Origin Validation (ROV) policy of a router, describing how RPKI-invalid routes are
+/// treated. Routers without a ROV policy treat all routes as if they were valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RovPolicy {
+    /// Drop all routes learned over eBGP that are RPKI-invalid.
+    RejectInvalid,
+    /// Keep RPKI-invalid routes learned over eBGP, but overwrite their local preference with the
+    /// given value, such that they are only used as a last resort.
+    DepreferInvalid(u32),
+}
+
+/// Validation state of a BGP route, based on the Route Origin Authorizations (ROAs) known to the
+/// router.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RovState {
+    /// A ROA exists for the prefix, and the origin AS of the route matches the authorized AS.
+    Valid,
+    /// A ROA exists for the prefix, but the origin AS of the route does not match.
+    Invalid,
+    /// No ROA exists for the prefix.
+    NotFound,
+}
+
 /// BGP Events
 #[derive(Debug, Clone, PartialEq)]
 pub enum BgpEvent {
@@ -117,6 +180,9 @@ pub enum BgpEvent {
     Withdraw(Prefix),
     /// Update a route, or add a new one.
     Update(BgpRoute),
+    /// Request the peer to send its route for the prefix again (ROUTE-REFRESH). This is sent when
+    /// the incoming policy of an existing session changes, instead of resetting the session.
+    RouteRefresh(Prefix),
 }
 
 impl BgpEvent {
@@ -125,6 +191,7 @@ impl BgpEvent {
         match self {
             Self::Withdraw(p) => *p,
             Self::Update(r) => r.prefix,
+            Self::RouteRefresh(p) => *p,
         }
     }
 }
@@ -150,50 +217,102 @@ impl PartialEq for BgpRibEntry {
     }
 }
 
-impl PartialOrd for BgpRibEntry {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+impl BgpRibEntry {
+    /// Returns the neighbor AS, from which the route was learned (i.e., the first AS on the AS
+    /// path), or `None` if the route was originated in the own AS.
+    pub(crate) fn neighbor_as(&self) -> Option<AsId> {
+        self.route.as_path.first().copied()
+    }
+
+    /// Compare two entries in the BGP decision process, where the better route is greater. The MED
+    /// is only compared if both routes are learned from the same neighbor AS, or if
+    /// `always_compare_med` is set. Hence, without `always_compare_med`, this comparison is not
+    /// transitive. Use [`BgpRibEntry::select_best`] to select the best route out of many.
+    pub(crate) fn compare(&self, other: &Self, always_compare_med: bool) -> Ordering {
         let s = self.route.clone_default();
         let o = other.route.clone_default();
 
         match s.local_pref.unwrap().cmp(&o.local_pref.unwrap()) {
             Ordering::Equal => {}
-            o => return Some(o),
+            o => return o,
         }
 
         match s.as_path.len().cmp(&o.as_path.len()) {
             Ordering::Equal => {}
-            Ordering::Greater => return Some(Ordering::Less),
-            Ordering::Less => return Some(Ordering::Greater),
+            Ordering::Greater => return Ordering::Less,
+            Ordering::Less => return Ordering::Greater,
         }
 
-        match s.med.unwrap().cmp(&o.med.unwrap()) {
-            Ordering::Equal => {}
-            Ordering::Greater => return Some(Ordering::Less),
-            Ordering::Less => return Some(Ordering::Greater),
+        if always_compare_med || self.neighbor_as() == other.neighbor_as() {
+            match s.med.unwrap().cmp(&o.med.unwrap()) {
+                Ordering::Equal => {}
+                Ordering::Greater => return Ordering::Less,
+                Ordering::Less => return Ordering::Greater,
+            }
         }
 
         if self.from_type.is_ebgp() && other.from_type.is_ibgp() {
-            return Some(Ordering::Greater);
+            return Ordering::Greater;
         } else if self.from_type.is_ibgp() && other.from_type.is_ebgp() {
-            return Some(Ordering::Less);
+            return Ordering::Less;
         }
 
         match self.igp_cost.unwrap().partial_cmp(&other.igp_cost.unwrap()) {
             Some(Ordering::Equal) | None => {}
-            Some(Ordering::Greater) => return Some(Ordering::Less),
-            Some(Ordering::Less) => return Some(Ordering::Greater),
+            Some(Ordering::Greater) => return Ordering::Less,
+            Some(Ordering::Less) => return Ordering::Greater,
         }
 
         match s.next_hop.cmp(&o.next_hop) {
             Ordering::Equal => {}
-            Ordering::Greater => return Some(Ordering::Less),
-            Ordering::Less => return Some(Ordering::Greater),
+            Ordering::Greater => return Ordering::Less,
+            Ordering::Less => return Ordering::Greater,
+        }
+
+        match s.cluster_list.len().cmp(&o.cluster_list.len()) {
+            Ordering::Equal => {}
+            Ordering::Greater => return Ordering::Less,
+            Ordering::Less => return Ordering::Greater,
         }
 
         match self.from_id.cmp(&other.from_id) {
-            Ordering::Equal => Some(Ordering::Equal),
-            Ordering::Greater => Some(Ordering::Less),
-            Ordering::Less => Some(Ordering::Greater),
+            Ordering::Equal => Ordering::Equal,
+            Ordering::Greater => Ordering::Less,
+            Ordering::Less => Ordering::Greater,
         }
     }
+
+    /// Select the best route out of all `entries`. Unless `always_compare_med` is set, the MED is
+    /// only compared among routes from the same neighbor AS. To make the result independent of the
+    /// order of `entries` (deterministic MED), the best route of every neighbor AS is selected
+    /// first, and only then, the best routes of all neighbor ASes are compared with each other.
+    pub(crate) fn select_best(
+        entries: impl IntoIterator<Item = Self>,
+        always_compare_med: bool,
+    ) -> Option<Self> {
+        let mut best_per_as: HashMap<Option<AsId>, Self> = HashMap::new();
+        for entry in entries {
+            let key = if always_compare_med { None } else { entry.neighbor_as() };
+            match best_per_as.get(&key) {
+                Some(best) if entry.compare(best, always_compare_med) != Ordering::Greater => {}
+                _ => {
+                    best_per_as.insert(key, entry);
+                }
+            }
+        }
+        best_per_as.into_iter().map(|(_, e)| e).fold(None, |best, entry| match best {
+            Some(best) if entry.compare(&best, always_compare_med) != Ordering::Greater => {
+                Some(best)
+            }
+            _ => Some(entry),
+        })
+    }
+}
+
+impl PartialOrd for BgpRibEntry {
+    /// Compare two entries in the BGP decision process, where the MED is only compared for routes
+    /// from the same neighbor AS.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.compare(other, false))
+    }
 }
@@ -0,0 +1,318 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Centrality and Graph Analysis
+//!
+//! This module provides graph-analysis helpers on the IGP topology, like betweenness and closeness
+//! centrality, articulation points and k-cores. They are used to select realistic route reflectors
+//! when generating scenarios, or to estimate how many routers are affected when a router fails.
+//!
+//! The analysis is performed on an undirected graph. [`Network::igp_analysis`] builds this graph
+//! from all internal routers, with an edge for every link with finite IGP weight. If the weights of
+//! both directions differ, the smaller one is used.
+
+use crate::netsim::{LinkWeight, Network, RouterId};
+
+use petgraph::visit::EdgeRef;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Relative tolerance for considering two path costs as equal.
+const COST_TOLERANCE: LinkWeight = 1e-6;
+
+/// # Graph Analysis
+///
+/// Undirected, weighted graph of routers, providing centrality measures and structural properties.
+/// The nodes are kept in the order given when creating the graph, and all results listing routers
+/// are sorted by their id.
+///
+/// ```rust
+/// # use snowcap::netsim::centrality::GraphAnalysis;
+/// let (a, b, c) = (0.into(), 1.into(), 2.into());
+/// let g = GraphAnalysis::new(vec![a, b, c], vec![(a, b, 1.0), (b, c, 1.0)]);
+/// assert_eq!(g.articulation_points(), vec![b]);
+/// assert_eq!(g.betweenness()[&b], 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct GraphAnalysis {
+    nodes: Vec<RouterId>,
+    adj: HashMap<RouterId, Vec<(RouterId, LinkWeight)>>,
+}
+
+impl GraphAnalysis {
+    /// Create the graph from a set of nodes and undirected edges. Edges with infinite weight, or
+    /// with an endpoint not in `nodes`, are ignored. Of multiple edges between the same two nodes,
+    /// only the one with the smallest weight is kept.
+    pub fn new(
+        nodes: impl IntoIterator<Item = RouterId>,
+        edges: impl IntoIterator<Item = (RouterId, RouterId, LinkWeight)>,
+    ) -> Self {
+        let nodes: Vec<RouterId> = nodes.into_iter().collect();
+        let mut adj: HashMap<RouterId, Vec<(RouterId, LinkWeight)>> =
+            nodes.iter().map(|n| (*n, Vec::new())).collect();
+        for (a, b, w) in edges {
+            if a == b || w.is_infinite() || !adj.contains_key(&a) || !adj.contains_key(&b) {
+                continue;
+            }
+            for (x, y) in [(a, b), (b, a)].iter() {
+                let neighbors = adj.get_mut(x).unwrap();
+                match neighbors.iter_mut().find(|(n, _)| n == y) {
+                    Some((_, old)) => *old = old.min(w),
+                    None => neighbors.push((*y, w)),
+                }
+            }
+        }
+        Self { nodes, adj }
+    }
+
+    /// Create the graph from the IGP topology of all internal routers of the network. See the
+    /// module documentation for details.
+    pub fn from_network(net: &Network) -> Self {
+        let topo = net.get_topology();
+        let edges = net
+            .get_routers()
+            .into_iter()
+            .flat_map(|r| topo.edges(r).map(|e| (e.source(), e.target(), *e.weight())))
+            .collect::<Vec<_>>();
+        Self::new(net.get_routers(), edges)
+    }
+
+    /// Returns all nodes of the graph.
+    pub fn nodes(&self) -> &[RouterId] {
+        &self.nodes
+    }
+
+    /// Returns the number of neighbors of the router (0 if the router is not in the graph).
+    pub fn degree(&self, router: RouterId) -> usize {
+        self.adj.get(&router).map(|n| n.len()).unwrap_or(0)
+    }
+
+    /// Returns the shortest-path distance from the source to every reachable node (including the
+    /// source itself).
+    pub fn distances(&self, source: RouterId) -> HashMap<RouterId, LinkWeight> {
+        self.dijkstra(source).0.into_iter().map(|(n, (d, _))| (n, d)).collect()
+    }
+
+    /// Closeness centrality of every node: the number of other reachable nodes divided by the sum
+    /// of their distances. Isolated nodes have closeness 0.
+    pub fn closeness(&self) -> HashMap<RouterId, f64> {
+        self.nodes
+            .iter()
+            .map(|n| {
+                let distances = self.distances(*n);
+                let total: f64 = distances.values().map(|d| *d as f64).sum();
+                let closeness =
+                    if total > 0.0 { (distances.len() - 1) as f64 / total } else { 0.0 };
+                (*n, closeness)
+            })
+            .collect()
+    }
+
+    /// Betweenness centrality of every node (not normalized): the sum over all pairs of other nodes
+    /// of the fraction of shortest paths between them that traverse the node. Equal-cost paths are
+    /// all considered. This uses the algorithm of Brandes (*A faster algorithm for betweenness
+    /// centrality*, 2001).
+    pub fn betweenness(&self) -> HashMap<RouterId, f64> {
+        let mut result: HashMap<RouterId, f64> = self.nodes.iter().map(|n| (*n, 0.0)).collect();
+        for source in self.nodes.iter() {
+            let (info, order) = self.dijkstra(*source);
+            // number of shortest paths and predecessors
+            let mut sigma: HashMap<RouterId, f64> = HashMap::new();
+            let mut preds: HashMap<RouterId, Vec<RouterId>> = HashMap::new();
+            sigma.insert(*source, 1.0);
+            for node in order.iter().skip(1) {
+                let dist = info[node].0;
+                let node_preds: Vec<RouterId> = self.adj[node]
+                    .iter()
+                    .filter(|(p, w)| {
+                        info.get(p).map(|(d, _)| cost_eq(*d + w, dist)).unwrap_or(false)
+                    })
+                    .map(|(p, _)| *p)
+                    .collect();
+                sigma.insert(*node, node_preds.iter().map(|p| sigma[p]).sum());
+                preds.insert(*node, node_preds);
+            }
+            // accumulate the dependencies in reverse order
+            let mut delta: HashMap<RouterId, f64> = HashMap::new();
+            for node in order.iter().rev() {
+                let d = delta.get(node).copied().unwrap_or(0.0);
+                for p in preds.get(node).into_iter().flatten() {
+                    *delta.entry(*p).or_insert(0.0) += sigma[p] / sigma[node] * (1.0 + d);
+                }
+                if node != source {
+                    *result.get_mut(node).unwrap() += d;
+                }
+            }
+        }
+        // every pair was counted twice, since the graph is undirected.
+        result.values_mut().for_each(|v| *v /= 2.0);
+        result
+    }
+
+    /// Returns all articulation points (cut vertices), i.e., routers whose removal disconnects
+    /// the graph, sorted by their id.
+    pub fn articulation_points(&self) -> Vec<RouterId> {
+        let mut state = DfsState::default();
+        for node in self.nodes.iter() {
+            if !state.discovery.contains_key(node) {
+                self.articulation_dfs(*node, None, &mut state);
+            }
+        }
+        let mut result: Vec<RouterId> = state.articulation.into_iter().collect();
+        result.sort();
+        result
+    }
+
+    /// Returns the core number of every node. The core number of a node is the largest `k`, such
+    /// that the node is part of the k-core (the maximal subgraph in which every node has degree
+    /// at least `k`).
+    pub fn core_numbers(&self) -> HashMap<RouterId, usize> {
+        let mut degree: HashMap<RouterId, usize> =
+            self.nodes.iter().map(|n| (*n, self.degree(*n))).collect();
+        let mut remaining: HashSet<RouterId> = self.nodes.iter().cloned().collect();
+        let mut result: HashMap<RouterId, usize> = HashMap::new();
+        let mut k = 0;
+        while !remaining.is_empty() {
+            // remove the node with the smallest degree (the smallest id on ties)
+            let node = *remaining.iter().min_by_key(|n| (degree[*n], **n)).unwrap();
+            k = k.max(degree[&node]);
+            result.insert(node, k);
+            remaining.remove(&node);
+            for (n, _) in self.adj[&node].iter() {
+                if remaining.contains(n) {
+                    *degree.get_mut(n).unwrap() -= 1;
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns all nodes of the k-core, sorted by their id.
+    pub fn k_core(&self, k: usize) -> Vec<RouterId> {
+        let mut result: Vec<RouterId> =
+            self.core_numbers().into_iter().filter(|(_, c)| *c >= k).map(|(n, _)| n).collect();
+        result.sort();
+        result
+    }
+
+    /// Dijkstra from the source. Returns the distance and the predecessor of every reachable node,
+    /// and the nodes in the order of increasing distance.
+    #[allow(clippy::type_complexity)]
+    fn dijkstra(
+        &self,
+        source: RouterId,
+    ) -> (HashMap<RouterId, (LinkWeight, Option<RouterId>)>, Vec<RouterId>) {
+        let mut info: HashMap<RouterId, (LinkWeight, Option<RouterId>)> = HashMap::new();
+        let mut order: Vec<RouterId> = Vec::new();
+        let mut best: HashMap<RouterId, LinkWeight> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        if !self.adj.contains_key(&source) {
+            return (info, order);
+        }
+        best.insert(source, 0.0);
+        heap.push(HeapEntry { cost: 0.0, node: source, pred: None });
+        while let Some(HeapEntry { cost, node, pred }) = heap.pop() {
+            if info.contains_key(&node) {
+                continue;
+            }
+            info.insert(node, (cost, pred));
+            order.push(node);
+            for (n, w) in self.adj[&node].iter() {
+                let new_cost = cost + w;
+                if !info.contains_key(n) && best.get(n).map(|c| new_cost < *c).unwrap_or(true) {
+                    best.insert(*n, new_cost);
+                    heap.push(HeapEntry { cost: new_cost, node: *n, pred: Some(node) });
+                }
+            }
+        }
+        (info, order)
+    }
+
+    /// Recursive DFS of the algorithm by Hopcroft and Tarjan for finding articulation points.
+    fn articulation_dfs(&self, node: RouterId, parent: Option<RouterId>, state: &mut DfsState) {
+        state.time += 1;
+        state.discovery.insert(node, state.time);
+        state.low.insert(node, state.time);
+        let mut children = 0;
+        for (n, _) in self.adj[&node].iter() {
+            if Some(*n) == parent {
+                continue;
+            }
+            if let Some(d) = state.discovery.get(n).copied() {
+                let low = state.low[&node].min(d);
+                state.low.insert(node, low);
+            } else {
+                children += 1;
+                self.articulation_dfs(*n, Some(node), state);
+                let low = state.low[&node].min(state.low[n]);
+                state.low.insert(node, low);
+                if parent.is_some() && state.low[n] >= state.discovery[&node] {
+                    state.articulation.insert(node);
+                }
+            }
+        }
+        if parent.is_none() && children > 1 {
+            state.articulation.insert(node);
+        }
+    }
+}
+
+impl Network {
+    /// Returns the [`GraphAnalysis`] of the IGP topology of all internal routers. See the
+    /// [`centrality`](crate::netsim::centrality) module for details.
+    pub fn igp_analysis(&self) -> GraphAnalysis {
+        GraphAnalysis::from_network(self)
+    }
+}
+
+#[derive(Debug, Default)]
+struct DfsState {
+    time: usize,
+    discovery: HashMap<RouterId, usize>,
+    low: HashMap<RouterId, usize>,
+    articulation: HashSet<RouterId>,
+}
+
+#[derive(Debug, PartialEq)]
+struct HeapEntry {
+    cost: LinkWeight,
+    node: RouterId,
+    pred: Option<RouterId>,
+}
+
+impl Eq for HeapEntry {}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reverse the order for a min-heap, and break ties by the node id
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn cost_eq(a: LinkWeight, b: LinkWeight) -> bool {
+    (a - b).abs() <= COST_TOLERANCE * b.abs().max(1.0)
+}
@@ -0,0 +1,577 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Device Commands
+//!
+//! This module translates a [`ConfigModifier`] into the commands, which an operator enters on the
+//! devices, either for [FRRouting](https://frrouting.org) or for Cisco IOS (see [`CliFlavor`]).
+//! The result can be pasted into change tickets. The functions are also available as
+//! [`config::modifier_to_frr`](crate::netsim::config::modifier_to_frr) and
+//! [`config::modifier_to_cisco`](crate::netsim::config::modifier_to_cisco).
+//!
+//! The commands are generated only from the network and the modifier, without keeping any state.
+//! Therefore, the addresses follow the same convention as the runtime system:
+//!
+//! - The loopback address of internal router `x` is `10.0.x.1`, and the one of external router `x`
+//!   is `(100 + x).0.0.1`.
+//! - The `i`-th link (see [`Network::links_symmetric`]) from router `a` to router `b` uses the
+//!   network `10.1.i.0/24`, where `a` has address `10.1.i.1`, and `b` has address `10.1.i.2`. The
+//!   interface of a router is numbered by the order of its links.
+//! - iBGP sessions are established between loopback addresses, and eBGP sessions between the
+//!   addresses of the link. The routers have the peer groups `internal` and `external`.
+//! - Prefixes are written with their address range (see [`Network::set_prefix_range`]). Prefixes
+//!   without an address range are written as a placeholder `<prefix id>`.
+//! - All route maps of a router in the same direction form a single route map named
+//!   `<router>-in` (or `<router>-out`), where the order is used as the sequence number. Routes that
+//!   match no route map are allowed, which is why the route map ends with an entry allowing
+//!   everything (with sequence number 65535).
+//!
+//! Configuration which cannot be expressed on the device is written as a comment (starting with
+//! `!`), such that it is not lost in the change ticket.
+
+use crate::netsim::config::{ConfigExpr, ConfigModifier};
+use crate::netsim::route_map::{RouteMap, RouteMapDirection, RouteMapMatch, RouteMapSet};
+use crate::netsim::{
+    printer, BgpSessionType, Network, NetworkDevice, NetworkError, Prefix, RouterId,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Sequence number of the route map entry which allows all routes not matched before.
+const ROUTE_MAP_PERMIT_ALL: usize = 65535;
+
+/// Command-line dialect of the devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CliFlavor {
+    /// FRRouting (`vtysh`)
+    Frr,
+    /// Cisco IOS
+    CiscoIos,
+}
+
+/// Returns the commands to apply the modifier in FRRouting, as a single string with one block per
+/// device (see [`modifier_to_cli`]).
+pub fn modifier_to_frr(net: &Network, modifier: &ConfigModifier) -> Result<String, NetworkError> {
+    modifier_to_cli(net, modifier, CliFlavor::Frr)
+}
+
+/// Returns the commands to apply the modifier in Cisco IOS, as a single string with one block per
+/// device (see [`modifier_to_cli`]).
+pub fn modifier_to_cisco(net: &Network, modifier: &ConfigModifier) -> Result<String, NetworkError> {
+    modifier_to_cli(net, modifier, CliFlavor::CiscoIos)
+}
+
+/// Returns the commands to apply the modifier, as a single string. For every device, the block
+/// starts with a comment naming the device, and the commands are wrapped in `configure terminal`
+/// and `end`. If the modifier requires no commands, the string is empty.
+pub fn modifier_to_cli(
+    net: &Network,
+    modifier: &ConfigModifier,
+    flavor: CliFlavor,
+) -> Result<String, NetworkError> {
+    let mut blocks: Vec<String> = Vec::new();
+    for (router, cmds) in device_commands(net, modifier, flavor)? {
+        blocks.push(format!(
+            "! {}\nconfigure terminal\n{}\nend\n",
+            net.get_router_name(router)?,
+            cmds.join("\n")
+        ));
+    }
+    Ok(blocks.join("\n"))
+}
+
+/// Returns the commands to apply the modifier for every device, in the order in which they should
+/// be applied.
+pub fn device_commands(
+    net: &Network,
+    modifier: &ConfigModifier,
+    flavor: CliFlavor,
+) -> Result<Vec<(RouterId, Vec<String>)>, NetworkError> {
+    let cli = Cli { net, flavor };
+    let cmds = match modifier {
+        ConfigModifier::Insert(expr) => cli.insert(expr)?,
+        ConfigModifier::Remove(expr) => cli.remove(expr)?,
+        ConfigModifier::Update { from, to } => cli.update(from, to)?,
+    };
+
+    // merge the commands of the same device, keeping the order of the first occurrence.
+    let mut result: Vec<(RouterId, Vec<String>)> = Vec::new();
+    for (router, c) in cmds.into_iter().filter(|(_, c)| !c.is_empty()) {
+        match result.iter_mut().find(|(r, _)| *r == router) {
+            Some((_, existing)) => existing.extend(c),
+            None => result.push((router, c)),
+        }
+    }
+    Ok(result)
+}
+
+type Commands = Vec<(RouterId, Vec<String>)>;
+
+struct Cli<'a> {
+    net: &'a Network,
+    flavor: CliFlavor,
+}
+
+impl<'a> Cli<'a> {
+    fn insert(&self, expr: &ConfigExpr) -> Result<Commands, NetworkError> {
+        Ok(match expr {
+            ConfigExpr::IgpLinkWeight { weight, .. } if weight.is_infinite() => vec![],
+            ConfigExpr::IgpLinkWeight { source, target, weight } => {
+                let (iface, addr, _) = self.link(*source, *target)?;
+                let mut cmds = vec![format!("interface {}", iface), format!("ip address {}", addr)];
+                if self.is_internal(*source) && self.is_internal(*target) {
+                    cmds.push(String::from("ip ospf 1 area 0"));
+                    cmds.push(format!("ip ospf cost {}", weight.round() as u32));
+                }
+                cmds.push(String::from("exit"));
+                vec![(*source, cmds)]
+            }
+            ConfigExpr::BgpSession { source, target, session_type } => vec![
+                (
+                    *source,
+                    self.bgp_neighbor(
+                        *source,
+                        *target,
+                        *session_type,
+                        *session_type == BgpSessionType::IBgpClient,
+                    )?,
+                ),
+                (*target, self.bgp_neighbor(*target, *source, *session_type, false)?),
+            ],
+            ConfigExpr::BgpRouteMap { router, direction, map } => {
+                let name = self.route_map_name(*router, *direction)?;
+                let mut cmds = self.route_map_entry(&name, map)?;
+                cmds.push(String::from("exit"));
+                cmds.push(format!("route-map {} permit {}", name, ROUTE_MAP_PERMIT_ALL));
+                cmds.push(String::from("exit"));
+                cmds.extend(self.bgp(*router, self.attach_route_map(&name, *direction))?);
+                vec![(*router, cmds)]
+            }
+            ConfigExpr::StaticRoute { router, prefix, target } => {
+                vec![(*router, vec![self.static_route(*router, *prefix, *target)?])]
+            }
+            ConfigExpr::RedistributeStatic { router } => {
+                vec![(*router, self.bgp(*router, vec![String::from("redistribute static")])?)]
+            }
+            ConfigExpr::BgpAlwaysCompareMed { router } => {
+                vec![(*router, self.bgp(*router, vec![String::from("bgp always-compare-med")])?)]
+            }
+            ConfigExpr::BgpClusterId { router, cluster_id } => {
+                let cmd = format!("bgp cluster-id {}", self.loopback(*cluster_id)?);
+                vec![(*router, self.bgp(*router, vec![cmd])?)]
+            }
+            ConfigExpr::BgpGracefulRestart { router, neighbor } => {
+                let cmd = match self.flavor {
+                    CliFlavor::Frr => "graceful-restart",
+                    CliFlavor::CiscoIos => "ha-mode graceful-restart",
+                };
+                let cmd = format!("neighbor {} {}", self.peer_addr(*router, *neighbor)?, cmd);
+                vec![(*router, self.bgp(*router, vec![cmd])?)]
+            }
+            ConfigExpr::OspfArea { router_a, router_b, area } => {
+                vec![
+                    (*router_a, self.ospf_area(*router_a, *router_b, area.0)?),
+                    (*router_b, self.ospf_area(*router_b, *router_a, area.0)?),
+                ]
+            }
+            _ => self.unsupported(&ConfigModifier::Insert(expr.clone()))?,
+        })
+    }
+
+    fn remove(&self, expr: &ConfigExpr) -> Result<Commands, NetworkError> {
+        Ok(match expr {
+            ConfigExpr::IgpLinkWeight { weight, .. } if weight.is_infinite() => vec![],
+            ConfigExpr::IgpLinkWeight { source, target, weight } => {
+                let (iface, addr, _) = self.link(*source, *target)?;
+                let mut cmds = vec![format!("interface {}", iface)];
+                if self.is_internal(*source) && self.is_internal(*target) {
+                    cmds.push(format!("no ip ospf cost {}", weight.round() as u32));
+                    cmds.push(String::from("no ip ospf 1 area 0"));
+                }
+                cmds.push(format!("no ip address {}", addr));
+                cmds.push(String::from("exit"));
+                vec![(*source, cmds)]
+            }
+            ConfigExpr::BgpSession { source, target, .. } => vec![
+                (*source, self.bgp(*source, vec![self.no_neighbor(*source, *target)?])?),
+                (*target, self.bgp(*target, vec![self.no_neighbor(*target, *source)?])?),
+            ],
+            ConfigExpr::BgpRouteMap { router, direction, map } => {
+                let name = self.route_map_name(*router, *direction)?;
+                vec![(
+                    *router,
+                    vec![format!("no route-map {} {} {}", name, state(map), map.order())],
+                )]
+            }
+            ConfigExpr::StaticRoute { router, prefix, target } => {
+                vec![(
+                    *router,
+                    vec![format!("no {}", self.static_route(*router, *prefix, *target)?)],
+                )]
+            }
+            ConfigExpr::RedistributeStatic { router } => {
+                vec![(*router, self.bgp(*router, vec![String::from("no redistribute static")])?)]
+            }
+            ConfigExpr::BgpAlwaysCompareMed { router } => {
+                vec![(*router, self.bgp(*router, vec![String::from("no bgp always-compare-med")])?)]
+            }
+            ConfigExpr::BgpClusterId { router, .. } => {
+                vec![(*router, self.bgp(*router, vec![String::from("no bgp cluster-id")])?)]
+            }
+            ConfigExpr::BgpGracefulRestart { router, neighbor } => {
+                let cmd = match self.flavor {
+                    CliFlavor::Frr => "graceful-restart",
+                    CliFlavor::CiscoIos => "ha-mode graceful-restart",
+                };
+                let cmd = format!("no neighbor {} {}", self.peer_addr(*router, *neighbor)?, cmd);
+                vec![(*router, self.bgp(*router, vec![cmd])?)]
+            }
+            ConfigExpr::OspfArea { router_a, router_b, .. } => {
+                // links without an area are part of the backbone
+                vec![
+                    (*router_a, self.ospf_area(*router_a, *router_b, 0)?),
+                    (*router_b, self.ospf_area(*router_b, *router_a, 0)?),
+                ]
+            }
+            _ => self.unsupported(&ConfigModifier::Remove(expr.clone()))?,
+        })
+    }
+
+    fn update(&self, from: &ConfigExpr, to: &ConfigExpr) -> Result<Commands, NetworkError> {
+        Ok(match (from, to) {
+            (
+                ConfigExpr::IgpLinkWeight { weight: old, .. },
+                ConfigExpr::IgpLinkWeight { source, target, weight: new },
+            ) if old.is_finite() && new.is_finite() => {
+                if !(self.is_internal(*source) && self.is_internal(*target)) {
+                    return Ok(vec![]);
+                }
+                let (iface, _, _) = self.link(*source, *target)?;
+                vec![(
+                    *source,
+                    vec![
+                        format!("interface {}", iface),
+                        format!("ip ospf cost {}", new.round() as u32),
+                        String::from("exit"),
+                    ],
+                )]
+            }
+            (ConfigExpr::IgpLinkWeight { .. }, ConfigExpr::IgpLinkWeight { .. }) => {
+                // enabling or disabling the link.
+                let mut cmds = self.remove(from)?;
+                cmds.extend(self.insert(to)?);
+                cmds
+            }
+            (
+                ConfigExpr::BgpSession { session_type: old, .. },
+                ConfigExpr::BgpSession { source, target, session_type: new },
+            ) => {
+                let cmd = format!(
+                    "neighbor {} route-reflector-client",
+                    self.peer_addr(*source, *target)?
+                );
+                match (old, new) {
+                    (_, BgpSessionType::IBgpClient) => {
+                        vec![(*source, self.bgp(*source, vec![cmd])?)]
+                    }
+                    (BgpSessionType::IBgpClient, _) => {
+                        vec![(*source, self.bgp(*source, vec![format!("no {}", cmd)])?)]
+                    }
+                    _ => vec![],
+                }
+            }
+            (
+                ConfigExpr::BgpRouteMap { map: old, .. },
+                ConfigExpr::BgpRouteMap { router, direction, map: new },
+            ) if old.state() == new.state() => {
+                let name = self.route_map_name(*router, *direction)?;
+                let old_stmts = self.route_map_statements(old)?;
+                let new_stmts = self.route_map_statements(new)?;
+                let mut cmds = vec![format!("route-map {} {} {}", name, state(new), new.order())];
+                cmds.extend(old_stmts.iter().filter(|s| !new_stmts.contains(s)).map(|s| no(s)));
+                cmds.extend(new_stmts.iter().filter(|s| !old_stmts.contains(s)).cloned());
+                cmds.push(String::from("exit"));
+                vec![(*router, cmds)]
+            }
+            (
+                ConfigExpr::BgpRouteMap { .. },
+                ConfigExpr::BgpRouteMap { router, direction, map },
+            ) => {
+                // the state cannot be changed in place
+                let name = self.route_map_name(*router, *direction)?;
+                let mut cmds = self.remove(from)?;
+                let mut entry = self.route_map_entry(&name, map)?;
+                entry.push(String::from("exit"));
+                cmds.push((*router, entry));
+                cmds
+            }
+            (ConfigExpr::StaticRoute { .. }, ConfigExpr::StaticRoute { .. })
+            | (ConfigExpr::BgpClusterId { .. }, ConfigExpr::BgpClusterId { .. }) => {
+                // add the new one before removing the old one, such that there is no gap.
+                let mut cmds = self.insert(to)?;
+                if matches!(from, ConfigExpr::StaticRoute { .. }) {
+                    cmds.extend(self.remove(from)?);
+                }
+                cmds
+            }
+            (ConfigExpr::OspfArea { .. }, ConfigExpr::OspfArea { .. }) => self.insert(to)?,
+            _ => {
+                self.unsupported(&ConfigModifier::Update { from: from.clone(), to: to.clone() })?
+            }
+        })
+    }
+
+    /// Write the modifier as a comment on all routers it affects.
+    fn unsupported(&self, modifier: &ConfigModifier) -> Result<Commands, NetworkError> {
+        let comment = format!("! not supported: {}", printer::config_modifier(self.net, modifier)?);
+        Ok(modifier.routers().into_iter().map(|r| (r, vec![comment.clone()])).collect())
+    }
+
+    /// Wrap the commands into the BGP configuration of the router.
+    fn bgp(&self, router: RouterId, cmds: Vec<String>) -> Result<Vec<String>, NetworkError> {
+        let mut result = vec![format!("router bgp {}", self.as_id(router)?)];
+        result.extend(cmds);
+        result.push(String::from("exit"));
+        Ok(result)
+    }
+
+    /// Commands to configure the BGP session with `neighbor` on `router`. If `rr_client`, then
+    /// `neighbor` is configured as route reflector client.
+    fn bgp_neighbor(
+        &self,
+        router: RouterId,
+        neighbor: RouterId,
+        session_type: BgpSessionType,
+        rr_client: bool,
+    ) -> Result<Vec<String>, NetworkError> {
+        let addr = self.peer_addr(router, neighbor)?;
+        let mut cmds = vec![format!("neighbor {} remote-as {}", addr, self.as_id(neighbor)?)];
+        if session_type.is_ibgp() {
+            cmds.push(format!("neighbor {} update-source {}", addr, self.loopback_iface()));
+            cmds.push(format!("neighbor {} peer-group internal", addr));
+        } else {
+            cmds.push(format!("neighbor {} peer-group external", addr));
+        }
+        if rr_client {
+            cmds.push(format!("neighbor {} route-reflector-client", addr));
+        }
+        self.bgp(router, cmds)
+    }
+
+    /// Command to remove the neighbor from the BGP configuration.
+    fn no_neighbor(&self, router: RouterId, neighbor: RouterId) -> Result<String, NetworkError> {
+        let addr = self.peer_addr(router, neighbor)?;
+        Ok(match self.flavor {
+            CliFlavor::Frr => format!("no neighbor {} remote-as {}", addr, self.as_id(neighbor)?),
+            CliFlavor::CiscoIos => format!("no neighbor {}", addr),
+        })
+    }
+
+    /// Returns the commands of a single route map entry, without leaving the route map node.
+    fn route_map_entry(&self, name: &str, map: &RouteMap) -> Result<Vec<String>, NetworkError> {
+        let mut cmds = vec![format!("route-map {} {} {}", name, state(map), map.order())];
+        cmds.extend(self.route_map_statements(map)?);
+        Ok(cmds)
+    }
+
+    /// Returns all match and set statements of the route map.
+    fn route_map_statements(&self, map: &RouteMap) -> Result<Vec<String>, NetworkError> {
+        let mut cmds = Vec::new();
+        for cond in map.conds() {
+            cmds.push(match (cond, self.flavor) {
+                (RouteMapMatch::Neighbor(n), CliFlavor::Frr) => {
+                    format!("match peer {}", self.loopback(*n)?)
+                }
+                (RouteMapMatch::NextHop(nh), CliFlavor::Frr) => {
+                    format!("match ip next-hop address {}", self.loopback(*nh)?)
+                }
+                _ => {
+                    format!("! not supported: match {}", printer::route_map_match(self.net, cond)?)
+                }
+            });
+        }
+        for set in map.actions() {
+            cmds.push(match set {
+                RouteMapSet::NextHop(nh) => format!("set ip next-hop {}", self.loopback(*nh)?),
+                RouteMapSet::LocalPref(lp) => format!("set local-preference {}", lp.unwrap_or(100)),
+                RouteMapSet::Med(med) => format!("set metric {}", med.unwrap_or(0)),
+                RouteMapSet::Community(Some(c)) => format!("set community {}", c),
+                RouteMapSet::Community(None) => String::from("set community none"),
+                RouteMapSet::AddCommunity(c) => format!("set community {} additive", c),
+                RouteMapSet::AddCommunities(l) => format!(
+                    "set community {} additive",
+                    l.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")
+                ),
+                RouteMapSet::PrependAsPath(a, n) => {
+                    format!("set as-path prepend {}", vec![a.0.to_string(); *n].join(" "))
+                }
+                _ => format!("! not supported: set {}", printer::route_map_set(self.net, set)?),
+            });
+        }
+        Ok(cmds)
+    }
+
+    /// Commands to apply the route map on all BGP neighbors.
+    fn attach_route_map(&self, name: &str, direction: RouteMapDirection) -> Vec<String> {
+        let dir = match direction {
+            RouteMapDirection::Incoming => "in",
+            RouteMapDirection::Outgoing => "out",
+        };
+        vec![
+            String::from("address-family ipv4 unicast"),
+            format!("neighbor internal route-map {} {}", name, dir),
+            format!("neighbor external route-map {} {}", name, dir),
+            String::from("exit-address-family"),
+        ]
+    }
+
+    fn route_map_name(
+        &self,
+        router: RouterId,
+        direction: RouteMapDirection,
+    ) -> Result<String, NetworkError> {
+        Ok(format!(
+            "{}-{}",
+            self.net.get_router_name(router)?,
+            match direction {
+                RouteMapDirection::Incoming => "in",
+                RouteMapDirection::Outgoing => "out",
+            }
+        ))
+    }
+
+    /// Command to configure a static route (without the `no`).
+    fn static_route(
+        &self,
+        router: RouterId,
+        prefix: Prefix,
+        target: RouterId,
+    ) -> Result<String, NetworkError> {
+        let (_, _, next_hop) = self.link(router, target)?;
+        let network = match (self.net.get_prefix_range(prefix), self.flavor) {
+            (Some(range), CliFlavor::Frr) => range.to_string(),
+            (Some(range), CliFlavor::CiscoIos) => {
+                format!("{} {}", range.addr(), netmask(range.prefix_len()))
+            }
+            (None, _) => format!("<prefix {}>", prefix.0),
+        };
+        Ok(format!("ip route {} {}", network, next_hop))
+    }
+
+    /// Commands to put the interface towards `neighbor` into an OSPF area.
+    fn ospf_area(
+        &self,
+        router: RouterId,
+        neighbor: RouterId,
+        area: u32,
+    ) -> Result<Vec<String>, NetworkError> {
+        let (iface, _, _) = self.link(router, neighbor)?;
+        Ok(vec![
+            format!("interface {}", iface),
+            format!("ip ospf 1 area {}", area),
+            String::from("exit"),
+        ])
+    }
+
+    /// Returns the interface name, the own address (formatted for the `ip address` command) and
+    /// the address of the neighbor of the link from `router` to `neighbor`.
+    fn link(
+        &self,
+        router: RouterId,
+        neighbor: RouterId,
+    ) -> Result<(String, String, String), NetworkError> {
+        let mut iface_idx = 0;
+        for (link_id, (a, b)) in self.net.links_symmetric().enumerate() {
+            if (*a, *b) == (router, neighbor) || (*b, *a) == (router, neighbor) {
+                let (own, other) = if *a == router { (1, 2) } else { (2, 1) };
+                let (iface, own) = match self.flavor {
+                    CliFlavor::Frr => {
+                        (format!("eth{}", iface_idx), format!("10.1.{}.{}/24", link_id, own))
+                    }
+                    CliFlavor::CiscoIos => (
+                        format!("GigabitEthernet0/{}", iface_idx),
+                        format!("10.1.{}.{} 255.255.255.0", link_id, own),
+                    ),
+                };
+                return Ok((iface, own, format!("10.1.{}.{}", link_id, other)));
+            }
+            if *a == router || *b == router {
+                iface_idx += 1;
+            }
+        }
+        Err(NetworkError::RoutersNotConnected(router, neighbor))
+    }
+
+    /// Address of `neighbor`, as seen by `router` for the BGP session. Internal routers peer using
+    /// their loopback address, while external routers peer with the address of the link.
+    fn peer_addr(&self, router: RouterId, neighbor: RouterId) -> Result<String, NetworkError> {
+        if self.is_internal(router) && self.is_internal(neighbor) {
+            self.loopback(neighbor)
+        } else {
+            let (_, _, addr) = self.link(router, neighbor)?;
+            Ok(addr)
+        }
+    }
+
+    fn loopback(&self, router: RouterId) -> Result<String, NetworkError> {
+        match self.net.get_device(router) {
+            NetworkDevice::InternalRouter(_) => Ok(format!("10.0.{}.1", router.index())),
+            NetworkDevice::ExternalRouter(_) => Ok(format!("{}.0.0.1", router.index() + 100)),
+            NetworkDevice::None => Err(NetworkError::DeviceNotFound(router)),
+        }
+    }
+
+    fn loopback_iface(&self) -> &'static str {
+        match self.flavor {
+            CliFlavor::Frr => "lo",
+            CliFlavor::CiscoIos => "Loopback0",
+        }
+    }
+
+    fn as_id(&self, router: RouterId) -> Result<u32, NetworkError> {
+        match self.net.get_device(router) {
+            NetworkDevice::InternalRouter(r) => Ok(r.as_id().0),
+            NetworkDevice::ExternalRouter(r) => Ok(r.as_id().0),
+            NetworkDevice::None => Err(NetworkError::DeviceNotFound(router)),
+        }
+    }
+
+    fn is_internal(&self, router: RouterId) -> bool {
+        matches!(self.net.get_device(router), NetworkDevice::InternalRouter(_))
+    }
+}
+
+fn state(map: &RouteMap) -> &'static str {
+    if map.state().is_allow() {
+        "permit"
+    } else {
+        "deny"
+    }
+}
+
+/// Negate a route map statement
+fn no(statement: &str) -> String {
+    if statement.starts_with('!') {
+        statement.to_string()
+    } else {
+        format!("no {}", statement)
+    }
+}
+
+fn netmask(len: u8) -> std::net::Ipv4Addr {
+    u32::MAX.checked_shl(32 - len as u32).unwrap_or(0).into()
+}
@@ -27,6 +27,16 @@
 //! - [`ConfigModifier`]: A modification of a single [`ConfigExpr`] in a configuration. A
 //!   modification can either be an insertion of a new expression, a removal of an existing
 //!   expression, or a moification of an existing expression.
+//! - [`ConfigMeta`]: Free-form metadata (name, ticket and comment) attached to a [`ConfigExpr`]
+//!   in a [`Config`]. The metadata is stored by the [`ConfigExprKey`], does not influence the
+//!   comparison of two configurations, and is carried over into the [`ConfigPatch`] when computing
+//!   the difference of two configurations. This way, the synthesized steps can be traced back to
+//!   the intent of the operator.
+//! - [`NamedConfigModifier`]: A [`ConfigModifier`] that references the routers by their name
+//!   instead of their ID, such that it can be stored and replayed on a re-created network.
+//!
+//! A [`ConfigModifier`] can be translated into the commands for the devices with
+//! [`modifier_to_frr`] and [`modifier_to_cisco`] (see [`cli`](crate::netsim::cli)).
 //!
 //! # Example Usage
 //!
@@ -71,11 +81,19 @@
 //! }
 //! ```
 
-use crate::netsim::bgp::BgpSessionType;
+pub use crate::netsim::cli::{modifier_to_cisco, modifier_to_frr};
+
+use crate::netsim::bgp::{BgpRelationship, BgpSessionType, RovPolicy};
 use crate::netsim::route_map::{RouteMap, RouteMapDirection};
-use crate::netsim::{ConfigError, LinkWeight, Prefix, RouterId};
+use crate::netsim::{
+    AsId, ConfigError, LinkWeight, Network, NetworkError, OspfArea, Prefix, RouterId,
+};
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
 
 /// # Network Configuration
 /// This struct represents the configuration of a network. It is made up of several *unordered*
@@ -88,10 +106,17 @@ use std::collections::{HashMap, HashSet};
 /// The `Config` struct contains only "unique" `ConfigExpr`. This means, that a config cannot have a
 /// expression to set a specific link weight to 1, and another expression setting the same link to
 /// 2.0.
-#[derive(Debug, Clone)]
+///
+/// When serialized, the expressions are stored as a list, sorted by their key, such that the
+/// same configuration always results in the same output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     /// All lines of configuration
+    #[serde(with = "serde_expr_map")]
     pub(crate) expr: HashMap<ConfigExprKey, ConfigExpr>,
+    /// Metadata attached to the lines of configuration
+    #[serde(with = "serde_key_map", default)]
+    pub(crate) meta: HashMap<ConfigExprKey, ConfigMeta>,
 }
 
 impl Default for Config {
@@ -103,7 +128,7 @@ impl Default for Config {
 impl Config {
     /// Create an empty configuration
     pub fn new() -> Self {
-        Self { expr: HashMap::new() }
+        Self { expr: HashMap::new(), meta: HashMap::new() }
     }
 
     /// Add a single configuration expression. This fails if a similar expression already exists.
@@ -117,6 +142,26 @@ impl Config {
         }
     }
 
+    /// Add a single configuration expression together with its metadata. This fails if a similar
+    /// expression already exists.
+    pub fn add_annotated(&mut self, expr: ConfigExpr, meta: ConfigMeta) -> Result<(), ConfigError> {
+        let key = expr.key();
+        self.add(expr)?;
+        self.meta.insert(key, meta);
+        Ok(())
+    }
+
+    /// Attach metadata to the expression with the given key, replacing any existing metadata. The
+    /// expression does not need to exist in the configuration.
+    pub fn annotate(&mut self, key: ConfigExprKey, meta: ConfigMeta) {
+        self.meta.insert(key, meta);
+    }
+
+    /// Returns the metadata attached to the expression with the given key.
+    pub fn get_meta(&self, key: &ConfigExprKey) -> Option<&ConfigMeta> {
+        self.meta.get(key)
+    }
+
     /// Apply a single `ConfigModifier` to the configuration, updating the `Config` struct. This
     /// function checks if the modifier can be applied. If the modifier inserts an already existing
     /// expression, or if the modifier removes or updates a non-existing expression, the function
@@ -164,7 +209,8 @@ impl Config {
 
     /// Apply a patch on the current configuration. `self` will be updated to reflect all chages in
     /// the patch. The function will return an error if the patch cannot be applied. If an error
-    /// occurs, the config will remain untouched.
+    /// occurs, the config will remain untouched. The metadata of the patch is added to the
+    /// configuration.
     pub fn apply_patch(&mut self, patch: &ConfigPatch) -> Result<(), ConfigError> {
         // clone the current config
         // TODO this can be implemented more efficiently, by undoing the change in reverse.
@@ -179,11 +225,25 @@ impl Config {
                 }
             };
         }
+        self.meta.extend(patch.meta.iter().map(|(k, m)| (k.clone(), m.clone())));
         Ok(())
     }
 
+    /// Returns a hash of all expressions in the configuration, ignoring the metadata. The hash does
+    /// not depend on the order in which the expressions were added. Hence, applying the same set of
+    /// modifiers in any order on the same configuration results in the same hash.
+    pub fn content_hash(&self) -> u64 {
+        self.expr.values().fold(0u64, |acc, expr| {
+            // expressions contain floats, which do not implement `Hash`.
+            let mut hasher = DefaultHasher::new();
+            format!("{:?}", expr).hash(&mut hasher);
+            acc.wrapping_add(hasher.finish())
+        })
+    }
+
     /// returns a ConfigPatch containing the difference between self and other
-    /// When the patch is applied on self, it will be the same as other.
+    /// When the patch is applied on self, it will be the same as other. The patch contains the
+    /// metadata of all changed expressions, preferring the metadata of `other`.
     pub fn get_diff(&self, other: &Self) -> ConfigPatch {
         let mut patch = ConfigPatch::new();
         let self_keys: HashSet<&ConfigExprKey> = self.expr.keys().collect();
@@ -207,6 +267,14 @@ impl Config {
                 patch.add(ConfigModifier::Update { from: self_e.clone(), to: other_e.clone() })
             }
         }
+
+        // metadata of all changed expressions
+        for modifier in patch.modifiers.iter() {
+            let key = modifier.key();
+            if let Some(meta) = other.meta.get(&key).or_else(|| self.meta.get(&key)) {
+                patch.meta.insert(key, meta.clone());
+            }
+        }
         patch
     }
 
@@ -243,7 +311,7 @@ impl PartialEq for Config {
 
 /// # Single configuration expression
 /// The expression sets a specific thing in the network.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ConfigExpr {
     /// Sets the link weight of a single link (directional)
     /// TODO make sure that the weight is strictly smaller than infinity.
@@ -268,6 +336,48 @@ pub enum ConfigExpr {
         /// Session type
         session_type: BgpSessionType,
     },
+    /// Enable graceful restart on a router for the BGP session with a neighbor. Routes learned
+    /// from the neighbor are retained when the session is closed, until it is established again.
+    BgpGracefulRestart {
+        /// Router on which to enable graceful restart
+        router: RouterId,
+        /// Neighbor of the BGP session
+        neighbor: RouterId,
+    },
+    /// Graceful shutdown of the BGP session between a router and a neighbor. All routes learned by
+    /// the router from the neighbor get the lowest local preference (0), such that the network
+    /// moves to alternative routes before the session is torn down.
+    BgpGracefulShutdown {
+        /// Router on which to start the graceful shutdown
+        router: RouterId,
+        /// Neighbor of the BGP session
+        neighbor: RouterId,
+    },
+    /// Business relationship (customer, peer or provider) with an external neighbor. This is
+    /// configured on all internal routers, and determines the default export policy for routes
+    /// learned from and advertised to the neighbor.
+    BgpRelationship {
+        /// External neighbor
+        neighbor: RouterId,
+        /// Relationship with the neighbor, seen from the own AS
+        relationship: BgpRelationship,
+    },
+    /// Assign the link between two routers to an OSPF area. Links without an area are part of the
+    /// backbone.
+    OspfArea {
+        /// First router of the link
+        router_a: RouterId,
+        /// Second router of the link
+        router_b: RouterId,
+        /// Area of the link
+        area: OspfArea,
+    },
+    /// Configure an OSPF area as stub area. Routers in a stub area reach external routers by a
+    /// default route towards the closest area border router.
+    OspfStubArea {
+        /// The stub area
+        area: OspfArea,
+    },
     /// Set the BGP Route Map
     BgpRouteMap {
         /// Router to configure the route map
@@ -286,6 +396,48 @@ pub enum ConfigExpr {
         /// To which neighbor to forward packets to.
         target: RouterId,
     },
+    /// Redistribute all static routes of a router into BGP. The router originates a route for
+    /// every prefix with a static route, which competes with the learned routes in the BGP
+    /// decision process.
+    RedistributeStatic {
+        /// Router on which to redistribute the static routes
+        router: RouterId,
+    },
+    /// Compare the MED of all routes in the BGP decision process of a router, even if they are
+    /// learned from different neighbor ASes. Without it, the MED is only compared among routes
+    /// from the same neighbor AS.
+    BgpAlwaysCompareMed {
+        /// Router on which to always compare the MED
+        router: RouterId,
+    },
+    /// Cluster ID of a route reflector. Route reflectors with the same cluster ID form a single
+    /// cluster, and drop all routes that were already reflected within the cluster. The cluster ID
+    /// is given by the ID of a router (usually of one of the route reflectors of the cluster).
+    /// Without this expression, the router uses its own ID as cluster ID.
+    BgpClusterId {
+        /// Route reflector to configure
+        router: RouterId,
+        /// Cluster ID
+        cluster_id: RouterId,
+    },
+    /// Enable Route Origin Validation (ROV) on a router, with the given policy for handling
+    /// RPKI-invalid routes.
+    RovPolicy {
+        /// Router on which to enable ROV
+        router: RouterId,
+        /// How to handle RPKI-invalid routes
+        policy: RovPolicy,
+    },
+    /// Route Origin Authorization (ROA) known to the router (through its RPKI cache). Only one
+    /// authorized origin AS can exist for each prefix.
+    Roa {
+        /// Router which knows the ROA
+        router: RouterId,
+        /// Prefix covered by the ROA
+        prefix: Prefix,
+        /// AS that is authorized to originate the prefix
+        origin: AsId,
+    },
 }
 
 impl ConfigExpr {
@@ -304,6 +456,23 @@ impl ConfigExpr {
                     ConfigExprKey::BgpSession { speaker_a: *target, speaker_b: *source }
                 }
             }
+            ConfigExpr::BgpGracefulRestart { router, neighbor } => {
+                ConfigExprKey::BgpGracefulRestart { router: *router, neighbor: *neighbor }
+            }
+            ConfigExpr::BgpGracefulShutdown { router, neighbor } => {
+                ConfigExprKey::BgpGracefulShutdown { router: *router, neighbor: *neighbor }
+            }
+            ConfigExpr::BgpRelationship { neighbor, relationship: _ } => {
+                ConfigExprKey::BgpRelationship { neighbor: *neighbor }
+            }
+            ConfigExpr::OspfArea { router_a, router_b, area: _ } => {
+                if router_a < router_b {
+                    ConfigExprKey::OspfArea { router_a: *router_a, router_b: *router_b }
+                } else {
+                    ConfigExprKey::OspfArea { router_a: *router_b, router_b: *router_a }
+                }
+            }
+            ConfigExpr::OspfStubArea { area } => ConfigExprKey::OspfStubArea { area: *area },
             ConfigExpr::BgpRouteMap { router, direction, map } => ConfigExprKey::BgpRouteMap {
                 router: *router,
                 direction: *direction,
@@ -312,6 +481,21 @@ impl ConfigExpr {
             ConfigExpr::StaticRoute { router, prefix, target: _ } => {
                 ConfigExprKey::StaticRoute { router: *router, prefix: *prefix }
             }
+            ConfigExpr::RedistributeStatic { router } => {
+                ConfigExprKey::RedistributeStatic { router: *router }
+            }
+            ConfigExpr::BgpAlwaysCompareMed { router } => {
+                ConfigExprKey::BgpAlwaysCompareMed { router: *router }
+            }
+            ConfigExpr::BgpClusterId { router, cluster_id: _ } => {
+                ConfigExprKey::BgpClusterId { router: *router }
+            }
+            ConfigExpr::RovPolicy { router, policy: _ } => {
+                ConfigExprKey::RovPolicy { router: *router }
+            }
+            ConfigExpr::Roa { router, prefix, origin: _ } => {
+                ConfigExprKey::Roa { router: *router, prefix: *prefix }
+            }
         }
     }
 
@@ -320,10 +504,79 @@ impl ConfigExpr {
         match self {
             ConfigExpr::IgpLinkWeight { source, target, .. } => vec![*source, *target],
             ConfigExpr::BgpSession { source, target, .. } => vec![*source, *target],
+            ConfigExpr::BgpGracefulRestart { router, .. } => vec![*router],
+            ConfigExpr::BgpGracefulShutdown { router, .. } => vec![*router],
+            ConfigExpr::BgpRelationship { neighbor, .. } => vec![*neighbor],
+            ConfigExpr::OspfArea { router_a, router_b, .. } => vec![*router_a, *router_b],
+            ConfigExpr::OspfStubArea { .. } => vec![],
             ConfigExpr::BgpRouteMap { router, .. } => vec![*router],
             ConfigExpr::StaticRoute { router, .. } => vec![*router],
+            ConfigExpr::RedistributeStatic { router } => vec![*router],
+            ConfigExpr::BgpAlwaysCompareMed { router } => vec![*router],
+            ConfigExpr::BgpClusterId { router, .. } => vec![*router],
+            ConfigExpr::RovPolicy { router, .. } => vec![*router],
+            ConfigExpr::Roa { router, .. } => vec![*router],
         }
     }
+
+    /// Returns a copy of the expression, where every router is replaced by `f(router)`.
+    pub(crate) fn map_routers<E>(
+        &self,
+        f: &mut impl FnMut(RouterId) -> Result<RouterId, E>,
+    ) -> Result<Self, E> {
+        Ok(match self {
+            ConfigExpr::IgpLinkWeight { source, target, weight } => ConfigExpr::IgpLinkWeight {
+                source: f(*source)?,
+                target: f(*target)?,
+                weight: *weight,
+            },
+            ConfigExpr::BgpSession { source, target, session_type } => ConfigExpr::BgpSession {
+                source: f(*source)?,
+                target: f(*target)?,
+                session_type: *session_type,
+            },
+            ConfigExpr::BgpGracefulRestart { router, neighbor } => {
+                ConfigExpr::BgpGracefulRestart { router: f(*router)?, neighbor: f(*neighbor)? }
+            }
+            ConfigExpr::BgpGracefulShutdown { router, neighbor } => {
+                ConfigExpr::BgpGracefulShutdown { router: f(*router)?, neighbor: f(*neighbor)? }
+            }
+            ConfigExpr::BgpRelationship { neighbor, relationship } => {
+                ConfigExpr::BgpRelationship { neighbor: f(*neighbor)?, relationship: *relationship }
+            }
+            ConfigExpr::OspfArea { router_a, router_b, area } => ConfigExpr::OspfArea {
+                router_a: f(*router_a)?,
+                router_b: f(*router_b)?,
+                area: *area,
+            },
+            ConfigExpr::OspfStubArea { area } => ConfigExpr::OspfStubArea { area: *area },
+            ConfigExpr::BgpRouteMap { router, direction, map } => ConfigExpr::BgpRouteMap {
+                router: f(*router)?,
+                direction: *direction,
+                map: map.map_routers(f)?,
+            },
+            ConfigExpr::StaticRoute { router, prefix, target } => ConfigExpr::StaticRoute {
+                router: f(*router)?,
+                prefix: *prefix,
+                target: f(*target)?,
+            },
+            ConfigExpr::RedistributeStatic { router } => {
+                ConfigExpr::RedistributeStatic { router: f(*router)? }
+            }
+            ConfigExpr::BgpAlwaysCompareMed { router } => {
+                ConfigExpr::BgpAlwaysCompareMed { router: f(*router)? }
+            }
+            ConfigExpr::BgpClusterId { router, cluster_id } => {
+                ConfigExpr::BgpClusterId { router: f(*router)?, cluster_id: f(*cluster_id)? }
+            }
+            ConfigExpr::RovPolicy { router, policy } => {
+                ConfigExpr::RovPolicy { router: f(*router)?, policy: *policy }
+            }
+            ConfigExpr::Roa { router, prefix, origin } => {
+                ConfigExpr::Roa { router: f(*router)?, prefix: *prefix, origin: *origin }
+            }
+        })
+    }
 }
 
 /// # Key for Config Expressions
@@ -341,7 +594,7 @@ impl ConfigExpr {
 /// it would be used as a key-value store. By using a different struct, it is very clear how the
 /// `Config` is indexed, and which expressions represent the same key. In addition, it does not
 /// require us to reimplement `Eq` and `Hash`.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ConfigExprKey {
     /// Sets the link weight of a single link (directional)
     IgpLinkWeight {
@@ -357,6 +610,37 @@ pub enum ConfigExprKey {
         /// Target router for Session
         speaker_b: RouterId,
     },
+    /// Key for enabling graceful restart
+    BgpGracefulRestart {
+        /// Router to be configured
+        router: RouterId,
+        /// Neighbor of the BGP session
+        neighbor: RouterId,
+    },
+    /// Key for the graceful shutdown of a BGP session
+    BgpGracefulShutdown {
+        /// Router to be configured
+        router: RouterId,
+        /// Neighbor of the BGP session
+        neighbor: RouterId,
+    },
+    /// Key for the relationship with an external neighbor
+    BgpRelationship {
+        /// External neighbor
+        neighbor: RouterId,
+    },
+    /// Key for the OSPF area of a link
+    OspfArea {
+        /// Router of the link with the smaller id
+        router_a: RouterId,
+        /// Router of the link with the larger id
+        router_b: RouterId,
+    },
+    /// Key for configuring a stub area
+    OspfStubArea {
+        /// The stub area
+        area: OspfArea,
+    },
     /// Sets the local preference of an incoming route from an eBGp session, based on the router ID.
     BgpRouteMap {
         /// Rotuer for configuration
@@ -373,12 +657,39 @@ pub enum ConfigExprKey {
         /// Prefix for which to configure the router
         prefix: Prefix,
     },
+    /// Key for redistributing static routes into BGP
+    RedistributeStatic {
+        /// Router to be configured
+        router: RouterId,
+    },
+    /// Key for always comparing the MED
+    BgpAlwaysCompareMed {
+        /// Router to be configured
+        router: RouterId,
+    },
+    /// Key for the cluster ID of a route reflector
+    BgpClusterId {
+        /// Router to be configured
+        router: RouterId,
+    },
+    /// Key for enabling Route Origin Validation
+    RovPolicy {
+        /// Router to be configured
+        router: RouterId,
+    },
+    /// Key for a Route Origin Authorization
+    Roa {
+        /// Router to be configured
+        router: RouterId,
+        /// Prefix covered by the ROA
+        prefix: Prefix,
+    },
 }
 
 /// # Config Modifier
 /// A single patch to apply on a configuration. The modifier can either insert a new expression,
 /// update an existing expression or remove an old expression.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ConfigModifier {
     /// Insert a new expression
     Insert(ConfigExpr),
@@ -421,15 +732,109 @@ impl ConfigModifier {
             Self::Update { from, to } => Self::Update { from: to, to: from },
         }
     }
+
+    /// Returns the modifier, where all routers are referenced by their name in `net` (see
+    /// [`NamedConfigModifier`]). If a router does not exist in `net`, then
+    /// [`NetworkError::DeviceNotFound`] is returned.
+    pub fn with_names(&self, net: &Network) -> Result<NamedConfigModifier, NetworkError> {
+        let mut routers: Vec<String> = Vec::new();
+        let modifier = self.map_routers(&mut |r| -> Result<RouterId, NetworkError> {
+            let name = net.get_router_name(r)?;
+            let idx = match routers.iter().position(|n| n == name) {
+                Some(idx) => idx,
+                None => {
+                    routers.push(name.to_string());
+                    routers.len() - 1
+                }
+            };
+            Ok((idx as u32).into())
+        })?;
+        Ok(NamedConfigModifier { modifier, routers })
+    }
+
+    /// Returns a copy of the modifier, where every router is replaced by `f(router)`.
+    fn map_routers<E>(
+        &self,
+        f: &mut impl FnMut(RouterId) -> Result<RouterId, E>,
+    ) -> Result<Self, E> {
+        Ok(match self {
+            Self::Insert(e) => Self::Insert(e.map_routers(f)?),
+            Self::Remove(e) => Self::Remove(e.map_routers(f)?),
+            Self::Update { from, to } => {
+                Self::Update { from: from.map_routers(f)?, to: to.map_routers(f)? }
+            }
+        })
+    }
+}
+
+/// # Named Config Modifier
+/// A [`ConfigModifier`], where the routers are referenced by their name instead of their
+/// [`RouterId`]. The IDs are assigned when the network is built, and they change when the same
+/// network is created in a different order. A named modifier can be stored (it can be
+/// serialized), and replayed on a re-created network, as long as the router names are the same.
+/// Create it with [`ConfigModifier::with_names`], and convert it back with
+/// [`NamedConfigModifier::to_modifier`].
+///
+/// ```rust
+/// use snowcap::netsim::config::{ConfigExpr::IgpLinkWeight, ConfigModifier};
+/// use snowcap::netsim::Network;
+///
+/// let mut net = Network::new();
+/// let r1 = net.add_router("r1");
+/// let r2 = net.add_router("r2");
+/// let modifier =
+///     ConfigModifier::Insert(IgpLinkWeight { source: r1, target: r2, weight: 1.0 });
+/// let named = modifier.with_names(&net).unwrap();
+///
+/// // the same network, but the routers are added in a different order
+/// let mut rebuilt = Network::new();
+/// let r2 = rebuilt.add_router("r2");
+/// let r1 = rebuilt.add_router("r1");
+/// assert_eq!(
+///     named.to_modifier(&rebuilt).unwrap(),
+///     ConfigModifier::Insert(IgpLinkWeight { source: r1, target: r2, weight: 1.0 })
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedConfigModifier {
+    /// The modifier, where every [`RouterId`] is the index of the router name in `routers`.
+    pub modifier: ConfigModifier,
+    /// Names of all routers referenced by the modifier.
+    pub routers: Vec<String>,
+}
+
+impl NamedConfigModifier {
+    /// Returns the modifier, where every router name is replaced by the [`RouterId`] of the router
+    /// with the same name in `net`. If no router with the name exists, then
+    /// [`NetworkError::DeviceNameNotFound`] is returned.
+    pub fn to_modifier(&self, net: &Network) -> Result<ConfigModifier, NetworkError> {
+        self.modifier.map_routers(&mut |r| match self.routers.get(r.index()) {
+            Some(name) => net.get_router_id(name),
+            None => Err(NetworkError::DeviceNotFound(r)),
+        })
+    }
+
+    /// Returns the names of all routers which will be updated by this modifier (see
+    /// [`ConfigModifier::routers`]).
+    pub fn router_names(&self) -> Vec<&str> {
+        self.modifier
+            .routers()
+            .into_iter()
+            .filter_map(|r| self.routers.get(r.index()).map(|n| n.as_str()))
+            .collect()
+    }
 }
 
 /// # Config Patch
 /// A series of `ConfigModifiers` which can be applied on a `Config` to get a new `Config`. The
 /// series is an ordered list, and the modifiers are applied in the order they were added.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConfigPatch {
     /// List of all modifiers, in the order in which they are applied.
     pub modifiers: Vec<ConfigModifier>,
+    /// Metadata of the modified expressions
+    #[serde(with = "serde_key_map", default)]
+    pub meta: HashMap<ConfigExprKey, ConfigMeta>,
 }
 
 impl Default for ConfigPatch {
@@ -441,7 +846,7 @@ impl Default for ConfigPatch {
 impl ConfigPatch {
     /// Create an empty patch
     pub fn new() -> Self {
-        Self { modifiers: Vec::new() }
+        Self { modifiers: Vec::new(), meta: HashMap::new() }
     }
 
     /// Add a new modifier to the patch
@@ -449,3 +854,100 @@ impl ConfigPatch {
         self.modifiers.push(modifier);
     }
 }
+
+/// # Metadata of a Config Expression
+/// Free-form metadata, attached to a [`ConfigExpr`] in a [`Config`], in order to trace the
+/// configuration (and the synthesized modifiers) back to the intent of the operator.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigMeta {
+    /// Name of the expression, e.g., the name of the change in the intent document
+    pub name: Option<String>,
+    /// Ticket (or issue) in which the change was requested
+    pub ticket: Option<String>,
+    /// Free-form comment
+    pub comment: Option<String>,
+}
+
+impl ConfigMeta {
+    /// Create metadata with only a name
+    pub fn named(name: impl Into<String>) -> Self {
+        Self { name: Some(name.into()), ..Default::default() }
+    }
+
+    /// Set the ticket
+    pub fn ticket(mut self, ticket: impl Into<String>) -> Self {
+        self.ticket = Some(ticket.into());
+        self
+    }
+
+    /// Set the comment
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Returns `true` if no metadata is set.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.ticket.is_none() && self.comment.is_none()
+    }
+}
+
+impl fmt::Display for ConfigMeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(name) = self.name.as_ref() {
+            parts.push(name.clone());
+        }
+        if let Some(ticket) = self.ticket.as_ref() {
+            parts.push(format!("[{}]", ticket));
+        }
+        if let Some(comment) = self.comment.as_ref() {
+            parts.push(format!("\"{}\"", comment));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Serialize the configuration expressions as a list sorted by their key, since formats like JSON
+/// only support strings as keys of a map. The keys are recomputed when deserializing.
+mod serde_expr_map {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        map: &HashMap<ConfigExprKey, ConfigExpr>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<(&ConfigExprKey, &ConfigExpr)> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        serializer.collect_seq(entries.into_iter().map(|(_, expr)| expr))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<ConfigExprKey, ConfigExpr>, D::Error> {
+        Ok(Vec::<ConfigExpr>::deserialize(deserializer)?
+            .into_iter()
+            .map(|e| (e.key(), e))
+            .collect())
+    }
+}
+
+/// Serialize a map indexed by [`ConfigExprKey`] as a list of key-value pairs, sorted by the key.
+mod serde_key_map {
+    use super::*;
+
+    pub fn serialize<V: Serialize, S: Serializer>(
+        map: &HashMap<ConfigExprKey, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<(&ConfigExprKey, &V)> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        serializer.collect_seq(entries)
+    }
+
+    pub fn deserialize<'de, V: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<ConfigExprKey, V>, D::Error> {
+        Ok(Vec::<(ConfigExprKey, V)>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
@@ -41,6 +41,7 @@ impl Event {
         match self {
             Event::Bgp(_, _, BgpEvent::Update(route)) => Some(route.prefix),
             Event::Bgp(_, _, BgpEvent::Withdraw(prefix)) => Some(*prefix),
+            Event::Bgp(_, _, BgpEvent::RouteRefresh(prefix)) => Some(*prefix),
             Event::Config(_) => None,
             Event::AdvertiseExternalRoute(_, route) => Some(route.prefix),
             Event::WithdrawExternalRoute(_, prefix) => Some(*prefix),
@@ -43,6 +43,7 @@ pub struct ExternalRouter {
     neighbors: Vec<RouterId>,
     active_routes: Vec<BgpRoute>,
     undo_stack: Vec<UndoAction>,
+    track_undo: bool,
 }
 
 impl Clone for ExternalRouter {
@@ -54,6 +55,7 @@ impl Clone for ExternalRouter {
             neighbors: self.neighbors.clone(),
             active_routes: self.active_routes.clone(),
             undo_stack: Vec::new(),
+            track_undo: self.track_undo,
         }
     }
 }
@@ -68,18 +70,32 @@ impl ExternalRouter {
             neighbors: Vec::new(),
             active_routes: Vec::new(),
             undo_stack: Vec::new(),
+            track_undo: true,
         }
     }
 
     /// Handle an `Event` and produce the necessary result. Always returns Ok(false), to tell that
-    /// the forwarding state has not changed.
+    /// the forwarding state has not changed. The only event that causes a reaction is a route
+    /// refresh request of a neighbor, to which the active route is sent again.
     pub(crate) fn handle_event(
         &mut self,
-        _event: Event,
-        _queue: &mut EventQueue,
-        _parent_event_id: usize,
+        event: Event,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
     ) -> Result<bool, DeviceError> {
-        self.undo_stack.push(UndoAction::None);
+        if self.track_undo {
+            self.undo_stack.push(UndoAction::None);
+        }
+        if let Event::Bgp(from, to, BgpEvent::RouteRefresh(prefix)) = event {
+            if to == self.router_id && self.neighbors.contains(&from) {
+                if let Some(route) = self.active_routes.iter().find(|r| r.prefix == prefix) {
+                    queue.push_back((
+                        Event::Bgp(self.router_id, from, BgpEvent::Update(route.clone())),
+                        parent_event_id,
+                    ));
+                }
+            }
+        }
         Ok(false)
     }
 
@@ -132,6 +148,14 @@ impl ExternalRouter {
         self.undo_stack.clear();
     }
 
+    /// Enable or disable the undo tracking. Disabling the tracking clears the undo stack.
+    pub(crate) fn set_undo_tracking(&mut self, enabled: bool) {
+        self.track_undo = enabled;
+        if !enabled {
+            self.undo_stack.clear();
+        }
+    }
+
     /// Return the ID of the network device
     pub fn router_id(&self) -> RouterId {
         self.router_id
@@ -159,7 +183,7 @@ impl ExternalRouter {
         prefix: Prefix,
         as_path: Vec<AsId>,
         med: Option<u32>,
-        community: Option<u32>,
+        communities: impl IntoIterator<Item = u32>,
         queue: &mut EventQueue,
         parent_event_id: usize,
     ) -> BgpRoute {
@@ -169,7 +193,9 @@ impl ExternalRouter {
             next_hop: self.router_id,
             local_pref: None,
             med,
-            community,
+            communities: communities.into_iter().collect(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         };
 
         let mut new_route: bool = true;
@@ -177,14 +203,18 @@ impl ExternalRouter {
         for existing_route in self.active_routes.iter_mut() {
             if existing_route.prefix == route.prefix {
                 new_route = false;
-                self.undo_stack.push(UndoAction::UpdateActiveRoute(existing_route.clone()));
+                if self.track_undo {
+                    self.undo_stack.push(UndoAction::UpdateActiveRoute(existing_route.clone()));
+                }
                 *existing_route = route.clone();
                 break;
             }
         }
         if new_route {
             self.active_routes.push(route.clone());
-            self.undo_stack.push(UndoAction::RemoveActiveRoute(prefix));
+            if self.track_undo {
+                self.undo_stack.push(UndoAction::RemoveActiveRoute(prefix));
+            }
         }
 
         // send an UPDATE to all neighbors
@@ -215,7 +245,9 @@ impl ExternalRouter {
         if let Some(pos) = self.active_routes.iter().position(|x| x.prefix == prefix) {
             // remove the prefix from the vector
             let old_route = self.active_routes.remove(pos);
-            self.undo_stack.push(UndoAction::AddActiveRoute(old_route));
+            if self.track_undo {
+                self.undo_stack.push(UndoAction::AddActiveRoute(old_route));
+            }
 
             // only send the withdraw if the route actually did exist
             for neighbor in self.neighbors.iter() {
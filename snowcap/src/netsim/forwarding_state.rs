@@ -19,10 +19,11 @@
 //! containing the state, and providing some helper functions to extract certain information about
 //! the state.
 
-use crate::netsim::{Network, NetworkDevice, NetworkError, Prefix, RouterId};
+use crate::netsim::{Afi, Ipv4Net, Network, NetworkDevice, NetworkError, Prefix, RouterId};
 use log::*;
 use std::collections::{HashMap, HashSet};
 use std::iter::{repeat, Peekable};
+use std::net::Ipv4Addr;
 use std::vec::IntoIter;
 
 /// # Forwarding State
@@ -36,6 +37,15 @@ use std::vec::IntoIter;
 ///
 /// In addition, the `ForwardingState` caches the already computed results of any path for faster
 /// access.
+///
+/// If ECMP is enabled on the network (see [`Network::set_ecmp`]), routers may have multiple
+/// equal-cost next hops. In this case, `get_route` only follows the first next hop of every router,
+/// while [`ForwardingState::get_all_routes`] and [`ForwardingState::get_route_dag`] consider all of
+/// them.
+///
+/// If the prefixes have address ranges (see [`Network::set_prefix_range`]), the forwarding state
+/// can also be queried for a single address, in which case every router uses longest-prefix
+/// matching among all prefixes it knows a route to (see [`ForwardingState::get_route_to_addr`]).
 #[derive(Debug, Clone)]
 pub struct ForwardingState {
     /// Number of prefixes, needed for computing the index
@@ -55,6 +65,11 @@ pub struct ForwardingState {
     /// we have not yet computed the result there, But if cache[r * num_prefixes + p] is true, then
     /// it will store the result which was computed last time.
     cache: Vec<Option<(CacheResult, Vec<RouterId>)>>,
+    /// All equal-cost next hops, indexed in the same way as `state`. Only entries with more than
+    /// one next hop are stored. The first next hop is always the one stored in `state`.
+    multipath: HashMap<usize, Vec<RouterId>>,
+    /// Address ranges of the prefixes, sorted by decreasing prefix length.
+    ranges: Vec<(Ipv4Net, Prefix)>,
 }
 
 impl PartialEq for ForwardingState {
@@ -66,7 +81,7 @@ impl PartialEq for ForwardingState {
         for prefix in self.prefixes.keys() {
             for rid in 0..self.num_devices {
                 let router = (rid as u32).into();
-                if self.get_next_hop(router, *prefix) != other.get_next_hop(router, *prefix) {
+                if self.get_next_hops(router, *prefix) != other.get_next_hops(router, *prefix) {
                     return false;
                 }
             }
@@ -92,10 +107,20 @@ impl ForwardingState {
         // initialize state
         let mut state: Vec<Option<RouterId>> =
             repeat(None).take(num_prefixes * num_devices).collect();
+        let mut multipath: HashMap<usize, Vec<RouterId>> = HashMap::new();
         for rid in 0..num_devices as u32 {
             if let NetworkDevice::InternalRouter(r) = net.get_device(rid.into()) {
                 for (p, pid) in prefixes.iter() {
-                    state[get_idx(rid as usize, *pid, num_prefixes)] = r.get_next_hop(*p);
+                    let idx = get_idx(rid as usize, *pid, num_prefixes);
+                    if net.get_ecmp() {
+                        let next_hops = r.get_next_hops(*p);
+                        state[idx] = next_hops.first().cloned();
+                        if next_hops.len() > 1 {
+                            multipath.insert(idx, next_hops);
+                        }
+                    } else {
+                        state[idx] = r.get_next_hop(*p);
+                    }
                 }
             }
         }
@@ -112,7 +137,24 @@ impl ForwardingState {
         // prepare the cache
         let cache = repeat(None).take(num_prefixes * num_devices).collect();
 
-        Self { num_prefixes, num_devices, state, prefixes, external_routers, cache }
+        // collect the address ranges of all IPv4 prefixes, with the most specific range first
+        let mut ranges: Vec<(Ipv4Net, Prefix)> = prefixes
+            .keys()
+            .filter(|p| p.afi() == Afi::Ipv4)
+            .filter_map(|p| net.get_prefix_range(*p).map(|range| (range, *p)))
+            .collect();
+        ranges.sort_by(|a, b| b.0.prefix_len().cmp(&a.0.prefix_len()).then(a.1.cmp(&b.1)));
+
+        Self {
+            num_prefixes,
+            num_devices,
+            state,
+            prefixes,
+            external_routers,
+            cache,
+            multipath,
+            ranges,
+        }
     }
 
     /// Returns the route from the source router to a specific prefix. This function uses the cached
@@ -228,6 +270,237 @@ impl ForwardingState {
             Ok(None)
         }
     }
+
+    /// Get all equal-cost next hops of a router for a specific prefix. If that router does not
+    /// know any route, an empty vector is returned. Without ECMP, there is at most one next hop.
+    pub fn get_next_hops(
+        &self,
+        router: RouterId,
+        prefix: Prefix,
+    ) -> Result<Vec<RouterId>, NetworkError> {
+        if router.index() >= self.num_devices {
+            return Err(NetworkError::DeviceNotFound(router));
+        }
+        Ok(match self.prefixes.get(&prefix) {
+            Some(pid) => self.next_hops_at(get_idx(router.index(), *pid, self.num_prefixes)),
+            None => Vec::new(),
+        })
+    }
+
+    /// Returns every path from the source router towards the prefix, considering all equal-cost
+    /// next hops. Each path is checked in the same way as in [`ForwardingState::get_route`], i.e.,
+    /// a path ending in a forwarding loop or a black hole is returned as the corresponding error.
+    /// If no router has more than one next hop, this is equivalent to `vec![self.get_route(..)]`.
+    pub fn get_all_routes(
+        &mut self,
+        source: RouterId,
+        prefix: Prefix,
+    ) -> Vec<Result<Vec<RouterId>, NetworkError>> {
+        if self.multipath.is_empty() {
+            return vec![self.get_route(source, prefix)];
+        }
+        if source.index() >= self.num_devices {
+            return vec![Err(NetworkError::DeviceNotFound(source))];
+        }
+        let pid = match self.prefixes.get(&prefix) {
+            Some(pid) => *pid,
+            None => return vec![Err(NetworkError::ForwardingBlackHole(vec![source]))],
+        };
+
+        let mut routes = Vec::new();
+        let mut stack: Vec<Vec<RouterId>> = vec![vec![source]];
+        while let Some(path) = stack.pop() {
+            let current = *path.last().unwrap();
+            if path[..path.len() - 1].contains(&current) {
+                trace!("Forwarding loop detected: {:?}", path);
+                routes.push(Err(NetworkError::ForwardingLoop(path)));
+                continue;
+            }
+            let next_hops = self.next_hops_at(get_idx(current.index(), pid, self.num_prefixes));
+            if next_hops.is_empty() {
+                trace!("Black hole detected: {:?}", path);
+                routes.push(Err(NetworkError::ForwardingBlackHole(path)));
+            } else if self.external_routers.contains(&current) {
+                routes.push(Ok(path));
+            } else {
+                // push in reverse order, such that the paths are returned sorted by next hops
+                for nh in next_hops.into_iter().rev() {
+                    let mut next_path = path.clone();
+                    next_path.push(nh);
+                    stack.push(next_path);
+                }
+            }
+        }
+        routes
+    }
+
+    /// Returns the egress router of the route from the source towards the prefix, i.e., the last
+    /// internal router on the route, at which the traffic leaves the network towards an external
+    /// router. The route is computed as in [`ForwardingState::get_route`]. If the source itself is
+    /// an external router, then the source is returned.
+    pub fn get_egress(
+        &mut self,
+        source: RouterId,
+        prefix: Prefix,
+    ) -> Result<RouterId, NetworkError> {
+        self.get_route(source, prefix).map(|path| self.egress_of(&path))
+    }
+
+    /// Returns the egress router of every path from the source towards the prefix, considering all
+    /// equal-cost next hops (see [`ForwardingState::get_all_routes`]). The result contains one
+    /// entry for every path, so the same egress router may appear multiple times.
+    pub fn get_all_egresses(
+        &mut self,
+        source: RouterId,
+        prefix: Prefix,
+    ) -> Vec<Result<RouterId, NetworkError>> {
+        self.get_all_routes(source, prefix)
+            .into_iter()
+            .map(|route| route.map(|path| self.egress_of(&path)))
+            .collect()
+    }
+
+    /// Returns the forwarding graph from the source towards the prefix, considering all equal-cost
+    /// next hops. The result maps every router reachable from the source to its next hops. External
+    /// routers and routers without any route are mapped to an empty vector. Note, that the graph
+    /// is only acyclic if there is no forwarding loop.
+    pub fn get_route_dag(
+        &self,
+        source: RouterId,
+        prefix: Prefix,
+    ) -> Result<HashMap<RouterId, Vec<RouterId>>, NetworkError> {
+        if source.index() >= self.num_devices {
+            return Err(NetworkError::DeviceNotFound(source));
+        }
+        let mut dag: HashMap<RouterId, Vec<RouterId>> = HashMap::new();
+        let mut to_visit = vec![source];
+        while let Some(router) = to_visit.pop() {
+            if dag.contains_key(&router) {
+                continue;
+            }
+            let next_hops = if self.external_routers.contains(&router) {
+                Vec::new()
+            } else {
+                self.get_next_hops(router, prefix)?
+            };
+            to_visit.extend(next_hops.iter().cloned());
+            dag.insert(router, next_hops);
+        }
+        Ok(dag)
+    }
+
+    /// Returns the prefix, which the router uses to forward traffic towards the address, i.e., the
+    /// prefix with the longest range containing the address, for which the router knows a route.
+    /// External routers only use the prefixes they advertise. If the router knows no matching route,
+    /// `Ok(None)` is returned.
+    pub fn get_prefix_for_addr(
+        &self,
+        router: RouterId,
+        addr: Ipv4Addr,
+    ) -> Result<Option<Prefix>, NetworkError> {
+        if router.index() >= self.num_devices {
+            return Err(NetworkError::DeviceNotFound(router));
+        }
+        for (range, prefix) in self.ranges.iter().filter(|(range, _)| range.contains(addr)) {
+            if self.get_next_hop(router, *prefix)?.is_some() {
+                return Ok(Some(*prefix));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the route from the source router towards a single address. In contrast to
+    /// [`ForwardingState::get_route`], every router on the path chooses the prefix by
+    /// longest-prefix matching (see [`ForwardingState::get_prefix_for_addr`]), so the traffic may
+    /// follow a more specific prefix on some routers, and the aggregate on others. Only the first
+    /// next hop of every router is followed (see [`ForwardingState::get_all_routes_to_addr`] to
+    /// consider all equal-cost next hops). The result is not cached.
+    pub fn get_route_to_addr(
+        &self,
+        source: RouterId,
+        addr: Ipv4Addr,
+    ) -> Result<Vec<RouterId>, NetworkError> {
+        let mut path: Vec<RouterId> = Vec::new();
+        let mut current = source;
+        loop {
+            if path.contains(&current) {
+                path.push(current);
+                trace!("Forwarding loop detected: {:?}", path);
+                return Err(NetworkError::ForwardingLoop(path));
+            }
+            path.push(current);
+            let next_hop = match self.get_prefix_for_addr(current, addr)? {
+                Some(prefix) => self.get_next_hop(current, prefix)?.unwrap(),
+                None => {
+                    trace!("Black hole detected: {:?}", path);
+                    return Err(NetworkError::ForwardingBlackHole(path));
+                }
+            };
+            if self.external_routers.contains(&current) {
+                return Ok(path);
+            }
+            current = next_hop;
+        }
+    }
+
+    /// Returns every path from the source router towards a single address, considering all
+    /// equal-cost next hops. Every router on the path chooses the prefix by longest-prefix matching,
+    /// as in [`ForwardingState::get_route_to_addr`]. Each path ending in a forwarding loop or a black
+    /// hole is returned as the corresponding error, as in [`ForwardingState::get_all_routes`].
+    pub fn get_all_routes_to_addr(
+        &self,
+        source: RouterId,
+        addr: Ipv4Addr,
+    ) -> Vec<Result<Vec<RouterId>, NetworkError>> {
+        let mut routes = Vec::new();
+        let mut stack: Vec<Vec<RouterId>> = vec![vec![source]];
+        while let Some(path) = stack.pop() {
+            let current = *path.last().unwrap();
+            if path[..path.len() - 1].contains(&current) {
+                trace!("Forwarding loop detected: {:?}", path);
+                routes.push(Err(NetworkError::ForwardingLoop(path)));
+                continue;
+            }
+            let next_hops = match self.get_prefix_for_addr(current, addr).and_then(|p| match p {
+                Some(prefix) => self.get_next_hops(current, prefix),
+                None => Ok(Vec::new()),
+            }) {
+                Ok(next_hops) => next_hops,
+                Err(e) => {
+                    routes.push(Err(e));
+                    continue;
+                }
+            };
+            if next_hops.is_empty() {
+                trace!("Black hole detected: {:?}", path);
+                routes.push(Err(NetworkError::ForwardingBlackHole(path)));
+            } else if self.external_routers.contains(&current) {
+                routes.push(Ok(path));
+            } else {
+                // push in reverse order, such that the paths are returned sorted by next hops
+                for nh in next_hops.into_iter().rev() {
+                    let mut next_path = path.clone();
+                    next_path.push(nh);
+                    stack.push(next_path);
+                }
+            }
+        }
+        routes
+    }
+
+    /// Returns the last internal router of a valid path, or the first router if all of them are
+    /// external.
+    fn egress_of(&self, path: &[RouterId]) -> RouterId {
+        path.iter().rev().find(|r| !self.external_routers.contains(r)).copied().unwrap_or(path[0])
+    }
+
+    /// Returns all next hops stored at the index of the flattened state.
+    fn next_hops_at(&self, idx: usize) -> Vec<RouterId> {
+        match self.multipath.get(&idx) {
+            Some(next_hops) => next_hops.clone(),
+            None => self.state[idx].into_iter().collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -301,6 +574,8 @@ mod test {
             prefixes: maplit::hashmap![Prefix(0) => 0, ],
             external_routers: maplit::hashset![r0, r5],
             cache: vec![None, None, None, None, None, None],
+            multipath: HashMap::new(),
+            ranges: Vec::new(),
         };
         assert_eq!(state.get_route(r0, Prefix(0)), Ok(vec![r0]));
         assert_eq!(state.get_route(r1, Prefix(0)), Ok(vec![r1, r0]));
@@ -327,6 +602,8 @@ mod test {
             prefixes: maplit::hashmap![Prefix(0) => 0, ],
             external_routers: maplit::hashset![r0, r5],
             cache: vec![None, None, None, None, None, None],
+            multipath: HashMap::new(),
+            ranges: Vec::new(),
         };
         assert_eq!(state.get_route(r4, Prefix(0)), Ok(vec![r4, r2, r1, r0]));
         assert_eq!(state.cache[5], None);
@@ -352,6 +629,8 @@ mod test {
             prefixes: maplit::hashmap![Prefix(0) => 0, ],
             external_routers: maplit::hashset![r0, r5],
             cache: vec![None, None, None, None, None, None],
+            multipath: HashMap::new(),
+            ranges: Vec::new(),
         };
         assert_eq!(
             state.get_route(r2, Prefix(0)),
@@ -400,6 +679,8 @@ mod test {
             prefixes: maplit::hashmap![Prefix(0) => 0, ],
             external_routers: maplit::hashset![r0, r5],
             cache: vec![None, None, None, None, None, None],
+            multipath: HashMap::new(),
+            ranges: Vec::new(),
         };
         assert_eq!(
             state.get_route(r1, Prefix(0)),
@@ -92,26 +92,33 @@
 //!   network such that messages are sent over actual links, and that links with infinite weight
 //!   are not allowed to be used. Also, make the routers route the actual messages.
 //! - Allow links to go down.
-//! - MED should only be compared for the same AS
 
 pub mod bgp;
+pub mod centrality;
+pub mod cli;
 pub(crate) mod event;
 pub mod external_router;
 pub(crate) mod forwarding_state;
 pub mod route_map;
 pub mod router;
+pub mod scale;
+pub mod scheduler;
+pub mod snapshot;
 pub(crate) mod types;
 
 pub(crate) use event::{Event, EventQueue};
 
 pub mod config;
 pub(crate) mod network;
+pub(crate) mod ospf;
 pub mod printer;
 
 pub use bgp::BgpSessionType;
 pub use forwarding_state::ForwardingState;
-pub use network::Network;
+#[cfg(feature = "transient-violation")]
+pub use network::FuzzViolation;
+pub use network::{Network, PathResult, PrefixTrace, StepTrace, DEFAULT_LINK_DELAY};
 pub use types::{
-    AsId, ConfigError, DeviceError, IgpNetwork, LinkWeight, NetworkDevice, NetworkError, Prefix,
-    RouterId,
+    Afi, AsId, ConfigError, DeviceError, IgpAreas, IgpNetwork, Ipv4Net, LinkWeight, NetworkDevice,
+    NetworkError, OspfArea, Prefix, RouterId, RouterRole,
 };
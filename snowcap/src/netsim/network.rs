@@ -21,27 +21,95 @@
 //! network.
 
 #[cfg(feature = "transient-violation")]
-use crate::hard_policies::{Condition, PolicyError};
-use crate::netsim::bgp::{BgpEvent, BgpSessionType};
+use crate::hard_policies::{HardPolicy, PolicyError};
+use crate::netsim::bgp::{BgpEvent, BgpRelationship, BgpSessionType};
 use crate::netsim::config::{Config, ConfigExpr, ConfigModifier, ConfigPatch};
 use crate::netsim::event::{Event, EventQueue};
 use crate::netsim::external_router::ExternalRouter;
 use crate::netsim::printer;
 use crate::netsim::route_map::RouteMapDirection;
 use crate::netsim::router::Router;
-use crate::netsim::types::{IgpNetwork, NetworkDevice};
+use crate::netsim::scheduler::{EventScheduler, PendingMessage};
+use crate::netsim::types::{IgpAreas, IgpNetwork, NetworkDevice};
 use crate::netsim::{
-    AsId, ConfigError, ForwardingState, LinkWeight, NetworkError, Prefix, RouterId,
+    AsId, ConfigError, ForwardingState, Ipv4Net, LinkWeight, NetworkError, Prefix, RouterId,
+    RouterRole,
 };
+use crate::traffic_matrix::TrafficMatrix;
+use crate::ControlHandle;
 
 use log::*;
 use petgraph::algo::FloatMeasure;
 #[cfg(feature = "transient-violation")]
 use rand::prelude::*;
+use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 static DEFAULT_STOP_AFTER: usize = 10_000;
 static MAXIMUM_ALLOWED_LOOP_LEN: usize = 500;
+/// Default delay of a BGP message from one router to another, used to estimate the convergence
+/// time (see [`Network::get_convergence_time`]).
+pub const DEFAULT_LINK_DELAY: f64 = 1.0;
+
+/// An ordering of messages, in which the hard policy is violated in a transient state, found by
+/// [`Network::apply_modifier_fuzz_orderings`].
+///
+/// *This structure is only available if the `"transient-violation"` feature is enabled!*
+#[cfg(feature = "transient-violation")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzViolation {
+    /// All events (formatted as in [`Network::event_trace`]) up to and including the event that
+    /// caused the violation, starting with the modifier itself.
+    pub ordering: Vec<String>,
+    /// Errors of the hard policy in the violating transient state.
+    pub errors: HashSet<PolicyError>,
+}
+
+/// Trace of applying a single modifier, computed by [`Network::apply_modifier_with_trace`].
+#[derive(Debug, PartialEq)]
+pub struct StepTrace {
+    /// The applied modifier
+    pub modifier: ConfigModifier,
+    /// Trace of every prefix known before or after applying the modifier.
+    pub prefixes: HashMap<Prefix, PrefixTrace>,
+}
+
+impl StepTrace {
+    /// Returns all internal routers that changed their next hop for any prefix.
+    pub fn changed_routers(&self) -> HashSet<RouterId> {
+        self.prefixes.values().flat_map(|t| t.next_hop_changes.keys().cloned()).collect()
+    }
+
+    /// Returns the total number of BGP messages exchanged.
+    pub fn num_messages(&self) -> usize {
+        self.prefixes.values().map(|t| t.messages.len()).sum()
+    }
+}
+
+/// Changes of a single prefix, caused by applying a modifier (see [`StepTrace`]).
+#[derive(Debug, PartialEq, Default)]
+pub struct PrefixTrace {
+    /// Internal routers that changed their next hops, mapped to the next hops before and after
+    /// applying the modifier. Without ECMP, there is at most one next hop.
+    pub next_hop_changes: HashMap<RouterId, (Vec<RouterId>, Vec<RouterId>)>,
+    /// All BGP messages `(from, to, event)` for this prefix, in the order they were processed.
+    pub messages: Vec<(RouterId, RouterId, BgpEvent)>,
+    /// Internal routers whose paths changed, mapped to the paths before and after applying the
+    /// modifier. The paths are computed with [`ForwardingState::get_all_routes`]. This includes
+    /// routers that only changed their path because a router further down the path changed.
+    pub path_changes: HashMap<RouterId, (Vec<PathResult>, Vec<PathResult>)>,
+}
+
+impl PrefixTrace {
+    /// Returns `true` if the prefix was not affected at all.
+    pub fn is_empty(&self) -> bool {
+        self.next_hop_changes.is_empty() && self.messages.is_empty() && self.path_changes.is_empty()
+    }
+}
+
+/// A path towards a prefix, or the error (forwarding loop or black hole) that occurs along it.
+pub type PathResult = Result<Vec<RouterId>, NetworkError>;
 
 #[derive(Debug)]
 /// # Network struct
@@ -49,6 +117,9 @@ static MAXIMUM_ALLOWED_LOOP_LEN: usize = 500;
 /// all (both internal and external) routers, and handles all events between them. Configuration is
 /// applied on the network itself, treated as network-wide configuration.
 ///
+/// The network can be serialized (using `serde`) as a snapshot of its topology, configuration and
+/// advertised routes. See the module [`snapshot`](crate::netsim::snapshot) for details.
+///
 /// ## Undo Funcitonality
 ///
 /// The Undo funcitonality is implemented by two interacting parts. First, the network keeps track
@@ -76,7 +147,9 @@ static MAXIMUM_ALLOWED_LOOP_LEN: usize = 500;
 ///
 /// **NOTE** This part of the code is currently commented out due to legacy hard policy use.
 /// Additionally, we have figured out that for some networks, this approach may lead to an infinite
-/// loop of messages. This approach does not work, and therefore, it should not be used.
+/// loop of messages. This approach does not work, and therefore, it should not be used. Instead,
+/// [`Network::apply_modifier_fuzz_orderings`] checks the hard policy on a number of random
+/// orderings of messages (only if the `"transient-violation"` feature is enabled).
 ///
 /// By calling `apply_modifier_check_constraint`, we explore the entire space of event orderings.
 /// This is done by checking if two events do commute or not. The exact algorithm works as follows:
@@ -138,33 +211,59 @@ static MAXIMUM_ALLOWED_LOOP_LEN: usize = 500;
 ///   When not decoupling the prefixes, we need to check $3! \cdot 3! = 36$ orderings. However, when
 ///   decoupling the two prefixes, we only need to check $3! + 3! = 12$ orderings.
 pub struct Network {
-    net: IgpNetwork,
-    links: Vec<(RouterId, RouterId)>,
+    net: Arc<IgpNetwork>,
+    igp_areas: Arc<IgpAreas>,
+    links: Arc<Vec<(RouterId, RouterId)>>,
     routers: HashMap<RouterId, Router>,
     external_routers: HashMap<RouterId, ExternalRouter>,
     known_prefixes: HashSet<Prefix>,
+    roles: Arc<HashMap<RouterId, HashSet<RouterRole>>>,
+    traffic_matrix: Option<Arc<TrafficMatrix>>,
+    prefix_ranges: HashMap<Prefix, Ipv4Net>,
     stop_after: Option<usize>,
-    config: Config,
+    ecmp: bool,
+    config: Arc<Config>,
     queue: EventQueue,
     event_history: Vec<(Event, Option<usize>)>,
     skip_queue: bool,
+    abort: Option<ControlHandle>,
+    undo_tracking: bool,
+    scheduler: Option<Box<dyn EventScheduler>>,
+    link_delays: HashMap<(RouterId, RouterId), f64>,
+    default_link_delay: f64,
+    mrai: f64,
 }
 
 impl Clone for Network {
-    /// Cloning the network does not clone the event history, and any of the undo traces.
+    /// Cloning the network does not clone the event history, and any of the undo traces. The
+    /// topology, the OSPF areas, the router roles, the traffic matrix and the configuration are
+    /// shared between the clones, and only copied once a clone modifies them (copy-on-write). This
+    /// makes cloning cheap for parallel strategies on large topologies, where every worker only
+    /// changes the configuration and the state of the routers.
     fn clone(&self) -> Self {
         // for the new queue, remove the history of all enqueued events
         Self {
             net: self.net.clone(),
+            igp_areas: self.igp_areas.clone(),
             links: self.links.clone(),
             routers: self.routers.clone(),
             external_routers: self.external_routers.clone(),
             known_prefixes: self.known_prefixes.clone(),
+            roles: self.roles.clone(),
+            traffic_matrix: self.traffic_matrix.clone(),
+            prefix_ranges: self.prefix_ranges.clone(),
             stop_after: self.stop_after,
+            ecmp: self.ecmp,
             config: self.config.clone(),
             queue: self.queue.clone(),
             event_history: Vec::new(),
             skip_queue: false,
+            abort: self.abort.clone(),
+            undo_tracking: self.undo_tracking,
+            scheduler: self.scheduler.clone(),
+            link_delays: self.link_delays.clone(),
+            default_link_delay: self.default_link_delay,
+            mrai: self.mrai,
         }
     }
 }
@@ -179,16 +278,27 @@ impl Network {
     /// Generate an empty Network
     pub fn new() -> Self {
         Self {
-            net: IgpNetwork::new(),
-            links: Vec::new(),
+            net: Arc::new(IgpNetwork::new()),
+            igp_areas: Arc::default(),
+            links: Arc::default(),
             routers: HashMap::new(),
             known_prefixes: HashSet::new(),
+            roles: Arc::default(),
+            traffic_matrix: None,
+            prefix_ranges: HashMap::new(),
             external_routers: HashMap::new(),
             stop_after: Some(DEFAULT_STOP_AFTER),
-            config: Config::new(),
+            ecmp: false,
+            config: Arc::new(Config::new()),
             queue: EventQueue::new(),
             event_history: Vec::new(),
             skip_queue: false,
+            abort: None,
+            undo_tracking: true,
+            scheduler: None,
+            link_delays: HashMap::new(),
+            default_link_delay: DEFAULT_LINK_DELAY,
+            mrai: 0.0,
         }
     }
 
@@ -196,7 +306,9 @@ impl Network {
     /// function returns the ID of the router, which can be used to reference it while confiugring
     /// the network.
     pub fn add_router<S: Into<String>>(&mut self, name: S) -> RouterId {
-        let new_router = Router::new(name.into(), self.net.add_node(()), AsId(65001));
+        let node = Arc::make_mut(&mut self.net).add_node(());
+        let mut new_router = Router::new(name.into(), node, AsId(65001));
+        new_router.set_undo_tracking(self.undo_tracking);
         let router_id = new_router.router_id();
         self.routers.insert(router_id, new_router);
         router_id
@@ -206,9 +318,12 @@ impl Network {
     /// messages, it just advertises routes from outside of the network. This function returns
     /// the ID of the router, which can be used to reference it while configuring the network.
     pub fn add_external_router<S: Into<String>>(&mut self, name: S, as_id: AsId) -> RouterId {
-        let new_router = ExternalRouter::new(name.into(), self.net.add_node(()), as_id);
+        let node = Arc::make_mut(&mut self.net).add_node(());
+        let mut new_router = ExternalRouter::new(name.into(), node, as_id);
+        new_router.set_undo_tracking(self.undo_tracking);
         let router_id = new_router.router_id();
         self.external_routers.insert(router_id, new_router);
+        Arc::make_mut(&mut self.igp_areas).add_external_router(router_id);
         router_id
     }
 
@@ -237,9 +352,10 @@ impl Network {
     /// # }
     /// ```
     pub fn add_link(&mut self, source: RouterId, target: RouterId) {
-        self.links.push((source, target));
-        self.net.add_edge(source, target, LinkWeight::infinite());
-        self.net.add_edge(target, source, LinkWeight::infinite());
+        Arc::make_mut(&mut self.links).push((source, target));
+        let net = Arc::make_mut(&mut self.net);
+        net.add_edge(source, target, LinkWeight::infinite());
+        net.add_edge(target, source, LinkWeight::infinite());
     }
 
     /// Set the provided network-wide configuration. The network first computes the patch from the
@@ -273,6 +389,9 @@ impl Network {
     /// in an undefined state, and it should be rebuilt.
     pub fn apply_modifier(&mut self, modifier: &ConfigModifier) -> Result<(), NetworkError> {
         debug!("Applying modifier: {}", printer::config_modifier(self, modifier)?);
+        if let Some(abort) = self.abort.as_ref() {
+            abort.record_states(1);
+        }
 
         // add the event to the history
         let parent_event_id = self.event_history.len();
@@ -282,98 +401,128 @@ impl Network {
         self.apply_or_undo_modifier(modifier, false, parent_event_id)
     }
 
-    /// # Transient condition verification
+    /// # Event-Ordering Fuzzing
     ///
     /// *This method is only available if the `"transient-violation"` feature is enabled!*
     ///
-    /// This function applies the modifier `n_iter` times, each time in a different ordering. At
-    /// every intermediate state, it checks the condition, which must hold at each and every state.
-    /// Notice, that for the condition, it only allows [`Condition::Reachable`] and
-    /// [`Condition::NotReachable`]. This function then returns the number of convergence series, in
-    /// which every condition is satisfied at all intermediate states of this convergence process.
+    /// This function applies the modifier `n_iter` times, each time delivering the BGP messages in
+    /// a different random order, chosen by `rng` (but messages between the same two routers are
+    /// always delivered in order). At every transient state, the `hard_policy` is evaluated as if
+    /// the transient state were the next state after the current one. Every ordering, in which the
+    /// hard policy is violated at least once, is reported as a [`FuzzViolation`]. If there never
+    /// are multiple messages to choose from, the modifier is only applied once.
     ///
-    /// It does this by shuffling the queue each ane very time before performing the next step. If
-    /// there exists no message to be reordered, this function returns
-    /// [`NetworkError::NoEventsToReorder`].
+    /// This is a pragmatic alternative to exploring the entire space of event orderings: If no
+    /// violation is found, there might still exist an ordering violating the hard policy. In the
+    /// end, the modifier is applied normally (as a single action, which can be undone with
+    /// [`Network::undo_action`]). If an error occurs, the modifier remains (partially) applied.
     #[cfg(feature = "transient-violation")]
-    pub fn apply_modifier_check_transient(
+    pub fn apply_modifier_fuzz_orderings<R: Rng>(
         &mut self,
         modifier: &ConfigModifier,
-        policy: &[Condition],
         n_iter: usize,
-    ) -> Result<usize, NetworkError> {
-        debug!("Starting transient state condition");
+        rng: &mut R,
+        hard_policy: &HardPolicy,
+    ) -> Result<Vec<FuzzViolation>, NetworkError> {
+        debug!("Fuzzing the event orderings of: {}", printer::config_modifier(self, modifier)?);
+        let mut violations: Vec<FuzzViolation> = Vec::new();
+
+        for i in 0..n_iter {
+            trace!("Fuzzing ordering {}", i);
+            let start = self.event_history.len();
+            let mut violation: Option<FuzzViolation> = None;
+            let mut policy_result: Result<(), NetworkError> = Ok(());
+
+            let has_reordered = self.apply_modifier_random_order(modifier, rng, |net| {
+                if violation.is_some() || policy_result.is_err() {
+                    return;
+                }
+                let mut policy = hard_policy.clone();
+                let mut fw_state = net.get_forwarding_state();
+                policy_result = policy.step(net, &mut fw_state);
+                if policy_result.is_ok() && !policy.check() {
+                    violation = Some(FuzzViolation {
+                        ordering: net.event_history[start..]
+                            .iter()
+                            .map(|(e, _)| {
+                                printer::event(net, e).unwrap_or_else(|_| format!("{:?}", e))
+                            })
+                            .collect(),
+                        errors: policy.last_errors(),
+                    });
+                }
+            })?;
+            policy_result?;
+            violations.extend(violation);
 
-        let mut rng = thread_rng();
-        let mut num_success: usize = 0;
+            // undo the change
+            self.undo_action()?;
 
-        for _ in 0..n_iter {
-            debug!("Restarting Transient Condition Checker...");
+            if !has_reordered {
+                break;
+            }
+        }
 
-            // prohibit the network from executing the queue right away!
-            self.skip_queue = true;
-            self.apply_modifier(modifier)?;
-            self.skip_queue = false;
+        self.apply_modifier(modifier)?;
 
-            // initially, everything is ok
-            let mut process_correct: bool = true;
-            let mut has_reordered: bool = false;
+        Ok(violations)
+    }
 
-            // do the step
-            while !self.queue.is_empty() {
-                // check if we have reordered something
-                if self.queue.len() > 1 {
-                    has_reordered = true;
-                }
+    /// Apply a single configuration modification, and execute the messages in a random order,
+    /// chosen by `rng`. Messages between the same two routers are still delivered in order (as if
+    /// they were sent over TCP). After every message, `f` is called with the network in the current
+    /// transient state. This function returns `false` if there never were multiple messages to
+    /// choose from.
+    #[cfg(feature = "transient-violation")]
+    pub(crate) fn apply_modifier_random_order<R, F>(
+        &mut self,
+        modifier: &ConfigModifier,
+        rng: &mut R,
+        mut f: F,
+    ) -> Result<bool, NetworkError>
+    where
+        R: Rng,
+        F: FnMut(&mut Self),
+    {
+        // prohibit the network from executing the queue right away!
+        self.skip_queue = true;
+        let result = self.apply_modifier(modifier);
+        self.skip_queue = false;
+        result?;
 
-                // shuffle the first element of the queue
-                let mut pos = (rng.next_u64() as usize) % self.queue.len();
-                // get the source and target from the selected message and set pos to the first
-                // message from this source to this target. This guarantees TCP message ordering to
-                // be considered.
-                if let Event::Bgp(from, to, _) = self.queue.get(pos).unwrap().0 {
-                    pos = self
-                        .queue
-                        .iter()
-                        .take(pos + 1)
-                        .filter_map(|m| match m.0 {
-                            Event::Bgp(a, b, _) => Some((a, b)),
-                            _ => None,
-                        })
-                        .position(|(a, b)| a == from && b == to)
-                        .unwrap_or(pos);
-                }
-                self.queue.swap(0, pos);
-                // perform the step
-                self.do_queue_step()?;
-                // check the transient state
-                let mut fw_state = self.get_forwarding_state();
-
-                // the policies we check must be reachability policies.
-                // The only error, we consider, is a violation of the path condition
-                if policy.iter().filter(|c| matches!(c, Condition::Reachable(_, _, _))).any(|c| {
-                    matches!(c.check(&mut fw_state), Err(PolicyError::PathCondition { .. }))
-                }) {
-                    process_correct = false;
-                    debug!("Error: invalid path for packets!");
-                }
-            }
+        let mut has_reordered: bool = false;
 
-            if !has_reordered {
-                return Err(NetworkError::NoEventsToReorder);
+        // do the step
+        while !self.queue.is_empty() {
+            // check if we have reordered something
+            if self.queue.len() > 1 {
+                has_reordered = true;
             }
 
-            if process_correct {
-                num_success += 1;
+            // shuffle the first element of the queue
+            let mut pos = (rng.next_u64() as usize) % self.queue.len();
+            // get the source and target from the selected message and set pos to the first
+            // message from this source to this target. This guarantees TCP message ordering to
+            // be considered.
+            if let Event::Bgp(from, to, _) = self.queue.get(pos).unwrap().0 {
+                pos = self
+                    .queue
+                    .iter()
+                    .take(pos + 1)
+                    .filter_map(|m| match m.0 {
+                        Event::Bgp(a, b, _) => Some((a, b)),
+                        _ => None,
+                    })
+                    .position(|(a, b)| a == from && b == to)
+                    .unwrap_or(pos);
             }
-
-            // undo the change
-            self.undo_action()?;
+            self.queue.swap(0, pos);
+            // perform the step
+            self.do_queue_step()?;
+            f(self);
         }
 
-        self.apply_modifier(modifier)?;
-
-        Ok(num_success)
+        Ok(has_reordered)
     }
 
     /*
@@ -459,6 +608,20 @@ impl Network {
         as_path: Vec<AsId>,
         med: Option<u32>,
         community: Option<u32>,
+    ) -> Result<(), NetworkError> {
+        self.advertise_external_route_with_communities(source, prefix, as_path, med, community)
+    }
+
+    /// Advertise an external route carrying any number of communities, and let the network
+    /// converge. Apart from the communities, this function is identical to
+    /// [`Network::advertise_external_route`].
+    pub fn advertise_external_route_with_communities(
+        &mut self,
+        source: RouterId,
+        prefix: Prefix,
+        as_path: Vec<AsId>,
+        med: Option<u32>,
+        communities: impl IntoIterator<Item = u32>,
     ) -> Result<(), NetworkError> {
         debug!("Advertise prefix {} on {}", prefix.0, self.get_router_name(source)?);
         // insert the prefix into the hashset
@@ -471,7 +634,7 @@ impl Network {
             .external_routers
             .get_mut(&source)
             .ok_or(NetworkError::DeviceNotFound(source))?
-            .advertise_prefix(prefix, as_path, med, community, &mut self.queue, parent_event_id);
+            .advertise_prefix(prefix, as_path, med, communities, &mut self.queue, parent_event_id);
 
         // add the event to the history
         self.event_history.push((Event::AdvertiseExternalRoute(source, route), None));
@@ -512,11 +675,16 @@ impl Network {
     ///
     /// After undo, the event queue will be empty.
     ///
+    /// If the undo tracking is disabled (see [`Network::set_undo_tracking`]), this function
+    /// returns [`NetworkError::UndoDisabled`], and the network is not changed.
+    ///
     /// # Warning
     ///
     /// Once the network is cloned, the copy will not contain the information to undo!
     pub fn undo_action(&mut self) -> Result<bool, NetworkError> {
-        if self.event_history.is_empty() {
+        if !self.undo_tracking {
+            Err(NetworkError::UndoDisabled)
+        } else if self.event_history.is_empty() {
             Ok(false)
         } else {
             while self.undo_queue_step(false)? {}
@@ -539,6 +707,12 @@ impl Network {
         &self.net
     }
 
+    /// Returns `true` if the topology of both networks is still shared in memory, i.e., if `other`
+    /// is a clone of `self` (or vice versa), and none of them has changed the topology since.
+    pub fn shares_topology(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.net, &other.net) && Arc::ptr_eq(&self.links, &other.links)
+    }
+
     /// Returns the number of devices in the topology
     pub fn num_devices(&self) -> usize {
         self.routers.len() + self.external_routers.len()
@@ -565,6 +739,148 @@ impl Network {
         self.external_routers.keys().cloned().collect()
     }
 
+    /// Annotate an internal router with a role. A router can have multiple roles. Roles do not
+    /// change the behavior of the network, but they can be used by scenario generators, policy
+    /// templates and orderings.
+    pub fn add_router_role(
+        &mut self,
+        router: RouterId,
+        role: RouterRole,
+    ) -> Result<(), NetworkError> {
+        if self.external_routers.contains_key(&router) {
+            return Err(NetworkError::DeviceIsExternalRouter(router));
+        }
+        if !self.routers.contains_key(&router) {
+            return Err(NetworkError::DeviceNotFound(router));
+        }
+        Arc::make_mut(&mut self.roles).entry(router).or_default().insert(role);
+        Ok(())
+    }
+
+    /// Remove a role from a router. This function returns `true` if the router had the role.
+    pub fn remove_router_role(&mut self, router: RouterId, role: RouterRole) -> bool {
+        if !self.has_router_role(router, role) {
+            return false;
+        }
+        let all_roles = Arc::make_mut(&mut self.roles);
+        if let Some(roles) = all_roles.get_mut(&router) {
+            roles.remove(&role);
+            if roles.is_empty() {
+                all_roles.remove(&router);
+            }
+        }
+        true
+    }
+
+    /// Returns `true` if the router is annotated with the role.
+    pub fn has_router_role(&self, router: RouterId, role: RouterRole) -> bool {
+        self.roles.get(&router).map(|r| r.contains(&role)).unwrap_or(false)
+    }
+
+    /// Returns all roles of the router, sorted.
+    pub fn get_router_roles(&self, router: RouterId) -> Vec<RouterRole> {
+        let mut roles: Vec<RouterRole> =
+            self.roles.get(&router).into_iter().flatten().cloned().collect();
+        roles.sort();
+        roles
+    }
+
+    /// Returns all routers annotated with the role, sorted by their ID.
+    pub fn get_routers_with_role(&self, role: RouterRole) -> Vec<RouterId> {
+        let mut routers: Vec<RouterId> =
+            self.roles.iter().filter(|(_, roles)| roles.contains(&role)).map(|(r, _)| *r).collect();
+        routers.sort();
+        routers
+    }
+
+    /// Returns all internal routers whose name matches the regular expression `pattern`, sorted by
+    /// their ID. The pattern must match the entire name, e.g., `"core-.*"` matches `core-1`, but
+    /// not `zrh-core-1`. An invalid pattern results in [`NetworkError::InvalidPattern`].
+    ///
+    /// ```rust
+    /// # use snowcap::netsim::Network;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut net = Network::new();
+    /// let c1 = net.add_router("core-1");
+    /// let c2 = net.add_router("core-2");
+    /// let _e1 = net.add_router("edge-1");
+    /// assert_eq!(net.routers_matching("core-.*")?, vec![c1, c2]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn routers_matching(&self, pattern: &str) -> Result<Vec<RouterId>, NetworkError> {
+        let re = full_match_regex(pattern)?;
+        let mut routers: Vec<RouterId> = self
+            .routers
+            .values()
+            .filter(|r| re.is_match(r.name()))
+            .map(|r| r.router_id())
+            .collect();
+        routers.sort();
+        Ok(routers)
+    }
+
+    /// Returns all external routers whose name matches the regular expression `pattern`, sorted by
+    /// their ID. See [`Network::routers_matching`] for details.
+    pub fn external_routers_matching(&self, pattern: &str) -> Result<Vec<RouterId>, NetworkError> {
+        let re = full_match_regex(pattern)?;
+        let mut routers: Vec<RouterId> = self
+            .external_routers
+            .values()
+            .filter(|r| re.is_match(r.name()))
+            .map(|r| r.router_id())
+            .collect();
+        routers.sort();
+        Ok(routers)
+    }
+
+    /// Annotate all internal routers based on the current configuration. Routers with at least one
+    /// eBGP session are annotated as [`RouterRole::Border`], and all others as
+    /// [`RouterRole::Core`]. Routers with at least one route reflector client are annotated as
+    /// [`RouterRole::RouteReflector`]. Existing annotations of these three roles are replaced,
+    /// while [`RouterRole::Firewall`] is kept.
+    pub fn annotate_roles_from_config(&mut self) {
+        let all_roles = Arc::make_mut(&mut self.roles);
+        for (id, router) in self.routers.iter() {
+            let is_border = router.get_bgp_sessions().any(|(_, t)| t.is_ebgp());
+            let is_rr = router.get_bgp_sessions().any(|(_, t)| *t == BgpSessionType::IBgpClient);
+            let roles = all_roles.entry(*id).or_default();
+            roles.remove(&RouterRole::Border);
+            roles.remove(&RouterRole::Core);
+            roles.remove(&RouterRole::RouteReflector);
+            roles.insert(if is_border { RouterRole::Border } else { RouterRole::Core });
+            if is_rr {
+                roles.insert(RouterRole::RouteReflector);
+            }
+        }
+    }
+
+    /// Attach a traffic matrix to the network (or remove it, if `traffic_matrix` is `None`). The
+    /// traffic matrix does not change the behavior of the network, but it is used by soft policies
+    /// to weight their cost by the demand.
+    pub fn set_traffic_matrix(&mut self, traffic_matrix: Option<TrafficMatrix>) {
+        self.traffic_matrix = traffic_matrix.map(Arc::new);
+    }
+
+    /// Returns the traffic matrix attached to the network, if any.
+    pub fn get_traffic_matrix(&self) -> Option<&TrafficMatrix> {
+        self.traffic_matrix.as_deref()
+    }
+
+    /// Assign an IPv4 address range to the prefix, and return the previous range of the prefix.
+    /// Without a range, a prefix is treated as an opaque identifier, which does not overlap with any
+    /// other prefix. With ranges, an aggregate and a more specific prefix can be modelled, and the
+    /// forwarding state uses longest-prefix matching to forward traffic towards an address (see
+    /// [`ForwardingState::get_route_to_addr`]). Ranges of IPv6 prefixes are ignored.
+    pub fn set_prefix_range(&mut self, prefix: Prefix, range: Ipv4Net) -> Option<Ipv4Net> {
+        self.prefix_ranges.insert(prefix, range)
+    }
+
+    /// Returns the IPv4 address range of the prefix, if any.
+    pub fn get_prefix_range(&self, prefix: Prefix) -> Option<Ipv4Net> {
+        self.prefix_ranges.get(&prefix).copied()
+    }
+
     /// Get the RouterID with the given name. If multiple routers have the same name, then the first
     /// occurence of this name is returned. If the name was not found, an error is returned.
     pub fn get_router_id(&self, name: impl AsRef<str>) -> Result<RouterId, NetworkError> {
@@ -589,6 +905,11 @@ impl Network {
         }
     }
 
+    /// Returns the OSPF areas of all links
+    pub fn get_igp_areas(&self) -> &IgpAreas {
+        &self.igp_areas
+    }
+
     /// Returns a hashset of all known prefixes
     pub fn get_known_prefixes(&self) -> &HashSet<Prefix> {
         &self.known_prefixes
@@ -611,6 +932,201 @@ impl Network {
         self.stop_after = stop_after;
     }
 
+    /// Returns the maximum number of messages to exchange before aborting the convergence.
+    pub fn get_msg_limit(&self) -> Option<usize> {
+        self.stop_after
+    }
+
+    /// Set the control handle, which is checked while processing the queue. As soon as the stop
+    /// command is sent, the convergence is interrupted with [`NetworkError::Aborted`], and the
+    /// network is in an undefined state (like when the network does not converge). Clones of the
+    /// network share the same handle. Every applied modifier is recorded as an explored state in
+    /// the progress of the handle.
+    pub fn set_abort(&mut self, abort: Option<ControlHandle>) {
+        self.abort = abort;
+    }
+
+    /// Returns the control handle which is checked while processing the queue.
+    pub fn get_abort(&self) -> Option<&ControlHandle> {
+        self.abort.as_ref()
+    }
+
+    /// Set the scheduler, which chooses the next message to deliver while the network converges
+    /// (see the module [`scheduler`](crate::netsim::scheduler)). If set to `None`, the messages are
+    /// delivered in the order in which they were sent. Clones of the network receive a clone of the
+    /// scheduler.
+    pub fn set_scheduler(&mut self, scheduler: Option<Box<dyn EventScheduler>>) {
+        self.scheduler = scheduler;
+    }
+
+    /// Returns the scheduler which chooses the next message to deliver, if any.
+    pub fn get_scheduler(&self) -> Option<&dyn EventScheduler> {
+        self.scheduler.as_deref()
+    }
+
+    /// Set the propagation delay of BGP messages between `a` and `b` (in both directions), used to
+    /// estimate the convergence time (see [`Network::get_convergence_time`]). The delay is not
+    /// bound to a physical link, and can also be set for iBGP sessions between routers that are not
+    /// adjacent.
+    pub fn set_link_delay(&mut self, a: RouterId, b: RouterId, delay: f64) {
+        self.link_delays.insert((a, b), delay);
+        self.link_delays.insert((b, a), delay);
+    }
+
+    /// Returns the propagation delay of BGP messages from `a` to `b`. If no delay was set, the
+    /// default delay is returned (see [`Network::set_default_link_delay`]).
+    pub fn get_link_delay(&self, a: RouterId, b: RouterId) -> f64 {
+        self.link_delays.get(&(a, b)).copied().unwrap_or(self.default_link_delay)
+    }
+
+    /// Returns all specific propagation delays of BGP messages, once for each direction.
+    pub(crate) fn link_delays(&self) -> impl Iterator<Item = (&(RouterId, RouterId), &f64)> {
+        self.link_delays.iter()
+    }
+
+    /// Set the propagation delay of all BGP messages between routers, for which no specific delay
+    /// was set. By default, this is [`DEFAULT_LINK_DELAY`].
+    pub fn set_default_link_delay(&mut self, delay: f64) {
+        self.default_link_delay = delay;
+    }
+
+    /// Returns the propagation delay of BGP messages between routers without a specific delay.
+    pub fn get_default_link_delay(&self) -> f64 {
+        self.default_link_delay
+    }
+
+    /// Set the minimum route advertisement interval (MRAI). A router sends two consecutive
+    /// messages to the same neighbor at least this amount of time apart. By default, the MRAI is
+    /// zero, and messages are sent right away.
+    pub fn set_mrai(&mut self, mrai: f64) {
+        self.mrai = mrai;
+    }
+
+    /// Returns the minimum route advertisement interval (MRAI).
+    pub fn get_mrai(&self) -> f64 {
+        self.mrai
+    }
+
+    /// Estimate the time it took the network to converge after the last action (e.g., the last
+    /// applied modifier). The first messages are sent at time zero, and every message is sent as
+    /// soon as the event causing it is processed, but not earlier than the MRAI after the previous
+    /// message on the same session. The message is then received after the delay of the session.
+    /// The convergence time is the time when the last message is received. The estimate is based
+    /// on the event history, and it is therefore consistent with [`Network::undo_action`]. If the
+    /// last action did not cause any message, the convergence time is zero.
+    pub fn get_convergence_time(&self) -> f64 {
+        let start = match self.event_history.iter().rposition(|(e, _)| !e.is_bgp_event()) {
+            Some(start) => start,
+            None => return 0.0,
+        };
+        let mut arrival: HashMap<usize, f64> = HashMap::new();
+        let mut last_sent: HashMap<(RouterId, RouterId), f64> = HashMap::new();
+        let mut convergence_time: f64 = 0.0;
+        for (id, (event, parent)) in self.event_history.iter().enumerate().skip(start + 1) {
+            if let Event::Bgp(from, to, _) = event {
+                let mut sent = parent.and_then(|p| arrival.get(&p).copied()).unwrap_or(0.0);
+                if let Some(last) = last_sent.get(&(*from, *to)) {
+                    sent = sent.max(last + self.mrai);
+                }
+                last_sent.insert((*from, *to), sent);
+                let received = sent + self.get_link_delay(*from, *to);
+                arrival.insert(id, received);
+                convergence_time = convergence_time.max(received);
+            }
+        }
+        convergence_time
+    }
+
+    /// Apply a single configuration modification (see [`Network::apply_modifier`]), and return the
+    /// estimated convergence time (see [`Network::get_convergence_time`]).
+    pub fn apply_modifier_timed(&mut self, modifier: &ConfigModifier) -> Result<f64, NetworkError> {
+        self.apply_modifier(modifier)?;
+        Ok(self.get_convergence_time())
+    }
+
+    /// Apply a single configuration modification (see [`Network::apply_modifier`]), and return a
+    /// [`StepTrace`], containing for each prefix the routers that changed their next hops, the
+    /// BGP messages exchanged during convergence, and the paths that changed. Prefixes that were
+    /// not affected are still present in the trace, but empty (see [`PrefixTrace::is_empty`]).
+    pub fn apply_modifier_with_trace(
+        &mut self,
+        modifier: &ConfigModifier,
+    ) -> Result<StepTrace, NetworkError> {
+        let mut fw_before = self.get_forwarding_state();
+        let prefixes_before = self.known_prefixes.clone();
+        let start = self.event_history.len();
+        self.apply_modifier(modifier)?;
+        let mut fw_after = self.get_forwarding_state();
+
+        let mut prefixes: HashMap<Prefix, PrefixTrace> = HashMap::new();
+        for (event, _) in self.event_history[start..].iter() {
+            if let (Event::Bgp(from, to, bgp_event), Some(prefix)) = (event, event.prefix()) {
+                prefixes.entry(prefix).or_default().messages.push((*from, *to, bgp_event.clone()));
+            }
+        }
+
+        let routers = self.get_routers();
+        for prefix in prefixes_before.union(&self.known_prefixes) {
+            let prefix = *prefix;
+            let trace = prefixes.entry(prefix).or_default();
+            for r in routers.iter() {
+                let nh_before = fw_before.get_next_hops(*r, prefix)?;
+                let nh_after = fw_after.get_next_hops(*r, prefix)?;
+                if nh_before != nh_after {
+                    trace.next_hop_changes.insert(*r, (nh_before, nh_after));
+                }
+                let paths_before = fw_before.get_all_routes(*r, prefix);
+                let paths_after = fw_after.get_all_routes(*r, prefix);
+                if paths_before != paths_after {
+                    trace.path_changes.insert(*r, (paths_before, paths_after));
+                }
+            }
+        }
+
+        Ok(StepTrace { modifier: modifier.clone(), prefixes })
+    }
+
+    /// Enable or disable equal-cost multipath (ECMP) forwarding. If enabled, the forwarding state
+    /// keeps all equal-cost IGP next hops, and the policies are checked on every path. By default,
+    /// ECMP is disabled, and every router only uses a single next hop.
+    pub fn set_ecmp(&mut self, ecmp: bool) {
+        self.ecmp = ecmp;
+    }
+
+    /// Returns `true` if equal-cost multipath forwarding is enabled.
+    pub fn get_ecmp(&self) -> bool {
+        self.ecmp
+    }
+
+    /// Enable or disable the undo tracking. By default, the undo tracking is enabled, and every
+    /// router stores the information necessary to undo each processed event. If the network is
+    /// only ever restored from a clone (snapshot) instead of calling [`Network::undo_action`], this
+    /// bookkeeping is pure overhead, and it can be disabled to speed up the simulation. While the
+    /// tracking is disabled, [`Network::undo_action`] fails with [`NetworkError::UndoDisabled`].
+    /// Disabling the tracking clears the undo stacks of all routers, and re-enabling it only
+    /// allows undoing actions which were performed afterwards. Clones of the network inherit this
+    /// setting.
+    pub fn set_undo_tracking(&mut self, enabled: bool) {
+        if !enabled {
+            self.clear_undo_stack();
+        } else if !self.undo_tracking {
+            // the event history contains events which cannot be undone.
+            self.event_history.clear();
+        }
+        self.undo_tracking = enabled;
+        for r in self.routers.values_mut() {
+            r.set_undo_tracking(enabled);
+        }
+        for r in self.external_routers.values_mut() {
+            r.set_undo_tracking(enabled);
+        }
+    }
+
+    /// Returns `true` if the undo tracking is enabled.
+    pub fn get_undo_tracking(&self) -> bool {
+        self.undo_tracking
+    }
+
     /// Returns the name of the router, if the ID was found.
     pub fn get_router_name(&self, router_id: RouterId) -> Result<&str, NetworkError> {
         if let Some(r) = self.routers.get(&router_id) {
@@ -629,6 +1145,30 @@ impl Network {
         self.event_history.len()
     }
 
+    /// Returns all events processed since the undo stack was last cleared (see
+    /// [`Network::clear_undo_stack`]), in the order in which they were processed. This trace can
+    /// be used to replay how the network reached its current state.
+    pub fn event_trace(&self) -> Vec<String> {
+        self.event_history
+            .iter()
+            .map(|(e, _)| printer::event(self, e).unwrap_or_else(|_| format!("{:?}", e)))
+            .collect()
+    }
+
+    /// Returns all routers which either received a BGP message or were reconfigured since the
+    /// undo stack was last cleared (see [`Network::clear_undo_stack`]).
+    pub(crate) fn routers_touched(&self) -> HashSet<RouterId> {
+        self.event_history
+            .iter()
+            .flat_map(|(e, _)| match e {
+                Event::Bgp(_, to, _) => vec![*to],
+                Event::Config(modifier) => modifier.routers(),
+                Event::AdvertiseExternalRoute(_, _) | Event::WithdrawExternalRoute(_, _) => vec![],
+            })
+            .filter(|r| self.routers.contains_key(r))
+            .collect()
+    }
+
     /// Clear the undo stack of all routers, and reset the event history. This does not change
     /// anything on the state of the network itself.
     pub fn clear_undo_stack(&mut self) {
@@ -803,7 +1343,7 @@ impl Network {
         parent_event_id: usize,
     ) -> Result<(), NetworkError> {
         // check that the modifier can be applied on the config
-        self.config.apply_modifier(modifier)?;
+        Arc::make_mut(&mut self.config).apply_modifier(modifier)?;
 
         // If the modifier can be applied, then everything is ok and we can do the actual change.
         match modifier {
@@ -813,12 +1353,61 @@ impl Network {
                     if !self.net.contains_edge(*source, *target) {
                         return Err(NetworkError::RoutersNotConnected(*source, *target));
                     }
-                    self.net.update_edge(*source, *target, *weight);
+                    Arc::make_mut(&mut self.net).update_edge(*source, *target, *weight);
+                    self.write_igp_fw_tables(parent_event_id, undo)
+                }
+                ConfigExpr::OspfArea { router_a, router_b, area } => {
+                    // check if the routers are connected
+                    if !self.net.contains_edge(*router_a, *router_b) {
+                        return Err(NetworkError::RoutersNotConnected(*router_a, *router_b));
+                    }
+                    Arc::make_mut(&mut self.igp_areas).set_area(*router_a, *router_b, Some(*area));
+                    self.write_igp_fw_tables(parent_event_id, undo)
+                }
+                ConfigExpr::OspfStubArea { area } => {
+                    Arc::make_mut(&mut self.igp_areas).set_stub(*area, true);
                     self.write_igp_fw_tables(parent_event_id, undo)
                 }
                 ConfigExpr::BgpSession { source, target, session_type } => {
                     self.add_bgp_session(*source, *target, *session_type, parent_event_id, undo)
                 }
+                ConfigExpr::BgpGracefulRestart { router, neighbor } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_graceful_restart(
+                            *neighbor,
+                            true,
+                            &mut self.queue,
+                            parent_event_id,
+                            undo,
+                        )?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::BgpGracefulShutdown { router, neighbor } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_graceful_shutdown(
+                            *neighbor,
+                            true,
+                            &mut self.queue,
+                            parent_event_id,
+                            undo,
+                        )?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::BgpRelationship { neighbor, relationship } => {
+                    self.set_bgp_relationship(*neighbor, Some(*relationship), parent_event_id, undo)
+                }
                 ConfigExpr::BgpRouteMap { router, direction, map } => {
                     match direction {
                         RouteMapDirection::Incoming => {
@@ -858,8 +1447,80 @@ impl Network {
                     self.routers
                         .get_mut(router)
                         .ok_or(NetworkError::DeviceNotFound(*router))?
-                        .add_static_route(*prefix, *target)?;
-                    Ok(())
+                        .add_static_route(
+                            *prefix,
+                            *target,
+                            &mut self.queue,
+                            parent_event_id,
+                            undo,
+                        )?;
+                    // the prefix of a static route is known to the network
+                    self.known_prefixes.insert(*prefix);
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::RedistributeStatic { router } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_redistribute_static(true, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::BgpAlwaysCompareMed { router } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_always_compare_med(true, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::BgpClusterId { router, cluster_id } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_cluster_id(
+                            Some(*cluster_id),
+                            &mut self.queue,
+                            parent_event_id,
+                            undo,
+                        )?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::RovPolicy { router, policy } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_rov_policy(Some(*policy), &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::Roa { router, prefix, origin } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_roa(*prefix, Some(*origin), &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
                 }
             },
             ConfigModifier::Remove(expr) => match expr {
@@ -868,12 +1529,65 @@ impl Network {
                     if !self.net.contains_edge(*source, *target) {
                         return Err(NetworkError::RoutersNotConnected(*source, *target));
                     }
-                    self.net.update_edge(*source, *target, LinkWeight::infinite());
+                    Arc::make_mut(&mut self.net).update_edge(
+                        *source,
+                        *target,
+                        LinkWeight::infinite(),
+                    );
+                    self.write_igp_fw_tables(parent_event_id, undo)
+                }
+                ConfigExpr::OspfArea { router_a, router_b, area: _ } => {
+                    // check if the routers are connected
+                    if !self.net.contains_edge(*router_a, *router_b) {
+                        return Err(NetworkError::RoutersNotConnected(*router_a, *router_b));
+                    }
+                    Arc::make_mut(&mut self.igp_areas).set_area(*router_a, *router_b, None);
+                    self.write_igp_fw_tables(parent_event_id, undo)
+                }
+                ConfigExpr::OspfStubArea { area } => {
+                    Arc::make_mut(&mut self.igp_areas).set_stub(*area, false);
                     self.write_igp_fw_tables(parent_event_id, undo)
                 }
                 ConfigExpr::BgpSession { source, target, session_type: _ } => {
                     self.remove_bgp_session(*source, *target, parent_event_id, undo)
                 }
+                ConfigExpr::BgpGracefulRestart { router, neighbor } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_graceful_restart(
+                            *neighbor,
+                            false,
+                            &mut self.queue,
+                            parent_event_id,
+                            undo,
+                        )?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::BgpGracefulShutdown { router, neighbor } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_graceful_shutdown(
+                            *neighbor,
+                            false,
+                            &mut self.queue,
+                            parent_event_id,
+                            undo,
+                        )?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::BgpRelationship { neighbor, relationship: _ } => {
+                    self.set_bgp_relationship(*neighbor, None, parent_event_id, undo)
+                }
                 ConfigExpr::BgpRouteMap { router, direction, map } => {
                     match direction {
                         RouteMapDirection::Incoming => {
@@ -914,8 +1628,73 @@ impl Network {
                     self.routers
                         .get_mut(router)
                         .ok_or(NetworkError::DeviceNotFound(*router))?
-                        .remove_static_route(*prefix)?;
-                    Ok(())
+                        .remove_static_route(*prefix, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        // fix known prefixes, if the prefix was only known due to the static route
+                        if !self.external_routers.values().any(|r| r.has_active_route(*prefix))
+                            && !self.routers.values().any(|r| r.static_routes.contains_key(prefix))
+                        {
+                            self.known_prefixes.remove(prefix);
+                        }
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::RedistributeStatic { router } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_redistribute_static(false, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::BgpAlwaysCompareMed { router } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_always_compare_med(false, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::BgpClusterId { router, cluster_id: _ } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_cluster_id(None, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::RovPolicy { router, policy: _ } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_rov_policy(None, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                ConfigExpr::Roa { router, prefix, origin: _ } => {
+                    self.routers
+                        .get_mut(router)
+                        .ok_or(NetworkError::DeviceNotFound(*router))?
+                        .set_roa(*prefix, None, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
                 }
             },
             ConfigModifier::Update { from, to } => match (from, to) {
@@ -927,7 +1706,14 @@ impl Network {
                     if !self.net.contains_edge(*s1, *t1) {
                         return Err(NetworkError::RoutersNotConnected(*s1, *t1));
                     }
-                    self.net.update_edge(*s1, *t1, *w);
+                    Arc::make_mut(&mut self.net).update_edge(*s1, *t1, *w);
+                    self.write_igp_fw_tables(parent_event_id, undo)
+                }
+                (
+                    ConfigExpr::OspfArea { router_a: a1, router_b: b1, area: _ },
+                    ConfigExpr::OspfArea { router_a: a2, router_b: b2, area },
+                ) if (a1 == a2 && b1 == b2) || (a1 == b2 && b1 == a2) => {
+                    Arc::make_mut(&mut self.igp_areas).set_area(*a2, *b2, Some(*area));
                     self.write_igp_fw_tables(parent_event_id, undo)
                 }
                 (
@@ -983,8 +1769,68 @@ impl Network {
                     self.routers
                         .get_mut(r1)
                         .ok_or(NetworkError::DeviceNotFound(*r1))?
-                        .modify_static_route(*p1, *t)?;
-                    Ok(())
+                        .modify_static_route(*p1, *t, &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                (
+                    ConfigExpr::BgpClusterId { router: r1, cluster_id: _ },
+                    ConfigExpr::BgpClusterId { router: r2, cluster_id },
+                ) if r1 == r2 => {
+                    self.routers
+                        .get_mut(r1)
+                        .ok_or(NetworkError::DeviceNotFound(*r1))?
+                        .set_cluster_id(
+                            Some(*cluster_id),
+                            &mut self.queue,
+                            parent_event_id,
+                            undo,
+                        )?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                (
+                    ConfigExpr::RovPolicy { router: r1, policy: _ },
+                    ConfigExpr::RovPolicy { router: r2, policy },
+                ) if r1 == r2 => {
+                    self.routers
+                        .get_mut(r1)
+                        .ok_or(NetworkError::DeviceNotFound(*r1))?
+                        .set_rov_policy(Some(*policy), &mut self.queue, parent_event_id, undo)?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                (
+                    ConfigExpr::Roa { router: r1, prefix: p1, origin: _ },
+                    ConfigExpr::Roa { router: r2, prefix: p2, origin },
+                ) if r1 == r2 && p1 == p2 => {
+                    self.routers.get_mut(r1).ok_or(NetworkError::DeviceNotFound(*r1))?.set_roa(
+                        *p1,
+                        Some(*origin),
+                        &mut self.queue,
+                        parent_event_id,
+                        undo,
+                    )?;
+                    if undo {
+                        Ok(())
+                    } else {
+                        self.do_queue()
+                    }
+                }
+                (
+                    ConfigExpr::BgpRelationship { neighbor: n1, relationship: _ },
+                    ConfigExpr::BgpRelationship { neighbor: n2, relationship },
+                ) if n1 == n2 => {
+                    self.set_bgp_relationship(*n1, Some(*relationship), parent_event_id, undo)
                 }
                 _ => Err(NetworkError::ConfigError(ConfigError::ConfigModifierError(
                     modifier.clone(),
@@ -1170,6 +2016,29 @@ impl Network {
         }
     }
 
+    /// Set (or remove, if `relationship` is `None`) the relationship with an external neighbor on
+    /// all internal routers, and update their BGP tables. If the `undo` flag is set, then the
+    /// routers are only reconfigured, but no update will be triggered.
+    fn set_bgp_relationship(
+        &mut self,
+        neighbor: RouterId,
+        relationship: Option<BgpRelationship>,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), NetworkError> {
+        if !self.external_routers.contains_key(&neighbor) {
+            return Err(NetworkError::DeviceNotFound(neighbor));
+        }
+        for r in self.routers.values_mut() {
+            r.set_bgp_relationship(neighbor, relationship, &mut self.queue, parent_event_id, undo)?;
+        }
+        if undo {
+            Ok(())
+        } else {
+            self.do_queue()
+        }
+    }
+
     /// Write the igp forwarding tables for all internal routers. As soon as this is done, recompute
     /// the BGP table. and run the algorithm. This will happen all at once, in a very unpredictable
     /// manner. If you want to do this more predictable, use `write_ibgp_fw_table`.
@@ -1184,7 +2053,13 @@ impl Network {
     ) -> Result<(), NetworkError> {
         // update igp table
         for r in self.routers.values_mut() {
-            r.write_igp_forwarding_table(&self.net, &mut self.queue, parent_event_id, undo)?;
+            r.write_igp_forwarding_table(
+                &self.net,
+                &self.igp_areas,
+                &mut self.queue,
+                parent_event_id,
+                undo,
+            )?;
         }
         if undo {
             Ok(())
@@ -1389,12 +2264,51 @@ impl Network {
                 }
                 remaining_iter = Some(rem - 1);
             }
+            if self.abort.as_mut().and_then(|abort| abort.try_is_stop()).unwrap_or(false) {
+                debug!("Convergence was aborted!");
+                return Err(NetworkError::Aborted);
+            }
+            self.schedule_next();
             self.do_queue_step()?;
         }
 
         Ok(())
     }
 
+    /// Move the message chosen by the scheduler (if any) to the front of the queue. Messages between
+    /// the same two routers are always delivered in order.
+    fn schedule_next(&mut self) {
+        let scheduler = match self.scheduler.as_mut() {
+            Some(scheduler) if !self.queue.is_empty() => scheduler,
+            _ => return,
+        };
+        let pending: Vec<PendingMessage> = self
+            .queue
+            .iter()
+            .filter_map(|(e, parent)| match e {
+                Event::Bgp(from, to, bgp_event) => Some(PendingMessage {
+                    from: *from,
+                    to: *to,
+                    prefix: bgp_event.prefix(),
+                    parent: *parent,
+                }),
+                _ => None,
+            })
+            .collect();
+        if pending.len() != self.queue.len() {
+            return;
+        }
+        let pos = scheduler.schedule(&pending, self.event_history.len()).min(pending.len() - 1);
+        let pos = pending
+            .iter()
+            .position(|m| m.from == pending[pos].from && m.to == pending[pos].to)
+            .unwrap_or(pos);
+        if pos > 0 {
+            let event = self.queue.remove(pos).unwrap();
+            self.queue.push_front(event);
+        }
+    }
+
     /// Executes one single step. If the result is Ok(true), then a step is successfully executed.
     /// If the result is Ok(false), then there was no event present in the queue.
     fn do_queue_step(&mut self) -> Result<bool, NetworkError> {
@@ -1634,6 +2548,12 @@ impl Network {
                 self.get_router_name(*to)?,
                 prefix.0
             ),
+            Event::Bgp(from, to, BgpEvent::RouteRefresh(prefix)) => trace!(
+                "{} -> {}: BGP route refresh prefix {}",
+                self.get_router_name(*from)?,
+                self.get_router_name(*to)?,
+                prefix.0
+            ),
             Event::Config(modifier) => trace!("{}", printer::config_modifier(self, modifier)?),
             Event::AdvertiseExternalRoute(source, route) => trace!(
                 "Router {} advertises [{}]",
@@ -1722,6 +2642,12 @@ impl Network {
 
 type ConvergenceRepetition = (Vec<Event>, Vec<Network>);
 
+/// Compile the pattern, such that it only matches entire names.
+fn full_match_regex(pattern: &str) -> Result<Regex, NetworkError> {
+    Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| NetworkError::InvalidPattern(e.to_string()))
+}
+
 /// The `PartialEq` implementation checks if two networks are identica. The implementation first
 /// checks "simple" conditions, like the configuration, before checking the state of each individual
 /// router. Use the `Network::weak_eq` function to skip some checks, which can be known beforehand.
@@ -0,0 +1,251 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # OSPF Areas
+//!
+//! This module computes the IGP forwarding table of a router, if the IGP is split into multiple
+//! OSPF areas (see [`IgpAreas`]). Every router prefers intra-area paths over inter-area paths,
+//! independent of their cost. Destinations outside of the own areas are reached through an area
+//! border router (ABR), which advertises the cost of its own path towards the destination
+//! (summary). ABRs only use summaries received in the backbone, and only advertise intra-area
+//! paths into the backbone. Stub areas don't receive any summary for external routers. Instead,
+//! they use a default route towards the closest ABR.
+
+use crate::netsim::types::{IgpAreas, IgpNetwork, LinkWeight, OspfArea, RouterId};
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Cost of the default route, which is advertised by the ABRs into stub areas.
+pub(crate) const STUB_DEFAULT_COST: LinkWeight = 1.0;
+
+/// IGP forwarding table and all equal-cost next hops, as stored on the router.
+pub(crate) type IgpTables =
+    (HashMap<RouterId, Option<(RouterId, LinkWeight)>>, HashMap<RouterId, Vec<RouterId>>);
+
+/// Result of the shortest path computation from a single router within one area, mapping every
+/// reachable router to its cost and all equal-cost next hops.
+type Spf = HashMap<RouterId, (LinkWeight, Vec<RouterId>)>;
+
+/// Returns true if the links of the IGP are split into multiple areas. Otherwise, the IGP is flat,
+/// and the forwarding tables can be computed on the graph directly.
+pub(crate) fn is_hierarchical(graph: &IgpNetwork, areas: &IgpAreas) -> bool {
+    let mut link_areas = links(graph).map(|(a, b)| areas.get_area(a, b));
+    match link_areas.next() {
+        Some(first) => link_areas.any(|a| a != first),
+        None => false,
+    }
+}
+
+/// Compute the IGP forwarding table and all equal-cost next hops of the router `source`.
+pub(crate) fn igp_tables(graph: &IgpNetwork, areas: &IgpAreas, source: RouterId) -> IgpTables {
+    let topo = AreaTopology::new(graph, areas, source);
+    let mut fw_table = HashMap::new();
+    let mut ecmp_table = HashMap::new();
+    for dst in graph.node_indices() {
+        if dst == source {
+            fw_table.insert(dst, Some((dst, 0.0)));
+            ecmp_table.insert(dst, vec![dst]);
+            continue;
+        }
+        match topo.best_path(source, dst, true) {
+            Some(path) => {
+                fw_table.insert(dst, Some((path.next_hops[0], path.cost)));
+                ecmp_table.insert(dst, path.next_hops);
+            }
+            None => {
+                fw_table.insert(dst, None);
+            }
+        }
+    }
+    (fw_table, ecmp_table)
+}
+
+/// Returns all links (in both directions) with finite weight.
+fn links(graph: &IgpNetwork) -> impl Iterator<Item = (RouterId, RouterId)> + '_ {
+    graph
+        .node_indices()
+        .flat_map(move |r| graph.edges(r))
+        .filter(|e| e.weight().is_finite())
+        .map(|e| (e.source(), e.target()))
+}
+
+/// Best path of a router towards a destination
+#[derive(Debug, Clone)]
+struct Path {
+    /// Cost of the path
+    cost: LinkWeight,
+    /// Area in which the path leaves the router
+    area: OspfArea,
+    /// All equal-cost next hops, sorted by their id
+    next_hops: Vec<RouterId>,
+}
+
+/// Areas of all routers, and the shortest paths within each area from the source and all ABRs.
+#[derive(Debug)]
+struct AreaTopology<'a> {
+    areas: &'a IgpAreas,
+    router_areas: HashMap<RouterId, BTreeSet<OspfArea>>,
+    abrs: Vec<RouterId>,
+    spf: HashMap<(RouterId, OspfArea), Spf>,
+}
+
+impl<'a> AreaTopology<'a> {
+    fn new(graph: &IgpNetwork, areas: &'a IgpAreas, source: RouterId) -> Self {
+        let mut router_areas: HashMap<RouterId, BTreeSet<OspfArea>> = HashMap::new();
+        for (a, b) in links(graph) {
+            let area = areas.get_area(a, b);
+            router_areas.entry(a).or_default().insert(area);
+            router_areas.entry(b).or_default().insert(area);
+        }
+        // external routers don't run OSPF, and are therefore never an ABR.
+        let mut abrs: Vec<RouterId> = router_areas
+            .iter()
+            .filter(|(r, a)| {
+                !areas.is_external(**r) && a.len() >= 2 && a.contains(&OspfArea::BACKBONE)
+            })
+            .map(|(r, _)| *r)
+            .collect();
+        abrs.sort();
+
+        let mut spf = HashMap::new();
+        for router in abrs.iter().chain(std::iter::once(&source)) {
+            for area in router_areas.get(router).into_iter().flatten() {
+                spf.insert((*router, *area), shortest_paths(graph, areas, *router, *area));
+            }
+        }
+
+        Self { areas, router_areas, abrs, spf }
+    }
+
+    /// Returns all areas of the router
+    fn areas_of(&self, router: RouterId) -> impl Iterator<Item = OspfArea> + '_ {
+        self.router_areas.get(&router).into_iter().flatten().copied()
+    }
+
+    fn is_abr(&self, router: RouterId) -> bool {
+        self.abrs.binary_search(&router).is_ok()
+    }
+
+    /// Compute the best path from `router` towards `dst`. Inter-area paths are only considered if
+    /// `allow_inter` is set, and if no intra-area path exists.
+    fn best_path(&self, router: RouterId, dst: RouterId, allow_inter: bool) -> Option<Path> {
+        let mut best: Option<Path> = None;
+
+        // intra-area paths are always preferred
+        for area in self.areas_of(router) {
+            if let Some((cost, next_hops)) = self.spf.get(&(router, area)).and_then(|s| s.get(&dst))
+            {
+                merge_path(&mut best, Path { cost: *cost, area, next_hops: next_hops.clone() });
+            }
+        }
+        if best.is_some() || !allow_inter {
+            return best;
+        }
+
+        // ABRs only consider summaries from the backbone
+        let candidate_areas: Vec<OspfArea> = if self.is_abr(router) {
+            vec![OspfArea::BACKBONE]
+        } else {
+            self.areas_of(router).collect()
+        };
+
+        for area in candidate_areas {
+            let spf = match self.spf.get(&(router, area)) {
+                Some(spf) => spf,
+                None => continue,
+            };
+            for abr in self.abrs.iter().filter(|abr| **abr != router) {
+                let (abr_cost, next_hops) = match spf.get(abr) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let summary = if self.areas.is_stub(area) && self.areas.is_external(dst) {
+                    STUB_DEFAULT_COST
+                } else {
+                    // only intra-area paths are advertised into the backbone, and no path is
+                    // advertised back into the area where it starts.
+                    match self.best_path(*abr, dst, !area.is_backbone()) {
+                        Some(path) if path.area != area => path.cost,
+                        _ => continue,
+                    }
+                };
+                merge_path(
+                    &mut best,
+                    Path { cost: abr_cost + summary, area, next_hops: next_hops.clone() },
+                );
+            }
+        }
+
+        best
+    }
+}
+
+/// Replace `best` by `path` if it is cheaper, or add its next hops if both have the same cost.
+fn merge_path(best: &mut Option<Path>, path: Path) {
+    match best {
+        Some(b) if (b.cost - path.cost).abs() <= 1e-6 * path.cost.max(1.0) => {
+            b.next_hops.extend(path.next_hops);
+            b.next_hops.sort();
+            b.next_hops.dedup();
+        }
+        Some(b) if b.cost < path.cost => {}
+        _ => *best = Some(path),
+    }
+}
+
+/// Compute the shortest paths (including all equal-cost next hops) from `source` to all routers
+/// reachable within the `area`.
+fn shortest_paths(graph: &IgpNetwork, areas: &IgpAreas, source: RouterId, area: OspfArea) -> Spf {
+    let mut spf: Spf = HashMap::new();
+    let mut visited: HashSet<RouterId> = HashSet::new();
+    spf.insert(source, (0.0, Vec::new()));
+    loop {
+        // pick the closest router that is not yet visited
+        let (router, cost, next_hops) = match spf
+            .iter()
+            .filter(|(r, _)| !visited.contains(*r))
+            .min_by(|(ra, (ca, _)), (rb, (cb, _))| ca.partial_cmp(cb).unwrap().then(ra.cmp(rb)))
+        {
+            Some((r, (c, nh))) => (*r, *c, nh.clone()),
+            None => break,
+        };
+        visited.insert(router);
+        for edge in graph.edges(router) {
+            let neighbor = edge.target();
+            if !edge.weight().is_finite()
+                || visited.contains(&neighbor)
+                || areas.get_area(router, neighbor) != area
+            {
+                continue;
+            }
+            let new_cost = cost + edge.weight();
+            let new_next_hops = if router == source { vec![neighbor] } else { next_hops.clone() };
+            match spf.get_mut(&neighbor) {
+                Some((c, nh)) if (*c - new_cost).abs() <= 1e-6 * new_cost.max(1.0) => {
+                    nh.extend(new_next_hops);
+                    nh.sort();
+                    nh.dedup();
+                }
+                Some((c, _)) if *c < new_cost => {}
+                _ => {
+                    spf.insert(neighbor, (new_cost, new_next_hops));
+                }
+            }
+        }
+    }
+    spf
+}
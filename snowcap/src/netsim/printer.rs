@@ -19,13 +19,14 @@
 //! Module containing helper functions to get formatted strings and print information about the
 //! network.
 
-use crate::netsim::bgp::{BgpEvent, BgpRibEntry, BgpRoute};
-use crate::netsim::config::{Config, ConfigExpr, ConfigModifier, ConfigPatch};
+use crate::netsim::bgp::{BgpEvent, BgpRelationship, BgpRibEntry, BgpRoute, RovPolicy};
+use crate::netsim::config::{Config, ConfigExpr, ConfigMeta, ConfigModifier, ConfigPatch};
 use crate::netsim::event::Event;
 use crate::netsim::network::Network;
 use crate::netsim::route_map::*;
 use crate::netsim::router::Router;
-use crate::netsim::{BgpSessionType, NetworkError, Prefix};
+use crate::netsim::{BgpSessionType, ForwardingState, NetworkError, Prefix, RouterId};
+use crate::rationale::{DependencyReason, ModifierRationale};
 
 /// Get a vector of strings, which represent the bgp table. Each `String` in the vector represents
 /// one line (one known route). The strings are formatted, and the names of the routers are
@@ -85,8 +86,20 @@ pub fn bgp_route(net: &Network, route: &BgpRoute) -> Result<String, NetworkError
     if let Some(med) = route.med {
         result.push_str(&format!(", MED: {}", med))
     }
-    if let Some(community) = route.community {
-        result.push_str(&format!(", community: {}", community))
+    if !route.communities.is_empty() {
+        result
+            .push_str(&format!(", communities: {:?}", route.communities.iter().collect::<Vec<_>>()))
+    }
+    if let Some(originator_id) = route.originator_id {
+        result.push_str(&format!(", originator: {}", net.get_router_name(originator_id)?))
+    }
+    if !route.cluster_list.is_empty() {
+        let cluster_list = route
+            .cluster_list
+            .iter()
+            .map(|x| net.get_router_name(*x))
+            .collect::<Result<Vec<_>, _>>()?;
+        result.push_str(&format!(", cluster list: {:?}", cluster_list))
     }
     Ok(result)
 }
@@ -106,6 +119,12 @@ pub fn event(net: &Network, event: &Event) -> Result<String, NetworkError> {
             net.get_router_name(*to)?,
             prefix.0
         ),
+        Event::Bgp(from, to, BgpEvent::RouteRefresh(prefix)) => format!(
+            "BGP Event: {} -> {}: Route Refresh prefix {}",
+            net.get_router_name(*from)?,
+            net.get_router_name(*to)?,
+            prefix.0
+        ),
         Event::Config(modifier) => format!("Apply Config: {}", config_modifier(net, modifier)?,),
         Event::AdvertiseExternalRoute(r, route) => {
             format!("{} advertisees route [{}]", net.get_router_name(*r)?, bgp_route(net, route)?)
@@ -135,6 +154,13 @@ pub fn config_expr(net: &Network, expr: &ConfigExpr) -> Result<String, NetworkEr
             net.get_router_name(*target)?,
             weight
         ),
+        ConfigExpr::OspfArea { router_a, router_b, area } => format!(
+            "OSPF Area: {} <-> {}: {}",
+            net.get_router_name(*router_a)?,
+            net.get_router_name(*router_b)?,
+            area
+        ),
+        ConfigExpr::OspfStubArea { area } => format!("OSPF Stub Area: {}", area),
         ConfigExpr::BgpSession { source, target, session_type } => format!(
             "BGP Session: {} -> {}: type: {}",
             net.get_router_name(*source)?,
@@ -145,6 +171,25 @@ pub fn config_expr(net: &Network, expr: &ConfigExpr) -> Result<String, NetworkEr
                 BgpSessionType::IBgpPeer => "iBGP Peer",
             }
         ),
+        ConfigExpr::BgpGracefulRestart { router, neighbor } => format!(
+            "BGP Graceful Restart: {} for session with {}",
+            net.get_router_name(*router)?,
+            net.get_router_name(*neighbor)?,
+        ),
+        ConfigExpr::BgpGracefulShutdown { router, neighbor } => format!(
+            "BGP Graceful Shutdown: {} for session with {}",
+            net.get_router_name(*router)?,
+            net.get_router_name(*neighbor)?,
+        ),
+        ConfigExpr::BgpRelationship { neighbor, relationship } => format!(
+            "BGP Relationship: {} is a {}",
+            net.get_router_name(*neighbor)?,
+            match relationship {
+                BgpRelationship::Customer => "customer",
+                BgpRelationship::Peer => "peer",
+                BgpRelationship::Provider => "provider",
+            }
+        ),
         ConfigExpr::BgpRouteMap { router, direction, map } => format!(
             "BGP Route Map on {} [{}]: {}",
             net.get_router_name(*router)?,
@@ -160,6 +205,31 @@ pub fn config_expr(net: &Network, expr: &ConfigExpr) -> Result<String, NetworkEr
             prefix.0,
             net.get_router_name(*target)?,
         ),
+        ConfigExpr::RedistributeStatic { router } => {
+            format!("Redistribute Static Routes on {}", net.get_router_name(*router)?)
+        }
+        ConfigExpr::BgpAlwaysCompareMed { router } => {
+            format!("Always compare MED on {}", net.get_router_name(*router)?)
+        }
+        ConfigExpr::BgpClusterId { router, cluster_id } => format!(
+            "BGP Cluster ID on {}: {}",
+            net.get_router_name(*router)?,
+            net.get_router_name(*cluster_id)?,
+        ),
+        ConfigExpr::RovPolicy { router, policy } => format!(
+            "ROV Policy on {}: {}",
+            net.get_router_name(*router)?,
+            match policy {
+                RovPolicy::RejectInvalid => "reject invalid".to_string(),
+                RovPolicy::DepreferInvalid(lp) => format!("deprefer invalid (local pref {})", lp),
+            }
+        ),
+        ConfigExpr::Roa { router, prefix, origin } => format!(
+            "ROA on {}: Prefix {} originated by AS{}",
+            net.get_router_name(*router)?,
+            prefix.0,
+            origin.0,
+        ),
     })
 }
 
@@ -172,6 +242,107 @@ pub fn config_modifier(net: &Network, modifier: &ConfigModifier) -> Result<Strin
     })
 }
 
+/// Returns a formatted string for the given modifier, followed by its metadata (if any).
+pub fn config_modifier_with_meta(
+    net: &Network,
+    modifier: &ConfigModifier,
+    meta: Option<&ConfigMeta>,
+) -> Result<String, NetworkError> {
+    Ok(match meta.filter(|m| !m.is_empty()) {
+        Some(meta) => format!("{}  # {}", config_modifier(net, modifier)?, meta),
+        None => config_modifier(net, modifier)?,
+    })
+}
+
+/// Returns a formatted string for the given modifier, followed by the reasons why it is placed at
+/// this position of the sequence (one reason per line, indented by four spaces). The `sequence`
+/// is required to print the modifiers on which this one depends.
+pub fn config_modifier_with_rationale(
+    net: &Network,
+    modifier: &ConfigModifier,
+    rationale: &ModifierRationale,
+    sequence: &[ConfigModifier],
+) -> Result<String, NetworkError> {
+    let mut result = config_modifier(net, modifier)?;
+    for dep in rationale.depends_on.iter() {
+        result.push_str(&format!(
+            "\n    must follow step {} ({}): applying it before {}",
+            dep.step,
+            config_modifier(net, &sequence[dep.step])?,
+            match &dep.reason {
+                DependencyReason::NoConvergence =>
+                    String::from("prevents the network from converging"),
+                DependencyReason::PolicyViolation(errors) =>
+                    errors.iter().map(|e| e.repr_with_name(net)).collect::<Vec<_>>().join("; "),
+            }
+        ));
+    }
+    Ok(result)
+}
+
+/// Returns the formatted plan, with one entry per step. Each step is prefixed with its position in
+/// the sequence. If the `rationale` is given (see
+/// [`explain_sequence`](crate::rationale::explain_sequence)), then each step is annotated with the
+/// reasons why it is placed at this position.
+pub fn plan(
+    net: &Network,
+    sequence: &[ConfigModifier],
+    rationale: Option<&[ModifierRationale]>,
+) -> Result<Vec<String>, NetworkError> {
+    sequence
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            Ok(format!(
+                "{:>3}: {}",
+                i,
+                match rationale.and_then(|r| r.get(i)) {
+                    Some(r) => config_modifier_with_rationale(net, m, r, sequence)?,
+                    None => config_modifier(net, m)?,
+                }
+            ))
+        })
+        .collect()
+}
+
+/// Returns one line for every pair of internal router and prefix whose next hops differ between
+/// `before` and `after`, formatted as `prefix router: old => new`. Next hops are separated by `|`,
+/// and a router without a route is shown as `drop`. Every prefix is colored (using ANSI escape
+/// codes), such that all changes of the same prefix are easily recognized. The lines are sorted by
+/// prefix and router.
+pub fn forwarding_diff(
+    net: &Network,
+    before: &ForwardingState,
+    after: &ForwardingState,
+) -> Result<Vec<String>, NetworkError> {
+    let fmt_hops = |hops: &[RouterId]| -> Result<String, NetworkError> {
+        if hops.is_empty() {
+            return Ok(String::from("drop"));
+        }
+        Ok(hops.iter().map(|r| net.get_router_name(*r)).collect::<Result<Vec<_>, _>>()?.join("|"))
+    };
+    let mut prefixes: Vec<Prefix> = net.get_known_prefixes().iter().cloned().collect();
+    prefixes.sort();
+    let mut result: Vec<String> = Vec::new();
+    for prefix in prefixes {
+        for router in net.get_routers() {
+            let old = before.get_next_hops(router, prefix)?;
+            let new = after.get_next_hops(router, prefix)?;
+            if old != new {
+                result.push(format!(
+                    "\x1b[{}mprefix {}\x1b[0m {}: {} => {}",
+                    31 + prefix.0 % 6,
+                    prefix.0,
+                    net.get_router_name(router)?,
+                    fmt_hops(&old)?,
+                    fmt_hops(&new)?
+                ));
+            }
+        }
+    }
+    Ok(result)
+}
+
 /// Returns a formatted string of the route map, where all router names are inserted
 pub fn route_map(net: &Network, map: &RouteMap) -> Result<String, NetworkError> {
     Ok(format!(
@@ -197,8 +368,39 @@ pub fn route_map(net: &Network, map: &RouteMap) -> Result<String, NetworkError>
 /// Print the complete configuration to stdout
 pub fn print_config(net: &Network, config: &Config) -> Result<(), NetworkError> {
     println!("Config {{");
-    for expr in config.expr.values() {
-        println!("    {}", config_expr(net, expr)?);
+    for (key, expr) in config.expr.iter() {
+        match config.meta.get(key).filter(|m| !m.is_empty()) {
+            Some(meta) => println!("    {}  # {}", config_expr(net, expr)?, meta),
+            None => println!("    {}", config_expr(net, expr)?),
+        }
+    }
+    println!("}}");
+    Ok(())
+}
+
+/// Print the (annotated) plan to stdout
+pub fn print_plan(
+    net: &Network,
+    sequence: &[ConfigModifier],
+    rationale: Option<&[ModifierRationale]>,
+) -> Result<(), NetworkError> {
+    println!("Plan {{");
+    for step in plan(net, sequence, rationale)? {
+        println!("    {}", step.replace('\n', "\n    "));
+    }
+    println!("}}");
+    Ok(())
+}
+
+/// Print the changes in the forwarding state to stdout
+pub fn print_forwarding_diff(
+    net: &Network,
+    before: &ForwardingState,
+    after: &ForwardingState,
+) -> Result<(), NetworkError> {
+    println!("ForwardingDiff {{");
+    for line in forwarding_diff(net, before, after)? {
+        println!("    {}", line);
     }
     println!("}}");
     Ok(())
@@ -208,13 +410,19 @@ pub fn print_config(net: &Network, config: &Config) -> Result<(), NetworkError>
 pub fn print_config_patch(net: &Network, patch: &ConfigPatch) -> Result<(), NetworkError> {
     println!("ConfigPatch {{");
     for modifier in patch.modifiers.iter() {
-        println!("    {}", config_modifier(net, modifier)?);
+        println!(
+            "    {}",
+            config_modifier_with_meta(net, modifier, patch.meta.get(&modifier.key()))?
+        );
     }
     println!("}}");
     Ok(())
 }
 
-fn route_map_match(net: &Network, map_match: &RouteMapMatch) -> Result<String, NetworkError> {
+pub(crate) fn route_map_match(
+    net: &Network,
+    map_match: &RouteMapMatch,
+) -> Result<String, NetworkError> {
     Ok(match map_match {
         RouteMapMatch::Neighbor(n) => format!("Neighbor {}", net.get_router_name(*n)?),
         RouteMapMatch::Prefix(c) => format!("Prefix == {}", c),
@@ -222,10 +430,11 @@ fn route_map_match(net: &Network, map_match: &RouteMapMatch) -> Result<String, N
         RouteMapMatch::NextHop(nh) => format!("NextHop == {}", net.get_router_name(*nh)?),
         RouteMapMatch::Community(Some(c)) => format!("Community {}", c),
         RouteMapMatch::Community(None) => "Community empty".to_string(),
+        RouteMapMatch::CommunityList(l) => format!("{}", l),
     })
 }
 
-fn route_map_set(net: &Network, map_set: &RouteMapSet) -> Result<String, NetworkError> {
+pub(crate) fn route_map_set(net: &Network, map_set: &RouteMapSet) -> Result<String, NetworkError> {
     Ok(match map_set {
         RouteMapSet::NextHop(nh) => format!("NextHop = {}", net.get_router_name(*nh)?),
         RouteMapSet::LocalPref(Some(lp)) => format!("LocalPref = {}", lp),
@@ -235,5 +444,10 @@ fn route_map_set(net: &Network, map_set: &RouteMapSet) -> Result<String, Network
         RouteMapSet::IgpCost(w) => format!("IgpCost = {:.2}", w),
         RouteMapSet::Community(Some(c)) => format!("Community = {}", c),
         RouteMapSet::Community(None) => "clear Community".to_string(),
+        RouteMapSet::AddCommunity(c) => format!("Community += {}", c),
+        RouteMapSet::DelCommunity(c) => format!("Community -= {}", c),
+        RouteMapSet::AddCommunities(l) => format!("Community += {:?}", l),
+        RouteMapSet::DelCommunities(l) => format!("Community -= {:?}", l),
+        RouteMapSet::PrependAsPath(a, n) => format!("prepend AS{} {} times", a.0, n),
     })
 }
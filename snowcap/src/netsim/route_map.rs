@@ -18,9 +18,18 @@
 //! # Route-Maps
 //!
 //! This module contains the necessary structures to build route maps for internal BGP routers.
+//!
+//! As in FRR, the set actions of a route map are applied in the order in which they are
+//! specified. This allows chains of community manipulations (like removing all communities, and
+//! then adding new ones), where later actions see the result of earlier ones.
+//!
+//! Use [`evaluate`] to test a sequence of route maps on a single route, without building a
+//! network around it.
 
-use crate::netsim::bgp::BgpRibEntry;
+use crate::netsim::bgp::{BgpRibEntry, BgpRoute, BgpSessionType};
 use crate::netsim::{AsId, LinkWeight, Prefix, RouterId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::fmt;
 
 /// # Main RouteMap structure
@@ -41,7 +50,7 @@ use std::fmt;
 ///     .reset_local_pref()
 ///     .build();
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RouteMap {
     /// In which order should the route maps be checked. Lower values mean that they are checked
     /// earlier.
@@ -68,7 +77,8 @@ impl RouteMap {
 
     /// Apply the route map on a route (`BgpRibEntry`). The funciton returns either None, if the
     /// route matched and the state of the `RouteMap` is set to `Deny`, or `Some(BgpRibEntry)`, with
-    /// the values modified as described, if the route matches.
+    /// the values modified as described, if the route matches. The set actions are applied in
+    /// order.
     pub(crate) fn apply(&self, mut route: BgpRibEntry) -> (bool, Option<BgpRibEntry>) {
         match self.conds.iter().all(|c| c.matches(&route)) {
             true => {
@@ -118,6 +128,103 @@ impl RouteMap {
             .filter_map(|c| if let RouteMapMatch::Neighbor(n) = c { Some(*n) } else { None })
             .next()
     }
+
+    /// Returns a copy of the route map, where every router is replaced by `f(router)`.
+    pub(crate) fn map_routers<E>(
+        &self,
+        f: &mut impl FnMut(RouterId) -> Result<RouterId, E>,
+    ) -> Result<Self, E> {
+        let conds = self
+            .conds
+            .iter()
+            .map(|c| {
+                Ok(match c {
+                    RouteMapMatch::Neighbor(r) => RouteMapMatch::Neighbor(f(*r)?),
+                    RouteMapMatch::NextHop(r) => RouteMapMatch::NextHop(f(*r)?),
+                    c => c.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, E>>()?;
+        let set = self
+            .set
+            .iter()
+            .map(|s| {
+                Ok(match s {
+                    RouteMapSet::NextHop(r) => RouteMapSet::NextHop(f(*r)?),
+                    s => s.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, E>>()?;
+        Ok(Self { order: self.order, state: self.state, conds, set })
+    }
+}
+
+/// Evaluate a sequence of route maps on a route, exactly as a router does when it receives the
+/// route from `neighbor` (`Incoming`), or when it sends the route to `neighbor` (`Outgoing`). The
+/// route maps are checked in their order, and the first route map which matches decides on the
+/// route. The function returns `None` if the route is denied, and the (possibly modified) route
+/// otherwise. Default values (like the local preference) are not applied to the route.
+///
+/// ```
+/// # use snowcap::netsim::route_map::*;
+/// # use snowcap::netsim::bgp::BgpRoute;
+/// # use snowcap::netsim::{AsId, Prefix, RouterId};
+/// # let neighbor: RouterId = 0.into();
+/// let maps = vec![
+///     RouteMapBuilder::new().order(10).deny().match_prefix(Prefix(1)).build(),
+///     RouteMapBuilder::new().order(20).allow().set_local_pref(200).build(),
+/// ];
+/// let route = BgpRoute {
+///     prefix: Prefix(0),
+///     as_path: vec![AsId(65001)],
+///     next_hop: neighbor,
+///     local_pref: None,
+///     med: None,
+///     communities: Default::default(),
+///     originator_id: None,
+///     cluster_list: Default::default(),
+/// };
+/// let result = evaluate(route.clone(), neighbor, RouteMapDirection::Incoming, &maps);
+/// assert_eq!(result.unwrap().local_pref, Some(200));
+///
+/// let denied = BgpRoute { prefix: Prefix(1), ..route };
+/// assert_eq!(evaluate(denied, neighbor, RouteMapDirection::Incoming, &maps), None);
+/// ```
+pub fn evaluate(
+    route: BgpRoute,
+    neighbor: RouterId,
+    direction: RouteMapDirection,
+    maps: &[RouteMap],
+) -> Option<BgpRoute> {
+    let entry = BgpRibEntry {
+        route,
+        from_type: BgpSessionType::IBgpPeer,
+        from_id: neighbor,
+        to_id: match direction {
+            RouteMapDirection::Incoming => None,
+            RouteMapDirection::Outgoing => Some(neighbor),
+        },
+        igp_cost: None,
+    };
+    let mut maps: Vec<&RouteMap> = maps.iter().collect();
+    maps.sort_by_key(|m| m.order);
+    apply_route_maps(maps, entry).map(|e| e.route)
+}
+
+/// Apply a sequence of route maps, sorted by their order, on a route. The first route map which
+/// matches decides on the route. Returns `None` if the route is denied.
+pub(crate) fn apply_route_maps<'a>(
+    maps: impl IntoIterator<Item = &'a RouteMap>,
+    mut entry: BgpRibEntry,
+) -> Option<BgpRibEntry> {
+    for map in maps {
+        entry = match map.apply(entry) {
+            (true, e) => return e,
+            (false, Some(e)) => e,
+            (false, None) => unreachable!(),
+        }
+    }
+    Some(entry)
 }
 
 /// # Route Map Builder
@@ -250,6 +357,20 @@ impl RouteMapBuilder {
         self
     }
 
+    /// Add a match condition to the Route-Map, matching if at least one of the communities is set
+    pub fn match_community_any(&mut self, communities: impl IntoIterator<Item = u32>) -> &mut Self {
+        let list = RouteMapMatchCommunityList::Any(communities.into_iter().collect());
+        self.conds.push(RouteMapMatch::CommunityList(list));
+        self
+    }
+
+    /// Add a match condition to the Route-Map, matching if all of the communities are set
+    pub fn match_community_all(&mut self, communities: impl IntoIterator<Item = u32>) -> &mut Self {
+        let list = RouteMapMatchCommunityList::All(communities.into_iter().collect());
+        self.conds.push(RouteMapMatch::CommunityList(list));
+        self
+    }
+
     /// Add a set expression to the Route-Map.
     pub fn add_set(&mut self, set: RouteMapSet) -> &mut Self {
         self.set.push(set);
@@ -292,18 +413,48 @@ impl RouteMapBuilder {
         self
     }
 
-    /// Add a set expression, overwriting the Community
+    /// Add a set expression, replacing all communities with the given one
     pub fn set_community(&mut self, community: u32) -> &mut Self {
         self.set.push(RouteMapSet::Community(Some(community)));
         self
     }
 
-    /// Add a set expression, resetting the Community
+    /// Add a set expression, removing all communities
     pub fn reset_community(&mut self) -> &mut Self {
         self.set.push(RouteMapSet::Community(None));
         self
     }
 
+    /// Add a set expression, adding the community while keeping all existing ones
+    pub fn add_community(&mut self, community: u32) -> &mut Self {
+        self.set.push(RouteMapSet::AddCommunity(community));
+        self
+    }
+
+    /// Add a set expression, deleting the community while keeping all other ones
+    pub fn delete_community(&mut self, community: u32) -> &mut Self {
+        self.set.push(RouteMapSet::DelCommunity(community));
+        self
+    }
+
+    /// Add a set expression, adding all communities while keeping all existing ones
+    pub fn add_communities(&mut self, communities: impl IntoIterator<Item = u32>) -> &mut Self {
+        self.set.push(RouteMapSet::AddCommunities(communities.into_iter().collect()));
+        self
+    }
+
+    /// Add a set expression, deleting all communities of the list while keeping all other ones
+    pub fn delete_communities(&mut self, communities: impl IntoIterator<Item = u32>) -> &mut Self {
+        self.set.push(RouteMapSet::DelCommunities(communities.into_iter().collect()));
+        self
+    }
+
+    /// Add a set expression, prepending the AS `n` times to the AS path
+    pub fn prepend_as_path(&mut self, as_id: AsId, n: usize) -> &mut Self {
+        self.set.push(RouteMapSet::PrependAsPath(as_id, n));
+        self
+    }
+
     /// Build the route-map.
     ///
     /// # Panics
@@ -326,7 +477,7 @@ impl RouteMapBuilder {
 }
 
 /// State of a route map, which can either be allow or deny
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RouteMapState {
     /// Set the state to allow
     Allow,
@@ -347,7 +498,7 @@ impl RouteMapState {
 }
 
 /// Match statement of the route map. Can be combined to generate complex match statements
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RouteMapMatch {
     /// Matches on the neighbor (exact value only)
     Neighbor(RouterId),
@@ -357,8 +508,11 @@ pub enum RouteMapMatch {
     AsPath(RouteMapMatchAsPath),
     /// Matches on the Next Hop (exact value)
     NextHop(RouterId),
-    /// Matches on the community (either not set, or set and matches a value or a range)
+    /// Matches on the communities (either no community is set, or at least one community matches
+    /// a value or a range)
     Community(Option<RouteMapMatchClause<u32>>),
+    /// Matches on a list of communities (either any or all of them must be set)
+    CommunityList(RouteMapMatchCommunityList),
 }
 
 impl RouteMapMatch {
@@ -370,15 +524,16 @@ impl RouteMapMatch {
             Self::AsPath(clause) => clause.matches(&entry.route.as_path),
             Self::NextHop(nh) => entry.route.next_hop == *nh,
             Self::Community(Some(clause)) => {
-                entry.route.community.as_ref().map(|c| clause.matches(c)).unwrap_or(false)
+                entry.route.communities.iter().any(|c| clause.matches(c))
             }
-            Self::Community(None) => entry.route.community.is_none(),
+            Self::Community(None) => entry.route.communities.is_empty(),
+            Self::CommunityList(list) => list.matches(&entry.route.communities),
         }
     }
 }
 
 /// Generic RouteMapMatchClause to match on all, a range or on a specific element
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RouteMapMatchClause<T> {
     /// Matches a range of values (inclusive)
     Range(T, T),
@@ -442,7 +597,7 @@ impl fmt::Display for RouteMapMatchClause<AsId> {
 }
 
 /// Clause to match on the as path
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RouteMapMatchAsPath {
     /// Contains a specific AsId
     Contains(AsId),
@@ -471,8 +626,41 @@ impl fmt::Display for RouteMapMatchAsPath {
     }
 }
 
+/// Clause to match on a list of communities
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RouteMapMatchCommunityList {
+    /// Matches if at least one of the communities is set
+    Any(BTreeSet<u32>),
+    /// Matches if all of the communities are set
+    All(BTreeSet<u32>),
+}
+
+impl RouteMapMatchCommunityList {
+    /// Returns true if the communities match the clause
+    pub fn matches(&self, communities: &BTreeSet<u32>) -> bool {
+        match self {
+            Self::Any(list) => !list.is_disjoint(communities),
+            Self::All(list) => list.is_subset(communities),
+        }
+    }
+}
+
+impl fmt::Display for RouteMapMatchCommunityList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, list) = match self {
+            RouteMapMatchCommunityList::Any(list) => ("any", list),
+            RouteMapMatchCommunityList::All(list) => ("all", list),
+        };
+        f.write_fmt(format_args!(
+            "Community {} of {{{}}}",
+            kind,
+            list.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ")
+        ))
+    }
+}
+
 /// Set action, if a route map matches
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RouteMapSet {
     /// overwrite the next hop
     NextHop(RouterId),
@@ -482,8 +670,20 @@ pub enum RouteMapSet {
     Med(Option<u32>),
     /// overwrite the distance attribute (IGP weight). This does not affect peers.
     IgpCost(LinkWeight),
-    /// overwrite the community, (None means remove the field from the route)
+    /// Rewrite the communities, replacing all existing communities with the given one (None means
+    /// removing all communities from the route)
     Community(Option<u32>),
+    /// Add a community to the route, keeping all existing ones (`additive` in FRR)
+    AddCommunity(u32),
+    /// Delete a specific community from the route, keeping all other ones
+    DelCommunity(u32),
+    /// Add a list of communities to the route, keeping all existing ones
+    AddCommunities(BTreeSet<u32>),
+    /// Delete a list of communities from the route, keeping all other ones (`comm-list delete` in
+    /// FRR)
+    DelCommunities(BTreeSet<u32>),
+    /// Prepend the AS to the AS path the given number of times, making the path look longer
+    PrependAsPath(AsId, usize),
 }
 
 impl RouteMapSet {
@@ -498,13 +698,24 @@ impl RouteMapSet {
             Self::LocalPref(lp) => entry.route.local_pref = Some(lp.unwrap_or(100)),
             Self::Med(med) => entry.route.med = Some(med.unwrap_or(0)),
             Self::IgpCost(w) => entry.igp_cost = Some(*w),
-            Self::Community(c) => entry.route.community = *c,
+            Self::Community(c) => entry.route.communities = c.iter().cloned().collect(),
+            Self::AddCommunity(c) => {
+                entry.route.communities.insert(*c);
+            }
+            Self::DelCommunity(c) => {
+                entry.route.communities.remove(c);
+            }
+            Self::AddCommunities(list) => entry.route.communities.extend(list.iter().cloned()),
+            Self::DelCommunities(list) => entry.route.communities.retain(|c| !list.contains(c)),
+            Self::PrependAsPath(as_id, n) => {
+                entry.route.as_path.splice(0..0, std::iter::repeat(*as_id).take(*n));
+            }
         }
     }
 }
 
 /// Direction of the Route Map
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum RouteMapDirection {
     /// Incoming Route Map
     Incoming,
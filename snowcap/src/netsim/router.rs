@@ -17,14 +17,20 @@
 
 //! Module defining an internal router with BGP functionality.
 
-use crate::netsim::bgp::{BgpEvent, BgpRibEntry, BgpRoute, BgpSessionType};
-use crate::netsim::route_map::RouteMap;
-use crate::netsim::types::IgpNetwork;
+use crate::netsim::bgp::{
+    BgpEvent, BgpRelationship, BgpRibEntry, BgpRoute, BgpSessionType, RovPolicy, RovState,
+};
+use crate::netsim::ospf;
+use crate::netsim::route_map::{apply_route_maps, RouteMap};
+use crate::netsim::types::{IgpAreas, IgpNetwork};
 use crate::netsim::{AsId, DeviceError, LinkWeight, Prefix, RouterId};
 use crate::netsim::{Event, EventQueue};
 use log::*;
 use petgraph::algo::bellman_ford;
-use std::collections::{hash_map::Iter, HashMap, HashSet};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction::Incoming;
+use std::cmp::Ordering;
+use std::collections::{hash_map::Iter, BTreeSet, HashMap, HashSet};
 
 /// Bgp Router
 #[derive(Debug)]
@@ -37,10 +43,36 @@ pub struct Router {
     as_id: AsId,
     /// forwarding table for IGP messages
     pub(crate) igp_forwarding_table: HashMap<RouterId, Option<(RouterId, LinkWeight)>>,
+    /// All equal-cost next hops (ECMP) towards each reachable router, sorted by their id. The next
+    /// hop stored in `igp_forwarding_table` is always one of them.
+    pub(crate) igp_ecmp_table: HashMap<RouterId, Vec<RouterId>>,
     /// Static Routes for Prefixes
     pub(crate) static_routes: HashMap<Prefix, RouterId>,
+    /// If set, static routes are redistributed into BGP, originated by this router.
+    redistribute_static: bool,
+    /// If set, the MED of routes from different neighbor ASes is compared in the BGP decision
+    /// process.
+    bgp_always_compare_med: bool,
+    /// Cluster ID used for route reflection. If `None`, the router ID is used.
+    bgp_cluster_id: Option<RouterId>,
     /// hashmap of all bgp sessions
     bgp_sessions: HashMap<RouterId, BgpSessionType>,
+    /// Set of neighbors, for which graceful restart is enabled. Routes learned from these
+    /// neighbors are retained when the session is closed.
+    bgp_graceful_restart: HashSet<RouterId>,
+    /// Closed BGP sessions, of which the learned routes are still retained (and are stale), until
+    /// the session is re-established, or graceful restart is disabled.
+    bgp_stale_sessions: HashMap<RouterId, BgpSessionType>,
+    /// Neighbors with a BGP session in graceful shutdown, whose routes get the lowest local
+    /// preference.
+    bgp_graceful_shutdown: HashSet<RouterId>,
+    /// Prefixes and peers from which the routes were requested again (ROUTE-REFRESH). The reply
+    /// must run the decision process, even if the route is unchanged, since the incoming policy
+    /// has changed.
+    bgp_pending_refresh: HashSet<(Prefix, RouterId)>,
+    /// Business relationships with external neighbors, which determine the default export policy
+    /// for routes learned from them, and for routes advertised to them.
+    bgp_relationships: HashMap<RouterId, BgpRelationship>,
     /// Table containing all received entries. It is represented as a hashmap, mapping the prefixes
     /// to another hashmap, which maps the received router id to the entry. This way, we can store
     /// one entry for every prefix and every session.
@@ -57,9 +89,14 @@ pub struct Router {
     bgp_route_maps_in: Vec<RouteMap>,
     /// BGP Route-Maps for Output
     bgp_route_maps_out: Vec<RouteMap>,
+    /// Route Origin Validation policy. If `None`, ROV is disabled on this router.
+    rov_policy: Option<RovPolicy>,
+    /// Route Origin Authorizations known to the router, mapping each prefix to the authorized
+    /// origin AS.
+    roas: HashMap<Prefix, AsId>,
     /// Stack to undo action from event mesages. Each event processed will push a new vector onto
     /// the stack, containing all actions to perform in order to undo this event.
-    undo_stack: Vec<Vec<UndoAction>>,
+    undo_stack: UndoStack,
 }
 
 impl Clone for Router {
@@ -69,15 +106,26 @@ impl Clone for Router {
             router_id: self.router_id,
             as_id: self.as_id,
             igp_forwarding_table: self.igp_forwarding_table.clone(),
+            igp_ecmp_table: self.igp_ecmp_table.clone(),
             static_routes: self.static_routes.clone(),
+            redistribute_static: self.redistribute_static,
+            bgp_always_compare_med: self.bgp_always_compare_med,
+            bgp_cluster_id: self.bgp_cluster_id,
             bgp_sessions: self.bgp_sessions.clone(),
+            bgp_graceful_restart: self.bgp_graceful_restart.clone(),
+            bgp_stale_sessions: self.bgp_stale_sessions.clone(),
+            bgp_graceful_shutdown: self.bgp_graceful_shutdown.clone(),
+            bgp_pending_refresh: self.bgp_pending_refresh.clone(),
+            bgp_relationships: self.bgp_relationships.clone(),
             bgp_rib_in: self.bgp_rib_in.clone(),
             bgp_rib: self.bgp_rib.clone(),
             bgp_rib_out: self.bgp_rib_out.clone(),
             bgp_known_prefixes: self.bgp_known_prefixes.clone(),
             bgp_route_maps_in: self.bgp_route_maps_in.clone(),
             bgp_route_maps_out: self.bgp_route_maps_out.clone(),
-            undo_stack: Vec::new(),
+            rov_policy: self.rov_policy,
+            roas: self.roas.clone(),
+            undo_stack: UndoStack::new(self.undo_stack.enabled),
         }
     }
 }
@@ -89,15 +137,26 @@ impl Router {
             router_id,
             as_id,
             igp_forwarding_table: HashMap::new(),
+            igp_ecmp_table: HashMap::new(),
             static_routes: HashMap::new(),
+            redistribute_static: false,
+            bgp_always_compare_med: false,
+            bgp_cluster_id: None,
             bgp_sessions: HashMap::new(),
+            bgp_graceful_restart: HashSet::new(),
+            bgp_stale_sessions: HashMap::new(),
+            bgp_graceful_shutdown: HashSet::new(),
+            bgp_pending_refresh: HashSet::new(),
+            bgp_relationships: HashMap::new(),
             bgp_rib_in: HashMap::new(),
             bgp_rib: HashMap::new(),
             bgp_rib_out: HashMap::new(),
             bgp_known_prefixes: HashSet::new(),
             bgp_route_maps_in: Vec::new(),
             bgp_route_maps_out: Vec::new(),
-            undo_stack: Vec::new(),
+            rov_policy: None,
+            roas: HashMap::new(),
+            undo_stack: UndoStack::new(true),
         }
     }
 
@@ -132,8 +191,22 @@ impl Router {
         parent_event_id: usize,
     ) -> Result<bool, DeviceError> {
         // since we need to handle an event, we must push a new empty element to the undo stack
-        self.undo_stack.push(Vec::new());
+        self.undo_stack.push_frame();
         match event {
+            Event::Bgp(from, to, BgpEvent::RouteRefresh(prefix)) if to == self.router_id => {
+                if !self.bgp_sessions.contains_key(&from) {
+                    debug!("Received a bgp event form a non-neighbor! Ignore event!");
+                    return Ok(false);
+                }
+                // send the route again, which was advertised to the peer.
+                if let Some(entry) = self.bgp_rib_out.get(&prefix).and_then(|rib| rib.get(&from)) {
+                    queue.push_back((
+                        Event::Bgp(self.router_id, from, BgpEvent::Update(entry.route.clone())),
+                        parent_event_id,
+                    ));
+                }
+                Ok(false)
+            }
             Event::Bgp(from, to, bgp_event) if to == self.router_id => {
                 // first, check if the event was received from a bgp peer
                 if !self.bgp_sessions.contains_key(&from) {
@@ -141,14 +214,25 @@ impl Router {
                     return Ok(false);
                 }
                 // phase 1 of BGP protocol
-                let prefix = match bgp_event {
+                let (prefix, dirty) = match bgp_event {
                     BgpEvent::Update(route) => self.insert_bgp_route(route, from)?,
                     BgpEvent::Withdraw(prefix) => self.remove_bgp_route(prefix, from),
+                    BgpEvent::RouteRefresh(_) => unreachable!("Route refresh is handled above"),
                 };
                 if self.bgp_known_prefixes.insert(prefix) {
                     // value was not present. Add to the stack
-                    self.undo_stack.last_mut().unwrap().push(UndoAction::RemoveKnownPrefix(prefix));
+                    self.undo_stack.push(UndoAction::RemoveKnownPrefix(prefix));
                 };
+                // the reply to a route refresh must be processed with the new incoming policy
+                let refreshed = self.bgp_pending_refresh.remove(&(prefix, from));
+                if refreshed {
+                    self.undo_stack.push(UndoAction::InsertPendingRefresh(prefix, from));
+                }
+                // if the Adj-RIB-In is unchanged, then neither the decision nor the dissemination
+                // can change.
+                if !dirty && !refreshed {
+                    return Ok(false);
+                }
                 // phase 2
                 let previous_next_hop = self.get_next_hop(prefix);
                 self.run_bgp_decision_process_for_prefix(prefix)?;
@@ -215,7 +299,11 @@ impl Router {
                     (Some(_), Some(o), Some(b)) if o.from_id == b.from_id => Ok(true),
                     // If the new route is better than the best route (and everything above does not
                     // hold), then something will change
-                    (Some(n), _, Some(b)) if &n > b => Ok(true),
+                    (Some(n), _, Some(b))
+                        if n.compare(b, self.bgp_always_compare_med) == Ordering::Greater =>
+                    {
+                        Ok(true)
+                    }
                     // in the final case, nothing will change
                     _ => Ok(false),
                 }
@@ -299,6 +387,18 @@ impl Router {
                             "Entry in BGP RIB OUT does not exist",
                         ))?;
                 }
+                UndoAction::RemoveStaleSession(neighbor) => {
+                    self.bgp_stale_sessions.remove(&neighbor);
+                }
+                UndoAction::InsertStaleSession(neighbor, session_type) => {
+                    self.bgp_stale_sessions.insert(neighbor, session_type);
+                }
+                UndoAction::RemovePendingRefresh(prefix, neighbor) => {
+                    self.bgp_pending_refresh.remove(&(prefix, neighbor));
+                }
+                UndoAction::InsertPendingRefresh(prefix, neighbor) => {
+                    self.bgp_pending_refresh.insert((prefix, neighbor));
+                }
                 UndoAction::RemoveKnownPrefix(prefix) => {
                     if !self.bgp_known_prefixes.remove(&prefix) {
                         return Err(DeviceError::UndoStackError(
@@ -316,6 +416,12 @@ impl Router {
         self.undo_stack.clear();
     }
 
+    /// Enable or disable the undo tracking. If disabled, `undo_last_event` will always fail with
+    /// `DeviceError::UndoStackEmpty`.
+    pub(crate) fn set_undo_tracking(&mut self, enabled: bool) {
+        self.undo_stack.set_enabled(enabled);
+    }
+
     /// Get the IGP next hop for a prefix
     pub fn get_next_hop(&self, prefix: Prefix) -> Option<RouterId> {
         // first, check the static routes
@@ -331,6 +437,21 @@ impl Router {
         }
     }
 
+    /// Get all equal-cost next hops for a prefix (ECMP), sorted by their id. A static route always
+    /// has a single next hop. If the router knows no route, an empty vector is returned.
+    pub fn get_next_hops(&self, prefix: Prefix) -> Vec<RouterId> {
+        if let Some(target) = self.static_routes.get(&prefix) {
+            return vec![*target];
+        };
+        match self.bgp_rib.get(&prefix) {
+            Some(entry) => match self.igp_ecmp_table.get(&entry.route.next_hop) {
+                Some(next_hops) if !next_hops.is_empty() => next_hops.clone(),
+                _ => self.get_next_hop(prefix).into_iter().collect(),
+            },
+            None => Vec::new(),
+        }
+    }
+
     /// Return a list of all known bgp routes for a given origin
     pub fn get_known_bgp_routes(&self, prefix: Prefix) -> Result<Vec<BgpRibEntry>, DeviceError> {
         let mut entries: Vec<BgpRibEntry> = Vec::new();
@@ -350,36 +471,120 @@ impl Router {
     }
 
     /// Add a static route. Note that the router must be a neighbor. This is not checked in this
-    /// funciton.
+    /// funciton. If static routes are redistributed, the BGP tables are updated as well (or undone
+    /// from the undo_stack, if `undo` is set).
     pub(crate) fn add_static_route(
         &mut self,
         prefix: Prefix,
         target: RouterId,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
     ) -> Result<(), DeviceError> {
-        match self.static_routes.insert(prefix, target) {
-            None => Ok(()),
-            Some(_) => Err(DeviceError::StaticRouteAlreadyExists(prefix)),
+        if self.static_routes.insert(prefix, target).is_some() {
+            return Err(DeviceError::StaticRouteAlreadyExists(prefix));
         }
+        self.update_redistributed_routes(queue, parent_event_id, undo)
     }
 
-    /// Remove an existing static route
-    pub(crate) fn remove_static_route(&mut self, prefix: Prefix) -> Result<(), DeviceError> {
-        match self.static_routes.remove(&prefix) {
-            Some(_) => Ok(()),
-            None => Err(DeviceError::NoStaticRoute(prefix)),
+    /// Remove an existing static route. If static routes are redistributed, the BGP tables are
+    /// updated as well (or undone from the undo_stack, if `undo` is set).
+    pub(crate) fn remove_static_route(
+        &mut self,
+        prefix: Prefix,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        if self.static_routes.remove(&prefix).is_none() {
+            return Err(DeviceError::NoStaticRoute(prefix));
         }
+        self.update_redistributed_routes(queue, parent_event_id, undo)
     }
 
-    /// Modify a static route
+    /// Modify a static route. If static routes are redistributed, the BGP tables are updated as
+    /// well (or undone from the undo_stack, if `undo` is set).
     pub(crate) fn modify_static_route(
         &mut self,
         prefix: Prefix,
         target: RouterId,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
     ) -> Result<(), DeviceError> {
-        match self.static_routes.insert(prefix, target) {
-            Some(_) => Ok(()),
-            None => Err(DeviceError::NoStaticRoute(prefix)),
+        if self.static_routes.insert(prefix, target).is_none() {
+            return Err(DeviceError::NoStaticRoute(prefix));
         }
+        self.update_redistributed_routes(queue, parent_event_id, undo)
+    }
+
+    /// Enable or disable the redistribution of static routes into BGP, and update the BGP tables.
+    /// If `undo` is set, undo from the undo_stack instead of updating the bgp tables.
+    pub(crate) fn set_redistribute_static(
+        &mut self,
+        enabled: bool,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        self.redistribute_static = enabled;
+        if undo {
+            self.undo_last_event()
+        } else {
+            self.update_bgp_tables(queue, parent_event_id)
+        }
+    }
+
+    /// Returns true if static routes are redistributed into BGP.
+    pub fn get_redistribute_static(&self) -> bool {
+        self.redistribute_static
+    }
+
+    /// Enable or disable comparing the MED of routes from different neighbor ASes, and update the
+    /// BGP tables. If `undo` is set, undo from the undo_stack instead of updating the bgp tables.
+    pub(crate) fn set_always_compare_med(
+        &mut self,
+        enabled: bool,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        self.bgp_always_compare_med = enabled;
+        if undo {
+            self.undo_last_event()
+        } else {
+            self.update_bgp_tables(queue, parent_event_id)
+        }
+    }
+
+    /// Returns true if the MED of routes from different neighbor ASes is compared. Otherwise, the
+    /// MED is only compared among routes from the same neighbor AS.
+    pub fn get_always_compare_med(&self) -> bool {
+        self.bgp_always_compare_med
+    }
+
+    /// Set (or remove, if `cluster_id` is `None`) the cluster ID used for route reflection, and
+    /// update the BGP tables. If `undo` is set, undo from the undo_stack instead of updating the
+    /// bgp tables.
+    pub(crate) fn set_cluster_id(
+        &mut self,
+        cluster_id: Option<RouterId>,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        self.bgp_cluster_id = cluster_id;
+        if undo {
+            self.undo_last_event()
+        } else {
+            self.update_bgp_tables(queue, parent_event_id)
+        }
+    }
+
+    /// Returns the cluster ID used for route reflection, which is the router ID, unless a
+    /// different cluster ID is configured.
+    pub fn get_cluster_id(&self) -> RouterId {
+        self.bgp_cluster_id.unwrap_or(self.router_id)
     }
 
     /// establish a bgp session with a peer
@@ -405,7 +610,12 @@ impl Router {
         if undo {
             self.undo_last_event()
         } else {
-            self.update_bgp_tables(queue, parent_event_id)
+            // If routes of a previous session were retained, flush them. The peer will re-advertise
+            // all of its routes, since the session is established again.
+            let mut stack = self.flush_stale_routes(target);
+            self.update_bgp_tables(queue, parent_event_id)?;
+            self.undo_stack.append(&mut stack);
+            Ok(())
         }
     }
 
@@ -443,8 +653,8 @@ impl Router {
         parent_event_id: usize,
         undo: bool,
     ) -> Result<(), DeviceError> {
-        match self.bgp_sessions.remove(&target) {
-            Some(_) => Ok(()),
+        let session_type = match self.bgp_sessions.remove(&target) {
+            Some(t) => Ok(t),
             None => Err(DeviceError::NoBgpSession(target)),
         }?;
 
@@ -456,10 +666,19 @@ impl Router {
         } else {
             // temporary stack to push it to the undo stack after updating
             let mut stack: Vec<UndoAction> = Vec::new();
+            // If graceful restart is enabled, retain all routes learned from the peer.
+            let retain_routes = self.bgp_graceful_restart.contains(&target);
+            if retain_routes {
+                self.bgp_stale_sessions.insert(target, session_type);
+                stack.push(UndoAction::RemoveStaleSession(target));
+            }
             for prefix in self.bgp_known_prefixes.iter() {
                 // remove the entry in the rib tables, and add it to the stack
-                if let Some(entry) =
-                    self.bgp_rib_in.get_mut(&prefix).and_then(|rib| rib.remove(&target))
+                if let Some(entry) = self
+                    .bgp_rib_in
+                    .get_mut(&prefix)
+                    .filter(|_| !retain_routes)
+                    .and_then(|rib| rib.remove(&target))
                 {
                     stack.push(UndoAction::UpdateBgpRibIn(*prefix, target, entry));
                 }
@@ -471,11 +690,132 @@ impl Router {
             }
 
             self.update_bgp_tables(queue, parent_event_id)?;
-            self.undo_stack.last_mut().unwrap().append(&mut stack);
+            self.undo_stack.append(&mut stack);
+            Ok(())
+        }
+    }
+
+    /// Enable or disable graceful restart for the session with the neighbor, and update the BGP
+    /// tables. If graceful restart is enabled, all routes learned from the neighbor are retained
+    /// when the session is closed, until the session is established again. Disabling graceful
+    /// restart flushes all routes that are currently retained. If `undo` is set, undo from the
+    /// undo_stack instead of updating the bgp tables.
+    pub(crate) fn set_graceful_restart(
+        &mut self,
+        neighbor: RouterId,
+        enabled: bool,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        if enabled {
+            self.bgp_graceful_restart.insert(neighbor);
+        } else {
+            self.bgp_graceful_restart.remove(&neighbor);
+        }
+
+        if undo {
+            self.undo_last_event()
+        } else {
+            let mut stack = if enabled { Vec::new() } else { self.flush_stale_routes(neighbor) };
+            self.update_bgp_tables(queue, parent_event_id)?;
+            self.undo_stack.append(&mut stack);
             Ok(())
         }
     }
 
+    /// Returns `true` if graceful restart is enabled for the session with the neighbor.
+    pub fn has_graceful_restart(&self, neighbor: RouterId) -> bool {
+        self.bgp_graceful_restart.contains(&neighbor)
+    }
+
+    /// Start (or stop) the graceful shutdown of the session with the neighbor, and update the BGP
+    /// tables. During graceful shutdown, all routes learned from the neighbor get the lowest local
+    /// preference. If `undo` is set, undo from the undo_stack instead of updating the bgp tables.
+    pub(crate) fn set_graceful_shutdown(
+        &mut self,
+        neighbor: RouterId,
+        enabled: bool,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        if enabled {
+            self.bgp_graceful_shutdown.insert(neighbor);
+        } else {
+            self.bgp_graceful_shutdown.remove(&neighbor);
+        }
+        if undo {
+            self.undo_last_event()
+        } else {
+            self.update_bgp_tables(queue, parent_event_id)
+        }
+    }
+
+    /// Returns `true` if the session with the neighbor is in graceful shutdown.
+    pub fn has_graceful_shutdown(&self, neighbor: RouterId) -> bool {
+        self.bgp_graceful_shutdown.contains(&neighbor)
+    }
+
+    /// Returns an iterator over all closed sessions, of which the routes are still retained.
+    pub fn get_stale_bgp_sessions(&self) -> Iter<'_, RouterId, BgpSessionType> {
+        self.bgp_stale_sessions.iter()
+    }
+
+    /// Set (or remove, if `relationship` is `None`) the business relationship with the external
+    /// neighbor, and update the BGP tables. The relationship is used for routes learned from the
+    /// neighbor (even via iBGP), and for routes advertised to the neighbor. If `undo` is set, undo
+    /// from the undo_stack instead of updating the bgp tables.
+    pub(crate) fn set_bgp_relationship(
+        &mut self,
+        neighbor: RouterId,
+        relationship: Option<BgpRelationship>,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        match relationship {
+            Some(relationship) => self.bgp_relationships.insert(neighbor, relationship),
+            None => self.bgp_relationships.remove(&neighbor),
+        };
+        if undo {
+            self.undo_last_event()
+        } else {
+            self.update_bgp_tables(queue, parent_event_id)
+        }
+    }
+
+    /// Returns the business relationship with the external neighbor, or `None` if it is not set.
+    pub fn get_bgp_relationship(&self, neighbor: RouterId) -> Option<BgpRelationship> {
+        self.bgp_relationships.get(&neighbor).copied()
+    }
+
+    /// Returns all eBGP neighbors to which the router currently exports a route for the prefix
+    /// that violates the valley-free property, i.e., a route learned from a peer or a provider is
+    /// exported to a peer or a provider. For this check, neighbors without a relationship are
+    /// treated as non-customers. The result is sorted.
+    pub fn get_valley_exports(&self, prefix: Prefix) -> Vec<RouterId> {
+        let ingress = match self.bgp_rib.get(&prefix) {
+            Some(entry) => entry.route.next_hop,
+            None => return Vec::new(),
+        };
+        if self.get_bgp_relationship(ingress).map(|r| r.is_customer()).unwrap_or(false) {
+            return Vec::new();
+        }
+        let mut result: Vec<RouterId> = self
+            .bgp_rib_out
+            .get(&prefix)
+            .into_iter()
+            .flat_map(|rib| rib.keys())
+            .filter(|n| *n != &ingress)
+            .filter(|n| self.bgp_sessions.get(*n).map(|t| t.is_ebgp()).unwrap_or(false))
+            .filter(|n| !self.get_bgp_relationship(**n).map(|r| r.is_customer()).unwrap_or(false))
+            .copied()
+            .collect();
+        result.sort();
+        result
+    }
+
     /// Returns an interator over all BGP sessions
     pub fn get_bgp_sessions(&self) -> Iter<'_, RouterId, BgpSessionType> {
         self.bgp_sessions.iter()
@@ -487,8 +827,8 @@ impl Router {
         self.bgp_sessions.get(&neighbor).copied()
     }
 
-    /// Add a route-map for the input and update the BGP tables. If `undo` is set, undo from the
-    /// undo_stack instead of updating the bgp tables.
+    /// Add a route-map for the input and request a route refresh from all peers. If `undo` is set,
+    /// undo from the undo_stack instead of requesting a route refresh.
     pub(crate) fn add_bgp_route_map_in(
         &mut self,
         map: RouteMap,
@@ -506,12 +846,12 @@ impl Router {
         if undo {
             self.undo_last_event()
         } else {
-            self.update_bgp_tables(queue, parent_event_id)
+            self.request_route_refresh(queue, parent_event_id)
         }
     }
 
-    /// Remove a route-map for the input and update the BGP tables. If `undo` is set, undo from the
-    /// undo_stack instead of updating the bgp tables.
+    /// Remove a route-map for the input and request a route refresh from all peers. If `undo` is
+    /// set, undo from the undo_stack instead of requesting a route refresh.
     pub(crate) fn remove_bgp_route_map_in(
         &mut self,
         order: usize,
@@ -528,12 +868,12 @@ impl Router {
         if undo {
             self.undo_last_event()
         } else {
-            self.update_bgp_tables(queue, parent_event_id)
+            self.request_route_refresh(queue, parent_event_id)
         }
     }
 
-    /// Modify an existing route-map for the input and update the BGP tables. If `undo` is set, undo
-    /// from the undo_stack instead of updating the bgp tables.
+    /// Modify an existing route-map for the input and request a route refresh from all peers. If
+    /// `undo` is set, undo from the undo_stack instead of requesting a route refresh.
     pub(crate) fn modify_bgp_route_map_in(
         &mut self,
         order: usize,
@@ -561,7 +901,7 @@ impl Router {
         if undo {
             self.undo_last_event()
         } else {
-            self.update_bgp_tables(queue, parent_event_id)
+            self.request_route_refresh(queue, parent_event_id)
         }
     }
 
@@ -644,16 +984,31 @@ impl Router {
     }
 
     /// write forawrding table based on graph
-    /// This function requres that all RouterIds are set to the GraphId, and update the BGP tables
+    /// This function requres that all RouterIds are set to the GraphId, and update the BGP tables.
+    /// If the links are split into multiple OSPF areas, the forwarding table is computed based on
+    /// the area hierarchy (see [`IgpAreas`]).
     pub(crate) fn write_igp_forwarding_table(
         &mut self,
         graph: &IgpNetwork,
+        areas: &IgpAreas,
         queue: &mut EventQueue,
         parent_event_id: usize,
         undo: bool,
     ) -> Result<(), DeviceError> {
-        // clear the forwarding table
-        self.igp_forwarding_table = HashMap::new();
+        // clear the forwarding table, but remember the old one to find the affected prefixes
+        let old_table = std::mem::take(&mut self.igp_forwarding_table);
+
+        if ospf::is_hierarchical(graph, areas) {
+            let (fw_table, ecmp_table) = ospf::igp_tables(graph, areas, self.router_id);
+            self.igp_forwarding_table = fw_table;
+            self.igp_ecmp_table = ecmp_table;
+            return if undo {
+                self.undo_last_event()
+            } else {
+                let dirty = self.igp_dirty_prefixes(&old_table)?;
+                self.update_bgp_tables_for(Some(dirty), queue, parent_event_id)
+            };
+        }
         // compute shortest path to all other nodes in the graph
         let (path_weights, predecessors) = bellman_ford(graph, self.router_id).unwrap();
         let mut paths: Vec<(RouterId, LinkWeight, Option<RouterId>)> = path_weights
@@ -663,7 +1018,7 @@ impl Router {
             .map(|(i, (w, p))| ((i as u32).into(), w, p))
             .collect();
         paths.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-        for (router, cost, predecessor) in paths {
+        for &(router, cost, predecessor) in paths.iter() {
             if cost.is_infinite() {
                 self.igp_forwarding_table.insert(router, None);
                 continue;
@@ -684,6 +1039,94 @@ impl Router {
             };
             self.igp_forwarding_table.insert(router, Some((next_hop, cost)));
         }
+
+        // Compute all equal-cost next hops. Every predecessor on a shortest path contributes its
+        // own next hops, which are already known, because the paths are sorted by their cost.
+        self.igp_ecmp_table = HashMap::new();
+        let costs: HashMap<RouterId, LinkWeight> = paths.iter().map(|(r, c, _)| (*r, *c)).collect();
+        for &(router, cost, _) in paths.iter() {
+            if cost.is_infinite() {
+                continue;
+            }
+            if router == self.router_id {
+                self.igp_ecmp_table.insert(router, vec![router]);
+                continue;
+            }
+            let mut next_hops: Vec<RouterId> = Vec::new();
+            for edge in graph.edges_directed(router, Incoming) {
+                let predecessor = edge.source();
+                let pred_cost = costs.get(&predecessor).copied().unwrap_or(LinkWeight::INFINITY);
+                if pred_cost.is_infinite()
+                    || edge.weight().is_infinite()
+                    || (pred_cost + edge.weight() - cost).abs() > 1e-6 * cost.max(1.0)
+                {
+                    continue;
+                }
+                if predecessor == self.router_id {
+                    next_hops.push(router);
+                } else if let Some(nhs) = self.igp_ecmp_table.get(&predecessor) {
+                    next_hops.extend(nhs.iter().cloned());
+                }
+            }
+            next_hops.sort();
+            next_hops.dedup();
+            if next_hops.is_empty() {
+                // fall back to the single next hop (only possible with zero link weights)
+                next_hops.extend(self.igp_forwarding_table[&router].map(|(nh, _)| nh));
+            }
+            self.igp_ecmp_table.insert(router, next_hops);
+        }
+
+        if undo {
+            self.undo_last_event()
+        } else {
+            let dirty = self.igp_dirty_prefixes(&old_table)?;
+            self.update_bgp_tables_for(Some(dirty), queue, parent_event_id)
+        }
+    }
+
+    /// Returns all prefixes for which the decision process might change after the IGP forwarding
+    /// table was updated. The decision process only depends on the IGP table via the cost to the
+    /// next hop of the routes in the Adj-RIB-In (after applying the incoming route maps). Hence, a
+    /// prefix is dirty if the entry of any such next hop has changed.
+    fn igp_dirty_prefixes(
+        &self,
+        old_table: &HashMap<RouterId, Option<(RouterId, LinkWeight)>>,
+    ) -> Result<HashSet<Prefix>, DeviceError> {
+        let changed: HashSet<RouterId> = self
+            .igp_forwarding_table
+            .iter()
+            .filter(|(r, entry)| old_table.get(r) != Some(entry))
+            .map(|(r, _)| *r)
+            .chain(old_table.keys().filter(|r| !self.igp_forwarding_table.contains_key(r)).cloned())
+            .collect();
+        if changed.is_empty() {
+            return Ok(HashSet::new());
+        }
+        Ok(self
+            .bgp_rib_in
+            .iter()
+            .filter(|(_, rib_in)| {
+                rib_in.values().any(|entry| {
+                    apply_route_maps(&self.bgp_route_maps_in, entry.clone())
+                        .map(|e| changed.contains(&e.route.next_hop))
+                        .unwrap_or(false)
+                })
+            })
+            .map(|(prefix, _)| *prefix)
+            .collect())
+    }
+
+    /// Set (or remove, if `policy` is `None`) the Route Origin Validation policy and update the BGP
+    /// tables. If `undo` is set, undo from the undo_stack instead of updating the bgp tables.
+    pub(crate) fn set_rov_policy(
+        &mut self,
+        policy: Option<RovPolicy>,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        self.rov_policy = policy;
         if undo {
             self.undo_last_event()
         } else {
@@ -691,25 +1134,131 @@ impl Router {
         }
     }
 
+    /// Returns the Route Origin Validation policy, or `None` if ROV is disabled.
+    pub fn get_rov_policy(&self) -> Option<RovPolicy> {
+        self.rov_policy
+    }
+
+    /// Set (or remove, if `origin` is `None`) the Route Origin Authorization for a prefix and
+    /// update the BGP tables. If `undo` is set, undo from the undo_stack instead of updating the
+    /// bgp tables.
+    pub(crate) fn set_roa(
+        &mut self,
+        prefix: Prefix,
+        origin: Option<AsId>,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        match origin {
+            Some(origin) => self.roas.insert(prefix, origin),
+            None => self.roas.remove(&prefix),
+        };
+        if undo {
+            self.undo_last_event()
+        } else {
+            self.update_bgp_tables(queue, parent_event_id)
+        }
+    }
+
+    /// Returns the RPKI validation state of the route, based on the ROAs known to this router. The
+    /// origin of the route is the last AS in the AS path. The state is computed independently of
+    /// whether ROV is enabled on the router.
+    pub fn get_rov_state(&self, route: &BgpRoute) -> RovState {
+        match self.roas.get(&route.prefix) {
+            None => RovState::NotFound,
+            Some(origin) if route.as_path.last() == Some(origin) => RovState::Valid,
+            Some(_) => RovState::Invalid,
+        }
+    }
+
     /// Update the bgp tables only, This funciton also causes the undo stack to be created.
     fn update_bgp_tables(
         &mut self,
         queue: &mut EventQueue,
         parent_event_id: usize,
+    ) -> Result<(), DeviceError> {
+        self.update_bgp_tables_for(None, queue, parent_event_id)
+    }
+
+    /// Update the bgp tables, but only run the decision process and the route dissemination for
+    /// the `dirty` prefixes (or for all known prefixes, if `dirty` is `None`). All other prefixes
+    /// must be unaffected by the change. This funciton also causes the undo stack to be created.
+    fn update_bgp_tables_for(
+        &mut self,
+        dirty: Option<HashSet<Prefix>>,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
     ) -> Result<(), DeviceError> {
         // first, push an element to the stack
-        self.undo_stack.push(Vec::new());
+        self.undo_stack.push_frame();
+        // redistributed static routes make their prefixes known
+        if self.redistribute_static {
+            for prefix in self.static_routes.keys() {
+                if self.bgp_known_prefixes.insert(*prefix) {
+                    self.undo_stack.push(UndoAction::RemoveKnownPrefix(*prefix));
+                }
+            }
+        }
+        let prefixes: Vec<Prefix> = match dirty {
+            Some(dirty) => {
+                self.bgp_known_prefixes.iter().filter(|p| dirty.contains(p)).cloned().collect()
+            }
+            None => self.bgp_known_prefixes.iter().cloned().collect(),
+        };
         // run the decision process
-        for prefix in self.bgp_known_prefixes.clone() {
-            self.run_bgp_decision_process_for_prefix(prefix)?
+        for prefix in prefixes.iter() {
+            self.run_bgp_decision_process_for_prefix(*prefix)?
         }
         // run the route dissemination
-        for prefix in self.bgp_known_prefixes.clone() {
+        for prefix in prefixes {
             self.run_bgp_route_dissemination_for_prefix(prefix, queue, parent_event_id)?
         }
         Ok(())
     }
 
+    /// Update the bgp tables after a static route was changed, but only if static routes are
+    /// redistributed. If `undo` is set, undo from the undo_stack instead.
+    fn update_redistributed_routes(
+        &mut self,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+        undo: bool,
+    ) -> Result<(), DeviceError> {
+        if !self.redistribute_static {
+            Ok(())
+        } else if undo {
+            self.undo_last_event()
+        } else {
+            self.update_bgp_tables(queue, parent_event_id)
+        }
+    }
+
+    /// Request all peers to send their routes again (ROUTE-REFRESH), instead of resetting the
+    /// sessions. This is used when the incoming policy changes, as done by soft reconfiguration.
+    /// The BGP tables are updated once the refreshed routes are received. This funciton also
+    /// causes the undo stack to be created.
+    fn request_route_refresh(
+        &mut self,
+        queue: &mut EventQueue,
+        parent_event_id: usize,
+    ) -> Result<(), DeviceError> {
+        // first, push an element to the stack
+        self.undo_stack.push_frame();
+        for prefix in self.bgp_known_prefixes.iter() {
+            for peer in self.bgp_sessions.keys() {
+                if self.bgp_pending_refresh.insert((*prefix, *peer)) {
+                    self.undo_stack.push(UndoAction::RemovePendingRefresh(*prefix, *peer));
+                }
+                queue.push_back((
+                    Event::Bgp(self.router_id, *peer, BgpEvent::RouteRefresh(*prefix)),
+                    parent_event_id,
+                ));
+            }
+        }
+        Ok(())
+    }
+
     /// This function checks if all BGP tables are the same for all prefixes
     pub(crate) fn compare_bgp_table(&self, other: &Self) -> bool {
         if self.bgp_rib != other.bgp_rib {
@@ -740,24 +1289,19 @@ impl Router {
     fn run_bgp_decision_process_for_prefix(&mut self, prefix: Prefix) -> Result<(), DeviceError> {
         // search the best route and compare
         let old_entry = self.bgp_rib.get(&prefix);
-        let mut new_entry = None;
+        // a redistributed static route is originated locally, and competes with the learned ones
+        let mut candidates: Vec<BgpRibEntry> =
+            self.get_redistributed_route(prefix).into_iter().collect();
 
         // find the new best route
         if let Some(rib_in) = self.bgp_rib_in.get(&prefix) {
             for entry_unprocessed in rib_in.values() {
-                let entry = match self.process_bgp_rib_in_route(entry_unprocessed.clone())? {
-                    Some(e) => e,
-                    None => continue,
-                };
-                let mut better = true;
-                if let Some(current_best) = new_entry.as_ref() {
-                    better = &entry > current_best;
-                }
-                if better {
-                    new_entry = Some(entry)
+                if let Some(entry) = self.process_bgp_rib_in_route(entry_unprocessed.clone())? {
+                    candidates.push(entry);
                 }
             }
         }
+        let new_entry = BgpRibEntry::select_best(candidates, self.bgp_always_compare_med);
 
         // check if the entry will get changed
         if new_entry.as_ref() != old_entry {
@@ -765,20 +1309,13 @@ impl Router {
             if let Some(new_entry) = new_entry {
                 // insert the new entry, and add the change to the undo stack
                 match self.bgp_rib.insert(prefix, new_entry) {
-                    Some(old_entry) => self
-                        .undo_stack
-                        .last_mut()
-                        .unwrap()
-                        .push(UndoAction::UpdateBgpRib(prefix, old_entry)),
-                    None => {
-                        self.undo_stack.last_mut().unwrap().push(UndoAction::RemoveBgpRib(prefix))
+                    Some(old_entry) => {
+                        self.undo_stack.push(UndoAction::UpdateBgpRib(prefix, old_entry))
                     }
+                    None => self.undo_stack.push(UndoAction::RemoveBgpRib(prefix)),
                 };
             } else if let Some(old_entry) = self.bgp_rib.remove(&prefix) {
-                self.undo_stack
-                    .last_mut()
-                    .unwrap()
-                    .push(UndoAction::UpdateBgpRib(prefix, old_entry));
+                self.undo_stack.push(UndoAction::UpdateBgpRib(prefix, old_entry));
             }
         }
         Ok(())
@@ -811,7 +1348,9 @@ impl Router {
                 }
                 (Some(best_r), Some(_)) => {
                     // Route information was changed
-                    if self.should_export_route(best_r.from_id, *peer, *peer_type)? {
+                    if self.should_export_route(best_r.from_id, *peer, *peer_type)?
+                        && self.relationship_allows_export(prefix, *peer)
+                    {
                         // update the route
                         let old_entry = self
                             .bgp_rib_out
@@ -819,10 +1358,7 @@ impl Router {
                             .and_then(|rib| rib.insert(*peer, best_r.clone()))
                             .unwrap();
                         // push the update to the undo stack
-                        self.undo_stack
-                            .last_mut()
-                            .unwrap()
-                            .push(UndoAction::UpdateBgpRibOut(prefix, *peer, old_entry));
+                        self.undo_stack.push(UndoAction::UpdateBgpRibOut(prefix, *peer, old_entry));
                         Some(BgpEvent::Update(best_r.route))
                     } else {
                         // send a withdraw of the old route.
@@ -832,25 +1368,21 @@ impl Router {
                             .and_then(|rib| rib.remove(&peer))
                             .unwrap();
                         // push the update to the undo stack
-                        self.undo_stack
-                            .last_mut()
-                            .unwrap()
-                            .push(UndoAction::UpdateBgpRibOut(prefix, *peer, old_entry));
+                        self.undo_stack.push(UndoAction::UpdateBgpRibOut(prefix, *peer, old_entry));
                         Some(BgpEvent::Withdraw(prefix))
                     }
                 }
                 (Some(best_r), None) => {
                     // New route information received
-                    if self.should_export_route(best_r.from_id, *peer, *peer_type)? {
+                    if self.should_export_route(best_r.from_id, *peer, *peer_type)?
+                        && self.relationship_allows_export(prefix, *peer)
+                    {
                         // send the route, but update the undo stack accordingly
                         self.bgp_rib_out
                             .get_mut(&prefix)
                             .and_then(|rib| rib.insert(*peer, best_r.clone()));
                         // push the remove action to the stack, because there was no route before.
-                        self.undo_stack
-                            .last_mut()
-                            .unwrap()
-                            .push(UndoAction::RemoveBgpRibOut(prefix, *peer));
+                        self.undo_stack.push(UndoAction::RemoveBgpRibOut(prefix, *peer));
                         Some(BgpEvent::Update(best_r.route))
                     } else {
                         None
@@ -864,10 +1396,7 @@ impl Router {
                         .and_then(|rib| rib.remove(&peer))
                         .unwrap();
                     // push the update action to the undo stack
-                    self.undo_stack
-                        .last_mut()
-                        .unwrap()
-                        .push(UndoAction::UpdateBgpRibOut(prefix, *peer, old_entry));
+                    self.undo_stack.push(UndoAction::UpdateBgpRibOut(prefix, *peer, old_entry));
                     Some(BgpEvent::Withdraw(prefix))
                 }
                 (None, None) => {
@@ -885,8 +1414,13 @@ impl Router {
     }
 
     /// Tries to insert the route into the bgp_rib_in table. If the same route already exists in the table,
-    /// replace the route. It returns the prefix for which the route was inserted
-    fn insert_bgp_route(&mut self, route: BgpRoute, from: RouterId) -> Result<Prefix, DeviceError> {
+    /// replace the route. It returns the prefix for which the route was inserted, and whether the
+    /// table has changed (i.e., the prefix is dirty). An identical route is not inserted again.
+    fn insert_bgp_route(
+        &mut self,
+        route: BgpRoute,
+        from: RouterId,
+    ) -> Result<(Prefix, bool), DeviceError> {
         let from_type = *self.bgp_sessions.get(&from).ok_or(DeviceError::NoBgpSession(from))?;
 
         // the incoming bgp routes should not be processed here!
@@ -905,55 +1439,101 @@ impl Router {
             self.bgp_rib_in.get_mut(&prefix).unwrap()
         };
 
+        // nothing changes if the same route was already received from the neighbor
+        let unchanged = rib_in
+            .get(&from)
+            .map(|old| old.from_type == from_type && same_route(&old.route, &new_entry.route))
+            .unwrap_or(false);
+        if unchanged {
+            return Ok((prefix, false));
+        }
+
         // insert the new route. Also, update the undo action to be able to go back.
         match rib_in.insert(from, new_entry) {
-            Some(old_entry) => self
-                .undo_stack
-                .last_mut()
-                .unwrap()
-                .push(UndoAction::UpdateBgpRibIn(prefix, from, old_entry)),
-            None => {
-                self.undo_stack.last_mut().unwrap().push(UndoAction::RemoveBgpRibIn(prefix, from))
+            Some(old_entry) => {
+                self.undo_stack.push(UndoAction::UpdateBgpRibIn(prefix, from, old_entry))
             }
+            None => self.undo_stack.push(UndoAction::RemoveBgpRibIn(prefix, from)),
         }
 
-        Ok(prefix)
+        Ok((prefix, true))
     }
 
     /// remove an existing bgp route in bgp_rib_in and returns the prefix for which the route was
-    /// inserted.
-    fn remove_bgp_route(&mut self, prefix: Prefix, from: RouterId) -> Prefix {
+    /// inserted, and whether an entry was removed (i.e., the prefix is dirty).
+    fn remove_bgp_route(&mut self, prefix: Prefix, from: RouterId) -> (Prefix, bool) {
         // check if the prefix does exist in the table. if there was an entry, then also add it to
         // the undo action.
         if let Some(old_entry) = self.bgp_rib_in.get_mut(&prefix).and_then(|rib| rib.remove(&from))
         {
-            self.undo_stack
-                .last_mut()
-                .unwrap()
-                .push(UndoAction::UpdateBgpRibIn(prefix, from, old_entry));
+            self.undo_stack.push(UndoAction::UpdateBgpRibIn(prefix, from, old_entry));
+            (prefix, true)
+        } else {
+            (prefix, false)
+        }
+    }
+
+    /// Remove all retained routes learned from the neighbor, if the session with the neighbor is
+    /// stale. The actions to undo this are returned, and must be added to the undo stack after
+    /// updating the bgp tables.
+    fn flush_stale_routes(&mut self, neighbor: RouterId) -> Vec<UndoAction> {
+        let mut stack: Vec<UndoAction> = Vec::new();
+        if let Some(session_type) = self.bgp_stale_sessions.remove(&neighbor) {
+            stack.push(UndoAction::InsertStaleSession(neighbor, session_type));
+            for prefix in self.bgp_known_prefixes.iter() {
+                if let Some(entry) =
+                    self.bgp_rib_in.get_mut(&prefix).and_then(|rib| rib.remove(&neighbor))
+                {
+                    stack.push(UndoAction::UpdateBgpRibIn(*prefix, neighbor, entry));
+                }
+            }
         }
-        prefix
+        stack
+    }
+
+    /// Returns the route originated by this router for a redistributed static route, or `None` if
+    /// no static route for the prefix exists, or if static routes are not redistributed.
+    fn get_redistributed_route(&self, prefix: Prefix) -> Option<BgpRibEntry> {
+        if !self.redistribute_static || !self.static_routes.contains_key(&prefix) {
+            return None;
+        }
+        let mut route = BgpRoute {
+            prefix,
+            as_path: Vec::new(),
+            next_hop: self.router_id,
+            local_pref: None,
+            med: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
+        };
+        route.apply_default();
+        Some(BgpRibEntry {
+            route,
+            from_type: BgpSessionType::IBgpPeer,
+            from_id: self.router_id,
+            to_id: None,
+            igp_cost: Some(0.0),
+        })
     }
 
     /// process incoming routes from bgp_rib_in
     fn process_bgp_rib_in_route(
         &self,
-        mut entry: BgpRibEntry,
+        entry: BgpRibEntry,
     ) -> Result<Option<BgpRibEntry>, DeviceError> {
+        // drop reflected routes which were originated by this router, or which were already
+        // reflected in the own cluster.
+        if entry.route.originator_id == Some(self.router_id)
+            || entry.route.cluster_list.contains(&self.get_cluster_id())
+        {
+            return Ok(None);
+        }
+
         // apply bgp_route_map_in
-        let mut maps = self.bgp_route_maps_in.iter();
-        let mut entry = loop {
-            match maps.next() {
-                Some(map) => {
-                    entry = match map.apply(entry) {
-                        (true, Some(e)) => break e,
-                        (true, None) => return Ok(None),
-                        (false, Some(e)) => e,
-                        (false, None) => unreachable!(),
-                    }
-                }
-                None => break entry,
-            }
+        let mut entry = match apply_route_maps(&self.bgp_route_maps_in, entry) {
+            Some(e) => e,
+            None => return Ok(None),
         };
 
         // compute the igp cost
@@ -978,6 +1558,22 @@ impl Router {
         // set the default values
         entry.route.apply_default();
 
+        // apply route origin validation on routes learned from external peers
+        if entry.from_type.is_ebgp() && self.get_rov_state(&entry.route) == RovState::Invalid {
+            match self.rov_policy {
+                Some(RovPolicy::RejectInvalid) => return Ok(None),
+                Some(RovPolicy::DepreferInvalid(local_pref)) => {
+                    entry.route.local_pref = Some(local_pref)
+                }
+                None => {}
+            }
+        }
+
+        // routes learned over a session in graceful shutdown are least preferred
+        if self.bgp_graceful_shutdown.contains(&entry.from_id) {
+            entry.route.local_pref = Some(0);
+        }
+
         // set the to_id to None
         entry.to_id = None;
 
@@ -995,22 +1591,13 @@ impl Router {
         entry.to_id = Some(target_peer);
 
         // apply bgp_route_map_out
-        let mut maps = self.bgp_route_maps_out.iter();
-        let mut entry = loop {
-            match maps.next() {
-                Some(map) => {
-                    entry = match map.apply(entry) {
-                        (true, Some(e)) => break e,
-                        (true, None) => return Ok(None),
-                        (false, Some(e)) => e,
-                        (false, None) => unreachable!(),
-                    }
-                }
-                None => break entry,
-            }
+        let mut entry = match apply_route_maps(&self.bgp_route_maps_out, entry) {
+            Some(e) => e,
+            None => return Ok(None),
         };
 
         // get the peer type
+        let learned_from_ibgp = entry.from_type.is_ibgp() && entry.from_id != self.router_id;
         entry.from_type =
             *self.bgp_sessions.get(&target_peer).ok_or(DeviceError::NoBgpSession(target_peer))?;
 
@@ -1018,11 +1605,37 @@ impl Router {
         if entry.from_type.is_ebgp() {
             entry.route.next_hop = self.router_id;
             entry.route.local_pref = None;
+            entry.route.originator_id = None;
+            entry.route.cluster_list.clear();
+        } else if learned_from_ibgp {
+            // the route is reflected. The first route reflector sets the originator, and every
+            // route reflector prepends its cluster ID.
+            entry.route.originator_id.get_or_insert(entry.from_id);
+            entry.route.cluster_list.insert(0, self.get_cluster_id());
         }
 
         Ok(Some(entry))
     }
 
+    /// returns a bool which tells if the relationships allow the route to be exported to the
+    /// target. Routes are always exported to customers and to neighbors without a relationship.
+    /// To all other neighbors, only routes that entered the network from a customer (or from a
+    /// neighbor without a relationship) are exported. The ingress neighbor is the next-hop of the
+    /// selected route, which is never rewritten on iBGP sessions.
+    fn relationship_allows_export(&self, prefix: Prefix, to: RouterId) -> bool {
+        let ingress = match self.bgp_rib.get(&prefix) {
+            Some(entry) => entry.route.next_hop,
+            None => return true,
+        };
+        match self.bgp_relationships.get(&to) {
+            None | Some(BgpRelationship::Customer) => true,
+            Some(_) => match self.bgp_relationships.get(&ingress) {
+                None | Some(BgpRelationship::Customer) => true,
+                Some(_) => false,
+            },
+        }
+    }
+
     /// returns a bool which tells to export the route to the target, which was advertised by the
     /// source.
     fn should_export_route(
@@ -1035,8 +1648,17 @@ impl Router {
         if from == to {
             return Ok(false);
         }
-        // check the types
-        let from_type = self.bgp_sessions.get(&from).ok_or(DeviceError::NoBgpSession(from))?;
+        // routes originated by this router (redistributed static routes) are sent to everyone
+        if from == self.router_id {
+            return Ok(true);
+        }
+        // check the types. Routes retained from a closed session are treated as if the session
+        // still exists.
+        let from_type = self
+            .bgp_sessions
+            .get(&from)
+            .or_else(|| self.bgp_stale_sessions.get(&from))
+            .ok_or(DeviceError::NoBgpSession(from))?;
 
         Ok(match (from_type, to_type) {
             (BgpSessionType::EBgp, _) => true,
@@ -1048,6 +1670,19 @@ impl Router {
     }
 }
 
+/// Returns true if both routes are identical. In contrast to `BgpRoute::eq`, missing values are not
+/// replaced by their defaults, since they are treated differently when forwarding the route.
+fn same_route(a: &BgpRoute, b: &BgpRoute) -> bool {
+    a.prefix == b.prefix
+        && a.as_path == b.as_path
+        && a.next_hop == b.next_hop
+        && a.local_pref == b.local_pref
+        && a.med == b.med
+        && a.communities == b.communities
+        && a.originator_id == b.originator_id
+        && a.cluster_list == b.cluster_list
+}
+
 #[derive(Debug)]
 enum UndoAction {
     /// Undo by updating (or inserting) a BGP RIB entry in the BGP RIB IN table
@@ -1064,4 +1699,68 @@ enum UndoAction {
     RemoveBgpRibOut(Prefix, RouterId),
     /// Remove a known prefix, if it was not previously there.
     RemoveKnownPrefix(Prefix),
+    /// Undo by removing the stale session, whose routes were retained.
+    RemoveStaleSession(RouterId),
+    /// Undo by inserting the stale session again, whose routes were retained.
+    InsertStaleSession(RouterId, BgpSessionType),
+    /// Undo by removing a pending route refresh.
+    RemovePendingRefresh(Prefix, RouterId),
+    /// Undo by inserting a pending route refresh again.
+    InsertPendingRefresh(Prefix, RouterId),
+}
+
+/// Stack of undo actions. Each event processed will push a new frame onto the stack, containing all
+/// actions to perform in order to undo this event. If tracking is disabled, no frames and no
+/// actions are stored, and the stack always remains empty.
+#[derive(Debug)]
+struct UndoStack {
+    frames: Vec<Vec<UndoAction>>,
+    enabled: bool,
+}
+
+impl UndoStack {
+    fn new(enabled: bool) -> Self {
+        Self { frames: Vec::new(), enabled }
+    }
+
+    /// Push a new (empty) frame onto the stack
+    fn push_frame(&mut self) {
+        if self.enabled {
+            self.frames.push(Vec::new());
+        }
+    }
+
+    /// Add an action to the topmost frame
+    fn push(&mut self, action: UndoAction) {
+        if self.enabled {
+            self.frames.last_mut().unwrap().push(action);
+        }
+    }
+
+    /// Move all actions of `actions` to the topmost frame, leaving `actions` empty.
+    fn append(&mut self, actions: &mut Vec<UndoAction>) {
+        if self.enabled {
+            self.frames.last_mut().unwrap().append(actions);
+        } else {
+            actions.clear();
+        }
+    }
+
+    /// Remove the topmost frame
+    fn pop(&mut self) -> Option<Vec<UndoAction>> {
+        self.frames.pop()
+    }
+
+    /// Remove all frames
+    fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Enable or disable the tracking. Disabling the tracking clears the stack.
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.frames.clear();
+        }
+    }
 }
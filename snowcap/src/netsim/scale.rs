@@ -0,0 +1,316 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Topology Scaling
+//!
+//! This module replicates an existing network into multiple *pods*, to generate arbitrarily large,
+//! but structured benchmarks. Use [`Network::scale`] to create the scaled network. The returned
+//! [`Scaling`] maps the routers of the original network into every pod, such that configurations
+//! and modifiers of the original network can be translated into the scaled network.
+//!
+//! Each pod is an exact copy of the original network, including the external routers, the
+//! configuration, the advertised routes, the router roles and the traffic matrix. Pod 0 keeps the
+//! original router names, while all routers of pod `i > 0` get the suffix `_i`. The pods are
+//! connected in a chain through their *gateway*, which is the internal router with the most links
+//! in the original network. Consecutive gateways are connected by a link (using the largest IGP
+//! weight of the original network), and by an iBGP peer session.
+
+use crate::netsim::bgp::BgpSessionType;
+use crate::netsim::config::{Config, ConfigExpr, ConfigModifier};
+use crate::netsim::route_map::{RouteMap, RouteMapMatch, RouteMapSet};
+use crate::netsim::{LinkWeight, Network, NetworkDevice, NetworkError, RouterId};
+use crate::traffic_matrix::TrafficMatrix;
+
+use std::collections::HashMap;
+
+/// # Scaling of a Network
+/// Mapping of the routers of the original network into each pod of the scaled network, created by
+/// [`Network::scale`].
+#[derive(Debug, Clone)]
+pub struct Scaling {
+    pods: Vec<HashMap<RouterId, RouterId>>,
+    interconnect: Vec<ConfigExpr>,
+}
+
+impl Scaling {
+    /// Returns the number of pods.
+    pub fn num_pods(&self) -> usize {
+        self.pods.len()
+    }
+
+    /// Returns the copy of the original `router` in the given `pod`.
+    pub fn router(&self, router: RouterId, pod: usize) -> Option<RouterId> {
+        self.pods.get(pod).and_then(|p| p.get(&router)).copied()
+    }
+
+    /// Returns the configuration expressions which connect the pods with each other.
+    pub fn interconnect(&self) -> &[ConfigExpr] {
+        &self.interconnect
+    }
+
+    /// Translate a configuration of the original network into the scaled network. The result
+    /// contains the expression for every pod, and the configuration connecting the pods.
+    pub fn map_config(&self, config: &Config) -> Result<Config, NetworkError> {
+        let mut result = Config::new();
+        for pod in 0..self.num_pods() {
+            for expr in config.iter() {
+                result.add(self.map_expr(expr, pod)?)?;
+            }
+        }
+        for expr in self.interconnect.iter() {
+            result.add(expr.clone())?;
+        }
+        Ok(result)
+    }
+
+    /// Translate a modifier of the original network into the scaled network, returning one
+    /// modifier for every pod.
+    pub fn map_modifier(
+        &self,
+        modifier: &ConfigModifier,
+    ) -> Result<Vec<ConfigModifier>, NetworkError> {
+        (0..self.num_pods())
+            .map(|pod| {
+                Ok(match modifier {
+                    ConfigModifier::Insert(e) => ConfigModifier::Insert(self.map_expr(e, pod)?),
+                    ConfigModifier::Remove(e) => ConfigModifier::Remove(self.map_expr(e, pod)?),
+                    ConfigModifier::Update { from, to } => ConfigModifier::Update {
+                        from: self.map_expr(from, pod)?,
+                        to: self.map_expr(to, pod)?,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Translate a configuration expression of the original network into the given `pod`.
+    pub fn map_expr(&self, expr: &ConfigExpr, pod: usize) -> Result<ConfigExpr, NetworkError> {
+        let r = |router: &RouterId| {
+            self.router(*router, pod).ok_or(NetworkError::DeviceNotFound(*router))
+        };
+        Ok(match expr {
+            ConfigExpr::IgpLinkWeight { source, target, weight } => ConfigExpr::IgpLinkWeight {
+                source: r(source)?,
+                target: r(target)?,
+                weight: *weight,
+            },
+            ConfigExpr::OspfArea { router_a, router_b, area } => {
+                ConfigExpr::OspfArea { router_a: r(router_a)?, router_b: r(router_b)?, area: *area }
+            }
+            ConfigExpr::OspfStubArea { area } => ConfigExpr::OspfStubArea { area: *area },
+            ConfigExpr::BgpSession { source, target, session_type } => ConfigExpr::BgpSession {
+                source: r(source)?,
+                target: r(target)?,
+                session_type: *session_type,
+            },
+            ConfigExpr::BgpGracefulRestart { router, neighbor } => {
+                ConfigExpr::BgpGracefulRestart { router: r(router)?, neighbor: r(neighbor)? }
+            }
+            ConfigExpr::BgpGracefulShutdown { router, neighbor } => {
+                ConfigExpr::BgpGracefulShutdown { router: r(router)?, neighbor: r(neighbor)? }
+            }
+            ConfigExpr::BgpRelationship { neighbor, relationship } => {
+                ConfigExpr::BgpRelationship { neighbor: r(neighbor)?, relationship: *relationship }
+            }
+            ConfigExpr::BgpRouteMap { router, direction, map } => ConfigExpr::BgpRouteMap {
+                router: r(router)?,
+                direction: *direction,
+                map: self.map_route_map(map, pod)?,
+            },
+            ConfigExpr::StaticRoute { router, prefix, target } => {
+                ConfigExpr::StaticRoute { router: r(router)?, prefix: *prefix, target: r(target)? }
+            }
+            ConfigExpr::RedistributeStatic { router } => {
+                ConfigExpr::RedistributeStatic { router: r(router)? }
+            }
+            ConfigExpr::BgpAlwaysCompareMed { router } => {
+                ConfigExpr::BgpAlwaysCompareMed { router: r(router)? }
+            }
+            ConfigExpr::BgpClusterId { router, cluster_id } => {
+                ConfigExpr::BgpClusterId { router: r(router)?, cluster_id: r(cluster_id)? }
+            }
+            ConfigExpr::RovPolicy { router, policy } => {
+                ConfigExpr::RovPolicy { router: r(router)?, policy: *policy }
+            }
+            ConfigExpr::Roa { router, prefix, origin } => {
+                ConfigExpr::Roa { router: r(router)?, prefix: *prefix, origin: *origin }
+            }
+        })
+    }
+
+    /// Translate all router references of a route map into the given `pod`.
+    fn map_route_map(&self, map: &RouteMap, pod: usize) -> Result<RouteMap, NetworkError> {
+        let r = |router: &RouterId| {
+            self.router(*router, pod).ok_or(NetworkError::DeviceNotFound(*router))
+        };
+        let conds = map
+            .conds
+            .iter()
+            .map(|c| {
+                Ok(match c {
+                    RouteMapMatch::Neighbor(n) => RouteMapMatch::Neighbor(r(n)?),
+                    RouteMapMatch::NextHop(nh) => RouteMapMatch::NextHop(r(nh)?),
+                    c => c.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, NetworkError>>()?;
+        let set = map
+            .set
+            .iter()
+            .map(|s| {
+                Ok(match s {
+                    RouteMapSet::NextHop(nh) => RouteMapSet::NextHop(r(nh)?),
+                    s => s.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, NetworkError>>()?;
+        Ok(RouteMap::new(map.order, map.state, conds, set))
+    }
+}
+
+impl Network {
+    /// Replicate the network `factor` times, and connect the replicas (pods) in a chain. See the
+    /// module [`crate::netsim::scale`] for a description of the resulting network. The function
+    /// returns the scaled network, together with the [`Scaling`], which can be used to translate
+    /// configurations and modifiers into the scaled network. `factor` must be at least 1.
+    ///
+    /// ```rust
+    /// # use snowcap::netsim::Network;
+    /// # use snowcap::example_networks::{ExampleNetwork, SimpleNet};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let net = SimpleNet::net(0);
+    /// let final_config = SimpleNet::final_config(&net, 0);
+    /// let (scaled, scaling) = net.scale(3)?;
+    /// let scaled_final_config = scaling.map_config(&final_config)?;
+    /// assert_eq!(scaled.num_devices(), 3 * net.num_devices());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn scale(&self, factor: usize) -> Result<(Network, Scaling), NetworkError> {
+        assert!(factor >= 1, "The scaling factor must be at least 1");
+
+        let mut devices: Vec<RouterId> = self.get_routers();
+        devices.extend(self.get_external_routers());
+        devices.sort();
+
+        let mut net = Network::new();
+        net.set_msg_limit(self.get_msg_limit());
+
+        // create all devices
+        let mut pods: Vec<HashMap<RouterId, RouterId>> = Vec::with_capacity(factor);
+        for pod in 0..factor {
+            let mut mapping = HashMap::new();
+            for router in devices.iter() {
+                let name = self.get_router_name(*router)?;
+                let name = if pod == 0 { name.to_string() } else { format!("{}_{}", name, pod) };
+                let new_router = match self.get_device(*router) {
+                    NetworkDevice::ExternalRouter(ext) => {
+                        net.add_external_router(name, ext.as_id())
+                    }
+                    _ => net.add_router(name),
+                };
+                mapping.insert(*router, new_router);
+            }
+            pods.push(mapping);
+        }
+
+        // create all links
+        for pod in pods.iter() {
+            for (a, b) in self.links_symmetric() {
+                net.add_link(pod[a], pod[b]);
+            }
+        }
+
+        // connect the pods through their gateways
+        let mut interconnect = Vec::new();
+        if let Some(gateway) = self.gateway() {
+            let weight = self
+                .current_config()
+                .iter()
+                .filter_map(|e| match e {
+                    ConfigExpr::IgpLinkWeight { weight, .. } => Some(*weight),
+                    _ => None,
+                })
+                .filter(|w| w.is_finite())
+                .fold(1.0, LinkWeight::max);
+            for (prev, next) in pods.iter().zip(pods.iter().skip(1)) {
+                let (a, b) = (prev[&gateway], next[&gateway]);
+                net.add_link(a, b);
+                interconnect.push(ConfigExpr::IgpLinkWeight { source: a, target: b, weight });
+                interconnect.push(ConfigExpr::IgpLinkWeight { source: b, target: a, weight });
+                interconnect.push(ConfigExpr::BgpSession {
+                    source: a,
+                    target: b,
+                    session_type: BgpSessionType::IBgpPeer,
+                });
+            }
+        }
+
+        let scaling = Scaling { pods, interconnect };
+
+        // apply the configuration
+        net.set_config(&scaling.map_config(self.current_config())?)?;
+
+        // advertise all routes
+        for pod in scaling.pods.iter() {
+            for ext in self.get_external_routers() {
+                for route in self.get_device(ext).unwrap_external().get_advertised_routes() {
+                    net.advertise_external_route_with_communities(
+                        pod[&ext],
+                        route.prefix,
+                        route.as_path.clone(),
+                        route.med,
+                        route.communities.iter().cloned(),
+                    )?;
+                }
+            }
+        }
+
+        // copy the roles and the traffic matrix
+        for pod in scaling.pods.iter() {
+            for router in self.get_routers() {
+                for role in self.get_router_roles(router) {
+                    net.add_router_role(pod[&router], role)?;
+                }
+            }
+        }
+        if let Some(tm) = self.get_traffic_matrix() {
+            let mut scaled_tm = TrafficMatrix::new();
+            for pod in scaling.pods.iter() {
+                for ((router, prefix), volume) in tm.iter() {
+                    scaled_tm.add_demand(pod[router], *prefix, *volume);
+                }
+            }
+            net.set_traffic_matrix(Some(scaled_tm));
+        }
+
+        Ok((net, scaling))
+    }
+
+    /// Returns the internal router with the most links, preferring the smaller router id.
+    fn gateway(&self) -> Option<RouterId> {
+        let mut degree: HashMap<RouterId, usize> = HashMap::new();
+        for (a, b) in self.links_symmetric() {
+            *degree.entry(*a).or_default() += 1;
+            *degree.entry(*b).or_default() += 1;
+        }
+        let mut routers = self.get_routers();
+        routers.sort();
+        routers
+            .into_iter()
+            .max_by_key(|r| (degree.get(r).copied().unwrap_or(0), std::cmp::Reverse(*r)))
+    }
+}
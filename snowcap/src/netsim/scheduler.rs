@@ -0,0 +1,181 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Event Scheduling
+//!
+//! While the network converges, the BGP messages are kept in a queue. By default, they are
+//! delivered in the order in which they were sent (FIFO). An [`EventScheduler`] can be set on the
+//! network (see [`Network::set_scheduler`](crate::netsim::Network::set_scheduler)) to choose a
+//! different message to deliver next, in order to study how the scheduling of messages affects the
+//! convergence. Independent of the scheduler, messages between the same two routers are always
+//! delivered in order (as if they were sent over TCP).
+//!
+//! ```
+//! use snowcap::netsim::Network;
+//! use snowcap::netsim::scheduler::RandomScheduler;
+//!
+//! let mut net = Network::new();
+//! net.set_scheduler(Some(Box::new(RandomScheduler::new(42))));
+//! ```
+
+use crate::netsim::{Prefix, RouterId};
+
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A BGP message waiting in the queue, as presented to the [`EventScheduler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingMessage {
+    /// Router that sent the message
+    pub from: RouterId,
+    /// Router that will receive the message
+    pub to: RouterId,
+    /// Prefix of the message
+    pub prefix: Prefix,
+    /// ID of the event, during which the message was sent.
+    pub parent: usize,
+}
+
+/// Policy for choosing the next message to be delivered out of all pending messages.
+pub trait EventScheduler: fmt::Debug + SchedulerBoxClone + Send + Sync {
+    /// Choose the message to deliver next, and return its position in `pending`. The messages in
+    /// `pending` are ordered by the time they were sent. The chosen message will get the ID
+    /// `event_id`. If the returned position is out of bounds, the last message is chosen.
+    fn schedule(&mut self, pending: &[PendingMessage], event_id: usize) -> usize;
+}
+
+/// Helper trait to clone boxed event schedulers. It is implemented for all schedulers that
+/// implement [`Clone`].
+pub trait SchedulerBoxClone {
+    /// Clone the scheduler into a new box.
+    fn box_clone(&self) -> Box<dyn EventScheduler>;
+}
+
+impl<T> SchedulerBoxClone for T
+where
+    T: 'static + EventScheduler + Clone,
+{
+    fn box_clone(&self) -> Box<dyn EventScheduler> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn EventScheduler> {
+    fn clone(&self) -> Box<dyn EventScheduler> {
+        self.box_clone()
+    }
+}
+
+/// Deliver the messages in the order in which they were sent. This is the default behavior of the
+/// network.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FifoScheduler;
+
+impl EventScheduler for FifoScheduler {
+    fn schedule(&mut self, _pending: &[PendingMessage], _event_id: usize) -> usize {
+        0
+    }
+}
+
+/// Always deliver the message that was sent most recently (but still respecting the order of
+/// messages between the same two routers).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LifoScheduler;
+
+impl EventScheduler for LifoScheduler {
+    fn schedule(&mut self, pending: &[PendingMessage], _event_id: usize) -> usize {
+        pending.len().saturating_sub(1)
+    }
+}
+
+/// Deliver the messages in a random order, which is determined by the seed. Clones of the
+/// scheduler (and thus, clones of the network) continue with the same sequence of random numbers.
+#[derive(Debug, Clone)]
+pub struct RandomScheduler {
+    rng: StdRng,
+}
+
+impl RandomScheduler {
+    /// Create a new random scheduler, seeded with `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl EventScheduler for RandomScheduler {
+    fn schedule(&mut self, pending: &[PendingMessage], _event_id: usize) -> usize {
+        self.rng.gen_range(0, pending.len().max(1))
+    }
+}
+
+/// Deliver the messages in the order in which they arrive, where each message takes a fixed delay
+/// to traverse from the sender to the receiver. A message is sent at the time, at which the event
+/// causing it was delivered. Messages caused by configuration changes or external advertisements
+/// are sent at the time of the most recently delivered message, when they are first scheduled. On
+/// equal arrival times, the
+/// message sent first is delivered first.
+#[derive(Debug, Clone)]
+pub struct DelayScheduler {
+    default_delay: f64,
+    delays: HashMap<(RouterId, RouterId), f64>,
+    arrival: HashMap<usize, f64>,
+    clock: f64,
+}
+
+impl DelayScheduler {
+    /// Create a new delay scheduler, where all messages take `default_delay`.
+    pub fn new(default_delay: f64) -> Self {
+        Self { default_delay, delays: HashMap::new(), arrival: HashMap::new(), clock: 0.0 }
+    }
+
+    /// Set the delay of all messages sent from `from` to `to`.
+    pub fn set_delay(&mut self, from: RouterId, to: RouterId, delay: f64) {
+        self.delays.insert((from, to), delay);
+    }
+
+    /// Returns the delay of messages sent from `from` to `to`.
+    pub fn get_delay(&self, from: RouterId, to: RouterId) -> f64 {
+        self.delays.get(&(from, to)).copied().unwrap_or(self.default_delay)
+    }
+
+    /// Returns the arrival time of the most recently delivered message.
+    pub fn clock(&self) -> f64 {
+        self.clock
+    }
+}
+
+impl EventScheduler for DelayScheduler {
+    fn schedule(&mut self, pending: &[PendingMessage], event_id: usize) -> usize {
+        // event IDs are assigned sequentially. Larger IDs belong to events that were undone.
+        self.arrival.retain(|id, _| *id < event_id);
+        // events that were not scheduled happen at the time they are first seen.
+        for m in pending {
+            self.arrival.entry(m.parent).or_insert(self.clock);
+        }
+        let (pos, time) = pending
+            .iter()
+            .map(|m| self.arrival[&m.parent] + self.get_delay(m.from, m.to))
+            .enumerate()
+            .fold((0, f64::INFINITY), |best, (i, t)| if t < best.1 { (i, t) } else { best });
+        if time.is_finite() {
+            self.clock = time;
+        }
+        self.arrival.insert(event_id, self.clock);
+        pos
+    }
+}
@@ -0,0 +1,253 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Network Snapshots
+//!
+//! This module implements `Serialize` and `Deserialize` for [`Network`]. The network is stored as
+//! a snapshot of its topology, its configuration, the routes advertised by the external routers
+//! and the annotations (router roles, traffic matrix, prefix ranges, message limit and timing
+//! model). The state
+//! of the routers (i.e., their RIBs and forwarding tables) is not stored. Instead, the network is
+//! rebuilt when deserializing, and converges to the state of the snapshot. Neither the event queue
+//! nor the undo history are part of the snapshot, which is why the network should be serialized in
+//! a converged state.
+//!
+//! Routers are created in the order of their [`RouterId`], such that every `RouterId` of the
+//! restored network is identical to the one in the original network. Configurations and
+//! modifiers, which reference routers by their `RouterId`, are therefore valid for both networks.
+
+use crate::netsim::config::Config;
+use crate::netsim::{
+    AsId, Ipv4Net, Network, NetworkDevice, NetworkError, Prefix, RouterId, RouterRole,
+    DEFAULT_LINK_DELAY,
+};
+use crate::traffic_matrix::TrafficMatrix;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializable snapshot of the network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NetworkSnapshot {
+    /// All routers, sorted by their `RouterId`.
+    routers: Vec<RouterSnapshot>,
+    /// All links
+    links: Vec<(RouterId, RouterId)>,
+    /// Current configuration
+    config: Config,
+    /// Routes advertised by the external routers
+    advertisements: Vec<AdvertisementSnapshot>,
+    /// Roles of the internal routers
+    #[serde(default)]
+    roles: Vec<(RouterId, Vec<RouterRole>)>,
+    /// Traffic matrix, as a list of demands
+    #[serde(default)]
+    traffic_matrix: Option<Vec<(RouterId, Prefix, f64)>>,
+    /// Limit of messages exchanged until convergence
+    #[serde(default)]
+    msg_limit: Option<usize>,
+    /// Whether equal-cost multipath forwarding is enabled
+    #[serde(default)]
+    ecmp: bool,
+    /// Address ranges of the prefixes
+    #[serde(default)]
+    prefix_ranges: Vec<(Prefix, Ipv4Net)>,
+    /// Propagation delays of BGP messages, in one direction
+    #[serde(default)]
+    link_delays: Vec<(RouterId, RouterId, f64)>,
+    /// Propagation delay of BGP messages without a specific delay
+    #[serde(default = "default_link_delay")]
+    default_link_delay: f64,
+    /// Minimum route advertisement interval
+    #[serde(default)]
+    mrai: f64,
+}
+
+fn default_link_delay() -> f64 {
+    DEFAULT_LINK_DELAY
+}
+
+/// Internal or external router of the snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RouterSnapshot {
+    id: RouterId,
+    name: String,
+    /// AS of the router, only set for external routers
+    external_as: Option<AsId>,
+}
+
+/// Route advertised by an external router
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AdvertisementSnapshot {
+    router: RouterId,
+    prefix: Prefix,
+    as_path: Vec<AsId>,
+    med: Option<u32>,
+    /// Single community, only present in snapshots that were created before routes could carry
+    /// multiple communities.
+    #[serde(default, skip_serializing)]
+    community: Option<u32>,
+    #[serde(default)]
+    communities: Vec<u32>,
+}
+
+impl From<&Network> for NetworkSnapshot {
+    fn from(net: &Network) -> Self {
+        let mut routers: Vec<RouterSnapshot> = net
+            .get_routers()
+            .into_iter()
+            .chain(net.get_external_routers())
+            .map(|id| match net.get_device(id) {
+                NetworkDevice::InternalRouter(r) => {
+                    RouterSnapshot { id, name: r.name().to_string(), external_as: None }
+                }
+                NetworkDevice::ExternalRouter(r) => {
+                    RouterSnapshot { id, name: r.name().to_string(), external_as: Some(r.as_id()) }
+                }
+                NetworkDevice::None => unreachable!(),
+            })
+            .collect();
+        routers.sort_by_key(|r| r.id);
+
+        let mut advertisements: Vec<AdvertisementSnapshot> = Vec::new();
+        for r in routers.iter().filter(|r| r.external_as.is_some()) {
+            let mut routes = net.get_device(r.id).unwrap_external().get_advertised_routes().clone();
+            routes.sort_by_key(|route| route.prefix);
+            advertisements.extend(routes.into_iter().map(|route| AdvertisementSnapshot {
+                router: r.id,
+                prefix: route.prefix,
+                as_path: route.as_path,
+                med: route.med,
+                community: None,
+                communities: route.communities.into_iter().collect(),
+            }));
+        }
+
+        let roles = routers
+            .iter()
+            .map(|r| (r.id, net.get_router_roles(r.id)))
+            .filter(|(_, roles)| !roles.is_empty())
+            .collect();
+
+        let traffic_matrix = net.get_traffic_matrix().map(|tm| {
+            let mut demands: Vec<(RouterId, Prefix, f64)> =
+                tm.iter().map(|((r, p), v)| (*r, *p, *v)).collect();
+            demands.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+            demands
+        });
+
+        let mut prefix_ranges: Vec<(Prefix, Ipv4Net)> = net
+            .get_known_prefixes()
+            .iter()
+            .filter_map(|p| net.get_prefix_range(*p).map(|range| (*p, range)))
+            .collect();
+        prefix_ranges.sort();
+
+        let mut link_delays: Vec<(RouterId, RouterId, f64)> =
+            net.link_delays().map(|((a, b), d)| (*a, *b, *d)).collect();
+        link_delays.sort_by(|x, y| (x.0, x.1).cmp(&(y.0, y.1)));
+
+        Self {
+            routers,
+            links: net.links_symmetric().cloned().collect(),
+            config: net.current_config().clone(),
+            advertisements,
+            roles,
+            traffic_matrix,
+            msg_limit: net.get_msg_limit(),
+            ecmp: net.get_ecmp(),
+            prefix_ranges,
+            link_delays,
+            default_link_delay: net.get_default_link_delay(),
+            mrai: net.get_mrai(),
+        }
+    }
+}
+
+impl NetworkSnapshot {
+    /// Rebuild the network from the snapshot, and let it converge.
+    fn build(self) -> Result<Network, NetworkError> {
+        let mut net = Network::new();
+        net.set_msg_limit(self.msg_limit);
+        net.set_ecmp(self.ecmp);
+
+        let mut routers = self.routers;
+        routers.sort_by_key(|r| r.id);
+        for r in routers {
+            let id = match r.external_as {
+                None => net.add_router(r.name),
+                Some(as_id) => net.add_external_router(r.name, as_id),
+            };
+            // The network assigns the ids in increasing order, without any gaps.
+            if id != r.id {
+                return Err(NetworkError::DeviceNotFound(r.id));
+            }
+        }
+
+        for (a, b) in self.links {
+            net.add_link(a, b);
+        }
+        net.set_config(&self.config)?;
+
+        for adv in self.advertisements {
+            net.advertise_external_route_with_communities(
+                adv.router,
+                adv.prefix,
+                adv.as_path,
+                adv.med,
+                adv.community.into_iter().chain(adv.communities),
+            )?;
+        }
+
+        for (router, roles) in self.roles {
+            for role in roles {
+                net.add_router_role(router, role)?;
+            }
+        }
+
+        if let Some(demands) = self.traffic_matrix {
+            let mut tm = TrafficMatrix::new();
+            for (router, prefix, volume) in demands {
+                tm.add_demand(router, prefix, volume);
+            }
+            net.set_traffic_matrix(Some(tm));
+        }
+
+        for (prefix, range) in self.prefix_ranges {
+            net.set_prefix_range(prefix, range);
+        }
+
+        net.set_default_link_delay(self.default_link_delay);
+        net.set_mrai(self.mrai);
+        for (a, b, delay) in self.link_delays {
+            net.set_link_delay(a, b, delay);
+        }
+
+        Ok(net)
+    }
+}
+
+impl Serialize for Network {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NetworkSnapshot::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Network {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        NetworkSnapshot::deserialize(deserializer)?.build().map_err(D::Error::custom)
+    }
+}
@@ -25,22 +25,284 @@ use crate::netsim::network::Network;
 use crate::netsim::router::Router;
 use petgraph::prelude::*;
 use petgraph::stable_graph::StableGraph;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::net::Ipv4Addr;
 use thiserror::Error;
 
 type IndexType = u32;
 /// Router Identification (and index into the graph)
 pub type RouterId = NodeIndex<IndexType>;
-/// IP Prefix (simple representation)
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+/// IP Prefix (simple representation). The most significant bit stores the address family of the
+/// prefix (see [`Prefix::afi`]), such that `Prefix(x)` is always an IPv4 prefix for `x < 2^31`.
+/// Use [`Prefix::new`] to create prefixes of any address family.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Prefix(pub u32);
+
+impl Prefix {
+    /// Bit marking IPv6 prefixes
+    const IPV6_FLAG: u32 = 1 << 31;
+
+    /// Create a new prefix of a given address family. The same `id` may be used for one IPv4 and
+    /// one IPv6 prefix, which are distinct.
+    ///
+    /// ```
+    /// # use snowcap::netsim::{Afi, Prefix};
+    /// let v4 = Prefix::new(1, Afi::Ipv4);
+    /// let v6 = Prefix::new(1, Afi::Ipv6);
+    /// assert_eq!(v4, Prefix(1));
+    /// assert_ne!(v4, v6);
+    /// assert_eq!((v6.id(), v6.afi()), (1, Afi::Ipv6));
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `id` is not smaller than `2^31`.
+    pub fn new(id: u32, afi: Afi) -> Self {
+        assert!(id < Self::IPV6_FLAG, "Prefix id {} is too large!", id);
+        match afi {
+            Afi::Ipv4 => Self(id),
+            Afi::Ipv6 => Self(id | Self::IPV6_FLAG),
+        }
+    }
+
+    /// Returns the identifier of the prefix, without the address family.
+    pub fn id(&self) -> u32 {
+        self.0 & !Self::IPV6_FLAG
+    }
+
+    /// Returns the address family of the prefix.
+    pub fn afi(&self) -> Afi {
+        if self.0 & Self::IPV6_FLAG == 0 {
+            Afi::Ipv4
+        } else {
+            Afi::Ipv6
+        }
+    }
+}
+
+/// Address family of a [`Prefix`]. BGP sessions are multiprotocol sessions, and exchange routes of
+/// both address families.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Afi {
+    /// IPv4 unicast
+    Ipv4,
+    /// IPv6 unicast
+    Ipv6,
+}
+
+impl Default for Afi {
+    fn default() -> Self {
+        Self::Ipv4
+    }
+}
+
+impl std::fmt::Display for Afi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ipv4 => write!(f, "ipv4"),
+            Self::Ipv6 => write!(f, "ipv6"),
+        }
+    }
+}
+
+/// IPv4 address range of a [`Prefix`], given by the network address and the prefix length (e.g.,
+/// `10.0.0.0/16`). The host bits of the network address are always zero. Ranges of different
+/// prefixes may overlap, in which case routers use longest-prefix matching (see
+/// [`ForwardingState::get_route_to_addr`](crate::netsim::ForwardingState::get_route_to_addr)).
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "Ipv4NetRepr")]
+pub struct Ipv4Net {
+    addr: u32,
+    len: u8,
+}
+
+/// Serialized form of an [`Ipv4Net`], which is checked by [`Ipv4Net::new`] when deserializing.
+#[derive(Deserialize)]
+struct Ipv4NetRepr {
+    addr: u32,
+    len: u8,
+}
+
+impl TryFrom<Ipv4NetRepr> for Ipv4Net {
+    type Error = NetworkError;
+
+    fn try_from(repr: Ipv4NetRepr) -> Result<Self, Self::Error> {
+        Self::new(repr.addr.into(), repr.len)
+    }
+}
+
+impl Ipv4Net {
+    /// Create a new address range. The host bits of `addr` are cleared. Returns
+    /// `Err(InvalidPrefixRange)` if `len` is larger than 32.
+    pub fn new(addr: Ipv4Addr, len: u8) -> Result<Self, NetworkError> {
+        if len > 32 {
+            return Err(NetworkError::InvalidPrefixRange(format!("{}/{}", addr, len)));
+        }
+        let addr = u32::from(addr) & Self::mask_of(len);
+        Ok(Self { addr, len })
+    }
+
+    /// Returns the network address of the range.
+    pub fn addr(&self) -> Ipv4Addr {
+        self.addr.into()
+    }
+
+    /// Returns the length of the prefix, i.e., the number of fixed bits.
+    pub fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns `true` if the address is part of the range.
+    pub fn contains(&self, addr: Ipv4Addr) -> bool {
+        u32::from(addr) & Self::mask_of(self.len) == self.addr
+    }
+
+    /// Returns `true` if `other` is a subrange of `self` (or equal to `self`).
+    pub fn covers(&self, other: &Self) -> bool {
+        self.len <= other.len && self.contains(other.addr())
+    }
+
+    fn mask_of(len: u8) -> u32 {
+        u32::MAX.checked_shl(32 - len as u32).unwrap_or(0)
+    }
+}
+
+impl std::fmt::Display for Ipv4Net {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr(), self.len)
+    }
+}
+
+impl std::str::FromStr for Ipv4Net {
+    type Err = NetworkError;
+
+    /// Parse a range in the form `a.b.c.d/len`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || NetworkError::InvalidPrefixRange(s.to_string());
+        let mut parts = s.trim().splitn(2, '/');
+        let addr = parts.next().and_then(|a| a.parse().ok()).ok_or_else(invalid)?;
+        let len = parts.next().and_then(|l| l.parse().ok()).ok_or_else(invalid)?;
+        Self::new(addr, len)
+    }
+}
+
 /// AS Number
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct AsId(pub u32);
+/// Role of an internal router, used to annotate routers in the [`Network`]. Roles are used by
+/// scenario generators, policy templates and orderings. A router may have multiple roles.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum RouterRole {
+    /// Border router, with at least one eBGP session
+    Border,
+    /// Core router, without any eBGP session
+    Core,
+    /// Route reflector
+    RouteReflector,
+    /// Firewall, which must be traversed by some of the traffic
+    Firewall,
+}
+
+impl std::fmt::Display for RouterRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Border => write!(f, "border"),
+            Self::Core => write!(f, "core"),
+            Self::RouteReflector => write!(f, "rr"),
+            Self::Firewall => write!(f, "firewall"),
+        }
+    }
+}
+
 /// Link Weight for the IGP graph
 pub type LinkWeight = f32;
 /// IGP Network graph
 pub type IgpNetwork = StableGraph<(), LinkWeight, Directed, IndexType>;
 
+/// OSPF area. Area 0 is the backbone, to which all other areas are attached through area border
+/// routers (ABRs).
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct OspfArea(pub u32);
+
+impl OspfArea {
+    /// The backbone area (area 0)
+    pub const BACKBONE: OspfArea = OspfArea(0);
+
+    /// Returns true if the area is the backbone area
+    pub fn is_backbone(&self) -> bool {
+        *self == Self::BACKBONE
+    }
+}
+
+impl std::fmt::Display for OspfArea {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "area {}", self.0)
+    }
+}
+
+/// Hierarchical structure of the IGP, which complements the [`IgpNetwork`] with the OSPF area of
+/// every link. Links without an explicit area are part of the backbone. Hence, a network without
+/// any area configuration is a flat IGP, where every router computes the shortest path to all
+/// other routers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IgpAreas {
+    /// Area of each link, indexed by the pair of routers, where the first one has the smaller id.
+    links: HashMap<(RouterId, RouterId), OspfArea>,
+    /// Set of all stub areas
+    stub_areas: HashSet<OspfArea>,
+    /// External routers, which are reached by a default route from within stub areas.
+    external_routers: HashSet<RouterId>,
+}
+
+impl IgpAreas {
+    /// Returns the area of the link between `a` and `b`.
+    pub fn get_area(&self, a: RouterId, b: RouterId) -> OspfArea {
+        self.links.get(&Self::link_key(a, b)).copied().unwrap_or(OspfArea::BACKBONE)
+    }
+
+    /// Returns true if the area is configured as a stub area.
+    pub fn is_stub(&self, area: OspfArea) -> bool {
+        self.stub_areas.contains(&area)
+    }
+
+    /// Returns true if the router is an external router.
+    pub fn is_external(&self, router: RouterId) -> bool {
+        self.external_routers.contains(&router)
+    }
+
+    /// Set the area of the link between `a` and `b`. If `area` is `None`, the link is moved back
+    /// into the backbone.
+    pub(crate) fn set_area(&mut self, a: RouterId, b: RouterId, area: Option<OspfArea>) {
+        match area {
+            Some(area) => self.links.insert(Self::link_key(a, b), area),
+            None => self.links.remove(&Self::link_key(a, b)),
+        };
+    }
+
+    /// Configure an area as stub area, or as normal area, if `stub` is false.
+    pub(crate) fn set_stub(&mut self, area: OspfArea, stub: bool) {
+        if stub {
+            self.stub_areas.insert(area);
+        } else {
+            self.stub_areas.remove(&area);
+        }
+    }
+
+    /// Register an external router
+    pub(crate) fn add_external_router(&mut self, router: RouterId) {
+        self.external_routers.insert(router);
+    }
+
+    fn link_key(a: RouterId, b: RouterId) -> (RouterId, RouterId) {
+        if a < b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+}
+
 /// Configuration Error
 #[derive(Error, Debug, PartialEq)]
 pub enum ConfigError {
@@ -233,6 +495,9 @@ pub enum NetworkError {
     /// Convergence Problem
     #[error("Network cannot converge in the given time!")]
     NoConvergence,
+    /// The convergence was interrupted by the control handle (see [`Network::set_abort`])
+    #[error("The convergence was aborted")]
+    Aborted,
     /// Two routers are not adjacent
     #[error("Network link does not exist: {0:?} -> {1:?}")]
     RoutersNotConnected(RouterId, RouterId),
@@ -249,11 +514,20 @@ pub enum NetworkError {
     /// History is invalid
     #[error("History is invalid: {0}")]
     HistoryError(&'static str),
+    /// The regular expression to select routers is invalid
+    #[error("Invalid router name pattern: {0}")]
+    InvalidPattern(String),
+    /// The address range of a prefix is invalid
+    #[error("Invalid IPv4 prefix: {0}")]
+    InvalidPrefixRange(String),
+    /// Undo was requested, but the undo tracking is disabled (see [`Network::set_undo_tracking`])
+    #[error("Cannot undo, the undo tracking is disabled")]
+    UndoDisabled,
     /// Constraints are not satisfied during convergence
     #[error("Constraints are not satisfied during convergence: {0}")]
     UnsatisfiedConstraints(#[from] crate::hard_policies::PolicyError),
     /// No events to reorder (This error is only thorwn when approximating transient state violation
-    /// probability in [`Network::apply_modifier_check_transient`])
+    /// probabilities in the `transient` module)
     #[error("No events to reorder")]
     NoEventsToReorder,
 }
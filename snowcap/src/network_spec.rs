@@ -0,0 +1,532 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Network Specification
+//!
+//! This module contains the [`NetworkSpec`], a declarative description of a network and a
+//! reconfiguration scenario, which can be stored as JSON or YAML. It describes the internal and
+//! external routers, the links, the advertised prefixes, both the initial and the final
+//! configuration, and the hard policy. All routers are referenced by their name.
+//!
+//! ```text
+//! routers: [r1, r2]
+//! external_routers:
+//!   - {name: e1, as_id: 65101}
+//! links: [[r1, r2], [r1, e1]]
+//! advertisements:
+//!   - {router: e1, prefix: 0, as_path: [65101, 65200]}
+//! initial_config:
+//!   - IgpWeight: {source: r1, target: r2, weight: 1, symmetric: true}
+//!   - BgpSession: {source: r1, target: r2, session_type: IBgpPeer}
+//!   - BgpSession: {source: e1, target: r1, session_type: EBgp}
+//! final_config:
+//!   - IgpWeight: {source: r1, target: r2, weight: 1, symmetric: true}
+//!   - BgpSession: {source: r2, target: r1, session_type: IBgpClient}
+//!   - BgpSession: {source: e1, target: r1, session_type: EBgp}
+//!   - RouteMap:
+//!       router: r1
+//!       direction: Incoming
+//!       order: 10
+//!       state: Allow
+//!       conds: [{Neighbor: e1}, {Prefix: {Equal: 0}}]
+//!       set: [{LocalPref: 200}]
+//! policy: "G(Reachable(r1, 0) && Reachable(r2, 0))"
+//! ```
+//!
+//! The policy is parsed using [`HardPolicy::parse`]. If it is omitted, all prefixes must be
+//! reachable from all internal routers.
+
+use crate::hard_policies::{HardPolicy, ParseError};
+use crate::netsim::bgp::{BgpRelationship, RovPolicy};
+use crate::netsim::config::{Config, ConfigExpr};
+use crate::netsim::route_map::{
+    RouteMap, RouteMapDirection, RouteMapMatch, RouteMapMatchAsPath, RouteMapMatchClause,
+    RouteMapMatchCommunityList, RouteMapSet, RouteMapState,
+};
+use crate::netsim::{
+    AsId, BgpSessionType, ConfigError, LinkWeight, Network, NetworkError, OspfArea, Prefix,
+    RouterId,
+};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use thiserror::Error;
+
+/// # Network Specification
+/// Declarative description of a network, its initial and final configuration, and the hard
+/// policy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkSpec {
+    /// Names of all internal routers
+    pub routers: Vec<String>,
+    /// External routers
+    #[serde(default)]
+    pub external_routers: Vec<ExternalRouterSpec>,
+    /// Links, referencing both routers by their name
+    #[serde(default)]
+    pub links: Vec<(String, String)>,
+    /// Routes advertised by external routers
+    #[serde(default)]
+    pub advertisements: Vec<AdvertisementSpec>,
+    /// Initial configuration
+    #[serde(default)]
+    pub initial_config: Vec<ConfigExprSpec>,
+    /// Final configuration
+    #[serde(default)]
+    pub final_config: Vec<ConfigExprSpec>,
+    /// Hard policy, which is parsed with [`HardPolicy::parse`]. If it is `None`, all prefixes
+    /// must be reachable from all internal routers.
+    #[serde(default)]
+    pub policy: Option<String>,
+}
+
+/// External router of a [`NetworkSpec`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalRouterSpec {
+    /// Name of the external router
+    pub name: String,
+    /// AS number of the external router
+    pub as_id: u32,
+}
+
+/// Route advertised by an external router of a [`NetworkSpec`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdvertisementSpec {
+    /// Name of the external router advertising the route
+    pub router: String,
+    /// Advertised prefix
+    pub prefix: u32,
+    /// AS path of the route, starting with the AS of the external router
+    pub as_path: Vec<u32>,
+    /// MED attribute of the route
+    #[serde(default)]
+    pub med: Option<u32>,
+    /// Community of the route
+    #[serde(default)]
+    pub community: Option<u32>,
+    /// Additional communities of the route
+    #[serde(default)]
+    pub communities: Vec<u32>,
+}
+
+/// Configuration expression of a [`NetworkSpec`]. These expressions correspond to
+/// [`ConfigExpr`], but all routers are referenced by their name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ConfigExprSpec {
+    /// Link weight of a link. If `symmetric` is set, the weight is configured in both directions.
+    IgpWeight {
+        /// Source router of the link
+        source: String,
+        /// Target router of the link
+        target: String,
+        /// Link weight
+        weight: LinkWeight,
+        /// Configure the weight in both directions
+        #[serde(default)]
+        symmetric: bool,
+    },
+    /// OSPF area of a link
+    OspfArea {
+        /// First router of the link
+        router_a: String,
+        /// Second router of the link
+        router_b: String,
+        /// Area of the link
+        area: u32,
+    },
+    /// OSPF stub area
+    OspfStubArea {
+        /// The stub area
+        area: u32,
+    },
+    /// BGP session
+    BgpSession {
+        /// Source router of the session
+        source: String,
+        /// Target router of the session
+        target: String,
+        /// Session type
+        session_type: BgpSessionType,
+    },
+    /// Graceful restart for the BGP session with a neighbor
+    BgpGracefulRestart {
+        /// Router on which to enable graceful restart
+        router: String,
+        /// Neighbor of the BGP session
+        neighbor: String,
+    },
+    /// Graceful shutdown of the BGP session with a neighbor
+    BgpGracefulShutdown {
+        /// Router on which the graceful shutdown is started
+        router: String,
+        /// Neighbor of the BGP session
+        neighbor: String,
+    },
+    /// Business relationship with an external neighbor
+    BgpRelationship {
+        /// External neighbor
+        neighbor: String,
+        /// Relationship with the neighbor
+        relationship: BgpRelationship,
+    },
+    /// Route map on a router
+    RouteMap {
+        /// Router on which the route map is configured
+        router: String,
+        /// Direction of the route map
+        direction: RouteMapDirection,
+        /// Order of the route map
+        order: usize,
+        /// State of the route map (allow or deny)
+        state: RouteMapState,
+        /// Match conditions of the route map
+        #[serde(default)]
+        conds: Vec<RouteMapMatchSpec>,
+        /// Set actions of the route map
+        #[serde(default)]
+        set: Vec<RouteMapSetSpec>,
+    },
+    /// Static route
+    StaticRoute {
+        /// Router on which the static route is configured
+        router: String,
+        /// Prefix of the static route
+        prefix: u32,
+        /// Neighbor to which the packets are forwarded
+        target: String,
+    },
+    /// Redistribution of static routes into BGP
+    RedistributeStatic {
+        /// Router on which the static routes are redistributed
+        router: String,
+    },
+    /// Comparing the MED of routes from different neighbor ASes
+    BgpAlwaysCompareMed {
+        /// Router on which the MED is always compared
+        router: String,
+    },
+    /// Cluster ID of a route reflector
+    BgpClusterId {
+        /// Route reflector on which the cluster ID is configured
+        router: String,
+        /// Router whose ID is used as cluster ID
+        cluster_id: String,
+    },
+    /// Route Origin Validation on a router
+    RovPolicy {
+        /// Router on which ROV is enabled
+        router: String,
+        /// How to handle RPKI-invalid routes
+        policy: RovPolicy,
+    },
+    /// Route Origin Authorization known to a router
+    Roa {
+        /// Router which knows the ROA
+        router: String,
+        /// Prefix covered by the ROA
+        prefix: u32,
+        /// AS that is authorized to originate the prefix
+        origin: u32,
+    },
+}
+
+/// Match condition of a route map in a [`NetworkSpec`], corresponding to [`RouteMapMatch`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RouteMapMatchSpec {
+    /// Match on the neighbor from which the route was learned
+    Neighbor(String),
+    /// Match on the prefix
+    Prefix(RouteMapMatchClause<Prefix>),
+    /// Match on the AS path
+    AsPath(RouteMapMatchAsPath),
+    /// Match on the next hop
+    NextHop(String),
+    /// Match on the community, or on routes without community if `None`
+    Community(Option<RouteMapMatchClause<u32>>),
+    /// Match on a list of communities
+    CommunityList(RouteMapMatchCommunityList),
+}
+
+/// Set action of a route map in a [`NetworkSpec`], corresponding to [`RouteMapSet`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RouteMapSetSpec {
+    /// Overwrite the next hop
+    NextHop(String),
+    /// Overwrite (or reset) the local preference
+    LocalPref(Option<u32>),
+    /// Overwrite (or reset) the MED
+    Med(Option<u32>),
+    /// Overwrite the IGP cost to the next hop
+    IgpCost(LinkWeight),
+    /// Overwrite (or remove) the community
+    Community(Option<u32>),
+    /// Add a community
+    AddCommunity(u32),
+    /// Remove a community
+    DelCommunity(u32),
+    /// Add a list of communities
+    AddCommunities(BTreeSet<u32>),
+    /// Remove a list of communities
+    DelCommunities(BTreeSet<u32>),
+    /// Prepend the AS to the AS path the given number of times
+    PrependAsPath(u32, usize),
+}
+
+impl NetworkSpec {
+    /// Read the specification from JSON.
+    pub fn from_json(reader: impl Read) -> Result<Self, NetworkSpecError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Read the specification from YAML.
+    pub fn from_yaml(reader: impl Read) -> Result<Self, NetworkSpecError> {
+        Ok(serde_yaml::from_reader(reader)?)
+    }
+
+    /// Read the specification from a file. If the file ends with `.yaml` or `.yml`, it is parsed
+    /// as YAML, and as JSON otherwise.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, NetworkSpecError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        if path.extension().map(|e| e == "yaml" || e == "yml").unwrap_or(false) {
+            Self::from_yaml(file)
+        } else {
+            Self::from_json(file)
+        }
+    }
+
+    /// Build the network (configured with the initial configuration), the final configuration and
+    /// the hard policy.
+    pub fn build(&self) -> Result<(Network, Config, HardPolicy), NetworkSpecError> {
+        let mut net = Network::new();
+        let mut ids: HashMap<String, RouterId> = HashMap::new();
+
+        for name in self.routers.iter() {
+            if ids.insert(name.clone(), net.add_router(name.as_str())).is_some() {
+                return Err(NetworkSpecError::DuplicateRouter(name.clone()));
+            }
+        }
+        for r in self.external_routers.iter() {
+            let id = net.add_external_router(r.name.as_str(), AsId(r.as_id));
+            if ids.insert(r.name.clone(), id).is_some() {
+                return Err(NetworkSpecError::DuplicateRouter(r.name.clone()));
+            }
+        }
+
+        for (a, b) in self.links.iter() {
+            net.add_link(lookup(&ids, a)?, lookup(&ids, b)?);
+        }
+
+        let initial_config = build_config(&ids, &self.initial_config)?;
+        let final_config = build_config(&ids, &self.final_config)?;
+        net.set_config(&initial_config)?;
+
+        for adv in self.advertisements.iter() {
+            net.advertise_external_route_with_communities(
+                lookup(&ids, &adv.router)?,
+                Prefix(adv.prefix),
+                adv.as_path.iter().map(|a| AsId(*a)).collect(),
+                adv.med,
+                adv.community.into_iter().chain(adv.communities.iter().cloned()),
+            )?;
+        }
+
+        let hard_policy = match self.policy.as_ref() {
+            Some(policy) => HardPolicy::parse(policy, &net)?,
+            None => {
+                HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter())
+            }
+        };
+
+        Ok((net, final_config, hard_policy))
+    }
+}
+
+/// Resolve the name of a router
+fn lookup(ids: &HashMap<String, RouterId>, name: &str) -> Result<RouterId, NetworkSpecError> {
+    ids.get(name).copied().ok_or_else(|| NetworkSpecError::UnknownRouter(name.to_string()))
+}
+
+/// Build the configuration from the specification
+fn build_config(
+    ids: &HashMap<String, RouterId>,
+    exprs: &[ConfigExprSpec],
+) -> Result<Config, NetworkSpecError> {
+    let mut config = Config::new();
+    for expr in exprs {
+        match expr {
+            ConfigExprSpec::IgpWeight { source, target, weight, symmetric } => {
+                let source = lookup(ids, source)?;
+                let target = lookup(ids, target)?;
+                config.add(ConfigExpr::IgpLinkWeight { source, target, weight: *weight })?;
+                if *symmetric {
+                    config.add(ConfigExpr::IgpLinkWeight {
+                        source: target,
+                        target: source,
+                        weight: *weight,
+                    })?;
+                }
+            }
+            ConfigExprSpec::OspfArea { router_a, router_b, area } => {
+                config.add(ConfigExpr::OspfArea {
+                    router_a: lookup(ids, router_a)?,
+                    router_b: lookup(ids, router_b)?,
+                    area: OspfArea(*area),
+                })?
+            }
+            ConfigExprSpec::OspfStubArea { area } => {
+                config.add(ConfigExpr::OspfStubArea { area: OspfArea(*area) })?
+            }
+            ConfigExprSpec::BgpSession { source, target, session_type } => {
+                config.add(ConfigExpr::BgpSession {
+                    source: lookup(ids, source)?,
+                    target: lookup(ids, target)?,
+                    session_type: *session_type,
+                })?
+            }
+            ConfigExprSpec::BgpGracefulRestart { router, neighbor } => {
+                config.add(ConfigExpr::BgpGracefulRestart {
+                    router: lookup(ids, router)?,
+                    neighbor: lookup(ids, neighbor)?,
+                })?
+            }
+            ConfigExprSpec::BgpGracefulShutdown { router, neighbor } => {
+                config.add(ConfigExpr::BgpGracefulShutdown {
+                    router: lookup(ids, router)?,
+                    neighbor: lookup(ids, neighbor)?,
+                })?
+            }
+            ConfigExprSpec::BgpRelationship { neighbor, relationship } => {
+                config.add(ConfigExpr::BgpRelationship {
+                    neighbor: lookup(ids, neighbor)?,
+                    relationship: *relationship,
+                })?
+            }
+            ConfigExprSpec::RouteMap { router, direction, order, state, conds, set } => {
+                let conds = conds
+                    .iter()
+                    .map(|c| {
+                        Ok(match c {
+                            RouteMapMatchSpec::Neighbor(n) => {
+                                RouteMapMatch::Neighbor(lookup(ids, n)?)
+                            }
+                            RouteMapMatchSpec::Prefix(c) => RouteMapMatch::Prefix(c.clone()),
+                            RouteMapMatchSpec::AsPath(c) => RouteMapMatch::AsPath(c.clone()),
+                            RouteMapMatchSpec::NextHop(n) => {
+                                RouteMapMatch::NextHop(lookup(ids, n)?)
+                            }
+                            RouteMapMatchSpec::Community(c) => RouteMapMatch::Community(c.clone()),
+                            RouteMapMatchSpec::CommunityList(l) => {
+                                RouteMapMatch::CommunityList(l.clone())
+                            }
+                        })
+                    })
+                    .collect::<Result<Vec<_>, NetworkSpecError>>()?;
+                let set = set
+                    .iter()
+                    .map(|s| {
+                        Ok(match s {
+                            RouteMapSetSpec::NextHop(n) => RouteMapSet::NextHop(lookup(ids, n)?),
+                            RouteMapSetSpec::LocalPref(x) => RouteMapSet::LocalPref(*x),
+                            RouteMapSetSpec::Med(x) => RouteMapSet::Med(*x),
+                            RouteMapSetSpec::IgpCost(x) => RouteMapSet::IgpCost(*x),
+                            RouteMapSetSpec::Community(x) => RouteMapSet::Community(*x),
+                            RouteMapSetSpec::AddCommunity(x) => RouteMapSet::AddCommunity(*x),
+                            RouteMapSetSpec::DelCommunity(x) => RouteMapSet::DelCommunity(*x),
+                            RouteMapSetSpec::AddCommunities(l) => {
+                                RouteMapSet::AddCommunities(l.clone())
+                            }
+                            RouteMapSetSpec::DelCommunities(l) => {
+                                RouteMapSet::DelCommunities(l.clone())
+                            }
+                            RouteMapSetSpec::PrependAsPath(a, n) => {
+                                RouteMapSet::PrependAsPath(AsId(*a), *n)
+                            }
+                        })
+                    })
+                    .collect::<Result<Vec<_>, NetworkSpecError>>()?;
+                config.add(ConfigExpr::BgpRouteMap {
+                    router: lookup(ids, router)?,
+                    direction: *direction,
+                    map: RouteMap::new(*order, *state, conds, set),
+                })?
+            }
+            ConfigExprSpec::StaticRoute { router, prefix, target } => {
+                config.add(ConfigExpr::StaticRoute {
+                    router: lookup(ids, router)?,
+                    prefix: Prefix(*prefix),
+                    target: lookup(ids, target)?,
+                })?
+            }
+            ConfigExprSpec::RedistributeStatic { router } => {
+                config.add(ConfigExpr::RedistributeStatic { router: lookup(ids, router)? })?
+            }
+            ConfigExprSpec::BgpAlwaysCompareMed { router } => {
+                config.add(ConfigExpr::BgpAlwaysCompareMed { router: lookup(ids, router)? })?
+            }
+            ConfigExprSpec::BgpClusterId { router, cluster_id } => {
+                config.add(ConfigExpr::BgpClusterId {
+                    router: lookup(ids, router)?,
+                    cluster_id: lookup(ids, cluster_id)?,
+                })?
+            }
+            ConfigExprSpec::RovPolicy { router, policy } => config
+                .add(ConfigExpr::RovPolicy { router: lookup(ids, router)?, policy: *policy })?,
+            ConfigExprSpec::Roa { router, prefix, origin } => config.add(ConfigExpr::Roa {
+                router: lookup(ids, router)?,
+                prefix: Prefix(*prefix),
+                origin: AsId(*origin),
+            })?,
+        }
+    }
+    Ok(config)
+}
+
+/// Error while loading a network specification
+#[derive(Debug, Error)]
+pub enum NetworkSpecError {
+    /// Cannot read the file
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// Cannot parse the JSON file
+    #[error("JSON Error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    /// Cannot parse the YAML file
+    #[error("YAML Error: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+    /// The router name is used multiple times
+    #[error("Router {0} is defined multiple times")]
+    DuplicateRouter(String),
+    /// The router name does not exist in the network
+    #[error("Router {0} does not exist in the network")]
+    UnknownRouter(String),
+    /// The configuration is invalid
+    #[error("Config Error: {0}")]
+    ConfigError(#[from] ConfigError),
+    /// The network cannot be built
+    #[error("Network Error: {0}")]
+    NetworkError(#[from] NetworkError),
+    /// The hard policy cannot be parsed
+    #[error("Policy Parse Error: {0}")]
+    PolicyParseError(#[from] ParseError),
+}
@@ -22,8 +22,11 @@ use crate::hard_policies::HardPolicy;
 use crate::netsim::config::ConfigModifier;
 use crate::netsim::{printer, Network, NetworkError};
 use crate::soft_policies::SoftPolicy;
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error, PartialResult};
 
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, SystemTime};
 
 use log::*;
@@ -32,7 +35,8 @@ use log::*;
 ///
 /// Optimizer that enumerates all possible solutions, and chooses the one which minimizes the soft
 /// policies. This optimizer is no longer feasible to use for problems containing 10 or more
-/// modifiers. However, it will always return the best possible ordering.
+/// modifiers. However, it will always return the best possible ordering. Use
+/// [`GlobalOptimizer::work_parallel`] to explore the orderings using multiple threads.
 #[derive(Debug)]
 pub struct GlobalOptimizer<P>
 where
@@ -74,7 +78,7 @@ where
         let mut fw_state = net.get_forwarding_state();
         hard_policy.step(&mut net, &mut fw_state)?;
         if !hard_policy.check() {
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
         Ok(Box::new(Self {
@@ -88,7 +92,7 @@ where
         }))
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<(Vec<ConfigModifier>, f64), Error> {
+    fn work(&mut self, mut abort: ControlHandle) -> Result<(Vec<ConfigModifier>, f64), Error> {
         let num_mod = self.modifiers.len();
 
         let mut net = self.net.clone();
@@ -113,11 +117,14 @@ where
         // stores the currently best solution
         let mut best: Option<(Vec<usize>, f64)> = None;
         let mut aborted: bool = false;
+        // longest valid prefix, which is reported if no solution is found before the timeout
+        let mut best_prefix: (Vec<usize>, f64) = (Vec::new(), 0.0);
+        let start_time = SystemTime::now();
 
         // start the procedure
         loop {
             // check for max iterations
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 // time budget is used up!
                 aborted = true;
                 if best.is_some() {
@@ -142,6 +149,7 @@ where
             // check if we have already a complete ordering
             if current_ord.len() == num_mod {
                 let cost = current_ord.iter().fold(0.0, |acc, x| acc + x.cost);
+                abort.record_cost(cost);
                 if best.is_some() {
                     let (old_ord, old_cost) = best.unwrap();
                     if cost < old_cost {
@@ -176,6 +184,12 @@ where
                     &mut hard_policy,
                 ));
                 current_ord.push(next_best_option);
+                if current_ord.len() > best_prefix.0.len() {
+                    best_prefix = (
+                        current_ord.iter().map(|x| x.mod_idx).collect(),
+                        current_ord.iter().fold(0.0, |acc, x| acc + x.cost),
+                    );
+                }
             } else {
                 // pop the stack, we need to go back because the top stack frame has no options left
                 stack.pop();
@@ -197,11 +211,18 @@ where
             }
         } else {
             error!("No valid solution was found!");
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 if abort.try_is_stop().unwrap_or(false) {
                     Err(Error::Abort)
                 } else {
-                    Err(Error::Timeout)
+                    let sequence =
+                        best_prefix.0.iter().map(|m| self.modifiers[*m].clone()).collect();
+                    let partial = PartialResult::new(sequence, &self.modifiers)
+                        .with_cost(best_prefix.1)
+                        .with_elapsed(start_time.elapsed().unwrap_or_default());
+                    #[cfg(feature = "count-states")]
+                    let partial = partial.with_num_states(self.num_states);
+                    Err(partial.into())
                 }
             } else {
                 Err(Error::NoSafeOrdering)
@@ -228,50 +249,299 @@ where
         net: &mut Network,
         hard_policy: &mut HardPolicy,
     ) -> Vec<StepOption<P>> {
-        let mut result = Vec::new();
-        for (i, opt) in state.choices.clone().into_iter().enumerate() {
-            #[cfg(feature = "count-states")]
-            {
-                self.num_states += 1;
+        #[allow(unused_variables)]
+        let (result, num_states) = next_options(&self.modifiers, state, net, hard_policy);
+        #[cfg(feature = "count-states")]
+        {
+            self.num_states += num_states;
+        }
+        result
+    }
+}
+
+impl<P> GlobalOptimizer<P>
+where
+    P: SoftPolicy + Clone + Send + 'static,
+{
+    /// Enumerate all possible solutions using multiple threads, and choose the one which minimizes
+    /// the soft policies. If `num_threads` is `None`, then one thread per CPU is spawned.
+    ///
+    /// The options for the first modifier are computed upfront, and each one forms a disjoint
+    /// subtree of the search space. The threads take the subtrees (lowest cost first), and explore
+    /// them in the same way as [`Optimizer::work`]. All threads share the cost of the best solution
+    /// found so far. The time budget and the `abort` handle apply to all threads.
+    ///
+    /// Unlike [`Optimizer::work`], which enumerates every valid ordering, the search is pruned: a
+    /// partial ordering is not extended once its cost exceeds the cost of the best complete
+    /// ordering found so far. Since the cost of every step is non-negative (see [`SoftPolicy`]),
+    /// this never prunes an optimal ordering. However, fewer orderings are explored and reported
+    /// to the `abort` handle, and if the time budget is used up, the returned ordering may differ
+    /// from the one returned by [`Optimizer::work`].
+    ///
+    /// The cost of the result is the same as the one of [`Optimizer::work`]. However, if multiple
+    /// orderings have the same cost, this function returns the one with the lexicographically
+    /// smallest sequence of modifier indices, which might be a different ordering than the one
+    /// found by [`Optimizer::work`].
+    pub fn work_parallel(
+        &mut self,
+        abort: ControlHandle,
+        num_threads: Option<usize>,
+    ) -> Result<(Vec<ConfigModifier>, f64), Error> {
+        let num_threads = num_threads.unwrap_or_else(num_cpus::get).max(1);
+        if self.modifiers.is_empty() {
+            return Ok((Vec::new(), 0.0));
+        }
+
+        // compute all subtrees
+        let mut net = self.net.clone();
+        let mut hard_policy = self.hard_policy.clone();
+        let root = StepOption {
+            mod_idx: 0,
+            cost: 0.0,
+            choices: (0..self.modifiers.len()).collect(),
+            soft_policy: self.soft_policy.clone(),
+        };
+        let subtrees = self.compute_next_options(&root, &mut net, &mut hard_policy);
+        info!("Exploring {} subtrees with {} threads", subtrees.len(), num_threads);
+
+        let shared = Arc::new(SharedSearch {
+            modifiers: self.modifiers.clone(),
+            stop_time: self.stop_time,
+            subtrees: Mutex::new(subtrees),
+            best: Mutex::new(None),
+            incumbent: AtomicU64::new(f64::INFINITY.to_bits()),
+            aborted: AtomicBool::new(false),
+            num_states: AtomicUsize::new(0),
+        });
+
+        let handles = (0..num_threads)
+            .map(|_| {
+                let shared = shared.clone();
+                let net = net.clone();
+                let hard_policy = hard_policy.clone();
+                let abort = abort.clone();
+                thread::spawn(move || shared.explore(net, hard_policy, abort))
+            })
+            .collect::<Vec<_>>();
+
+        let mut error = None;
+        for handle in handles {
+            if let Err(e) = handle.join().unwrap() {
+                error = Some(e);
             }
-            // first, apply the modifier and get the new network
-            let modifier = self.modifiers.get(opt).unwrap();
-            match net.apply_modifier(modifier) {
-                Ok(_) => {
-                    // Network did converge! get the network state
-                    let mut fw_state = net.get_forwarding_state();
-                    if let Err(e) = hard_policy.step(net, &mut fw_state) {
-                        error!("Error while checking the hard policy: {}", e);
-                        panic!("Error while checking the hard policy: {}", e);
-                    }
-                    // first, check the hard policies
-                    if hard_policy.check() {
-                        // Hard hard_policy are met! Compute the cost and add to the stack frame
-                        let mut soft_policy = state.soft_policy.clone();
-                        soft_policy.update(&mut fw_state, &net);
-                        let cost = soft_policy.cost();
-                        let mut choices_left = state.choices.clone();
-                        choices_left.remove(i);
-                        result.push(StepOption {
-                            mod_idx: opt,
-                            cost,
-                            choices: choices_left,
-                            soft_policy,
-                        });
-                    }
+        }
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        #[cfg(feature = "count-states")]
+        {
+            self.num_states += shared.num_states.load(Ordering::Relaxed);
+        }
+
+        let aborted = shared.aborted.load(Ordering::Relaxed);
+        let best = shared.best.lock().unwrap().take();
+        match (best, aborted) {
+            (Some((ord, cost)), false) => {
+                Ok((ord.into_iter().map(|mid| self.modifiers[mid].clone()).collect(), cost))
+            }
+            (Some((ord, cost)), true) => Err(Error::GlobalOptimumNotFound(
+                ord.into_iter().map(|mid| self.modifiers[mid].clone()).collect(),
+                cost,
+            )),
+            (None, false) => {
+                error!("No valid solution was found!");
+                Err(Error::NoSafeOrdering)
+            }
+            (None, true) => {
+                error!("No valid solution was found!");
+                if abort.is_stop() {
+                    Err(Error::Abort)
+                } else {
+                    Err(Error::timeout(&self.modifiers))
+                }
+            }
+        }
+    }
+}
+
+/// State shared by all threads of [`GlobalOptimizer::work_parallel`].
+struct SharedSearch<P: SoftPolicy + Clone> {
+    modifiers: Vec<ConfigModifier>,
+    stop_time: Option<SystemTime>,
+    /// Subtrees which are not yet explored, the one with the lowest cost is last.
+    subtrees: Mutex<Vec<StepOption<P>>>,
+    /// Best ordering found so far.
+    best: Mutex<Option<(Vec<usize>, f64)>>,
+    /// Cost of the best ordering found so far, stored as the bits of a `f64`.
+    incumbent: AtomicU64,
+    aborted: AtomicBool,
+    num_states: AtomicUsize,
+}
+
+impl<P: SoftPolicy + Clone> SharedSearch<P> {
+    /// Explore subtrees until there are none left, or until the search is aborted. The network
+    /// and the hard policy must be in the initial state.
+    fn explore(
+        &self,
+        mut net: Network,
+        mut hard_policy: HardPolicy,
+        mut abort: ControlHandle,
+    ) -> Result<(), Error> {
+        let num_mod = self.modifiers.len();
+
+        while let Some(subtree) = self.next_subtree() {
+            if subtree.cost > self.incumbent() {
+                continue;
+            }
+
+            net.apply_modifier(&self.modifiers[subtree.mod_idx]).unwrap();
+            let mut fw_state = net.get_forwarding_state();
+            hard_policy.step(&mut net, &mut fw_state)?;
+            let mut stack = vec![self.next_options(&subtree, &mut net, &mut hard_policy)];
+            let mut current_ord = vec![subtree];
+
+            while !stack.is_empty() {
+                if self.should_stop(&mut abort) {
+                    self.aborted.store(true, Ordering::Relaxed);
+                    return Ok(());
+                }
+
+                // check if we have already a complete ordering
+                if current_ord.len() == num_mod {
+                    let cost = cost_of(&current_ord);
+                    abort.record_cost(cost);
+                    self.update_best(current_ord.iter().map(|x| x.mod_idx).collect(), cost);
+                    stack.pop();
+                    current_ord.pop();
+                    net.undo_action()?;
                     hard_policy.undo();
+                    continue;
                 }
-                Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
-                    // Network did not converge! Option is not possible. Nothing to do here!
+
+                let next_option = stack.last_mut().unwrap().pop();
+                match next_option {
+                    Some(next) if cost_of(&current_ord) + next.cost <= self.incumbent() => {
+                        net.apply_modifier(&self.modifiers[next.mod_idx]).unwrap();
+                        let mut fw_state = net.get_forwarding_state();
+                        hard_policy.step(&mut net, &mut fw_state)?;
+                        stack.push(self.next_options(&next, &mut net, &mut hard_policy));
+                        current_ord.push(next);
+                    }
+                    _ => {
+                        // all remaining options are more expensive than the best solution (the
+                        // frame is sorted), or no option is left. Go back.
+                        stack.pop();
+                        current_ord.pop();
+                        net.undo_action()?;
+                        hard_policy.undo();
+                    }
                 }
-                Err(e) => panic! {"Unrecoverable network error: {}", e},
             }
-            net.undo_action().unwrap();
         }
-        // sort the frame sucht that lowest cost element is last (to be popped first)!
-        result.sort_by(|a, b| b.cost.partial_cmp(&a.cost).unwrap());
+        Ok(())
+    }
+
+    /// Returns the next subtree to explore, or `None` if all subtrees are explored, or if the
+    /// search was aborted by another thread.
+    fn next_subtree(&self) -> Option<StepOption<P>> {
+        if self.aborted.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.subtrees.lock().unwrap().pop()
+    }
+
+    fn next_options(
+        &self,
+        state: &StepOption<P>,
+        net: &mut Network,
+        hard_policy: &mut HardPolicy,
+    ) -> Vec<StepOption<P>> {
+        let (result, num_states) = next_options(&self.modifiers, state, net, hard_policy);
+        self.num_states.fetch_add(num_states, Ordering::Relaxed);
         result
     }
+
+    fn should_stop(&self, abort: &mut ControlHandle) -> bool {
+        self.aborted.load(Ordering::Relaxed)
+            || abort.time_is_up(self.stop_time)
+            || abort.try_is_stop().unwrap_or(false)
+    }
+
+    fn incumbent(&self) -> f64 {
+        f64::from_bits(self.incumbent.load(Ordering::Relaxed))
+    }
+
+    /// Store the ordering if it is better than the best one. Orderings with the same cost are
+    /// compared lexicographically, to make the result independent of the thread scheduling.
+    fn update_best(&self, ord: Vec<usize>, cost: f64) {
+        let mut best = self.best.lock().unwrap();
+        let is_better = match best.as_ref() {
+            Some((old_ord, old_cost)) => cost < *old_cost || (cost == *old_cost && ord < *old_ord),
+            None => true,
+        };
+        if is_better {
+            self.incumbent.store(cost.to_bits(), Ordering::Relaxed);
+            *best = Some((ord, cost));
+        }
+    }
+}
+
+/// Returns the cost of a (partial) ordering
+fn cost_of<P: SoftPolicy + Clone>(ord: &[StepOption<P>]) -> f64 {
+    ord.iter().fold(0.0, |acc, x| acc + x.cost)
+}
+
+/// Takes in a vector over the options to pick (as index of the modifiers), and returns a single
+/// stack frame with all the possible options to take, sorted such that the option with the lowest
+/// cost is last, and the number of explored states. In the end, the network will not be modified.
+fn next_options<P: SoftPolicy + Clone>(
+    modifiers: &[ConfigModifier],
+    state: &StepOption<P>,
+    net: &mut Network,
+    hard_policy: &mut HardPolicy,
+) -> (Vec<StepOption<P>>, usize) {
+    let mut result = Vec::new();
+    let mut num_states = 0;
+    for (i, opt) in state.choices.clone().into_iter().enumerate() {
+        num_states += 1;
+        // first, apply the modifier and get the new network
+        let modifier = modifiers.get(opt).unwrap();
+        match net.apply_modifier(modifier) {
+            Ok(_) => {
+                // Network did converge! get the network state
+                let mut fw_state = net.get_forwarding_state();
+                if let Err(e) = hard_policy.step(net, &mut fw_state) {
+                    error!("Error while checking the hard policy: {}", e);
+                    panic!("Error while checking the hard policy: {}", e);
+                }
+                // first, check the hard policies
+                if hard_policy.check() {
+                    // Hard hard_policy are met! Compute the cost and add to the stack frame
+                    let mut soft_policy = state.soft_policy.clone();
+                    soft_policy.update(&mut fw_state, &net);
+                    let cost = soft_policy.cost();
+                    let mut choices_left = state.choices.clone();
+                    choices_left.remove(i);
+                    result.push(StepOption {
+                        mod_idx: opt,
+                        cost,
+                        choices: choices_left,
+                        soft_policy,
+                    });
+                }
+                hard_policy.undo();
+            }
+            Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                // Network did not converge! Option is not possible. Nothing to do here!
+            }
+            Err(e) => panic! {"Unrecoverable network error: {}", e},
+        }
+        net.undo_action().unwrap();
+    }
+    // sort the frame sucht that lowest cost element is last (to be popped first)!
+    result.sort_by(|a, b| b.cost.partial_cmp(&a.cost).unwrap());
+    (result, num_states)
 }
 
 #[derive(Debug, Clone)]
@@ -288,6 +558,7 @@ mod tests {
     use crate::example_networks::repetitions::*;
     use crate::example_networks::*;
     use crate::hard_policies::*;
+    use crate::rationale::check_ordering;
     use crate::soft_policies::*;
     use assert_approx_eq::assert_approx_eq;
 
@@ -307,7 +578,7 @@ mod tests {
 
         let expected_cost = (R::get_count() as f64) / ((R::get_count() + 2) as f64);
 
-        let (_, cost) = o.work(Stopper::new()).unwrap();
+        let (_, cost) = o.work(ControlHandle::new()).unwrap();
         assert_approx_eq!(expected_cost, cost);
     }
 
@@ -327,7 +598,110 @@ mod tests {
 
         let expected_cost = 0.0;
 
-        let (_, cost) = o.work(Stopper::new()).unwrap();
+        let (_, cost) = o.work(ControlHandle::new()).unwrap();
         assert_approx_eq!(expected_cost, cost);
     }
+
+    #[test]
+    fn test_chain_gadget_parallel() {
+        type R = Repetition5;
+        type T = ChainGadget<R>;
+        let net = T::net(0);
+        let cf = T::final_config(&net, 0);
+        let patch = net.current_config().get_diff(&cf);
+        let hard_policy =
+            HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+        let soft_policy = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
+
+        let mut o = GlobalOptimizer::new(
+            net.clone(),
+            patch.modifiers.clone(),
+            hard_policy.clone(),
+            soft_policy.clone(),
+            None,
+        )
+        .unwrap();
+        let (_, expected_cost) = o.work(ControlHandle::new()).unwrap();
+
+        for num_threads in vec![1, 4] {
+            let mut o = GlobalOptimizer::new(
+                net.clone(),
+                patch.modifiers.clone(),
+                hard_policy.clone(),
+                soft_policy.clone(),
+                None,
+            )
+            .unwrap();
+            let (sequence, cost) =
+                o.work_parallel(ControlHandle::new(), Some(num_threads)).unwrap();
+            assert_approx_eq!(expected_cost, cost);
+            assert_eq!(sequence.len(), patch.modifiers.len());
+        }
+    }
+
+    #[test]
+    fn test_chain_gadget_parallel_ltl() {
+        type R = Repetition5;
+        type T = ChainGadget<R>;
+        let net = T::net(0);
+        let cf = T::final_config(&net, 0);
+        let patch = net.current_config().get_diff(&cf);
+
+        // once a router uses its final egress, it must keep it. Until then, it must keep its
+        // initial egress.
+        let mut final_net = net.clone();
+        final_net.set_config(&cf).unwrap();
+        let mut fw_state_a = net.get_forwarding_state();
+        let mut fw_state_b = final_net.get_forwarding_state();
+        let mut prop_vars = Vec::new();
+        let mut expr: Vec<Box<dyn LTLOperator>> = Vec::new();
+        for r in net.get_routers() {
+            for p in net.get_known_prefixes() {
+                let egress_a = *fw_state_a.get_route(r, *p).unwrap().last().unwrap();
+                let egress_b = *fw_state_b.get_route(r, *p).unwrap().last().unwrap();
+                let i = prop_vars.len();
+                prop_vars.push(Condition::Reachable(r, *p, Some(PathCondition::Node(egress_a))));
+                prop_vars.push(Condition::Reachable(r, *p, Some(PathCondition::Node(egress_b))));
+                expr.push(Box::new(LTLModal::WeakUntil(
+                    Box::new(i),
+                    Box::new(LTLModal::Globally(Box::new(i + 1))),
+                )));
+            }
+        }
+        let hard_policy =
+            HardPolicy::new(prop_vars, LTLModal::Now(Box::new(LTLBoolean::And(expr))));
+        let soft_policy = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
+
+        let mut o = GlobalOptimizer::new(
+            net.clone(),
+            patch.modifiers.clone(),
+            hard_policy.clone(),
+            soft_policy.clone(),
+            None,
+        )
+        .unwrap();
+        let expected = o.work(ControlHandle::new());
+
+        for num_threads in vec![1, 4] {
+            let mut o = GlobalOptimizer::new(
+                net.clone(),
+                patch.modifiers.clone(),
+                hard_policy.clone(),
+                soft_policy.clone(),
+                None,
+            )
+            .unwrap();
+            match (&expected, o.work_parallel(ControlHandle::new(), Some(num_threads))) {
+                (Ok((_, expected_cost)), Ok((sequence, cost))) => {
+                    assert_approx_eq!(*expected_cost, cost);
+                    let ordering = sequence.iter().collect::<Vec<_>>();
+                    assert!(check_ordering(&net, &ordering, &hard_policy).unwrap().is_none());
+                }
+                (Err(Error::NoSafeOrdering), Err(Error::NoSafeOrdering)) => {}
+                (expected, result) => {
+                    panic!("Expected {:?}, but got {:?}", expected, result)
+                }
+            }
+        }
+    }
 }
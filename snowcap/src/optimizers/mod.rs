@@ -55,7 +55,7 @@
 //!   the sequence will always first insert, then modify, and finally, remove configuration.
 
 mod tree;
-pub use tree::TreeOptimizer;
+pub use tree::{TieBreaker, TreeOptimizer};
 
 mod global;
 pub use global::GlobalOptimizer;
@@ -74,19 +74,69 @@ mod naive_most_important_last;
 #[cfg(feature = "strawman-strategies")]
 pub use naive_most_important_last::NaiveMostImportantLast;
 
-pub use crate::dep_groups::optimizer::DepGroupsOptimizer;
+pub use crate::dep_groups::optimizer::{DepGroupsOptimizer, StoppingCriteria};
 pub use crate::dep_groups::optimizer_trta::OptimizerTRTA;
 
 use crate::hard_policies::HardPolicy;
 use crate::netsim::config::{Config, ConfigModifier};
 use crate::netsim::Network;
 use crate::soft_policies::SoftPolicy;
-use crate::{Error, Stopper};
+use crate::strategies::AlgorithmInfo;
+use crate::{ControlHandle, Error};
 
 use std::time::Duration;
 
 use log::*;
 
+const SOFT_POLICY_PARAM: (&str, &str) = ("P", "Soft policy, whose cost is minimized");
+
+/// List of all optimizers, in the order they are described in the [module
+/// documentation](crate::optimizers). The strawman optimizers, enabled with the feature
+/// `strawman-strategies`, are not part of this list.
+pub const OPTIMIZERS: &[AlgorithmInfo] = &[
+    AlgorithmInfo {
+        name: "OptimizerTRTA",
+        description: "Tree traversal minimizing the cost, searching for dependencies when needed",
+        parameters: &[SOFT_POLICY_PARAM],
+        exhaustive: false,
+    },
+    AlgorithmInfo {
+        name: "TreeOptimizer",
+        description: "Tree traversal, always choosing the modifier with the lowest local cost",
+        parameters: &[SOFT_POLICY_PARAM],
+        exhaustive: true,
+    },
+    AlgorithmInfo {
+        name: "GlobalOptimizer",
+        description: "Computes the cost of every valid ordering and returns the global minimum",
+        parameters: &[SOFT_POLICY_PARAM],
+        exhaustive: true,
+    },
+    AlgorithmInfo {
+        name: "DepGroupsOptimizer",
+        description: "Builds groups of dependencies and keeps the best solution found",
+        parameters: &[
+            SOFT_POLICY_PARAM,
+            ("Perm", "Permutator used to generate the permutations of the groups"),
+            ("S", "Group strategy, used to solve the smaller problems"),
+            ("O", "Optimizer used to solve the smaller problems"),
+        ],
+        exhaustive: false,
+    },
+    AlgorithmInfo {
+        name: "NaiveRandomOptimizer",
+        description: "Tries random orderings (only for evaluation)",
+        parameters: &[SOFT_POLICY_PARAM],
+        exhaustive: false,
+    },
+    AlgorithmInfo {
+        name: "NaiveRandomIBROptimizer",
+        description: "Tries random orderings, scheduling insert before modify before remove",
+        parameters: &[SOFT_POLICY_PARAM],
+        exhaustive: false,
+    },
+];
+
 /// Infterface for all Optimizers
 pub trait Optimizer<P>
 where
@@ -99,7 +149,7 @@ where
         hard_policy: HardPolicy,
         soft_policy: P,
         time_budget: Option<Duration>,
-        abort: Stopper,
+        abort: ControlHandle,
     ) -> Result<(Vec<ConfigModifier>, f64), Error> {
         let start_config = net.current_config().clone();
         let patch = start_config.get_diff(&end_config);
@@ -109,9 +159,9 @@ where
                 info!("Initial configuration is valid!");
                 o
             }
-            Err(Error::InvalidInitialState) => {
-                error!("Invalid initial state");
-                return Err(Error::InvalidInitialState);
+            Err(e @ Error::InvalidInitialState(_)) => {
+                error!("{}", e);
+                return Err(e);
             }
             Err(e) => {
                 error!("Unexpected error while setting up the strategy: {}", e);
@@ -132,7 +182,7 @@ where
 
     /// Main function to find a valid reconfiguration sequence (if it exists) and return it.
     /// The function also returns the cost of the sequence.
-    fn work(&mut self, abort: Stopper) -> Result<(Vec<ConfigModifier>, f64), Error>;
+    fn work(&mut self, abort: ControlHandle) -> Result<(Vec<ConfigModifier>, f64), Error>;
     /// Returns the number of states explored by the strategy.
     ///
     /// *This method is only available if the `"count-states"` feature is enabled!*
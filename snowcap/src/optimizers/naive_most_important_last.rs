@@ -25,7 +25,7 @@ use crate::netsim::{
     Network, RouterId,
 };
 use crate::soft_policies::SoftPolicy;
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error};
 
 use log::*;
 use rand::prelude::*;
@@ -77,12 +77,12 @@ impl<P: SoftPolicy + Clone> Optimizer<P> for NaiveMostImportantLast<P> {
                     .collect::<Vec<_>>()
                     .join("\n    "),
             );
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         Ok(Box::new(Self { net, modifiers, hard_policy, soft_policy }))
     }
 
-    fn work(&mut self, _abort: Stopper) -> Result<(Vec<ConfigModifier>, f64), Error> {
+    fn work(&mut self, _abort: ControlHandle) -> Result<(Vec<ConfigModifier>, f64), Error> {
         let flows =
             self.net.get_forwarding_state().into_iter().map(|(_, _, p)| p).collect::<Vec<_>>();
 
@@ -164,11 +164,32 @@ fn modifier_cost(m: &ConfigModifier, flows: &[Vec<RouterId>]) -> (ModifierGroup,
             ModifierGroup::Link,
             flows.iter().filter(|f| path_contains_edge(source, target, f).is_some()).count(),
         ),
+        ConfigExprKey::OspfArea { router_a, router_b } => (
+            ModifierGroup::Link,
+            flows
+                .iter()
+                .filter(|f| {
+                    path_contains_edge(router_a, router_b, f).is_some()
+                        || path_contains_edge(router_b, router_a, f).is_some()
+                })
+                .count(),
+        ),
+        // a stub area may change the paths of all flows
+        ConfigExprKey::OspfStubArea { .. } => (ModifierGroup::Link, flows.len()),
         ConfigExprKey::BgpSession { speaker_a, speaker_b } => (
             ModifierGroup::Pair,
             flows.iter().filter(|f| f.contains(&speaker_a) || f.contains(&speaker_b)).count(),
         ),
-        ConfigExprKey::BgpRouteMap { router, .. } | ConfigExprKey::StaticRoute { router, .. } => {
+        ConfigExprKey::BgpRouteMap { router, .. }
+        | ConfigExprKey::StaticRoute { router, .. }
+        | ConfigExprKey::RedistributeStatic { router }
+        | ConfigExprKey::BgpAlwaysCompareMed { router }
+        | ConfigExprKey::BgpClusterId { router }
+        | ConfigExprKey::RovPolicy { router }
+        | ConfigExprKey::BgpGracefulRestart { router, .. }
+        | ConfigExprKey::BgpGracefulShutdown { router, .. }
+        | ConfigExprKey::BgpRelationship { neighbor: router }
+        | ConfigExprKey::Roa { router, .. } => {
             (ModifierGroup::Node, flows.iter().filter(|f| f.contains(&router)).count())
         }
     }
@@ -20,7 +20,7 @@ use crate::hard_policies::HardPolicy;
 use crate::netsim::{config::ConfigModifier, Network, NetworkError};
 use crate::soft_policies::SoftPolicy;
 use crate::strategies::{NaiveRandomStrategy, Strategy};
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error};
 
 use log::*;
 use std::time::Duration;
@@ -63,7 +63,7 @@ where
                     .collect::<Vec<_>>()
                     .join("\n    "),
             );
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         Ok(Box::new(Self {
             net,
@@ -76,7 +76,7 @@ where
         }))
     }
 
-    fn work(&mut self, abort: Stopper) -> Result<(Vec<ConfigModifier>, f64), Error> {
+    fn work(&mut self, abort: ControlHandle) -> Result<(Vec<ConfigModifier>, f64), Error> {
         let mut child = NaiveRandomStrategy::new(
             self.net.clone(),
             self.modifiers.clone(),
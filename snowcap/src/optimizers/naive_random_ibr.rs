@@ -20,7 +20,7 @@ use crate::hard_policies::HardPolicy;
 use crate::netsim::{config::ConfigModifier, Network, NetworkError};
 use crate::soft_policies::SoftPolicy;
 use crate::strategies::{NaiveRandomIBRStrategy, Strategy};
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error};
 
 use log::*;
 use std::time::Duration;
@@ -64,7 +64,7 @@ where
                     .collect::<Vec<_>>()
                     .join("\n    "),
             );
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         Ok(Box::new(Self {
             net,
@@ -77,7 +77,7 @@ where
         }))
     }
 
-    fn work(&mut self, abort: Stopper) -> Result<(Vec<ConfigModifier>, f64), Error> {
+    fn work(&mut self, abort: ControlHandle) -> Result<(Vec<ConfigModifier>, f64), Error> {
         let mut child = NaiveRandomIBRStrategy::new(
             self.net.clone(),
             self.modifiers.clone(),
@@ -22,11 +22,12 @@ use crate::hard_policies::HardPolicy;
 use crate::netsim::config::ConfigModifier;
 use crate::netsim::{printer, Network, NetworkError};
 use crate::soft_policies::SoftPolicy;
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error, PartialResult};
 
 use std::time::{Duration, SystemTime};
 
 use log::*;
+use rand::prelude::*;
 
 /// # Tree Optimizer
 ///
@@ -41,6 +42,9 @@ use log::*;
 /// with an immediate effect. In this case, this algorithm is extremely fast. However, when the
 /// problems have no immedaite effect, this algorithm will have problems if the number of modidfiers
 /// is too large.
+///
+/// If multiple options have the same cost, the [`TieBreaker`] decides which one is tried first
+/// (see [`TreeOptimizer::set_tie_breaker`]).
 #[derive(Debug)]
 pub struct TreeOptimizer<P>
 where
@@ -52,10 +56,39 @@ where
     soft_policy: P,
     stop_time: Option<SystemTime>,
     max_backtrack_level: usize,
+    tie_breaker: TieBreaker,
+    rng: StdRng,
+    rejections: Vec<usize>,
     #[cfg(feature = "count-states")]
     num_states: usize,
 }
 
+/// # Tie Breaker
+///
+/// Decides which option the [`TreeOptimizer`] tries first if multiple options have the same cost.
+/// The choice may have a large effect on the final cost, since the optimizer only greedily
+/// minimizes the cost of the next step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreaker {
+    /// Keep the order in which the modifiers were given (default).
+    Arbitrary,
+    /// Choose randomly among the options with equal cost, using a random number generator seeded
+    /// with the given value.
+    Random(u64),
+    /// Prefer modifiers which were rejected more often before (because they violated the hard
+    /// policy). Such modifiers depend on others, and are applied as soon as they become valid.
+    DependencyAware,
+    /// Prefer modifiers with a lower risk score, which is the number of BGP messages exchanged
+    /// while the network converges after applying the modifier.
+    RiskScore,
+}
+
+impl Default for TieBreaker {
+    fn default() -> Self {
+        Self::Arbitrary
+    }
+}
+
 impl<P> Optimizer<P> for TreeOptimizer<P>
 where
     P: SoftPolicy + Clone,
@@ -81,9 +114,10 @@ where
         let mut fw_state = net.get_forwarding_state();
         hard_policy.step(&mut net, &mut fw_state)?;
         if !hard_policy.check() {
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
+        let rejections = vec![0; modifiers.len()];
         Ok(Box::new(Self {
             net,
             modifiers,
@@ -91,12 +125,15 @@ where
             soft_policy,
             stop_time,
             max_backtrack_level: usize::MAX,
+            tie_breaker: TieBreaker::default(),
+            rng: StdRng::seed_from_u64(0),
+            rejections,
             #[cfg(feature = "count-states")]
             num_states: 0,
         }))
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<(Vec<ConfigModifier>, f64), Error> {
+    fn work(&mut self, mut abort: ControlHandle) -> Result<(Vec<ConfigModifier>, f64), Error> {
         let num_mod = self.modifiers.len();
 
         let mut net = self.net.clone();
@@ -120,14 +157,23 @@ where
 
         // generate a vector that stores the current ordering
         let mut current_ord: Vec<StepOption<P>> = Vec::with_capacity(num_mod);
+        // longest valid prefix (as index of the modifiers) found so far, including its cost
+        let mut best_prefix: (Vec<usize>, f64) = (Vec::new(), 0.0);
+        let start_time = SystemTime::now();
 
         // start the procedure
         loop {
             // check for max iterations
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 // time budget is used up!
                 error!("Time budget is used up! No solution was found yet!");
-                break Err(Error::Timeout);
+                let sequence = best_prefix.0.iter().map(|m| self.modifiers[*m].clone()).collect();
+                let partial = PartialResult::new(sequence, &self.modifiers)
+                    .with_cost(best_prefix.1)
+                    .with_elapsed(start_time.elapsed().unwrap_or_default());
+                #[cfg(feature = "count-states")]
+                let partial = partial.with_num_states(self.num_states);
+                break Err(partial.into());
             }
 
             //eprintln!("stack: {}", stack.len());
@@ -143,6 +189,7 @@ where
                 let cost = current_ord.iter().fold(0.0, |acc, x| acc + x.cost);
                 let ordering =
                     current_ord.iter().map(|x| self.modifiers[x.mod_idx].clone()).collect();
+                abort.record_cost(cost);
                 break Ok((ordering, cost));
             }
 
@@ -163,6 +210,12 @@ where
                     &mut hard_policy,
                 ));
                 current_ord.push(next_best_option);
+                if current_ord.len() > best_prefix.0.len() {
+                    best_prefix = (
+                        current_ord.iter().map(|x| x.mod_idx).collect(),
+                        current_ord.iter().fold(0.0, |acc, x| acc + x.cost),
+                    );
+                }
             } else {
                 // pop the stack, we need to go back because the top stack frame has no options left
                 stack.pop();
@@ -198,6 +251,15 @@ where
         self.max_backtrack_level = max_backtrack;
     }
 
+    /// Set the tie breaker, which decides which option is tried first if multiple options have the
+    /// same cost. By default, [`TieBreaker::Arbitrary`] is used.
+    pub fn set_tie_breaker(&mut self, tie_breaker: TieBreaker) {
+        if let TieBreaker::Random(seed) = tie_breaker {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+        self.tie_breaker = tie_breaker;
+    }
+
     /// Takes in a vector over the options to pick (as index of the modifiers), and returns a single
     /// stack frame with all the possible options to take. The network will be modified, but the
     /// returned state will remain exactly the same!
@@ -208,6 +270,7 @@ where
         hard_policy: &mut HardPolicy,
     ) -> Vec<StepOption<P>> {
         let mut result = Vec::new();
+        let mut risk: Vec<usize> = Vec::new();
         for (i, opt) in state.choices.clone().into_iter().enumerate() {
             #[cfg(feature = "count-states")]
             {
@@ -215,6 +278,7 @@ where
             }
             // first, apply the modifier and get the new network
             let modifier = self.modifiers.get(opt).unwrap();
+            let num_msg_before = net.num_msg_exchanged();
             match net.apply_modifier(modifier) {
                 Ok(_) => {
                     // Network did converge! get the network state
@@ -237,20 +301,36 @@ where
                             choices: choices_left,
                             soft_policy,
                         });
+                        risk.push(net.num_msg_exchanged() - num_msg_before);
+                    } else {
+                        self.rejections[opt] += 1;
                     }
                     hard_policy.undo();
                 }
                 Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
-                    // Network did not converge! Option is not possible. Nothing to do here!
+                    // Network did not converge! Option is not possible.
+                    self.rejections[opt] += 1;
                 }
                 Err(e) => panic! {"Unrecoverable network error: {}", e},
             }
             // undo the last action
             net.undo_action().unwrap();
         }
+        // compute the tie breaking score (lower is better)
+        let ties: Vec<f64> = match self.tie_breaker {
+            TieBreaker::Arbitrary => vec![0.0; result.len()],
+            TieBreaker::Random(_) => result.iter().map(|_| self.rng.gen()).collect(),
+            TieBreaker::DependencyAware => {
+                result.iter().map(|o| -(self.rejections[o.mod_idx] as f64)).collect()
+            }
+            TieBreaker::RiskScore => risk.into_iter().map(|r| r as f64).collect(),
+        };
+        let mut result: Vec<(StepOption<P>, f64)> = result.into_iter().zip(ties).collect();
         // sort the frame sucht that lowest cost element is last (to be popped first)!
-        result.sort_by(|a, b| b.cost.partial_cmp(&a.cost).unwrap());
-        result
+        result.sort_by(|(a, a_tie), (b, b_tie)| {
+            b.cost.partial_cmp(&a.cost).unwrap().then(b_tie.partial_cmp(a_tie).unwrap())
+        });
+        result.into_iter().map(|(o, _)| o).collect()
     }
 }
 
@@ -287,10 +367,50 @@ mod tests {
 
         let expected_cost = (R::get_count() as f64) / ((R::get_count() + 2) as f64);
 
-        let (_, cost) = o.work(Stopper::new()).unwrap();
+        let (_, cost) = o.work(ControlHandle::new()).unwrap();
         assert_approx_eq!(expected_cost, cost);
     }
 
+    #[test]
+    fn test_chain_gadget_tie_breakers() {
+        type R = Repetition5;
+        type T = ChainGadget<R>;
+        let net = T::net(0);
+        let cf = T::final_config(&net, 0);
+        let patch = net.current_config().get_diff(&cf);
+        let hard_policy =
+            HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+        let soft_policy = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
+
+        let run = |tie_breaker: TieBreaker| {
+            let mut o = TreeOptimizer::new(
+                net.clone(),
+                patch.modifiers.clone(),
+                hard_policy.clone(),
+                soft_policy.clone(),
+                None,
+            )
+            .unwrap();
+            o.set_tie_breaker(tie_breaker);
+            o.work(ControlHandle::new()).unwrap()
+        };
+
+        let expected_cost = (R::get_count() as f64) / ((R::get_count() + 2) as f64);
+        for tie_breaker in vec![
+            TieBreaker::Arbitrary,
+            TieBreaker::Random(42),
+            TieBreaker::DependencyAware,
+            TieBreaker::RiskScore,
+        ] {
+            let (sequence, cost) = run(tie_breaker);
+            assert_eq!(sequence.len(), patch.modifiers.len());
+            assert_approx_eq!(expected_cost, cost);
+        }
+
+        // the random tie breaker is reproducible
+        assert_eq!(run(TieBreaker::Random(1)).0, run(TieBreaker::Random(1)).0);
+    }
+
     #[test]
     fn test_state_specific_chain_gadget() {
         type R = Repetition5;
@@ -307,7 +427,7 @@ mod tests {
 
         let expected_cost = 0.0;
 
-        let (_, cost) = o.work(Stopper::new()).unwrap();
+        let (_, cost) = o.work(ControlHandle::new()).unwrap();
         assert_approx_eq!(expected_cost, cost);
     }
 }
@@ -0,0 +1,457 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Migration Plan
+//!
+//! This module contains the [`MigrationPlan`], a synthesized sequence of [`ConfigModifier`]s,
+//! annotated with the effort the network needs to converge after each step (see
+//! [`ConvergenceEffort`]), and with an estimate of the wall-clock time each step takes.
+//!
+//! The time is estimated using a [`TimeEstimator`], a linear model on the convergence effort.
+//! The model can be calibrated with measurements of previous runs (e.g., from the runtime system),
+//! using [`TimeEstimator::calibrate`]. If the measured time of a step is known, it is used instead
+//! of the estimate (see [`MigrationPlan::estimate_time`]).
+//!
+//! Applying a modifier on the real devices takes time as well, which depends on the kind of the
+//! configuration change (e.g., a new BGP session takes longer than changing a link weight). This
+//! time is estimated using a [`ModifierCostModel`] (see [`MigrationPlan::estimate_application`]).
+//! Together with the convergence time, it gives the total duration of the maintenance (see
+//! [`MigrationPlan::total_duration`]).
+//!
+//! The steps can be annotated with the metadata of the configuration (see
+//! [`ConfigMeta`](crate::netsim::config::ConfigMeta)) using [`MigrationPlan::annotate`], such that
+//! each step can be traced back to the intent of the operator.
+//!
+//! Commands which must be applied together can be bundled into [`MigrationStep`]s using
+//! [`migration_steps`]. Learned dependency groups become atomic steps, while consecutive modifiers
+//! without any dependency between them are merged into a single parallel step, which shortens the
+//! maintenance window.
+
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::{Config, ConfigExpr, ConfigMeta, ConfigModifier};
+use crate::netsim::{printer, Network, NetworkError};
+use crate::rationale::check_ordering;
+use crate::Error;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// # Migration Plan
+/// Sequence of modifiers, together with the convergence effort and the estimated time of each
+/// step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationPlan {
+    /// All steps of the plan, in order
+    pub steps: Vec<PlanStep>,
+}
+
+/// # Step of a Migration Plan
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanStep {
+    /// Modifier applied in this step
+    pub modifier: ConfigModifier,
+    /// Effort of the network to converge after applying the modifier
+    pub effort: ConvergenceEffort,
+    /// Estimated time this step takes, until the network has converged. This is `None` until
+    /// [`MigrationPlan::estimate_time`] is called.
+    pub estimated_time: Option<Duration>,
+    /// Estimated time to apply the modifier on the devices. This is `None` until
+    /// [`MigrationPlan::estimate_application`] is called.
+    pub application_time: Option<Duration>,
+    /// Metadata of the modified configuration expression. This is `None` until
+    /// [`MigrationPlan::annotate`] is called, or if the expression has no metadata.
+    pub meta: Option<ConfigMeta>,
+}
+
+/// # Step of a Maintenance Window
+/// Set of modifiers which are applied together, computed by [`migration_steps`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationStep {
+    /// Modifiers of this step. If the step is not parallelizable, they must be applied in this
+    /// order.
+    pub modifiers: Vec<ConfigModifier>,
+    /// `true` if the step contains multiple modifiers which can be applied in any order (after all
+    /// previous steps), i.e., at the same time. `false` for a single modifier, or for a dependency group,
+    /// whose modifiers must be applied atomically in the given order.
+    pub parallelizable: bool,
+}
+
+/// # Convergence Effort
+/// Effort of the network to converge after a single modifier was applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConvergenceEffort {
+    /// Number of events (BGP messages and configuration changes) processed until convergence
+    pub num_messages: usize,
+    /// Number of internal routers which were either reconfigured or received a BGP message
+    pub routers_touched: usize,
+}
+
+impl MigrationPlan {
+    /// Build the migration plan by applying the sequence on a copy of the network, and measuring
+    /// the convergence effort of each step. The network must be in the initial state.
+    pub fn new(net: &Network, sequence: &[ConfigModifier]) -> Result<Self, NetworkError> {
+        let mut net = net.clone();
+        let steps = sequence
+            .iter()
+            .map(|modifier| {
+                net.clear_undo_stack();
+                net.apply_modifier(modifier)?;
+                Ok(PlanStep {
+                    modifier: modifier.clone(),
+                    effort: ConvergenceEffort {
+                        num_messages: net.num_msg_exchanged(),
+                        routers_touched: net.routers_touched().len(),
+                    },
+                    estimated_time: None,
+                    application_time: None,
+                    meta: None,
+                })
+            })
+            .collect::<Result<Vec<_>, NetworkError>>()?;
+        Ok(Self { steps })
+    }
+
+    /// Estimate the time of every step. If `measured` contains the time of a step (at the same
+    /// position), then this measurement is used. Otherwise, the time is estimated from the
+    /// convergence effort using the `estimator`.
+    pub fn estimate_time(&mut self, estimator: &TimeEstimator, measured: Option<&[Duration]>) {
+        for (i, step) in self.steps.iter_mut().enumerate() {
+            step.estimated_time = Some(
+                measured
+                    .and_then(|m| m.get(i))
+                    .cloned()
+                    .unwrap_or_else(|| estimator.estimate(&step.effort)),
+            );
+        }
+    }
+
+    /// Estimate the time to apply the modifier of every step on the devices, using the `model`.
+    pub fn estimate_application(&mut self, model: &ModifierCostModel) {
+        for step in self.steps.iter_mut() {
+            step.application_time = Some(model.cost(&step.modifier));
+        }
+    }
+
+    /// Attach the metadata of the configuration expressions to the steps. Both the initial and
+    /// the final configuration can be passed, where the metadata of the first one containing the
+    /// modified expression is used.
+    pub fn annotate(&mut self, configs: &[&Config]) {
+        for step in self.steps.iter_mut() {
+            let key = step.modifier.key();
+            step.meta = configs.iter().find_map(|c| c.get_meta(&key)).cloned();
+        }
+    }
+
+    /// Returns the estimated total time of the plan, or `None` if the time is not yet estimated
+    /// (see [`MigrationPlan::estimate_time`]).
+    pub fn total_time(&self) -> Option<Duration> {
+        self.steps.iter().map(|s| s.estimated_time).sum()
+    }
+
+    /// Returns the estimated duration of the entire maintenance, i.e., the time to apply all
+    /// modifiers plus the time until the network has converged after each step. Returns `None` if
+    /// either of them is not yet estimated (see [`MigrationPlan::estimate_time`] and
+    /// [`MigrationPlan::estimate_application`]).
+    pub fn total_duration(&self) -> Option<Duration> {
+        self.steps.iter().map(|s| Some(s.application_time? + s.estimated_time?)).sum()
+    }
+
+    /// Returns the sequence of modifiers
+    pub fn modifiers(&self) -> Vec<ConfigModifier> {
+        self.steps.iter().map(|s| s.modifier.clone()).collect()
+    }
+
+    /// Returns the formatted plan, with one line for each step.
+    pub fn repr(&self, net: &Network) -> Result<Vec<String>, NetworkError> {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                Ok(format!(
+                    "{:>3}: {} ({} messages, {} routers{}{}){}",
+                    i,
+                    printer::config_modifier(net, &s.modifier)?,
+                    s.effort.num_messages,
+                    s.effort.routers_touched,
+                    s.application_time
+                        .map(|t| format!(", apply ~{:.0}s", t.as_secs_f64()))
+                        .unwrap_or_default(),
+                    s.estimated_time
+                        .map(|t| format!(", ~{:.1}s", t.as_secs_f64()))
+                        .unwrap_or_default(),
+                    s.meta
+                        .as_ref()
+                        .filter(|m| !m.is_empty())
+                        .map(|m| format!("  # {}", m))
+                        .unwrap_or_default()
+                ))
+            })
+            .collect()
+    }
+}
+
+/// # Time Estimator
+/// Linear model, estimating the time a step takes based on its convergence effort:
+///
+/// $$t = t_\text{base} + n_\text{msg} \cdot t_\text{msg} + n_\text{routers} \cdot t_\text{router}$$
+///
+/// The default values are chosen for FRR routers running inside GNS3.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeEstimator {
+    /// Constant time of each step (e.g., time to apply the configuration)
+    pub base: Duration,
+    /// Time for processing a single message
+    pub per_message: Duration,
+    /// Additional time for every router touched (e.g., for updating the FIB)
+    pub per_router: Duration,
+}
+
+impl Default for TimeEstimator {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(1000),
+            per_message: Duration::from_millis(20),
+            per_router: Duration::from_millis(200),
+        }
+    }
+}
+
+impl TimeEstimator {
+    /// Estimate the time it takes for the network to converge
+    pub fn estimate(&self, effort: &ConvergenceEffort) -> Duration {
+        self.base
+            + self.per_message * effort.num_messages as u32
+            + self.per_router * effort.routers_touched as u32
+    }
+
+    /// Calibrate the model with measurements of previous runs, given as pairs of convergence
+    /// effort and measured time. All parameters are scaled by the same factor, such that the total
+    /// estimated time of all samples matches the total measured time. If there are no samples (or
+    /// the estimate is zero), the model is not changed.
+    pub fn calibrate(&mut self, samples: &[(ConvergenceEffort, Duration)]) {
+        let estimated: f64 = samples.iter().map(|(e, _)| self.estimate(e).as_secs_f64()).sum();
+        let measured: f64 = samples.iter().map(|(_, t)| t.as_secs_f64()).sum();
+        if estimated > 0.0 {
+            let factor = measured / estimated;
+            self.base = self.base.mul_f64(factor);
+            self.per_message = self.per_message.mul_f64(factor);
+            self.per_router = self.per_router.mul_f64(factor);
+        }
+    }
+}
+
+/// Kind of a configuration change, used by the [`ModifierCostModel`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ModifierKind {
+    /// Change of an IGP link weight
+    IgpLinkWeight,
+    /// Change of a BGP session, or of the cluster ID of a route reflector
+    BgpSession,
+    /// Change of graceful restart on a BGP session
+    BgpGracefulRestart,
+    /// Start or end of the graceful shutdown of a BGP session
+    BgpGracefulShutdown,
+    /// Change of the business relationship with an external neighbor
+    BgpRelationship,
+    /// Change of the BGP decision process (e.g., always comparing the MED)
+    BgpDecision,
+    /// Change of an OSPF area, or of a stub area
+    Ospf,
+    /// Change of a BGP route map
+    BgpRouteMap,
+    /// Change of a static route, or of its redistribution into BGP
+    StaticRoute,
+    /// Change of route origin validation, or of a ROA
+    Rov,
+}
+
+impl ModifierKind {
+    /// All kinds of configuration changes
+    pub const ALL: [ModifierKind; 10] = [
+        ModifierKind::IgpLinkWeight,
+        ModifierKind::BgpSession,
+        ModifierKind::BgpGracefulRestart,
+        ModifierKind::BgpGracefulShutdown,
+        ModifierKind::BgpRelationship,
+        ModifierKind::BgpDecision,
+        ModifierKind::Ospf,
+        ModifierKind::BgpRouteMap,
+        ModifierKind::StaticRoute,
+        ModifierKind::Rov,
+    ];
+
+    /// Returns the kind of the configuration change done by the modifier.
+    pub fn of(modifier: &ConfigModifier) -> Self {
+        let expr = match modifier {
+            ConfigModifier::Insert(e) | ConfigModifier::Remove(e) => e,
+            ConfigModifier::Update { to, .. } => to,
+        };
+        match expr {
+            ConfigExpr::IgpLinkWeight { .. } => Self::IgpLinkWeight,
+            ConfigExpr::BgpSession { .. } | ConfigExpr::BgpClusterId { .. } => Self::BgpSession,
+            ConfigExpr::BgpGracefulRestart { .. } => Self::BgpGracefulRestart,
+            ConfigExpr::BgpGracefulShutdown { .. } => Self::BgpGracefulShutdown,
+            ConfigExpr::BgpRelationship { .. } => Self::BgpRelationship,
+            ConfigExpr::BgpAlwaysCompareMed { .. } => Self::BgpDecision,
+            ConfigExpr::OspfArea { .. } | ConfigExpr::OspfStubArea { .. } => Self::Ospf,
+            ConfigExpr::BgpRouteMap { .. } => Self::BgpRouteMap,
+            ConfigExpr::StaticRoute { .. } | ConfigExpr::RedistributeStatic { .. } => {
+                Self::StaticRoute
+            }
+            ConfigExpr::RovPolicy { .. } | ConfigExpr::Roa { .. } => Self::Rov,
+        }
+    }
+}
+
+/// # Modifier Cost Model
+/// Time it takes to apply a single modifier on the devices (e.g., for the operator to enter and
+/// verify the change), depending on the [`ModifierKind`]. Kinds without an explicit cost take the
+/// `default` time. The model can be stored and loaded (e.g., as JSON), such that it can be
+/// adjusted to the devices and the processes of the operator.
+///
+/// The default values assume that changing a link weight or a static route is quick, while BGP
+/// sessions and route maps need additional verification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModifierCostModel {
+    /// Time of every kind without an explicit cost
+    pub default: Duration,
+    /// Time of each kind of configuration change
+    pub costs: HashMap<ModifierKind, Duration>,
+}
+
+impl Default for ModifierCostModel {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60))
+            .with_cost(ModifierKind::IgpLinkWeight, Duration::from_secs(30))
+            .with_cost(ModifierKind::StaticRoute, Duration::from_secs(30))
+            .with_cost(ModifierKind::BgpSession, Duration::from_secs(120))
+            .with_cost(ModifierKind::BgpRouteMap, Duration::from_secs(180))
+    }
+}
+
+impl ModifierCostModel {
+    /// Create a model, where every kind of configuration change takes the same time.
+    pub fn new(default: Duration) -> Self {
+        Self { default, costs: HashMap::new() }
+    }
+
+    /// Set the time of a kind of configuration change.
+    pub fn with_cost(mut self, kind: ModifierKind, cost: Duration) -> Self {
+        self.costs.insert(kind, cost);
+        self
+    }
+
+    /// Returns the time to apply the modifier.
+    pub fn cost(&self, modifier: &ConfigModifier) -> Duration {
+        self.costs.get(&ModifierKind::of(modifier)).cloned().unwrap_or(self.default)
+    }
+
+    /// Returns the time to apply all modifiers.
+    pub fn total(&self, modifiers: &[ConfigModifier]) -> Duration {
+        modifiers.iter().map(|m| self.cost(m)).sum()
+    }
+
+    /// Returns the largest time of any kind of configuration change.
+    pub fn max_cost(&self) -> Duration {
+        self.costs.values().cloned().fold(self.default, Duration::max)
+    }
+}
+
+/// Maximum number of modifiers in a parallel [`MigrationStep`]. [`migration_steps`] checks every
+/// ordering of a parallel step, which grows with the factorial of its size.
+pub const MAX_PARALLEL_MODIFIERS: usize = 5;
+
+/// Bundle the valid `sequence` into [`MigrationStep`]s. The network must be in the initial state.
+///
+/// Every dependency group in `groups` (with more than one modifier), which appears in the sequence
+/// as a consecutive block, becomes a single step which is not parallelizable. All other modifiers
+/// are merged greedily into parallel steps: A modifier is added to the current step if every
+/// ordering of the step together with the new modifier (applied after all previous steps) keeps
+/// the hard policy satisfied. Otherwise, or if the step already contains
+/// [`MAX_PARALLEL_MODIFIERS`] modifiers, a new step is started. Flattening the steps yields the
+/// original sequence.
+pub fn migration_steps(
+    net: &Network,
+    sequence: &[ConfigModifier],
+    groups: &[Vec<ConfigModifier>],
+    hard_policy: &HardPolicy,
+) -> Result<Vec<MigrationStep>, Error> {
+    let mut steps: Vec<MigrationStep> = Vec::new();
+    // positions in the sequence of all modifiers in the current parallel step
+    let mut current: Vec<usize> = Vec::new();
+    let mut i = 0;
+    while i < sequence.len() {
+        // check if a dependency group starts at this position
+        if let Some(group) = groups.iter().find(|g| g.len() > 1 && sequence[i..].starts_with(g)) {
+            finish_step(&mut steps, &mut current, sequence);
+            steps.push(MigrationStep { modifiers: group.clone(), parallelizable: false });
+            i += group.len();
+            continue;
+        }
+        let independent = match current.first() {
+            Some(start) if current.len() < MAX_PARALLEL_MODIFIERS => {
+                let step = current.iter().chain(std::iter::once(&i)).map(|k| &sequence[*k]);
+                all_orderings_valid(net, &sequence[..*start], step.collect(), hard_policy)?
+            }
+            Some(_) => false,
+            None => true,
+        };
+        if !independent {
+            finish_step(&mut steps, &mut current, sequence);
+        }
+        current.push(i);
+        i += 1;
+    }
+    finish_step(&mut steps, &mut current, sequence);
+    Ok(steps)
+}
+
+/// Returns `true` if every ordering of the `step`, applied after the `prefix`, keeps the hard policy
+/// satisfied.
+fn all_orderings_valid(
+    net: &Network,
+    prefix: &[ConfigModifier],
+    step: Vec<&ConfigModifier>,
+    hard_policy: &HardPolicy,
+) -> Result<bool, Error> {
+    let k = step.len();
+    for permutation in step.into_iter().permutations(k) {
+        let ordering = prefix.iter().chain(permutation).collect::<Vec<_>>();
+        if check_ordering(net, &ordering, hard_policy)?.is_some() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Push the modifiers at the positions `current` as a new step, and clear `current`.
+fn finish_step(
+    steps: &mut Vec<MigrationStep>,
+    current: &mut Vec<usize>,
+    sequence: &[ConfigModifier],
+) {
+    if current.is_empty() {
+        return;
+    }
+    steps.push(MigrationStep {
+        modifiers: current.iter().map(|i| sequence[*i].clone()).collect(),
+        parallelizable: current.len() > 1,
+    });
+    current.clear();
+}
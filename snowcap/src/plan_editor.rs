@@ -0,0 +1,212 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Interactive Plan Editing
+//!
+//! This module allows a previously verified sequence of [`ConfigModifier`] to be edited manually,
+//! e.g., by swapping two steps or inserting a new step. After every edit, only the affected suffix
+//! of the sequence is verified again. The [`PlanEditor`] keeps the network in the state after the
+//! last verified step, and uses the undo stack of the network and the hard policy to go back to
+//! the first step that is affected by the edit. Hence, editing a step near the end of a long plan
+//! is much cheaper than replaying the entire plan from scratch.
+
+use crate::hard_policies::{HardPolicy, PolicyError};
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{Network, NetworkError};
+use crate::Error;
+
+use log::*;
+
+/// # Edit of a plan
+/// Manual change of a sequence, applied with [`PlanEditor::edit`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanEdit {
+    /// Swap the two steps at the given positions.
+    Swap(usize, usize),
+    /// Insert a new step at the given position, shifting all later steps.
+    Insert(usize, ConfigModifier),
+    /// Remove the step at the given position.
+    Remove(usize),
+}
+
+impl PlanEdit {
+    /// Returns the position of the first step which is affected by the edit.
+    pub fn first_affected(&self) -> usize {
+        match self {
+            Self::Swap(a, b) => *a.min(b),
+            Self::Insert(pos, _) | Self::Remove(pos) => *pos,
+        }
+    }
+}
+
+/// # Violation of a plan
+/// Describes the first step of the sequence which violates the hard policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanViolation {
+    /// Position of the step which violates the hard policy (starting at 0).
+    pub step: usize,
+    /// Errors of the hard policy after applying the step. If the network does not converge, this
+    /// contains [`PolicyError::NoConvergence`].
+    pub errors: Vec<PolicyError>,
+}
+
+/// # Plan Editor
+///
+/// Holds a sequence of modifiers together with the network in the state after the last valid
+/// step. After every [`PlanEdit`], the editor goes back to the first affected step, and verifies
+/// only the remaining suffix.
+///
+/// ```
+/// use snowcap::example_networks::*;
+/// use snowcap::hard_policies::HardPolicy;
+/// use snowcap::plan_editor::{PlanEdit, PlanEditor};
+///
+/// let net = SimpleNet::net(0);
+/// let final_config = SimpleNet::final_config(&net, 0);
+/// let sequence = net.current_config().get_diff(&final_config).modifiers;
+/// let hard_policy = HardPolicy::globally(vec![]);
+///
+/// let mut editor = PlanEditor::new(&net, sequence, hard_policy).unwrap();
+/// assert!(editor.is_valid());
+/// let num_replayed = editor.edit(PlanEdit::Swap(0, 1)).unwrap();
+/// assert!(num_replayed <= editor.sequence().len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlanEditor {
+    net: Network,
+    hard_policy: HardPolicy,
+    sequence: Vec<ConfigModifier>,
+    num_applied: usize,
+    violation: Option<PlanViolation>,
+}
+
+impl PlanEditor {
+    /// Create a new plan editor and verify the entire sequence. The network must be in the
+    /// initial state. Returns `Err(InvalidInitialState)` if the initial state violates the hard
+    /// policy.
+    pub fn new(
+        net: &Network,
+        sequence: Vec<ConfigModifier>,
+        mut hard_policy: HardPolicy,
+    ) -> Result<Self, Error> {
+        let mut net = net.clone();
+        hard_policy.reset();
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state)?;
+        if !hard_policy.check_overwrite_finish(sequence.is_empty()) {
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+        }
+
+        let mut editor = Self { net, hard_policy, sequence, num_applied: 0, violation: None };
+        editor.verify_suffix()?;
+        Ok(editor)
+    }
+
+    /// Returns the current sequence.
+    pub fn sequence(&self) -> &[ConfigModifier] {
+        &self.sequence
+    }
+
+    /// Returns `true` if the current sequence satisfies the hard policy.
+    pub fn is_valid(&self) -> bool {
+        self.violation.is_none()
+    }
+
+    /// Returns the first violation of the current sequence, or `None` if it is valid.
+    pub fn violation(&self) -> Option<&PlanViolation> {
+        self.violation.as_ref()
+    }
+
+    /// Returns the network in the state after the last valid step of the current sequence.
+    pub fn network(&self) -> &Network {
+        &self.net
+    }
+
+    /// Apply the edit to the sequence, and verify the affected suffix. The function returns the
+    /// number of steps that needed to be replayed. Use [`PlanEditor::violation`] to check the
+    /// result.
+    ///
+    /// # Panics
+    /// Panics if a position of the edit is out of bounds.
+    pub fn edit(&mut self, edit: PlanEdit) -> Result<usize, Error> {
+        let new_len = match edit {
+            PlanEdit::Swap(_, _) => self.sequence.len(),
+            PlanEdit::Insert(_, _) => self.sequence.len() + 1,
+            PlanEdit::Remove(_) => self.sequence.len() - 1,
+        };
+        // The last step is checked on the complete trace, and all others only partially. Hence,
+        // if the last step changes, it needs to be verified again.
+        let first = edit.first_affected().min(new_len.saturating_sub(1));
+
+        // go back to the first affected step
+        while self.num_applied > first {
+            self.net.undo_action()?;
+            self.hard_policy.undo();
+            self.num_applied -= 1;
+        }
+        self.violation = None;
+
+        match edit {
+            PlanEdit::Swap(a, b) => self.sequence.swap(a, b),
+            PlanEdit::Insert(pos, modifier) => self.sequence.insert(pos, modifier),
+            PlanEdit::Remove(pos) => {
+                self.sequence.remove(pos);
+            }
+        }
+
+        let start = self.num_applied;
+        self.verify_suffix()?;
+        let num_replayed = self.num_applied - start + self.violation.is_some() as usize;
+        debug!("Re-verified {} of {} steps", num_replayed, self.sequence.len());
+        Ok(num_replayed)
+    }
+
+    /// Consume the editor, and return the current sequence.
+    pub fn into_sequence(self) -> Vec<ConfigModifier> {
+        self.sequence
+    }
+
+    /// Apply all remaining steps, starting at `num_applied`, until either the sequence is done, or
+    /// the hard policy is violated. The violating step is undone again.
+    fn verify_suffix(&mut self) -> Result<(), Error> {
+        let len = self.sequence.len();
+        while self.num_applied < len {
+            let step = self.num_applied;
+            match self.net.apply_modifier(&self.sequence[step]) {
+                Ok(()) => {}
+                Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                    self.net.undo_action()?;
+                    self.violation =
+                        Some(PlanViolation { step, errors: vec![PolicyError::NoConvergence] });
+                    return Ok(());
+                }
+                Err(e) => return Err(e.into()),
+            }
+            let mut fw_state = self.net.get_forwarding_state();
+            self.hard_policy.step(&mut self.net, &mut fw_state)?;
+            if !self.hard_policy.check_overwrite_finish(step + 1 == len) {
+                let mut errors = self.hard_policy.last_errors().into_iter().collect::<Vec<_>>();
+                errors.sort_by_key(|e| format!("{:?}", e));
+                self.hard_policy.undo();
+                self.net.undo_action()?;
+                self.violation = Some(PlanViolation { step, errors });
+                return Ok(());
+            }
+            self.num_applied += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Rationale of a Reconfiguration Plan
+//!
+//! This module computes human-readable "why here" annotations for a synthesized sequence of
+//! [`ConfigModifier`]. For every step, we determine which of the earlier steps it depends on, by
+//! swapping the two steps and checking whether the hard policy is violated. The annotations can be
+//! printed using [`printer::plan`](crate::netsim::printer::plan).
+//!
+//! For every pair of steps $i < j$, the sequence $m_1, \ldots, m_{i-1}, m_{i+1}, \ldots, m_j, m_i$
+//! is checked. This keeps the relative ordering of all other modifiers, and only moves $m_i$ after
+//! $m_j$. If this ordering violates the hard policy, then step $j$ must follow step $i$. The errors
+//! reported are the ones that show up while applying this alternative ordering.
+//!
+//! Computing the rationale requires $O(n^3)$ modifiers to be applied, where $n$ is the length of
+//! the sequence.
+
+use crate::hard_policies::{HardPolicy, PolicyError};
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{Network, NetworkError};
+use crate::Error;
+
+use log::*;
+
+/// # Rationale of a single step
+/// Collection of all earlier steps on which this step depends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifierRationale {
+    /// Position of the step in the sequence (starting at 0).
+    pub step: usize,
+    /// All earlier steps which must be applied before this step.
+    pub depends_on: Vec<StepDependency>,
+}
+
+impl ModifierRationale {
+    /// Returns `true` if this step does not depend on any earlier step.
+    pub fn is_independent(&self) -> bool {
+        self.depends_on.is_empty()
+    }
+}
+
+/// # Dependency on an earlier step
+/// Describes why a step must follow an earlier step in the sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepDependency {
+    /// Position of the earlier step in the sequence (starting at 0).
+    pub step: usize,
+    /// Reason why the two steps cannot be swapped.
+    pub reason: DependencyReason,
+}
+
+/// # Reason for a dependency
+/// Describes what goes wrong if two steps are applied in the opposite order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DependencyReason {
+    /// The hard policy is violated, with the given errors.
+    PolicyViolation(Vec<PolicyError>),
+    /// The network does not converge.
+    NoConvergence,
+}
+
+/// Compute the rationale for every step in the sequence. The network must be in the initial
+/// state, and the `sequence` must be valid with respect to the `hard_policy` (i.e., this function
+/// does not check the sequence itself). The returned vector contains one entry for every step in
+/// the sequence, in the same order.
+pub fn explain_sequence(
+    net: &Network,
+    sequence: &[ConfigModifier],
+    hard_policy: &HardPolicy,
+) -> Result<Vec<ModifierRationale>, Error> {
+    let mut result: Vec<ModifierRationale> = (0..sequence.len())
+        .map(|step| ModifierRationale { step, depends_on: Vec::new() })
+        .collect();
+
+    for later in 1..sequence.len() {
+        for earlier in 0..later {
+            // build the sequence, where `earlier` is moved right after `later`.
+            let ordering = sequence[..later + 1]
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != earlier)
+                .map(|(_, m)| m)
+                .chain(std::iter::once(&sequence[earlier]))
+                .collect::<Vec<_>>();
+            if let Some(reason) = check_ordering(net, &ordering, hard_policy)? {
+                debug!("Step {} depends on step {}", later, earlier);
+                result[later].depends_on.push(StepDependency { step: earlier, reason });
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Apply the ordering on a clone of the network, and check the hard policy after every step. If
+/// the ordering is valid, `Ok(None)` is returned. Else, the reason is returned.
+pub(crate) fn check_ordering(
+    net: &Network,
+    ordering: &[&ConfigModifier],
+    hard_policy: &HardPolicy,
+) -> Result<Option<DependencyReason>, Error> {
+    let mut net = net.clone();
+    let mut hard_policy = hard_policy.clone();
+    hard_policy.reset();
+
+    // check the initial state
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state)?;
+    if !hard_policy.check() {
+        return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+    }
+
+    for modifier in ordering {
+        match net.apply_modifier(modifier) {
+            Ok(()) => {}
+            Err(NetworkError::NoConvergence) | Err(NetworkError::ConvergenceLoop(_, _)) => {
+                return Ok(Some(DependencyReason::NoConvergence))
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state)?;
+        if !hard_policy.check() {
+            let mut errors = hard_policy.last_errors().into_iter().collect::<Vec<_>>();
+            errors.sort_by_key(|e| format!("{:?}", e));
+            return Ok(Some(DependencyReason::PolicyViolation(errors)));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,215 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Synthesis Session
+//!
+//! This module contains the [`SynthesisSession`], which is meant for interactive front-ends (like
+//! a service or a terminal UI), where an operator synthesizes the same reconfiguration several
+//! times, while tightening the hard policy between the attempts. The session keeps the network in
+//! the initial state, the set of modifiers, the dependency groups learned by [`StrategyTRTA`], and
+//! the last valid solution. After the hard policy is replaced or extended, the last solution is
+//! verified first. Only if it violates the new hard policy, a new search is started, which begins
+//! with the previously learned dependency groups.
+
+use crate::hard_policies::{Condition, HardPolicy};
+use crate::netsim::config::{Config, ConfigModifier};
+use crate::netsim::Network;
+use crate::plan_editor::PlanEditor;
+use crate::strategies::{GroupStrategy, Strategy, StrategyTRTA};
+use crate::{ControlHandle, Error};
+
+use log::*;
+use std::time::{Duration, SystemTime};
+
+/// # Synthesis Session
+///
+/// Repeated synthesis of the same reconfiguration, where the hard policy can be replaced (with
+/// [`SynthesisSession::set_hard_policy`]) or extended (with
+/// [`SynthesisSession::extend_hard_policy`]) between the attempts.
+///
+/// ```
+/// use snowcap::example_networks::*;
+/// use snowcap::hard_policies::{Condition, HardPolicy};
+/// use snowcap::session::SynthesisSession;
+/// use snowcap::ControlHandle;
+///
+/// let net = SimpleNet::net(0);
+/// let final_config = SimpleNet::final_config(&net, 0);
+/// let mut session = SynthesisSession::new(net, &final_config, HardPolicy::globally(vec![]));
+/// let sequence = session.synthesize(None, ControlHandle::new()).unwrap();
+/// assert_eq!(sequence.len(), session.modifiers().len());
+///
+/// // tighten the policy, and synthesize again
+/// let router = session.network().get_routers()[0];
+/// let prefix = session.network().get_known_prefixes().iter().next().cloned().unwrap();
+/// session.extend_hard_policy(vec![Condition::Reachable(router, prefix, None)]).unwrap();
+/// let sequence = session.synthesize(None, ControlHandle::new()).unwrap();
+/// assert_eq!(sequence.len(), session.modifiers().len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SynthesisSession {
+    net: Network,
+    modifiers: Vec<ConfigModifier>,
+    hard_policy: HardPolicy,
+    groups: Vec<Vec<ConfigModifier>>,
+    solution: Option<Vec<ConfigModifier>>,
+    num_attempts: usize,
+}
+
+impl SynthesisSession {
+    /// Create a new session, to reconfigure the network from its current configuration to the
+    /// `final_config`.
+    pub fn new(net: Network, final_config: &Config, hard_policy: HardPolicy) -> Self {
+        let modifiers = net.current_config().get_diff(final_config).modifiers;
+        Self::from_modifiers(net, modifiers, hard_policy)
+    }
+
+    /// Create a new session from the network in the initial state and the set of modifiers.
+    pub fn from_modifiers(
+        mut net: Network,
+        modifiers: Vec<ConfigModifier>,
+        hard_policy: HardPolicy,
+    ) -> Self {
+        net.clear_undo_stack();
+        Self { net, modifiers, hard_policy, groups: Vec::new(), solution: None, num_attempts: 0 }
+    }
+
+    /// Returns the network in the initial state.
+    pub fn network(&self) -> &Network {
+        &self.net
+    }
+
+    /// Returns the modifiers, which need to be ordered.
+    pub fn modifiers(&self) -> &[ConfigModifier] {
+        &self.modifiers
+    }
+
+    /// Returns the current hard policy.
+    pub fn hard_policy(&self) -> &HardPolicy {
+        &self.hard_policy
+    }
+
+    /// Returns the dependency groups learned in the previous attempts. The modifiers within a
+    /// group are stored in the order in which they must be applied.
+    pub fn learned_groups(&self) -> &[Vec<ConfigModifier>] {
+        &self.groups
+    }
+
+    /// Returns the solution of the last successful attempt.
+    pub fn solution(&self) -> Option<&[ConfigModifier]> {
+        self.solution.as_deref()
+    }
+
+    /// Returns the number of calls to [`SynthesisSession::synthesize`].
+    pub fn num_attempts(&self) -> usize {
+        self.num_attempts
+    }
+
+    /// Replace the hard policy. The learned dependency groups and the last solution are kept, and
+    /// verified against the new hard policy in the next attempt.
+    pub fn set_hard_policy(&mut self, mut hard_policy: HardPolicy) {
+        hard_policy.reset();
+        self.hard_policy = hard_policy;
+    }
+
+    /// Extend the hard policy with conditions, which must hold in every state. This fails if the
+    /// current hard policy contains anchors (see [`HardPolicy::extend_globally`]).
+    pub fn extend_hard_policy(&mut self, conditions: Vec<Condition>) -> Result<(), Error> {
+        self.hard_policy.extend_globally(conditions)
+    }
+
+    /// Forget the learned dependency groups and the last solution.
+    pub fn forget(&mut self) {
+        self.groups.clear();
+        self.solution = None;
+    }
+
+    /// Synthesize a sequence of the modifiers, which satisfies the current hard policy. If the
+    /// last solution still satisfies the hard policy, it is returned without searching. Otherwise,
+    /// [`StrategyTRTA`] is started with the learned dependency groups. If no solution can be found
+    /// with these groups (they might have been learned with a weaker hard policy), the search is
+    /// repeated without them, using the remaining time budget.
+    pub fn synthesize(
+        &mut self,
+        time_budget: Option<Duration>,
+        abort: ControlHandle,
+    ) -> Result<Vec<ConfigModifier>, Error> {
+        self.num_attempts += 1;
+        let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
+
+        // check if the last solution still works
+        if let Some(solution) = self.solution.as_ref() {
+            let editor = PlanEditor::new(&self.net, solution.clone(), self.hard_policy.clone())?;
+            if editor.is_valid() {
+                info!("The last solution still satisfies the hard policy!");
+                return Ok(solution.clone());
+            }
+            debug!(
+                "The last solution violates the hard policy at step {}",
+                editor.violation().map(|v| v.step).unwrap_or_default()
+            );
+            self.solution = None;
+        }
+
+        if !self.groups.is_empty() {
+            match self.run(time_budget, abort.clone()) {
+                Err(Error::NoSafeOrdering) | Err(Error::ProbablyNoSafeOrdering) => {
+                    info!("Could not find a solution with the learned groups! Retry without them");
+                    self.groups.clear();
+                }
+                result => return result,
+            }
+        }
+
+        let time_budget = stop_time.map(|time| {
+            time.duration_since(SystemTime::now()).unwrap_or_else(|_| Duration::new(0, 0))
+        });
+        self.run(time_budget, abort)
+    }
+
+    /// Run the strategy with the learned groups, and remember the new groups and the solution.
+    fn run(
+        &mut self,
+        time_budget: Option<Duration>,
+        abort: ControlHandle,
+    ) -> Result<Vec<ConfigModifier>, Error> {
+        let mut strategy = StrategyTRTA::from_groups(
+            self.net.clone(),
+            self.initial_groups(),
+            self.hard_policy.clone(),
+            time_budget,
+        )?;
+        let result = strategy.work(abort);
+        // keep the learned dependencies, even if no solution was found
+        self.groups = strategy.groups().iter().filter(|g| g.len() > 1).cloned().collect();
+        if let Ok(sequence) = result.as_ref() {
+            self.solution = Some(sequence.clone());
+        }
+        result
+    }
+
+    /// Returns the learned groups, followed by a single group for every other modifier.
+    fn initial_groups(&self) -> Vec<Vec<ConfigModifier>> {
+        let mut remaining = self.modifiers.clone();
+        for m in self.groups.iter().flatten() {
+            if let Some(pos) = remaining.iter().position(|x| x == m) {
+                remaining.remove(pos);
+            }
+        }
+        self.groups.iter().cloned().chain(remaining.into_iter().map(|m| vec![m])).collect()
+    }
+}
@@ -0,0 +1,163 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Soft Policy to respect change freeze windows declared by the operator
+
+use super::SoftPolicy;
+use crate::netsim::config::Config;
+use crate::netsim::{ForwardingState, Network, RouterId};
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Default cost of a step which touches a frozen router.
+pub const DEFAULT_FREEZE_PENALTY: f64 = 1.0;
+
+/// Period of the migration during which a set of routers must not be reconfigured. The period is
+/// given by the positions of the modifiers in the sequence, starting at 0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FreezeWindow {
+    /// Routers under change freeze
+    pub routers: HashSet<RouterId>,
+    /// Positions of the modifiers during which the routers are frozen
+    pub steps: Range<usize>,
+}
+
+impl FreezeWindow {
+    /// Freeze the routers during the given positions of the sequence.
+    pub fn new(routers: impl IntoIterator<Item = RouterId>, steps: Range<usize>) -> Self {
+        Self { routers: routers.into_iter().collect(), steps }
+    }
+
+    /// Freeze the routers for all positions before `step`. To require that the routers are only
+    /// changed by the last `k` out of `n` modifiers, use `FreezeWindow::before(routers, n - k)`.
+    pub fn before(routers: impl IntoIterator<Item = RouterId>, step: usize) -> Self {
+        Self::new(routers, 0..step)
+    }
+
+    /// Freeze the routers for all positions starting at `step`.
+    pub fn after(routers: impl IntoIterator<Item = RouterId>, step: usize) -> Self {
+        Self::new(routers, step..usize::MAX)
+    }
+
+    /// Returns true if the router is frozen at the given position of the sequence.
+    pub fn is_frozen(&self, router: RouterId, step: usize) -> bool {
+        self.steps.contains(&step) && self.routers.contains(&router)
+    }
+}
+
+/// # Soft Policy: Freeze Windows
+///
+/// This soft policy penalizes every step which reconfigures a router during one of its
+/// [`FreezeWindow`]s. Such a step costs the penalty (by default [`DEFAULT_FREEZE_PENALTY`], the
+/// maximum cost of a soft policy), all other steps are free. This way, operational constraints
+/// like change freezes can be integrated into the optimization without making the problem
+/// infeasible. To reject all sequences which violate a window, express the freeze as a hard policy
+/// instead.
+///
+/// The policy detects which routers are reconfigured by comparing the configuration of the network
+/// with the one of the previous step. Hence, [`SoftPolicy::update`] must be called exactly once
+/// for every modifier.
+#[derive(Clone, Debug)]
+pub struct FreezeWindows {
+    windows: Vec<FreezeWindow>,
+    penalty: f64,
+    last_config: Config,
+    step: usize,
+    violations: Vec<(usize, RouterId)>,
+    cost: f64,
+}
+
+impl FreezeWindows {
+    /// Add a freeze window.
+    pub fn with_window(mut self, window: FreezeWindow) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// Set the cost of a step which touches a frozen router. Like every soft policy cost, the
+    /// penalty must be within `0.0..=1.0`.
+    ///
+    /// # Panics
+    /// Panics if the penalty is outside of `0.0..=1.0` (including NaN).
+    pub fn with_penalty(mut self, penalty: f64) -> Self {
+        assert!((0.0..=1.0).contains(&penalty), "The penalty must be within 0.0..=1.0");
+        self.penalty = penalty;
+        self
+    }
+
+    /// Returns all declared freeze windows
+    pub fn windows(&self) -> &[FreezeWindow] {
+        &self.windows
+    }
+
+    /// Returns all violations observed so far, as the position of the step and the router which
+    /// was reconfigured during its freeze window.
+    pub fn violations(&self) -> &[(usize, RouterId)] {
+        &self.violations
+    }
+}
+
+impl SoftPolicy for FreezeWindows {
+    fn new(_state: &mut ForwardingState, net: &Network) -> Self {
+        Self {
+            windows: Vec::new(),
+            penalty: DEFAULT_FREEZE_PENALTY,
+            last_config: net.current_config().clone(),
+            step: 0,
+            violations: Vec::new(),
+            cost: 0.0,
+        }
+    }
+
+    fn reset(&mut self, state: &mut ForwardingState, net: &Network) {
+        let mut policy = Self::new(state, net);
+        policy.windows = std::mem::take(&mut self.windows);
+        policy.penalty = self.penalty;
+        *self = policy;
+    }
+
+    fn update(&mut self, _state: &mut ForwardingState, net: &Network) {
+        let config = net.current_config();
+        let mut touched: Vec<RouterId> = self
+            .last_config
+            .get_diff(config)
+            .modifiers
+            .iter()
+            .flat_map(|m| m.routers())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        touched.sort();
+
+        let step = self.step;
+        let violated: Vec<(usize, RouterId)> = touched
+            .into_iter()
+            .filter(|r| self.windows.iter().any(|w| w.is_frozen(*r, step)))
+            .map(|r| (step, r))
+            .collect();
+        self.cost = if violated.is_empty() { 0.0 } else { self.penalty };
+        self.violations.extend(violated);
+
+        self.last_config = config.clone();
+        self.step += 1;
+    }
+
+    fn cost(&self) -> f64 {
+        self.cost
+    }
+}
@@ -0,0 +1,213 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Soft Policy to minimize the time traffic spends on temporary paths
+
+use super::{PrefixWeights, SoftPolicy};
+use crate::netsim::config::Config;
+use crate::netsim::{ForwardingState, Network, NetworkError, Prefix, RouterId};
+
+/// Default number of steps, after which a churn window is penalized with the maximum cost.
+pub const DEFAULT_CHURN_HORIZON: usize = 10;
+
+/// # Soft Policy: Minimize Config Churn Window
+///
+/// This is a soft policy trying to minimize the time traffic spends on temporary paths during
+/// reconfiguration. For every router and prefix, the path is temporary if it is neither the initial
+/// nor the final path (forwarding loops and black holes are always temporary). The *churn window*
+/// is the number of consecutive steps during which the path is temporary. After every step, each
+/// router and prefix contributes `min(window, horizon) / horizon` times its weight (see
+/// [`PrefixWeights`]), and the cost is the fraction of the total weight. Hence, the summed cost of
+/// a migration grows quadratically with the length of a churn window, up to the horizon.
+///
+/// In contrast to [`MinimizeTrafficShift`](super::MinimizeTrafficShift), a single long detour is
+/// more expensive than several short ones.
+///
+/// The final paths are not known when the soft policy is created with [`SoftPolicy::new`]. In this
+/// case, only the initial path is considered stable. Use
+/// [`MinimizeConfigChurnWindow::with_final_state`] or
+/// [`MinimizeConfigChurnWindow::with_final_config`] to set them.
+#[derive(Clone, Debug)]
+pub struct MinimizeConfigChurnWindow {
+    routers: Vec<RouterId>,
+    prefix_lookup: Vec<(Prefix, usize)>,
+    num_prefixes: usize,
+    initial_paths: Vec<Option<Vec<RouterId>>>,
+    final_paths: Option<Vec<Option<Vec<RouterId>>>>,
+    windows: Vec<usize>,
+    prefix_weights: PrefixWeights,
+    weights: Vec<f64>,
+    total_weight: f64,
+    horizon: usize,
+    cost: f64,
+}
+
+impl MinimizeConfigChurnWindow {
+    /// Set the final forwarding state of the migration. Paths that are equal to the final path are
+    /// not temporary.
+    pub fn with_final_state(mut self, final_state: &mut ForwardingState) -> Self {
+        self.final_paths =
+            Some(get_paths(final_state, &self.routers, &self.prefix_lookup, self.num_prefixes));
+        self
+    }
+
+    /// Compute the final forwarding state by applying the final configuration on a copy of the
+    /// network. Paths that are equal to the final path are not temporary.
+    pub fn with_final_config(
+        self,
+        net: &Network,
+        final_config: &Config,
+    ) -> Result<Self, NetworkError> {
+        let mut final_net = net.clone();
+        final_net.set_config(final_config)?;
+        Ok(self.with_final_state(&mut final_net.get_forwarding_state()))
+    }
+
+    /// Set the weight of every router and prefix.
+    pub fn with_weights(mut self, prefix_weights: PrefixWeights) -> Self {
+        self.prefix_weights = prefix_weights;
+        self.compute_weights();
+        self
+    }
+
+    /// Set the number of steps, after which a churn window is penalized with the maximum cost. The
+    /// horizon must be at least 1.
+    pub fn with_horizon(mut self, horizon: usize) -> Self {
+        assert!(horizon > 0);
+        self.horizon = horizon;
+        self
+    }
+
+    /// Returns the weights used by this soft policy
+    pub fn prefix_weights(&self) -> &PrefixWeights {
+        &self.prefix_weights
+    }
+
+    /// Returns the current churn window of the router and prefix, i.e., the number of consecutive
+    /// steps during which the path was temporary.
+    pub fn window(&self, router: RouterId, prefix: Prefix) -> usize {
+        self.prefix_lookup
+            .iter()
+            .find(|(p, _)| *p == prefix)
+            .and_then(|(_, pid)| {
+                let rid = self.routers.iter().position(|r| *r == router)?;
+                Some(self.windows[get_idx(rid, *pid, self.num_prefixes)])
+            })
+            .unwrap_or(0)
+    }
+
+    /// Recompute the weights and the total weight from the prefix weights.
+    fn compute_weights(&mut self) {
+        let mut weights = vec![0.0; self.routers.len() * self.num_prefixes];
+        for (rid, r) in self.routers.iter().enumerate() {
+            for (p, pid) in self.prefix_lookup.iter() {
+                weights[get_idx(rid, *pid, self.num_prefixes)] = self.prefix_weights.get(*r, *p);
+            }
+        }
+        self.total_weight = weights.iter().sum();
+        self.weights = weights;
+    }
+}
+
+impl SoftPolicy for MinimizeConfigChurnWindow {
+    fn new(state: &mut ForwardingState, net: &Network) -> Self {
+        let prefix_lookup: Vec<(Prefix, usize)> =
+            net.get_known_prefixes().iter().cloned().enumerate().map(|(i, p)| (p, i)).collect();
+        let num_prefixes = prefix_lookup.len();
+        let routers = net.get_routers();
+
+        assert!(num_prefixes > 0);
+        assert!(!routers.is_empty());
+
+        let initial_paths = get_paths(state, &routers, &prefix_lookup, num_prefixes);
+        let prefix_weights = match net.get_traffic_matrix() {
+            Some(tm) => PrefixWeights::from_traffic_matrix(tm),
+            None => PrefixWeights::default(),
+        };
+        let mut policy = Self {
+            windows: vec![0; initial_paths.len()],
+            routers,
+            prefix_lookup,
+            num_prefixes,
+            initial_paths,
+            final_paths: None,
+            prefix_weights,
+            weights: Vec::new(),
+            total_weight: 0.0,
+            horizon: DEFAULT_CHURN_HORIZON,
+            cost: 0.0,
+        };
+        policy.compute_weights();
+        policy
+    }
+
+    fn reset(&mut self, state: &mut ForwardingState, net: &Network) {
+        let mut policy = Self::new(state, net).with_weights(self.prefix_weights.clone());
+        policy.horizon = self.horizon;
+        // the final paths are only kept if the network still has the same routers and prefixes
+        if policy.routers == self.routers && policy.prefix_lookup == self.prefix_lookup {
+            policy.final_paths = self.final_paths.take();
+        }
+        *self = policy;
+    }
+
+    fn update(&mut self, state: &mut ForwardingState, _net: &Network) {
+        let paths = get_paths(state, &self.routers, &self.prefix_lookup, self.num_prefixes);
+        let mut cost: f64 = 0.0;
+        for (idx, path) in paths.into_iter().enumerate() {
+            let temporary = path.is_none()
+                || (path != self.initial_paths[idx]
+                    && self.final_paths.as_ref().map(|f| path != f[idx]).unwrap_or(true));
+            if temporary {
+                self.windows[idx] += 1;
+                let window = self.windows[idx].min(self.horizon);
+                cost += self.weights[idx] * window as f64 / self.horizon as f64;
+            } else {
+                self.windows[idx] = 0;
+            }
+        }
+        self.cost = cost;
+    }
+
+    fn cost(&self) -> f64 {
+        if self.total_weight > 0.0 {
+            self.cost / self.total_weight
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Get the paths of all routers towards all prefixes. Forwarding loops and black holes are `None`.
+fn get_paths(
+    state: &mut ForwardingState,
+    routers: &[RouterId],
+    prefix_lookup: &[(Prefix, usize)],
+    num_prefixes: usize,
+) -> Vec<Option<Vec<RouterId>>> {
+    let mut paths = vec![None; routers.len() * num_prefixes];
+    for (rid, r) in routers.iter().enumerate() {
+        for (p, pid) in prefix_lookup.iter() {
+            paths[get_idx(rid, *pid, num_prefixes)] = state.get_route(*r, *p).ok();
+        }
+    }
+    paths
+}
+
+fn get_idx(rid: usize, pid: usize, n_prefixes: usize) -> usize {
+    rid * n_prefixes + pid
+}
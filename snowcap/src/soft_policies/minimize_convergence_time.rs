@@ -0,0 +1,86 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Soft Policy to minimize the time the network needs to converge after every modifier
+
+use super::SoftPolicy;
+use crate::netsim::{ForwardingState, Network};
+
+/// Default reference time of [`MinimizeTotalConvergenceTime`], at which the cost of a step is 0.5.
+pub const DEFAULT_CONVERGENCE_REFERENCE: f64 = 10.0;
+
+/// # Soft Policy: Minimize Total Convergence Time
+///
+/// This soft policy uses the convergence time estimated by the network after every modifier (see
+/// [`Network::get_convergence_time`]), which depends on the link delays and the MRAI configured on
+/// the network. The cost of a step with convergence time `t` is `t / (t + reference)`, such that
+/// it is between 0 and 1, and 0.5 if the network converges in the reference time. Orderings that
+/// cause long chains of messages (e.g., path exploration) have a larger cost. The estimated total
+/// time is reported by [`MinimizeTotalConvergenceTime::total_time`].
+///
+/// The convergence time is taken from the last action of the network. Hence,
+/// [`SoftPolicy::update`] must be called right after applying each modifier.
+#[derive(Clone, Debug)]
+pub struct MinimizeTotalConvergenceTime {
+    reference: f64,
+    total_time: f64,
+    cost: f64,
+}
+
+impl MinimizeTotalConvergenceTime {
+    /// Use the given reference time instead of [`DEFAULT_CONVERGENCE_REFERENCE`].
+    ///
+    /// # Panics
+    /// Panics if `reference` is not positive.
+    pub fn with_reference(mut self, reference: f64) -> Self {
+        assert!(reference > 0.0, "The reference time must be positive!");
+        self.reference = reference;
+        self
+    }
+
+    /// Returns the reference time.
+    pub fn reference(&self) -> f64 {
+        self.reference
+    }
+
+    /// Returns the estimated convergence time, summed over all modifiers observed so far.
+    pub fn total_time(&self) -> f64 {
+        self.total_time
+    }
+}
+
+impl SoftPolicy for MinimizeTotalConvergenceTime {
+    fn new(_state: &mut ForwardingState, _net: &Network) -> Self {
+        Self { reference: DEFAULT_CONVERGENCE_REFERENCE, total_time: 0.0, cost: 0.0 }
+    }
+
+    fn reset(&mut self, state: &mut ForwardingState, net: &Network) {
+        let reference = self.reference;
+        *self = Self::new(state, net);
+        self.reference = reference;
+    }
+
+    fn update(&mut self, _state: &mut ForwardingState, net: &Network) {
+        let time = net.get_convergence_time();
+        self.total_time += time;
+        self.cost = time / (time + self.reference);
+    }
+
+    fn cost(&self) -> f64 {
+        self.cost
+    }
+}
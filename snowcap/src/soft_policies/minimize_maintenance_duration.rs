@@ -0,0 +1,94 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Soft Policy to minimize the time needed to apply the modifiers on the devices
+
+use super::SoftPolicy;
+use crate::netsim::config::Config;
+use crate::netsim::{ForwardingState, Network};
+use crate::plan::ModifierCostModel;
+
+use std::time::Duration;
+
+/// # Soft Policy: Minimize Maintenance Duration
+///
+/// This soft policy estimates the time to apply every modifier on the devices, using a
+/// [`ModifierCostModel`]. The cost of a step is the time to apply its modifier, relative to the
+/// most expensive kind of configuration change in the model, such that it is between 0 and 1.
+/// For a fixed set of modifiers, the total cost is the same for every ordering. Hence, the policy
+/// is meant for comparing sequences with a different set of modifiers (e.g., with temporary
+/// changes), and for reporting the estimated total duration (see
+/// [`MinimizeMaintenanceDuration::duration`]).
+///
+/// The policy detects the applied modifier by comparing the configuration of the network with the
+/// one of the previous step. Hence, [`SoftPolicy::update`] must be called exactly once for every
+/// modifier.
+#[derive(Clone, Debug)]
+pub struct MinimizeMaintenanceDuration {
+    model: ModifierCostModel,
+    last_config: Config,
+    duration: Duration,
+    cost: f64,
+}
+
+impl MinimizeMaintenanceDuration {
+    /// Use the given cost model instead of the default one.
+    pub fn with_model(mut self, model: ModifierCostModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Returns the cost model
+    pub fn model(&self) -> &ModifierCostModel {
+        &self.model
+    }
+
+    /// Returns the estimated time to apply all modifiers observed so far.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+impl SoftPolicy for MinimizeMaintenanceDuration {
+    fn new(_state: &mut ForwardingState, net: &Network) -> Self {
+        Self {
+            model: ModifierCostModel::default(),
+            last_config: net.current_config().clone(),
+            duration: Duration::default(),
+            cost: 0.0,
+        }
+    }
+
+    fn reset(&mut self, state: &mut ForwardingState, net: &Network) {
+        let mut policy = Self::new(state, net);
+        policy.model = std::mem::take(&mut self.model);
+        *self = policy;
+    }
+
+    fn update(&mut self, _state: &mut ForwardingState, net: &Network) {
+        let config = net.current_config();
+        let time: Duration = self.model.total(&self.last_config.get_diff(config).modifiers);
+        let max = self.model.max_cost().as_secs_f64();
+        self.cost = if max > 0.0 { (time.as_secs_f64() / max).min(1.0) } else { 0.0 };
+        self.duration += time;
+        self.last_config = config.clone();
+    }
+
+    fn cost(&self) -> f64 {
+        self.cost
+    }
+}
@@ -0,0 +1,152 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! Soft Policy to minimize the maximum link utilization
+
+use super::SoftPolicy;
+use crate::netsim::{ForwardingState, Network, RouterId};
+use crate::traffic_matrix::TrafficMatrix;
+
+use std::collections::HashMap;
+
+/// # Soft Policy: Minimize Maximum Link Utilization
+///
+/// This soft policy routes the demand of a [`TrafficMatrix`] in the forwarding state after every
+/// step (see [`TrafficMatrix::link_loads`]), and computes the utilization of every link, i.e., its
+/// load divided by its capacity. The cost is the maximum utilization of all links. If any link is
+/// overloaded (utilization above 1), the cost is 1, the highest possible cost.
+///
+/// When created with [`SoftPolicy::new`], the demand is taken from the traffic matrix attached to
+/// the network. If there is no traffic matrix, every internal router has a demand of 1 towards
+/// every known prefix. Without any capacities set, every link has the total demand as capacity,
+/// such that the utilization is the fraction of all traffic crossing the link. Use
+/// [`MinimizeMaxLinkUtilization::with_default_capacity`] and
+/// [`MinimizeMaxLinkUtilization::with_link_capacity`] to set the capacities explicitly.
+#[derive(Clone, Debug)]
+pub struct MinimizeMaxLinkUtilization {
+    demand: TrafficMatrix,
+    capacities: HashMap<(RouterId, RouterId), f64>,
+    default_capacity: Option<f64>,
+    max_utilization: f64,
+    busiest_link: Option<(RouterId, RouterId)>,
+}
+
+impl MinimizeMaxLinkUtilization {
+    /// Use the given capacity for all links, for which no specific capacity is set.
+    pub fn with_default_capacity(mut self, capacity: f64) -> Self {
+        self.default_capacity = Some(capacity);
+        self
+    }
+
+    /// Set the capacity of the link between `a` and `b`, in both directions.
+    pub fn with_link_capacity(mut self, a: RouterId, b: RouterId, capacity: f64) -> Self {
+        self.capacities.insert((a, b), capacity);
+        self.capacities.insert((b, a), capacity);
+        self
+    }
+
+    /// Returns the demand routed through the network.
+    pub fn demand(&self) -> &TrafficMatrix {
+        &self.demand
+    }
+
+    /// Returns the capacity of the directed link from `source` to `target`.
+    pub fn capacity(&self, source: RouterId, target: RouterId) -> f64 {
+        self.capacities
+            .get(&(source, target))
+            .copied()
+            .or(self.default_capacity)
+            .unwrap_or_else(|| self.demand.total_demand())
+    }
+
+    /// Returns the maximum link utilization in the last state.
+    pub fn max_utilization(&self) -> f64 {
+        self.max_utilization
+    }
+
+    /// Returns the directed link with the highest utilization in the last state, or `None` if no
+    /// link carries any traffic.
+    pub fn busiest_link(&self) -> Option<(RouterId, RouterId)> {
+        self.busiest_link
+    }
+
+    /// Returns `true` if any link was overloaded in the last state.
+    pub fn is_overloaded(&self) -> bool {
+        self.max_utilization > 1.0
+    }
+
+    /// Compute the utilization of all links in the given state.
+    fn compute_utilization(&mut self, state: &mut ForwardingState) {
+        self.max_utilization = 0.0;
+        self.busiest_link = None;
+        for ((source, target), load) in self.demand.link_loads(state) {
+            let capacity = self.capacity(source, target);
+            let utilization = if capacity > 0.0 {
+                load / capacity
+            } else if load > 0.0 {
+                f64::INFINITY
+            } else {
+                0.0
+            };
+            if utilization > self.max_utilization {
+                self.max_utilization = utilization;
+                self.busiest_link = Some((source, target));
+            }
+        }
+    }
+}
+
+impl SoftPolicy for MinimizeMaxLinkUtilization {
+    fn new(state: &mut ForwardingState, net: &Network) -> Self {
+        let demand = match net.get_traffic_matrix() {
+            Some(tm) => tm.clone(),
+            None => {
+                let mut tm = TrafficMatrix::new();
+                for r in net.get_routers() {
+                    for p in net.get_known_prefixes() {
+                        tm.add_demand(r, *p, 1.0);
+                    }
+                }
+                tm
+            }
+        };
+        let mut policy = Self {
+            demand,
+            capacities: HashMap::new(),
+            default_capacity: None,
+            max_utilization: 0.0,
+            busiest_link: None,
+        };
+        policy.compute_utilization(state);
+        policy
+    }
+
+    fn reset(&mut self, state: &mut ForwardingState, net: &Network) {
+        let mut policy = Self::new(state, net);
+        policy.capacities = std::mem::take(&mut self.capacities);
+        policy.default_capacity = self.default_capacity;
+        policy.compute_utilization(state);
+        *self = policy;
+    }
+
+    fn update(&mut self, state: &mut ForwardingState, _net: &Network) {
+        self.compute_utilization(state);
+    }
+
+    fn cost(&self) -> f64 {
+        self.max_utilization.min(1.0)
+    }
+}
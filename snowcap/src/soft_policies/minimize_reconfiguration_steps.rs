@@ -0,0 +1,129 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! Soft Policy to reach the final forwarding state as early as possible
+
+use super::SoftPolicy;
+use crate::netsim::config::{Config, ConfigModifier};
+use crate::netsim::{ForwardingState, Network, NetworkError, Prefix, RouterId};
+
+/// # Soft Policy: Minimize Reconfiguration Steps
+///
+/// Often, a subset of the modifiers already brings the network into a state which is equivalent to
+/// the final one, and the remaining modifiers only clean up (e.g., by removing sessions which are
+/// no longer used). This soft policy prefers sequences which reach the final forwarding state
+/// early. The cost of a step is the fraction of routers and prefixes, whose next hop differs from
+/// the one in the final state. Once the network forwards all traffic in the same way as in the
+/// final state, all further steps are free. Hence, the total cost is lower, the earlier the
+/// sequence reaches the final forwarding state.
+///
+/// The final state is set with [`MinimizeReconfigurationSteps::with_final_config`]. When created
+/// with [`SoftPolicy::new`], the final state is unknown, and every step is free. Use
+/// [`cleanup_only`] to find the trailing modifiers of a sequence which do not change the
+/// forwarding state anymore.
+#[derive(Clone, Debug)]
+pub struct MinimizeReconfigurationSteps {
+    target: Vec<((RouterId, Prefix), Option<RouterId>)>,
+    num_different: usize,
+}
+
+impl MinimizeReconfigurationSteps {
+    /// Set the final state of the reconfiguration, by applying the final configuration on a clone
+    /// of the network, which must be in the initial state.
+    pub fn with_final_config(
+        mut self,
+        net: &Network,
+        final_config: &Config,
+    ) -> Result<Self, NetworkError> {
+        let mut final_net = net.clone();
+        final_net.set_config(final_config)?;
+        self.target = next_hops(&final_net, &final_net.get_forwarding_state())?;
+        self.num_different = self.target.len();
+        Ok(self)
+    }
+
+    /// Returns `true` if the last state forwards all traffic in the same way as the final state.
+    /// This is always `false` if the final state is unknown.
+    pub fn is_complete(&self) -> bool {
+        !self.target.is_empty() && self.num_different == 0
+    }
+}
+
+impl SoftPolicy for MinimizeReconfigurationSteps {
+    fn new(_state: &mut ForwardingState, _net: &Network) -> Self {
+        Self { target: Vec::new(), num_different: 0 }
+    }
+
+    fn reset(&mut self, _state: &mut ForwardingState, _net: &Network) {
+        self.num_different = self.target.len();
+    }
+
+    fn update(&mut self, state: &mut ForwardingState, _net: &Network) {
+        self.num_different = self
+            .target
+            .iter()
+            .filter(|((r, p), nh)| state.get_next_hop(*r, *p).ok() != Some(*nh))
+            .count();
+    }
+
+    fn cost(&self) -> f64 {
+        if self.target.is_empty() {
+            0.0
+        } else {
+            self.num_different as f64 / self.target.len() as f64
+        }
+    }
+}
+
+/// Returns the position of the first modifier of the sequence which is *cleanup only*, i.e., after
+/// which the forwarding state no longer changes until the end of the sequence. All modifiers from
+/// this position onwards can be deferred without changing how the traffic is forwarded. If the
+/// last modifier still changes the forwarding state, `sequence.len()` is returned.
+pub fn cleanup_only(net: &Network, sequence: &[ConfigModifier]) -> Result<usize, NetworkError> {
+    let mut net = net.clone();
+    let mut states = vec![net.get_forwarding_state()];
+    for modifier in sequence.iter() {
+        net.apply_modifier(modifier)?;
+        states.push(net.get_forwarding_state());
+    }
+
+    let mut policy = MinimizeReconfigurationSteps::new(&mut states[0], &net);
+    policy.target = next_hops(&net, &states[sequence.len()])?;
+
+    let mut first = sequence.len();
+    for (i, state) in states.iter_mut().enumerate().rev() {
+        policy.update(state, &net);
+        if !policy.is_complete() {
+            break;
+        }
+        first = i;
+    }
+    Ok(first)
+}
+
+/// Returns the next hop of every internal router for every known prefix.
+fn next_hops(
+    net: &Network,
+    state: &ForwardingState,
+) -> Result<Vec<((RouterId, Prefix), Option<RouterId>)>, NetworkError> {
+    let mut result = Vec::new();
+    for r in net.get_routers() {
+        for p in net.get_known_prefixes() {
+            result.push(((r, *p), state.get_next_hop(r, *p)?));
+        }
+    }
+    Ok(result)
+}
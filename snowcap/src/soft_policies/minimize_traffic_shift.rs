@@ -19,23 +19,41 @@
 
 use super::SoftPolicy;
 use crate::netsim::{ForwardingState, Network, Prefix, RouterId};
+use crate::traffic_matrix::TrafficMatrix;
+
+use std::collections::HashMap;
 
 /// # Soft Policy: Minimize Traffic Shift
 ///
 /// This is a soft policy trying to minimize the number of traffic shifts during reconfiguration.
 /// Traffic shifts are counted in the following way: For every router and every prefix, if the next
-/// hop changes from the previous state to the current state, then increase the count by 1.
+/// hop changes from the previous state to the current state, then increase the count by the weight
+/// of the router and prefix (see [`PrefixWeights`]). The cost is the fraction of the total weight
+/// that is shifted.
+///
+/// When created with [`SoftPolicy::new`], the weights are derived from the
+/// [`TrafficMatrix`](crate::traffic_matrix::TrafficMatrix) attached to the network. If there is no
+/// traffic matrix, every router and prefix has weight 1. Use
+/// [`MinimizeTrafficShift::with_weights`] to set the weights explicitly.
 #[derive(Clone, Debug)]
 pub struct MinimizeTrafficShift {
     current_next_hops: Vec<Option<RouterId>>,
+    prefix_weights: PrefixWeights,
+    weights: Vec<f64>,
+    total_weight: f64,
     prefix_lookup: Vec<(Prefix, usize)>,
     num_prefixes: usize,
-    num_routers: usize,
-    num_different: usize,
+    num_different: f64,
 }
 
-impl SoftPolicy for MinimizeTrafficShift {
-    fn new(state: &mut ForwardingState, net: &Network) -> Self {
+impl MinimizeTrafficShift {
+    /// Create the soft policy, where every shift of the router and prefix is weighted using the
+    /// given weights.
+    pub fn with_weights(
+        state: &mut ForwardingState,
+        net: &Network,
+        prefix_weights: PrefixWeights,
+    ) -> Self {
         let prefix_lookup: Vec<(Prefix, usize)> =
             net.get_known_prefixes().iter().cloned().enumerate().map(|(i, p)| (p, i)).collect();
         let num_prefixes = prefix_lookup.len();
@@ -46,18 +64,49 @@ impl SoftPolicy for MinimizeTrafficShift {
 
         let mut current_next_hops: Vec<Option<RouterId>> =
             std::iter::repeat(None).take(net.num_devices() * num_prefixes).collect();
+        let mut weights: Vec<f64> =
+            std::iter::repeat(0.0).take(net.num_devices() * num_prefixes).collect();
         for r in net.get_routers() {
             for (p, pid) in prefix_lookup.iter() {
                 let idx = get_idx(r.index(), *pid, num_prefixes);
                 current_next_hops[idx] = state.get_next_hop(r, *p).unwrap();
+                weights[idx] = prefix_weights.get(r, *p);
             }
         }
+        let total_weight = weights.iter().sum();
 
-        Self { current_next_hops, prefix_lookup, num_prefixes, num_routers, num_different: 0 }
+        Self {
+            current_next_hops,
+            prefix_weights,
+            weights,
+            total_weight,
+            prefix_lookup,
+            num_prefixes,
+            num_different: 0.0,
+        }
+    }
+
+    /// Returns the weights used by this soft policy
+    pub fn prefix_weights(&self) -> &PrefixWeights {
+        &self.prefix_weights
+    }
+}
+
+impl SoftPolicy for MinimizeTrafficShift {
+    fn new(state: &mut ForwardingState, net: &Network) -> Self {
+        let prefix_weights = match net.get_traffic_matrix() {
+            Some(tm) => PrefixWeights::from_traffic_matrix(tm),
+            None => PrefixWeights::default(),
+        };
+        Self::with_weights(state, net, prefix_weights)
+    }
+
+    fn reset(&mut self, state: &mut ForwardingState, net: &Network) {
+        *self = Self::with_weights(state, net, self.prefix_weights.clone());
     }
 
     fn update(&mut self, state: &mut ForwardingState, net: &Network) {
-        let mut count: usize = 0;
+        let mut count: f64 = 0.0;
         for router in net.get_routers() {
             for (p, pid) in self.prefix_lookup.iter() {
                 let idx = get_idx(router.index(), *pid, self.num_prefixes);
@@ -65,7 +114,7 @@ impl SoftPolicy for MinimizeTrafficShift {
                 let old_next_hop = self.current_next_hops[idx];
                 if new_next_hop.is_some() && old_next_hop.is_some() && new_next_hop != old_next_hop
                 {
-                    count += 1;
+                    count += self.weights[idx];
                 }
                 self.current_next_hops[idx] = new_next_hop;
             }
@@ -74,11 +123,71 @@ impl SoftPolicy for MinimizeTrafficShift {
     }
 
     fn cost(&self) -> f64 {
-        let total_next_hops = self.num_routers * self.num_prefixes;
-        (self.num_different as f64) / (total_next_hops as f64)
+        if self.total_weight > 0.0 {
+            self.num_different / self.total_weight
+        } else {
+            0.0
+        }
     }
 }
 
 fn get_idx(rid: usize, pid: usize, n_prefixes: usize) -> usize {
     rid * n_prefixes + pid
 }
+
+/// # Prefix Weights
+/// Weight of every router and prefix, used by [`MinimizeTrafficShift`] to decide how costly a
+/// traffic shift is. The weight of a router and prefix is looked up in the following order:
+///
+/// 1. The weight set for this specific router and prefix, using [`PrefixWeights::set`].
+/// 2. The weight set for the prefix on all routers, using [`PrefixWeights::set_prefix`].
+/// 3. The default weight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrefixWeights {
+    weights: HashMap<(RouterId, Prefix), f64>,
+    prefix_weights: HashMap<Prefix, f64>,
+    default: f64,
+}
+
+impl Default for PrefixWeights {
+    /// All routers and prefixes have weight 1.
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl PrefixWeights {
+    /// Create the weights, where all routers and prefixes have the default weight.
+    pub fn new(default: f64) -> Self {
+        Self { weights: HashMap::new(), prefix_weights: HashMap::new(), default }
+    }
+
+    /// Use the demand of the traffic matrix as weights. All routers and prefixes without demand
+    /// have weight 0.
+    pub fn from_traffic_matrix(tm: &TrafficMatrix) -> Self {
+        let mut weights = Self::new(0.0);
+        for ((router, prefix), volume) in tm.iter() {
+            weights.set(*router, *prefix, *volume);
+        }
+        weights
+    }
+
+    /// Set the weight of a specific router and prefix.
+    pub fn set(&mut self, router: RouterId, prefix: Prefix, weight: f64) {
+        self.weights.insert((router, prefix), weight);
+    }
+
+    /// Set the weight of a prefix on all routers, for which no specific weight is set.
+    pub fn set_prefix(&mut self, prefix: Prefix, weight: f64) {
+        self.prefix_weights.insert(prefix, weight);
+    }
+
+    /// Returns the weight of the router and prefix.
+    pub fn get(&self, router: RouterId, prefix: Prefix) -> f64 {
+        self.weights
+            .get(&(router, prefix))
+            .or_else(|| self.prefix_weights.get(&prefix))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
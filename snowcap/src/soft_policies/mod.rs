@@ -23,8 +23,20 @@
 use crate::netsim::config::ConfigModifier;
 use crate::netsim::{ForwardingState, Network, NetworkError};
 
+mod freeze_windows;
+mod minimize_churn_window;
+mod minimize_convergence_time;
+mod minimize_maintenance_duration;
+mod minimize_max_link_utilization;
+mod minimize_reconfiguration_steps;
 mod minimize_traffic_shift;
-pub use minimize_traffic_shift::MinimizeTrafficShift;
+pub use freeze_windows::{FreezeWindow, FreezeWindows, DEFAULT_FREEZE_PENALTY};
+pub use minimize_churn_window::{MinimizeConfigChurnWindow, DEFAULT_CHURN_HORIZON};
+pub use minimize_convergence_time::{MinimizeTotalConvergenceTime, DEFAULT_CONVERGENCE_REFERENCE};
+pub use minimize_maintenance_duration::MinimizeMaintenanceDuration;
+pub use minimize_max_link_utilization::MinimizeMaxLinkUtilization;
+pub use minimize_reconfiguration_steps::{cleanup_only, MinimizeReconfigurationSteps};
+pub use minimize_traffic_shift::{MinimizeTrafficShift, PrefixWeights};
 
 /// Trait for creating soft policies.
 pub trait SoftPolicy {
@@ -35,6 +47,15 @@ pub trait SoftPolicy {
     /// is applied.
     fn update(&mut self, state: &mut ForwardingState, net: &Network);
 
+    /// Reinitialize the soft policy on a new state, as if it was newly created. Implementations
+    /// which are parameterized (and not only derived from the network) must keep their parameters.
+    fn reset(&mut self, state: &mut ForwardingState, net: &Network)
+    where
+        Self: Sized,
+    {
+        *self = Self::new(state, net);
+    }
+
     /// Compute the score based on the information gathered by several calls to update. The output
     /// of this funciton is between 0 and 1, and lower is better.
     fn cost(&self) -> f64;
@@ -0,0 +1,158 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Checkpoints
+//!
+//! Strategies exploring the search space as a tree can be interrupted and resumed later (see
+//! [`CheckpointStrategy`]). The [`StrategyState`] describes the position in the tree, and can be
+//! serialized, such that the synthesis survives a restart of the process.
+
+use super::Strategy;
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{Network, NetworkError};
+use crate::Error;
+
+use serde::{Deserialize, Serialize};
+
+/// # Checkpoint Strategy
+///
+/// Strategy, whose exploration can be interrupted and resumed. When [`Strategy::work`] returns
+/// because it was aborted, or because the time budget was used up, the strategy remembers its
+/// position in the search space. Calling [`Strategy::work`] again continues at that position.
+/// The position can be saved with [`CheckpointStrategy::checkpoint`], and be restored (possibly on
+/// a new strategy, created for the same problem) with [`CheckpointStrategy::resume`].
+pub trait CheckpointStrategy: Strategy {
+    /// Returns the current state of the exploration. If the strategy did not run yet (or found a
+    /// result), the state points to the beginning of the exploration.
+    fn checkpoint(&self) -> StrategyState;
+
+    /// Continue the exploration at the given state in the next call to [`Strategy::work`]. The
+    /// state must have been created by the same kind of strategy, for the same set of modifiers.
+    /// The network and the hard policy are only checked once the exploration continues.
+    fn resume(&mut self, state: StrategyState) -> Result<(), Error>;
+}
+
+/// State of a strategy exploring the search space as a tree, used to resume the exploration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyState {
+    /// Name of the strategy which created the state
+    pub strategy: String,
+    /// All groups of modifiers known to the strategy. Groups with more than one modifier are
+    /// always applied together, in the given order. All indices refer to this vector.
+    pub groups: Vec<Vec<ConfigModifier>>,
+    /// Stack of the exploration tree, starting at the root.
+    pub frames: Vec<FrameState>,
+    /// Groups applied at the current position of the tree, one for every frame except the last.
+    pub sequence: Vec<usize>,
+    /// Longest valid sequence of modifiers found so far
+    pub best_sequence: Vec<ConfigModifier>,
+}
+
+/// State of a single level of the exploration tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameState {
+    /// Groups remaining at this level, in the order in which they are tried.
+    pub remaining: Vec<usize>,
+    /// Number of groups already tried at this level.
+    pub next: usize,
+}
+
+impl StrategyState {
+    /// Create the state at the root of the exploration tree, where all groups are remaining.
+    pub(crate) fn root(strategy: &str, groups: Vec<Vec<ConfigModifier>>) -> Self {
+        let remaining = (0..groups.len()).collect();
+        Self {
+            strategy: strategy.to_string(),
+            groups,
+            frames: vec![FrameState { remaining, next: 0 }],
+            sequence: Vec::new(),
+            best_sequence: Vec::new(),
+        }
+    }
+
+    /// Check that the state was created by the given strategy, for the same modifiers (in any
+    /// order), and that all indices are valid.
+    pub(crate) fn validate(
+        &self,
+        strategy: &str,
+        modifiers: &[ConfigModifier],
+    ) -> Result<(), Error> {
+        if self.strategy != strategy {
+            return Err(Error::InvalidCheckpoint(format!(
+                "created by {}, but resumed with {}",
+                self.strategy, strategy
+            )));
+        }
+        let mut unused: Vec<&ConfigModifier> = modifiers.iter().collect();
+        for m in self.groups.iter().flatten() {
+            match unused.iter().position(|x| *x == m) {
+                Some(pos) => {
+                    unused.swap_remove(pos);
+                }
+                None => {
+                    return Err(Error::InvalidCheckpoint(
+                        "contains modifiers which are not part of the problem".to_string(),
+                    ))
+                }
+            }
+        }
+        if !unused.is_empty() {
+            return Err(Error::InvalidCheckpoint(format!("misses {} modifiers", unused.len())));
+        }
+        // an empty stack is only valid if the entire search space was explored
+        let num_groups = self.groups.len();
+        let exhausted = self.frames.is_empty() && self.sequence.is_empty();
+        if (!exhausted && self.sequence.len() + 1 != self.frames.len())
+            || self.sequence.iter().any(|g| *g >= num_groups)
+            || self.frames.iter().flat_map(|f| f.remaining.iter()).any(|g| *g >= num_groups)
+        {
+            return Err(Error::InvalidCheckpoint("the exploration stack is invalid".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns all modifiers of the current sequence, in order.
+    pub(crate) fn sequence_modifiers(&self) -> Vec<ConfigModifier> {
+        self.sequence.iter().flat_map(|g| self.groups[*g].iter()).cloned().collect()
+    }
+}
+
+/// Apply the sequence of modifiers on the network and the hard policy, in order to get to the
+/// state of the exploration. Fails if a modifier cannot be applied, or if the hard policy is
+/// violated.
+pub(crate) fn replay<'a>(
+    net: &mut Network,
+    hard_policy: &mut HardPolicy,
+    sequence: impl IntoIterator<Item = &'a ConfigModifier>,
+) -> Result<(), Error> {
+    for m in sequence {
+        match net.apply_modifier(m) {
+            Ok(()) => {}
+            Err(NetworkError::Aborted) => return Err(Error::Abort),
+            Err(_) => {
+                return Err(Error::InvalidCheckpoint("cannot apply the sequence".to_string()))
+            }
+        }
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(net, &mut fw_state)?;
+        if !hard_policy.check() {
+            return Err(Error::InvalidCheckpoint("the sequence violates the policy".to_string()));
+        }
+    }
+    Ok(())
+}
@@ -23,7 +23,7 @@ use super::Strategy;
 use crate::hard_policies::{ConstraintsChecker, PolicyError};
 use crate::netsim::config::ConfigModifier;
 use crate::netsim::{printer, Network, NetworkError};
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error};
 use log::*;
 use rand::prelude::*;
 use std::time::{Duration, SystemTime};
@@ -139,7 +139,7 @@ impl Strategy for DepPairsBuilder {
             if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
                 // time budget is used up!
                 error!("Time budget is used up! No solution was found yet!");
-                return Err(Error::Timeout);
+                return Err(Error::timeout(&self.modifiers));
             }
             // generate a possible ordering
             let ordering = match self.generate_possible_ordering() {
@@ -0,0 +1,266 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # IGP Link Weight Decomposition
+//!
+//! Changing the weight of a single link may cause transient forwarding loops, while the routers
+//! update their IGP forwarding tables one after the other. Following [Disruption Free Topology
+//! Reconfiguration in OSPF Networks](https://ieeexplore.ieee.org/document/4215601), such a change
+//! can always be split into a sequence of intermediate weights, such that no forwarding loop can
+//! occur between two consecutive weights, regardless of the order in which the routers update
+//! their tables.
+//!
+//! Two IGP states are considered loop-free, if, for every destination, the union of the next hops
+//! of both states contains no cycle. The candidate weights are the ones at which a shortest path
+//! changes (and the values in between). Among those, we pick the shortest sequence of loop-free
+//! steps using a breadth-first search.
+
+use crate::netsim::config::{ConfigExpr, ConfigModifier};
+use crate::netsim::{LinkWeight, Network, NetworkError, RouterId};
+
+use log::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// IGP next hops of every internal router (first key) towards every destination (second key).
+type NextHops = HashMap<RouterId, HashMap<RouterId, Vec<RouterId>>>;
+
+/// Split an update of a single IGP link weight into a sequence of updates, such that no transient
+/// forwarding loop can occur while the network converges after each of them. The result is the
+/// shortest such sequence over the weights at which any shortest path changes. The first modifier
+/// starts at the current weight, and the last one ends at the desired weight.
+///
+/// If `modifier` is not an update of an [`ConfigExpr::IgpLinkWeight`], or if no loop-free
+/// sequence is found, then the modifier is returned unchanged.
+///
+/// ```
+/// use snowcap::netsim::config::{Config, ConfigExpr::IgpLinkWeight, ConfigModifier};
+/// use snowcap::netsim::Network;
+/// use snowcap::strategies::decompose_igp_weight_change;
+///
+/// let mut net = Network::new();
+/// let r1 = net.add_router("r1");
+/// let r2 = net.add_router("r2");
+/// net.add_link(r1, r2);
+/// let mut c = Config::new();
+/// c.add(IgpLinkWeight { source: r1, target: r2, weight: 1.0 }).unwrap();
+/// c.add(IgpLinkWeight { source: r2, target: r1, weight: 1.0 }).unwrap();
+/// net.set_config(&c).unwrap();
+///
+/// let modifier = ConfigModifier::Update {
+///     from: IgpLinkWeight { source: r1, target: r2, weight: 1.0 },
+///     to: IgpLinkWeight { source: r1, target: r2, weight: 5.0 },
+/// };
+/// // nothing can loop in a network with two routers
+/// assert_eq!(decompose_igp_weight_change(&net, &modifier).unwrap(), vec![modifier]);
+/// ```
+pub fn decompose_igp_weight_change(
+    net: &Network,
+    modifier: &ConfigModifier,
+) -> Result<Vec<ConfigModifier>, NetworkError> {
+    let (source, target, old, new) = match modifier {
+        ConfigModifier::Update {
+            from: ConfigExpr::IgpLinkWeight { source, target, weight: old },
+            to: ConfigExpr::IgpLinkWeight { weight: new, .. },
+        } if old != new => (*source, *target, *old, *new),
+        _ => return Ok(vec![modifier.clone()]),
+    };
+    let update = |from: LinkWeight, to: LinkWeight| ConfigModifier::Update {
+        from: ConfigExpr::IgpLinkWeight { source, target, weight: from },
+        to: ConfigExpr::IgpLinkWeight { source, target, weight: to },
+    };
+
+    let weights = candidate_weights(net, source, target, old, new)?;
+    let mut states: Vec<NextHops> = Vec::with_capacity(weights.len());
+    for weight in weights.iter() {
+        let mut net = net.clone();
+        if *weight != old {
+            net.apply_modifier(&update(old, *weight))?;
+        }
+        states.push(igp_next_hops(&net));
+    }
+    let destinations: Vec<RouterId> =
+        net.get_routers().into_iter().chain(net.get_external_routers()).collect();
+
+    // breadth-first search for the shortest loop-free sequence of weights.
+    let n = weights.len();
+    let mut predecessor: Vec<Option<usize>> = vec![None; n];
+    let mut queue: VecDeque<usize> = VecDeque::from(vec![0]);
+    while let Some(i) = queue.pop_front() {
+        if i == n - 1 {
+            break;
+        }
+        for (j, state) in states.iter().enumerate().skip(i + 1) {
+            if predecessor[j].is_none() && is_loop_free(&states[i], state, &destinations) {
+                predecessor[j] = Some(i);
+                queue.push_back(j);
+            }
+        }
+    }
+
+    if predecessor[n - 1].is_none() {
+        warn!("Could not find a loop-free sequence of IGP weights from {} to {}!", old, new);
+        return Ok(vec![modifier.clone()]);
+    }
+    let mut sequence = vec![n - 1];
+    while let Some(i) = predecessor[*sequence.last().unwrap()] {
+        sequence.push(i);
+    }
+    sequence.reverse();
+    Ok(sequence.windows(2).map(|w| update(weights[w[0]], weights[w[1]])).collect())
+}
+
+/// Replace every IGP link weight update in `modifiers` by the sequence computed with
+/// [`decompose_igp_weight_change`]. Each sequence is returned as a single group, while all other
+/// modifiers are in a group of their own. The result can be passed to
+/// [`GroupStrategy::from_groups`](crate::strategies::GroupStrategy::from_groups).
+pub fn decompose_igp_weight_changes(
+    net: &Network,
+    modifiers: Vec<ConfigModifier>,
+) -> Result<Vec<Vec<ConfigModifier>>, NetworkError> {
+    modifiers.iter().map(|m| decompose_igp_weight_change(net, m)).collect()
+}
+
+/// Compute the candidate weights of the link `source -> target`, ordered from `old` to `new`
+/// (including both). These are all weights at which the shortest path of any internal router
+/// towards any destination changes, and one weight in between each two of those.
+fn candidate_weights(
+    net: &Network,
+    source: RouterId,
+    target: RouterId,
+    old: LinkWeight,
+    new: LinkWeight,
+) -> Result<Vec<LinkWeight>, NetworkError> {
+    // compute the distances without the link, which are the same for all weights.
+    let mut net = net.clone();
+    if old.is_finite() {
+        net.apply_modifier(&ConfigModifier::Update {
+            from: ConfigExpr::IgpLinkWeight { source, target, weight: old },
+            to: ConfigExpr::IgpLinkWeight { source, target, weight: LinkWeight::INFINITY },
+        })?;
+    }
+    let distance = |x: RouterId, d: RouterId| -> Option<LinkWeight> {
+        if x == d {
+            return Some(0.0);
+        }
+        match net.get_device(x).internal() {
+            Some(r) => r.get_igp_fw_table().get(&d).cloned().flatten().map(|(_, w)| w),
+            None => None,
+        }
+    };
+
+    let (lo, hi) = if old < new { (old, new) } else { (new, old) };
+    let destinations: Vec<RouterId> =
+        net.get_routers().into_iter().chain(net.get_external_routers()).collect();
+    let mut breakpoints: Vec<LinkWeight> = Vec::new();
+    for x in net.get_routers() {
+        let to_source = match distance(x, source) {
+            Some(w) => w,
+            None => continue,
+        };
+        for d in destinations.iter().filter(|d| **d != x) {
+            // the link is used as soon as its weight is below the breakpoint
+            let breakpoint = match (distance(x, *d), distance(target, *d)) {
+                (Some(direct), Some(from_target)) => direct - to_source - from_target,
+                _ => continue,
+            };
+            if lo < breakpoint && breakpoint < hi {
+                breakpoints.push(breakpoint);
+            }
+        }
+    }
+    breakpoints.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    breakpoints.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+    if old > new {
+        breakpoints.reverse();
+    }
+
+    let mut weights = vec![old];
+    for next in breakpoints.into_iter().chain(std::iter::once(new)) {
+        let prev = *weights.last().unwrap();
+        weights.push(match (prev.is_finite(), next.is_finite()) {
+            (true, true) => (prev + next) / 2.0,
+            (true, false) => prev + 1.0,
+            _ => next + 1.0,
+        });
+        weights.push(next);
+    }
+    Ok(weights)
+}
+
+/// Extract the IGP next hops of all internal routers. If ECMP is disabled, every router only uses
+/// its single next hop.
+fn igp_next_hops(net: &Network) -> NextHops {
+    net.get_routers()
+        .into_iter()
+        .map(|r| {
+            let router = net.get_device(r).unwrap_internal();
+            let table = if net.get_ecmp() {
+                router.igp_ecmp_table.iter().map(|(d, nhs)| (*d, nhs.clone())).collect()
+            } else {
+                router
+                    .get_igp_fw_table()
+                    .iter()
+                    .filter_map(|(d, e)| e.map(|(nh, _)| (*d, vec![nh])))
+                    .collect()
+            };
+            (r, table)
+        })
+        .collect()
+}
+
+/// Check that, for every destination, the union of the next hops of `a` and `b` has no cycle.
+fn is_loop_free(a: &NextHops, b: &NextHops, destinations: &[RouterId]) -> bool {
+    destinations.iter().all(|d| {
+        let next_hops = |r: RouterId| -> Vec<RouterId> {
+            [a, b]
+                .iter()
+                .filter_map(|s| s.get(&r).and_then(|t| t.get(d)))
+                .flatten()
+                .filter(|nh| *nh != d && **nh != r)
+                .cloned()
+                .collect()
+        };
+        // depth-first search, remembering the routers on the current path.
+        let mut done: HashSet<RouterId> = HashSet::new();
+        for start in a.keys() {
+            if done.contains(start) {
+                continue;
+            }
+            let mut on_path: HashSet<RouterId> = HashSet::new();
+            let mut stack: Vec<(RouterId, Vec<RouterId>)> = vec![(*start, next_hops(*start))];
+            on_path.insert(*start);
+            while let Some((r, children)) = stack.last_mut() {
+                let r = *r;
+                match children.pop() {
+                    Some(c) if on_path.contains(&c) => return false,
+                    Some(c) if done.contains(&c) || !a.contains_key(&c) => {}
+                    Some(c) => {
+                        on_path.insert(c);
+                        let c_next_hops = next_hops(c);
+                        stack.push((c, c_next_hops));
+                    }
+                    None => {
+                        on_path.remove(&r);
+                        done.insert(r);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        true
+    })
+}
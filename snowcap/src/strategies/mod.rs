@@ -69,6 +69,8 @@
 //!   backtrace, we try to find a single dependency. If it succeeds, we repeat the tree traversal
 //!   using the new dependency. Using this approach, we only search for the dependencies, which are
 //!   not solvable by using the naive Tree strategy.
+//!   [`StrategyTRTA::work_parallel`] explores disjoint parts of the search space with multiple
+//!   threads.
 //!
 //!   *Type Arguments*: None, this algorithm is as good as it gets (using this approach)
 //!
@@ -123,6 +125,28 @@
 //!
 //! - **[`NaiveRandomIBRStrategy`]**: This strategy is similar to the random strategy, but it always
 //!   schedules insert before modify before remove commands.
+//!
+//! - **`SatStrategy`**: This strategy encodes the ordering as a SAT problem. It proposes an
+//!   ordering satisfying all clauses, simulates it, and adds a clause blocking the failure. If the
+//!   policy only depends on the current state, the clause blocks every ordering applying the same
+//!   set of modifiers first, which helps for dependencies with *sparse* *solutions* and *no*
+//!   *immediate* *effect*. This strategy is an [`ExhaustiveStrategy`].
+//!
+//!   *This strategy is only available if the `"sat-strategy"` feature is enabled!*
+//!
+//! ## Precedence Constraints
+//!
+//! The [`PermutationStrategy`], the [`TreeStrategy`] and the [`PushBackTreeStrategy`] implement
+//! [`PrecedenceStrategy`], and can be restricted to orderings which satisfy the
+//! [`PrecedenceConstraints`] given by the operator (e.g., "all route-map changes on router X before
+//! any session changes").
+//!
+//! ## IGP Link Weight Decomposition
+//!
+//! [`decompose_igp_weight_change`] splits a single update of an IGP link weight into a sequence of
+//! intermediate weights, such that no transient forwarding loop can occur. Every
+//! [`GroupStrategy`] can treat this sequence as a single group, using
+//! [`GroupStrategy::synthesize_decomposed`].
 
 mod permutation;
 pub use permutation::PermutationStrategy;
@@ -139,6 +163,25 @@ pub use naive_random::NaiveRandomStrategy;
 mod naive_random_ibr;
 pub use naive_random_ibr::NaiveRandomIBRStrategy;
 
+#[cfg(feature = "sat-strategy")]
+mod sat;
+#[cfg(feature = "sat-strategy")]
+pub use sat::SatStrategy;
+
+mod checkpoint;
+pub(crate) use checkpoint::replay;
+pub use checkpoint::{CheckpointStrategy, FrameState, StrategyState};
+
+mod precedence;
+pub use precedence::{ModifierSelector, Precedence, PrecedenceConstraints, PrecedenceStrategy};
+
+mod igp_decomposition;
+pub use igp_decomposition::{decompose_igp_weight_change, decompose_igp_weight_changes};
+
+mod state_cache;
+pub(crate) use state_cache::CheckResults;
+pub use state_cache::{StateCache, DEFAULT_STATE_CACHE_CAPACITY};
+
 // dep_pairs_builder is very bad! Therefore, we do not re-export the name!
 //mod dep_pairs_builder;
 //pub use dep_pairs_builder::DepPairsBuilder;
@@ -150,12 +193,76 @@ pub use crate::dep_groups::strategy_trta::StrategyTRTA;
 use crate::hard_policies::HardPolicy;
 use crate::netsim::config::{Config, ConfigModifier};
 use crate::netsim::Network;
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error};
 
 use std::time::Duration;
 
 use log::*;
 
+/// Description of an algorithm (either a strategy or an optimizer), used to enumerate the
+/// available algorithms, for instance in the command-line interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlgorithmInfo {
+    /// Name of the type implementing the algorithm
+    pub name: &'static str,
+    /// Short description of the algorithm
+    pub description: &'static str,
+    /// Type arguments of the algorithm, as pairs of the name and a description.
+    pub parameters: &'static [(&'static str, &'static str)],
+    /// Wether the algorithm is exhaustive, i.e., it is guaranteed to find a solution if one exists.
+    pub exhaustive: bool,
+}
+
+/// List of all strategies, in the order they are described in the [module
+/// documentation](crate::strategies).
+pub const STRATEGIES: &[AlgorithmInfo] = &[
+    AlgorithmInfo {
+        name: "StrategyTRTA",
+        description: "Tree traversal, searching for dependencies as soon as it needs to backtrack",
+        parameters: &[],
+        exhaustive: false,
+    },
+    AlgorithmInfo {
+        name: "PermutationStrategy",
+        description: "Checks every permutation one after the other",
+        parameters: &[("P", "Permutator used to generate the permutations")],
+        exhaustive: true,
+    },
+    AlgorithmInfo {
+        name: "TreeStrategy",
+        description: "Tree traversal, skipping all orderings with a known bad prefix",
+        parameters: &[("O", "Modifier ordering applied before the traversal starts")],
+        exhaustive: true,
+    },
+    AlgorithmInfo {
+        name: "PushBackTreeStrategy",
+        description: "Tree traversal, pushing modifiers which cause problems to the back",
+        parameters: &[("O", "Modifier ordering applied before the traversal starts")],
+        exhaustive: true,
+    },
+    AlgorithmInfo {
+        name: "DepGroupsStrategy",
+        description: "Builds groups of dependencies, which are solvable on their own",
+        parameters: &[
+            ("S", "Group strategy, used to solve the smaller problems"),
+            ("P", "Permutator used to generate the permutations of the groups"),
+        ],
+        exhaustive: false,
+    },
+    AlgorithmInfo {
+        name: "NaiveRandomStrategy",
+        description: "Tries random orderings (only for evaluation)",
+        parameters: &[],
+        exhaustive: false,
+    },
+    AlgorithmInfo {
+        name: "NaiveRandomIBRStrategy",
+        description: "Tries random orderings, scheduling insert before modify before remove",
+        parameters: &[],
+        exhaustive: false,
+    },
+];
+
 /// Infterface for all strategies
 pub trait Strategy {
     /// Wrapper, that creates the strategy and synthesizes the network update order.
@@ -164,7 +271,7 @@ pub trait Strategy {
         end_config: Config,
         hard_policy: HardPolicy,
         time_budget: Option<Duration>,
-        abort: Stopper,
+        abort: ControlHandle,
     ) -> Result<Vec<ConfigModifier>, Error> {
         let start_config = net.current_config().clone();
         let patch = start_config.get_diff(&end_config);
@@ -174,9 +281,9 @@ pub trait Strategy {
                 info!("Initial configuration is valid!");
                 s
             }
-            Err(Error::InvalidInitialState) => {
-                error!("Invalid initial state");
-                return Err(Error::InvalidInitialState);
+            Err(e @ Error::InvalidInitialState(_)) => {
+                error!("{}", e);
+                return Err(e);
             }
             Err(e) => {
                 error!("Unexpected error while setting up the strategy: {}", e);
@@ -196,7 +303,7 @@ pub trait Strategy {
 
     /// Main function to find a valid reconfiguration sequence (if it exists) and return it.
     /// The function also returns the number of sequences that were tested.
-    fn work(&mut self, abort: Stopper) -> Result<Vec<ConfigModifier>, Error>;
+    fn work(&mut self, abort: ControlHandle) -> Result<Vec<ConfigModifier>, Error>;
     /// Returns the number of states explored by the strategy.
     ///
     /// *This method is only available if the `"count-states"` feature is enabled!*
@@ -213,6 +320,28 @@ pub trait GroupStrategy: Strategy {
         hard_policy: HardPolicy,
         time_budget: Option<Duration>,
     ) -> Result<Box<Self>, Error>;
+
+    /// Wrapper, that splits every update of an IGP link weight into a loop-free sequence of
+    /// intermediate weights (see [`decompose_igp_weight_change`]), creates the strategy with each
+    /// such sequence as a single group, and synthesizes the network update order.
+    fn synthesize_decomposed(
+        net: Network,
+        end_config: Config,
+        hard_policy: HardPolicy,
+        time_budget: Option<Duration>,
+        abort: ControlHandle,
+    ) -> Result<Vec<ConfigModifier>, Error> {
+        let modifiers = net.current_config().get_diff(&end_config).modifiers;
+        let groups = decompose_igp_weight_changes(&net, modifiers)?;
+        let mut strategy = match Self::from_groups(net, groups, hard_policy, time_budget) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Unexpected error while setting up the strategy: {}", e);
+                return Err(e);
+            }
+        };
+        strategy.work(abort)
+    }
 }
 
 /// Marking to tell that this strategy is exhaustive.
@@ -18,7 +18,7 @@
 use super::Strategy;
 use crate::hard_policies::HardPolicy;
 use crate::netsim::{config::ConfigModifier, Network, NetworkError};
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error, PartialResult};
 
 use log::*;
 use rand::prelude::*;
@@ -58,7 +58,7 @@ impl Strategy for NaiveRandomIBRStrategy {
                     .collect::<Vec<_>>()
                     .join("\n    "),
             );
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
         Ok(Box::new(Self {
@@ -71,7 +71,7 @@ impl Strategy for NaiveRandomIBRStrategy {
         }))
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<Vec<ConfigModifier>, Error> {
+    fn work(&mut self, mut abort: ControlHandle) -> Result<Vec<ConfigModifier>, Error> {
         let mut sequence_insert = self
             .modifiers
             .iter()
@@ -81,7 +81,7 @@ impl Strategy for NaiveRandomIBRStrategy {
         let mut sequence_update = self
             .modifiers
             .iter()
-            .filter(|m| matches!(m, ConfigModifier::Update{..}))
+            .filter(|m| matches!(m, ConfigModifier::Update { .. }))
             .cloned()
             .collect::<Vec<_>>();
         let mut sequence_remove = self
@@ -91,12 +91,18 @@ impl Strategy for NaiveRandomIBRStrategy {
             .cloned()
             .collect::<Vec<_>>();
         let mut rng = thread_rng();
+        let mut best_sequence: Vec<ConfigModifier> = Vec::new();
+        let start_time = SystemTime::now();
         loop {
             // check for time budget
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 // time budget is used up!
                 error!("Time budget is used up! No solution was found yet!");
-                return Err(Error::Timeout);
+                let partial = PartialResult::new(best_sequence, &self.modifiers)
+                    .with_elapsed(start_time.elapsed().unwrap_or_default());
+                #[cfg(feature = "count-states")]
+                let partial = partial.with_num_states(self.num_states);
+                return Err(partial.into());
             }
 
             // check for abort criteria
@@ -113,9 +119,21 @@ impl Strategy for NaiveRandomIBRStrategy {
             sequence_insert.shuffle(&mut rng);
             sequence_update.shuffle(&mut rng);
             sequence_remove.shuffle(&mut rng);
-            if self.check_sequence(&sequence_insert, &sequence_update, &sequence_remove) {
-                let sequence = [sequence_insert, sequence_update, sequence_remove].concat();
-                return Ok(sequence);
+            match self.check_sequence(&sequence_insert, &sequence_update, &sequence_remove) {
+                Ok(()) => {
+                    let sequence = [sequence_insert, sequence_update, sequence_remove].concat();
+                    return Ok(sequence);
+                }
+                Err(index) if index > best_sequence.len() => {
+                    best_sequence = sequence_insert
+                        .iter()
+                        .chain(sequence_update.iter())
+                        .chain(sequence_remove.iter())
+                        .take(index)
+                        .cloned()
+                        .collect();
+                }
+                Err(_) => {}
             }
         }
     }
@@ -127,33 +145,35 @@ impl Strategy for NaiveRandomIBRStrategy {
 }
 
 impl NaiveRandomIBRStrategy {
+    /// Check the sequence. If it is not valid, the index of the first failing modifier is
+    /// returned.
     fn check_sequence(
         &self,
         seq_i: &[ConfigModifier],
         seq_u: &[ConfigModifier],
         seq_r: &[ConfigModifier],
-    ) -> bool {
+    ) -> Result<(), usize> {
         let mut net = self.net.clone();
         let mut hard_policy = self.hard_policy.clone();
 
         // apply every step in sequence
-        for modifier in seq_i.iter().chain(seq_u.iter()).chain(seq_r.iter()) {
+        for (i, modifier) in seq_i.iter().chain(seq_u.iter()).chain(seq_r.iter()).enumerate() {
             match net.apply_modifier(modifier) {
                 Ok(()) => {} // nothing to do
-                Err(NetworkError::NoConvergence) => return false,
-                Err(NetworkError::ConvergenceLoop(_, _)) => return false,
+                Err(NetworkError::NoConvergence) => return Err(i),
+                Err(NetworkError::ConvergenceLoop(_, _)) => return Err(i),
                 Err(e) => panic!("Unrecoverable network error: {}", e),
             }
             let mut fw_state = net.get_forwarding_state();
             if let Err(e) = hard_policy.step(&mut net, &mut fw_state) {
                 warn!("Error while checking hard policies: {}", e);
-                return false;
+                return Err(i);
             };
             if !hard_policy.check() {
-                return false;
+                return Err(i);
             }
         }
 
-        true
+        Ok(())
     }
 }
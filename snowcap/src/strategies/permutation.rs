@@ -17,12 +17,12 @@
 
 //! # The Permutation Strategy
 
-use super::{ExhaustiveStrategy, Strategy};
+use super::{ExhaustiveStrategy, PrecedenceConstraints, PrecedenceStrategy, Strategy};
 use crate::hard_policies::HardPolicy;
 use crate::netsim::config::ConfigModifier;
 use crate::netsim::{printer, Network, NetworkError};
 use crate::permutators::{Permutator, PermutatorItem};
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error, PartialResult};
 
 use log::*;
 use std::marker::PhantomData;
@@ -48,6 +48,7 @@ pub struct PermutationStrategy<P> {
     modifiers: Vec<ConfigModifier>,
     hard_policy: HardPolicy,
     stop_time: Option<SystemTime>,
+    precedence: PrecedenceConstraints,
     phantom: PhantomData<P>,
     #[cfg(feature = "count-states")]
     num_states: usize,
@@ -87,7 +88,7 @@ where
                     .collect::<Vec<_>>()
                     .join("\n    "),
             );
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
         Ok(Box::new(Self {
@@ -95,21 +96,28 @@ where
             modifiers,
             hard_policy,
             stop_time,
+            precedence: PrecedenceConstraints::new(),
             phantom: PhantomData,
             #[cfg(feature = "count-states")]
             num_states: 0,
         }))
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<Vec<ConfigModifier>, Error> {
+    fn work(&mut self, mut abort: ControlHandle) -> Result<Vec<ConfigModifier>, Error> {
         // check all permutations
         let mut permutator = P::new(self.modifiers.clone());
+        let mut best_sequence: Vec<ConfigModifier> = Vec::new();
+        let start_time = SystemTime::now();
         while let Some(possible_try) = permutator.next() {
             // check for time budget
-            if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+            if abort.time_is_up(self.stop_time) {
                 // time budget is used up!
                 error!("Time budget is used up! No solution was found yet!");
-                return Err(Error::Timeout);
+                let partial = PartialResult::new(best_sequence, &self.modifiers)
+                    .with_elapsed(start_time.elapsed().unwrap_or_default());
+                #[cfg(feature = "count-states")]
+                let partial = partial.with_num_states(self.num_states);
+                return Err(partial.into());
             }
 
             // check for abort criteria
@@ -119,6 +127,11 @@ where
             }
 
             let possible_try = possible_try.as_patches();
+            // skip the sequence if it violates the precedence constraints
+            if let Some(index) = self.precedence.first_violation(&possible_try) {
+                permutator.fail_pos(index);
+                continue;
+            }
             debug!(
                 "{:?}",
                 possible_try
@@ -129,6 +142,10 @@ where
             match self.check_sequence(&possible_try) {
                 Ok(()) => return Ok(possible_try),
                 Err(index) => {
+                    // remember the longest valid prefix
+                    if index > best_sequence.len() {
+                        best_sequence = possible_try[..index].to_vec();
+                    }
                     // tell the permutator that we failed
                     permutator.fail_pos(index);
                 }
@@ -151,6 +168,16 @@ where
 {
 }
 
+impl<P> PrecedenceStrategy for PermutationStrategy<P>
+where
+    P: Permutator<ConfigModifier> + Iterator,
+    P::Item: PermutatorItem<ConfigModifier>,
+{
+    fn set_precedence(&mut self, precedence: PrecedenceConstraints) {
+        self.precedence = precedence;
+    }
+}
+
 impl<P> PermutationStrategy<P>
 where
     P: Permutator<ConfigModifier> + Iterator,
@@ -0,0 +1,247 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Precedence Constraints
+//!
+//! Operators often have institutional constraints on the order of the commands, like "all
+//! route-map changes on router X before any session changes". Such constraints can be passed to
+//! strategies implementing [`PrecedenceStrategy`], which will only explore orderings satisfying
+//! all of them. In contrast to the hard policies, the constraints are not checked by simulating the
+//! network, but by looking at the modifiers only.
+//!
+//! If [`PrecedenceConstraints::graceful_shutdown`] is set, every removal of a BGP session is
+//! expanded into a graceful shutdown: The routes learned over the session are first depreferred
+//! (see [`ConfigExpr::BgpGracefulShutdown`]), such that the network moves to alternative routes
+//! before the session is torn down. The constraints enforce this order.
+
+use super::Strategy;
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::{Config, ConfigExpr, ConfigExprKey, ConfigModifier};
+use crate::netsim::{Network, RouterId};
+use crate::{ControlHandle, Error};
+
+use log::*;
+use std::time::Duration;
+
+/// # Precedence Strategy
+///
+/// Strategy, which can be restricted to orderings satisfying the [`PrecedenceConstraints`] given
+/// by the user. Orderings violating any of the constraints are skipped without simulating them.
+pub trait PrecedenceStrategy: Strategy {
+    /// Only explore orderings satisfying the constraints. This replaces any previously set
+    /// constraints.
+    fn set_precedence(&mut self, precedence: PrecedenceConstraints);
+
+    /// Wrapper, that creates the strategy, sets the precedence constraints and synthesizes the
+    /// network update order (see [`Strategy::synthesize`]).
+    fn synthesize_with_precedence(
+        net: Network,
+        end_config: Config,
+        hard_policy: HardPolicy,
+        precedence: PrecedenceConstraints,
+        time_budget: Option<Duration>,
+        abort: ControlHandle,
+    ) -> Result<Vec<ConfigModifier>, Error> {
+        let start_config = net.current_config().clone();
+        let patch = start_config.get_diff(&end_config);
+        let (modifiers, precedence) = precedence.expand_graceful_shutdown(&net, patch.modifiers);
+        let mut strategy = Self::new(net, modifiers, hard_policy, time_budget).map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+        strategy.set_precedence(precedence);
+        strategy.work(abort)
+    }
+}
+
+/// Selects a set of modifiers, used to describe a [`Precedence`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModifierSelector {
+    /// Exactly the given modifier
+    Modifier(ConfigModifier),
+    /// All modifiers which reconfigure the router
+    Router(RouterId),
+    /// All modifiers of the route maps on the router
+    RouteMaps(RouterId),
+    /// All modifiers of BGP sessions
+    BgpSessions,
+    /// All modifiers selected by any of the selectors
+    Any(Vec<ModifierSelector>),
+}
+
+impl ModifierSelector {
+    /// Returns `true` if the modifier is selected.
+    pub fn matches(&self, modifier: &ConfigModifier) -> bool {
+        match self {
+            Self::Modifier(m) => m == modifier,
+            Self::Router(r) => modifier.routers().contains(r),
+            Self::RouteMaps(r) => {
+                matches!(modifier.key(), ConfigExprKey::BgpRouteMap { router, .. } if router == *r)
+            }
+            Self::BgpSessions => matches!(modifier.key(), ConfigExprKey::BgpSession { .. }),
+            Self::Any(selectors) => selectors.iter().any(|s| s.matches(modifier)),
+        }
+    }
+}
+
+/// Every modifier selected by `before` must be applied before every modifier selected by `after`.
+/// Modifiers selected by both are not constrained.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Precedence {
+    /// Modifiers which must be applied first
+    pub before: ModifierSelector,
+    /// Modifiers which must be applied afterwards
+    pub after: ModifierSelector,
+}
+
+impl Precedence {
+    /// Returns `true` if `first` must be applied before `second`.
+    pub fn requires(&self, first: &ConfigModifier, second: &ConfigModifier) -> bool {
+        self.before.matches(first)
+            && !self.after.matches(first)
+            && self.after.matches(second)
+            && !self.before.matches(second)
+    }
+}
+
+/// # Precedence Constraints
+///
+/// Set of [`Precedence`] rules, which must all be satisfied by the ordering.
+///
+/// ```
+/// use snowcap::strategies::{ModifierSelector, PrecedenceConstraints};
+/// use snowcap::netsim::RouterId;
+///
+/// let r0: RouterId = 0.into();
+/// let constraints = PrecedenceConstraints::new()
+///     .before(ModifierSelector::RouteMaps(r0), ModifierSelector::BgpSessions);
+/// assert_eq!(constraints.rules().len(), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrecedenceConstraints {
+    rules: Vec<Precedence>,
+    graceful_shutdown: bool,
+}
+
+impl PrecedenceConstraints {
+    /// Create an empty set of constraints, allowing every ordering.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require that all modifiers selected by `before` are applied before the ones selected by
+    /// `after`, and return the updated constraints.
+    pub fn before(mut self, before: ModifierSelector, after: ModifierSelector) -> Self {
+        self.rules.push(Precedence { before, after });
+        self
+    }
+
+    /// Expand every removal of a BGP session into a graceful shutdown, and return the updated
+    /// constraints. The expansion is done by [`PrecedenceStrategy::synthesize_with_precedence`].
+    pub fn graceful_shutdown(mut self) -> Self {
+        self.graceful_shutdown = true;
+        self
+    }
+
+    /// Returns `true` if removals of BGP sessions are expanded into a graceful shutdown.
+    pub fn is_graceful_shutdown(&self) -> bool {
+        self.graceful_shutdown
+    }
+
+    /// Returns all rules
+    pub fn rules(&self) -> &[Precedence] {
+        &self.rules
+    }
+
+    /// Returns `true` if there are no constraints.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns `true` if `next` may be applied, while the modifiers in `remaining` are still left
+    /// to be applied afterwards. Occurrences of `next` in `remaining` are ignored.
+    pub fn allows<'a>(
+        &self,
+        next: &ConfigModifier,
+        remaining: impl IntoIterator<Item = &'a ConfigModifier>,
+    ) -> bool {
+        self.rules.is_empty()
+            || !remaining
+                .into_iter()
+                .any(|m| m != next && self.rules.iter().any(|r| r.requires(m, next)))
+    }
+
+    /// Expand every `Remove(BgpSession)` into the two-phase graceful variant, if
+    /// [`PrecedenceConstraints::graceful_shutdown`] is set. For each internal router of the session,
+    /// a [`ConfigExpr::BgpGracefulShutdown`] is inserted before, and removed again after the session
+    /// is removed. The returned constraints contain additional rules enforcing this order. If the
+    /// flag is not set, the modifiers and constraints are returned unchanged.
+    pub fn expand_graceful_shutdown(
+        mut self,
+        net: &Network,
+        modifiers: Vec<ConfigModifier>,
+    ) -> (Vec<ConfigModifier>, Self) {
+        if !self.graceful_shutdown {
+            return (modifiers, self);
+        }
+        let mut result = Vec::with_capacity(modifiers.len());
+        for modifier in modifiers {
+            let (source, target) = match &modifier {
+                ConfigModifier::Remove(ConfigExpr::BgpSession { source, target, .. }) => {
+                    (*source, *target)
+                }
+                _ => {
+                    result.push(modifier);
+                    continue;
+                }
+            };
+            for (router, neighbor) in vec![(source, target), (target, source)] {
+                let shutdown = ConfigExpr::BgpGracefulShutdown { router, neighbor };
+                // skip external routers, and sessions already in graceful shutdown
+                if !net.get_device(router).is_internal()
+                    || net.current_config().iter().any(|e| e.key() == shutdown.key())
+                {
+                    continue;
+                }
+                let start = ConfigModifier::Insert(shutdown.clone());
+                let end = ConfigModifier::Remove(shutdown);
+                self.rules.push(Precedence {
+                    before: ModifierSelector::Modifier(start.clone()),
+                    after: ModifierSelector::Modifier(modifier.clone()),
+                });
+                self.rules.push(Precedence {
+                    before: ModifierSelector::Modifier(modifier.clone()),
+                    after: ModifierSelector::Modifier(end.clone()),
+                });
+                result.push(start);
+                result.push(end);
+            }
+            result.push(modifier);
+        }
+        (result, self)
+    }
+
+    /// Returns the position of the first modifier in the sequence which is applied too early, or
+    /// `None` if the sequence satisfies all constraints. Every sequence starting with the same
+    /// modifiers up to (and including) this position violates the constraints as well.
+    pub fn first_violation(&self, sequence: &[ConfigModifier]) -> Option<usize> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        (0..sequence.len()).find(|i| !self.allows(&sequence[*i], &sequence[i + 1..]))
+    }
+}
@@ -17,12 +17,15 @@
 
 //! # The Push-Back Tree Strategy
 
-use super::{ExhaustiveStrategy, GroupStrategy, Strategy};
+use super::{
+    replay, CheckpointStrategy, ExhaustiveStrategy, FrameState, GroupStrategy,
+    PrecedenceConstraints, PrecedenceStrategy, Strategy, StrategyState,
+};
 use crate::hard_policies::HardPolicy;
 use crate::modifier_ordering::ModifierOrdering;
 use crate::netsim::config::ConfigModifier;
-use crate::netsim::{printer, Network};
-use crate::{Error, Stopper};
+use crate::netsim::{printer, Network, NetworkError};
+use crate::{ControlHandle, Error, PartialResult};
 
 use log::*;
 use std::collections::VecDeque;
@@ -49,6 +52,8 @@ use std::time::{Duration, SystemTime};
 /// find a solution of a `sparse problem` with *immediate effect* very quickly (`O(n^3)`). However,
 /// it has problems when dependencies have *no immediate effect*.
 ///
+/// The exploration can be interrupted and resumed (see [`CheckpointStrategy`]).
+///
 /// ## Type Arguments
 /// - `O` represents the chosen [`ModifierOrdering`](crate::modifier_ordering::ModifierOrdering),
 ///   which is used to order the modifiers before the tree algorithm starts.
@@ -59,6 +64,10 @@ pub struct PushBackTreeStrategy<O> {
     hard_policy: HardPolicy,
     stop_time: Option<SystemTime>,
     max_backtrack_level: usize,
+    resume_point: Option<StrategyState>,
+    precedence: PrecedenceConstraints,
+    #[cfg(feature = "transient-violation")]
+    fuzz_orderings: Option<usize>,
     phantom: PhantomData<O>,
     #[cfg(feature = "count-states")]
     num_states: usize,
@@ -105,7 +114,7 @@ where
                     .collect::<Vec<_>>()
                     .join("\n    "),
             );
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
 
@@ -116,21 +125,41 @@ where
             hard_policy,
             stop_time,
             max_backtrack_level: usize::MAX,
+            resume_point: None,
+            precedence: PrecedenceConstraints::new(),
+            #[cfg(feature = "transient-violation")]
+            fuzz_orderings: None,
             phantom: PhantomData,
             #[cfg(feature = "count-states")]
             num_states: 0,
         }))
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<Vec<ConfigModifier>, Error> {
-        // initialize the stack
-        let mut stack: Vec<Stack> = vec![Stack::from_vec((0..self.groups.len()).collect(), 0)];
+    fn work(&mut self, mut abort: ControlHandle) -> Result<Vec<ConfigModifier>, Error> {
+        // initialize the stack, either at the root, or at the point where the last call stopped
+        let state = self.checkpoint();
+        self.resume_point = None;
+        let mut stack: Vec<Stack> = Vec::with_capacity(state.frames.len());
+        for (i, frame) in state.frames.iter().enumerate() {
+            let num_undo = match i {
+                0 => 0,
+                i => self.groups[state.sequence[i - 1]].len(),
+            };
+            let mut s = Stack::from_vec(frame.remaining.clone(), frame.next);
+            s.num_undo = num_undo;
+            stack.push(s);
+        }
         // points into the groups vector
-        let mut group_sequence: Vec<usize> = Vec::new();
-        // number of modifiers applied on the network
+        let mut group_sequence: Vec<usize> = state.sequence.clone();
+        // longest valid prefix (in modifiers) found so far
+        let mut best_sequence: Vec<ConfigModifier> = state.best_sequence.clone();
+        let start_time = SystemTime::now();
 
         let mut net = self.net.clone();
         let mut hard_policy = self.hard_policy.clone();
+        replay(&mut net, &mut hard_policy, state.sequence_modifiers().iter())?;
+        // interrupt the convergence if the operation is aborted
+        net.set_abort(Some(abort.clone()));
 
         // backtrack level checker
         let mut num_backtrack: usize = 0;
@@ -138,6 +167,7 @@ where
         'main_loop: loop {
             let mut pop_stack: bool = false;
             let mut push_stack: Option<Stack> = None;
+            let mut aborted: bool = false;
             if let Some(s) = stack.last_mut() {
                 // we are done if s.rem_mod is empty
                 if s.rem_group.is_empty() {
@@ -151,6 +181,13 @@ where
                     // get the current modifier and clone the current network
                     let current_group: usize = s.rem_group.pop_front().unwrap();
 
+                    // skip the group if other modifiers must be applied before it
+                    if !self.group_allowed(current_group, &s.rem_group) {
+                        s.rem_group.push_back(current_group);
+                        s.cur_idx += 1;
+                        continue 'main_loop;
+                    }
+
                     // print trace
                     debug!("Trying the sequence {:?}", group_sequence);
 
@@ -165,17 +202,21 @@ where
                         }
 
                         num_undo += 1;
-                        if net.apply_modifier(self.modifiers.get(*m_idx).unwrap()).is_ok() {
-                            num_undo_policy += 1;
-                            let mut fw_state = net.get_forwarding_state();
-                            hard_policy.step(&mut net, &mut fw_state)?;
-                            if !hard_policy.check() {
+                        match self.apply_modifier(&mut net, *m_idx, &hard_policy) {
+                            Ok(transient_ok) => {
+                                num_undo_policy += 1;
+                                let mut fw_state = net.get_forwarding_state();
+                                hard_policy.step(&mut net, &mut fw_state)?;
+                                if !transient_ok || !hard_policy.check() {
+                                    mod_ok = false;
+                                    break 'apply_group;
+                                }
+                            }
+                            Err(e) => {
+                                aborted = e == NetworkError::Aborted;
                                 mod_ok = false;
                                 break 'apply_group;
                             }
-                        } else {
-                            mod_ok = false;
-                            break 'apply_group;
                         }
                     }
 
@@ -184,6 +225,15 @@ where
                         push_stack =
                             Some(Stack { num_undo, rem_group: s.rem_group.clone(), cur_idx: 0 });
                         group_sequence.push(current_group);
+                        let seq_len: usize =
+                            group_sequence.iter().map(|g| self.groups[*g].len()).sum();
+                        if seq_len > best_sequence.len() {
+                            best_sequence = group_sequence
+                                .iter()
+                                .flat_map(|g| self.groups[*g].iter())
+                                .map(|m_idx| self.modifiers[*m_idx].clone())
+                                .collect();
+                        }
                     } else {
                         // undo the changes
                         for _ in 0..num_undo {
@@ -193,10 +243,16 @@ where
                             hard_policy.undo();
                         }
                     }
-                    // push the current modifier back into the ring buffer, at the last position.
-                    s.rem_group.push_back(current_group);
-                    // move cur_idx to the next position for the next iteration
-                    s.cur_idx += 1;
+                    if aborted {
+                        // the group was not checked, try it again when resuming
+                        s.rem_group.push_front(current_group);
+                    } else {
+                        // push the current modifier back into the ring buffer, at the last
+                        // position.
+                        s.rem_group.push_back(current_group);
+                        // move cur_idx to the next position for the next iteration
+                        s.cur_idx += 1;
+                    }
                 }
             } else {
                 // the stack is empty! We found nothing!
@@ -236,6 +292,12 @@ where
                 }
             }
 
+            if aborted {
+                info!("Operation was aborted!");
+                self.resume_point = Some(self.state(&stack, &group_sequence, &best_sequence));
+                break Err(Error::Abort);
+            }
+
             if pop_stack {
                 let stack_frame = stack.pop();
                 // undo the network
@@ -249,15 +311,21 @@ where
                 trace!("Backtrack from tree, current levels: {}", stack.len());
 
                 // check for time budget
-                if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+                if abort.time_is_up(self.stop_time) {
                     // time budget is used up!
                     error!("Time budget is used up! No solution was found yet!");
-                    break Err(Error::Timeout);
+                    self.resume_point = Some(self.state(&stack, &group_sequence, &best_sequence));
+                    let partial = PartialResult::new(best_sequence, &self.modifiers)
+                        .with_elapsed(start_time.elapsed().unwrap_or_default());
+                    #[cfg(feature = "count-states")]
+                    let partial = partial.with_num_states(self.num_states);
+                    break Err(partial.into());
                 }
 
                 // check for abort criteria
                 if abort.try_is_stop().unwrap_or(false) {
                     info!("Operation was aborted!");
+                    self.resume_point = Some(self.state(&stack, &group_sequence, &best_sequence));
                     break Err(Error::Abort);
                 }
 
@@ -327,7 +395,7 @@ where
         let mut fw_state = net.get_forwarding_state();
         hard_policy.step(&mut net, &mut fw_state)?;
         if !hard_policy.check() {
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
         Ok(Box::new(Self {
@@ -337,6 +405,10 @@ where
             hard_policy,
             stop_time,
             max_backtrack_level: usize::MAX,
+            resume_point: None,
+            precedence: PrecedenceConstraints::new(),
+            #[cfg(feature = "transient-violation")]
+            fuzz_orderings: None,
             phantom: PhantomData,
             #[cfg(feature = "count-states")]
             num_states: 0,
@@ -356,6 +428,89 @@ where
         self.max_backtrack_level = max_backtrack;
     }
 
+    /// Additionally check every modifier by fuzzing `n_iter` random orderings of the BGP messages
+    /// (see [`Network::apply_modifier_fuzz_orderings`]). A modifier is only applied if the hard
+    /// policy is satisfied in every transient state of all orderings. Pass `None` to disable the
+    /// fuzzing again.
+    ///
+    /// *This method is only available if the `"transient-violation"` feature is enabled!*
+    #[cfg(feature = "transient-violation")]
+    pub fn set_fuzz_orderings(&mut self, n_iter: Option<usize>) {
+        self.fuzz_orderings = n_iter;
+    }
+
+    /// Apply the modifier with index `m_idx`. The result is `false` if fuzzing the orderings of
+    /// messages revealed a transient violation of the hard policy (see
+    /// [`PushBackTreeStrategy::set_fuzz_orderings`]).
+    #[allow(unused_variables)]
+    fn apply_modifier(
+        &self,
+        net: &mut Network,
+        m_idx: usize,
+        hard_policy: &HardPolicy,
+    ) -> Result<bool, NetworkError> {
+        let modifier = &self.modifiers[m_idx];
+        #[cfg(feature = "transient-violation")]
+        if let Some(n_iter) = self.fuzz_orderings {
+            let violations = net.apply_modifier_fuzz_orderings(
+                modifier,
+                n_iter,
+                &mut rand::thread_rng(),
+                hard_policy,
+            )?;
+            for v in violations.iter() {
+                debug!("Transient violation: {:?} in ordering {:?}", v.errors, v.ordering);
+            }
+            return Ok(violations.is_empty());
+        }
+        net.apply_modifier(modifier).map(|()| true)
+    }
+
+    /// Returns `true` if the group may be applied according to the precedence constraints, while
+    /// all groups in `remaining` are still left to be applied afterwards.
+    fn group_allowed(&self, group: usize, remaining: &VecDeque<usize>) -> bool {
+        if self.precedence.is_empty() {
+            return true;
+        }
+        let group = &self.groups[group];
+        group.iter().enumerate().all(|(i, m)| {
+            self.precedence.allows(
+                &self.modifiers[*m],
+                group[i + 1..]
+                    .iter()
+                    .chain(remaining.iter().flat_map(|g| self.groups[*g].iter()))
+                    .map(|m| &self.modifiers[*m]),
+            )
+        })
+    }
+
+    /// Returns the modifiers of every group
+    fn group_modifiers(&self) -> Vec<Vec<ConfigModifier>> {
+        self.groups.iter().map(|g| g.iter().map(|i| self.modifiers[*i].clone()).collect()).collect()
+    }
+
+    /// Describe the current state of the exploration
+    fn state(
+        &self,
+        stack: &[Stack],
+        group_sequence: &[usize],
+        best_sequence: &[ConfigModifier],
+    ) -> StrategyState {
+        StrategyState {
+            strategy: "PushBackTreeStrategy".to_string(),
+            groups: self.group_modifiers(),
+            frames: stack
+                .iter()
+                .map(|s| FrameState {
+                    remaining: s.rem_group.iter().cloned().collect(),
+                    next: s.cur_idx,
+                })
+                .collect(),
+            sequence: group_sequence.to_vec(),
+            best_sequence: best_sequence.to_vec(),
+        }
+    }
+
     fn finalize_ordering(&self, group_ordering: Vec<usize>) -> Vec<ConfigModifier> {
         group_ordering
             .iter()
@@ -366,8 +521,43 @@ where
     }
 }
 
+impl<O> CheckpointStrategy for PushBackTreeStrategy<O>
+where
+    O: ModifierOrdering<ConfigModifier>,
+{
+    fn checkpoint(&self) -> StrategyState {
+        match self.resume_point.as_ref() {
+            Some(state) => state.clone(),
+            None => StrategyState::root("PushBackTreeStrategy", self.group_modifiers()),
+        }
+    }
+
+    fn resume(&mut self, state: StrategyState) -> Result<(), Error> {
+        state.validate("PushBackTreeStrategy", &self.modifiers)?;
+        let mut modifiers: Vec<ConfigModifier> = Vec::with_capacity(self.modifiers.len());
+        let mut groups: Vec<Vec<usize>> = Vec::with_capacity(state.groups.len());
+        for group in state.groups.iter() {
+            groups.push((modifiers.len()..modifiers.len() + group.len()).collect());
+            modifiers.extend(group.iter().cloned());
+        }
+        self.modifiers = modifiers;
+        self.groups = groups;
+        self.resume_point = Some(state);
+        Ok(())
+    }
+}
+
 impl<O> ExhaustiveStrategy for PushBackTreeStrategy<O> where O: ModifierOrdering<ConfigModifier> {}
 
+impl<O> PrecedenceStrategy for PushBackTreeStrategy<O>
+where
+    O: ModifierOrdering<ConfigModifier>,
+{
+    fn set_precedence(&mut self, precedence: PrecedenceConstraints) {
+        self.precedence = precedence;
+    }
+}
+
 struct Stack {
     pub num_undo: usize,
     pub rem_group: VecDeque<usize>,
@@ -0,0 +1,299 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # The SAT Strategy
+//!
+//! *This module is only available if the `"sat-strategy"` feature is enabled!*
+
+use super::{ExhaustiveStrategy, Strategy};
+use crate::hard_policies::{HardPolicy, LTLModal};
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{printer, Network, NetworkError};
+use crate::{ControlHandle, Error, PartialResult};
+
+use log::*;
+use std::time::{Duration, SystemTime};
+use varisat::{ExtendFormula, Lit, Solver, Var};
+
+/// # The SAT Strategy
+///
+/// This strategy encodes the ordering of the modifiers as a SAT problem, with one variable for
+/// every pair of modifiers, stating which of the two is applied first (and clauses enforcing the
+/// transitivity). It then iterates the following steps (counterexample-guided refinement), until a
+/// valid ordering is found, or the SAT problem becomes unsatisfiable:
+///
+/// 1. Ask the SAT solver for an ordering, satisfying all clauses learned so far.
+/// 2. Simulate the ordering, until the hard policy is violated, or the network cannot converge.
+/// 3. Add a clause blocking every ordering with the same failure.
+///
+/// If the hard policy is of the form $\mathbf{G}\ \phi$ (without transient conditions), whether it
+/// is violated only depends on the current state of the network. In this case, the learned clause
+/// does not only block the failing sequence, but every sequence applying the same set of modifiers
+/// first (in any order), which is why this strategy works well for problems with *sparse*
+/// *solutions* and *no immediate effect* (like the
+/// [`DifficultGadget`](crate::example_networks::DifficultGadgetMinimal)), where the tree search
+/// explodes. Here, it is assumed that the network always converges to the same state for the same
+/// set of applied modifiers. For all other policies, only the failing sequence is blocked.
+///
+/// *This strategy is only available if the `"sat-strategy"` feature is enabled!*
+pub struct SatStrategy {
+    net: Network,
+    modifiers: Vec<ConfigModifier>,
+    hard_policy: HardPolicy,
+    stop_time: Option<SystemTime>,
+    #[cfg(feature = "count-states")]
+    num_states: usize,
+}
+
+impl Strategy for SatStrategy {
+    fn new(
+        mut net: Network,
+        modifiers: Vec<ConfigModifier>,
+        mut hard_policy: HardPolicy,
+        time_budget: Option<Duration>,
+    ) -> Result<Box<Self>, Error> {
+        // every sequence is checked on a fresh clone of the network
+        net.set_undo_tracking(false);
+
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.set_num_mods_if_none(modifiers.len());
+        hard_policy.step(&mut net, &mut fw_state)?;
+        if !hard_policy.check() {
+            error!(
+                "Initial state errors: \n    {}",
+                hard_policy
+                    .last_errors()
+                    .into_iter()
+                    .map(|e| e.repr_with_name(&net))
+                    .collect::<Vec<_>>()
+                    .join("\n    "),
+            );
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
+        }
+        let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
+        Ok(Box::new(Self {
+            net,
+            modifiers,
+            hard_policy,
+            stop_time,
+            #[cfg(feature = "count-states")]
+            num_states: 0,
+        }))
+    }
+
+    fn work(&mut self, mut abort: ControlHandle) -> Result<Vec<ConfigModifier>, Error> {
+        let n = self.modifiers.len();
+        let mut encoding = OrderEncoding::new(n);
+        let state_only = self.is_state_only();
+        let mut best_sequence: Vec<ConfigModifier> = Vec::new();
+        let start_time = SystemTime::now();
+        let mut net = self.net.clone();
+        net.set_abort(Some(abort.clone()));
+
+        loop {
+            // check for time budget
+            if abort.time_is_up(self.stop_time) {
+                // time budget is used up!
+                error!("Time budget is used up! No solution was found yet!");
+                let partial = PartialResult::new(best_sequence, &self.modifiers)
+                    .with_elapsed(start_time.elapsed().unwrap_or_default());
+                #[cfg(feature = "count-states")]
+                let partial = partial.with_num_states(self.num_states);
+                return Err(partial.into());
+            }
+
+            // check for abort criteria
+            if abort.try_is_stop().unwrap_or(false) {
+                info!("Operation was aborted!");
+                return Err(Error::Abort);
+            }
+
+            let order = match encoding.solve()? {
+                Some(order) => order,
+                None => return Err(Error::NoSafeOrdering),
+            };
+            debug!("{:?}", order);
+
+            let (failure, network_error) = match self.check_sequence(&net, &order) {
+                Ok(()) => return Ok(order.iter().map(|i| self.modifiers[*i].clone()).collect()),
+                Err(Failure::Aborted) => {
+                    info!("Operation was aborted!");
+                    return Err(Error::Abort);
+                }
+                Err(Failure::Policy(pos)) => (pos, false),
+                Err(Failure::Network(pos)) => (pos, true),
+            };
+            if failure > best_sequence.len() {
+                best_sequence =
+                    order[..failure].iter().map(|i| self.modifiers[*i].clone()).collect();
+            }
+            trace!(
+                "Sequence fails at {}",
+                printer::config_modifier(&self.net, &self.modifiers[order[failure]]).unwrap()
+            );
+
+            let (applied, remaining) = order.split_at(failure + 1);
+            if !state_only {
+                encoding.block_sequence(applied, remaining);
+            } else if network_error {
+                // the convergence depends on the modifier applied last
+                encoding.block_last(applied, remaining);
+            } else {
+                encoding.block_set(applied, remaining);
+            }
+        }
+    }
+
+    #[cfg(feature = "count-states")]
+    fn num_states(&self) -> usize {
+        self.num_states
+    }
+}
+
+impl ExhaustiveStrategy for SatStrategy {}
+
+impl SatStrategy {
+    /// Returns `true` if the hard policy only depends on the current state of the network.
+    fn is_state_only(&self) -> bool {
+        matches!(self.hard_policy.expr, LTLModal::Globally(_))
+            && !self.hard_policy.prop_vars.iter().any(|c| c.is_transient())
+    }
+
+    /// Check the sequence (of indices into `self.modifiers`). If it is not valid, the position of
+    /// the first failing modifier is returned.
+    fn check_sequence(&mut self, net: &Network, order: &[usize]) -> Result<(), Failure> {
+        let mut net = net.clone();
+        let mut hard_policy = self.hard_policy.clone();
+
+        for (pos, i) in order.iter().enumerate() {
+            #[cfg(feature = "count-states")]
+            {
+                self.num_states += 1;
+            }
+            match net.apply_modifier(&self.modifiers[*i]) {
+                Ok(()) => {}
+                Err(NetworkError::Aborted) => return Err(Failure::Aborted),
+                Err(_) => return Err(Failure::Network(pos)),
+            }
+            let mut fw_state = net.get_forwarding_state();
+            if let Err(e) = hard_policy.step(&mut net, &mut fw_state) {
+                warn!("Error while checking hard policies: {}", e);
+                return Err(Failure::Network(pos));
+            }
+            if !hard_policy.check() {
+                return Err(Failure::Policy(pos));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reason why a sequence is not valid, with the position of the modifier causing the problem.
+enum Failure {
+    /// The hard policy is violated
+    Policy(usize),
+    /// The network cannot converge
+    Network(usize),
+    /// The simulation was aborted
+    Aborted,
+}
+
+/// SAT encoding of a total order of `n` elements. For every pair `i < j`, the variable states that
+/// `i` is ordered before `j`.
+struct OrderEncoding {
+    n: usize,
+    solver: Solver<'static>,
+    vars: Vec<Var>,
+}
+
+impl OrderEncoding {
+    /// Create the encoding, including the clauses for transitivity.
+    fn new(n: usize) -> Self {
+        let mut solver = Solver::new();
+        let vars = (0..n * n.saturating_sub(1) / 2).map(|_| solver.new_var()).collect();
+        let mut encoding = Self { n, solver, vars };
+        for i in 0..n {
+            for j in (0..n).filter(|j| *j != i) {
+                for k in (0..n).filter(|k| *k != i && *k != j) {
+                    let clause =
+                        [!encoding.before(i, j), !encoding.before(j, k), encoding.before(i, k)];
+                    encoding.solver.add_clause(&clause);
+                }
+            }
+        }
+        encoding
+    }
+
+    /// Literal stating that `i` is ordered before `j`.
+    fn before(&self, i: usize, j: usize) -> Lit {
+        let (a, b) = if i < j { (i, j) } else { (j, i) };
+        // index of the pair (a, b) in the upper triangle of the matrix
+        let idx = a * (2 * self.n - a - 1) / 2 + (b - a - 1);
+        Lit::from_var(self.vars[idx], i < j)
+    }
+
+    /// Returns an order satisfying all clauses, or `None` if there is no such order.
+    fn solve(&mut self) -> Result<Option<Vec<usize>>, Error> {
+        if !self.solver.solve().map_err(|e| Error::SatSolverError(e.to_string()))? {
+            return Ok(None);
+        }
+        let mut values = vec![false; self.vars.len()];
+        for lit in self.solver.model().unwrap_or_default() {
+            values[lit.var().index()] = lit.is_positive();
+        }
+        let holds = |lit: Lit| values[lit.var().index()] == lit.is_positive();
+        // the position of every element is its number of predecessors
+        let mut order = (0..self.n).collect::<Vec<_>>();
+        order.sort_by_cached_key(|i| {
+            (0..self.n).filter(|j| j != i && holds(self.before(*j, *i))).count()
+        });
+        Ok(Some(order))
+    }
+
+    /// Block every order starting with the sequence `applied`.
+    fn block_sequence(&mut self, applied: &[usize], remaining: &[usize]) {
+        let last = applied.len() - 1;
+        let clause = applied
+            .windows(2)
+            .map(|w| !self.before(w[0], w[1]))
+            .chain(remaining.iter().map(|r| !self.before(applied[last], *r)))
+            .collect::<Vec<_>>();
+        self.solver.add_clause(&clause);
+    }
+
+    /// Block every order, which starts with the elements of `applied` (in any order), followed by
+    /// the last element of `applied`.
+    fn block_last(&mut self, applied: &[usize], remaining: &[usize]) {
+        let (last, first) = applied.split_last().unwrap();
+        let clause = first
+            .iter()
+            .map(|f| !self.before(*f, *last))
+            .chain(remaining.iter().map(|r| !self.before(*last, *r)))
+            .collect::<Vec<_>>();
+        self.solver.add_clause(&clause);
+    }
+
+    /// Block every order, which starts with the elements of `applied` (in any order).
+    fn block_set(&mut self, applied: &[usize], remaining: &[usize]) {
+        let clause = applied
+            .iter()
+            .flat_map(|a| remaining.iter().map(move |r| (*a, *r)))
+            .map(|(a, r)| !self.before(a, r))
+            .collect::<Vec<_>>();
+        self.solver.add_clause(&clause);
+    }
+}
@@ -0,0 +1,183 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # State Cache
+//!
+//! Different orderings of the same modifiers often reach identical intermediate states. The
+//! [`StateCache`] remembers the forwarding state and the results of the hard policy checks for
+//! the most recently used states, such that they need to be computed only once per strategy run.
+
+use crate::hard_policies::PolicyError;
+use crate::netsim::{ForwardingState, Network};
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Default number of states stored in the [`StateCache`].
+pub const DEFAULT_STATE_CACHE_CAPACITY: usize = 1024;
+
+/// Results of checking all conditions of a hard policy, except the transient ones and the anchors.
+pub(crate) type CheckResults = (Vec<bool>, Vec<Option<PolicyError>>);
+
+/// # State Cache
+///
+/// Bounded cache of forwarding states and hard policy checks, keyed by the content hash of the
+/// network configuration (see
+/// [`Config::content_hash`](crate::netsim::config::Config::content_hash)).
+/// Since the configuration does not depend on the order in which the modifiers were applied, every
+/// state reached by applying the same set of modifiers is only simulated once. When the cache is
+/// full, the least recently used state is evicted.
+///
+/// The cache assumes that all networks share the same initial state (including the advertised
+/// routes), and only differ in their configuration. It must therefore not be shared between
+/// different problems.
+#[derive(Debug, Clone)]
+pub struct StateCache {
+    capacity: usize,
+    entries: HashMap<u64, CacheEntry>,
+    lru: BTreeMap<usize, u64>,
+    tick: usize,
+    hits: usize,
+    misses: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    last_used: usize,
+    fw_state: ForwardingState,
+    checks: Option<CheckResults>,
+}
+
+impl StateCache {
+    /// Create a new, empty cache, storing at most `capacity` states. The capacity must be at
+    /// least 1.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0);
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the maximum number of states stored in the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of states currently stored in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache contains no state.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number of lookups, which could be answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Returns the number of lookups, which required a new simulation.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Remove all states from the cache.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+    }
+
+    /// Returns the forwarding state of the network, either from the cache, or by computing it (and
+    /// storing it in the cache).
+    pub fn forwarding_state(&mut self, net: &Network) -> ForwardingState {
+        let key = net.current_config().content_hash();
+        if self.touch(key) {
+            self.hits += 1;
+            self.entries[&key].fw_state.clone()
+        } else {
+            self.misses += 1;
+            let fw_state = net.get_forwarding_state();
+            self.insert(key, CacheEntry { last_used: 0, fw_state: fw_state.clone(), checks: None });
+            fw_state
+        }
+    }
+
+    /// Returns the cached results of the hard policy checks with `num_conditions` conditions.
+    pub(crate) fn checks(&mut self, net: &Network, num_conditions: usize) -> Option<CheckResults> {
+        let key = net.current_config().content_hash();
+        if !self.touch(key) {
+            return None;
+        }
+        match &self.entries[&key].checks {
+            Some(checks) if checks.0.len() == num_conditions => {
+                self.hits += 1;
+                Some(checks.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Store the results of the hard policy checks for the current state of the network. The
+    /// forwarding state must already be stored in the cache (see
+    /// [`StateCache::forwarding_state`]), otherwise, nothing is stored.
+    pub(crate) fn set_checks(&mut self, net: &Network, checks: CheckResults) {
+        let key = net.current_config().content_hash();
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.checks = Some(checks);
+        }
+    }
+
+    /// Mark the entry as most recently used. Returns false if the entry does not exist.
+    fn touch(&mut self, key: u64) -> bool {
+        self.tick += 1;
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                self.lru.remove(&entry.last_used);
+                entry.last_used = self.tick;
+                self.lru.insert(self.tick, key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert a new entry, evicting the least recently used one if the cache is full.
+    fn insert(&mut self, key: u64, mut entry: CacheEntry) {
+        if self.entries.len() >= self.capacity {
+            let oldest = self.lru.keys().next().cloned();
+            if let Some(key) = oldest.and_then(|tick| self.lru.remove(&tick)) {
+                self.entries.remove(&key);
+            }
+        }
+        self.tick += 1;
+        entry.last_used = self.tick;
+        self.lru.insert(self.tick, key);
+        self.entries.insert(key, entry);
+    }
+}
+
+impl Default for StateCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATE_CACHE_CAPACITY)
+    }
+}
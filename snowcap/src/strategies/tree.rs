@@ -17,12 +17,15 @@
 
 //! # The Tree Strategy
 
-use super::{ExhaustiveStrategy, Strategy};
+use super::{
+    replay, CheckpointStrategy, ExhaustiveStrategy, FrameState, PrecedenceConstraints,
+    PrecedenceStrategy, Strategy, StrategyState,
+};
 use crate::hard_policies::HardPolicy;
 use crate::modifier_ordering::ModifierOrdering;
 use crate::netsim::config::ConfigModifier;
-use crate::netsim::{printer, Network};
-use crate::{Error, Stopper};
+use crate::netsim::{printer, Network, NetworkError};
+use crate::{ControlHandle, Error, PartialResult};
 
 use log::*;
 use std::marker::PhantomData;
@@ -42,6 +45,8 @@ use std::time::{Duration, SystemTime};
 /// find a solution of a `sparse problem` with *immediate effect* very quickly (`O(n^3)`). However,
 /// it has problems when dependencies have *no immediate effect*.
 ///
+/// The exploration can be interrupted and resumed (see [`CheckpointStrategy`]).
+///
 /// ## Type Arguments
 /// - `O` represents the chosen [`ModifierOrdering`](crate::modifier_ordering::ModifierOrdering),
 ///   which is used to order the modifiers before the tree algorithm starts.
@@ -53,6 +58,8 @@ where
     modifiers: Vec<ConfigModifier>,
     hard_policy: HardPolicy,
     stop_time: Option<SystemTime>,
+    resume_point: Option<StrategyState>,
+    precedence: PrecedenceConstraints,
     phantom: PhantomData<O>,
     #[cfg(feature = "count-states")]
     num_states: usize,
@@ -96,7 +103,7 @@ where
                     .map(|e| e.repr_with_name(&net))
                     .collect::<Vec<_>>()
             );
-            return Err(Error::InvalidInitialState);
+            return Err(Error::invalid_initial_state(&net, &hard_policy, &mut fw_state));
         }
         let stop_time: Option<SystemTime> = time_budget.map(|dur| SystemTime::now() + dur);
         Ok(Box::new(Self {
@@ -104,23 +111,40 @@ where
             modifiers,
             hard_policy,
             stop_time,
+            resume_point: None,
+            precedence: PrecedenceConstraints::new(),
             phantom: PhantomData,
             #[cfg(feature = "count-states")]
             num_states: 0,
         }))
     }
 
-    fn work(&mut self, mut abort: Stopper) -> Result<Vec<ConfigModifier>, Error> {
-        // initialize the stack
-        let mut stack: Vec<Stack> = vec![Stack { rem_mod: self.modifiers.clone(), cur_idx: 0 }];
-        let mut mod_sequence: Vec<ConfigModifier> = Vec::new();
+    fn work(&mut self, mut abort: ControlHandle) -> Result<Vec<ConfigModifier>, Error> {
+        // initialize the stack, either at the root, or at the point where the last call stopped
+        let state = self.checkpoint();
+        self.resume_point = None;
+        let mut stack: Vec<Stack> = state
+            .frames
+            .iter()
+            .map(|f| Stack {
+                rem_mod: f.remaining.iter().map(|i| self.modifiers[*i].clone()).collect(),
+                cur_idx: f.next,
+            })
+            .collect();
+        let mut mod_sequence: Vec<ConfigModifier> = state.sequence_modifiers();
+        let mut best_sequence: Vec<ConfigModifier> = state.best_sequence;
+        let start_time = SystemTime::now();
 
         let mut net = self.net.clone();
         let mut hard_policy = self.hard_policy.clone();
+        replay(&mut net, &mut hard_policy, mod_sequence.iter())?;
+        // interrupt the convergence if the operation is aborted
+        net.set_abort(Some(abort.clone()));
 
         loop {
             let mut pop_stack: bool = false;
             let mut push_stack: Option<Stack> = None;
+            let mut aborted: bool = false;
             if let Some(s) = stack.last_mut() {
                 // we are done if s.rem_mod is empty
                 if s.rem_mod.is_empty() {
@@ -138,6 +162,11 @@ where
                     // get the current modifier and clone the current network
                     let current_mod: &ConfigModifier = &s.rem_mod[cur_idx];
 
+                    // skip the modifier if others must be applied before it
+                    if !self.precedence.allows(current_mod, &s.rem_mod) {
+                        continue;
+                    }
+
                     // print the current sequence
                     if STATIC_MAX_LEVEL >= LevelFilter::Debug {
                         let mut print_vec: Vec<usize> = Vec::new();
@@ -155,16 +184,23 @@ where
                         self.num_states += 1;
                     }
 
-                    let (mod_ok, undo_policy) = if net.apply_modifier(current_mod).is_ok() {
-                        let mut fw_state = net.get_forwarding_state();
-                        hard_policy.step(&mut net, &mut fw_state)?;
-                        if hard_policy.check() {
-                            (true, false)
-                        } else {
-                            (false, true)
+                    let (mod_ok, undo_policy) = match net.apply_modifier(current_mod) {
+                        Ok(()) => {
+                            let mut fw_state = net.get_forwarding_state();
+                            hard_policy.step(&mut net, &mut fw_state)?;
+                            if hard_policy.check() {
+                                (true, false)
+                            } else {
+                                (false, true)
+                            }
                         }
-                    } else {
-                        (false, false)
+                        Err(NetworkError::Aborted) => {
+                            // the modifier was not checked, try it again when resuming
+                            s.cur_idx = cur_idx;
+                            aborted = true;
+                            (false, false)
+                        }
+                        Err(_) => (false, false),
                     };
 
                     if mod_ok {
@@ -173,6 +209,9 @@ where
                         new_mod.remove(cur_idx);
                         push_stack = Some(Stack { rem_mod: new_mod, cur_idx: 0 });
                         mod_sequence.push(current_mod.clone());
+                        if mod_sequence.len() > best_sequence.len() {
+                            best_sequence = mod_sequence.clone();
+                        }
                     } else {
                         net.undo_action()?;
                         if undo_policy {
@@ -185,6 +224,12 @@ where
                 break Err(Error::NoSafeOrdering);
             }
 
+            if aborted {
+                info!("Operation was aborted!");
+                self.resume_point = Some(self.state(&stack, &mod_sequence, &best_sequence));
+                break Err(Error::Abort);
+            }
+
             if pop_stack {
                 // undo the network
                 net.undo_action()?;
@@ -195,15 +240,21 @@ where
                 debug!("Backtrack from tree, current levels: {}", stack.len());
 
                 // check for time budget
-                if self.stop_time.as_ref().map(|time| time.elapsed().is_ok()).unwrap_or(false) {
+                if abort.time_is_up(self.stop_time) {
                     // time budget is used up!
                     error!("Time budget is used up! No solution was found yet!");
-                    break Err(Error::Timeout);
+                    self.resume_point = Some(self.state(&stack, &mod_sequence, &best_sequence));
+                    let partial = PartialResult::new(best_sequence, &self.modifiers)
+                        .with_elapsed(start_time.elapsed().unwrap_or_default());
+                    #[cfg(feature = "count-states")]
+                    let partial = partial.with_num_states(self.num_states);
+                    break Err(partial.into());
                 }
 
                 // check for abort criteria
                 if abort.try_is_stop().unwrap_or(false) {
                     info!("Operation was aborted!");
+                    self.resume_point = Some(self.state(&stack, &mod_sequence, &best_sequence));
                     break Err(Error::Abort);
                 }
             }
@@ -220,8 +271,70 @@ where
     }
 }
 
+impl<O> CheckpointStrategy for TreeStrategy<O>
+where
+    O: ModifierOrdering<ConfigModifier>,
+{
+    fn checkpoint(&self) -> StrategyState {
+        match self.resume_point.as_ref() {
+            Some(state) => state.clone(),
+            None => StrategyState::root(
+                "TreeStrategy",
+                self.modifiers.iter().map(|m| vec![m.clone()]).collect(),
+            ),
+        }
+    }
+
+    fn resume(&mut self, state: StrategyState) -> Result<(), Error> {
+        state.validate("TreeStrategy", &self.modifiers)?;
+        if state.groups.iter().any(|g| g.len() != 1) {
+            return Err(Error::InvalidCheckpoint("contains groups of modifiers".to_string()));
+        }
+        self.modifiers = state.groups.iter().flatten().cloned().collect();
+        self.resume_point = Some(state);
+        Ok(())
+    }
+}
+
+impl<O> TreeStrategy<O>
+where
+    O: ModifierOrdering<ConfigModifier>,
+{
+    /// Describe the current state of the exploration
+    fn state(
+        &self,
+        stack: &[Stack],
+        mod_sequence: &[ConfigModifier],
+        best_sequence: &[ConfigModifier],
+    ) -> StrategyState {
+        let idx = |m: &ConfigModifier| self.modifiers.iter().position(|x| x == m).unwrap();
+        StrategyState {
+            strategy: "TreeStrategy".to_string(),
+            groups: self.modifiers.iter().map(|m| vec![m.clone()]).collect(),
+            frames: stack
+                .iter()
+                .map(|s| FrameState {
+                    remaining: s.rem_mod.iter().map(idx).collect(),
+                    next: s.cur_idx,
+                })
+                .collect(),
+            sequence: mod_sequence.iter().map(idx).collect(),
+            best_sequence: best_sequence.to_vec(),
+        }
+    }
+}
+
 impl<O> ExhaustiveStrategy for TreeStrategy<O> where O: ModifierOrdering<ConfigModifier> {}
 
+impl<O> PrecedenceStrategy for TreeStrategy<O>
+where
+    O: ModifierOrdering<ConfigModifier>,
+{
+    fn set_precedence(&mut self, precedence: PrecedenceConstraints) {
+        self.precedence = precedence;
+    }
+}
+
 struct Stack {
     pub rem_mod: Vec<ConfigModifier>,
     pub cur_idx: usize,
@@ -22,9 +22,9 @@ use crate::hard_policies::HardPolicy;
 use crate::netsim::config::{Config, ConfigModifier};
 use crate::netsim::Network;
 use crate::optimizers::{Optimizer, OptimizerTRTA};
-use crate::soft_policies::SoftPolicy;
+use crate::soft_policies::{cleanup_only, MinimizeReconfigurationSteps, SoftPolicy};
 use crate::strategies::{Strategy, StrategyTRTA};
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error};
 
 use log::*;
 use std::thread;
@@ -86,7 +86,7 @@ pub fn synthesize(
     let mut strategy = StrategyTRTA::new(net, modifiers, hard_policy, time_limit)?;
 
     // try to solve the problem
-    match strategy.work(Stopper::new()) {
+    match strategy.work(ControlHandle::new()) {
         Ok(sequence) => {
             info!("Found a valid solution!");
             Ok(sequence)
@@ -158,13 +158,14 @@ pub fn synthesize_parallel(
     let modifiers: Vec<ConfigModifier> = patch.modifiers;
 
     // create the atomic bool to communicate when a solution was found
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
 
     let n_threads = n_threads.unwrap_or_else(num_cpus::get);
     info!("Spawning {} threads", n_threads);
 
     let handles = (0..n_threads)
         .map(|_| {
+            // the topology and configuration are shared between the workers until modified
             let n = net.clone();
             let m = modifiers.clone();
             let p = hard_policy.clone();
@@ -271,7 +272,7 @@ pub fn optimize<SP: SoftPolicy + Clone>(
     info!("Solving the problem...");
 
     // try to solve the problem
-    match optimizer.work(Stopper::new()) {
+    match optimizer.work(ControlHandle::new()) {
         Ok((sequence, cost)) => {
             info!("Found a valid solution!");
             Ok((sequence, cost))
@@ -282,3 +283,78 @@ pub fn optimize<SP: SoftPolicy + Clone>(
         }
     }
 }
+
+/// # Synthesize Configuration Updates which reach the final state early
+///
+/// This function uses the [`OptimizerTRTA`](crate::optimizers::Optimizer) with the soft policy
+/// [`MinimizeReconfigurationSteps`], which prefers sequences reaching a forwarding state
+/// equivalent to the final one as early as possible. It returns the sequence, and the position of
+/// the first modifier which is *cleanup only* (see [`cleanup_only`]). All modifiers from this
+/// position onwards do not change the forwarding state anymore, and can be deferred.
+///
+/// ## Usage
+///
+/// ```
+/// use snowcap::hard_policies::*;
+/// use snowcap::optimize_early_completion;
+/// use snowcap::Error;
+/// # use snowcap::example_networks::*;
+///
+/// fn main() -> Result<(), Error> {
+/// # let net = SimpleNet::net(0);
+/// # let initial_config = net.current_config().clone();
+/// # let final_config = SimpleNet::final_config(&net, 0);
+/// # let hard_policy = HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+///     let (sequence, first_cleanup) =
+///         optimize_early_completion(net, initial_config, final_config, hard_policy, None)?;
+///     let (effective, cleanup) = sequence.split_at(first_cleanup);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn optimize_early_completion(
+    mut net: Network,
+    config_a: Config,
+    config_b: Config,
+    hard_policy: HardPolicy,
+    time_limit: Option<Duration>,
+) -> Result<(Vec<ConfigModifier>, usize), Error> {
+    // setup the network and reset the undo tracker
+    net.set_config(&config_a)?;
+    net.clear_undo_stack();
+
+    // setup soft policy
+    let mut fw_state = net.get_forwarding_state();
+    let soft_policy = MinimizeReconfigurationSteps::new(&mut fw_state, &net)
+        .with_final_config(&net, &config_b)?;
+
+    // compute the set of modifiers
+    let patch = config_a.get_diff(&config_b);
+    let modifiers: Vec<ConfigModifier> = patch.modifiers;
+
+    let mut optimizer = OptimizerTRTA::<MinimizeReconfigurationSteps>::new(
+        net.clone(),
+        modifiers,
+        hard_policy,
+        soft_policy,
+        time_limit,
+    )?;
+
+    info!("Solving the problem...");
+
+    // try to solve the problem
+    match optimizer.work(ControlHandle::new()) {
+        Ok((sequence, _)) => {
+            let first_cleanup = cleanup_only(&net, &sequence)?;
+            info!(
+                "Found a valid solution! The last {} modifiers are cleanup only.",
+                sequence.len() - first_cleanup
+            );
+            Ok((sequence, first_cleanup))
+        }
+        Err(e) => {
+            error!("Could not solve the problem: {}", e);
+            Err(e)
+        }
+    }
+}
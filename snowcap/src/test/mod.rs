@@ -15,20 +15,161 @@
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
+#[cfg(test)]
+mod test_analysis;
+#[cfg(test)]
+mod test_centrality;
+#[cfg(test)]
+mod test_checkpoint;
+#[cfg(test)]
+mod test_churn_window;
+#[cfg(test)]
+mod test_cli;
 #[cfg(test)]
 mod test_config;
+mod test_control_handle;
+#[cfg(test)]
+mod test_convergence_time;
+#[cfg(test)]
+mod test_corpus;
+#[cfg(test)]
+mod test_dependencies;
+#[cfg(test)]
+mod test_diagnostics;
+#[cfg(test)]
+mod test_dual_stack;
+#[cfg(test)]
+mod test_ecmp;
+#[cfg(test)]
+mod test_egress;
+#[cfg(test)]
+mod test_example_networks;
+mod test_execution;
+#[cfg(test)]
+mod test_failure;
 #[cfg(test)]
 mod test_forwarding_state;
 #[cfg(test)]
+mod test_freeze_windows;
+#[cfg(test)]
+mod test_graceful_shutdown;
+#[cfg(test)]
+mod test_heatmap;
+#[cfg(test)]
+mod test_igp_decomposition;
+#[cfg(test)]
+mod test_initial_state;
+#[cfg(test)]
+mod test_ltl_ast;
+#[cfg(test)]
+mod test_med;
+#[cfg(test)]
 mod test_network;
 #[cfg(test)]
 mod test_network_complete;
 #[cfg(test)]
+mod test_network_spec;
+#[cfg(test)]
+mod test_ospf;
+#[cfg(test)]
+mod test_parallel_trta;
+#[cfg(test)]
+mod test_parser;
+#[cfg(test)]
+mod test_partial_result;
+#[cfg(test)]
+mod test_path_bounds;
+#[cfg(test)]
+mod test_plan;
+#[cfg(test)]
+mod test_plan_editor;
+#[cfg(test)]
+mod test_policy_templates;
+#[cfg(test)]
+mod test_precedence;
+#[cfg(test)]
+mod test_prefix_range;
+#[cfg(test)]
+mod test_rationale;
+#[cfg(test)]
+mod test_reconfiguration_steps;
+#[cfg(test)]
 mod test_route_map;
 #[cfg(test)]
+mod test_route_reflection;
+#[cfg(test)]
 mod test_router;
 #[cfg(test)]
+mod test_scale;
+#[cfg(test)]
+mod test_scheduler;
+#[cfg(test)]
+mod test_serde;
+#[cfg(test)]
+mod test_session;
+#[cfg(test)]
 mod test_solve_network;
-// NOTE These tests are deactivated, since this feature is temporarily disabled.
-//#[cfg(test)]
-//mod test_transient_behavior;
+#[cfg(test)]
+mod test_state_cache;
+#[cfg(test)]
+mod test_step_shared;
+#[cfg(test)]
+mod test_step_trace;
+#[cfg(test)]
+mod test_strategy_equivalence;
+#[cfg(test)]
+mod test_traffic_matrix;
+#[cfg(all(test, feature = "transient-violation"))]
+mod test_transient;
+#[cfg(test)]
+mod test_transient_behavior;
+#[cfg(test)]
+mod test_windows;
+
+#[cfg(test)]
+use crate::netsim::config::{Config, ConfigExpr::*};
+#[cfg(test)]
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// # Test network
+///
+/// ```text
+/// E1 ---- R1 ---- R2 ---- E2
+/// ```
+///
+/// All links have weight 1. R1 and R2 are connected with an iBGP peer session, and both have an
+/// eBGP session with their external neighbor. No route is advertised yet. The routers are returned
+/// in the order `[r1, r2, e1, e2]`.
+#[cfg(test)]
+fn two_egress_topology() -> (Network, [RouterId; 4]) {
+    let mut net = Network::new();
+    let r1 = net.add_router("R1");
+    let r2 = net.add_router("R2");
+    let e1 = net.add_external_router("E1", AsId(65101));
+    let e2 = net.add_external_router("E2", AsId(65102));
+
+    let mut c = Config::new();
+    for (a, b) in vec![(r1, r2), (r1, e1), (r2, e2)] {
+        net.add_link(a, b);
+        c.add(IgpLinkWeight { source: a, target: b, weight: 1.0 }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight: 1.0 }).unwrap();
+    }
+    c.add(BgpSession { source: r1, target: r2, session_type: IBgpPeer }).unwrap();
+    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r2, target: e2, session_type: EBgp }).unwrap();
+    net.set_config(&c).unwrap();
+
+    (net, [r1, r2, e1, e2])
+}
+
+/// The network of [`two_egress_topology`], where both external routers advertise prefix 0 with
+/// the same AS path length. Both R1 and R2 prefer their own eBGP route.
+#[cfg(test)]
+fn two_egress_net() -> (Network, [RouterId; 4]) {
+    let (mut net, [r1, r2, e1, e2]) = two_egress_topology();
+    net.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None)
+        .unwrap();
+    net.advertise_external_route(e2, Prefix(0), vec![AsId(65102), AsId(65200)], None, None)
+        .unwrap();
+    (net, [r1, r2, e1, e2])
+}
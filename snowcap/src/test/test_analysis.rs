@@ -0,0 +1,69 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate::analysis::*;
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+
+use assert_approx_eq::assert_approx_eq;
+
+#[test]
+fn test_estimate_unconstrained() {
+    // without any condition, every ordering is valid, and the tree is uniform. Hence, the estimate
+    // is exact.
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+    let hard_policy = HardPolicy::globally(vec![]);
+
+    let n = modifiers.len();
+    let mut expected_states = 0.0;
+    let mut nodes_at_depth = 1.0;
+    for k in 0..n {
+        nodes_at_depth *= (n - k) as f64;
+        expected_states += nodes_at_depth;
+    }
+
+    let estimate = estimate_search_space(&net, &modifiers, &hard_policy, 5, Some(42)).unwrap();
+    assert_eq!(estimate.num_probes, 5);
+    assert_approx_eq!(estimate.expected_states, expected_states);
+    assert_approx_eq!(estimate.expected_solutions, nodes_at_depth);
+    assert_approx_eq!(estimate.std_dev, 0.0);
+    assert!(estimate.is_feasible(expected_states));
+    assert!(!estimate.is_feasible(expected_states - 1.0));
+}
+
+#[test]
+fn test_estimate_chain_gadget() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let unconstrained =
+        estimate_search_space(&net, &modifiers, &HardPolicy::globally(vec![]), 1, None).unwrap();
+
+    let estimate = estimate_search_space(&net, &modifiers, &hard_policy, 100, Some(42)).unwrap();
+    assert!(estimate.expected_states >= 1.0);
+    // the hard policy only removes states from the tree
+    assert!(estimate.expected_states < unconstrained.expected_states);
+
+    // the same seed produces the same estimate
+    let again = estimate_search_space(&net, &modifiers, &hard_policy, 100, Some(42)).unwrap();
+    assert_eq!(estimate, again);
+}
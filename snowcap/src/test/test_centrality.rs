@@ -0,0 +1,129 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::netsim::centrality::GraphAnalysis;
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier::*};
+use crate::netsim::{AsId, Network, RouterId};
+
+/// # Test network
+///
+/// ```text
+/// A ---- B ---- C ---- D
+///               |     /
+///               |    /
+///               E --´
+/// ```
+///
+/// Additionally, the external router X is connected to A, and all links have weight 1.
+fn get_test_net() -> (Network, Vec<RouterId>) {
+    let mut net = Network::new();
+    let a = net.add_router("A");
+    let b = net.add_router("B");
+    let c = net.add_router("C");
+    let d = net.add_router("D");
+    let e = net.add_router("E");
+    let x = net.add_external_router("X", AsId(65101));
+
+    let mut config = Config::new();
+    for (s, t) in vec![(a, b), (b, c), (c, d), (c, e), (d, e), (x, a)] {
+        net.add_link(s, t);
+        config.add(IgpLinkWeight { source: s, target: t, weight: 1.0 }).unwrap();
+        config.add(IgpLinkWeight { source: t, target: s, weight: 1.0 }).unwrap();
+    }
+    net.set_config(&config).unwrap();
+    (net, vec![a, b, c, d, e, x])
+}
+
+#[test]
+fn test_from_network() {
+    let (net, ids) = get_test_net();
+    let g = net.igp_analysis();
+    // the external router is not part of the analysis
+    assert_eq!(g.nodes().len(), 5);
+    assert_eq!(g.degree(ids[0]), 1);
+    assert_eq!(g.degree(ids[2]), 3);
+    assert_eq!(g.degree(ids[5]), 0);
+    assert_eq!(g.distances(ids[0])[&ids[4]], 3.0);
+}
+
+#[test]
+fn test_links_without_weight() {
+    let (mut net, ids) = get_test_net();
+    let (c, d) = (ids[2], ids[3]);
+    net.apply_modifier(&Remove(IgpLinkWeight { source: c, target: d, weight: 1.0 })).unwrap();
+    // the link is still used, as the other direction has a finite weight
+    assert_eq!(net.igp_analysis().degree(c), 3);
+
+    net.apply_modifier(&Remove(IgpLinkWeight { source: d, target: c, weight: 1.0 })).unwrap();
+    assert_eq!(net.igp_analysis().degree(c), 2);
+    assert_eq!(net.igp_analysis().articulation_points(), vec![ids[1], ids[2], ids[4]]);
+}
+
+#[test]
+fn test_articulation_points() {
+    let (net, ids) = get_test_net();
+    assert_eq!(net.igp_analysis().articulation_points(), vec![ids[1], ids[2]]);
+}
+
+#[test]
+fn test_betweenness_and_closeness() {
+    let (net, ids) = get_test_net();
+    let (a, b, c, d, e) = (ids[0], ids[1], ids[2], ids[3], ids[4]);
+    let g = net.igp_analysis();
+
+    let betweenness = g.betweenness();
+    assert_eq!(betweenness[&a], 0.0);
+    assert_eq!(betweenness[&b], 3.0);
+    assert_eq!(betweenness[&c], 4.0);
+    assert_eq!(betweenness[&d], 0.0);
+    assert_eq!(betweenness[&e], 0.0);
+
+    let closeness = g.closeness();
+    assert_eq!(closeness[&c], 0.8);
+    assert!(closeness[&c] > closeness[&b]);
+    assert!(closeness[&b] > closeness[&a]);
+}
+
+#[test]
+fn test_betweenness_equal_cost() {
+    // square, where both B and D are on one of two shortest paths between A and C.
+    let (a, b, c, d) = (0.into(), 1.into(), 2.into(), 3.into());
+    let g = GraphAnalysis::new(
+        vec![a, b, c, d],
+        vec![(a, b, 1.0), (b, c, 2.0), (c, d, 1.0), (d, a, 2.0)],
+    );
+    let betweenness = g.betweenness();
+    assert_eq!(betweenness[&b], 0.5);
+    assert_eq!(betweenness[&d], 0.5);
+    // similarly, A and C are on one of two shortest paths between B and D
+    assert_eq!(betweenness[&a], 0.5);
+    assert_eq!(betweenness[&c], 0.5);
+}
+
+#[test]
+fn test_k_core() {
+    let (net, ids) = get_test_net();
+    let g = net.igp_analysis();
+    let cores = g.core_numbers();
+    assert_eq!(cores[&ids[0]], 1);
+    assert_eq!(cores[&ids[1]], 1);
+    assert_eq!(cores[&ids[2]], 2);
+    assert_eq!(g.k_core(2), vec![ids[2], ids[3], ids[4]]);
+    assert_eq!(g.k_core(3), vec![]);
+    assert_eq!(g.k_core(0).len(), 5);
+}
@@ -0,0 +1,127 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::modifier_ordering::NoOrdering;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::Network;
+use crate::plan_editor::PlanEditor;
+use crate::strategies::{
+    CheckpointStrategy, PushBackTreeStrategy, Strategy, StrategyState, StrategyTRTA, TreeStrategy,
+};
+use crate::{ControlHandle, Error};
+
+use std::time::Duration;
+
+fn problem<N: ExampleNetwork>() -> (Network, Vec<ConfigModifier>, HardPolicy) {
+    let net = N::net(0);
+    let final_config = N::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    (net, modifiers, hard_policy)
+}
+
+/// Repeatedly abort the strategy, and resume on a fresh strategy from the serialized checkpoint,
+/// until a solution is found. The time until the strategy is aborted doubles after every attempt,
+/// such that even modifiers with a long convergence are eventually checked.
+fn interrupt_and_resume<S: CheckpointStrategy, N: ExampleNetwork>() {
+    let (net, modifiers, hard_policy) = problem::<N>();
+    let mut strategy = S::new(net.clone(), modifiers.clone(), hard_policy.clone(), None).unwrap();
+    let mut budget = Duration::from_millis(1);
+    let sequence = loop {
+        let abort = ControlHandle::new();
+        let timer = abort.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(budget);
+            timer.send_stop();
+        });
+        match strategy.work(abort) {
+            Ok(sequence) => break sequence,
+            Err(Error::Abort) => {
+                let json = serde_json::to_string(&strategy.checkpoint()).unwrap();
+                let state: StrategyState = serde_json::from_str(&json).unwrap();
+                assert_eq!(state, strategy.checkpoint());
+                strategy =
+                    S::new(net.clone(), modifiers.clone(), hard_policy.clone(), None).unwrap();
+                strategy.resume(state).unwrap();
+                budget *= 2;
+            }
+            Err(e) => panic!("Unexpected error: {}", e),
+        }
+    };
+    assert_eq!(sequence.len(), modifiers.len());
+    let editor = PlanEditor::new(&net, sequence, hard_policy).unwrap();
+    assert!(editor.is_valid());
+}
+
+#[test]
+fn resume_tree_strategy() {
+    interrupt_and_resume::<TreeStrategy<NoOrdering>, ChainGadget<Repetition5>>();
+    interrupt_and_resume::<TreeStrategy<NoOrdering>, DifficultGadgetMinimal>();
+}
+
+#[test]
+fn resume_push_back_tree_strategy() {
+    interrupt_and_resume::<PushBackTreeStrategy<NoOrdering>, ChainGadget<Repetition5>>();
+    interrupt_and_resume::<PushBackTreeStrategy<NoOrdering>, DifficultGadgetMinimal>();
+}
+
+#[test]
+fn resume_strategy_trta() {
+    interrupt_and_resume::<StrategyTRTA, ChainGadget<Repetition5>>();
+    interrupt_and_resume::<StrategyTRTA, DifficultGadgetMinimal>();
+}
+
+#[test]
+fn checkpoint_before_work_is_root() {
+    let (net, modifiers, hard_policy) = problem::<ChainGadget<Repetition3>>();
+    let strategy =
+        TreeStrategy::<NoOrdering>::new(net, modifiers.clone(), hard_policy, None).unwrap();
+    let state = strategy.checkpoint();
+    assert_eq!(state.strategy, "TreeStrategy");
+    assert_eq!(state.groups.len(), modifiers.len());
+    assert_eq!(state.frames.len(), 1);
+    assert_eq!(state.frames[0].remaining.len(), modifiers.len());
+    assert!(state.sequence.is_empty());
+}
+
+#[test]
+fn resume_rejects_invalid_checkpoint() {
+    let (net, modifiers, hard_policy) = problem::<ChainGadget<Repetition3>>();
+    let tree =
+        TreeStrategy::<NoOrdering>::new(net.clone(), modifiers.clone(), hard_policy.clone(), None)
+            .unwrap();
+    let mut trta =
+        StrategyTRTA::new(net.clone(), modifiers.clone(), hard_policy.clone(), None).unwrap();
+
+    // created by a different strategy
+    assert!(matches!(trta.resume(tree.checkpoint()), Err(Error::InvalidCheckpoint(_))));
+
+    // created for a different problem
+    let mut state = trta.checkpoint();
+    state.groups.pop();
+    assert!(matches!(trta.resume(state), Err(Error::InvalidCheckpoint(_))));
+
+    // invalid stack
+    let mut state = trta.checkpoint();
+    state.sequence.push(0);
+    assert!(matches!(trta.resume(state), Err(Error::InvalidCheckpoint(_))));
+}
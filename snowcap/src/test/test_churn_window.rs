@@ -0,0 +1,105 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use super::two_egress_net;
+use crate::netsim::config::{ConfigExpr::*, ConfigModifier::*};
+use crate::netsim::{BgpSessionType::*, Network, Prefix, RouterId};
+use crate::soft_policies::{MinimizeConfigChurnWindow, PrefixWeights, SoftPolicy};
+
+/// The networks of [`two_egress_net`]. In the final state, the session between R1 and E1 is
+/// removed, and in the intermediate state, the session between R2 and E2 is removed, such that R2
+/// uses the temporary path via E1.
+fn get_test_nets() -> (Network, Network, Network, [RouterId; 4]) {
+    let (net, [r1, r2, e1, e2]) = two_egress_net();
+
+    let mut intermediate = net.clone();
+    intermediate
+        .apply_modifier(&Remove(BgpSession { source: r2, target: e2, session_type: EBgp }))
+        .unwrap();
+    let mut final_net = net.clone();
+    final_net
+        .apply_modifier(&Remove(BgpSession { source: r1, target: e1, session_type: EBgp }))
+        .unwrap();
+
+    (net, intermediate, final_net, [r1, r2, e1, e2])
+}
+
+#[test]
+fn test_churn_window() {
+    let (net, intermediate, final_net, ids) = get_test_nets();
+    let (r1, r2) = (ids[0], ids[1]);
+    let mut sp = MinimizeConfigChurnWindow::new(&mut net.get_forwarding_state(), &net)
+        .with_final_state(&mut final_net.get_forwarding_state())
+        .with_horizon(2);
+    assert_eq!(sp.cost(), 0.0);
+
+    // R2 is on a temporary path, and the cost grows with the window
+    sp.update(&mut intermediate.get_forwarding_state(), &intermediate);
+    assert_eq!(sp.window(r2, Prefix(0)), 1);
+    assert_eq!(sp.window(r1, Prefix(0)), 0);
+    assert_eq!(sp.cost(), 0.25);
+    sp.update(&mut intermediate.get_forwarding_state(), &intermediate);
+    assert_eq!(sp.window(r2, Prefix(0)), 2);
+    assert_eq!(sp.cost(), 0.5);
+    // the cost is bounded by the horizon
+    sp.update(&mut intermediate.get_forwarding_state(), &intermediate);
+    assert_eq!(sp.window(r2, Prefix(0)), 3);
+    assert_eq!(sp.cost(), 0.5);
+
+    // the final path is not temporary
+    sp.update(&mut final_net.get_forwarding_state(), &final_net);
+    assert_eq!(sp.window(r2, Prefix(0)), 0);
+    assert_eq!(sp.cost(), 0.0);
+}
+
+#[test]
+fn test_churn_window_without_final_state() {
+    let (net, _, final_net, ids) = get_test_nets();
+    let mut sp = MinimizeConfigChurnWindow::new(&mut net.get_forwarding_state(), &net);
+    sp.update(&mut final_net.get_forwarding_state(), &final_net);
+    assert_eq!(sp.window(ids[0], Prefix(0)), 1);
+    assert_eq!(sp.cost(), 0.05);
+
+    // with the final config, the same step is free
+    let final_config = final_net.current_config().clone();
+    let mut sp = MinimizeConfigChurnWindow::new(&mut net.get_forwarding_state(), &net)
+        .with_final_config(&net, &final_config)
+        .unwrap();
+    sp.update(&mut final_net.get_forwarding_state(), &final_net);
+    assert_eq!(sp.cost(), 0.0);
+}
+
+#[test]
+fn test_churn_window_weights() {
+    let (net, intermediate, final_net, ids) = get_test_nets();
+    let mut weights = PrefixWeights::new(1.0);
+    weights.set(ids[1], Prefix(0), 3.0);
+    let mut sp = MinimizeConfigChurnWindow::new(&mut net.get_forwarding_state(), &net)
+        .with_final_state(&mut final_net.get_forwarding_state())
+        .with_weights(weights)
+        .with_horizon(1);
+    sp.update(&mut intermediate.get_forwarding_state(), &intermediate);
+    assert_eq!(sp.cost(), 0.75);
+
+    // the parameters are kept when resetting the soft policy
+    sp.reset(&mut intermediate.get_forwarding_state(), &intermediate);
+    assert_eq!(sp.cost(), 0.0);
+    assert_eq!(sp.prefix_weights().get(ids[1], Prefix(0)), 3.0);
+    sp.update(&mut final_net.get_forwarding_state(), &final_net);
+    assert_eq!(sp.cost(), 0.0);
+}
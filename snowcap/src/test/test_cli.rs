@@ -0,0 +1,246 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for the translation of modifiers into device commands
+
+#![cfg(test)]
+use crate::netsim::cli::*;
+use crate::netsim::config::{self, Config, ConfigExpr::*, ConfigModifier::*};
+use crate::netsim::route_map::*;
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// Network:
+///
+/// ```text
+///       1
+/// r1 ------- r2
+///  |
+///  | 1
+///  |
+/// e1
+/// ```
+fn small_net() -> (Network, [RouterId; 3]) {
+    let mut net = Network::new();
+    let r1 = net.add_router("r1");
+    let r2 = net.add_router("r2");
+    let e1 = net.add_external_router("e1", AsId(65100));
+
+    let mut c = Config::new();
+    for (a, b) in vec![(r1, r2), (r1, e1)] {
+        net.add_link(a, b);
+        c.add(IgpLinkWeight { source: a, target: b, weight: 1.0 }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight: 1.0 }).unwrap();
+    }
+    net.set_config(&c).unwrap();
+
+    (net, [r1, r2, e1])
+}
+
+fn cmds(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn link_weight() {
+    let (net, [r1, r2, e1]) = small_net();
+    let modifier = Update {
+        from: IgpLinkWeight { source: r1, target: r2, weight: 1.0 },
+        to: IgpLinkWeight { source: r1, target: r2, weight: 5.0 },
+    };
+    assert_eq!(
+        device_commands(&net, &modifier, CliFlavor::Frr).unwrap(),
+        vec![(r1, cmds(&["interface eth0", "ip ospf cost 5", "exit"]))]
+    );
+    assert_eq!(
+        modifier_to_cli(&net, &modifier, CliFlavor::CiscoIos).unwrap(),
+        "! r1\nconfigure terminal\ninterface GigabitEthernet0/0\nip ospf cost 5\nexit\nend\n"
+    );
+
+    // the link towards the external router is not part of OSPF
+    let modifier = Insert(IgpLinkWeight { source: r1, target: e1, weight: 1.0 });
+    assert_eq!(
+        device_commands(&net, &modifier, CliFlavor::Frr).unwrap(),
+        vec![(r1, cmds(&["interface eth1", "ip address 10.1.1.1/24", "exit"]))]
+    );
+    let modifier = Insert(IgpLinkWeight { source: r2, target: r1, weight: 1.0 });
+    assert_eq!(
+        device_commands(&net, &modifier, CliFlavor::CiscoIos).unwrap(),
+        vec![(
+            r2,
+            cmds(&[
+                "interface GigabitEthernet0/0",
+                "ip address 10.1.0.2 255.255.255.0",
+                "ip ospf 1 area 0",
+                "ip ospf cost 1",
+                "exit"
+            ])
+        )]
+    );
+}
+
+#[test]
+fn bgp_session() {
+    let (net, [r1, r2, e1]) = small_net();
+
+    let modifier = Insert(BgpSession { source: r1, target: r2, session_type: IBgpClient });
+    assert_eq!(
+        device_commands(&net, &modifier, CliFlavor::Frr).unwrap(),
+        vec![
+            (
+                r1,
+                cmds(&[
+                    "router bgp 65001",
+                    "neighbor 10.0.1.1 remote-as 65001",
+                    "neighbor 10.0.1.1 update-source lo",
+                    "neighbor 10.0.1.1 peer-group internal",
+                    "neighbor 10.0.1.1 route-reflector-client",
+                    "exit"
+                ])
+            ),
+            (
+                r2,
+                cmds(&[
+                    "router bgp 65001",
+                    "neighbor 10.0.0.1 remote-as 65001",
+                    "neighbor 10.0.0.1 update-source lo",
+                    "neighbor 10.0.0.1 peer-group internal",
+                    "exit"
+                ])
+            ),
+        ]
+    );
+
+    // eBGP sessions use the addresses of the link
+    let modifier = Insert(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let result = device_commands(&net, &modifier, CliFlavor::Frr).unwrap();
+    assert_eq!(result[0].1[1], "neighbor 10.1.1.2 remote-as 65100");
+    assert_eq!(result[0].1[2], "neighbor 10.1.1.2 peer-group external");
+    assert_eq!(result[1].1[0], "router bgp 65100");
+    assert_eq!(result[1].1[1], "neighbor 10.1.1.1 remote-as 65001");
+
+    let modifier = Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let frr = device_commands(&net, &modifier, CliFlavor::Frr).unwrap();
+    assert_eq!(
+        frr[0].1,
+        cmds(&["router bgp 65001", "no neighbor 10.1.1.2 remote-as 65100", "exit"])
+    );
+    let cisco = device_commands(&net, &modifier, CliFlavor::CiscoIos).unwrap();
+    assert_eq!(cisco[0].1, cmds(&["router bgp 65001", "no neighbor 10.1.1.2", "exit"]));
+
+    // changing the session type only changes the route reflector client
+    let modifier = Update {
+        from: BgpSession { source: r1, target: r2, session_type: IBgpPeer },
+        to: BgpSession { source: r1, target: r2, session_type: IBgpClient },
+    };
+    assert_eq!(
+        device_commands(&net, &modifier, CliFlavor::Frr).unwrap(),
+        vec![(r1, cmds(&["router bgp 65001", "neighbor 10.0.1.1 route-reflector-client", "exit"]))]
+    );
+}
+
+#[test]
+fn route_map() {
+    let (net, [r1, _, e1]) = small_net();
+    let map =
+        RouteMapBuilder::new().order(10).allow().match_neighbor(e1).set_local_pref(200).build();
+    let modifier = Insert(BgpRouteMap {
+        router: r1,
+        direction: RouteMapDirection::Incoming,
+        map: map.clone(),
+    });
+
+    let frr = device_commands(&net, &modifier, CliFlavor::Frr).unwrap();
+    assert_eq!(frr.len(), 1);
+    assert_eq!(
+        frr[0].1,
+        cmds(&[
+            "route-map r1-in permit 10",
+            "match peer 102.0.0.1",
+            "set local-preference 200",
+            "exit",
+            "route-map r1-in permit 65535",
+            "exit",
+            "router bgp 65001",
+            "address-family ipv4 unicast",
+            "neighbor internal route-map r1-in in",
+            "neighbor external route-map r1-in in",
+            "exit-address-family",
+            "exit"
+        ])
+    );
+
+    // matching on the neighbor is not available in Cisco IOS
+    let cisco = device_commands(&net, &modifier, CliFlavor::CiscoIos).unwrap();
+    assert!(cisco[0].1[1].starts_with("! not supported: match"));
+
+    // updates only change the modified statements
+    let new_map =
+        RouteMapBuilder::new().order(10).allow().match_neighbor(e1).set_local_pref(300).build();
+    let modifier = Update {
+        from: BgpRouteMap { router: r1, direction: RouteMapDirection::Incoming, map },
+        to: BgpRouteMap { router: r1, direction: RouteMapDirection::Incoming, map: new_map },
+    };
+    assert_eq!(
+        device_commands(&net, &modifier, CliFlavor::Frr).unwrap(),
+        vec![(
+            r1,
+            cmds(&[
+                "route-map r1-in permit 10",
+                "no set local-preference 200",
+                "set local-preference 300",
+                "exit"
+            ])
+        )]
+    );
+}
+
+#[test]
+fn static_route() {
+    let (mut net, [r1, r2, _]) = small_net();
+    net.set_prefix_range(Prefix(0), "200.0.0.0/16".parse().unwrap());
+    let modifier = Insert(StaticRoute { router: r2, prefix: Prefix(0), target: r1 });
+    assert_eq!(
+        config::modifier_to_frr(&net, &modifier).unwrap(),
+        "! r2\nconfigure terminal\nip route 200.0.0.0/16 10.1.0.1\nend\n"
+    );
+    assert_eq!(
+        config::modifier_to_cisco(&net, &modifier).unwrap(),
+        "! r2\nconfigure terminal\nip route 200.0.0.0 255.255.0.0 10.1.0.1\nend\n"
+    );
+
+    // prefixes without an address range are written as placeholder
+    let modifier = Remove(StaticRoute { router: r2, prefix: Prefix(1), target: r1 });
+    assert_eq!(
+        device_commands(&net, &modifier, CliFlavor::Frr).unwrap(),
+        vec![(r2, cmds(&["no ip route <prefix 1> 10.1.0.1"]))]
+    );
+}
+
+#[test]
+fn unsupported() {
+    let (net, [r1, _, e1]) = small_net();
+    let modifier = Insert(BgpGracefulShutdown { router: r1, neighbor: e1 });
+    let result = config::modifier_to_frr(&net, &modifier).unwrap();
+    let lines: Vec<&str> = result.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "! r1");
+    assert!(lines[2].starts_with("! not supported: "));
+
+    // infinite link weights require no commands
+    let modifier = Insert(IgpLinkWeight { source: r1, target: e1, weight: f32::INFINITY });
+    assert_eq!(config::modifier_to_frr(&net, &modifier).unwrap(), "");
+}
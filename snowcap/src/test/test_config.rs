@@ -16,9 +16,11 @@
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
 use crate::netsim::bgp::BgpSessionType::*;
-use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier::*, ConfigPatch};
+use crate::netsim::config::{
+    Config, ConfigExpr::*, ConfigMeta, ConfigModifier::*, ConfigPatch, NamedConfigModifier,
+};
 use crate::netsim::route_map::*;
-use crate::netsim::{Prefix, RouterId};
+use crate::netsim::{AsId, Network, NetworkError, Prefix, RouterId};
 #[test]
 fn test_config_diff() {
     let mut c1 = Config::new();
@@ -318,3 +320,115 @@ fn test_config_undo_wrong_patch() {
     c.apply_patch(&patch).unwrap_err();
     assert_eq!(c, c_before);
 }
+
+#[test]
+fn test_config_meta() {
+    let r0: RouterId = 0.into();
+    let r1: RouterId = 1.into();
+    let r2: RouterId = 2.into();
+
+    let sess01 = BgpSession { source: r0, target: r1, session_type: IBgpPeer };
+    let sess02 = BgpSession { source: r0, target: r2, session_type: IBgpPeer };
+    let weight = |w| IgpLinkWeight { source: r0, target: r1, weight: w };
+
+    let mut c1 = Config::new();
+    c1.add_annotated(sess01.clone(), ConfigMeta::named("old session")).unwrap();
+    c1.add(weight(1.0)).unwrap();
+
+    let mut c2 = Config::new();
+    let meta_sess = ConfigMeta::named("new session").ticket("NET-1");
+    let meta_weight = ConfigMeta::named("cost out").comment("drain the link");
+    c2.add_annotated(sess02.clone(), meta_sess.clone()).unwrap();
+    c2.add_annotated(weight(10.0), meta_weight.clone()).unwrap();
+    assert_eq!(meta_sess.to_string(), "new session [NET-1]");
+    assert_eq!(meta_weight.to_string(), "cost out \"drain the link\"");
+
+    // metadata does not influence the comparison
+    let mut c3 = c2.clone();
+    c3.annotate(sess02.key(), ConfigMeta::default());
+    assert_eq!(c2, c3);
+
+    // metadata survives diffing, preferring the target configuration
+    let patch = c1.get_diff(&c2);
+    assert_eq!(patch.modifiers.len(), 3);
+    assert_eq!(patch.meta.len(), 3);
+    assert_eq!(patch.meta.get(&sess01.key()), Some(&ConfigMeta::named("old session")));
+    assert_eq!(patch.meta.get(&sess02.key()), Some(&meta_sess));
+    assert_eq!(patch.meta.get(&weight(1.0).key()), Some(&meta_weight));
+
+    // and is applied together with the patch
+    let mut c = c1.clone();
+    c.apply_patch(&patch).unwrap();
+    assert_eq!(c, c2);
+    assert_eq!(c.get_meta(&sess02.key()), Some(&meta_sess));
+    assert_eq!(c.get_meta(&weight(10.0).key()), Some(&meta_weight));
+}
+
+#[test]
+fn test_named_modifier() {
+    let mut net = Network::new();
+    let r1 = net.add_router("r1");
+    let r2 = net.add_router("r2");
+    let e1 = net.add_external_router("e1", AsId(65101));
+
+    let map = |neighbor: RouterId, next_hop: RouterId| {
+        RouteMapBuilder::new()
+            .order(10)
+            .allow()
+            .match_neighbor(neighbor)
+            .set_next_hop(next_hop)
+            .build()
+    };
+    let modifiers = vec![
+        Insert(BgpSession { source: r1, target: e1, session_type: EBgp }),
+        Remove(BgpRouteMap {
+            router: r2,
+            direction: RouteMapDirection::Incoming,
+            map: map(r1, r2),
+        }),
+        Update {
+            from: IgpLinkWeight { source: r2, target: r1, weight: 1.0 },
+            to: IgpLinkWeight { source: r2, target: r1, weight: 5.0 },
+        },
+    ];
+    let named: Vec<NamedConfigModifier> =
+        modifiers.iter().map(|m| m.with_names(&net).unwrap()).collect();
+    assert_eq!(named[0].routers, vec!["r1".to_string(), "e1".to_string()]);
+    assert_eq!(named[1].routers, vec!["r2".to_string(), "r1".to_string()]);
+    assert_eq!(named[2].router_names(), vec!["r2", "r1"]);
+
+    // store the sequence, and replay it on the same network, which is built in a different order
+    let json = serde_json::to_string(&named).unwrap();
+    let restored: Vec<NamedConfigModifier> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, named);
+
+    let mut rebuilt = Network::new();
+    let e1 = rebuilt.add_external_router("e1", AsId(65101));
+    let r2 = rebuilt.add_router("r2");
+    let r1 = rebuilt.add_router("r1");
+    let replayed: Vec<_> = restored.iter().map(|m| m.to_modifier(&rebuilt).unwrap()).collect();
+    assert_eq!(
+        replayed,
+        vec![
+            Insert(BgpSession { source: r1, target: e1, session_type: EBgp }),
+            Remove(BgpRouteMap {
+                router: r2,
+                direction: RouteMapDirection::Incoming,
+                map: map(r1, r2)
+            }),
+            Update {
+                from: IgpLinkWeight { source: r2, target: r1, weight: 1.0 },
+                to: IgpLinkWeight { source: r2, target: r1, weight: 5.0 },
+            },
+        ]
+    );
+
+    // routers must exist in both networks
+    let mut other = Network::new();
+    other.add_router("r1");
+    assert!(
+        matches!(named[0].to_modifier(&other), Err(NetworkError::DeviceNameNotFound(n)) if n == "e1")
+    );
+    let missing = Insert(RedistributeStatic { router: 10.into() });
+    assert!(matches!(missing.with_names(&net), Err(NetworkError::DeviceNotFound(_))));
+}
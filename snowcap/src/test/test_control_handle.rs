@@ -0,0 +1,150 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::modifier_ordering::*;
+use crate::optimizers::*;
+use crate::permutators::*;
+use crate::soft_policies::*;
+use crate::strategies::*;
+use crate::{ControlHandle, Error, Progress};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn test_pause_resume() {
+    let handle = ControlHandle::new();
+    let done = Arc::new(AtomicBool::new(false));
+
+    handle.pause();
+    assert!(handle.is_paused());
+    let worker = {
+        let handle = handle.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            handle.wait_while_paused();
+            done.store(true, Ordering::SeqCst);
+        })
+    };
+    thread::sleep(Duration::from_millis(50));
+    assert!(!done.load(Ordering::SeqCst));
+
+    handle.resume();
+    worker.join().unwrap();
+    assert!(done.load(Ordering::SeqCst));
+    assert!(!handle.is_paused());
+    assert!(!handle.is_stop());
+}
+
+#[test]
+fn test_stop_while_paused() {
+    let handle = ControlHandle::new();
+    handle.pause();
+    let worker = {
+        let mut handle = handle.clone();
+        thread::spawn(move || loop {
+            if handle.try_is_stop().unwrap_or(false) {
+                break;
+            }
+        })
+    };
+    thread::sleep(Duration::from_millis(50));
+    handle.send_stop();
+    worker.join().unwrap();
+    assert!(handle.is_paused());
+}
+
+#[test]
+fn test_soft_deadline() {
+    let net = SimpleNet::net(0);
+    let cf = SimpleNet::final_config(&net, 0);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    let handle = ControlHandle::new();
+    assert!(!handle.time_is_up(None));
+    handle.set_soft_deadline(Some(SystemTime::now() - Duration::from_secs(1)));
+    assert!(handle.time_is_up(None));
+
+    // the strategy returns a partial result instead of aborting
+    match PermutationStrategy::<HeapsPermutator<NoOrdering>>::synthesize(
+        net.clone(),
+        cf.clone(),
+        hard_policy.clone(),
+        None,
+        handle.clone(),
+    ) {
+        Err(Error::Timeout(partial)) => assert!(!partial.is_complete()),
+        r => panic!("Unexpected result: {:?}", r.map(|s| s.len())),
+    }
+
+    // without a deadline, the strategy finds a solution
+    handle.set_soft_deadline(None);
+    assert!(handle.soft_deadline().is_none());
+    PermutationStrategy::<HeapsPermutator<NoOrdering>>::synthesize(
+        net,
+        cf,
+        hard_policy,
+        None,
+        handle,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_progress() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let num_mods = net.current_config().get_diff(&cf).modifiers.len();
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    let handle = ControlHandle::new();
+    assert_eq!(handle.progress(), Progress::default());
+
+    // the strategy attaches the handle to the network, which records every applied modifier
+    TreeStrategy::<SimpleOrdering>::synthesize(
+        net.clone(),
+        cf.clone(),
+        hard_policy.clone(),
+        None,
+        handle.clone(),
+    )
+    .unwrap();
+    let progress = handle.progress();
+    assert!(progress.num_states >= num_mods);
+    assert_eq!(progress.best_cost, None);
+
+    // the optimizer records the cost of the solution
+    let handle = ControlHandle::new();
+    let soft_policy = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
+    let (_, cost) = TreeOptimizer::<MinimizeTrafficShift>::synthesize(
+        net,
+        cf,
+        hard_policy,
+        soft_policy,
+        None,
+        handle.clone(),
+    )
+    .unwrap();
+    assert_eq!(handle.progress().best_cost, Some(cost));
+}
@@ -0,0 +1,164 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for the estimation of the convergence time
+
+#![cfg(test)]
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId, DEFAULT_LINK_DELAY};
+use crate::soft_policies::{
+    compute_cost, MinimizeTotalConvergenceTime, SoftPolicy, DEFAULT_CONVERGENCE_REFERENCE,
+};
+
+/// Network:
+///
+/// ```text
+///              1        5
+/// e1 ---- r1 ----- r2 ----- r3 ---- e3
+/// ```
+///
+/// `r2` is the route reflector of `r1` and `r3`. Both `e1` and `e3` advertise two prefixes.
+fn line_net() -> (Network, [RouterId; 4]) {
+    let mut n = Network::new();
+    let mut c = Config::new();
+
+    let r1 = n.add_router("r1");
+    let r2 = n.add_router("r2");
+    let r3 = n.add_router("r3");
+    let e1 = n.add_external_router("e1", AsId(65101));
+    let e3 = n.add_external_router("e3", AsId(65103));
+
+    for (a, b, weight) in vec![(r1, r2, 1.0), (r2, r3, 5.0), (r1, e1, 1.0), (r3, e3, 1.0)] {
+        n.add_link(a, b);
+        c.add(IgpLinkWeight { source: a, target: b, weight }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight }).unwrap();
+    }
+
+    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r2, target: r1, session_type: IBgpClient }).unwrap();
+    c.add(BgpSession { source: r2, target: r3, session_type: IBgpClient }).unwrap();
+
+    n.set_config(&c).unwrap();
+
+    for p in vec![Prefix(0), Prefix(1)] {
+        n.advertise_external_route(e1, p, vec![AsId(65101), AsId(65200)], None, None).unwrap();
+        n.advertise_external_route(e3, p, vec![AsId(65103), AsId(65200)], None, None).unwrap();
+    }
+
+    (n, [r1, r2, r3, e1])
+}
+
+fn remove_session(r1: RouterId, e1: RouterId) -> ConfigModifier {
+    ConfigModifier::Remove(BgpSession { source: r1, target: e1, session_type: EBgp })
+}
+
+#[test]
+fn link_delays() {
+    let (mut net, [r1, r2, r3, _]) = line_net();
+    assert_eq!(net.get_default_link_delay(), DEFAULT_LINK_DELAY);
+    net.set_link_delay(r1, r2, 5.0);
+    assert_eq!(net.get_link_delay(r1, r2), 5.0);
+    assert_eq!(net.get_link_delay(r2, r1), 5.0);
+    assert_eq!(net.get_link_delay(r2, r3), DEFAULT_LINK_DELAY);
+    net.set_default_link_delay(2.0);
+    assert_eq!(net.get_link_delay(r2, r3), 2.0);
+    assert_eq!(net.get_link_delay(r1, r2), 5.0);
+}
+
+#[test]
+fn convergence_time() {
+    let (net, [r1, r2, _, e1]) = line_net();
+    let modifier = remove_session(r1, e1);
+
+    let mut n = net.clone();
+    let time = n.apply_modifier_timed(&modifier).unwrap();
+    assert!(time > 0.0);
+    assert_eq!(n.get_convergence_time(), time);
+
+    // the estimate is consistent with undo
+    n.undo_action().unwrap();
+    assert_eq!(n.apply_modifier_timed(&modifier).unwrap(), time);
+
+    // scaling all delays scales the convergence time
+    let mut n = net.clone();
+    n.set_default_link_delay(2.0 * DEFAULT_LINK_DELAY);
+    assert_eq!(n.apply_modifier_timed(&modifier).unwrap(), 2.0 * time);
+
+    // a slower session increases the convergence time
+    let mut n = net.clone();
+    n.set_link_delay(r1, r2, 10.0);
+    assert!(n.apply_modifier_timed(&modifier).unwrap() > time);
+
+    // modifiers without any message take no time
+    let mut n = net;
+    let weight = ConfigModifier::Update {
+        from: IgpLinkWeight { source: r1, target: r2, weight: 1.0 },
+        to: IgpLinkWeight { source: r1, target: r2, weight: 2.0 },
+    };
+    assert_eq!(n.apply_modifier_timed(&weight).unwrap(), 0.0);
+}
+
+#[test]
+fn mrai() {
+    let (net, [r1, _, _, e1]) = line_net();
+    let modifier = remove_session(r1, e1);
+    let time = net.clone().apply_modifier_timed(&modifier).unwrap();
+
+    // r1 withdraws both prefixes from r2, one after the other.
+    let mut n = net;
+    n.set_mrai(10.0);
+    assert_eq!(n.get_mrai(), 10.0);
+    let time_mrai = n.apply_modifier_timed(&modifier).unwrap();
+    assert!(time_mrai >= 10.0 + DEFAULT_LINK_DELAY);
+    assert!(time_mrai > time);
+}
+
+#[test]
+fn snapshot_timing() {
+    let (mut net, [r1, r2, _, _]) = line_net();
+    net.set_link_delay(r1, r2, 3.0);
+    net.set_default_link_delay(0.5);
+    net.set_mrai(2.0);
+    let restored: Network = serde_json::from_str(&serde_json::to_string(&net).unwrap()).unwrap();
+    assert_eq!(restored.get_link_delay(r2, r1), 3.0);
+    assert_eq!(restored.get_default_link_delay(), 0.5);
+    assert_eq!(restored.get_mrai(), 2.0);
+}
+
+#[test]
+fn soft_policy() {
+    let (net, [r1, _, _, e1]) = line_net();
+    let modifier = remove_session(r1, e1);
+    let time = net.clone().apply_modifier_timed(&modifier).unwrap();
+
+    let cost = compute_cost::<MinimizeTotalConvergenceTime>(&net, &[modifier.clone()]).unwrap();
+    assert!((cost - time / (time + DEFAULT_CONVERGENCE_REFERENCE)).abs() < 1e-9);
+
+    let mut n = net.clone();
+    let mut policy =
+        MinimizeTotalConvergenceTime::new(&mut n.get_forwarding_state(), &n).with_reference(time);
+    assert_eq!(policy.reference(), time);
+    n.apply_modifier(&modifier).unwrap();
+    policy.update(&mut n.get_forwarding_state(), &n);
+    assert!((policy.cost() - 0.5).abs() < 1e-9);
+    assert_eq!(policy.total_time(), time);
+
+    policy.reset(&mut n.get_forwarding_state(), &n);
+    assert_eq!(policy.total_time(), 0.0);
+    assert_eq!(policy.reference(), time);
+}
@@ -0,0 +1,109 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::corpus::CorpusEntry;
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::netsim::config::Config;
+use crate::netsim::Network;
+use crate::topology_zoo::{Scenario, ZooTopology};
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+const TIME_BUDGET: Duration = Duration::from_secs(600);
+
+fn corpus_path(name: &str) -> PathBuf {
+    PathBuf::from(format!("{}/tests/corpus/{}.json", env!("CARGO_MANIFEST_DIR"), name))
+}
+
+/// Compare the problem with the stored entry. If `SNOWCAP_BLESS_CORPUS` is set, the entry is
+/// generated instead. A missing entry makes the test fail.
+fn run_corpus(name: &str, net: Network, final_config: Config, hard_policy: HardPolicy) {
+    let path = corpus_path(name);
+    if std::env::var_os("SNOWCAP_BLESS_CORPUS").is_some() {
+        let entry =
+            CorpusEntry::generate(name, net, final_config, hard_policy, Some(TIME_BUDGET)).unwrap();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        entry.save(&path).unwrap();
+        return;
+    }
+
+    assert!(
+        path.exists(),
+        "{}: the corpus entry {} is missing. Run the tests with SNOWCAP_BLESS_CORPUS set, and \
+         commit the generated file.",
+        name,
+        path.display()
+    );
+    let entry = CorpusEntry::load(&path).unwrap();
+    assert_eq!(entry.name, name);
+    assert!(entry.same_problem(&net, &final_config), "{}: the problem has changed", name);
+    let mismatches = entry.check(&hard_policy, Some(TIME_BUDGET)).unwrap();
+    assert!(mismatches.is_empty(), "{}: {:?}", name, mismatches);
+}
+
+fn example<N: ExampleNetwork>(name: &str, initial_variant: usize, final_variant: usize) {
+    let net = N::net(initial_variant);
+    let final_config = N::final_config(&net, final_variant);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    run_corpus(
+        &format!("{}_{}_{}", name, initial_variant, final_variant),
+        net,
+        final_config,
+        hard_policy,
+    );
+}
+
+fn zoo(scenario: Scenario, seed: u64) {
+    let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+    let name = format!("zoo_switch_{:?}_{}", scenario, seed);
+    let (net, final_config, hard_policy) = ZooTopology::new(gml_filename, seed)
+        .unwrap()
+        .apply_scenario(scenario, false, 100, 1, 1.0)
+        .unwrap();
+    run_corpus(&name, net, final_config, hard_policy);
+}
+
+#[test]
+fn corpus_example_networks() {
+    example::<SimpleNet>("simple_net", 0, 0);
+    example::<SimpleNet>("simple_net", 1, 0);
+    example::<SmallNet>("small_net", 0, 1);
+    example::<SmallNet>("small_net", 2, 1);
+    example::<MediumNet>("medium_net", 0, 0);
+    example::<MediumNet>("medium_net", 1, 3);
+}
+
+#[test]
+fn corpus_gadgets() {
+    example::<ChainGadget<Repetition5>>("chain_gadget_5", 0, 0);
+    example::<DifficultGadgetMinimal>("difficult_gadget_minimal", 0, 0);
+    example::<DifficultGadgetRepeated<Repetition2>>("difficult_gadget_repeated_2", 0, 0);
+}
+
+#[test]
+fn corpus_topology_zoo() {
+    for seed in 0..2 {
+        zoo(Scenario::DoubleIgpWeight, seed);
+        zoo(Scenario::DoubleLocalPref, seed);
+        zoo(Scenario::IntroduceSecondRouteReflector, seed);
+    }
+}
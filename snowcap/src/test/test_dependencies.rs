@@ -0,0 +1,58 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::dependencies::{analyze_dependencies, DependencyGroup, DependencyStatus};
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::netsim::config::ConfigModifier;
+
+fn analyze<N: ExampleNetwork>() -> (Vec<ConfigModifier>, Vec<DependencyGroup>) {
+    let net = N::net(0);
+    let final_config = N::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let groups = analyze_dependencies(&net, &modifiers, &hard_policy).unwrap();
+    for group in groups.iter() {
+        assert!(group.modifiers.iter().all(|m| modifiers.contains(m)));
+        assert!(!group.evidence.is_empty());
+    }
+    (modifiers, groups)
+}
+
+#[test]
+fn dependencies_difficult_gadget() {
+    let (modifiers, groups) = analyze::<DifficultGadgetMinimal>();
+    // a safe ordering exists, hence all groups are solved and disjoint
+    assert!(groups.iter().all(|g| g.status == DependencyStatus::Solved));
+    assert!(groups.iter().all(|g| g.modifiers.len() > 1));
+    let num_grouped: usize = groups.iter().map(|g| g.modifiers.len()).sum();
+    assert!(num_grouped <= modifiers.len());
+    for (i, a) in groups.iter().enumerate() {
+        for b in groups.iter().skip(i + 1) {
+            assert!(a.modifiers.iter().all(|m| !b.modifiers.contains(m)));
+        }
+    }
+}
+
+#[test]
+fn dependencies_carousel_gadget() {
+    let (_, groups) = analyze::<CarouselGadget>();
+    // no safe ordering exists, hence some group must remain unresolved
+    assert!(groups.iter().any(|g| g.status == DependencyStatus::Unresolved));
+}
@@ -0,0 +1,97 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::diagnostics::*;
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+
+use std::time::Duration;
+
+#[test]
+fn test_profile_chain_gadget() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    let profile = profile_problem(&net, &modifiers, &hard_policy, 3, Some(42)).unwrap();
+    assert_eq!(profile.num_samples, 3 * modifiers.len());
+    assert!(profile.num_violations <= profile.num_samples);
+    assert_eq!(profile.conditions.len(), 1);
+    assert_eq!(profile.conditions[0].0, ConditionKind::Forwarding);
+    assert!(profile.total() > Duration::default());
+    assert_eq!(
+        profile.total(),
+        profile.simulation + profile.policy_check + profile.search_overhead
+    );
+}
+
+#[test]
+fn test_profile_condition_kinds() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+    let p = *net.get_known_prefixes().iter().next().unwrap();
+    let r = net.get_routers()[0];
+    let hard_policy = HardPolicy::globally(vec![
+        Condition::LatencyAtMost(r, p, Latency(1e9)),
+        Condition::Reachable(r, p, None),
+    ]);
+
+    let profile = profile_problem(&net, &modifiers, &hard_policy, 1, Some(42)).unwrap();
+    let kinds: Vec<ConditionKind> = profile.conditions.iter().map(|(k, _)| *k).collect();
+    assert_eq!(kinds, vec![ConditionKind::Forwarding, ConditionKind::Latency]);
+}
+
+#[test]
+fn test_recommendations() {
+    let ms = Duration::from_millis;
+    let profile = ProblemProfile {
+        num_samples: 10,
+        num_violations: 1,
+        simulation: ms(10),
+        policy_check: ms(80),
+        search_overhead: ms(10),
+        conditions: vec![(ConditionKind::Forwarding, ms(5)), (ConditionKind::Reliability, ms(75))],
+    };
+    assert_eq!(
+        profile.recommendations(),
+        vec![Recommendation::ConditionsDominate(ConditionKind::Reliability)]
+    );
+    assert!(profile.recommendations()[0]
+        .to_string()
+        .starts_with("reliability conditions dominate"));
+
+    let profile = ProblemProfile {
+        num_samples: 10,
+        num_violations: 8,
+        simulation: ms(60),
+        policy_check: ms(20),
+        search_overhead: ms(20),
+        conditions: vec![(ConditionKind::Forwarding, ms(20))],
+    };
+    assert_eq!(
+        profile.recommendations(),
+        vec![Recommendation::SimulationDominates, Recommendation::ManyViolations]
+    );
+
+    // no measurements, no recommendations
+    assert_eq!(ProblemProfile::default().recommendations(), vec![]);
+}
@@ -0,0 +1,76 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for dual-stack networks with IPv4 and IPv6 prefixes
+
+#![cfg(test)]
+use super::two_egress_topology;
+use crate::netsim::config::{ConfigExpr::*, ConfigModifier};
+use crate::netsim::{Afi, AsId, BgpSessionType::*, Prefix};
+
+#[test]
+fn prefix_afi() {
+    assert_eq!(Prefix(3).afi(), Afi::Ipv4);
+    assert_eq!(Prefix(3).id(), 3);
+    assert_eq!(Prefix::new(3, Afi::Ipv4), Prefix(3));
+
+    let v6 = Prefix::new(3, Afi::Ipv6);
+    assert_ne!(v6, Prefix(3));
+    assert_eq!(v6.afi(), Afi::Ipv6);
+    assert_eq!(v6.id(), 3);
+    assert_eq!(Afi::default(), Afi::Ipv4);
+    assert_eq!(Afi::Ipv6.to_string(), "ipv6");
+}
+
+#[test]
+#[should_panic]
+fn prefix_id_too_large() {
+    Prefix::new(1 << 31, Afi::Ipv6);
+}
+
+#[test]
+fn dual_stack_routing() {
+    // Network:
+    //
+    // e1 ---- r1 ---- r2 ---- e2
+    //
+    // e1 advertises the IPv4 prefix, and e2 the IPv6 prefix with the same id.
+    let (mut net, [r1, r2, e1, e2]) = two_egress_topology();
+
+    let v4 = Prefix::new(0, Afi::Ipv4);
+    let v6 = Prefix::new(0, Afi::Ipv6);
+    net.advertise_external_route(e1, v4, vec![AsId(65101)], None, None).unwrap();
+    net.advertise_external_route(e2, v6, vec![AsId(65102)], None, None).unwrap();
+    assert_eq!(net.get_known_prefixes().len(), 2);
+
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(fw_state.get_route(r1, v4).unwrap(), vec![r1, e1]);
+    assert_eq!(fw_state.get_route(r1, v6).unwrap(), vec![r1, r2, e2]);
+    let route = net.get_device(r1).unwrap_internal().get_selected_bgp_route(v6).unwrap().route;
+    assert_eq!(route.afi(), Afi::Ipv6);
+
+    // removing the IPv6 egress does not affect IPv4
+    net.apply_modifier(&ConfigModifier::Remove(BgpSession {
+        source: r2,
+        target: e2,
+        session_type: EBgp,
+    }))
+    .unwrap();
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(fw_state.get_route(r2, v4).unwrap(), vec![r2, r1, e1]);
+    assert!(fw_state.get_route(r1, v6).is_err());
+}
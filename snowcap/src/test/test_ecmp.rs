@@ -0,0 +1,146 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::hard_policies::{Condition, PathCondition};
+use crate::netsim::config::{Config, ConfigExpr::*};
+use crate::netsim::{AsId, BgpSessionType::*, Network, NetworkError, Prefix, RouterId};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+/// Diamond topology, where `a` reaches `d` (and the external router `e`) via `b` and `c` with equal
+/// cost.
+fn diamond() -> (Network, [RouterId; 5]) {
+    let mut net = Network::new();
+    let a = net.add_router("a");
+    let b = net.add_router("b");
+    let c = net.add_router("c");
+    let d = net.add_router("d");
+    let e = net.add_external_router("e", AsId(65101));
+
+    net.add_link(a, b);
+    net.add_link(a, c);
+    net.add_link(b, d);
+    net.add_link(c, d);
+    net.add_link(d, e);
+
+    let mut config = Config::new();
+    for (x, y) in vec![(a, b), (a, c), (b, d), (c, d), (d, e)] {
+        config.add(IgpLinkWeight { source: x, target: y, weight: 1.0 }).unwrap();
+        config.add(IgpLinkWeight { source: y, target: x, weight: 1.0 }).unwrap();
+    }
+    config.add(BgpSession { source: d, target: a, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: b, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: c, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: e, session_type: EBgp }).unwrap();
+    net.set_config(&config).unwrap();
+
+    net.advertise_external_route(e, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
+
+    (net, [a, b, c, d, e])
+}
+
+#[test]
+fn router_next_hops() {
+    let (net, [a, b, c, d, _]) = diamond();
+    let router = net.get_device(a).unwrap_internal();
+    assert_eq!(router.get_next_hops(Prefix(0)), vec![b, c]);
+    assert!(router.get_next_hops(Prefix(0)).contains(&router.get_next_hop(Prefix(0)).unwrap()));
+    assert_eq!(net.get_device(b).unwrap_internal().get_next_hops(Prefix(0)), vec![d]);
+    assert_eq!(net.get_device(a).unwrap_internal().get_next_hops(Prefix(1)), vec![]);
+}
+
+#[test]
+fn forwarding_state_without_ecmp() {
+    let (net, [a, ..]) = diamond();
+    assert!(!net.get_ecmp());
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(fw_state.get_next_hops(a, Prefix(0)).unwrap().len(), 1);
+    let route = fw_state.get_route(a, Prefix(0));
+    assert_eq!(fw_state.get_all_routes(a, Prefix(0)), vec![route]);
+}
+
+#[test]
+fn forwarding_state_with_ecmp() {
+    let (mut net, [a, b, c, d, e]) = diamond();
+    net.set_ecmp(true);
+    let mut fw_state = net.get_forwarding_state();
+
+    assert_eq!(fw_state.get_next_hops(a, Prefix(0)).unwrap(), vec![b, c]);
+    assert_eq!(fw_state.get_next_hops(d, Prefix(0)).unwrap(), vec![e]);
+    assert_eq!(
+        fw_state.get_all_routes(a, Prefix(0)),
+        vec![Ok(vec![a, b, d, e]), Ok(vec![a, c, d, e])]
+    );
+    assert_eq!(fw_state.get_all_routes(b, Prefix(0)), vec![Ok(vec![b, d, e])]);
+    // get_route follows the first next hop
+    assert_eq!(fw_state.get_route(a, Prefix(0)), Ok(vec![a, b, d, e]));
+
+    let expected: HashMap<RouterId, Vec<RouterId>> =
+        vec![(a, vec![b, c]), (b, vec![d]), (c, vec![d]), (d, vec![e]), (e, vec![])]
+            .into_iter()
+            .collect();
+    assert_eq!(fw_state.get_route_dag(a, Prefix(0)), Ok(expected));
+    assert_eq!(
+        fw_state.get_route_dag(10.into(), Prefix(0)),
+        Err(NetworkError::DeviceNotFound(10.into()))
+    );
+
+    // the forwarding state differs from the one without ECMP
+    net.set_ecmp(false);
+    assert_ne!(net.get_forwarding_state(), fw_state);
+}
+
+#[test]
+fn routes_to_addr_with_ecmp() {
+    let (mut net, [a, b, c, d, e]) = diamond();
+    net.set_ecmp(true);
+    net.set_prefix_range(Prefix(0), "10.0.0.0/8".parse().unwrap());
+    let fw_state = net.get_forwarding_state();
+
+    let addr = Ipv4Addr::new(10, 1, 2, 3);
+    assert_eq!(
+        fw_state.get_all_routes_to_addr(a, addr),
+        vec![Ok(vec![a, b, d, e]), Ok(vec![a, c, d, e])]
+    );
+    // get_route_to_addr follows the first next hop
+    assert_eq!(fw_state.get_route_to_addr(a, addr), Ok(vec![a, b, d, e]));
+    assert_eq!(
+        fw_state.get_all_routes_to_addr(a, Ipv4Addr::new(192, 168, 0, 1)),
+        vec![Err(NetworkError::ForwardingBlackHole(vec![a]))]
+    );
+}
+
+#[test]
+fn conditions_checked_on_every_path() {
+    let (mut net, [a, b, c, d, _]) = diamond();
+
+    // without ECMP, exactly one of the two waypoints is traversed
+    let mut fw_state = net.get_forwarding_state();
+    let via_b = Condition::Reachable(a, Prefix(0), Some(PathCondition::Node(b)));
+    let via_c = Condition::Reachable(a, Prefix(0), Some(PathCondition::Node(c)));
+    assert!(via_b.check(&mut fw_state).is_ok() ^ via_c.check(&mut fw_state).is_ok());
+
+    // with ECMP, both fail, since one of the paths does not traverse the waypoint
+    net.set_ecmp(true);
+    let mut fw_state = net.get_forwarding_state();
+    assert!(via_b.check(&mut fw_state).is_err());
+    assert!(via_c.check(&mut fw_state).is_err());
+    let via_d = Condition::Reachable(a, Prefix(0), Some(PathCondition::Node(d)));
+    assert!(via_d.check(&mut fw_state).is_ok());
+    assert!(Condition::NotReachable(a, Prefix(0)).check(&mut fw_state).is_err());
+}
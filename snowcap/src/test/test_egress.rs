@@ -0,0 +1,111 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::hard_policies::{Condition, HardPolicy, PolicyError};
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// Network with two border routers `b` and `c`, each connected to an external router (`e1` and
+/// `e2`) that advertise the same prefix. `a` is closer to `b` than to `c`.
+fn two_borders() -> (Network, [RouterId; 5]) {
+    let mut net = Network::new();
+    let a = net.add_router("a");
+    let b = net.add_router("b");
+    let c = net.add_router("c");
+    let e1 = net.add_external_router("e1", AsId(65101));
+    let e2 = net.add_external_router("e2", AsId(65102));
+
+    net.add_link(a, b);
+    net.add_link(a, c);
+    net.add_link(b, e1);
+    net.add_link(c, e2);
+
+    let mut config = Config::new();
+    for (x, y, w) in vec![(a, b, 1.0), (a, c, 2.0), (b, e1, 1.0), (c, e2, 1.0)] {
+        config.add(IgpLinkWeight { source: x, target: y, weight: w }).unwrap();
+        config.add(IgpLinkWeight { source: y, target: x, weight: w }).unwrap();
+    }
+    config.add(BgpSession { source: a, target: b, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: a, target: c, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: b, target: e1, session_type: EBgp }).unwrap();
+    config.add(BgpSession { source: c, target: e2, session_type: EBgp }).unwrap();
+    net.set_config(&config).unwrap();
+
+    net.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None)
+        .unwrap();
+    net.advertise_external_route(e2, Prefix(0), vec![AsId(65102), AsId(65200)], None, None)
+        .unwrap();
+
+    (net, [a, b, c, e1, e2])
+}
+
+#[test]
+fn forwarding_state_egress() {
+    let (net, [a, b, c, e1, _]) = two_borders();
+    let p = Prefix(0);
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(fw_state.get_egress(a, p), Ok(b));
+    assert_eq!(fw_state.get_egress(b, p), Ok(b));
+    assert_eq!(fw_state.get_egress(c, p), Ok(c));
+    // external routers are their own egress
+    assert_eq!(fw_state.get_egress(e1, p), Ok(e1));
+    assert_eq!(fw_state.get_all_egresses(a, p), vec![Ok(b)]);
+    assert!(fw_state.get_egress(a, Prefix(1)).is_err());
+}
+
+#[test]
+fn egress_condition() {
+    let (mut net, [a, b, c, _, _]) = two_borders();
+    let p = Prefix(0);
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(Condition::EgressIs(a, p, vec![b]).check(&mut fw_state), Ok(()));
+    assert_eq!(Condition::EgressIs(a, p, vec![c, b]).check(&mut fw_state), Ok(()));
+    assert_eq!(
+        Condition::EgressIs(a, p, vec![c]).check(&mut fw_state),
+        Err(PolicyError::WrongEgress { router: a, prefix: p, egress: b, allowed: vec![c] })
+    );
+    assert_eq!(
+        Condition::EgressIs(a, Prefix(1), vec![b]).check(&mut fw_state),
+        Err(PolicyError::BlackHole { router: a, prefix: Prefix(1) })
+    );
+
+    // moving the traffic to the other border router violates the policy
+    let mut hard_policy = HardPolicy::globally(vec![Condition::EgressIs(a, p, vec![b])]);
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
+    net.apply_modifier(&ConfigModifier::Update {
+        from: IgpLinkWeight { source: a, target: b, weight: 1.0 },
+        to: IgpLinkWeight { source: a, target: b, weight: 5.0 },
+    })
+    .unwrap();
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(fw_state.get_egress(a, p), Ok(c));
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(!hard_policy.check());
+}
+
+#[test]
+fn egress_error_repr() {
+    let (net, [a, b, c, _, _]) = two_borders();
+    let error =
+        PolicyError::WrongEgress { router: a, prefix: Prefix(0), egress: c, allowed: vec![b] };
+    assert_eq!(
+        error.repr_with_name(&net),
+        "Traffic from a to prefix 0 leaves the network at c, instead of one of {b}"
+    );
+}
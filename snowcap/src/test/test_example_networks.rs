@@ -0,0 +1,61 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+
+use crate::example_networks::*;
+use crate::optimizers::OPTIMIZERS;
+use crate::strategies::STRATEGIES;
+use crate::topology_zoo::Scenario;
+
+fn check_variants<T: ExampleNetwork>() {
+    let initial = T::initial_variants();
+    let last = T::final_variants();
+    for i in initial.min..=initial.max.unwrap() {
+        let net = T::net(i);
+        for f in last.min..=last.max.unwrap() {
+            T::final_config(&net, f);
+            T::get_policy(&net, f);
+        }
+    }
+}
+
+#[test]
+fn variant_ranges() {
+    check_variants::<SimpleNet>();
+    check_variants::<SmallNet>();
+    check_variants::<MediumNet>();
+    check_variants::<FirewallNet>();
+    check_variants::<CarouselGadget>();
+}
+
+#[test]
+fn variant_range_repr() {
+    assert_eq!(VariantRange::single().to_string(), "0");
+    assert_eq!(VariantRange::new(0, 6).to_string(), "0..=6");
+    assert_eq!(VariantRange::from(2).to_string(), "2..");
+    assert!(VariantRange::from(2).contains(100));
+    assert!(!VariantRange::from(2).contains(1));
+    assert!(!VariantRange::new(0, 1).contains(2));
+}
+
+#[test]
+fn registries() {
+    assert_eq!(STRATEGIES.len(), 7);
+    assert_eq!(OPTIMIZERS.len(), 6);
+    assert!(STRATEGIES.iter().chain(OPTIMIZERS.iter()).all(|a| !a.description.is_empty()));
+    assert_eq!(Scenario::ALL.len(), 23);
+}
@@ -0,0 +1,103 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::execution::*;
+use crate::hard_policies::*;
+use crate::modifier_ordering::SimpleOrdering;
+use crate::soft_policies::MinimizeTrafficShift;
+use crate::strategies::{Strategy, TreeStrategy};
+use crate::ControlHandle;
+
+#[test]
+fn test_next_step_options_chain_gadget() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let sequence = TreeStrategy::<SimpleOrdering>::synthesize(
+        net.clone(),
+        cf,
+        hard_policy.clone(),
+        None,
+        ControlHandle::new(),
+    )
+    .unwrap();
+
+    // execute the plan in reverse order of the steps, such that it must be reordered.
+    let plan = sequence.iter().rev().cloned().collect::<Vec<_>>();
+    let mut state = ExecutionState::<MinimizeTrafficShift>::new(&net, plan, hard_policy).unwrap();
+    let mut executed = Vec::new();
+    while !state.is_done() {
+        let options = next_step_options(&state).unwrap();
+        // the chain gadget has a single valid solution, so the next step of it is always safe.
+        let expected = &sequence[executed.len()];
+        assert!(options.iter().any(|o| o.modifier == *expected));
+        for w in options.windows(2) {
+            assert!(w[0].cost_delta < w[1].cost_delta || w[0].risk <= w[1].risk);
+        }
+        let index = state.remaining().iter().position(|m| m == expected).unwrap();
+        assert!(state.execute(index).unwrap());
+        executed.push(expected.clone());
+        assert_eq!(state.executed(), &executed[..]);
+    }
+    assert_eq!(state.executed(), &sequence[..]);
+    assert!(next_step_options(&state).unwrap().is_empty());
+}
+
+#[test]
+fn test_next_step_options_unchanged_state() {
+    let net = SimpleNet::net(0);
+    let final_config = SimpleNet::final_config(&net, 0);
+    let plan = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let state =
+        ExecutionState::<MinimizeTrafficShift>::new(&net, plan.clone(), hard_policy).unwrap();
+
+    let options = next_step_options(&state).unwrap();
+    assert!(!options.is_empty());
+    for o in options.iter() {
+        assert!(plan.contains(&o.modifier));
+        assert!(o.cost_delta >= 0.0);
+    }
+
+    // computing the options does not change the state
+    assert!(state.executed().is_empty());
+    assert_eq!(state.remaining(), &plan[..]);
+    assert_eq!(state.network().current_config(), net.current_config());
+    assert_eq!(next_step_options(&state).unwrap(), options);
+}
+
+#[test]
+fn test_execute_violation() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let plan = net.current_config().get_diff(&cf).modifiers;
+    let mut state =
+        ExecutionState::<MinimizeTrafficShift>::new(&net, plan.clone(), hard_policy).unwrap();
+
+    // a step which is not offered as an option violates the hard policy when executed anyways.
+    let options = next_step_options(&state).unwrap();
+    let index = plan.iter().position(|m| options.iter().all(|o| o.modifier != *m)).unwrap();
+    assert!(!state.execute(index).unwrap());
+    assert_eq!(state.executed(), &plan[index..=index]);
+    assert_eq!(state.remaining().len(), plan.len() - 1);
+}
@@ -0,0 +1,74 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::*;
+use crate::failure::explain_failure;
+use crate::hard_policies::*;
+use crate::modifier_ordering::NoOrdering;
+use crate::plan_editor::PlanEditor;
+use crate::strategies::{Strategy, TreeStrategy};
+use crate::{ControlHandle, Error};
+
+use std::time::Duration;
+
+#[test]
+fn explain_carousel_gadget() {
+    let net = CarouselGadget::net(0);
+    let final_config = CarouselGadget::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    let failure = explain_failure(&net, &modifiers, &hard_policy, Some(Duration::from_secs(60)))
+        .unwrap()
+        .expect("The carousel gadget has no safe ordering");
+
+    // the prefix is valid, and every remaining modifier is blocked
+    assert!(PlanEditor::new(&net, failure.prefix.clone(), hard_policy.clone()).unwrap().is_valid());
+    assert_eq!(failure.prefix.len() + failure.blocked.len(), modifiers.len());
+    assert!(failure.blocked.iter().all(|b| !b.errors.is_empty()));
+
+    // the minimal subset has no safe ordering, but removing any modifier makes it solvable
+    let subset = failure.minimal_subset.clone().expect("No minimal subset found");
+    assert!(!subset.is_empty());
+    assert!(subset.iter().all(|m| modifiers.contains(m)));
+    let solve = |mods: Vec<_>| {
+        TreeStrategy::<NoOrdering>::new(net.clone(), mods, hard_policy.clone(), None)
+            .unwrap()
+            .work(ControlHandle::new())
+    };
+    assert!(matches!(solve(subset.clone()), Err(Error::NoSafeOrdering)));
+    for i in 0..subset.len() {
+        let mut reduced = subset.clone();
+        reduced.remove(i);
+        assert!(solve(reduced).is_ok());
+    }
+
+    let repr = failure.repr_with_name(&net);
+    assert!(repr.contains("Minimal subset"));
+}
+
+#[test]
+fn explain_solvable_problem() {
+    let net = SimpleNet::net(0);
+    let final_config = SimpleNet::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    assert_eq!(explain_failure(&net, &modifiers, &hard_policy, None).unwrap(), None);
+}
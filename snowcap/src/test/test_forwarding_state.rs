@@ -15,9 +15,10 @@
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
+use crate::example_networks::{DifficultGadgetMinimal, ExampleNetwork};
 use crate::netsim::config::{Config, ConfigExpr::*};
 use crate::netsim::route_map::*;
-use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix};
+use crate::netsim::{printer, AsId, BgpSessionType::*, Network, Prefix};
 
 #[test]
 fn test_forwarding_state_carousel_gadget() {
@@ -208,3 +209,35 @@ fn test_forwarding_state_carousel_gadget() {
         }
     }
 }
+
+#[test]
+fn test_forwarding_diff() {
+    let mut net = DifficultGadgetMinimal::net(0);
+    let final_config = DifficultGadgetMinimal::final_config(&net, 0);
+    let before = net.get_forwarding_state();
+
+    // no change
+    assert!(printer::forwarding_diff(&net, &before, &before).unwrap().is_empty());
+
+    net.set_config(&final_config).unwrap();
+    let after = net.get_forwarding_state();
+    let diff = printer::forwarding_diff(&net, &before, &after).unwrap();
+
+    // one line for every changed pair of router and prefix
+    let mut expected = 0;
+    for prefix in net.get_known_prefixes().iter() {
+        for router in net.get_routers() {
+            let old = before.get_next_hops(router, *prefix).unwrap();
+            let new = after.get_next_hops(router, *prefix).unwrap();
+            if old != new {
+                let name = net.get_router_name(router).unwrap();
+                let line = format!("prefix {}\x1b[0m {}: ", prefix.0, name);
+                assert!(diff.iter().any(|l| l.contains(&line)));
+                expected += 1;
+            }
+        }
+    }
+    assert!(expected > 0);
+    assert_eq!(diff.len(), expected);
+    assert!(diff.iter().all(|l| l.contains(" => ")));
+}
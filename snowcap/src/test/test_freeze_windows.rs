@@ -0,0 +1,100 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use super::two_egress_net;
+use crate::netsim::config::{ConfigExpr::*, ConfigModifier, ConfigModifier::*};
+use crate::netsim::BgpSessionType::*;
+use crate::soft_policies::{compute_cost, FreezeWindow, FreezeWindows, SoftPolicy};
+
+#[test]
+fn test_freeze_window() {
+    let (mut net, ids) = two_egress_net();
+    let (r1, r2) = (ids[0], ids[1]);
+    let mut sp = FreezeWindows::new(&mut net.get_forwarding_state(), &net)
+        .with_window(FreezeWindow::before(vec![r2], 1));
+    assert_eq!(sp.cost(), 0.0);
+
+    // R2 is frozen in the first step
+    net.apply_modifier(&Update {
+        from: IgpLinkWeight { source: r2, target: r1, weight: 1.0 },
+        to: IgpLinkWeight { source: r2, target: r1, weight: 2.0 },
+    })
+    .unwrap();
+    sp.update(&mut net.get_forwarding_state(), &net);
+    assert_eq!(sp.cost(), 1.0);
+    assert_eq!(sp.violations(), &[(0, r2)]);
+
+    // R2 is no longer frozen in the second step
+    net.apply_modifier(&Update {
+        from: IgpLinkWeight { source: r2, target: r1, weight: 2.0 },
+        to: IgpLinkWeight { source: r2, target: r1, weight: 3.0 },
+    })
+    .unwrap();
+    sp.update(&mut net.get_forwarding_state(), &net);
+    assert_eq!(sp.cost(), 0.0);
+    assert_eq!(sp.violations().len(), 1);
+
+    // the windows are kept when resetting the soft policy
+    sp.reset(&mut net.get_forwarding_state(), &net);
+    assert_eq!(sp.windows().len(), 1);
+    assert!(sp.violations().is_empty());
+}
+
+#[test]
+fn test_freeze_window_sequence_cost() {
+    let (net, ids) = two_egress_net();
+    let (r1, r2, e1, e2) = (ids[0], ids[1], ids[2], ids[3]);
+    let change_r1 = Update {
+        from: IgpLinkWeight { source: r1, target: r2, weight: 1.0 },
+        to: IgpLinkWeight { source: r1, target: r2, weight: 5.0 },
+    };
+    let change_r2 = Update {
+        from: IgpLinkWeight { source: r2, target: e2, weight: 1.0 },
+        to: IgpLinkWeight { source: r2, target: e2, weight: 5.0 },
+    };
+    let remove_session = Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+
+    // R1 must only be changed by the last modifier
+    let eval = |sequence: &[ConfigModifier]| {
+        let mut net = net.clone();
+        let mut sp = FreezeWindows::new(&mut net.get_forwarding_state(), &net)
+            .with_window(FreezeWindow::before(vec![r1], sequence.len() - 1))
+            .with_penalty(0.5);
+        let mut cost = 0.0;
+        for m in sequence {
+            net.apply_modifier(m).unwrap();
+            sp.update(&mut net.get_forwarding_state(), &net);
+            cost += sp.cost();
+        }
+        cost
+    };
+    assert_eq!(eval(&[change_r2.clone(), change_r1.clone()]), 0.0);
+    assert_eq!(eval(&[change_r1.clone(), change_r2.clone()]), 0.5);
+    // the session is configured on both routers, and therefore also touches R1
+    assert_eq!(eval(&[remove_session.clone(), change_r2.clone()]), 0.5);
+
+    // without any window, the policy is free
+    assert_eq!(compute_cost::<FreezeWindows>(&net, &[change_r1, remove_session]).unwrap(), 0.0);
+}
+
+#[test]
+#[should_panic]
+fn test_freeze_window_infinite_penalty() {
+    let (net, _) = two_egress_net();
+    let _ = FreezeWindows::new(&mut net.get_forwarding_state(), &net).with_penalty(f64::INFINITY);
+}
@@ -0,0 +1,110 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for the graceful shutdown of BGP sessions
+
+#![cfg(test)]
+use super::two_egress_net;
+use crate::hard_policies::HardPolicy;
+use crate::modifier_ordering::NoOrdering;
+use crate::netsim::config::{ConfigExpr::*, ConfigModifier};
+use crate::netsim::{BgpSessionType::*, Prefix};
+use crate::plan::ModifierKind;
+use crate::strategies::{PrecedenceConstraints, PrecedenceStrategy, TreeStrategy};
+use crate::ControlHandle;
+
+#[test]
+fn graceful_shutdown_depreferences_routes() {
+    let (mut net, [r1, r2, e1, e2]) = two_egress_net();
+    let p = Prefix(0);
+    assert_eq!(net.get_forwarding_state().get_route(r1, p).unwrap(), vec![r1, e1]);
+
+    let modifier = ConfigModifier::Insert(BgpGracefulShutdown { router: r1, neighbor: e1 });
+    assert_eq!(ModifierKind::of(&modifier), ModifierKind::BgpGracefulShutdown);
+    net.apply_modifier(&modifier).unwrap();
+    assert!(net.get_device(r1).unwrap_internal().has_graceful_shutdown(e1));
+    assert_eq!(net.get_forwarding_state().get_route(r1, p).unwrap(), vec![r1, r2, e2]);
+
+    net.apply_modifier(&ConfigModifier::Remove(BgpGracefulShutdown { router: r1, neighbor: e1 }))
+        .unwrap();
+    assert!(!net.get_device(r1).unwrap_internal().has_graceful_shutdown(e1));
+    assert_eq!(net.get_forwarding_state().get_route(r1, p).unwrap(), vec![r1, e1]);
+}
+
+#[test]
+fn expand_graceful_shutdown() {
+    let (net, [r1, r2, e1, _]) = two_egress_net();
+    let remove_ebgp =
+        ConfigModifier::Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let remove_ibgp =
+        ConfigModifier::Remove(BgpSession { source: r1, target: r2, session_type: IBgpPeer });
+
+    // without the flag, nothing is expanded
+    let (modifiers, constraints) =
+        PrecedenceConstraints::new().expand_graceful_shutdown(&net, vec![remove_ebgp.clone()]);
+    assert_eq!(modifiers, vec![remove_ebgp.clone()]);
+    assert!(constraints.is_empty());
+
+    // only the internal router of an eBGP session is shut down gracefully
+    let (modifiers, constraints) = PrecedenceConstraints::new()
+        .graceful_shutdown()
+        .expand_graceful_shutdown(&net, vec![remove_ebgp.clone()]);
+    let start = ConfigModifier::Insert(BgpGracefulShutdown { router: r1, neighbor: e1 });
+    let end = ConfigModifier::Remove(BgpGracefulShutdown { router: r1, neighbor: e1 });
+    assert_eq!(modifiers, vec![start.clone(), end.clone(), remove_ebgp.clone()]);
+    assert!(constraints.is_graceful_shutdown());
+    assert_eq!(constraints.rules().len(), 2);
+    let ordered = vec![start.clone(), remove_ebgp.clone(), end.clone()];
+    assert_eq!(constraints.first_violation(&ordered), None);
+    assert_eq!(constraints.first_violation(&[remove_ebgp.clone(), start, end]), Some(0));
+
+    // both routers of an iBGP session are shut down gracefully
+    let (modifiers, constraints) = PrecedenceConstraints::new()
+        .graceful_shutdown()
+        .expand_graceful_shutdown(&net, vec![remove_ibgp]);
+    assert_eq!(modifiers.len(), 5);
+    assert_eq!(constraints.rules().len(), 4);
+}
+
+#[test]
+fn synthesize_graceful_shutdown() {
+    let (net, [r1, _, e1, _]) = two_egress_net();
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let remove_ebgp =
+        ConfigModifier::Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let mut end_config = net.current_config().clone();
+    end_config.apply_modifier(&remove_ebgp).unwrap();
+
+    let sequence = TreeStrategy::<NoOrdering>::synthesize_with_precedence(
+        net,
+        end_config,
+        hard_policy,
+        PrecedenceConstraints::new().graceful_shutdown(),
+        None,
+        ControlHandle::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        sequence,
+        vec![
+            ConfigModifier::Insert(BgpGracefulShutdown { router: r1, neighbor: e1 }),
+            remove_ebgp,
+            ConfigModifier::Remove(BgpGracefulShutdown { router: r1, neighbor: e1 }),
+        ]
+    );
+}
@@ -0,0 +1,72 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::heatmap::*;
+
+#[test]
+fn test_heatmap_chain_gadget() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    let heatmap = ViolationHeatmap::compute(&net, &modifiers, &hard_policy, 50, Some(42)).unwrap();
+    assert_eq!(heatmap.num_playouts, 50);
+    assert_eq!(heatmap.modifiers.len(), modifiers.len());
+    // the chain gadget has a single valid solution, so most random orderings are invalid
+    assert!(heatmap.num_violations > 0);
+    assert!(heatmap.num_violations <= heatmap.num_playouts);
+    // every violating playout is caused by exactly one modifier
+    assert_eq!(heatmap.modifiers.iter().sum::<usize>(), heatmap.num_violations);
+    assert!(!heatmap.routers.is_empty());
+    assert!(heatmap.routers.values().all(|c| *c <= heatmap.num_violations));
+    for ((router, prefix), count) in heatmap.routers.iter() {
+        assert_eq!(heatmap.router_frequency(*router, *prefix), *count as f64 / 50.0);
+    }
+
+    // the same seed produces the same heatmap
+    let again = ViolationHeatmap::compute(&net, &modifiers, &hard_policy, 50, Some(42)).unwrap();
+    assert_eq!(heatmap, again);
+}
+
+#[test]
+fn test_heatmap_csv() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let heatmap = ViolationHeatmap::compute(&net, &modifiers, &hard_policy, 20, Some(1)).unwrap();
+
+    let mut buffer: Vec<u8> = Vec::new();
+    heatmap.write_router_csv(&net, &mut buffer).unwrap();
+    let csv = String::from_utf8(buffer).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "router,prefix,violations,frequency");
+    assert_eq!(lines.len(), heatmap.routers.len() + 1);
+
+    let mut buffer: Vec<u8> = Vec::new();
+    heatmap.write_modifier_csv(&net, &modifiers, &mut buffer).unwrap();
+    let csv = String::from_utf8(buffer).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "index,modifier,violations,frequency");
+    assert_eq!(lines.len(), modifiers.len() + 1);
+}
@@ -0,0 +1,160 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for the decomposition of IGP link weight changes
+
+#![cfg(test)]
+use crate::hard_policies::HardPolicy;
+use crate::modifier_ordering::NoOrdering;
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+use crate::strategies::{
+    decompose_igp_weight_change, decompose_igp_weight_changes, GroupStrategy, PushBackTreeStrategy,
+};
+use crate::ControlHandle;
+
+/// Network:
+///
+/// ```text
+///       1
+///  a ------- d
+///   \       /
+///  1 \     / 5
+///     \   /
+///       b
+/// ```
+///
+/// Increasing the weight of `a -> d` to 10 makes `a` forward via `b`, while `b` stops forwarding
+/// via `a`. If `a` changes before `b`, the traffic towards `d` loops between `a` and `b`.
+fn triangle_net() -> (Network, [RouterId; 3]) {
+    let mut net = Network::new();
+    let a = net.add_router("a");
+    let b = net.add_router("b");
+    let d = net.add_router("d");
+
+    let mut c = Config::new();
+    for (x, y, weight) in vec![(a, d, 1.0), (a, b, 1.0), (b, d, 5.0)] {
+        net.add_link(x, y);
+        c.add(IgpLinkWeight { source: x, target: y, weight }).unwrap();
+        c.add(IgpLinkWeight { source: y, target: x, weight }).unwrap();
+    }
+    net.set_config(&c).unwrap();
+
+    (net, [a, b, d])
+}
+
+fn weight_update(source: RouterId, target: RouterId, from: f32, to: f32) -> ConfigModifier {
+    ConfigModifier::Update {
+        from: IgpLinkWeight { source, target, weight: from },
+        to: IgpLinkWeight { source, target, weight: to },
+    }
+}
+
+fn weights(modifier: &ConfigModifier) -> (f32, f32) {
+    match modifier {
+        ConfigModifier::Update {
+            from: IgpLinkWeight { weight: from, .. },
+            to: IgpLinkWeight { weight: to, .. },
+        } => (*from, *to),
+        _ => panic!("Unexpected modifier: {:?}", modifier),
+    }
+}
+
+#[test]
+fn decompose_weight_increase() {
+    let (net, [a, _, d]) = triangle_net();
+    let sequence = decompose_igp_weight_change(&net, &weight_update(a, d, 1.0, 10.0)).unwrap();
+
+    // b must switch to its direct link (at weight 4) before a switches to b (at weight 6).
+    assert_eq!(sequence.len(), 2);
+    let (start, mid) = weights(&sequence[0]);
+    let (mid_2, end) = weights(&sequence[1]);
+    assert_eq!(start, 1.0);
+    assert_eq!(mid, mid_2);
+    assert!((4.0..=6.0).contains(&mid));
+    assert_eq!(end, 10.0);
+}
+
+#[test]
+fn decompose_weight_decrease() {
+    let (mut net, [a, _, d]) = triangle_net();
+    net.apply_modifier(&weight_update(a, d, 1.0, 10.0)).unwrap();
+    let sequence = decompose_igp_weight_change(&net, &weight_update(a, d, 10.0, 1.0)).unwrap();
+    assert_eq!(sequence.len(), 2);
+    assert_eq!(weights(&sequence[0]).0, 10.0);
+    assert!((4.0..=6.0).contains(&weights(&sequence[0]).1));
+    assert_eq!(weights(&sequence[1]).1, 1.0);
+}
+
+#[test]
+fn decompose_nothing() {
+    let (net, [a, b, d]) = triangle_net();
+
+    // no shortest path changes
+    let modifier = weight_update(a, d, 1.0, 2.0);
+    assert_eq!(decompose_igp_weight_change(&net, &modifier).unwrap(), vec![modifier.clone()]);
+
+    // other modifiers are not changed
+    let session =
+        ConfigModifier::Insert(BgpSession { source: a, target: b, session_type: IBgpPeer });
+    assert_eq!(decompose_igp_weight_change(&net, &session).unwrap(), vec![session.clone()]);
+
+    let groups = decompose_igp_weight_changes(
+        &net,
+        vec![session.clone(), weight_update(a, d, 1.0, 10.0), modifier.clone()],
+    )
+    .unwrap();
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[0], vec![session]);
+    assert_eq!(groups[1].len(), 2);
+    assert_eq!(groups[2], vec![modifier]);
+}
+
+#[test]
+fn synthesize_decomposed() {
+    let (mut net, [a, b, d]) = triangle_net();
+    let e = net.add_external_router("e", AsId(65100));
+    net.add_link(d, e);
+    let mut c = net.current_config().clone();
+    c.add(IgpLinkWeight { source: d, target: e, weight: 1.0 }).unwrap();
+    c.add(IgpLinkWeight { source: e, target: d, weight: 1.0 }).unwrap();
+    c.add(BgpSession { source: d, target: e, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: d, target: a, session_type: IBgpClient }).unwrap();
+    c.add(BgpSession { source: d, target: b, session_type: IBgpClient }).unwrap();
+    net.set_config(&c).unwrap();
+    net.advertise_external_route(e, Prefix(0), vec![AsId(65100)], None, None).unwrap();
+
+    let mut end_config = c.clone();
+    end_config.apply_modifier(&weight_update(a, d, 1.0, 10.0)).unwrap();
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    let sequence = PushBackTreeStrategy::<NoOrdering>::synthesize_decomposed(
+        net.clone(),
+        end_config.clone(),
+        hard_policy,
+        None,
+        ControlHandle::new(),
+    )
+    .unwrap();
+    assert_eq!(sequence.len(), 2);
+
+    for modifier in sequence.iter() {
+        net.apply_modifier(modifier).unwrap();
+    }
+    assert_eq!(net.current_config(), &end_config);
+}
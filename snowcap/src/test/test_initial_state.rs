@@ -0,0 +1,74 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::*;
+use crate::hard_policies::{Condition, HardPolicy, LTLModal};
+use crate::modifier_ordering::SimpleOrdering;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{Network, Prefix};
+use crate::strategies::{Strategy, TreeStrategy};
+use crate::Error;
+
+fn modifiers(net: &Network) -> Vec<ConfigModifier> {
+    let cf = SimpleNet::final_config(net, 0);
+    net.current_config().get_diff(&cf).modifiers
+}
+
+#[test]
+fn invalid_initial_state_reports_violations() {
+    let net = SimpleNet::net(0);
+    let r1 = net.get_router_id("r1").unwrap();
+    let r2 = net.get_router_id("r2").unwrap();
+    let p = Prefix(0);
+
+    let hard_policy = HardPolicy::globally(vec![
+        Condition::Reachable(r2, p, None),
+        Condition::NotReachable(r1, p),
+    ]);
+    let mods = modifiers(&net);
+    let violations = match TreeStrategy::<SimpleOrdering>::new(net.clone(), mods, hard_policy, None)
+    {
+        Err(Error::InvalidInitialState(v)) => v,
+        Err(e) => panic!("Unexpected error: {}", e),
+        Ok(_) => panic!("Initial state was accepted"),
+    };
+
+    // only the isolation condition is reported
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].condition, "Isolation(r1, prefix 0)");
+    assert!(violations[0].error.starts_with("Router r1 can reach unallowed prefix 0"));
+    assert_eq!(violations[0].paths.len(), 1);
+    assert!(violations[0].paths[0].starts_with("r1 -> "));
+
+    // the message contains the violated condition and the path
+    let msg = Error::InvalidInitialState(violations.clone()).to_string();
+    assert!(msg.contains("Isolation(r1, prefix 0)"));
+    assert!(msg.contains(&violations[0].paths[0]));
+}
+
+#[test]
+fn invalid_initial_state_without_conditions() {
+    let net = SimpleNet::net(0);
+    let hard_policy = HardPolicy::new(vec![], LTLModal::Globally(Box::new(false)));
+    let mods = modifiers(&net);
+    match TreeStrategy::<SimpleOrdering>::new(net, mods, hard_policy, None) {
+        Err(Error::InvalidInitialState(v)) => assert!(v.is_empty()),
+        Err(e) => panic!("Unexpected error: {}", e),
+        Ok(_) => panic!("Initial state was accepted"),
+    }
+}
@@ -0,0 +1,64 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate as snowcap;
+use crate::hard_policies::*;
+use snowcap_ltl_parser::ltl;
+
+fn check_round_trip(expr: LTLModal) {
+    let ast = expr.ast();
+    assert_eq!(ast.to_string(), expr.repr());
+    let back = ast.to_modal();
+    assert_eq!(back.ast(), ast);
+    assert_eq!(back.repr(), expr.repr());
+    let json = serde_json::to_string(&ast).unwrap();
+    let parsed: LtlAst = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, ast);
+}
+
+#[test]
+fn test_round_trip() {
+    check_round_trip(ltl!(0));
+    check_round_trip(ltl!(G(And(0, 1, 2))));
+    check_round_trip(ltl!(U(Or(0, Not(1)), G(Implies(2, 3)))));
+    check_round_trip(ltl!(W(Xor(0, 1), M(Iff(2, 3), F(N(4))))));
+    check_round_trip(ltl!(R(true, false)));
+}
+
+#[test]
+fn test_non_modal_wrapped_in_now() {
+    let ast = LtlAst::And(vec![LtlAst::Var(0), LtlAst::Not(Box::new(LtlAst::Var(1)))]);
+    assert!(!ast.is_modal());
+    assert!(ast.to_boolean().is_some());
+    assert_eq!(ast.to_modal().ast(), LtlAst::Now(Box::new(ast.clone())));
+    assert_eq!(ast.to_string(), "(x00 && !x01)");
+}
+
+#[test]
+fn test_ast_semantics() {
+    let expr = ltl!(U(0, G(1)));
+    let ast: LtlAst = (&expr).into();
+    let op = ast.to_operator();
+    let histories = vec![
+        vec![vec![true, false], vec![false, true], vec![false, true]],
+        vec![vec![true, false], vec![false, false], vec![false, true]],
+        vec![vec![false, true], vec![false, true]],
+    ];
+    for h in histories.iter() {
+        assert_eq!(op.check(h), expr.check(h));
+    }
+}
@@ -0,0 +1,83 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for the comparison of the MED in the BGP decision process
+
+#![cfg(test)]
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+use crate::plan::ModifierKind;
+
+/// Network:
+///
+/// ```text
+///        5          1
+/// e1 ------- r1 ------- e2
+/// ```
+///
+/// `e1` advertises the prefix with MED 10, and `e2` with MED 100. Both routes have an AS path of
+/// the same length, and the first AS on the path of `e2` is `e2_as`.
+fn med_net(e2_as: AsId) -> (Network, [RouterId; 3]) {
+    let mut net = Network::new();
+    let r1 = net.add_router("r1");
+    let e1 = net.add_external_router("e1", AsId(65101));
+    let e2 = net.add_external_router("e2", e2_as);
+
+    let mut c = Config::new();
+    for (a, b, weight) in vec![(r1, e1, 5.0), (r1, e2, 1.0)] {
+        net.add_link(a, b);
+        c.add(IgpLinkWeight { source: a, target: b, weight }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight }).unwrap();
+    }
+    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r1, target: e2, session_type: EBgp }).unwrap();
+    net.set_config(&c).unwrap();
+
+    net.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], Some(10), None)
+        .unwrap();
+    net.advertise_external_route(e2, Prefix(0), vec![e2_as, AsId(65200)], Some(100), None).unwrap();
+
+    (net, [r1, e1, e2])
+}
+
+#[test]
+fn med_ignored_across_ases() {
+    let (net, [r1, _, e2]) = med_net(AsId(65102));
+    assert!(!net.get_device(r1).unwrap_internal().get_always_compare_med());
+    // the MED is not compared, so the IGP cost decides.
+    assert_eq!(net.get_forwarding_state().get_route(r1, Prefix(0)).unwrap(), vec![r1, e2]);
+}
+
+#[test]
+fn med_compared_within_as() {
+    let (net, [r1, e1, _]) = med_net(AsId(65101));
+    assert_eq!(net.get_forwarding_state().get_route(r1, Prefix(0)).unwrap(), vec![r1, e1]);
+}
+
+#[test]
+fn always_compare_med() {
+    let (mut net, [r1, e1, e2]) = med_net(AsId(65102));
+
+    let modifier = ConfigModifier::Insert(BgpAlwaysCompareMed { router: r1 });
+    assert_eq!(ModifierKind::of(&modifier), ModifierKind::BgpDecision);
+    net.apply_modifier(&modifier).unwrap();
+    assert!(net.get_device(r1).unwrap_internal().get_always_compare_med());
+    assert_eq!(net.get_forwarding_state().get_route(r1, Prefix(0)).unwrap(), vec![r1, e1]);
+
+    net.apply_modifier(&ConfigModifier::Remove(BgpAlwaysCompareMed { router: r1 })).unwrap();
+    assert_eq!(net.get_forwarding_state().get_route(r1, Prefix(0)).unwrap(), vec![r1, e2]);
+}
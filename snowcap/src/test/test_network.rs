@@ -17,12 +17,18 @@
 
 //! Test the simple functionality of the network, without running it entirely.
 
+use crate::hard_policies::{Condition, HardPolicy};
+use crate::modifier_ordering::sort_by_router_roles;
+use crate::netsim::bgp::{BgpRelationship as Rel, RovPolicy as Rov, RovState};
 use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier::*};
 use crate::netsim::network::Network;
 use crate::netsim::route_map::{
     RouteMap, RouteMapDirection::*, RouteMapMatch as Match, RouteMapSet as Set, RouteMapState::*,
 };
-use crate::netsim::{AsId, BgpSessionType::*, LinkWeight, NetworkError, Prefix, RouterId};
+use crate::netsim::{
+    AsId, BgpSessionType::*, LinkWeight, NetworkError, Prefix, RouterId, RouterRole,
+};
+use crate::ControlHandle;
 use lazy_static::lazy_static;
 use petgraph::algo::FloatMeasure;
 
@@ -525,3 +531,323 @@ fn test_route_maps() {
     assert!(original_net == save_1);
     assert_eq!(original_net.undo_action(), Ok(false));
 }
+
+#[test]
+fn test_route_origin_validation() {
+    let mut net = get_test_net_bgp();
+
+    let p = Prefix(0);
+
+    // advertise the prefix on both external routers, originated by AS65201
+    net.advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None).unwrap();
+    net.advertise_external_route(*E4, p, vec![AsId(65104), AsId(65201)], None, None).unwrap();
+
+    // add a stale ROA on both border routers, which does not match the actual origin
+    net.apply_modifier(&Insert(Roa { router: *R1, prefix: p, origin: AsId(65202) })).unwrap();
+    net.apply_modifier(&Insert(Roa { router: *R4, prefix: p, origin: AsId(65202) })).unwrap();
+
+    // without ROV, the routes are still used.
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *E1]));
+    assert_eq!(net.get_route(*R2, p), Ok(vec![*R2, *R4, *E4]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R1, *E1]));
+    assert_eq!(net.get_route(*R4, p), Ok(vec![*R4, *E4]));
+
+    let r1 = net.get_device(*R1).unwrap_internal();
+    let route = r1.get_known_bgp_routes(p).unwrap().into_iter().find(|e| e.from_id == *E1);
+    assert_eq!(r1.get_rov_state(&route.unwrap().route), RovState::Invalid);
+
+    // enable ROV on R1
+    let save_1 = net.clone();
+    net.apply_modifier(&Insert(RovPolicy { router: *R1, policy: Rov::RejectInvalid })).unwrap();
+
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *R3, *R4, *E4]));
+    assert_eq!(net.get_route(*R2, p), Ok(vec![*R2, *R4, *E4]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R4, *E4]));
+    assert_eq!(net.get_route(*R4, p), Ok(vec![*R4, *E4]));
+
+    // enable ROV on R4, which blackholes the prefix
+    let save_2 = net.clone();
+    net.apply_modifier(&Insert(RovPolicy { router: *R4, policy: Rov::RejectInvalid })).unwrap();
+
+    for router in net.get_routers().iter() {
+        assert_eq!(
+            net.get_route(*router, p),
+            Err(NetworkError::ForwardingBlackHole(vec![*router]))
+        );
+    }
+
+    // fix the ROA on R4
+    let save_3 = net.clone();
+    net.apply_modifier(&Update {
+        from: Roa { router: *R4, prefix: p, origin: AsId(65202) },
+        to: Roa { router: *R4, prefix: p, origin: AsId(65201) },
+    })
+    .unwrap();
+
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *R3, *R4, *E4]));
+    assert_eq!(net.get_route(*R2, p), Ok(vec![*R2, *R4, *E4]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R4, *E4]));
+    assert_eq!(net.get_route(*R4, p), Ok(vec![*R4, *E4]));
+
+    // only deprefer invalid routes on R1, which still prefers the valid route from R4
+    let save_4 = net.clone();
+    net.apply_modifier(&Update {
+        from: RovPolicy { router: *R1, policy: Rov::RejectInvalid },
+        to: RovPolicy { router: *R1, policy: Rov::DepreferInvalid(50) },
+    })
+    .unwrap();
+
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *R3, *R4, *E4]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R4, *E4]));
+
+    // disable ROV on R1
+    let save_5 = net.clone();
+    net.apply_modifier(&Remove(RovPolicy { router: *R1, policy: Rov::DepreferInvalid(50) }))
+        .unwrap();
+
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *E1]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R1, *E1]));
+
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_5);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_4);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_3);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_2);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_1);
+}
+
+#[test]
+fn test_graceful_restart() {
+    let mut net = get_test_net_bgp();
+
+    let p = Prefix(0);
+
+    net.advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None).unwrap();
+    net.advertise_external_route(*E4, p, vec![AsId(65104), AsId(65201)], None, None).unwrap();
+
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *E1]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R1, *E1]));
+
+    // without graceful restart, closing the session immediately withdraws the routes
+    let mut test_net = net.clone();
+    test_net
+        .apply_modifier(&Remove(BgpSession { source: *R1, target: *E1, session_type: EBgp }))
+        .unwrap();
+    assert_eq!(test_net.get_route(*R1, p), Ok(vec![*R1, *R3, *R4, *E4]));
+    assert_eq!(test_net.get_route(*R3, p), Ok(vec![*R3, *R4, *E4]));
+
+    // enable graceful restart on R1 for the session with E1
+    let save_1 = net.clone();
+    net.apply_modifier(&Insert(BgpGracefulRestart { router: *R1, neighbor: *E1 })).unwrap();
+    assert!(net.get_device(*R1).unwrap_internal().has_graceful_restart(*E1));
+
+    // close the session. The routes are retained
+    let save_2 = net.clone();
+    net.apply_modifier(&Remove(BgpSession { source: *R1, target: *E1, session_type: EBgp }))
+        .unwrap();
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *E1]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R1, *E1]));
+    assert_eq!(net.get_device(*R1).unwrap_internal().get_stale_bgp_sessions().count(), 1);
+
+    // re-establish the session, which replaces the stale routes
+    let save_3 = net.clone();
+    net.apply_modifier(&Insert(BgpSession { source: *R1, target: *E1, session_type: EBgp }))
+        .unwrap();
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *E1]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R1, *E1]));
+    assert_eq!(net.get_device(*R1).unwrap_internal().get_stale_bgp_sessions().count(), 0);
+
+    // close the session again, and disable graceful restart, which flushes the stale routes
+    let save_4 = net.clone();
+    net.apply_modifier(&Remove(BgpSession { source: *R1, target: *E1, session_type: EBgp }))
+        .unwrap();
+    let save_5 = net.clone();
+    net.apply_modifier(&Remove(BgpGracefulRestart { router: *R1, neighbor: *E1 })).unwrap();
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *R3, *R4, *E4]));
+    assert_eq!(net.get_route(*R3, p), Ok(vec![*R3, *R4, *E4]));
+    assert_eq!(net.get_device(*R1).unwrap_internal().get_stale_bgp_sessions().count(), 0);
+
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_5);
+    assert_eq!(net.get_route(*R1, p), Ok(vec![*R1, *E1]));
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_4);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_3);
+    assert_eq!(net.get_device(*R1).unwrap_internal().get_stale_bgp_sessions().count(), 1);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_2);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_1);
+}
+
+#[test]
+fn test_bgp_relationships() {
+    let mut net = get_test_net_bgp();
+
+    let p = Prefix(0);
+
+    // the prefix is only advertised by E1
+    net.advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None).unwrap();
+
+    let mut hard_policy = HardPolicy::globally(vec![Condition::ValleyFree(*R4, p)]);
+
+    // without any relationship, both neighbors count as non-customers.
+    assert_eq!(net.get_device(*R4).unwrap_internal().get_valley_exports(p), vec![*E4]);
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(!hard_policy.check());
+
+    // E1 is a customer, which is exported to everyone
+    let save_1 = net.clone();
+    net.apply_modifier(&Insert(BgpRelationship { neighbor: *E1, relationship: Rel::Customer }))
+        .unwrap();
+    assert_eq!(
+        net.get_device(*R3).unwrap_internal().get_bgp_relationship(*E1),
+        Some(Rel::Customer)
+    );
+    let r4 = net.get_device(*R4).unwrap_internal();
+    assert_eq!(r4.get_valley_exports(p), Vec::<RouterId>::new());
+    hard_policy.reset();
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
+
+    // migrate E1 to a peer. Since E4 has no relationship, the route is still exported.
+    let save_2 = net.clone();
+    net.apply_modifier(&Update {
+        from: BgpRelationship { neighbor: *E1, relationship: Rel::Customer },
+        to: BgpRelationship { neighbor: *E1, relationship: Rel::Peer },
+    })
+    .unwrap();
+    assert_eq!(net.get_device(*R4).unwrap_internal().get_valley_exports(p), vec![*E4]);
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(!hard_policy.check());
+
+    // E4 is a provider, so the route is no longer exported to E4.
+    let save_3 = net.clone();
+    net.apply_modifier(&Insert(BgpRelationship { neighbor: *E4, relationship: Rel::Provider }))
+        .unwrap();
+    let r4 = net.get_device(*R4).unwrap_internal();
+    assert_eq!(r4.get_valley_exports(p), Vec::<RouterId>::new());
+    hard_policy.reset();
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
+
+    // forwarding inside the network is not affected
+    assert!(net.get_route(*R4, p).is_ok());
+
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_3);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_2);
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == save_1);
+}
+
+#[test]
+fn test_abort_convergence() {
+    let mut net = get_test_net_bgp();
+    let mut net_no_abort = net.clone();
+
+    let abort = ControlHandle::new();
+    abort.send_stop();
+    net.set_abort(Some(abort));
+    assert!(net.get_abort().is_some());
+
+    // the convergence is interrupted by the control handle
+    let mut result = Ok(());
+    for p in (0..10).map(Prefix) {
+        result = net.advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None);
+        if result.is_err() {
+            break;
+        }
+    }
+    assert_eq!(result, Err(NetworkError::Aborted));
+
+    // the network without a control handle converges
+    assert!(net_no_abort.get_abort().is_none());
+    for p in (0..10).map(Prefix) {
+        net_no_abort
+            .advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None)
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_router_roles() {
+    let mut net = get_test_net_bgp();
+
+    // R1 and R4 have eBGP sessions
+    net.annotate_roles_from_config();
+    assert_eq!(net.get_routers_with_role(RouterRole::Border), vec![*R1, *R4]);
+    assert_eq!(net.get_routers_with_role(RouterRole::Core), vec![*R2, *R3]);
+    assert_eq!(net.get_routers_with_role(RouterRole::RouteReflector), Vec::<RouterId>::new());
+
+    net.add_router_role(*R3, RouterRole::Firewall).unwrap();
+    assert_eq!(net.get_router_roles(*R3), vec![RouterRole::Core, RouterRole::Firewall]);
+    assert!(net.has_router_role(*R3, RouterRole::Firewall));
+    assert_eq!(
+        net.add_router_role(*E1, RouterRole::Border),
+        Err(NetworkError::DeviceIsExternalRouter(*E1))
+    );
+
+    // roles are kept when cloning
+    let net_clone = net.clone();
+    assert_eq!(net_clone.get_router_roles(*R3), vec![RouterRole::Core, RouterRole::Firewall]);
+
+    // re-annotating keeps the firewall
+    net.annotate_roles_from_config();
+    assert!(net.has_router_role(*R3, RouterRole::Firewall));
+    assert!(net.remove_router_role(*R3, RouterRole::Firewall));
+    assert!(!net.remove_router_role(*R3, RouterRole::Firewall));
+    assert_eq!(net.get_router_roles(*R3), vec![RouterRole::Core]);
+
+    // reconfigure core before border routers
+    let mut modifiers = vec![
+        Insert(StaticRoute { router: *R1, prefix: Prefix(0), target: *E1 }),
+        Insert(StaticRoute { router: *R2, prefix: Prefix(0), target: *R1 }),
+        Insert(StaticRoute { router: *R4, prefix: Prefix(0), target: *E4 }),
+        Insert(StaticRoute { router: *R3, prefix: Prefix(0), target: *R1 }),
+    ];
+    sort_by_router_roles(&net, &mut modifiers, &[RouterRole::Core, RouterRole::Border]);
+    assert_eq!(
+        modifiers.iter().map(|m| m.routers()[0]).collect::<Vec<_>>(),
+        vec![*R2, *R3, *R1, *R4]
+    );
+}
+
+#[test]
+fn test_clone_on_write() {
+    let net = get_test_net_bgp();
+    let mut net_clone = net.clone();
+    assert!(net_clone.shares_topology(&net));
+
+    // changing a BGP session does not copy the topology
+    net_clone
+        .apply_modifier(&Remove(BgpSession { source: *R1, target: *R2, session_type: IBgpPeer }))
+        .unwrap();
+    assert!(net_clone.shares_topology(&net));
+    assert!(net_clone.current_config() != net.current_config());
+
+    // changing a link weight copies the topology, and leaves the original untouched
+    net_clone
+        .apply_modifier(&Update {
+            from: IgpLinkWeight { source: *R1, target: *R2, weight: 5.0 },
+            to: IgpLinkWeight { source: *R1, target: *R2, weight: 1.0 },
+        })
+        .unwrap();
+    assert!(!net_clone.shares_topology(&net));
+    let weight = |n: &Network| {
+        let topo = n.get_topology();
+        *topo.edge_weight(topo.find_edge(*R1, *R2).unwrap()).unwrap()
+    };
+    assert_eq!(weight(&net), 5.0);
+    assert_eq!(weight(&net_clone), 1.0);
+}
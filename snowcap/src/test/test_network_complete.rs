@@ -82,6 +82,73 @@ fn test_simple() {
     assert_route_equal(&t, b1, prefix, vec![b1, e1]);
 }
 
+#[test]
+fn undo_tracking_disabled() {
+    // r0 ----- r1
+    // |        |
+    // b0       b1   internal
+    // |........|............
+    // |        |    external
+    // e0       e1
+    let mut t = Network::new();
+    t.set_undo_tracking(false);
+
+    let prefix = Prefix(0);
+
+    let e0 = t.add_external_router("E0", AsId(1));
+    let b0 = t.add_router("B0");
+    let r0 = t.add_router("R0");
+    let r1 = t.add_router("R1");
+    let b1 = t.add_router("B1");
+    let e1 = t.add_external_router("E1", AsId(1));
+
+    t.add_link(e0, b0);
+    t.add_link(b0, r0);
+    t.add_link(r0, r1);
+    t.add_link(r1, b1);
+    t.add_link(b1, e1);
+
+    let mut c = Config::new();
+    for &(a, b) in [(e0, b0), (b0, r0), (r0, r1), (r1, b1), (b1, e1)].iter() {
+        c.add(ConfigExpr::IgpLinkWeight { source: a, target: b, weight: 1.0 }).unwrap();
+        c.add(ConfigExpr::IgpLinkWeight { source: b, target: a, weight: 1.0 }).unwrap();
+    }
+    c.add(ConfigExpr::BgpSession { source: e0, target: b0, session_type: EBgp }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: r0, target: b0, session_type: IBgpClient }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: r0, target: r1, session_type: IBgpPeer }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: r1, target: b1, session_type: IBgpClient }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: e1, target: b1, session_type: EBgp }).unwrap();
+
+    t.set_config(&c).unwrap();
+
+    t.advertise_external_route(e0, prefix, vec![AsId(1), AsId(2), AsId(3)], None, None).unwrap();
+    t.advertise_external_route(e1, prefix, vec![AsId(1), AsId(2), AsId(3)], None, None).unwrap();
+
+    // the simulation is not affected by the missing undo tracking
+    assert_route_equal(&t, b0, prefix, vec![b0, e0]);
+    assert_route_equal(&t, r0, prefix, vec![r0, b0, e0]);
+    assert_route_equal(&t, r1, prefix, vec![r1, b1, e1]);
+    assert_route_equal(&t, b1, prefix, vec![b1, e1]);
+
+    // undo is refused, and the clone inherits the setting
+    let save = t.clone();
+    assert!(!save.get_undo_tracking());
+    assert_eq!(t.undo_action(), Err(NetworkError::UndoDisabled));
+    assert!(t == save);
+
+    // after enabling the tracking, only the new actions can be undone
+    t.set_undo_tracking(true);
+    t.apply_modifier(&ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: r0,
+        target: r1,
+        session_type: IBgpPeer,
+    }))
+    .unwrap();
+    assert_eq!(t.undo_action(), Ok(true));
+    assert!(t == save);
+    assert_eq!(t.undo_action(), Ok(false));
+}
+
 #[test]
 fn test_external_router() {
     // Topology:
@@ -283,6 +350,194 @@ fn test_route_order1() {
     assert_route_equal(&t, b1, prefix, vec![b1, e1]);
 }
 
+#[test]
+fn as_path_prepend_and_med() {
+    // All weights are 1, b0 and b1 are iBGP peers
+    //
+    // b0 ----- b1   internal
+    // |........|............
+    // |        |    external
+    // e0       e1
+    let mut t = Network::new();
+
+    let prefix = Prefix(0);
+
+    let e0 = t.add_external_router("E0", AsId(1));
+    let b0 = t.add_router("B0");
+    let b1 = t.add_router("B1");
+    let e1 = t.add_external_router("E1", AsId(1));
+
+    t.add_link(e0, b0);
+    t.add_link(b0, b1);
+    t.add_link(b1, e1);
+
+    let mut c = Config::new();
+    c.add(ConfigExpr::IgpLinkWeight { source: e0, target: b0, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: e0, source: b0, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { source: b0, target: b1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: b0, source: b1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { source: b1, target: e1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: b1, source: e1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: e0, target: b0, session_type: EBgp }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: b0, target: b1, session_type: IBgpPeer }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: e1, target: b1, session_type: EBgp }).unwrap();
+
+    t.set_config(&c).unwrap();
+
+    t.advertise_external_route(e0, prefix, vec![AsId(1), AsId(2)], None, None).unwrap();
+    t.advertise_external_route(e1, prefix, vec![AsId(1), AsId(2)], None, None).unwrap();
+
+    assert_route_equal(&t, b0, prefix, vec![b0, e0]);
+    assert_route_equal(&t, b1, prefix, vec![b1, e1]);
+
+    // prepending the AS path on b0 makes the route of e1 preferable
+    let prepend = ConfigExpr::BgpRouteMap {
+        router: b0,
+        direction: RouteMapDirection::Incoming,
+        map: RouteMapBuilder::new()
+            .order(10)
+            .allow()
+            .match_neighbor(e0)
+            .prepend_as_path(AsId(1), 2)
+            .build(),
+    };
+    t.apply_modifier(&ConfigModifier::Insert(prepend.clone())).unwrap();
+    assert_route_equal(&t, b0, prefix, vec![b0, b1, e1]);
+    assert_route_equal(&t, b1, prefix, vec![b1, e1]);
+
+    t.apply_modifier(&ConfigModifier::Remove(prepend)).unwrap();
+    assert_route_equal(&t, b0, prefix, vec![b0, e0]);
+
+    // a higher MED on b1 makes the route of e0 preferable, since both come from the same AS
+    let med = ConfigExpr::BgpRouteMap {
+        router: b1,
+        direction: RouteMapDirection::Incoming,
+        map: RouteMapBuilder::new().order(10).allow().match_neighbor(e1).set_med(100).build(),
+    };
+    t.apply_modifier(&ConfigModifier::Insert(med)).unwrap();
+    assert_route_equal(&t, b0, prefix, vec![b0, e0]);
+    assert_route_equal(&t, b1, prefix, vec![b1, b0, e0]);
+}
+
+#[test]
+fn community_list_filter() {
+    // b0 and b1 are iBGP peers, b0 drops all routes tagged with both communities 1 and 2
+    //
+    // b0 ----- b1   internal
+    // |........|............
+    // |        |    external
+    // e0       e1
+    let mut t = Network::new();
+
+    let prefix = Prefix(0);
+
+    let e0 = t.add_external_router("E0", AsId(1));
+    let b0 = t.add_router("B0");
+    let b1 = t.add_router("B1");
+    let e1 = t.add_external_router("E1", AsId(2));
+
+    t.add_link(e0, b0);
+    t.add_link(b0, b1);
+    t.add_link(b1, e1);
+
+    let mut c = Config::new();
+    c.add(ConfigExpr::IgpLinkWeight { source: e0, target: b0, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: e0, source: b0, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { source: b0, target: b1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: b0, source: b1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { source: b1, target: e1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: b1, source: e1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: e0, target: b0, session_type: EBgp }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: b0, target: b1, session_type: IBgpPeer }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: e1, target: b1, session_type: EBgp }).unwrap();
+    c.add(ConfigExpr::BgpRouteMap {
+        router: b0,
+        direction: RouteMapDirection::Incoming,
+        map: RouteMapBuilder::new().order(10).deny().match_community_all(vec![1, 2]).build(),
+    })
+    .unwrap();
+
+    t.set_config(&c).unwrap();
+
+    t.advertise_external_route_with_communities(e0, prefix, vec![AsId(1)], None, vec![1, 3])
+        .unwrap();
+    t.advertise_external_route(e1, prefix, vec![AsId(2), AsId(3)], None, None).unwrap();
+
+    // the route of e0 carries only one of the communities, and is shorter
+    assert_route_equal(&t, b0, prefix, vec![b0, e0]);
+    assert_route_equal(&t, b1, prefix, vec![b1, b0, e0]);
+
+    // tag the route with both communities
+    t.advertise_external_route_with_communities(e0, prefix, vec![AsId(1)], None, vec![1, 2])
+        .unwrap();
+    assert_route_equal(&t, b0, prefix, vec![b0, b1, e1]);
+    assert_route_equal(&t, b1, prefix, vec![b1, e1]);
+}
+
+#[test]
+fn static_route_redistribution() {
+    // All weights are 1, b0 and b1 are iBGP peers. Only e0 advertises the prefix, and b1 pins the
+    // traffic towards e1 with a static route.
+    //
+    // b0 ----- b1   internal
+    // |........|............
+    // |        |    external
+    // e0       e1
+    let mut t = Network::new();
+
+    let prefix = Prefix(0);
+
+    let e0 = t.add_external_router("E0", AsId(1));
+    let b0 = t.add_router("B0");
+    let b1 = t.add_router("B1");
+    let e1 = t.add_external_router("E1", AsId(2));
+
+    t.add_link(e0, b0);
+    t.add_link(b0, b1);
+    t.add_link(b1, e1);
+
+    let mut c = Config::new();
+    c.add(ConfigExpr::IgpLinkWeight { source: e0, target: b0, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: e0, source: b0, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { source: b0, target: b1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: b0, source: b1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { source: b1, target: e1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::IgpLinkWeight { target: b1, source: e1, weight: 1.0 }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: e0, target: b0, session_type: EBgp }).unwrap();
+    c.add(ConfigExpr::BgpSession { source: b0, target: b1, session_type: IBgpPeer }).unwrap();
+
+    t.set_config(&c).unwrap();
+
+    t.advertise_external_route(e0, prefix, vec![AsId(1)], None, None).unwrap();
+
+    assert_route_equal(&t, b0, prefix, vec![b0, e0]);
+    assert_route_equal(&t, b1, prefix, vec![b1, b0, e0]);
+
+    // the static route is preferred over BGP, but only on b1
+    let static_route = ConfigExpr::StaticRoute { router: b1, prefix, target: e1 };
+    t.apply_modifier(&ConfigModifier::Insert(static_route.clone())).unwrap();
+    assert_route_equal(&t, b0, prefix, vec![b0, e0]);
+    assert_route_equal(&t, b1, prefix, vec![b1, e1]);
+
+    // redistributing the static route attracts the traffic of b0, since the route is shorter
+    let before_redistribution = t.clone();
+    let redistribute = ConfigExpr::RedistributeStatic { router: b1 };
+    t.apply_modifier(&ConfigModifier::Insert(redistribute.clone())).unwrap();
+    assert!(t.get_device(b1).unwrap_internal().get_redistribute_static());
+    assert_route_equal(&t, b0, prefix, vec![b0, b1, e1]);
+    assert_route_equal(&t, b1, prefix, vec![b1, e1]);
+
+    // undo restores the previous state
+    assert_eq!(t.undo_action(), Ok(true));
+    assert!(t == before_redistribution);
+    t.apply_modifier(&ConfigModifier::Insert(redistribute)).unwrap();
+
+    // removing the static route withdraws the redistributed route
+    t.apply_modifier(&ConfigModifier::Remove(static_route)).unwrap();
+    assert_route_equal(&t, b0, prefix, vec![b0, e0]);
+    assert_route_equal(&t, b1, prefix, vec![b1, b0, e0]);
+}
+
 #[test]
 fn test_route_order2() {
     // All weights are 1
@@ -0,0 +1,100 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+
+use crate::netsim::config::ConfigExpr;
+use crate::netsim::route_map::RouteMapDirection::Incoming;
+use crate::netsim::{BgpSessionType, Prefix};
+use crate::network_spec::*;
+use crate::strategies::{Strategy, StrategyTRTA};
+use crate::ControlHandle;
+
+fn spec_file() -> String {
+    format!("{}/test_files/simple_spec.yaml", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn load_yaml() {
+    let spec = NetworkSpec::from_file(spec_file()).unwrap();
+    assert_eq!(spec.routers.len(), 4);
+    assert_eq!(spec.external_routers.len(), 2);
+    assert_eq!(spec.initial_config.len(), 9);
+
+    let (net, final_config, hard_policy) = spec.build().unwrap();
+    let r1 = net.get_router_id("r1").unwrap();
+    let r4 = net.get_router_id("r4").unwrap();
+    assert_eq!(net.get_routers().len(), 4);
+    assert_eq!(net.get_known_prefixes().len(), 1);
+    // symmetric link weights are expanded
+    assert_eq!(net.current_config().len(), 14);
+    assert_eq!(hard_policy.prop_vars.len(), 4);
+    assert!(final_config.iter().any(|e| e
+        == &ConfigExpr::BgpSession {
+            source: r1,
+            target: r4,
+            session_type: BgpSessionType::IBgpPeer
+        }));
+    assert!(final_config.iter().any(|e| matches!(
+        e,
+        ConfigExpr::BgpRouteMap { router, direction: Incoming, .. } if *router == r1
+    )));
+
+    let sequence =
+        StrategyTRTA::synthesize(net, final_config, hard_policy, None, ControlHandle::new())
+            .unwrap();
+    assert_eq!(sequence.len(), 3);
+}
+
+#[test]
+fn json_roundtrip() {
+    let spec = NetworkSpec::from_file(spec_file()).unwrap();
+    let json = serde_json::to_string(&spec).unwrap();
+    assert_eq!(NetworkSpec::from_json(json.as_bytes()).unwrap(), spec);
+}
+
+#[test]
+fn default_policy() {
+    let mut spec = NetworkSpec::from_file(spec_file()).unwrap();
+    spec.policy = None;
+    let (net, _, hard_policy) = spec.build().unwrap();
+    assert_eq!(
+        hard_policy.prop_vars.len(),
+        net.get_routers().len() * net.get_known_prefixes().len()
+    );
+    assert!(net.get_known_prefixes().contains(&Prefix(0)));
+}
+
+#[test]
+fn errors() {
+    let unknown = r#"{"routers": ["r1"], "links": [["r1", "r2"]]}"#;
+    let spec = NetworkSpec::from_json(unknown.as_bytes()).unwrap();
+    assert!(matches!(spec.build(), Err(NetworkSpecError::UnknownRouter(r)) if r == "r2"));
+
+    let duplicate = r#"{"routers": ["r1"], "external_routers": [{"name": "r1", "as_id": 1}]}"#;
+    let spec = NetworkSpec::from_json(duplicate.as_bytes()).unwrap();
+    assert!(matches!(spec.build(), Err(NetworkSpecError::DuplicateRouter(r)) if r == "r1"));
+
+    let policy = r#"{"routers": ["r1"], "policy": "G(Reachable(r2, 0))"}"#;
+    let spec = NetworkSpec::from_json(policy.as_bytes()).unwrap();
+    assert!(matches!(spec.build(), Err(NetworkSpecError::PolicyParseError(_))));
+
+    let unknown_field = r#"{"routers": ["r1"], "rooters": []}"#;
+    assert!(matches!(
+        NetworkSpec::from_json(unknown_field.as_bytes()),
+        Err(NetworkSpecError::JsonError(_))
+    ));
+}
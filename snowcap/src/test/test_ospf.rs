@@ -0,0 +1,156 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::netsim::config::{Config, ConfigExpr, ConfigModifier};
+use crate::netsim::{AsId, LinkWeight, Network, OspfArea, RouterId};
+
+/// Network with the backbone `b0 -- b1`, area 1 with `x` and `y` (attached to `b0` and `b1`), and
+/// area 2 with `z` (attached to `b1`). The external router `e` is connected to `b1`.
+///
+/// ```text
+///   x ---(10)--- y        area 1
+///   |            |
+///   b0 -------- b1 ---- e backbone
+///                |
+///                z        area 2
+/// ```
+fn hierarchical_net() -> (Network, [RouterId; 6]) {
+    let mut net = Network::new();
+    let b0 = net.add_router("b0");
+    let b1 = net.add_router("b1");
+    let x = net.add_router("x");
+    let y = net.add_router("y");
+    let z = net.add_router("z");
+    let e = net.add_external_router("e", AsId(65101));
+
+    let links = vec![
+        (b0, b1, 1.0, OspfArea::BACKBONE),
+        (b1, e, 1.0, OspfArea::BACKBONE),
+        (b0, x, 1.0, OspfArea(1)),
+        (x, y, 10.0, OspfArea(1)),
+        (y, b1, 1.0, OspfArea(1)),
+        (b1, z, 1.0, OspfArea(2)),
+    ];
+
+    let mut config = Config::new();
+    for (a, b, weight, area) in links {
+        net.add_link(a, b);
+        config.add(ConfigExpr::IgpLinkWeight { source: a, target: b, weight }).unwrap();
+        config.add(ConfigExpr::IgpLinkWeight { source: b, target: a, weight }).unwrap();
+        if !area.is_backbone() {
+            config.add(ConfigExpr::OspfArea { router_a: a, router_b: b, area }).unwrap();
+        }
+    }
+    net.set_config(&config).unwrap();
+
+    (net, [b0, b1, x, y, z, e])
+}
+
+fn igp_route(net: &Network, router: RouterId, dst: RouterId) -> Option<(RouterId, LinkWeight)> {
+    net.get_device(router).unwrap_internal().get_igp_fw_table()[&dst]
+}
+
+#[test]
+fn intra_area_preferred() {
+    let (net, [b0, b1, x, y, ..]) = hierarchical_net();
+    // the path through the backbone would be cheaper, but the intra-area path is preferred.
+    assert_eq!(igp_route(&net, x, y), Some((y, 10.0)));
+    assert_eq!(igp_route(&net, y, x), Some((x, 10.0)));
+    // ABRs still use the backbone between each other
+    assert_eq!(igp_route(&net, b0, b1), Some((b1, 1.0)));
+    assert_eq!(igp_route(&net, b1, b0), Some((b0, 1.0)));
+}
+
+#[test]
+fn inter_area_through_abr() {
+    let (net, [b0, b1, x, y, z, e]) = hierarchical_net();
+    assert_eq!(igp_route(&net, x, z), Some((b0, 3.0)));
+    assert_eq!(igp_route(&net, y, z), Some((b1, 2.0)));
+    // b1 has an intra-area path to x, which it prefers over the cheaper path through b0.
+    assert_eq!(igp_route(&net, b1, x), Some((y, 11.0)));
+    assert_eq!(igp_route(&net, z, x), Some((b1, 12.0)));
+    assert_eq!(igp_route(&net, x, e), Some((b0, 3.0)));
+    assert_eq!(igp_route(&net, b0, z), Some((b1, 2.0)));
+    assert_eq!(net.get_igp_areas().get_area(b1, z), OspfArea(2));
+    assert_eq!(net.get_igp_areas().get_area(z, b1), OspfArea(2));
+}
+
+#[test]
+fn flat_without_areas() {
+    let (mut net, [b0, b1, x, y, z, _]) = hierarchical_net();
+    for (a, b) in vec![(b0, x), (x, y), (y, b1), (b1, z)] {
+        let area = net.get_igp_areas().get_area(a, b);
+        net.apply_modifier(&ConfigModifier::Remove(ConfigExpr::OspfArea {
+            router_a: a,
+            router_b: b,
+            area,
+        }))
+        .unwrap();
+    }
+    assert_eq!(igp_route(&net, x, y), Some((b0, 3.0)));
+    assert_eq!(igp_route(&net, x, z), Some((b0, 3.0)));
+}
+
+#[test]
+fn stub_area_default_route() {
+    let (mut net, [b0, _, x, _, z, e]) = hierarchical_net();
+    let before = net.clone();
+    net.apply_modifier(&ConfigModifier::Insert(ConfigExpr::OspfStubArea { area: OspfArea(1) }))
+        .unwrap();
+    // the external router is reached by the default route towards the closest ABR
+    assert_eq!(igp_route(&net, x, e), Some((b0, 2.0)));
+    // internal routers of other areas are still reachable with their summaries
+    assert_eq!(igp_route(&net, x, z), Some((b0, 3.0)));
+
+    assert_eq!(net.undo_action(), Ok(true));
+    assert!(net == before);
+    assert_eq!(igp_route(&net, x, e), Some((b0, 3.0)));
+}
+
+#[test]
+fn area_renumbering() {
+    let (mut net, [b0, b1, x, y, z, _]) = hierarchical_net();
+    let before = net.clone();
+
+    // move the first link of area 1 into area 3. Now, x is no longer an internal router of area 1,
+    // but it still reaches z through b0.
+    net.apply_modifier(&ConfigModifier::Update {
+        from: ConfigExpr::OspfArea { router_a: b0, router_b: x, area: OspfArea(1) },
+        to: ConfigExpr::OspfArea { router_a: x, router_b: b0, area: OspfArea(3) },
+    })
+    .unwrap();
+    assert_eq!(igp_route(&net, x, y), Some((y, 10.0)));
+    assert_eq!(igp_route(&net, x, z), Some((b0, 3.0)));
+    // y is only attached to b1 in area 1, and reaches b0 through the backbone.
+    assert_eq!(igp_route(&net, y, b0), Some((b1, 2.0)));
+
+    // move the remaining links
+    for (a, b) in vec![(x, y), (y, b1)] {
+        net.apply_modifier(&ConfigModifier::Update {
+            from: ConfigExpr::OspfArea { router_a: a, router_b: b, area: OspfArea(1) },
+            to: ConfigExpr::OspfArea { router_a: a, router_b: b, area: OspfArea(3) },
+        })
+        .unwrap();
+    }
+    assert_eq!(igp_route(&net, y, b0), Some((x, 11.0)));
+
+    for _ in 0..3 {
+        assert_eq!(net.undo_action(), Ok(true));
+    }
+    assert!(net == before);
+}
@@ -0,0 +1,87 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::plan_editor::PlanEditor;
+use crate::strategies::{Strategy, StrategyTRTA};
+use crate::{ControlHandle, Error};
+
+use std::time::Duration;
+
+fn test_parallel<N: ExampleNetwork>(num_threads: usize, time_budget: Option<Duration>) {
+    let net = N::net(0);
+    let final_config = N::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    let mut strategy =
+        StrategyTRTA::new(net.clone(), modifiers.clone(), hard_policy.clone(), time_budget)
+            .unwrap();
+    match strategy.work_parallel(ControlHandle::new(), Some(num_threads)) {
+        Ok(sequence) => {
+            assert_eq!(sequence.len(), modifiers.len());
+            let editor = PlanEditor::new(&net, sequence, hard_policy).unwrap();
+            assert!(editor.is_valid());
+        }
+        Err(Error::Timeout(partial)) if time_budget.is_some() => {
+            assert_eq!(partial.num_modifiers(), modifiers.len());
+            let editor = PlanEditor::new(&net, partial.sequence.clone(), hard_policy).unwrap();
+            assert!(editor.is_valid());
+        }
+        Err(e) => panic!("Unexpected error: {}", e),
+    }
+
+    // the groups are kept in the strategy
+    assert_eq!(strategy.groups().iter().map(|g| g.len()).sum::<usize>(), modifiers.len());
+}
+
+#[test]
+fn test_parallel_single_thread() {
+    test_parallel::<ChainGadget<Repetition5>>(1, None);
+    test_parallel::<DifficultGadgetMinimal>(1, None);
+}
+
+#[test]
+fn test_parallel_multiple_threads() {
+    test_parallel::<ChainGadget<Repetition5>>(4, None);
+    test_parallel::<DifficultGadgetMinimal>(4, None);
+}
+
+#[test]
+fn test_parallel_timeout() {
+    test_parallel::<ChainGadget<Repetition10>>(4, Some(Duration::from_secs(0)));
+}
+
+#[test]
+fn test_parallel_abort() {
+    let net = ChainGadget::<Repetition10>::net(0);
+    let final_config = ChainGadget::<Repetition10>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let mut strategy = StrategyTRTA::new(net, modifiers, hard_policy, None).unwrap();
+    let abort = ControlHandle::new();
+    abort.send_stop();
+    match strategy.work_parallel(abort, Some(4)) {
+        Err(Error::Abort) => {}
+        r => panic!("Expected the operation to be aborted, but got {:?}", r),
+    }
+}
@@ -0,0 +1,177 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate as snowcap;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::netsim::{NetworkError, Prefix, RouterId};
+use snowcap_ltl_parser::ltl;
+
+#[test]
+fn test_parse_ltl_like_macro() {
+    assert_eq!(parse_ltl("0").unwrap().ast(), ltl!(0).ast());
+    assert_eq!(parse_ltl("G(And(0, 1, 2))").unwrap().ast(), ltl!(G(And(0, 1, 2))).ast());
+    assert_eq!(
+        parse_ltl("U(Or(0, Not(1)), G(Implies(2, 3)))").unwrap().ast(),
+        ltl!(U(Or(0, Not(1)), G(Implies(2, 3)))).ast()
+    );
+    assert_eq!(
+        parse_ltl("W(Xor(0, 1), M(Iff(2, 3), F(N(4))))").unwrap().ast(),
+        ltl!(W(Xor(0, 1), M(Iff(2, 3), F(N(4))))).ast()
+    );
+    assert_eq!(parse_ltl("r(true, false)").unwrap().ast(), ltl!(r(true, false)).ast());
+}
+
+#[test]
+fn test_parse_ltl_precedence() {
+    assert_eq!(parse_ltl("0 || 1 && 2").unwrap().ast(), ltl!(0 || 1 && 2).ast());
+    assert_eq!(parse_ltl("0 + 1 * 2").unwrap().ast(), ltl!(0 + 1 * 2).ast());
+    assert_eq!(parse_ltl("!0 & 1 | 2 ^ 3").unwrap().ast(), ltl!(!0 & 1 | 2 ^ 3).ast());
+    assert_eq!(parse_ltl("0 >> 1 << 2").unwrap().ast(), ltl!(0 >> 1 << 2).ast());
+    assert_eq!(parse_ltl("(0 || 1) && -2").unwrap().ast(), ltl!((0 || 1) && -2).ast());
+    assert_eq!(parse_ltl("0 == 1 && 2 <= 3").unwrap().ast(), ltl!(0 == 1 && 2 <= 3).ast());
+    assert_eq!(parse_ltl("G(0) & F(1)").unwrap().ast(), ltl!(G(0) & F(1)).ast());
+}
+
+#[test]
+fn test_parse_ltl_errors() {
+    assert!(parse_ltl("").is_err());
+    assert!(parse_ltl("G(0, 1)").is_err());
+    assert!(parse_ltl("Foo(0)").is_err());
+    assert!(parse_ltl("G(0").is_err());
+    assert!(parse_ltl("0 1").is_err());
+    assert!(parse_ltl("0 $ 1").is_err());
+    assert_eq!(parse_ltl("G(0) &").unwrap_err().position, 6);
+    // conditions can only be parsed with a network
+    assert!(parse_ltl("Reachability(r1, prefix 0)").is_err());
+}
+
+#[test]
+fn test_parse_hard_policy() {
+    let net = SimpleNet::net(0);
+    let r = |name: &str| net.get_router_id(name).unwrap();
+    let p = Prefix(0);
+
+    let policy = HardPolicy::parse(
+        "# reachability with a waypoint
+         G(Reachability(r1, prefix 0) & Reachable(\"r2\", 0, [* -> r3 -> ?]));
+         F(Isolation(r4, prefix 0)) # the last line",
+        &net,
+    )
+    .unwrap();
+    assert_eq!(
+        policy.prop_vars,
+        vec![
+            Condition::Reachable(r("r1"), p, None),
+            Condition::Reachable(
+                r("r2"),
+                p,
+                Some(PathCondition::Positional(vec![
+                    Waypoint::Star,
+                    Waypoint::Fix(r("r3")),
+                    Waypoint::Any
+                ]))
+            ),
+            Condition::NotReachable(r("r4"), p),
+        ]
+    );
+    assert_eq!(policy.expr.ast(), ltl!(And(G(And(0, 1)), F(2))).ast());
+
+    // the same condition is only added once
+    let policy = HardPolicy::parse("Reachability(r1, 0) & X(Reachability(r1, 0))", &net).unwrap();
+    assert_eq!(policy.prop_vars.len(), 1);
+    assert_eq!(policy.expr.ast(), ltl!(0 & X(0)).ast());
+
+    // numbers cannot be used as propositional variables, and routers must exist
+    assert!(HardPolicy::parse("G(0)", &net).is_err());
+    assert!(HardPolicy::parse("G(Reachability(r9, 0))", &net).is_err());
+    assert!(HardPolicy::parse("G(Transient(r1, 0))", &net).is_err());
+}
+
+#[test]
+fn test_parse_conditions_round_trip() {
+    let net = SimpleNet::net(0);
+    let r = |name: &str| -> RouterId { net.get_router_id(name).unwrap() };
+    let p = Prefix(0);
+    let conditions = vec![
+        Condition::Reachable(r("r1"), p, None),
+        Condition::Reachable(
+            r("r1"),
+            p,
+            Some(PathCondition::And(vec![
+                PathCondition::Node(r("r2")),
+                PathCondition::Not(Box::new(PathCondition::Edge(r("r3"), r("r4")))),
+            ])),
+        ),
+        Condition::Reliable(
+            r("r2"),
+            p,
+            Some(PathCondition::Or(vec![
+                PathCondition::Node(r("r3")),
+                PathCondition::Node(r("r4")),
+            ])),
+        ),
+        Condition::TransientPath(
+            r("r3"),
+            p,
+            PathCondition::Positional(vec![Waypoint::Star, Waypoint::Fix(r("r1")), Waypoint::Star]),
+        ),
+        Condition::NotReachable(r("r4"), p),
+        Condition::ValleyFree(r("r4"), p),
+        Condition::PathLengthAtMost(r("r1"), p, 3),
+        Condition::LatencyAtMost(r("r2"), p, Latency(20.0)),
+        Condition::EgressIs(r("r1"), p, vec![r("r3"), r("r4")]),
+    ];
+    for condition in conditions {
+        let policy = HardPolicy::parse(&condition.repr_with_name(&net), &net).unwrap();
+        assert_eq!(policy.prop_vars, vec![condition]);
+    }
+}
+
+#[test]
+fn test_parse_selections() {
+    let net = SimpleNet::net(0);
+    let r = |name: &str| -> RouterId { net.get_router_id(name).unwrap() };
+    let p = Prefix(0);
+
+    assert_eq!(net.routers_matching("r[12]"), Ok(vec![r("r1"), r("r2")]));
+    assert_eq!(net.routers_matching("r"), Ok(vec![]));
+    assert_eq!(net.external_routers_matching("e.*"), Ok(vec![r("e1"), r("e4")]));
+    assert!(matches!(net.routers_matching("r("), Err(NetworkError::InvalidPattern(_))));
+
+    assert_eq!(PrefixSet::All.prefixes(&net), vec![p]);
+    assert_eq!(PrefixSet::AdvertisedBy(vec![r("e1"), r("e4")]).prefixes(&net), vec![p]);
+    assert_eq!(
+        for_all(&[r("r1"), r("r2")], &[p], |r, p| Condition::NotReachable(r, p)),
+        vec![Condition::NotReachable(r("r1"), p), Condition::NotReachable(r("r2"), p)]
+    );
+
+    let policy = HardPolicy::parse("G(Reachability(matching(\"r[12]\"), prefix *))", &net).unwrap();
+    assert_eq!(
+        policy.prop_vars,
+        vec![Condition::Reachable(r("r1"), p, None), Condition::Reachable(r("r2"), p, None)]
+    );
+    assert_eq!(policy.expr.ast(), ltl!(G(And(0, 1))).ast());
+
+    let policy = HardPolicy::parse("G(Isolation(r3, prefix [0, 0]))", &net).unwrap();
+    assert_eq!(policy.prop_vars, vec![Condition::NotReachable(r("r3"), p)]);
+
+    assert!(HardPolicy::parse("G(Reachability(matching(\"x.*\"), 0))", &net).is_err());
+    assert!(HardPolicy::parse("G(Reachability(matching(\"r(\"), 0))", &net).is_err());
+    assert!(HardPolicy::parse("G(Reachability(matching(r1), 0))", &net).is_err());
+    assert!(HardPolicy::parse("G(Reachability(r1, prefix []))", &net).is_err());
+}
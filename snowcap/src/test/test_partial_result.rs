@@ -0,0 +1,134 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+
+use crate::hard_policies::*;
+use crate::modifier_ordering::*;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::Network;
+use crate::strategies::*;
+use crate::{ControlHandle, Error, PartialResult};
+
+use std::time::Duration;
+
+/// Check that the partial result is a valid prefix, and that it covers all modifiers.
+fn check_partial(net: &Network, modifiers: &[ConfigModifier], partial: &PartialResult) {
+    assert_eq!(partial.num_modifiers(), modifiers.len());
+    let mut all = partial.sequence.iter().chain(partial.remaining.iter()).collect::<Vec<_>>();
+    for m in modifiers.iter() {
+        let pos = all.iter().position(|x| *x == m).unwrap();
+        all.remove(pos);
+    }
+    assert!(all.is_empty());
+
+    let mut net = net.clone();
+    let mut hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    hard_policy.set_num_mods_if_none(modifiers.len());
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
+    for m in partial.sequence.iter() {
+        net.apply_modifier(m).unwrap();
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state).unwrap();
+        assert!(hard_policy.check());
+    }
+}
+
+fn test_timeout<S, N>(budget: Duration)
+where
+    S: Strategy,
+    N: ExampleNetwork,
+{
+    let net = N::net(0);
+    let cf = N::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    match S::synthesize(net.clone(), cf, hard_policy, Some(budget), ControlHandle::new()) {
+        Ok(sequence) => assert_eq!(sequence.len(), modifiers.len()),
+        Err(Error::Timeout(partial)) => {
+            assert!(!partial.is_complete());
+            check_partial(&net, &modifiers, &partial)
+        }
+        Err(e) => panic!("Unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn test_partial_result_remaining() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+
+    let partial = PartialResult::new(modifiers[..1].to_vec(), &modifiers);
+    assert_eq!(partial.sequence, modifiers[..1].to_vec());
+    assert_eq!(partial.remaining, modifiers[1..].to_vec());
+    assert_eq!(partial.num_modifiers(), modifiers.len());
+    assert!(!partial.is_complete());
+    assert_eq!(partial.cost, None);
+
+    let partial = PartialResult::new(modifiers.clone(), &modifiers).with_cost(1.0);
+    assert!(partial.is_complete());
+    assert_eq!(partial.cost, Some(1.0));
+
+    let err: Error = partial.into();
+    assert!(err.is_timeout());
+    assert_eq!(err.partial_result().unwrap().sequence, modifiers);
+    assert!(!Error::NoSafeOrdering.is_timeout());
+    assert!(Error::NoSafeOrdering.partial_result().is_none());
+}
+
+#[test]
+fn test_naive_random_timeout() {
+    let net = ChainGadget::<Repetition5>::net(0);
+    let cf = ChainGadget::<Repetition5>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&cf).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    // with no time budget, the strategy must time out immediately, without any prefix.
+    match NaiveRandomStrategy::synthesize(
+        net.clone(),
+        cf,
+        hard_policy,
+        Some(Duration::from_secs(0)),
+        ControlHandle::new(),
+    ) {
+        Err(Error::Timeout(partial)) => {
+            assert!(partial.sequence.is_empty());
+            check_partial(&net, &modifiers, &partial);
+        }
+        r => panic!("Expected a timeout, but got {:?}", r),
+    }
+}
+
+#[test]
+fn test_strategies_timeout_with_valid_prefix() {
+    let budget = Duration::from_secs(0);
+    test_timeout::<TreeStrategy<RandomOrdering>, ChainGadget<Repetition5>>(budget);
+    test_timeout::<TreeStrategy<RandomOrdering>, DifficultGadgetMinimal>(budget);
+    test_timeout::<PushBackTreeStrategy<RandomOrdering>, ChainGadget<Repetition5>>(budget);
+    test_timeout::<PushBackTreeStrategy<RandomOrdering>, DifficultGadgetMinimal>(budget);
+    test_timeout::<StrategyTRTA, ChainGadget<Repetition5>>(budget);
+    test_timeout::<StrategyTRTA, DifficultGadgetMinimal>(budget);
+}
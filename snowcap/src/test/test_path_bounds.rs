@@ -0,0 +1,125 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::hard_policies::{Condition, HardPolicy, Latency, PolicyError};
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// Diamond topology, where `a` reaches `d` (and the external router `e`) either via `b` (latency 3)
+/// or via `c` (latency 5).
+fn diamond() -> (Network, [RouterId; 5]) {
+    let mut net = Network::new();
+    let a = net.add_router("a");
+    let b = net.add_router("b");
+    let c = net.add_router("c");
+    let d = net.add_router("d");
+    let e = net.add_external_router("e", AsId(65101));
+
+    net.add_link(a, b);
+    net.add_link(a, c);
+    net.add_link(b, d);
+    net.add_link(c, d);
+    net.add_link(d, e);
+
+    let mut config = Config::new();
+    for (x, y, w) in vec![(a, b, 1.0), (a, c, 3.0), (b, d, 1.0), (c, d, 1.0), (d, e, 1.0)] {
+        config.add(IgpLinkWeight { source: x, target: y, weight: w }).unwrap();
+        config.add(IgpLinkWeight { source: y, target: x, weight: w }).unwrap();
+    }
+    config.add(BgpSession { source: d, target: a, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: b, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: c, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: e, session_type: EBgp }).unwrap();
+    net.set_config(&config).unwrap();
+
+    net.advertise_external_route(e, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
+
+    (net, [a, b, c, d, e])
+}
+
+#[test]
+fn path_length() {
+    let (net, [a, b, _, d, e]) = diamond();
+    let p = Prefix(0);
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(Condition::PathLengthAtMost(a, p, 3).check(&mut fw_state), Ok(()));
+    assert_eq!(Condition::PathLengthAtMost(d, p, 1).check(&mut fw_state), Ok(()));
+    assert_eq!(
+        Condition::PathLengthAtMost(a, p, 2).check(&mut fw_state),
+        Err(PolicyError::PathTooLong { router: a, prefix: p, path: vec![a, b, d, e], max_len: 2 })
+    );
+    assert_eq!(
+        Condition::PathLengthAtMost(a, Prefix(1), 10).check(&mut fw_state),
+        Err(PolicyError::BlackHole { router: a, prefix: Prefix(1) })
+    );
+}
+
+#[test]
+fn latency() {
+    let (net, [a, b, _, d, e]) = diamond();
+    let p = Prefix(0);
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(Latency::of_path(&net, &[a, b, d, e]), Latency(3.0));
+
+    // latency conditions are not checked by `Condition::check`
+    let c = Condition::LatencyAtMost(a, p, Latency(2.0));
+    assert!(c.is_latency());
+    assert_eq!(c.check(&mut fw_state), Ok(()));
+    assert_eq!(
+        c.check_latency(&net, &mut fw_state),
+        Err(PolicyError::LatencyExceeded {
+            router: a,
+            prefix: p,
+            path: vec![a, b, d, e],
+            latency: Latency(3.0),
+            bound: Latency(2.0),
+        })
+    );
+    let c = Condition::LatencyAtMost(a, p, Latency(3.0));
+    assert_eq!(c.check_latency(&net, &mut fw_state), Ok(()));
+}
+
+#[test]
+fn latency_stretch() {
+    let (mut net, [a, b, _, _, _]) = diamond();
+    let p = Prefix(0);
+
+    assert_eq!(Condition::latency_stretch(&net, a, Prefix(1), 2.0), None);
+    let c = Condition::latency_stretch(&net, a, p, 1.5).unwrap();
+    assert_eq!(c, Condition::LatencyAtMost(a, p, Latency(4.5)));
+
+    let mut hard_policy = HardPolicy::globally(vec![c]);
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
+
+    // move the traffic to the backup path via c, which has a latency of 5.
+    net.apply_modifier(&ConfigModifier::Update {
+        from: IgpLinkWeight { source: a, target: b, weight: 1.0 },
+        to: IgpLinkWeight { source: a, target: b, weight: 10.0 },
+    })
+    .unwrap();
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(!hard_policy.check());
+    let (_, errors) = hard_policy.get_watch_errors();
+    assert!(matches!(
+        errors.as_slice(),
+        [Some(PolicyError::LatencyExceeded { latency: Latency(l), .. })] if *l == 5.0
+    ));
+}
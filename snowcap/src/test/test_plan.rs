@@ -0,0 +1,215 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::*;
+use crate::hard_policies::HardPolicy;
+use crate::modifier_ordering::SimpleOrdering;
+use crate::netsim::config::{ConfigExpr, ConfigModifier};
+use crate::netsim::{BgpSessionType, Prefix};
+use crate::plan::{
+    migration_steps, ConvergenceEffort, MigrationPlan, MigrationStep, ModifierCostModel,
+    ModifierKind, TimeEstimator, MAX_PARALLEL_MODIFIERS,
+};
+use crate::rationale::{check_ordering, explain_sequence};
+use crate::soft_policies::{compute_cost, MinimizeMaintenanceDuration, SoftPolicy};
+use crate::strategies::{Strategy, StrategyTRTA, TreeStrategy};
+use crate::ControlHandle;
+
+use itertools::Itertools;
+use std::time::Duration;
+
+#[test]
+fn test_estimator() {
+    let estimator = TimeEstimator {
+        base: Duration::from_secs(1),
+        per_message: Duration::from_millis(10),
+        per_router: Duration::from_millis(100),
+    };
+    let effort = ConvergenceEffort { num_messages: 10, routers_touched: 3 };
+    assert_eq!(estimator.estimate(&effort), Duration::from_millis(1400));
+
+    // calibrate with a measurement that took twice as long
+    let mut calibrated = estimator;
+    calibrated.calibrate(&[(effort, Duration::from_millis(2800))]);
+    assert_eq!(calibrated.estimate(&effort), Duration::from_millis(2800));
+
+    // calibrating without samples does not change anything
+    calibrated.calibrate(&[]);
+    assert_eq!(calibrated.estimate(&effort), Duration::from_millis(2800));
+}
+
+#[test]
+fn test_migration_plan() {
+    let net = SimpleNet::net(0);
+    let final_config = SimpleNet::final_config(&net, 0);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let sequence = TreeStrategy::<SimpleOrdering>::synthesize(
+        net.clone(),
+        final_config,
+        hard_policy,
+        None,
+        ControlHandle::new(),
+    )
+    .unwrap();
+
+    let mut plan = MigrationPlan::new(&net, &sequence).unwrap();
+    assert_eq!(plan.modifiers(), sequence);
+    assert!(plan.steps.iter().all(|s| s.effort.num_messages > 0 && s.estimated_time.is_none()));
+    assert_eq!(plan.total_time(), None);
+
+    // use the measurement of the first step, and estimate all others
+    let estimator = TimeEstimator::default();
+    plan.estimate_time(&estimator, Some(&[Duration::from_secs(42)]));
+    assert_eq!(plan.steps[0].estimated_time, Some(Duration::from_secs(42)));
+    let expected: Duration = Duration::from_secs(42)
+        + plan.steps.iter().skip(1).map(|s| estimator.estimate(&s.effort)).sum::<Duration>();
+    assert_eq!(plan.total_time(), Some(expected));
+    assert_eq!(plan.repr(&net).unwrap().len(), sequence.len());
+
+    // add the time to apply the modifiers
+    assert_eq!(plan.total_duration(), None);
+    let model = ModifierCostModel::new(Duration::from_secs(10));
+    plan.estimate_application(&model);
+    assert_eq!(
+        plan.total_duration(),
+        Some(expected + Duration::from_secs(10) * sequence.len() as u32)
+    );
+}
+
+#[test]
+fn test_modifier_cost_model() {
+    let weight = ConfigModifier::Insert(ConfigExpr::IgpLinkWeight {
+        source: 0.into(),
+        target: 1.into(),
+        weight: 1.0,
+    });
+    let session = ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: 0.into(),
+        target: 1.into(),
+        session_type: BgpSessionType::IBgpPeer,
+    });
+    let route = ConfigModifier::Insert(ConfigExpr::StaticRoute {
+        router: 0.into(),
+        prefix: Prefix(0),
+        target: 1.into(),
+    });
+    assert_eq!(ModifierKind::of(&weight), ModifierKind::IgpLinkWeight);
+    assert_eq!(ModifierKind::of(&session), ModifierKind::BgpSession);
+    assert_eq!(ModifierKind::of(&route), ModifierKind::StaticRoute);
+
+    let model = ModifierCostModel::new(Duration::from_secs(5))
+        .with_cost(ModifierKind::BgpSession, Duration::from_secs(20));
+    assert_eq!(model.cost(&weight), Duration::from_secs(5));
+    assert_eq!(model.cost(&session), Duration::from_secs(20));
+    assert_eq!(model.total(&[weight, session, route]), Duration::from_secs(30));
+    assert_eq!(model.max_cost(), Duration::from_secs(20));
+
+    // the model can be stored and loaded
+    let json = serde_json::to_string(&model).unwrap();
+    assert_eq!(serde_json::from_str::<ModifierCostModel>(&json).unwrap(), model);
+}
+
+#[test]
+fn test_minimize_maintenance_duration() {
+    let net = SimpleNet::net(0);
+    let final_config = SimpleNet::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let model = ModifierCostModel::default();
+
+    // every step costs at most 1, and the default model is used by compute_cost
+    let cost = compute_cost::<MinimizeMaintenanceDuration>(&net, &modifiers).unwrap();
+    let max = model.max_cost().as_secs_f64();
+    let expected: f64 = modifiers.iter().map(|m| model.cost(m).as_secs_f64() / max).sum();
+    assert!((cost - expected).abs() < 1e-9);
+
+    // the duration is accumulated for every step
+    let mut net = net;
+    let model = ModifierCostModel::new(Duration::from_secs(7));
+    let mut policy = MinimizeMaintenanceDuration::new(&mut net.get_forwarding_state(), &net)
+        .with_model(model.clone());
+    for m in modifiers.iter() {
+        net.apply_modifier(m).unwrap();
+        let mut state = net.get_forwarding_state();
+        policy.update(&mut state, &net);
+        assert_eq!(policy.cost(), 1.0);
+    }
+    assert_eq!(policy.duration(), Duration::from_secs(7) * modifiers.len() as u32);
+
+    // resetting keeps the model
+    let mut state = net.get_forwarding_state();
+    policy.reset(&mut state, &net);
+    assert_eq!(policy.model(), &model);
+    assert_eq!(policy.duration(), Duration::default());
+}
+
+#[test]
+fn test_migration_steps() {
+    let net = SimpleNet::net(0);
+    let final_config = SimpleNet::final_config(&net, 0);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let sequence = TreeStrategy::<SimpleOrdering>::synthesize(
+        net.clone(),
+        final_config,
+        hard_policy.clone(),
+        None,
+        ControlHandle::new(),
+    )
+    .unwrap();
+
+    let steps = migration_steps(&net, &sequence, &[], &hard_policy).unwrap();
+    let flat: Vec<ConfigModifier> = steps.iter().flat_map(|s| s.modifiers.clone()).collect();
+    assert_eq!(flat, sequence);
+    assert!(steps.iter().all(|s| s.parallelizable == (s.modifiers.len() > 1)));
+
+    // no step within a parallel step depends on another one of the same step
+    let rationale = explain_sequence(&net, &sequence, &hard_policy).unwrap();
+    let mut start = 0;
+    for step in steps.iter() {
+        let end = start + step.modifiers.len();
+        for r in rationale[start..end].iter() {
+            assert!(r.depends_on.iter().all(|d| d.step < start));
+        }
+        // every ordering of a parallel step is valid
+        assert!(step.modifiers.len() <= MAX_PARALLEL_MODIFIERS);
+        for permutation in step.modifiers.iter().permutations(step.modifiers.len()) {
+            let ordering = sequence[..start].iter().chain(permutation).collect::<Vec<_>>();
+            assert!(check_ordering(&net, &ordering, &hard_policy).unwrap().is_none());
+        }
+        start = end;
+    }
+}
+
+#[test]
+fn test_migration_steps_groups() {
+    let net = DifficultGadgetMinimal::net(0);
+    let final_config = DifficultGadgetMinimal::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let mut strategy = StrategyTRTA::new(net, modifiers.clone(), hard_policy, None).unwrap();
+    let steps: Vec<MigrationStep> = strategy.work_steps(ControlHandle::new()).unwrap();
+
+    assert_eq!(steps.iter().map(|s| s.modifiers.len()).sum::<usize>(), modifiers.len());
+    assert!(steps.iter().flat_map(|s| s.modifiers.iter()).all(|m| modifiers.contains(m)));
+    // every atomic step with multiple modifiers is a learned dependency group
+    for step in steps.iter().filter(|s| !s.parallelizable && s.modifiers.len() > 1) {
+        assert!(strategy.groups().contains(&step.modifiers));
+    }
+}
@@ -0,0 +1,87 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::modifier_ordering::SimpleOrdering;
+use crate::plan_editor::*;
+use crate::strategies::{Strategy, TreeStrategy};
+use crate::ControlHandle;
+
+#[test]
+fn test_plan_editor_chain_gadget() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    let sequence = TreeStrategy::<SimpleOrdering>::synthesize(
+        net.clone(),
+        cf,
+        hard_policy.clone(),
+        None,
+        ControlHandle::new(),
+    )
+    .unwrap();
+    let n = sequence.len();
+
+    let mut editor = PlanEditor::new(&net, sequence.clone(), hard_policy.clone()).unwrap();
+    assert!(editor.is_valid());
+    assert_eq!(editor.sequence(), &sequence[..]);
+
+    // the chain gadget has a single valid solution, so swapping the last two steps breaks it.
+    let num_replayed = editor.edit(PlanEdit::Swap(n - 2, n - 1)).unwrap();
+    assert!(num_replayed <= 2);
+    let violation = editor.violation().unwrap().clone();
+    assert!(violation.step >= n - 2);
+    assert!(!violation.errors.is_empty());
+
+    // the result is the same as when verifying from scratch
+    let scratch = PlanEditor::new(&net, editor.sequence().to_vec(), hard_policy.clone()).unwrap();
+    assert_eq!(scratch.violation(), Some(&violation));
+
+    // swapping them back makes the plan valid again, replaying only the last two steps
+    let num_replayed = editor.edit(PlanEdit::Swap(n - 2, n - 1)).unwrap();
+    assert!(editor.is_valid());
+    assert_eq!(num_replayed, 2);
+    assert_eq!(editor.sequence(), &sequence[..]);
+
+    // remove the last step, and insert it again
+    editor.edit(PlanEdit::Remove(n - 1)).unwrap();
+    assert_eq!(editor.sequence().len(), n - 1);
+    let num_replayed = editor.edit(PlanEdit::Insert(n - 1, sequence[n - 1].clone())).unwrap();
+    assert!(editor.is_valid());
+    assert!(num_replayed <= 2);
+    assert_eq!(editor.into_sequence(), sequence);
+}
+
+#[test]
+fn test_plan_editor_insert_first() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let hard_policy = HardPolicy::globally(vec![]);
+    let sequence = net.current_config().get_diff(&cf).modifiers;
+    let n = sequence.len();
+
+    let mut editor = PlanEditor::new(&net, sequence[1..].to_vec(), hard_policy).unwrap();
+    assert!(editor.is_valid());
+    // inserting at the beginning requires the entire plan to be replayed
+    let num_replayed = editor.edit(PlanEdit::Insert(0, sequence[0].clone())).unwrap();
+    assert!(editor.is_valid());
+    assert_eq!(num_replayed, n);
+    assert_eq!(editor.sequence(), &sequence[..]);
+}
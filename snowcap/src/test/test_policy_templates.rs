@@ -0,0 +1,172 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::hard_policies::*;
+use crate::netsim::bgp::BgpRelationship as Rel;
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier::*};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId, RouterRole};
+
+/// # Test network
+///
+/// ```text
+/// E1 (customer) ---- R1 ---- R2 ---- E2 (peer)
+///                     |    .-'
+///                     | .-'
+///                     R3 ---- E3 (provider)
+/// ```
+///
+/// Prefix 0 is advertised by both E2 and E3 (with a longer AS path), and prefix 1 is advertised
+/// by E1.
+fn get_test_net() -> (Network, Vec<RouterId>) {
+    let mut net = Network::new();
+    let r1 = net.add_router("R1");
+    let r2 = net.add_router("R2");
+    let r3 = net.add_router("R3");
+    let e1 = net.add_external_router("E1", AsId(65101));
+    let e2 = net.add_external_router("E2", AsId(65102));
+    let e3 = net.add_external_router("E3", AsId(65103));
+
+    let links = vec![(r1, r2), (r1, r3), (r2, r3), (r1, e1), (r2, e2), (r3, e3)];
+    let mut c = Config::new();
+    for (a, b) in links {
+        net.add_link(a, b);
+        c.add(IgpLinkWeight { source: a, target: b, weight: 1.0 }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight: 1.0 }).unwrap();
+    }
+    c.add(BgpSession { source: r1, target: r2, session_type: IBgpPeer }).unwrap();
+    c.add(BgpSession { source: r1, target: r3, session_type: IBgpPeer }).unwrap();
+    c.add(BgpSession { source: r2, target: r3, session_type: IBgpPeer }).unwrap();
+    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r2, target: e2, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
+    c.add(BgpRelationship { neighbor: e1, relationship: Rel::Customer }).unwrap();
+    c.add(BgpRelationship { neighbor: e2, relationship: Rel::Peer }).unwrap();
+    c.add(BgpRelationship { neighbor: e3, relationship: Rel::Provider }).unwrap();
+    net.set_config(&c).unwrap();
+
+    net.advertise_external_route(e1, Prefix(1), vec![AsId(65101)], None, None).unwrap();
+    net.advertise_external_route(e2, Prefix(0), vec![AsId(65102), AsId(65200)], None, None)
+        .unwrap();
+    net.advertise_external_route(
+        e3,
+        Prefix(0),
+        vec![AsId(65103), AsId(65104), AsId(65200)],
+        None,
+        None,
+    )
+    .unwrap();
+
+    (net, vec![r1, r2, r3, e1, e2, e3])
+}
+
+#[test]
+fn test_roles_from_relationships() {
+    let (net, ids) = get_test_net();
+    let roles = TemplateRoles::from_relationships(&net).with_firewalls(vec![ids[1]]);
+    assert_eq!(
+        roles,
+        TemplateRoles {
+            customers: vec![ids[3]],
+            peers: vec![ids[4]],
+            providers: vec![ids[5]],
+            firewalls: vec![ids[1]],
+        }
+    );
+}
+
+#[test]
+fn test_roles_from_network() {
+    let (mut net, ids) = get_test_net();
+    net.add_router_role(ids[2], RouterRole::Firewall).unwrap();
+    let roles = TemplateRoles::from_network(&net);
+    assert_eq!(roles.customers, vec![ids[3]]);
+    assert_eq!(roles.firewalls, vec![ids[2]]);
+}
+
+#[test]
+fn test_template_conditions() {
+    let (net, ids) = get_test_net();
+    let (r1, r2, r3, e1, e2) = (ids[0], ids[1], ids[2], ids[3], ids[4]);
+    let p0 = Prefix(0);
+    let p1 = Prefix(1);
+    let roles = TemplateRoles::from_relationships(&net).with_firewalls(vec![r2]);
+
+    // prefix 1 is advertised by the only customer, and is skipped.
+    let no_transit = PathCondition::Not(Box::new(PathCondition::Or(vec![PathCondition::Node(e1)])));
+    assert_eq!(
+        PolicyTemplate::NoTransitThroughCustomer.conditions(&net, &roles),
+        vec![
+            Condition::Reachable(r1, p0, Some(no_transit.clone())),
+            Condition::Reachable(r2, p0, Some(no_transit.clone())),
+            Condition::Reachable(r3, p0, Some(no_transit)),
+        ]
+    );
+
+    // only prefix 0 is advertised by a provider.
+    let via_peer = PathCondition::Or(vec![PathCondition::Node(e2)]);
+    assert_eq!(
+        PolicyTemplate::PreferPeerOverProvider.conditions(&net, &roles),
+        vec![
+            Condition::Reachable(r1, p0, Some(via_peer.clone())),
+            Condition::Reachable(r2, p0, Some(via_peer.clone())),
+            Condition::Reachable(r3, p0, Some(via_peer)),
+        ]
+    );
+
+    // the firewall itself is skipped
+    let via_fw = PathCondition::Or(vec![PathCondition::Node(r2)]);
+    assert_eq!(
+        PolicyTemplate::FirewallTraversal(vec![p1]).conditions(&net, &roles),
+        vec![
+            Condition::Reachable(r1, p1, Some(via_fw.clone())),
+            Condition::Reachable(r3, p1, Some(via_fw)),
+        ]
+    );
+
+    // all routers are border routers
+    assert_eq!(PolicyTemplate::ValleyFree.conditions(&net, &roles).len(), 6);
+}
+
+#[test]
+fn test_template_policy() {
+    let (mut net, ids) = get_test_net();
+    let e3 = ids[5];
+    let roles = TemplateRoles::from_relationships(&net);
+
+    let mut hard_policy = PolicyTemplate::build(
+        &[
+            PolicyTemplate::NoTransitThroughCustomer,
+            PolicyTemplate::PreferPeerOverProvider,
+            PolicyTemplate::ValleyFree,
+        ],
+        &net,
+        &roles,
+    );
+    assert_eq!(hard_policy.prop_vars.len(), 3 + 3 + 6);
+
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
+
+    // removing the relationship with the provider causes R3 to export the route from the peer.
+    net.apply_modifier(&Remove(BgpRelationship { neighbor: e3, relationship: Rel::Provider }))
+        .unwrap();
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    assert!(!hard_policy.check());
+}
@@ -0,0 +1,132 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::modifier_ordering::NoOrdering;
+use crate::netsim::config::{ConfigExpr, ConfigModifier};
+use crate::netsim::{BgpSessionType, Network, Prefix, RouterId};
+use crate::permutators::TreePermutator;
+use crate::strategies::*;
+use crate::{ControlHandle, Error};
+
+fn problem() -> (Network, Vec<ConfigModifier>, HardPolicy) {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let final_config = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    (net, modifiers, hard_policy)
+}
+
+fn work<S: PrecedenceStrategy>(
+    precedence: PrecedenceConstraints,
+) -> Result<Vec<ConfigModifier>, Error> {
+    let (net, modifiers, hard_policy) = problem();
+    let mut strategy = S::new(net, modifiers, hard_policy, None).unwrap();
+    strategy.set_precedence(precedence);
+    strategy.work(ControlHandle::new())
+}
+
+/// The chain gadget has a single valid ordering. Constraints which agree with this ordering still
+/// find it, while constraints which contradict it (or each other) make the problem infeasible.
+fn check_strategy<S: PrecedenceStrategy>() {
+    let solution = work::<S>(PrecedenceConstraints::new()).unwrap();
+    let first = ModifierSelector::Modifier(solution[0].clone());
+    let last = ModifierSelector::Modifier(solution.last().unwrap().clone());
+
+    let agree = solution.windows(2).fold(PrecedenceConstraints::new(), |c, w| {
+        c.before(ModifierSelector::Modifier(w[0].clone()), ModifierSelector::Modifier(w[1].clone()))
+    });
+    assert_eq!(work::<S>(agree).unwrap(), solution);
+
+    let contradict = PrecedenceConstraints::new().before(last.clone(), first.clone());
+    assert!(matches!(work::<S>(contradict), Err(Error::NoSafeOrdering)));
+
+    let cycle = PrecedenceConstraints::new()
+        .before(first.clone(), last.clone())
+        .before(last.clone(), first.clone());
+    assert!(matches!(work::<S>(cycle), Err(Error::NoSafeOrdering)));
+}
+
+#[test]
+fn precedence_tree_strategy() {
+    check_strategy::<TreeStrategy<NoOrdering>>();
+}
+
+#[test]
+fn precedence_push_back_tree_strategy() {
+    check_strategy::<PushBackTreeStrategy<NoOrdering>>();
+}
+
+#[test]
+fn precedence_permutation_strategy() {
+    check_strategy::<PermutationStrategy<TreePermutator<NoOrdering>>>();
+}
+
+#[test]
+fn precedence_synthesize() {
+    let (net, _, hard_policy) = problem();
+    let final_config = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let solution = work::<TreeStrategy<NoOrdering>>(PrecedenceConstraints::new()).unwrap();
+    let sequence = TreeStrategy::<NoOrdering>::synthesize_with_precedence(
+        net,
+        final_config,
+        hard_policy,
+        PrecedenceConstraints::new().before(
+            ModifierSelector::Modifier(solution[0].clone()),
+            ModifierSelector::Modifier(solution[1].clone()),
+        ),
+        None,
+        ControlHandle::new(),
+    );
+    assert_eq!(sequence.unwrap(), solution);
+}
+
+#[test]
+fn precedence_violation() {
+    let r: Vec<RouterId> = (0..3).map(|i| i.into()).collect();
+    let a = ConfigModifier::Insert(ConfigExpr::IgpLinkWeight {
+        source: r[0],
+        target: r[1],
+        weight: 1.0,
+    });
+    let b = ConfigModifier::Insert(ConfigExpr::BgpSession {
+        source: r[0],
+        target: r[1],
+        session_type: BgpSessionType::IBgpPeer,
+    });
+    let c = ConfigModifier::Insert(ConfigExpr::StaticRoute {
+        router: r[2],
+        prefix: Prefix(0),
+        target: r[0],
+    });
+
+    let constraints = PrecedenceConstraints::new()
+        .before(ModifierSelector::Router(r[2]), ModifierSelector::BgpSessions);
+    assert!(ModifierSelector::BgpSessions.matches(&b));
+    assert!(!ModifierSelector::BgpSessions.matches(&a));
+    assert!(!constraints.allows(&b, &[c.clone()]));
+    assert!(constraints.allows(&b, &[a.clone()]));
+    assert!(constraints.allows(&c, &[a.clone(), b.clone()]));
+    assert_eq!(constraints.first_violation(&[a.clone(), b.clone(), c.clone()]), Some(1));
+    assert_eq!(constraints.first_violation(&[c.clone(), a.clone(), b.clone()]), None);
+    assert_eq!(constraints.first_violation(&[a.clone(), c.clone(), b.clone()]), None);
+    assert_eq!(PrecedenceConstraints::new().first_violation(&[b, c, a]), None);
+}
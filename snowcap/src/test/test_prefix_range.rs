@@ -0,0 +1,134 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for prefix ranges and longest-prefix matching
+
+#![cfg(test)]
+use super::two_egress_topology;
+use crate::netsim::config::{ConfigExpr::*, ConfigModifier};
+use crate::netsim::{AsId, BgpSessionType::*, Ipv4Net, Network, NetworkError, Prefix, RouterId};
+
+use std::net::Ipv4Addr;
+
+/// The network of [`two_egress_topology`], where `e1` advertises the aggregate `Prefix(0)`
+/// (`10.0.0.0/16`), and `e2` the more specific `Prefix(1)` (`10.0.1.0/24`).
+fn aggregate_net() -> (Network, [RouterId; 4]) {
+    let (mut net, [r1, r2, e1, e2]) = two_egress_topology();
+    net.advertise_external_route(e1, Prefix(0), vec![AsId(65101)], None, None).unwrap();
+    net.advertise_external_route(e2, Prefix(1), vec![AsId(65102)], None, None).unwrap();
+    net.set_prefix_range(Prefix(0), "10.0.0.0/16".parse().unwrap());
+    net.set_prefix_range(Prefix(1), "10.0.1.0/24".parse().unwrap());
+
+    (net, [r1, r2, e1, e2])
+}
+
+#[test]
+fn ipv4_net() {
+    let range: Ipv4Net = "10.0.1.0/24".parse().unwrap();
+    assert_eq!(range.addr(), Ipv4Addr::new(10, 0, 1, 0));
+    assert_eq!(range.prefix_len(), 24);
+    assert_eq!(range.to_string(), "10.0.1.0/24");
+    assert!(range.contains(Ipv4Addr::new(10, 0, 1, 255)));
+    assert!(!range.contains(Ipv4Addr::new(10, 0, 2, 0)));
+
+    // host bits are cleared
+    assert_eq!(Ipv4Net::new(Ipv4Addr::new(10, 0, 1, 7), 24).unwrap(), range);
+
+    let aggregate: Ipv4Net = "10.0.0.0/16".parse().unwrap();
+    assert!(aggregate.covers(&range));
+    assert!(aggregate.covers(&aggregate));
+    assert!(!range.covers(&aggregate));
+
+    let default: Ipv4Net = "0.0.0.0/0".parse().unwrap();
+    assert!(default.contains(Ipv4Addr::new(192, 168, 0, 1)));
+    assert!(default.covers(&range));
+
+    for invalid in vec!["10.0.0.0/33", "10.0.0/8", "10.0.0.0", "10.0.0.0/x"] {
+        assert!(matches!(
+            invalid.parse::<Ipv4Net>(),
+            Err(NetworkError::InvalidPrefixRange(s)) if s == invalid
+        ));
+    }
+}
+
+#[test]
+fn ipv4_net_deserialize() {
+    let range: Ipv4Net = "10.0.1.0/24".parse().unwrap();
+    let json = serde_json::to_string(&range).unwrap();
+    assert_eq!(serde_json::from_str::<Ipv4Net>(&json).unwrap(), range);
+
+    // host bits are cleared, and invalid lengths are rejected
+    let with_host_bits: Ipv4Net = serde_json::from_str(r#"{"addr":167772423,"len":24}"#).unwrap();
+    assert_eq!(with_host_bits, range);
+    assert!(serde_json::from_str::<Ipv4Net>(r#"{"addr":0,"len":33}"#).is_err());
+}
+
+#[test]
+fn longest_prefix_match() {
+    let (net, [r1, r2, e1, e2]) = aggregate_net();
+    let fw_state = net.get_forwarding_state();
+
+    let specific = Ipv4Addr::new(10, 0, 1, 5);
+    let other = Ipv4Addr::new(10, 0, 2, 1);
+    assert_eq!(fw_state.get_prefix_for_addr(r1, specific).unwrap(), Some(Prefix(1)));
+    assert_eq!(fw_state.get_prefix_for_addr(r1, other).unwrap(), Some(Prefix(0)));
+    // external routers only use the prefixes they advertise
+    assert_eq!(fw_state.get_prefix_for_addr(e1, specific).unwrap(), Some(Prefix(0)));
+
+    assert_eq!(fw_state.get_route_to_addr(r1, specific).unwrap(), vec![r1, r2, e2]);
+    assert_eq!(fw_state.get_route_to_addr(r1, other).unwrap(), vec![r1, e1]);
+    assert_eq!(fw_state.get_route_to_addr(r2, other).unwrap(), vec![r2, r1, e1]);
+    assert!(matches!(
+        fw_state.get_route_to_addr(r1, Ipv4Addr::new(192, 168, 0, 1)),
+        Err(NetworkError::ForwardingBlackHole(path)) if path == vec![r1]
+    ));
+}
+
+#[test]
+fn deaggregation() {
+    let (mut net, [r1, r2, e1, e2]) = aggregate_net();
+    let specific = Ipv4Addr::new(10, 0, 1, 5);
+
+    // once the more specific prefix is withdrawn, the traffic falls back to the aggregate.
+    net.apply_modifier(&ConfigModifier::Remove(BgpSession {
+        source: r2,
+        target: e2,
+        session_type: EBgp,
+    }))
+    .unwrap();
+    let fw_state = net.get_forwarding_state();
+    assert_eq!(fw_state.get_prefix_for_addr(r2, specific).unwrap(), Some(Prefix(0)));
+    assert_eq!(fw_state.get_route_to_addr(r2, specific).unwrap(), vec![r2, r1, e1]);
+}
+
+#[test]
+fn no_prefix_ranges() {
+    let (net, [r1, ..]) = aggregate_net();
+    assert_eq!(net.get_prefix_range(Prefix(1)), Some("10.0.1.0/24".parse().unwrap()));
+    assert_eq!(net.get_prefix_range(Prefix(2)), None);
+
+    // prefixes without a range are never matched
+    let mut net = Network::new();
+    let r = net.add_router("r");
+    let fw_state = net.get_forwarding_state();
+    assert_eq!(fw_state.get_prefix_for_addr(r, Ipv4Addr::new(10, 0, 1, 5)).unwrap(), None);
+    assert!(matches!(
+        fw_state.get_route_to_addr(r, Ipv4Addr::new(10, 0, 1, 5)),
+        Err(NetworkError::ForwardingBlackHole(path)) if path == vec![r]
+    ));
+    assert_ne!(r, r1);
+}
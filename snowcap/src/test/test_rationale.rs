@@ -0,0 +1,61 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::modifier_ordering::*;
+use crate::netsim::printer;
+use crate::rationale::*;
+use crate::strategies::*;
+use crate::ControlHandle;
+
+#[test]
+fn test_rationale_chain_gadget() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let cf = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+
+    let sequence = TreeStrategy::<SimpleOrdering>::synthesize(
+        net.clone(),
+        cf,
+        hard_policy.clone(),
+        None,
+        ControlHandle::new(),
+    )
+    .unwrap();
+
+    let rationale = explain_sequence(&net, &sequence, &hard_policy).unwrap();
+    assert_eq!(rationale.len(), sequence.len());
+    for (i, r) in rationale.iter().enumerate() {
+        assert_eq!(r.step, i);
+        assert!(r.depends_on.iter().all(|d| d.step < i));
+    }
+    // the first step never depends on anything
+    assert!(rationale[0].is_independent());
+    // the chain gadget has a single valid solution, so there must be some dependency
+    assert!(rationale.iter().any(|r| !r.is_independent()));
+
+    // the plan contains one entry per step, and the annotations are added to the entries
+    let plan = printer::plan(&net, &sequence, Some(&rationale)).unwrap();
+    assert_eq!(plan.len(), sequence.len());
+    for (entry, r) in plan.iter().zip(rationale.iter()) {
+        assert_eq!(entry.matches("must follow step").count(), r.depends_on.len());
+    }
+}
@@ -0,0 +1,102 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use super::two_egress_net;
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier, ConfigModifier::*};
+use crate::netsim::{BgpSessionType::*, Network, Prefix, RouterId};
+use crate::optimize_early_completion;
+use crate::soft_policies::{cleanup_only, compute_cost, MinimizeReconfigurationSteps, SoftPolicy};
+
+/// The network of [`two_egress_net`]. The reconfiguration removes the session between R1 and E1
+/// (which changes the forwarding state), and changes the weight of the link between R1 and R2
+/// (which does not).
+fn get_test_net() -> (Network, Config, Vec<ConfigModifier>) {
+    let (net, [r1, r2, e1, _]) = two_egress_net();
+
+    let remove_session = Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let change_weight = Update {
+        from: IgpLinkWeight { source: r1, target: r2, weight: 1.0 },
+        to: IgpLinkWeight { source: r1, target: r2, weight: 2.0 },
+    };
+    let mut final_config = net.current_config().clone();
+    final_config.apply_modifier(&remove_session).unwrap();
+    final_config.apply_modifier(&change_weight).unwrap();
+
+    (net, final_config, vec![remove_session, change_weight])
+}
+
+fn routers(net: &Network) -> Vec<RouterId> {
+    vec![net.get_router_id("R1").unwrap(), net.get_router_id("R2").unwrap()]
+}
+
+#[test]
+fn test_cleanup_only() {
+    let (net, _, modifiers) = get_test_net();
+    let reversed = modifiers.iter().rev().cloned().collect::<Vec<_>>();
+
+    // changing the link weight does not change the forwarding state
+    assert_eq!(cleanup_only(&net, &modifiers).unwrap(), 1);
+    assert_eq!(cleanup_only(&net, &reversed).unwrap(), 2);
+    assert_eq!(cleanup_only(&net, &modifiers[1..]).unwrap(), 0);
+    assert_eq!(cleanup_only(&net, &[]).unwrap(), 0);
+}
+
+#[test]
+fn test_reconfiguration_steps_cost() {
+    let (net, final_config, modifiers) = get_test_net();
+
+    // without the final state, every step is free
+    assert_eq!(compute_cost::<MinimizeReconfigurationSteps>(&net, &modifiers).unwrap(), 0.0);
+
+    let mut sp = MinimizeReconfigurationSteps::new(&mut net.get_forwarding_state(), &net)
+        .with_final_config(&net, &final_config)
+        .unwrap();
+    assert!(!sp.is_complete());
+
+    // the forwarding state of R1 changes, while R2 keeps forwarding to E2
+    let mut after = net.clone();
+    after.apply_modifier(&modifiers[1]).unwrap();
+    sp.update(&mut after.get_forwarding_state(), &after);
+    assert_eq!(sp.cost(), 0.5);
+    assert!(!sp.is_complete());
+    after.apply_modifier(&modifiers[0]).unwrap();
+    sp.update(&mut after.get_forwarding_state(), &after);
+    assert_eq!(sp.cost(), 0.0);
+    assert!(sp.is_complete());
+
+    // the final state is kept when resetting the policy
+    sp.reset(&mut net.get_forwarding_state(), &net);
+    assert!(!sp.is_complete());
+    sp.update(&mut net.get_forwarding_state(), &net);
+    assert_eq!(sp.cost(), 0.5);
+}
+
+#[test]
+fn test_optimize_early_completion() {
+    let (net, final_config, modifiers) = get_test_net();
+    let initial_config = net.current_config().clone();
+    let hard_policy = HardPolicy::reachability(routers(&net).iter(), vec![Prefix(0)].iter());
+
+    let (sequence, first_cleanup) =
+        optimize_early_completion(net.clone(), initial_config, final_config, hard_policy, None)
+            .unwrap();
+    // removing the session first reaches the final forwarding state after the first step
+    assert_eq!(sequence, modifiers);
+    assert_eq!(first_cleanup, 1);
+    assert_eq!(first_cleanup, cleanup_only(&net, &sequence).unwrap());
+}
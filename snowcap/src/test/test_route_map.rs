@@ -20,10 +20,13 @@ use crate::netsim::bgp::{BgpRibEntry, BgpRoute};
 use crate::netsim::route_map::RouteMapMatch as Match;
 use crate::netsim::route_map::RouteMapMatchAsPath as AClause;
 use crate::netsim::route_map::RouteMapMatchClause as Clause;
+use crate::netsim::route_map::RouteMapMatchCommunityList as CList;
 use crate::netsim::route_map::RouteMapSet as Set;
 use crate::netsim::route_map::RouteMapState::*;
 use crate::netsim::route_map::*;
 use crate::netsim::{AsId, Prefix};
+use maplit::btreeset;
+use std::collections::BTreeSet;
 
 #[test]
 fn simple_matches() {
@@ -34,7 +37,9 @@ fn simple_matches() {
             next_hop: 0.into(),
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         },
         from_type: IBgpClient,
         from_id: 0.into(),
@@ -130,31 +135,31 @@ fn simple_matches() {
     // Match on communits, not set
     let map = RouteMap::new(10, Deny, vec![Match::Community(None)], vec![]);
     let mut entry = default_entry.clone();
-    entry.route.community = None;
+    entry.route.communities = BTreeSet::new();
     assert_eq!(map.apply(entry.clone()).0, true);
-    entry.route.community = Some(0);
+    entry.route.communities = btreeset! {0};
     assert_eq!(map.apply(entry.clone()).0, false);
 
     // Match on Community, exact
     let map = RouteMap::new(10, Deny, vec![Match::Community(Some(Clause::Equal(0)))], vec![]);
     let mut entry = default_entry.clone();
-    entry.route.community = Some(0);
+    entry.route.communities = btreeset! {0};
     assert_eq!(map.apply(entry.clone()).0, true);
-    entry.route.community = Some(1);
+    entry.route.communities = btreeset! {1};
     assert_eq!(map.apply(entry.clone()).0, false);
-    entry.route.community = None;
+    entry.route.communities = BTreeSet::new();
     assert_eq!(map.apply(entry.clone()).0, false);
 
     // Match on Community with range
     let map = RouteMap::new(10, Deny, vec![Match::Community(Some(Clause::Range(0, 9)))], vec![]);
     let mut entry = default_entry.clone();
-    entry.route.community = Some(0);
+    entry.route.communities = btreeset! {0};
     assert_eq!(map.apply(entry.clone()).0, true);
-    entry.route.community = Some(9);
+    entry.route.communities = btreeset! {9};
     assert_eq!(map.apply(entry.clone()).0, true);
-    entry.route.community = Some(10);
+    entry.route.communities = btreeset! {10};
     assert_eq!(map.apply(entry.clone()).0, false);
-    entry.route.community = None;
+    entry.route.communities = BTreeSet::new();
     assert_eq!(map.apply(entry.clone()).0, false);
 
     // Match on Community with exclusive_range
@@ -165,13 +170,21 @@ fn simple_matches() {
         vec![],
     );
     let mut entry = default_entry.clone();
-    entry.route.community = Some(0);
+    entry.route.communities = btreeset! {0};
     assert_eq!(map.apply(entry.clone()).0, true);
-    entry.route.community = Some(9);
+    entry.route.communities = btreeset! {9};
     assert_eq!(map.apply(entry.clone()).0, true);
-    entry.route.community = Some(10);
+    entry.route.communities = btreeset! {10};
     assert_eq!(map.apply(entry.clone()).0, false);
-    entry.route.community = None;
+    entry.route.communities = BTreeSet::new();
+    assert_eq!(map.apply(entry.clone()).0, false);
+
+    // Match on Community, if any of multiple communities matches
+    let map = RouteMap::new(10, Deny, vec![Match::Community(Some(Clause::Equal(5)))], vec![]);
+    let mut entry = default_entry.clone();
+    entry.route.communities = btreeset! {1, 5, 10};
+    assert_eq!(map.apply(entry.clone()).0, true);
+    entry.route.communities = btreeset! {1, 10};
     assert_eq!(map.apply(entry.clone()).0, false);
 }
 
@@ -184,7 +197,9 @@ fn complex_matches() {
             next_hop: 0.into(),
             local_pref: None,
             med: None,
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         },
         from_type: IBgpClient,
         from_id: 0.into(),
@@ -226,7 +241,9 @@ fn overwrite() {
             next_hop: 0.into(),
             local_pref: Some(1),
             med: Some(10),
-            community: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
         },
         from_type: IBgpClient,
         from_id: 0.into(),
@@ -388,4 +405,223 @@ fn route_map_builder() {
         RouteMap::new(10, Allow, vec![], vec![Set::Community(None)]),
         RouteMapBuilder::new().order(10).allow().reset_community().build()
     );
+
+    assert_eq!(
+        RouteMap::new(10, Allow, vec![], vec![Set::AddCommunity(10)]),
+        RouteMapBuilder::new().order(10).allow().add_community(10).build()
+    );
+
+    assert_eq!(
+        RouteMap::new(10, Allow, vec![], vec![Set::DelCommunity(10)]),
+        RouteMapBuilder::new().order(10).allow().delete_community(10).build()
+    );
+
+    assert_eq!(
+        RouteMap::new(10, Allow, vec![], vec![Set::PrependAsPath(AsId(1), 3)]),
+        RouteMapBuilder::new().order(10).allow().prepend_as_path(AsId(1), 3).build()
+    );
+}
+
+#[test]
+fn as_path_prepending() {
+    let default_entry = BgpRibEntry {
+        route: BgpRoute {
+            prefix: Prefix(0),
+            as_path: vec![AsId(1), AsId(2)],
+            next_hop: 0.into(),
+            local_pref: None,
+            med: None,
+            communities: BTreeSet::new(),
+            originator_id: None,
+            cluster_list: Vec::new(),
+        },
+        from_type: EBgp,
+        from_id: 0.into(),
+        to_id: None,
+        igp_cost: Some(10.0),
+    };
+
+    let map =
+        RouteMapBuilder::new().order(10).allow().prepend_as_path(AsId(1), 2).set_med(5).build();
+    let entry = map.apply(default_entry.clone()).1.unwrap();
+    assert_eq!(entry.route.as_path, vec![AsId(1), AsId(1), AsId(1), AsId(2)]);
+    assert_eq!(entry.route.med, Some(5));
+
+    // prepending zero times does not change the path
+    let map = RouteMapBuilder::new().order(10).allow().prepend_as_path(AsId(3), 0).build();
+    let entry = map.apply(default_entry).1.unwrap();
+    assert_eq!(entry.route.as_path, vec![AsId(1), AsId(2)]);
+}
+
+#[test]
+fn community_rewriting_chain() {
+    let default_entry = BgpRibEntry {
+        route: BgpRoute {
+            prefix: Prefix(0),
+            as_path: vec![AsId(0)],
+            next_hop: 0.into(),
+            local_pref: None,
+            med: None,
+            communities: btreeset! {1, 2, 3},
+            originator_id: None,
+            cluster_list: Vec::new(),
+        },
+        from_type: EBgp,
+        from_id: 0.into(),
+        to_id: None,
+        igp_cost: Some(10.0),
+    };
+
+    // add and delete specific communities
+    let map = RouteMapBuilder::new()
+        .order(10)
+        .allow()
+        .add_community(4)
+        .delete_community(2)
+        .delete_community(5)
+        .build();
+    let entry = map.apply(default_entry.clone()).1.unwrap();
+    assert_eq!(entry.route.communities, btreeset! {1, 3, 4});
+
+    // the actions are applied in order: delete all, then add
+    let map = RouteMapBuilder::new().order(10).allow().reset_community().add_community(7).build();
+    let entry = map.apply(default_entry.clone()).1.unwrap();
+    assert_eq!(entry.route.communities, btreeset! {7});
+
+    // adding, and then rewriting all communities
+    let map = RouteMapBuilder::new().order(10).allow().add_community(7).set_community(8).build();
+    let entry = map.apply(default_entry.clone()).1.unwrap();
+    assert_eq!(entry.route.communities, btreeset! {8});
+
+    // rewriting, and then deleting the only community
+    let map = RouteMapBuilder::new().order(10).allow().set_community(8).delete_community(8).build();
+    let entry = map.apply(default_entry.clone()).1.unwrap();
+    assert!(entry.route.communities.is_empty());
+}
+
+#[test]
+fn community_lists() {
+    let default_entry = BgpRibEntry {
+        route: BgpRoute {
+            prefix: Prefix(0),
+            as_path: vec![AsId(0)],
+            next_hop: 0.into(),
+            local_pref: None,
+            med: None,
+            communities: btreeset! {1, 2, 3},
+            originator_id: None,
+            cluster_list: Vec::new(),
+        },
+        from_type: EBgp,
+        from_id: 0.into(),
+        to_id: None,
+        igp_cost: Some(10.0),
+    };
+
+    let any = |l: BTreeSet<u32>| {
+        RouteMap::new(10, Deny, vec![Match::CommunityList(CList::Any(l))], vec![])
+    };
+    assert_eq!(any(btreeset! {3, 4}).apply(default_entry.clone()).0, true);
+    assert_eq!(any(btreeset! {4, 5}).apply(default_entry.clone()).0, false);
+    assert_eq!(any(btreeset! {}).apply(default_entry.clone()).0, false);
+
+    let all = |l: BTreeSet<u32>| {
+        RouteMap::new(10, Deny, vec![Match::CommunityList(CList::All(l))], vec![])
+    };
+    assert_eq!(all(btreeset! {1, 3}).apply(default_entry.clone()).0, true);
+    assert_eq!(all(btreeset! {}).apply(default_entry.clone()).0, true);
+    assert_eq!(all(btreeset! {1, 4}).apply(default_entry.clone()).0, false);
+
+    assert_eq!(
+        RouteMap::new(10, Allow, vec![Match::CommunityList(CList::All(btreeset! {1, 2}))], vec![]),
+        RouteMapBuilder::new().order(10).allow().match_community_all(vec![2, 1]).build()
+    );
+
+    // add and delete lists of communities
+    let map = RouteMapBuilder::new()
+        .order(10)
+        .allow()
+        .match_community_any(vec![3, 7])
+        .add_communities(vec![4, 5])
+        .delete_communities(vec![1, 3, 6])
+        .build();
+    let entry = map.apply(default_entry).1.unwrap();
+    assert_eq!(entry.route.communities, btreeset! {2, 4, 5});
+}
+
+#[test]
+fn evaluate_route_maps() {
+    let route = BgpRoute {
+        prefix: Prefix(0),
+        as_path: vec![AsId(1), AsId(2)],
+        next_hop: 1.into(),
+        local_pref: None,
+        med: None,
+        communities: btreeset! {10},
+        originator_id: None,
+        cluster_list: Vec::new(),
+    };
+    let with = |f: fn(&mut BgpRoute)| {
+        let mut r = route.clone();
+        f(&mut r);
+        r
+    };
+    let (n1, n2) = (1.into(), 2.into());
+    let (inc, out) = (RouteMapDirection::Incoming, RouteMapDirection::Outgoing);
+
+    let deny_p1 = RouteMapBuilder::new().order(10).deny().match_prefix(Prefix(1)).build();
+    let lp_n1 =
+        RouteMapBuilder::new().order(20).allow().match_neighbor(n1).set_local_pref(50).build();
+    let tag = RouteMapBuilder::new().order(30).allow().add_community(20).set_med(5).build();
+    let deny_all = RouteMapBuilder::new().order(40).deny().build();
+    let prepend = RouteMapBuilder::new().order(5).allow().prepend_as_path(AsId(9), 2).build();
+
+    // (maps, route, neighbor, direction, expected outcome)
+    let cases: Vec<(Vec<RouteMap>, BgpRoute, _, _, Option<BgpRoute>)> = vec![
+        // no route maps accept the route unchanged
+        (vec![], route.clone(), n1, inc, Some(route.clone())),
+        // denied prefix
+        (vec![deny_p1.clone()], with(|r| r.prefix = Prefix(1)), n1, inc, None),
+        // no route map matches
+        (vec![deny_p1.clone()], route.clone(), n1, inc, Some(route.clone())),
+        // the first matching route map decides
+        (
+            vec![deny_all.clone(), tag.clone(), lp_n1.clone()],
+            route.clone(),
+            n1,
+            inc,
+            Some(with(|r| r.local_pref = Some(50))),
+        ),
+        (
+            vec![deny_all.clone(), tag.clone(), lp_n1.clone()],
+            route.clone(),
+            n2,
+            inc,
+            Some(with(|r| {
+                r.communities.insert(20);
+                r.med = Some(5);
+            })),
+        ),
+        (vec![deny_all.clone(), lp_n1.clone()], route.clone(), n2, out, None),
+        // the neighbor is matched on outgoing route maps as well
+        (
+            vec![lp_n1.clone(), deny_all.clone()],
+            route.clone(),
+            n1,
+            out,
+            Some(with(|r| r.local_pref = Some(50))),
+        ),
+        // AS path prepending
+        (
+            vec![prepend, deny_p1],
+            route.clone(),
+            n2,
+            out,
+            Some(with(|r| r.as_path = vec![AsId(9), AsId(9), AsId(1), AsId(2)])),
+        ),
+    ];
+
+    for (i, (maps, route, neighbor, direction, expected)) in cases.into_iter().enumerate() {
+        assert_eq!(evaluate(route, neighbor, direction, &maps), expected, "case {}", i);
+    }
 }
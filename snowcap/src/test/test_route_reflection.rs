@@ -0,0 +1,143 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for route reflection with ORIGINATOR_ID, CLUSTER_LIST and cluster IDs
+
+#![cfg(test)]
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier};
+use crate::netsim::route_map::{RouteMapBuilder, RouteMapDirection};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// Network:
+///
+/// ```text
+///          rr1 ---- rr2
+///           |  \  /  |
+///           |   \/   |
+///           |   /\   |
+///           |  /  \  |
+/// e1 ----- c1        c2
+/// ```
+///
+/// `rr1` and `rr2` are route reflectors (connected with a peer session), and `c1` and `c2` are
+/// clients of both. All links have weight 1, and `e1` advertises the prefix.
+fn two_rr_net() -> (Network, [RouterId; 5]) {
+    let mut net = Network::new();
+    let rr1 = net.add_router("rr1");
+    let rr2 = net.add_router("rr2");
+    let c1 = net.add_router("c1");
+    let c2 = net.add_router("c2");
+    let e1 = net.add_external_router("e1", AsId(65101));
+
+    let mut c = Config::new();
+    for (a, b) in vec![(rr1, rr2), (rr1, c1), (rr1, c2), (rr2, c1), (rr2, c2), (c1, e1)] {
+        net.add_link(a, b);
+        c.add(IgpLinkWeight { source: a, target: b, weight: 1.0 }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight: 1.0 }).unwrap();
+    }
+    c.add(BgpSession { source: c1, target: e1, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: rr1, target: rr2, session_type: IBgpPeer }).unwrap();
+    for rr in vec![rr1, rr2] {
+        for client in vec![c1, c2] {
+            c.add(BgpSession { source: rr, target: client, session_type: IBgpClient }).unwrap();
+        }
+    }
+    net.set_config(&c).unwrap();
+
+    net.advertise_external_route(e1, Prefix(0), vec![AsId(65101)], None, None).unwrap();
+
+    (net, [rr1, rr2, c1, c2, e1])
+}
+
+#[test]
+fn reflected_attributes() {
+    let (net, [rr1, rr2, c1, c2, e1]) = two_rr_net();
+    let p = Prefix(0);
+
+    // both route reflectors set c1 as originator, and add their own cluster ID.
+    let mut routes = net.get_device(c2).unwrap_internal().get_known_bgp_routes(p).unwrap();
+    routes.sort_by_key(|e| e.from_id);
+    assert_eq!(routes.len(), 2);
+    for (entry, rr) in routes.iter().zip(vec![rr1, rr2]) {
+        assert_eq!(entry.from_id, rr);
+        assert_eq!(entry.route.originator_id, Some(c1));
+        assert_eq!(entry.route.cluster_list, vec![rr]);
+    }
+
+    // the route learned from the client is preferred over the one reflected by the other route
+    // reflector, since its cluster list is shorter.
+    let rr2_routes = net.get_device(rr2).unwrap_internal().get_known_bgp_routes(p).unwrap();
+    assert_eq!(rr2_routes.len(), 2);
+    let selected = net.get_device(rr2).unwrap_internal().get_selected_bgp_route(p).unwrap();
+    assert_eq!(selected.from_id, c1);
+    assert_eq!(selected.route.originator_id, None);
+    assert!(selected.route.cluster_list.is_empty());
+
+    // the attributes are not sent to external peers, and do not affect forwarding.
+    assert_eq!(net.get_forwarding_state().get_route(c2, p).unwrap(), vec![c2, c1, e1]);
+}
+
+#[test]
+fn cluster_id_loop_prevention() {
+    let (mut net, [rr1, rr2, c1, _, _]) = two_rr_net();
+    let p = Prefix(0);
+    assert_eq!(net.get_device(rr2).unwrap_internal().get_cluster_id(), rr2);
+
+    // place both route reflectors in the same cluster
+    let modifier = ConfigModifier::Insert(BgpClusterId { router: rr2, cluster_id: rr1 });
+    net.apply_modifier(&modifier).unwrap();
+    assert_eq!(net.get_device(rr2).unwrap_internal().get_cluster_id(), rr1);
+
+    // both route reflectors drop the route reflected by the other one
+    for rr in vec![rr1, rr2] {
+        let routes = net.get_device(rr).unwrap_internal().get_known_bgp_routes(p).unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].from_id, c1);
+    }
+
+    net.apply_modifier(&ConfigModifier::Remove(BgpClusterId { router: rr2, cluster_id: rr1 }))
+        .unwrap();
+    assert_eq!(net.get_device(rr2).unwrap_internal().get_cluster_id(), rr2);
+    assert_eq!(net.get_device(rr2).unwrap_internal().get_known_bgp_routes(p).unwrap().len(), 2);
+}
+
+#[test]
+fn originator_id_loop_prevention() {
+    let (mut net, [rr1, rr2, c1, c2, e1]) = two_rr_net();
+    let p = Prefix(0);
+
+    // rr2 prefers the route reflected by rr1, and reflects it back to c1.
+    net.apply_modifier(&ConfigModifier::Insert(BgpRouteMap {
+        router: rr2,
+        direction: RouteMapDirection::Incoming,
+        map: RouteMapBuilder::new()
+            .order(10)
+            .allow()
+            .match_neighbor(rr1)
+            .set_local_pref(200)
+            .build(),
+    }))
+    .unwrap();
+    let selected = net.get_device(rr2).unwrap_internal().get_selected_bgp_route(p).unwrap();
+    assert_eq!(selected.from_id, rr1);
+
+    // c1 drops the route, since it is the originator.
+    let routes = net.get_device(c1).unwrap_internal().get_known_bgp_routes(p).unwrap();
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].from_id, e1);
+    assert_eq!(net.get_forwarding_state().get_route(c2, p).unwrap(), vec![c2, c1, e1]);
+}
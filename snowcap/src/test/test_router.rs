@@ -19,10 +19,12 @@ use crate::netsim::bgp::BgpSessionType::{EBgp, IBgpClient, IBgpPeer};
 use crate::netsim::bgp::{BgpEvent, BgpRoute};
 use crate::netsim::event::{Event, EventQueue};
 use crate::netsim::external_router::*;
+use crate::netsim::route_map::RouteMapBuilder;
 use crate::netsim::router::*;
-use crate::netsim::types::IgpNetwork;
+use crate::netsim::types::{IgpAreas, IgpNetwork};
 use crate::netsim::{AsId, DeviceError, Prefix};
 use maplit::{hashmap, hashset};
+use std::collections::BTreeSet;
 
 #[test]
 fn test_bgp_single() {
@@ -63,7 +65,9 @@ fn test_bgp_single() {
                 next_hop: 100.into(),
                 local_pref: None,
                 med: None,
-                community: None,
+                communities: BTreeSet::new(),
+                originator_id: None,
+                cluster_list: Vec::new(),
             }),
         ),
         &mut queue,
@@ -105,7 +109,9 @@ fn test_bgp_single() {
                 next_hop: 11.into(),
                 local_pref: Some(50),
                 med: None,
-                community: None,
+                communities: BTreeSet::new(),
+                originator_id: None,
+                cluster_list: Vec::new(),
             }),
         ),
         &mut queue,
@@ -150,7 +156,9 @@ fn test_bgp_single() {
                 next_hop: 10.into(),
                 local_pref: None,
                 med: None,
-                community: None,
+                communities: BTreeSet::new(),
+                originator_id: None,
+                cluster_list: Vec::new(),
             }),
         ),
         &mut queue,
@@ -191,7 +199,9 @@ fn test_bgp_single() {
                 next_hop: 5.into(),
                 local_pref: Some(150),
                 med: None,
-                community: None,
+                communities: BTreeSet::new(),
+                originator_id: None,
+                cluster_list: Vec::new(),
             }),
         ),
         &mut queue,
@@ -322,7 +332,8 @@ fn test_fw_table_simple() {
      * a       e
      */
 
-    a.write_igp_forwarding_table(&net, &mut EventQueue::new(), 0, false).unwrap();
+    a.write_igp_forwarding_table(&net, &IgpAreas::default(), &mut EventQueue::new(), 0, false)
+        .unwrap();
 
     let expected_forwarding_table = hashmap! {
         a.router_id() => Some((a.router_id(), 0.0)),
@@ -339,7 +350,8 @@ fn test_fw_table_simple() {
         assert_eq!(exp.get(&target.router_id()), acq.get(&target.router_id()));
     }
 
-    b.write_igp_forwarding_table(&net, &mut EventQueue::new(), 0, false).unwrap();
+    b.write_igp_forwarding_table(&net, &IgpAreas::default(), &mut EventQueue::new(), 0, false)
+        .unwrap();
 
     let expected_forwarding_table = hashmap! {
         a.router_id() => Some((a.router_id(), 1.0)),
@@ -356,7 +368,8 @@ fn test_fw_table_simple() {
         assert_eq!(exp.get(&target.router_id()), acq.get(&target.router_id()));
     }
 
-    c.write_igp_forwarding_table(&net, &mut EventQueue::new(), 0, false).unwrap();
+    c.write_igp_forwarding_table(&net, &IgpAreas::default(), &mut EventQueue::new(), 0, false)
+        .unwrap();
 
     let expected_forwarding_table = hashmap! {
         a.router_id() => Some((b.router_id(), 2.0)),
@@ -419,7 +432,8 @@ fn test_igp_fw_table_complex() {
      *    1      8      1
      */
 
-    a.write_igp_forwarding_table(&net, &mut EventQueue::new(), 0, false).unwrap();
+    a.write_igp_forwarding_table(&net, &IgpAreas::default(), &mut EventQueue::new(), 0, false)
+        .unwrap();
 
     let expected_forwarding_table = hashmap! {
         a.router_id() => Some((a.router_id(), 0.0)),
@@ -439,7 +453,8 @@ fn test_igp_fw_table_complex() {
         assert_eq!(exp.get(&target.router_id()), acq.get(&target.router_id()));
     }
 
-    c.write_igp_forwarding_table(&net, &mut EventQueue::new(), 0, false).unwrap();
+    c.write_igp_forwarding_table(&net, &IgpAreas::default(), &mut EventQueue::new(), 0, false)
+        .unwrap();
 
     let expected_forwarding_table = hashmap! {
         a.router_id() => Some((f.router_id(), 3.0)),
@@ -492,7 +507,9 @@ fn external_router_advertise_to_neighbors() {
                 next_hop: 0.into(),
                 local_pref: None,
                 med: None,
-                community: None,
+                communities: BTreeSet::new(),
+                originator_id: None,
+                cluster_list: Vec::new(),
             }),
         )
     );
@@ -536,7 +553,9 @@ fn external_router_new_neighbor() {
                 next_hop: 0.into(),
                 local_pref: None,
                 med: None,
-                community: None,
+                communities: BTreeSet::new(),
+                originator_id: None,
+                cluster_list: Vec::new(),
             }),
         )
     );
@@ -549,3 +568,149 @@ fn external_router_new_neighbor() {
     r.widthdraw_prefix(Prefix(0), &mut queue, 0);
     assert_eq!(queue.len(), 0);
 }
+
+#[test]
+fn test_route_refresh() {
+    let mut r = Router::new("test".to_string(), 0.into(), AsId(65001));
+    let mut queue: EventQueue = EventQueue::new();
+    r.establish_bgp_session(100.into(), EBgp, &mut queue, 0, false).unwrap();
+    r.establish_bgp_session(1.into(), IBgpPeer, &mut queue, 0, false).unwrap();
+    r.igp_forwarding_table = hashmap! {
+        100.into() => Some((100.into(), 0.0)),
+        1.into()   => Some((1.into(), 1.0)),
+    };
+
+    let route = BgpRoute {
+        prefix: Prefix(200),
+        as_path: vec![AsId(1), AsId(2)],
+        next_hop: 100.into(),
+        local_pref: None,
+        med: None,
+        communities: BTreeSet::new(),
+        originator_id: None,
+        cluster_list: Vec::new(),
+    };
+    r.handle_event(Event::Bgp(100.into(), 0.into(), BgpEvent::Update(route)), &mut queue, 0)
+        .unwrap();
+    assert_eq!(r.get_selected_bgp_route(Prefix(200)).unwrap().route.next_hop, 100.into());
+    queue.clear();
+
+    // deny all routes from 100. Instead of updating the tables, a route refresh is requested
+    let map = RouteMapBuilder::new().order(10).deny().match_neighbor(100.into()).build();
+    r.add_bgp_route_map_in(map, &mut queue, 0, false).unwrap();
+    assert!(r.get_selected_bgp_route(Prefix(200)).is_some());
+    assert_eq!(queue.len(), 2);
+    while let Some((job, _)) = queue.pop_front() {
+        match job {
+            Event::Bgp(from, to, BgpEvent::RouteRefresh(p)) => {
+                assert_eq!(from, 0.into());
+                assert!(to == 100.into() || to == 1.into());
+                assert_eq!(p, Prefix(200));
+            }
+            _ => assert!(false),
+        }
+    }
+
+    // a route refresh request from the peer causes the route to be sent again
+    r.handle_event(
+        Event::Bgp(1.into(), 0.into(), BgpEvent::RouteRefresh(Prefix(200))),
+        &mut queue,
+        0,
+    )
+    .unwrap();
+    assert_eq!(queue.len(), 1);
+    match queue.pop_front() {
+        Some((Event::Bgp(from, to, BgpEvent::Update(route)), _)) => {
+            assert_eq!(from, 0.into());
+            assert_eq!(to, 1.into());
+            assert_eq!(route.prefix, Prefix(200));
+        }
+        _ => assert!(false),
+    }
+
+    // receiving the refreshed route applies the new policy
+    let route = BgpRoute {
+        prefix: Prefix(200),
+        as_path: vec![AsId(1), AsId(2)],
+        next_hop: 100.into(),
+        local_pref: None,
+        med: None,
+        communities: BTreeSet::new(),
+        originator_id: None,
+        cluster_list: Vec::new(),
+    };
+    r.handle_event(Event::Bgp(100.into(), 0.into(), BgpEvent::Update(route)), &mut queue, 0)
+        .unwrap();
+    assert!(r.get_selected_bgp_route(Prefix(200)).is_none());
+}
+
+#[test]
+fn test_unchanged_route_is_not_processed() {
+    let mut r = Router::new("test".to_string(), 0.into(), AsId(65001));
+    let mut queue: EventQueue = EventQueue::new();
+    r.establish_bgp_session(100.into(), EBgp, &mut queue, 0, false).unwrap();
+    r.establish_bgp_session(1.into(), IBgpPeer, &mut queue, 0, false).unwrap();
+    r.igp_forwarding_table = hashmap! {
+        100.into() => Some((100.into(), 0.0)),
+        1.into()   => Some((1.into(), 1.0)),
+    };
+
+    let route = BgpRoute {
+        prefix: Prefix(200),
+        as_path: vec![AsId(1), AsId(2)],
+        next_hop: 100.into(),
+        local_pref: None,
+        med: None,
+        communities: BTreeSet::new(),
+        originator_id: None,
+        cluster_list: Vec::new(),
+    };
+    let update =
+        |route: &BgpRoute| Event::Bgp(100.into(), 0.into(), BgpEvent::Update(route.clone()));
+    assert!(r.handle_event(update(&route), &mut queue, 0).unwrap());
+    assert_eq!(queue.len(), 1);
+    queue.clear();
+
+    // receiving the same route again does not change anything
+    assert!(!r.handle_event(update(&route), &mut queue, 0).unwrap());
+    assert_eq!(queue.len(), 0);
+    r.undo_last_event().unwrap();
+    assert_eq!(r.get_selected_bgp_route(Prefix(200)).unwrap().route, route);
+
+    // a changed route is processed and disseminated
+    let changed = BgpRoute { as_path: vec![AsId(1), AsId(3)], ..route.clone() };
+    assert!(!r.handle_event(update(&changed), &mut queue, 0).unwrap());
+    assert_eq!(queue.len(), 1);
+    assert_eq!(r.get_selected_bgp_route(Prefix(200)).unwrap().route, changed);
+
+    // withdrawing an unknown route does not change anything either
+    queue.clear();
+    r.handle_event(Event::Bgp(1.into(), 0.into(), BgpEvent::Withdraw(Prefix(200))), &mut queue, 0)
+        .unwrap();
+    assert_eq!(queue.len(), 0);
+    assert!(r.get_selected_bgp_route(Prefix(200)).is_some());
+}
+
+#[test]
+fn test_external_router_route_refresh() {
+    let mut r = ExternalRouter::new("test".to_string(), 0.into(), AsId(65001));
+    let mut queue: EventQueue = EventQueue::new();
+    r.establish_ebgp_session(1.into(), &mut queue, 0, false).unwrap();
+    r.advertise_prefix(Prefix(0), vec![AsId(0)], None, None, &mut queue, 0);
+    queue.clear();
+
+    r.handle_event(
+        Event::Bgp(1.into(), 0.into(), BgpEvent::RouteRefresh(Prefix(0))),
+        &mut queue,
+        0,
+    )
+    .unwrap();
+    assert_eq!(queue.len(), 1);
+    r.handle_event(
+        Event::Bgp(1.into(), 0.into(), BgpEvent::RouteRefresh(Prefix(1))),
+        &mut queue,
+        0,
+    )
+    .unwrap();
+    assert_eq!(queue.len(), 1);
+}
@@ -0,0 +1,82 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::netsim::config::ConfigModifier;
+
+#[test]
+fn test_scale_simple_net() {
+    let net = SimpleNet::net(0);
+    let (scaled, scaling) = net.scale(3).unwrap();
+
+    assert_eq!(scaling.num_pods(), 3);
+    assert_eq!(scaled.num_devices(), 3 * net.num_devices());
+    assert_eq!(scaled.links_symmetric().count(), 3 * net.links_symmetric().count() + 2);
+    assert_eq!(scaled.current_config().len(), 3 * net.current_config().len() + 6);
+    assert_eq!(scaled.get_known_prefixes(), net.get_known_prefixes());
+
+    // names are kept in the first pod, and suffixed in all others
+    for router in net.get_routers() {
+        let name = net.get_router_name(router).unwrap();
+        assert_eq!(scaled.get_router_id(name).unwrap(), scaling.router(router, 0).unwrap());
+        assert_eq!(
+            scaled.get_router_id(format!("{}_2", name)).unwrap(),
+            scaling.router(router, 2).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_scale_keeps_forwarding_in_pods() {
+    let mut net = ChainGadget::<Repetition3>::net(0);
+    let final_config = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let (mut scaled, scaling) = net.scale(2).unwrap();
+    let scaled_final_config = scaling.map_config(&final_config).unwrap();
+
+    net.set_config(&final_config).unwrap();
+    scaled.set_config(&scaled_final_config).unwrap();
+
+    for pod in 0..scaling.num_pods() {
+        for router in net.get_routers() {
+            for prefix in net.get_known_prefixes() {
+                let path = net.get_route(router, *prefix).unwrap();
+                let expected: Vec<_> =
+                    path.into_iter().map(|r| scaling.router(r, pod).unwrap()).collect();
+                let scaled_router = scaling.router(router, pod).unwrap();
+                assert_eq!(scaled.get_route(scaled_router, *prefix).unwrap(), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_scale_modifier() {
+    let net = ChainGadget::<Repetition3>::net(0);
+    let final_config = ChainGadget::<Repetition3>::final_config(&net, 0);
+    let (scaled, scaling) = net.scale(2).unwrap();
+
+    let patch = net.current_config().get_diff(&final_config);
+    let scaled_patch =
+        scaled.current_config().get_diff(&scaling.map_config(&final_config).unwrap());
+    let mapped: Vec<ConfigModifier> =
+        patch.modifiers.iter().flat_map(|m| scaling.map_modifier(m).unwrap()).collect();
+    assert_eq!(mapped.len(), scaled_patch.modifiers.len());
+    for modifier in mapped {
+        assert!(scaled_patch.modifiers.contains(&modifier));
+    }
+}
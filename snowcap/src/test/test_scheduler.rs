@@ -0,0 +1,143 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for the event schedulers
+
+#![cfg(test)]
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier::*};
+use crate::netsim::scheduler::*;
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// Network:
+///
+/// ```text
+///              1        5
+/// e1 ---- r1 ----- r2 ----- r3 ---- e3
+/// ```
+///
+/// `r2` is the route reflector of `r1` and `r3`. Both `e1` and `e3` advertise the prefix.
+fn line_net(scheduler: Option<Box<dyn EventScheduler>>) -> (Network, [RouterId; 5]) {
+    let mut n = Network::new();
+    n.set_scheduler(scheduler);
+    let mut c = Config::new();
+
+    let r1 = n.add_router("r1");
+    let r2 = n.add_router("r2");
+    let r3 = n.add_router("r3");
+    let e1 = n.add_external_router("e1", AsId(65101));
+    let e3 = n.add_external_router("e3", AsId(65103));
+
+    for (a, b, weight) in vec![(r1, r2, 1.0), (r2, r3, 5.0), (r1, e1, 1.0), (r3, e3, 1.0)] {
+        n.add_link(a, b);
+        c.add(IgpLinkWeight { source: a, target: b, weight }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight }).unwrap();
+    }
+
+    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r2, target: r1, session_type: IBgpClient }).unwrap();
+    c.add(BgpSession { source: r2, target: r3, session_type: IBgpClient }).unwrap();
+
+    n.set_config(&c).unwrap();
+
+    n.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
+    n.advertise_external_route(e3, Prefix(0), vec![AsId(65103), AsId(65200)], None, None).unwrap();
+
+    (n, [r1, r2, r3, e1, e3])
+}
+
+/// Remove the session from `r1` to `e1`, and return the event trace of the convergence.
+fn remove_session(net: &mut Network, r1: RouterId, e1: RouterId) -> Vec<String> {
+    net.clear_undo_stack();
+    net.apply_modifier(&Remove(BgpSession { source: r1, target: e1, session_type: EBgp })).unwrap();
+    net.event_trace()
+}
+
+fn msg(from: usize, to: usize, parent: usize) -> PendingMessage {
+    PendingMessage { from: (from as u32).into(), to: (to as u32).into(), prefix: Prefix(0), parent }
+}
+
+#[test]
+fn fifo_is_default() {
+    let (mut default, [r1, _, _, e1, _]) = line_net(None);
+    let (mut fifo, _) = line_net(Some(Box::new(FifoScheduler)));
+    assert!(fifo.get_scheduler().is_some());
+    assert_eq!(remove_session(&mut default, r1, e1), remove_session(&mut fifo, r1, e1));
+}
+
+#[test]
+fn schedulers_converge() {
+    let (mut default, [r1, r2, r3, e1, e3]) = line_net(None);
+    remove_session(&mut default, r1, e1);
+    let mut delay = DelayScheduler::new(1.0);
+    delay.set_delay(r2, r1, 10.0);
+
+    let schedulers: Vec<Box<dyn EventScheduler>> = vec![
+        Box::new(LifoScheduler),
+        Box::new(RandomScheduler::new(42)),
+        Box::new(RandomScheduler::new(7)),
+        Box::new(delay),
+    ];
+    for scheduler in schedulers {
+        let (mut net, _) = line_net(Some(scheduler));
+        remove_session(&mut net, r1, e1);
+        for r in vec![r1, r2, r3] {
+            assert_eq!(
+                net.get_route(r, Prefix(0)).unwrap(),
+                default.get_route(r, Prefix(0)).unwrap()
+            );
+        }
+        assert_eq!(net.get_route(r1, Prefix(0)).unwrap(), vec![r1, r2, r3, e3]);
+    }
+}
+
+#[test]
+fn random_is_deterministic() {
+    let (mut a, [r1, _, _, e1, _]) = line_net(Some(Box::new(RandomScheduler::new(42))));
+    let (mut b, _) = line_net(Some(Box::new(RandomScheduler::new(42))));
+    assert_eq!(remove_session(&mut a, r1, e1), remove_session(&mut b, r1, e1));
+
+    // clones continue with the same random numbers
+    let (net, _) = line_net(Some(Box::new(RandomScheduler::new(3))));
+    let mut c = net.clone();
+    let mut d = net.clone();
+    assert_eq!(remove_session(&mut c, r1, e1), remove_session(&mut d, r1, e1));
+}
+
+#[test]
+fn lifo_scheduler() {
+    let mut s = LifoScheduler;
+    assert_eq!(s.schedule(&[msg(0, 1, 0), msg(1, 2, 0), msg(2, 0, 0)], 1), 2);
+    assert_eq!(s.schedule(&[], 1), 0);
+}
+
+#[test]
+fn delay_scheduler() {
+    let mut s = DelayScheduler::new(1.0);
+    s.set_delay(0.into(), 1.into(), 10.0);
+    assert_eq!(s.get_delay(0.into(), 1.into()), 10.0);
+    assert_eq!(s.get_delay(1.into(), 0.into()), 1.0);
+
+    // the message from 2 to 1 arrives first
+    assert_eq!(s.schedule(&[msg(0, 1, 0), msg(2, 1, 0)], 1), 1);
+    assert_eq!(s.clock(), 1.0);
+    // the answer of 1 (sent at time 1) arrives before the slow message
+    assert_eq!(s.schedule(&[msg(0, 1, 0), msg(1, 2, 1)], 2), 1);
+    assert_eq!(s.clock(), 2.0);
+    assert_eq!(s.schedule(&[msg(0, 1, 0)], 3), 0);
+    assert_eq!(s.clock(), 10.0);
+}
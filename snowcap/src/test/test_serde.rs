@@ -0,0 +1,115 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use crate::example_networks::*;
+use crate::netsim::config::{Config, ConfigMeta, ConfigModifier, ConfigPatch};
+use crate::netsim::{Ipv4Net, Network, RouterRole};
+use crate::traffic_matrix::TrafficMatrix;
+
+fn roundtrip_net(net: &Network) -> Network {
+    let json = serde_json::to_string(net).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn config_roundtrip() {
+    let net = FirewallNet::net(0);
+    let mut config = FirewallNet::final_config(&net, 0);
+    let key = config.iter().next().unwrap().key();
+    config.annotate(key.clone(), ConfigMeta::named("test").ticket("CHG-1"));
+
+    let json = serde_json::to_string(&config).unwrap();
+    let restored: Config = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, config);
+    assert_eq!(restored.get_meta(&key), config.get_meta(&key));
+
+    // the output is stable
+    assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+}
+
+#[test]
+fn modifier_roundtrip() {
+    let net = MediumNet::net(0);
+    let patch = net.current_config().get_diff(&MediumNet::final_config(&net, 0));
+    assert!(!patch.modifiers.is_empty());
+
+    let json = serde_json::to_string(&patch.modifiers).unwrap();
+    let restored: Vec<ConfigModifier> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, patch.modifiers);
+
+    let json = serde_json::to_string(&patch).unwrap();
+    let restored: ConfigPatch = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, patch);
+}
+
+#[test]
+fn network_roundtrip() {
+    let net = SimpleNet::net(0);
+    let restored = roundtrip_net(&net);
+
+    assert_eq!(restored.get_routers(), net.get_routers());
+    assert_eq!(restored.get_external_routers(), net.get_external_routers());
+    for router in net.get_routers().into_iter().chain(net.get_external_routers()) {
+        assert_eq!(restored.get_router_name(router), net.get_router_name(router));
+    }
+    assert_eq!(restored.current_config(), net.current_config());
+    assert_eq!(restored.get_known_prefixes(), net.get_known_prefixes());
+    assert!(restored.weak_eq(&net));
+}
+
+#[test]
+fn network_roundtrip_mid_experiment() {
+    let mut net = SimpleNet::net(0);
+    let patch = net.current_config().get_diff(&SimpleNet::final_config(&net, 0));
+    // apply only the first half of the modifiers
+    for modifier in patch.modifiers.iter().take(patch.modifiers.len() / 2) {
+        net.apply_modifier(modifier).unwrap();
+    }
+
+    let mut restored = roundtrip_net(&net);
+    assert_eq!(restored.current_config(), net.current_config());
+    assert_eq!(restored.get_forwarding_state(), net.get_forwarding_state());
+
+    // the remaining modifiers can be applied to the restored network
+    for modifier in patch.modifiers.iter().skip(patch.modifiers.len() / 2) {
+        net.apply_modifier(modifier).unwrap();
+        restored.apply_modifier(modifier).unwrap();
+    }
+    assert_eq!(restored.get_forwarding_state(), net.get_forwarding_state());
+}
+
+#[test]
+fn network_roundtrip_annotations() {
+    let mut net = SimpleNet::net(0);
+    let r1 = net.get_router_id("r1").unwrap();
+    let r4 = net.get_router_id("r4").unwrap();
+    net.add_router_role(r1, RouterRole::Border).unwrap();
+    net.add_router_role(r1, RouterRole::RouteReflector).unwrap();
+    let mut tm = TrafficMatrix::new();
+    let prefix = *net.get_known_prefixes().iter().next().unwrap();
+    tm.add_demand(r4, prefix, 10.0);
+    net.set_traffic_matrix(Some(tm.clone()));
+    net.set_msg_limit(Some(5_000));
+    let range: Ipv4Net = "10.0.0.0/16".parse().unwrap();
+    net.set_prefix_range(prefix, range);
+
+    let restored = roundtrip_net(&net);
+    assert_eq!(restored.get_router_roles(r1), net.get_router_roles(r1));
+    assert_eq!(restored.get_traffic_matrix(), Some(&tm));
+    assert_eq!(restored.get_msg_limit(), Some(5_000));
+    assert_eq!(restored.get_prefix_range(prefix), Some(range));
+}
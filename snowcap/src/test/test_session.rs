@@ -0,0 +1,100 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::netsim::config::{ConfigExpr, ConfigModifier};
+use crate::netsim::Prefix;
+use crate::plan_editor::PlanEditor;
+use crate::session::SynthesisSession;
+use crate::{ControlHandle, Error};
+
+use std::time::Duration;
+
+fn reachability<N: ExampleNetwork>() -> (SynthesisSession, HardPolicy) {
+    let net = N::net(0);
+    let final_config = N::final_config(&net, 0);
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    (SynthesisSession::new(net, &final_config, HardPolicy::globally(vec![])), hard_policy)
+}
+
+#[test]
+fn test_extend_globally() {
+    let mut hard_policy = HardPolicy::globally(vec![]);
+    let c = Condition::Reachable(0.into(), Prefix(0), None);
+    hard_policy.extend_globally(vec![c.clone()]).unwrap();
+    assert_eq!(hard_policy.prop_vars, vec![c.clone()]);
+    hard_policy.extend_globally(vec![]).unwrap();
+    assert_eq!(hard_policy.prop_vars.len(), 1);
+
+    let mut anchored = WindowedPolicyBuilder::new()
+        .anchor(
+            "a",
+            ConfigModifier::Remove(ConfigExpr::IgpLinkWeight {
+                source: 0.into(),
+                target: 1.into(),
+                weight: 1.0,
+            }),
+        )
+        .build()
+        .unwrap();
+    match anchored.extend_globally(vec![c]) {
+        Err(Error::AnchoredPolicyExtension) => {}
+        r => panic!("Expected an error, but got {:?}", r),
+    }
+}
+
+#[test]
+fn test_session_reuses_solution() {
+    let (mut session, hard_policy) = reachability::<ChainGadget<Repetition3>>();
+    let sequence = session.synthesize(Some(Duration::from_secs(60)), ControlHandle::new()).unwrap();
+    assert_eq!(sequence.len(), session.modifiers().len());
+    assert_eq!(session.solution(), Some(sequence.as_slice()));
+
+    // tighten the hard policy.
+    session.set_hard_policy(hard_policy.clone());
+    let sequence = session.synthesize(Some(Duration::from_secs(60)), ControlHandle::new()).unwrap();
+    assert_eq!(sequence.len(), session.modifiers().len());
+    assert_eq!(session.num_attempts(), 2);
+    let editor = PlanEditor::new(session.network(), sequence.clone(), hard_policy).unwrap();
+    assert!(editor.is_valid());
+
+    // the solution is still valid, so it must be returned without searching.
+    let again = session.synthesize(Some(Duration::from_secs(60)), ControlHandle::new()).unwrap();
+    assert_eq!(again, sequence);
+    assert_eq!(session.num_attempts(), 3);
+}
+
+#[test]
+fn test_session_extend_policy() {
+    let (mut session, hard_policy) = reachability::<DifficultGadgetMinimal>();
+    session.synthesize(Some(Duration::from_secs(60)), ControlHandle::new()).unwrap();
+
+    session.extend_hard_policy(hard_policy.prop_vars.clone()).unwrap();
+    assert_eq!(session.hard_policy().prop_vars, hard_policy.prop_vars);
+    let sequence = session.synthesize(Some(Duration::from_secs(60)), ControlHandle::new()).unwrap();
+    let editor = PlanEditor::new(session.network(), sequence, hard_policy).unwrap();
+    assert!(editor.is_valid());
+
+    // forget everything learned
+    session.forget();
+    assert!(session.solution().is_none());
+    assert!(session.learned_groups().is_empty());
+}
@@ -24,7 +24,7 @@ use crate::modifier_ordering::*;
 use crate::netsim::printer;
 use crate::permutators::*;
 use crate::strategies::*;
-use crate::{Error, Stopper};
+use crate::{ControlHandle, Error};
 
 use std::time::Duration;
 
@@ -44,7 +44,8 @@ where
         HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
 
     // create instance
-    let result = S::synthesize(net, cf, hard_policy, Some(Duration::from_secs(60)), Stopper::new());
+    let result =
+        S::synthesize(net, cf, hard_policy, Some(Duration::from_secs(60)), ControlHandle::new());
 
     assert!(result.is_ok());
 
@@ -72,7 +73,8 @@ where
         HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
 
     // create instance
-    let result = S::synthesize(net, cf, hard_policy, Some(Duration::from_secs(60)), Stopper::new());
+    let result =
+        S::synthesize(net, cf, hard_policy, Some(Duration::from_secs(60)), ControlHandle::new());
 
     match result {
         Ok(r) => panic!(
@@ -100,13 +102,13 @@ where
         cf.clone(),
         hard_policy,
         Some(Duration::from_secs(60)),
-        Stopper::new(),
+        ControlHandle::new(),
     ) {
         Ok(r) => panic!(
             "Solution was found!\n{:#?}",
             r.iter().map(|m| printer::config_modifier(&net, m).unwrap()).collect::<Vec<String>>()
         ),
-        Err(Error::InvalidInitialState) => {}
+        Err(Error::InvalidInitialState(_)) => {}
         Err(e) => panic!("Unexpected error: {}", e),
     }
 
@@ -122,7 +124,7 @@ where
         cf.clone(),
         hard_policy,
         Some(Duration::from_secs(10)),
-        Stopper::new(),
+        ControlHandle::new(),
     ) {
         Ok(r) => panic!(
             "Solution was found!\n{:#?}",
@@ -140,9 +142,14 @@ where
     let net = FirewallNet::net(variant);
     let cf = FirewallNet::final_config(&net, variant);
     let hard_policy = FirewallNet::get_policy(&net, variant);
-    assert!(
-        S::synthesize(net, cf, hard_policy, Some(Duration::from_secs(60)), Stopper::new()).is_ok()
-    );
+    assert!(S::synthesize(
+        net,
+        cf,
+        hard_policy,
+        Some(Duration::from_secs(60)),
+        ControlHandle::new()
+    )
+    .is_ok());
 }
 
 #[test]
@@ -329,6 +336,21 @@ fn one_strategy_to_rule_them_all() {
     test_net::<StrategyTRTA, DifficultGadgetRepeated<Repetition3>>(0, 0);
 }
 
+#[cfg(feature = "sat-strategy")]
+#[test]
+fn sat_strategy() {
+    test_net::<SatStrategy, SimpleNet>(0, 0);
+    test_net::<SatStrategy, SimpleNet>(1, 0);
+    test_net::<SatStrategy, SmallNet>(0, 1);
+    test_net::<SatStrategy, MediumNet>(0, 0);
+    test_net::<SatStrategy, MediumNet>(1, 3);
+    test_net::<SatStrategy, ChainGadget<Repetition5>>(0, 0);
+    test_net::<SatStrategy, DifficultGadgetRepeated<Repetition1>>(0, 0);
+    test_net::<SatStrategy, DifficultGadgetRepeated<Repetition2>>(0, 0);
+    test_net_no_solution::<SatStrategy, CarouselGadget>(0, 0);
+    test_net_bad_policy::<SatStrategy>();
+}
+
 #[test]
 fn firewall_net() {
     for variant in vec![0, 1] {
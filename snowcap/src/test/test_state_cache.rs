@@ -0,0 +1,123 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::example_networks::repetitions::*;
+use crate::example_networks::*;
+use crate::hard_policies::*;
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::Network;
+use crate::plan_editor::PlanEditor;
+use crate::strategies::{StateCache, Strategy, StrategyTRTA};
+use crate::ControlHandle;
+
+fn get_problem() -> (Network, Vec<ConfigModifier>, HardPolicy) {
+    let net = ChainGadget::<Repetition5>::net(0);
+    let final_config = ChainGadget::<Repetition5>::final_config(&net, 0);
+    let modifiers = net.current_config().get_diff(&final_config).modifiers;
+    let hard_policy =
+        HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+    (net, modifiers, hard_policy)
+}
+
+#[test]
+fn content_hash_is_order_independent() {
+    let (net, modifiers, _) = get_problem();
+    assert!(modifiers.len() >= 2);
+    let mut a = net.clone();
+    a.apply_modifier(&modifiers[0]).unwrap();
+    a.apply_modifier(&modifiers[1]).unwrap();
+    let mut b = net.clone();
+    b.apply_modifier(&modifiers[1]).unwrap();
+    b.apply_modifier(&modifiers[0]).unwrap();
+    assert_eq!(a.current_config().content_hash(), b.current_config().content_hash());
+    assert_ne!(a.current_config().content_hash(), net.current_config().content_hash());
+
+    // undoing the modifiers results in the initial hash
+    a.undo_action().unwrap();
+    a.undo_action().unwrap();
+    assert_eq!(a.current_config().content_hash(), net.current_config().content_hash());
+}
+
+#[test]
+fn least_recently_used_state_is_evicted() {
+    let (net, modifiers, _) = get_problem();
+    let mut nets = vec![net.clone()];
+    for m in modifiers.iter().take(2) {
+        let mut n = nets.last().unwrap().clone();
+        n.apply_modifier(m).unwrap();
+        nets.push(n);
+    }
+
+    let mut cache = StateCache::new(2);
+    cache.forwarding_state(&nets[0]);
+    cache.forwarding_state(&nets[1]);
+    cache.forwarding_state(&nets[0]);
+    assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+    // the second network is evicted, since the first was used more recently
+    cache.forwarding_state(&nets[2]);
+    assert_eq!(cache.len(), 2);
+    cache.forwarding_state(&nets[0]);
+    assert_eq!((cache.hits(), cache.misses()), (2, 3));
+    cache.forwarding_state(&nets[1]);
+    assert_eq!((cache.hits(), cache.misses()), (2, 4));
+}
+
+#[test]
+fn step_cached_is_equivalent_to_step() {
+    let (mut net, modifiers, hard_policy) = get_problem();
+    let mut cached = hard_policy.clone();
+    cached.enable_state_cache(64);
+    let mut uncached = hard_policy;
+
+    // apply and undo every modifier twice, such that the second time is answered from the cache
+    for _ in 0..2 {
+        for m in modifiers.iter() {
+            net.apply_modifier(m).unwrap();
+            cached.step_cached(&mut net).unwrap();
+            let mut state = net.get_forwarding_state();
+            uncached.step(&mut net, &mut state).unwrap();
+            assert_eq!(cached.check(), uncached.check());
+            assert_eq!(cached.get_watch_errors(), uncached.get_watch_errors());
+        }
+        for _ in modifiers.iter() {
+            net.undo_action().unwrap();
+            cached.undo();
+            uncached.undo();
+        }
+    }
+
+    let cache = cached.state_cache().unwrap();
+    let cache = cache.lock().unwrap();
+    assert_eq!(cache.len(), modifiers.len());
+    assert_eq!(cache.hits(), modifiers.len());
+}
+
+#[test]
+fn trta_shares_state_cache() {
+    let (net, modifiers, mut hard_policy) = get_problem();
+    hard_policy.enable_state_cache(64);
+    let cache = hard_policy.state_cache().unwrap();
+
+    let mut strategy =
+        StrategyTRTA::new(net.clone(), modifiers.clone(), hard_policy.clone(), None).unwrap();
+    let sequence = strategy.work(ControlHandle::new()).unwrap();
+    assert_eq!(sequence.len(), modifiers.len());
+    assert!(PlanEditor::new(&net, sequence, hard_policy).unwrap().is_valid());
+    assert!(!cache.lock().unwrap().is_empty());
+}
@@ -0,0 +1,112 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use crate::hard_policies::{Condition, HardPolicy, PathCondition, PolicyError};
+use crate::netsim::config::{Config, ConfigExpr::*};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// Network with two border routers `b` and `c`, each connected to an external router (`e1` and
+/// `e2`) that advertise the same prefix. `a` is closer to `b` than to `c`.
+fn two_borders() -> (Network, [RouterId; 3]) {
+    let mut net = Network::new();
+    let a = net.add_router("a");
+    let b = net.add_router("b");
+    let c = net.add_router("c");
+    let e1 = net.add_external_router("e1", AsId(65101));
+    let e2 = net.add_external_router("e2", AsId(65102));
+
+    net.add_link(a, b);
+    net.add_link(a, c);
+    net.add_link(b, e1);
+    net.add_link(c, e2);
+
+    let mut config = Config::new();
+    for (x, y, w) in vec![(a, b, 1.0), (a, c, 2.0), (b, e1, 1.0), (c, e2, 1.0)] {
+        config.add(IgpLinkWeight { source: x, target: y, weight: w }).unwrap();
+        config.add(IgpLinkWeight { source: y, target: x, weight: w }).unwrap();
+    }
+    config.add(BgpSession { source: a, target: b, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: a, target: c, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: b, target: e1, session_type: EBgp }).unwrap();
+    config.add(BgpSession { source: c, target: e2, session_type: EBgp }).unwrap();
+    net.set_config(&config).unwrap();
+
+    net.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None)
+        .unwrap();
+    net.advertise_external_route(e2, Prefix(0), vec![AsId(65102), AsId(65200)], None, None)
+        .unwrap();
+
+    (net, [a, b, c])
+}
+
+fn policy(a: RouterId, b: RouterId, c: RouterId) -> HardPolicy {
+    let p = Prefix(0);
+    HardPolicy::globally(vec![
+        Condition::Reachable(a, p, None),
+        Condition::Reliable(a, p, None),
+        Condition::Reliable(a, p, Some(PathCondition::Node(b))),
+        Condition::Reliable(c, p, Some(PathCondition::Node(c))),
+        Condition::Reliable(b, p, Some(PathCondition::Not(Box::new(PathCondition::Node(c))))),
+    ])
+}
+
+#[test]
+fn step_shared_equals_step() {
+    let (mut net, [a, b, c]) = two_borders();
+
+    let mut expected = policy(a, b, c);
+    let mut fw_state = net.get_forwarding_state();
+    expected.step(&mut net, &mut fw_state).unwrap();
+    assert!(!expected.check());
+
+    for num_threads in vec![0, 1, 2, 3, 8] {
+        let mut hard_policy = policy(a, b, c);
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step_shared(&net, &mut fw_state, num_threads).unwrap();
+        assert_eq!(hard_policy.check(), expected.check());
+        assert_eq!(hard_policy.get_watch_errors(), expected.get_watch_errors());
+    }
+}
+
+#[test]
+fn step_shared_reliability_errors() {
+    let (net, [a, b, c]) = two_borders();
+    let p = Prefix(0);
+    let before = net.get_forwarding_state();
+
+    let mut hard_policy = policy(a, b, c);
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step_shared(&net, &mut fw_state, 2).unwrap();
+
+    // the network is not changed
+    assert_eq!(net.get_forwarding_state(), before);
+
+    let (watch, errors) = hard_policy.get_watch_errors();
+    assert_eq!(watch, vec![2, 4]);
+    // if the link a -- b fails, then traffic from a leaves the network at c
+    assert!(matches!(
+        errors[0],
+        Some(PolicyError::ReliabilityCondition { prefix, link_a, link_b, .. })
+            if prefix == p && [link_a, link_b].contains(&a) && [link_a, link_b].contains(&b)
+    ));
+    // if the link b -- e1 fails, then traffic from b leaves the network at c
+    assert!(matches!(
+        errors[1],
+        Some(PolicyError::ReliabilityCondition { prefix, .. }) if prefix == p
+    ));
+}
@@ -0,0 +1,122 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for tracing the changes caused by a single modifier
+
+#![cfg(test)]
+use crate::netsim::bgp::BgpEvent;
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// Network:
+///
+/// ```text
+///              1        5
+/// e1 ---- r1 ----- r2 ----- r3 ---- e3
+/// ```
+///
+/// `r2` is the route reflector of `r1` and `r3`. Both `e1` and `e3` advertise `Prefix(0)`, while
+/// only `e3` advertises `Prefix(1)`.
+fn line_net() -> (Network, [RouterId; 5]) {
+    let mut n = Network::new();
+    let mut c = Config::new();
+
+    let r1 = n.add_router("r1");
+    let r2 = n.add_router("r2");
+    let r3 = n.add_router("r3");
+    let e1 = n.add_external_router("e1", AsId(65101));
+    let e3 = n.add_external_router("e3", AsId(65103));
+
+    for (a, b, weight) in vec![(r1, r2, 1.0), (r2, r3, 5.0), (r1, e1, 1.0), (r3, e3, 1.0)] {
+        n.add_link(a, b);
+        c.add(IgpLinkWeight { source: a, target: b, weight }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight }).unwrap();
+    }
+
+    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r2, target: r1, session_type: IBgpClient }).unwrap();
+    c.add(BgpSession { source: r2, target: r3, session_type: IBgpClient }).unwrap();
+
+    n.set_config(&c).unwrap();
+
+    n.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
+    n.advertise_external_route(e3, Prefix(0), vec![AsId(65103), AsId(65200)], None, None).unwrap();
+    n.advertise_external_route(e3, Prefix(1), vec![AsId(65103), AsId(65201)], None, None).unwrap();
+
+    (n, [r1, r2, r3, e1, e3])
+}
+
+#[test]
+fn trace_session_removal() {
+    let (mut net, [r1, r2, r3, e1, e3]) = line_net();
+    let modifier =
+        ConfigModifier::Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let trace = net.apply_modifier_with_trace(&modifier).unwrap();
+    assert_eq!(trace.modifier, modifier);
+    assert_eq!(trace.prefixes.len(), 2);
+
+    let p0 = &trace.prefixes[&Prefix(0)];
+    assert_eq!(p0.next_hop_changes.len(), 2);
+    assert_eq!(p0.next_hop_changes[&r1], (vec![e1], vec![r2]));
+    assert_eq!(p0.next_hop_changes[&r2], (vec![r1], vec![r3]));
+    assert!(!p0.next_hop_changes.contains_key(&r3));
+
+    assert_eq!(p0.path_changes.len(), 2);
+    assert_eq!(p0.path_changes[&r1], (vec![Ok(vec![r1, e1])], vec![Ok(vec![r1, r2, r3, e3])]));
+    assert_eq!(p0.path_changes[&r2], (vec![Ok(vec![r2, r1, e1])], vec![Ok(vec![r2, r3, e3])]));
+
+    // r1 withdraws the route from r2, which then tells r1 about the route from r3.
+    assert!(p0.messages.iter().any(|(from, to, e)| *from == r1
+        && *to == r2
+        && matches!(e, BgpEvent::Withdraw(Prefix(0)))));
+    assert!(p0.messages.iter().any(|(from, to, _)| *from == r2 && *to == r1));
+    assert_eq!(trace.num_messages(), p0.messages.len());
+
+    // the other prefix is not affected
+    assert!(trace.prefixes[&Prefix(1)].is_empty());
+    assert_eq!(trace.changed_routers(), vec![r1, r2].into_iter().collect());
+
+    // the trace is consistent with the forwarding state
+    assert_eq!(net.get_route(r1, Prefix(0)).unwrap(), vec![r1, r2, r3, e3]);
+}
+
+#[test]
+fn trace_undo() {
+    let (mut net, [r1, _, _, e1, _]) = line_net();
+    let modifier =
+        ConfigModifier::Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let trace = net.apply_modifier_with_trace(&modifier).unwrap();
+
+    // the modifier is applied as a single action, and undoing it reverts all changes.
+    net.undo_action().unwrap();
+    assert_eq!(net.get_route(r1, Prefix(0)).unwrap(), vec![r1, e1]);
+    assert_eq!(net.apply_modifier_with_trace(&modifier).unwrap(), trace);
+}
+
+#[test]
+fn trace_no_change() {
+    let (mut net, [r1, r2, _, _, _]) = line_net();
+    let modifier = ConfigModifier::Update {
+        from: IgpLinkWeight { source: r1, target: r2, weight: 1.0 },
+        to: IgpLinkWeight { source: r1, target: r2, weight: 2.0 },
+    };
+    let trace = net.apply_modifier_with_trace(&modifier).unwrap();
+    assert!(trace.prefixes.values().all(|t| t.is_empty()));
+    assert_eq!(trace.num_messages(), 0);
+    assert!(trace.changed_routers().is_empty());
+}
@@ -0,0 +1,281 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+//! Cross-check all exhaustive strategies and all optimizers on small random instances. All
+//! exhaustive strategies must agree with the [`GlobalOptimizer`] on whether a problem is solvable,
+//! and no optimizer may find a solution with a lower cost than the global optimum. New strategies
+//! and optimizers should be added to [`check_instance`].
+
+use crate::hard_policies::*;
+use crate::modifier_ordering::*;
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier};
+use crate::netsim::{
+    AsId, BgpSessionType, BgpSessionType::*, Network, NetworkError, Prefix, RouterId,
+};
+use crate::optimizers::*;
+use crate::permutators::*;
+use crate::soft_policies::*;
+use crate::strategies::*;
+use crate::{ControlHandle, Error};
+
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const NUM_INSTANCES: u64 = 20;
+const TIME_BUDGET: Duration = Duration::from_secs(30);
+
+/// Random instance of the reconfiguration problem.
+struct Instance {
+    seed: u64,
+    net: Network,
+    final_config: Config,
+    hard_policy: HardPolicy,
+}
+
+impl Instance {
+    /// Generate a random instance with four internal and two external routers. The reconfiguration
+    /// changes up to three link weights, and possibly turns one iBGP session into a route
+    /// reflector session (in either direction). Returns `None` if the initial state does not
+    /// satisfy the hard policy.
+    fn random(seed: u64) -> Option<Self> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut net = Network::new();
+        let r: Vec<RouterId> = (0..4).map(|i| net.add_router(format!("R{}", i))).collect();
+        let e1 = net.add_external_router("E1", AsId(65101));
+        let e2 = net.add_external_router("E2", AsId(65102));
+
+        // ring of internal routers, with one random chord
+        let mut links = vec![(r[0], r[1]), (r[1], r[2]), (r[2], r[3]), (r[3], r[0])];
+        if rng.gen_bool(0.5) {
+            links.push((r[0], r[2]));
+        } else {
+            links.push((r[1], r[3]));
+        }
+        let b1 = r[rng.gen_range(0, 4)];
+        let b2 = *r.iter().filter(|x| **x != b1).choose(&mut rng).unwrap();
+
+        // initial link weights and sessions
+        let mut weights: HashMap<(RouterId, RouterId), f64> = HashMap::new();
+        let mut sessions: HashMap<(RouterId, RouterId), BgpSessionType> = HashMap::new();
+        for (a, b) in links.iter() {
+            net.add_link(*a, *b);
+            weights.insert((*a, *b), rng.gen_range(1, 6) as f64);
+            weights.insert((*b, *a), rng.gen_range(1, 6) as f64);
+        }
+        for (b, e) in vec![(b1, e1), (b2, e2)] {
+            net.add_link(b, e);
+            weights.insert((b, e), 1.0);
+            weights.insert((e, b), 1.0);
+            sessions.insert((b, e), EBgp);
+        }
+        for i in 0..4 {
+            for j in (i + 1)..4 {
+                sessions.insert((r[i], r[j]), IBgpPeer);
+            }
+        }
+        net.set_config(&build_config(&weights, &sessions)).ok()?;
+        net.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None)
+            .ok()?;
+        net.advertise_external_route(e2, Prefix(0), vec![AsId(65102), AsId(65200)], None, None)
+            .ok()?;
+
+        // change the link weights and sessions for the final configuration
+        for _ in 0..3 {
+            let (a, b) = links[rng.gen_range(0, links.len())];
+            let link = if rng.gen_bool(0.5) { (a, b) } else { (b, a) };
+            weights.insert(link, rng.gen_range(1, 6) as f64);
+        }
+        if rng.gen_bool(0.5) {
+            let i = rng.gen_range(0, 3);
+            let j = rng.gen_range(i + 1, 4);
+            sessions.remove(&(r[i], r[j]));
+            if rng.gen_bool(0.5) {
+                sessions.insert((r[i], r[j]), IBgpClient);
+            } else {
+                sessions.insert((r[j], r[i]), IBgpClient);
+            }
+        }
+        let final_config = build_config(&weights, &sessions);
+
+        let mut hard_policy =
+            HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state).ok()?;
+        if !hard_policy.check() {
+            return None;
+        }
+        hard_policy.reset();
+
+        Some(Self { seed, net, final_config, hard_policy })
+    }
+
+    fn modifiers(&self) -> Vec<ConfigModifier> {
+        self.net.current_config().get_diff(&self.final_config).modifiers
+    }
+
+    /// Apply the sequence to the network, and check that it contains every modifier exactly once,
+    /// and that the hard policy is satisfied in every step.
+    fn check_sequence(&self, name: &str, sequence: &[ConfigModifier]) {
+        let mut modifiers = self.modifiers();
+        assert_eq!(sequence.len(), modifiers.len(), "{} (seed {})", name, self.seed);
+        for m in sequence.iter() {
+            let pos = modifiers.iter().position(|x| m == x).unwrap();
+            modifiers.remove(pos);
+        }
+
+        let mut net = self.net.clone();
+        let mut hard_policy = self.hard_policy.clone();
+        hard_policy.set_num_mods_if_none(sequence.len());
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state).unwrap();
+        for m in sequence.iter() {
+            net.apply_modifier(m).unwrap();
+            let mut fw_state = net.get_forwarding_state();
+            hard_policy.step(&mut net, &mut fw_state).unwrap();
+            assert!(
+                hard_policy.check(),
+                "{} returned an invalid sequence (seed {})",
+                name,
+                self.seed
+            );
+        }
+    }
+
+    /// Run the strategy, check the returned sequence, and return if the problem is solvable.
+    fn check_strategy<S: ExhaustiveStrategy>(&self, name: &str) -> bool {
+        let result = S::synthesize(
+            self.net.clone(),
+            self.final_config.clone(),
+            self.hard_policy.clone(),
+            Some(TIME_BUDGET),
+            ControlHandle::new(),
+        );
+        match result {
+            Ok(sequence) => {
+                self.check_sequence(name, &sequence);
+                true
+            }
+            Err(Error::NoSafeOrdering) => false,
+            Err(e) => panic!("{} returned an unexpected error (seed {}): {}", name, self.seed, e),
+        }
+    }
+
+    /// Run the optimizer, check the returned sequence, and return the cost (if the problem is
+    /// solvable).
+    fn check_optimizer<O: Optimizer<MinimizeTrafficShift>>(&self, name: &str) -> Option<f64> {
+        let soft_policy =
+            MinimizeTrafficShift::new(&mut self.net.get_forwarding_state(), &self.net);
+        let result = O::synthesize(
+            self.net.clone(),
+            self.final_config.clone(),
+            self.hard_policy.clone(),
+            soft_policy,
+            Some(TIME_BUDGET),
+            ControlHandle::new(),
+        );
+        match result {
+            Ok((sequence, cost)) => {
+                self.check_sequence(name, &sequence);
+                Some(cost)
+            }
+            Err(Error::NoSafeOrdering)
+            | Err(Error::ProbablyNoSafeOrdering)
+            | Err(Error::ReachedMaxBacktrack) => None,
+            Err(Error::NetworkError(NetworkError::NoConvergence)) => None,
+            Err(e) => panic!("{} returned an unexpected error (seed {}): {}", name, self.seed, e),
+        }
+    }
+}
+
+fn build_config(
+    weights: &HashMap<(RouterId, RouterId), f64>,
+    sessions: &HashMap<(RouterId, RouterId), BgpSessionType>,
+) -> Config {
+    let mut c = Config::new();
+    for ((source, target), weight) in weights.iter() {
+        c.add(IgpLinkWeight { source: *source, target: *target, weight: *weight }).unwrap();
+    }
+    for ((source, target), session_type) in sessions.iter() {
+        c.add(BgpSession { source: *source, target: *target, session_type: *session_type })
+            .unwrap();
+    }
+    c
+}
+
+/// Check a single instance. Returns `true` if the problem is solvable.
+fn check_instance(instance: &Instance) -> bool {
+    type SP = MinimizeTrafficShift;
+    let seed = instance.seed;
+
+    let optimum = instance.check_optimizer::<GlobalOptimizer<SP>>("GlobalOptimizer");
+    let solvable = optimum.is_some();
+
+    let strategies = vec![
+        ("TreeStrategy", instance.check_strategy::<TreeStrategy<SimpleOrdering>>("TreeStrategy")),
+        (
+            "PushBackTreeStrategy",
+            instance.check_strategy::<PushBackTreeStrategy<SimpleOrdering>>("PushBackTreeStrategy"),
+        ),
+        (
+            "PermutationStrategy",
+            instance.check_strategy::<PermutationStrategy<LexicographicPermutator<NoOrdering>>>(
+                "PermutationStrategy",
+            ),
+        ),
+    ];
+    for (name, result) in strategies {
+        assert_eq!(result, solvable, "{} disagrees on solvability (seed {})", name, seed);
+    }
+
+    let optimizers = vec![
+        ("TreeOptimizer", instance.check_optimizer::<TreeOptimizer<SP>>("TreeOptimizer")),
+        ("OptimizerTRTA", instance.check_optimizer::<OptimizerTRTA<SP>>("OptimizerTRTA")),
+    ];
+    for (name, cost) in optimizers {
+        if let (Some(cost), Some(optimum)) = (cost, optimum) {
+            assert!(
+                cost >= optimum - 1e-9,
+                "{} found a cost below the global optimum (seed {}): {} < {}",
+                name,
+                seed,
+                cost,
+                optimum
+            );
+        }
+    }
+
+    solvable
+}
+
+#[test]
+fn test_strategy_equivalence() {
+    let mut num_checked = 0;
+    for seed in 0..NUM_INSTANCES {
+        let instance = match Instance::random(seed) {
+            Some(instance) => instance,
+            None => continue,
+        };
+        if instance.modifiers().is_empty() {
+            continue;
+        }
+        check_instance(&instance);
+        num_checked += 1;
+    }
+    assert!(num_checked > 0);
+}
@@ -0,0 +1,186 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+#![cfg(test)]
+use super::two_egress_net;
+use crate::netsim::config::{ConfigExpr::*, ConfigModifier::*};
+use crate::netsim::{BgpSessionType::*, Prefix};
+use crate::soft_policies::{
+    MinimizeMaxLinkUtilization, MinimizeTrafficShift, PrefixWeights, SoftPolicy,
+};
+use crate::traffic_matrix::{TrafficMatrix, TrafficMatrixError};
+
+#[test]
+fn test_csv() {
+    let (net, ids) = two_egress_net();
+    let data = "router,prefix,volume\nR1,0,10.5\nR2,0,4\nR1,0,1.5\n";
+    let tm = TrafficMatrix::from_csv(&net, data.as_bytes()).unwrap();
+    assert_eq!(tm.len(), 2);
+    assert_eq!(tm.get_demand(ids[0], Prefix(0)), 12.0);
+    assert_eq!(tm.get_demand(ids[1], Prefix(0)), 4.0);
+    assert_eq!(tm.get_demand(ids[1], Prefix(1)), 0.0);
+    assert_eq!(tm.total_demand(), 16.0);
+}
+
+#[test]
+fn test_json() {
+    let (net, ids) = two_egress_net();
+    let data = r#"[
+        {"router": "R1", "prefix": 0, "volume": 3},
+        {"router": "R2", "prefix": 0, "volume": 1},
+        {"router": "R2", "prefix": 1, "volume": 8}
+    ]"#;
+    let tm = TrafficMatrix::from_json(&net, data.as_bytes()).unwrap();
+    // prefix 1 is not known in the network
+    assert_eq!(tm.len(), 2);
+    assert_eq!(tm.get_demand(ids[0], Prefix(0)), 3.0);
+    assert_eq!(tm.get_demand(ids[1], Prefix(0)), 1.0);
+}
+
+#[test]
+fn test_invalid_records() {
+    let (net, _) = two_egress_net();
+    let data = "router,prefix,volume\nR3,0,1\n";
+    assert!(matches!(
+        TrafficMatrix::from_csv(&net, data.as_bytes()),
+        Err(TrafficMatrixError::UnknownRouter(r)) if r == "R3"
+    ));
+    let data = "router,prefix,volume\nE1,0,1\n";
+    assert!(matches!(
+        TrafficMatrix::from_csv(&net, data.as_bytes()),
+        Err(TrafficMatrixError::ExternalRouter(r)) if r == "E1"
+    ));
+    let data = "router,prefix,volume\nR1,0,-1\n";
+    assert!(matches!(
+        TrafficMatrix::from_csv(&net, data.as_bytes()),
+        Err(TrafficMatrixError::InvalidVolume(_, _))
+    ));
+    let data = "router,prefix,volume\nR1,zero,1\n";
+    assert!(matches!(
+        TrafficMatrix::from_csv(&net, data.as_bytes()),
+        Err(TrafficMatrixError::CsvError(_))
+    ));
+}
+
+#[test]
+fn test_weighted_traffic_shift() {
+    let (mut net, ids) = two_egress_net();
+    let (r1, e1) = (ids[0], ids[2]);
+    let modifier = Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+
+    // without traffic matrix, every shift counts the same
+    let mut after = net.clone();
+    after.apply_modifier(&modifier).unwrap();
+    let mut sp = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
+    sp.update(&mut after.get_forwarding_state(), &after);
+    assert_eq!(sp.cost(), 0.5);
+
+    // with traffic matrix, R1 carries three quarters of the demand
+    let data = "router,prefix,volume\nR1,0,3\nR2,0,1\n";
+    let tm = TrafficMatrix::from_csv(&net, data.as_bytes()).unwrap();
+    net.set_traffic_matrix(Some(tm));
+    let mut after = net.clone();
+    after.apply_modifier(&modifier).unwrap();
+    let mut sp = MinimizeTrafficShift::new(&mut net.get_forwarding_state(), &net);
+    sp.update(&mut after.get_forwarding_state(), &after);
+    assert_eq!(sp.cost(), 0.75);
+}
+
+#[test]
+fn test_prefix_weights() {
+    let (net, ids) = two_egress_net();
+    let (r1, r2, e1) = (ids[0], ids[1], ids[2]);
+    let mut weights = PrefixWeights::new(2.0);
+    assert_eq!(weights.get(r1, Prefix(0)), 2.0);
+    weights.set_prefix(Prefix(0), 1.0);
+    weights.set(r2, Prefix(0), 9.0);
+    assert_eq!(weights.get(r1, Prefix(0)), 1.0);
+    assert_eq!(weights.get(r2, Prefix(0)), 9.0);
+    assert_eq!(weights.get(r2, Prefix(1)), 2.0);
+
+    let mut after = net.clone();
+    after
+        .apply_modifier(&Remove(BgpSession { source: r1, target: e1, session_type: EBgp }))
+        .unwrap();
+    let mut sp = MinimizeTrafficShift::with_weights(&mut net.get_forwarding_state(), &net, weights);
+    sp.update(&mut after.get_forwarding_state(), &after);
+    assert_eq!(sp.cost(), 0.1);
+
+    // the weights are kept when resetting the soft policy
+    sp.reset(&mut after.get_forwarding_state(), &after);
+    assert_eq!(sp.cost(), 0.0);
+    assert_eq!(sp.prefix_weights().get(r2, Prefix(0)), 9.0);
+}
+
+#[test]
+fn test_link_loads() {
+    let (mut net, ids) = two_egress_net();
+    let (r1, r2, e1, e2) = (ids[0], ids[1], ids[2], ids[3]);
+    let data = "router,prefix,volume\nR1,0,3\nR2,0,1\n";
+    let tm = TrafficMatrix::from_csv(&net, data.as_bytes()).unwrap();
+
+    // both routers use their own egress
+    let loads = tm.link_loads(&mut net.get_forwarding_state());
+    assert_eq!(loads.len(), 2);
+    assert_eq!(loads.get(&(r1, e1)), Some(&3.0));
+    assert_eq!(loads.get(&(r2, e2)), Some(&1.0));
+
+    // without the session to E1, all traffic leaves over E2
+    net.apply_modifier(&Remove(BgpSession { source: r1, target: e1, session_type: EBgp })).unwrap();
+    let loads = tm.link_loads(&mut net.get_forwarding_state());
+    assert_eq!(loads.len(), 2);
+    assert_eq!(loads.get(&(r1, r2)), Some(&3.0));
+    assert_eq!(loads.get(&(r2, e2)), Some(&4.0));
+}
+
+#[test]
+fn test_max_link_utilization() {
+    let (mut net, ids) = two_egress_net();
+    let (r1, r2, e1, e2) = (ids[0], ids[1], ids[2], ids[3]);
+    let data = "router,prefix,volume\nR1,0,3\nR2,0,1\n";
+    net.set_traffic_matrix(Some(TrafficMatrix::from_csv(&net, data.as_bytes()).unwrap()));
+    let mut after = net.clone();
+    after
+        .apply_modifier(&Remove(BgpSession { source: r1, target: e1, session_type: EBgp }))
+        .unwrap();
+
+    // every link has the total demand as capacity
+    let mut sp = MinimizeMaxLinkUtilization::new(&mut net.get_forwarding_state(), &net);
+    assert_eq!(sp.max_utilization(), 0.75);
+    assert_eq!(sp.busiest_link(), Some((r1, e1)));
+    sp.update(&mut after.get_forwarding_state(), &after);
+    assert_eq!(sp.cost(), 1.0);
+    assert_eq!(sp.busiest_link(), Some((r2, e2)));
+    assert!(!sp.is_overloaded());
+
+    // with a smaller capacity, the link from R2 to E2 is overloaded
+    let mut sp = MinimizeMaxLinkUtilization::new(&mut net.get_forwarding_state(), &net)
+        .with_default_capacity(8.0)
+        .with_link_capacity(r2, e2, 2.0);
+    sp.update(&mut net.get_forwarding_state(), &net);
+    assert_eq!(sp.cost(), 0.5);
+    sp.update(&mut after.get_forwarding_state(), &after);
+    assert_eq!(sp.max_utilization(), 2.0);
+    assert!(sp.is_overloaded());
+    assert_eq!(sp.cost(), 1.0);
+
+    // the capacities are kept when resetting the policy
+    sp.reset(&mut net.get_forwarding_state(), &net);
+    assert_eq!(sp.capacity(r1, r2), 8.0);
+    assert_eq!(sp.capacity(e2, r2), 2.0);
+    assert_eq!(sp.cost(), 0.5);
+}
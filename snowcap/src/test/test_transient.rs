@@ -0,0 +1,204 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test cases for the probabilistic transient violation estimation
+
+#![cfg(test)]
+use crate::hard_policies::{Condition, HardPolicy, PolicyError};
+use crate::modifier_ordering::NoOrdering;
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier::*};
+use crate::netsim::{AsId, BgpSessionType::*, Network, NetworkError, Prefix, RouterId};
+use crate::strategies::{PushBackTreeStrategy, Strategy};
+use crate::transient::{estimate_violation_probability, estimate_violation_probability_with};
+use crate::{ControlHandle, Error};
+
+use rand::prelude::*;
+
+/// Network:
+///
+/// ```text
+///              1        5
+/// e1 ---- r1 ----- r2 ----- r3 ---- e3
+/// ```
+///
+/// `r2` is the route reflector of `r1` and `r3`. The prefix is advertised by `e1`, and by `e3` if
+/// `advertise_e3` is set.
+fn line_net(advertise_e3: bool) -> (Network, [RouterId; 4]) {
+    let mut n = Network::new();
+    let mut c = Config::new();
+
+    let r1 = n.add_router("r1");
+    let r2 = n.add_router("r2");
+    let r3 = n.add_router("r3");
+    let e1 = n.add_external_router("e1", AsId(65101));
+    let e3 = n.add_external_router("e3", AsId(65103));
+
+    n.add_link(r1, r2);
+    n.add_link(r2, r3);
+    n.add_link(r1, e1);
+    n.add_link(r3, e3);
+
+    for (a, b, weight) in vec![(r1, r2, 1.0), (r2, r3, 5.0), (r1, e1, 1.0), (r3, e3, 1.0)] {
+        c.add(IgpLinkWeight { source: a, target: b, weight }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight }).unwrap();
+    }
+
+    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
+    c.add(BgpSession { source: r2, target: r1, session_type: IBgpClient }).unwrap();
+    c.add(BgpSession { source: r2, target: r3, session_type: IBgpClient }).unwrap();
+
+    n.set_config(&c).unwrap();
+
+    n.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
+    if advertise_e3 {
+        n.advertise_external_route(e3, Prefix(0), vec![AsId(65103), AsId(65200)], None, None)
+            .unwrap();
+    }
+
+    (n, [r1, r2, r3, e1])
+}
+
+#[test]
+fn violation_probability() {
+    let (net, [r1, _, r3, e1]) = line_net(true);
+    let p = Prefix(0);
+    let modifier = Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let probs = estimate_violation_probability(&net, &modifier, 20, &mut rng).unwrap();
+    assert_eq!(probs.len(), 3);
+    assert!(probs.values().all(|p| (0.0..=1.0).contains(p)));
+    // r1 has no route until it receives the route of e3 from r2.
+    assert_eq!(probs[&(r1, p)], 1.0);
+    // r3 always uses its own route.
+    assert_eq!(probs[&(r3, p)], 0.0);
+
+    // the network itself is not changed
+    assert_eq!(net.get_forwarding_state().get_route(r1, p).unwrap(), vec![r1, e1]);
+}
+
+#[test]
+fn violation_probability_nothing_to_reorder() {
+    let (net, [_, r2, r3, _]) = line_net(false);
+    let modifier = Update {
+        from: IgpLinkWeight { source: r2, target: r3, weight: 5.0 },
+        to: IgpLinkWeight { source: r2, target: r3, weight: 1.0 },
+    };
+    let mut rng = StdRng::seed_from_u64(42);
+    assert!(matches!(
+        estimate_violation_probability(&net, &modifier, 20, &mut rng),
+        Err(Error::NetworkError(NetworkError::NoEventsToReorder))
+    ));
+}
+
+#[test]
+fn violation_probability_no_samples() {
+    let (net, [r1, _, _, e1]) = line_net(true);
+    let modifier = Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let mut rng = StdRng::seed_from_u64(42);
+    assert!(matches!(
+        estimate_violation_probability(&net, &modifier, 0, &mut rng),
+        Err(Error::NoTransientSamples)
+    ));
+}
+
+#[test]
+fn violation_probability_with() {
+    let (net, [r1, _, r3, e1]) = line_net(true);
+    let p = Prefix(0);
+    let modifier = Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let reach_r1 = Condition::Reachable(r1, p, None);
+    let prob = estimate_violation_probability_with(&net, &modifier, 20, &mut rng, |fw_state| {
+        reach_r1.check(fw_state).is_err()
+    })
+    .unwrap();
+    assert_eq!(prob, 1.0);
+
+    let reach_r3 = Condition::Reachable(r3, p, None);
+    let prob = estimate_violation_probability_with(&net, &modifier, 20, &mut rng, |fw_state| {
+        reach_r3.check(fw_state).is_err()
+    })
+    .unwrap();
+    assert_eq!(prob, 0.0);
+}
+
+#[test]
+fn fuzz_orderings() {
+    let (mut net, [r1, r2, r3, e1]) = line_net(true);
+    let e3 = net.get_router_id("e3").unwrap();
+    let p = Prefix(0);
+    let modifier = Remove(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let hard_policy = HardPolicy::globally(vec![Condition::Reachable(r1, p, None)]);
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let violations =
+        net.apply_modifier_fuzz_orderings(&modifier, 5, &mut rng, &hard_policy).unwrap();
+    // r1 has no route until it receives the route of e3 from r2.
+    assert_eq!(violations.len(), 5);
+    for v in violations {
+        assert!(v.ordering.len() > 1);
+        assert!(v.errors.contains(&PolicyError::BlackHole { router: r1, prefix: p }));
+    }
+
+    // the modifier is applied once
+    assert_eq!(net.get_forwarding_state().get_route(r1, p).unwrap(), vec![r1, r2, r3, e3]);
+    assert!(net.undo_action().unwrap());
+    assert_eq!(net.get_forwarding_state().get_route(r1, p).unwrap(), vec![r1, e1]);
+}
+
+#[test]
+fn fuzz_orderings_nothing_to_reorder() {
+    let (mut net, [_, r2, r3, _]) = line_net(false);
+    let modifier = Update {
+        from: IgpLinkWeight { source: r2, target: r3, weight: 5.0 },
+        to: IgpLinkWeight { source: r2, target: r3, weight: 1.0 },
+    };
+    let hard_policy = HardPolicy::reachability(net.get_routers().iter(), [Prefix(0)].iter());
+    let mut expected = net.current_config().clone();
+    expected.apply_modifier(&modifier).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let violations =
+        net.apply_modifier_fuzz_orderings(&modifier, 5, &mut rng, &hard_policy).unwrap();
+    assert!(violations.is_empty());
+    assert_eq!(net.current_config(), &expected);
+}
+
+#[test]
+fn push_back_tree_fuzz_orderings() {
+    let (net, [r1, _, _, e1]) = line_net(true);
+    let modifiers = vec![Remove(BgpSession { source: r1, target: e1, session_type: EBgp })];
+    let hard_policy = HardPolicy::reachability(net.get_routers().iter(), [Prefix(0)].iter());
+
+    let mut strategy = PushBackTreeStrategy::<NoOrdering>::new(
+        net.clone(),
+        modifiers.clone(),
+        hard_policy.clone(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(strategy.work(ControlHandle::new()).unwrap(), modifiers);
+
+    // with fuzzing, the transient black hole at r1 is detected.
+    let mut strategy =
+        PushBackTreeStrategy::<NoOrdering>::new(net, modifiers, hard_policy, None).unwrap();
+    strategy.set_fuzz_orderings(Some(5));
+    assert!(matches!(strategy.work(ControlHandle::new()), Err(Error::NoSafeOrdering)));
+}
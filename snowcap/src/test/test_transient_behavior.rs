@@ -15,27 +15,24 @@
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
-//! Test cases for transient behavior in the Network
-//!
-//! **TODO**: All these tests are deactivated, since this funcitonality is temporarily removed from
-//! the Network.
-
-use crate::hard_policies::Constraints;
-use crate::netsim::{
-    bgp::BgpSessionType::*,
-    config::{Config, ConfigExpr::*, ConfigModifier::*},
-    route_map::{RouteMapBuilder, RouteMapDirection::*},
-    AsId, Network, Prefix,
-};
-
-#[test]
-fn test_simple_case_correct() {
-    // Network:
-    //
-    //              1        5
-    // e1 ---- r1 ----- r2 ----- r3 ---- e3
-    //
-
+//! Test cases for the transient behavior analysis
+
+#![cfg(test)]
+use crate::hard_policies::transient_behavior::TransientStateAnalyzer;
+use crate::hard_policies::{Condition, HardPolicy, PathCondition, PolicyError};
+use crate::netsim::config::{Config, ConfigExpr::*, ConfigModifier::*};
+use crate::netsim::{AsId, BgpSessionType::*, Network, Prefix, RouterId};
+
+/// Network:
+///
+/// ```text
+///              1        5
+/// e1 ---- r1 ----- r2 ----- r3 ---- e3
+/// ```
+///
+/// `r2` is the route reflector of `r1` and `r3`. The prefix is always advertised by `e1`, and only
+/// by `e3` if `advertise_e3` is set.
+fn line_net(advertise_e3: bool) -> (Network, [RouterId; 5]) {
     let mut n = Network::new();
     let mut c = Config::new();
 
@@ -50,14 +47,10 @@ fn test_simple_case_correct() {
     n.add_link(r1, e1);
     n.add_link(r3, e3);
 
-    c.add(IgpLinkWeight { source: r1, target: r2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r2, target: r3, weight: 5.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r1, target: e1, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r3, target: e3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: r2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r2, source: r3, weight: 5.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: e1, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r3, source: e3, weight: 1.0 }).unwrap();
+    for (a, b, weight) in vec![(r1, r2, 1.0), (r2, r3, 5.0), (r1, e1, 1.0), (r3, e3, 1.0)] {
+        c.add(IgpLinkWeight { source: a, target: b, weight }).unwrap();
+        c.add(IgpLinkWeight { source: b, target: a, weight }).unwrap();
+    }
 
     c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
     c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
@@ -66,260 +59,147 @@ fn test_simple_case_correct() {
 
     n.set_config(&c).unwrap();
 
-    // advertise the same prefix everywhere
     n.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
-    n.advertise_external_route(e3, Prefix(0), vec![AsId(65103), AsId(65200)], None, None).unwrap();
+    if advertise_e3 {
+        n.advertise_external_route(e3, Prefix(0), vec![AsId(65103), AsId(65200)], None, None)
+            .unwrap();
+    }
 
-    // check constraints
-    let constraints = Constraints::reachability()
-        .build(&n.get_routers(), &n.get_known_prefixes().iter().cloned().collect());
-    assert!(constraints.check(&mut n.get_forwarding_state()).is_ok());
+    (n, [r1, r2, r3, e1, e3])
+}
 
-    n.apply_modifier_check_constraints(
-        &Remove(BgpSession { source: r3, target: e3, session_type: EBgp }),
-        &constraints,
-    )
+#[test]
+fn transient_path_single_egress() {
+    let (mut n, [r1, r2, r3, _, _]) = line_net(false);
+    let p = Prefix(0);
+
+    let mut hard_policy =
+        HardPolicy::globally(vec![Condition::TransientPath(r3, p, PathCondition::Node(r1))]);
+    hard_policy.set_num_mods_if_none(1);
+    let mut fw_state = n.get_forwarding_state();
+    hard_policy.step(&mut n, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
+
+    // every path must leave the network at e1, no matter how the network converges.
+    n.apply_modifier(&Update {
+        from: IgpLinkWeight { source: r2, target: r3, weight: 5.0 },
+        to: IgpLinkWeight { source: r2, target: r3, weight: 1.0 },
+    })
     .unwrap();
+    let mut fw_state = n.get_forwarding_state();
+    hard_policy.step(&mut n, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
 }
 
 #[test]
-fn simple_case_incorrect() {
-    // Network:
-    //
-    //              1        5
-    // e1 ---- r1 ----- r2 ----- r3 ---- e3
-    //
-
-    let mut n = Network::new();
-    let mut c = Config::new();
-
-    let r1 = n.add_router("r1");
-    let r2 = n.add_router("r2");
-    let r3 = n.add_router("r3");
-    let e1 = n.add_external_router("e1", AsId(65101));
-    let e3 = n.add_external_router("e3", AsId(65103));
-
-    n.add_link(r1, r2);
-    n.add_link(r2, r3);
-    n.add_link(r1, e1);
-    n.add_link(r3, e3);
-
-    c.add(IgpLinkWeight { source: r1, target: r2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r2, target: r3, weight: 5.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r1, target: e1, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r3, target: e3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: r2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r2, source: r3, weight: 5.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: e1, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r3, source: e3, weight: 1.0 }).unwrap();
-
-    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r2, target: r1, session_type: IBgpClient }).unwrap();
-    c.add(BgpSession { source: r2, target: r3, session_type: IBgpClient }).unwrap();
-
-    n.set_config(&c).unwrap();
-
-    // advertise the same prefix everywhere
-    n.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
-    n.advertise_external_route(e3, Prefix(0), vec![AsId(65103), AsId(65200)], None, None).unwrap();
-
-    // check constraints
-    let constraints = Constraints::reachability()
-        .build(&n.get_routers(), &n.get_known_prefixes().iter().cloned().collect());
-    assert!(constraints.check(&mut n.get_forwarding_state()).is_ok());
-
-    n.apply_modifier_check_constraints(
-        &Remove(BgpSession { source: r1, target: e1, session_type: EBgp }),
-        &constraints,
-    )
-    .unwrap_err();
+fn transient_path_violated() {
+    let (mut n, [r1, r2, _, e1, _]) = line_net(true);
+    let p = Prefix(0);
+    let condition = PathCondition::Node(r1);
+
+    let mut hard_policy =
+        HardPolicy::globally(vec![Condition::TransientPath(r2, p, condition.clone())]);
+    hard_policy.set_num_mods_if_none(1);
+    let mut fw_state = n.get_forwarding_state();
+    hard_policy.step(&mut n, &mut fw_state).unwrap();
+    assert!(hard_policy.check());
+
+    // r2 switches to the route from e3, which does not traverse r1.
+    n.apply_modifier(&Remove(BgpSession { source: r1, target: e1, session_type: EBgp })).unwrap();
+    let mut fw_state = n.get_forwarding_state();
+    hard_policy.step(&mut n, &mut fw_state).unwrap();
+    assert!(!hard_policy.check());
+    assert!(hard_policy.last_errors().contains(&PolicyError::TransientBehavior {
+        router: r2,
+        prefix: p,
+        condition,
+    }));
+
+    // after undoing the step, the condition is satisfied again
+    hard_policy.undo();
+    assert!(hard_policy.check());
 }
 
 #[test]
-fn multiple_options_incorrect() {
-    // Network:
-    //
-    // e1 ---- r1 ------ r3 ---- e3
-    //      .-'  \      /
-    // e2 -'      \    /
-    //              r2
-
-    let mut n = Network::new();
-    let mut c = Config::new();
-
-    let r1 = n.add_router("r1");
-    let r2 = n.add_router("r2");
-    let r3 = n.add_router("r3");
-    let e1 = n.add_external_router("e1", AsId(65101));
-    let e2 = n.add_external_router("e2", AsId(65102));
-    let e3 = n.add_external_router("e3", AsId(65103));
-
-    n.add_link(r1, r2);
-    n.add_link(r1, r3);
-    n.add_link(r2, r3);
-    n.add_link(r1, e1);
-    n.add_link(r1, e2);
-    n.add_link(r3, e3);
-
-    c.add(IgpLinkWeight { source: r1, target: r2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r1, target: r3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r2, target: r3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r1, target: e1, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r1, target: e2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r3, target: e3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: r2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: r3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r2, source: r3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: e1, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: e2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r3, source: e3, weight: 1.0 }).unwrap();
-
-    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r1, target: e2, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r1, target: r2, session_type: IBgpPeer }).unwrap();
-    c.add(BgpSession { source: r1, target: r3, session_type: IBgpPeer }).unwrap();
-    c.add(BgpSession { source: r2, target: r3, session_type: IBgpPeer }).unwrap();
-
-    c.add(BgpRouteMap {
-        router: r1,
-        direction: Incoming,
-        map: RouteMapBuilder::new()
-            .order(100)
-            .allow()
-            .match_neighbor(e1)
-            .set_local_pref(200)
-            .build(),
-    })
-    .unwrap();
-    c.add(BgpRouteMap {
-        router: r1,
-        direction: Incoming,
-        map: RouteMapBuilder::new()
-            .order(101)
-            .allow()
-            .match_neighbor(e2)
-            .set_local_pref(50)
-            .build(),
-    })
-    .unwrap();
-
-    n.set_config(&c).unwrap();
-
-    // advertise the same prefix everywhere
-    n.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
-    n.advertise_external_route(e2, Prefix(0), vec![AsId(65102), AsId(65200)], None, None).unwrap();
-    n.advertise_external_route(e3, Prefix(0), vec![AsId(65103), AsId(65200)], None, None).unwrap();
-
-    // check constraints
-    let constraints = Constraints::reachability()
-        .build(&n.get_routers(), &n.get_known_prefixes().iter().cloned().collect());
-    assert!(constraints.check(&mut n.get_forwarding_state()).is_ok());
-
-    n.apply_modifier_check_constraints(
-        &Remove(BgpSession { source: r1, target: e1, session_type: EBgp }),
-        &constraints,
-    )
-    .unwrap_err();
+fn analyzer_supergraph() {
+    let (mut n, [r1, r2, r3, e1, e3]) = line_net(true);
+    let p = Prefix(0);
+    let conditions = vec![
+        Condition::Reachable(r2, p, None),
+        Condition::TransientPath(r2, p, PathCondition::Node(r2)),
+        Condition::TransientPath(r2, p, PathCondition::Node(r1)),
+    ];
+
+    let mut tsa = TransientStateAnalyzer::new(n.get_known_prefixes(), &conditions);
+    assert!(tsa.forwarding_supergraph(p).is_none());
+    tsa.step(&n);
+    assert!(tsa.check().is_empty());
+    assert!(tsa.forwarding_supergraph(Prefix(1)).is_none());
+    let fwsg = tsa.forwarding_supergraph(p).unwrap();
+    assert!(fwsg.next_hops(r1).contains(&e1));
+    assert!(fwsg.next_hops(r3).contains(&e3));
+    assert!(fwsg.next_hops(e1).is_empty());
+
+    n.apply_modifier(&Remove(BgpSession { source: r1, target: e1, session_type: EBgp })).unwrap();
+    tsa.step(&n);
+    let mut result = tsa.check();
+    result.sort();
+    assert_eq!(result, vec![(1, true), (2, false)]);
+    let fwsg = tsa.forwarding_supergraph(p).unwrap();
+    assert_eq!(fwsg.next_hops(r1), &[r2]);
+    assert!(fwsg.next_hops(r2).contains(&r3));
+
+    tsa.undo();
+    assert!(tsa.forwarding_supergraph(p).unwrap().next_hops(r1).contains(&e1));
+    tsa.reset();
+    assert!(tsa.forwarding_supergraph(p).is_none());
 }
 
 #[test]
-fn multiple_options_correct() {
-    // Network:
+fn analyzer_supergraph_ecmp() {
+    // Network: a reaches d via b and c with equal cost.
     //
-    // e1 ---- r1 ------ r3 ---- e3
-    //      .-'  \      /
-    // e2 -'      \    /
-    //              r2 ----- e4
-
+    //     .-- b --.
+    // a --+       +-- d ---- e
+    //     '-- c --'
     let mut n = Network::new();
-    let mut c = Config::new();
-
-    let r1 = n.add_router("r1");
-    let r2 = n.add_router("r2");
-    let r3 = n.add_router("r3");
-    let e1 = n.add_external_router("e1", AsId(65101));
-    let e2 = n.add_external_router("e2", AsId(65102));
-    let e3 = n.add_external_router("e3", AsId(65103));
-    let e4 = n.add_external_router("e4", AsId(65104));
-
-    n.add_link(r1, r2);
-    n.add_link(r1, r3);
-    n.add_link(r2, r3);
-    n.add_link(r1, e1);
-    n.add_link(r1, e2);
-    n.add_link(r3, e3);
-    n.add_link(r2, e4);
-
-    c.add(IgpLinkWeight { source: r1, target: r2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r1, target: r3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r2, target: r3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r1, target: e1, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r1, target: e2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r3, target: e3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { source: r2, target: e4, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: r2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: r3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r2, source: r3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: e1, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r1, source: e2, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r3, source: e3, weight: 1.0 }).unwrap();
-    c.add(IgpLinkWeight { target: r2, source: e4, weight: 1.0 }).unwrap();
-
-    c.add(BgpSession { source: r1, target: e1, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r1, target: e2, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r3, target: e3, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r2, target: e4, session_type: EBgp }).unwrap();
-    c.add(BgpSession { source: r1, target: r2, session_type: IBgpPeer }).unwrap();
-    c.add(BgpSession { source: r1, target: r3, session_type: IBgpPeer }).unwrap();
-    c.add(BgpSession { source: r2, target: r3, session_type: IBgpPeer }).unwrap();
-
-    c.add(BgpRouteMap {
-        router: r1,
-        direction: Incoming,
-        map: RouteMapBuilder::new()
-            .order(100)
-            .allow()
-            .match_neighbor(e1)
-            .set_local_pref(200)
-            .build(),
-    })
-    .unwrap();
-    c.add(BgpRouteMap {
-        router: r1,
-        direction: Incoming,
-        map: RouteMapBuilder::new()
-            .order(101)
-            .allow()
-            .match_neighbor(e2)
-            .set_local_pref(50)
-            .build(),
+    let a = n.add_router("a");
+    let b = n.add_router("b");
+    let c = n.add_router("c");
+    let d = n.add_router("d");
+    let e = n.add_external_router("e", AsId(65101));
+
+    let mut config = Config::new();
+    for (x, y) in vec![(a, b), (a, c), (b, d), (c, d), (d, e)] {
+        n.add_link(x, y);
+        config.add(IgpLinkWeight { source: x, target: y, weight: 1.0 }).unwrap();
+        config.add(IgpLinkWeight { source: y, target: x, weight: 1.0 }).unwrap();
+    }
+    config.add(BgpSession { source: d, target: a, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: b, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: c, session_type: IBgpClient }).unwrap();
+    config.add(BgpSession { source: d, target: e, session_type: EBgp }).unwrap();
+    n.set_config(&config).unwrap();
+    n.advertise_external_route(e, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
+
+    let conditions = vec![Condition::TransientPath(a, Prefix(0), PathCondition::Node(b))];
+
+    // without ECMP, a uses a single next hop
+    let mut tsa = TransientStateAnalyzer::new(n.get_known_prefixes(), &conditions);
+    tsa.step(&n);
+    assert_eq!(tsa.forwarding_supergraph(Prefix(0)).unwrap().next_hops(a).len(), 1);
+
+    // with ECMP, a may use both next hops, and hence, traffic may bypass b during convergence.
+    n.set_ecmp(true);
+    let mut tsa = TransientStateAnalyzer::new(n.get_known_prefixes(), &conditions);
+    tsa.step(&n);
+    assert_eq!(tsa.forwarding_supergraph(Prefix(0)).unwrap().next_hops(a), &[b, c]);
+    n.apply_modifier(&Update {
+        from: IgpLinkWeight { source: a, target: c, weight: 1.0 },
+        to: IgpLinkWeight { source: a, target: c, weight: 2.0 },
     })
     .unwrap();
-    c.add(BgpRouteMap {
-        router: r1,
-        direction: Incoming,
-        map: RouteMapBuilder::new().order(10).allow().match_neighbor(e4).set_local_pref(50).build(),
-    })
-    .unwrap();
-
-    n.set_config(&c).unwrap();
-
-    // advertise the same prefix everywhere
-    n.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None).unwrap();
-    n.advertise_external_route(e2, Prefix(0), vec![AsId(65102), AsId(65200)], None, None).unwrap();
-    n.advertise_external_route(e3, Prefix(0), vec![AsId(65103), AsId(65200)], None, None).unwrap();
-    n.advertise_external_route(e4, Prefix(0), vec![AsId(65104), AsId(65200)], None, None).unwrap();
-
-    // check constraints
-    let constraints = Constraints::reachability()
-        .build(&n.get_routers(), &n.get_known_prefixes().iter().cloned().collect());
-    assert!(constraints.check(&mut n.get_forwarding_state()).is_ok());
-
-    n.apply_modifier_check_constraints(
-        &Remove(BgpSession { source: r1, target: e1, session_type: EBgp }),
-        &constraints,
-    )
-    .unwrap();
+    tsa.step(&n);
+    assert_eq!(tsa.forwarding_supergraph(Prefix(0)).unwrap().next_hops(a), &[b]);
+    assert_eq!(tsa.check(), vec![(0, false)]);
 }
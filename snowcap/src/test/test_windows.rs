@@ -0,0 +1,134 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![cfg(test)]
+use super::two_egress_net;
+use crate::hard_policies::*;
+use crate::netsim::config::{ConfigExpr::*, ConfigModifier, ConfigModifier::*};
+use crate::netsim::{BgpSessionType::*, Network, Prefix, RouterId};
+use crate::Error;
+
+const T: bool = true;
+const F: bool = false;
+
+/// The network of [`two_egress_net`], but initially, only the session between R2 and E2 is
+/// configured.
+fn get_test_net() -> (Network, [RouterId; 4]) {
+    let (mut net, [r1, r2, e1, e2]) = two_egress_net();
+    net.apply_modifier(&Remove(BgpSession { source: r1, target: e1, session_type: EBgp })).unwrap();
+    (net, [r1, r2, e1, e2])
+}
+
+/// Apply the sequence and check the hard policy after every step.
+fn check_sequence(net: &Network, hard_policy: &HardPolicy, sequence: &[&ConfigModifier]) -> bool {
+    let mut net = net.clone();
+    let mut hard_policy = hard_policy.clone();
+    hard_policy.set_num_mods_if_none(sequence.len());
+    let mut fw_state = net.get_forwarding_state();
+    hard_policy.step(&mut net, &mut fw_state).unwrap();
+    if !hard_policy.check() {
+        return false;
+    }
+    for modifier in sequence {
+        net.apply_modifier(modifier).unwrap();
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state).unwrap();
+        if !hard_policy.check() {
+            return false;
+        }
+    }
+    true
+}
+
+#[test]
+fn test_steps_window() {
+    let c = Condition::Reachable(0.into(), Prefix(0), None);
+    let hard_policy = WindowedPolicyBuilder::new()
+        .condition(c, ActivationWindow::Steps { from: 1, to: 2 })
+        .build()
+        .unwrap();
+    assert!(hard_policy.expr.check(&[vec![F], vec![T], vec![T], vec![F]]));
+    assert!(!hard_policy.expr.check(&[vec![F], vec![T], vec![F], vec![F]]));
+    assert!(!hard_policy.expr.check(&[vec![F], vec![F], vec![T], vec![F]]));
+}
+
+#[test]
+fn test_anchor_window_expr() {
+    let c = Condition::Reachable(0.into(), Prefix(0), None);
+    let (_, ids) = get_test_net();
+    let m_a = Insert(BgpSession { source: ids[0], target: ids[2], session_type: EBgp });
+    let m_b = Remove(BgpSession { source: ids[1], target: ids[3], session_type: EBgp });
+    let hard_policy = WindowedPolicyBuilder::new()
+        .anchor("a", m_a)
+        .anchor("b", m_b)
+        .condition(
+            c,
+            ActivationWindow::Anchors {
+                after: Some("a".to_string()),
+                before: Some("b".to_string()),
+            },
+        )
+        .build()
+        .unwrap();
+    assert_eq!(hard_policy.anchor_var("a"), Some(1));
+    assert_eq!(hard_policy.anchor_var("b"), Some(2));
+    assert_eq!(hard_policy.anchor_var("c"), None);
+    assert_eq!(hard_policy.anchors().len(), 2);
+    assert!(hard_policy.expr.check(&[vec![F, F, F], vec![T, T, F], vec![F, T, T]]));
+    assert!(!hard_policy.expr.check(&[vec![F, F, F], vec![F, T, F], vec![F, T, T]]));
+}
+
+#[test]
+fn test_unknown_anchor() {
+    let c = Condition::Reachable(0.into(), Prefix(0), None);
+    let result = WindowedPolicyBuilder::new()
+        .condition(c, ActivationWindow::Anchors { after: Some("a".to_string()), before: None })
+        .build();
+    assert!(matches!(result, Err(Error::UnknownAnchor(name)) if name == "a"));
+}
+
+#[test]
+fn test_anchor_window_network() {
+    let (net, ids) = get_test_net();
+    let (r1, r2, e1, e2) = (ids[0], ids[1], ids[2], ids[3]);
+    let add = Insert(BgpSession { source: r1, target: e1, session_type: EBgp });
+    let remove = Remove(BgpSession { source: r2, target: e2, session_type: EBgp });
+
+    // R1 must always reach prefix 0
+    let always = WindowedPolicyBuilder::new()
+        .condition(Condition::Reachable(r1, Prefix(0), None), ActivationWindow::Always)
+        .build()
+        .unwrap();
+    assert!(check_sequence(&net, &always, &[&add, &remove]));
+    assert!(!check_sequence(&net, &always, &[&remove, &add]));
+
+    // R1 must reach prefix 0 before the old session is removed, and after the new one is added.
+    let windowed = WindowedPolicyBuilder::new()
+        .anchor("add", add.clone())
+        .anchor("remove", remove.clone())
+        .condition(
+            Condition::Reachable(r1, Prefix(0), None),
+            ActivationWindow::Anchors { after: None, before: Some("remove".to_string()) },
+        )
+        .condition(
+            Condition::Reachable(r1, Prefix(0), None),
+            ActivationWindow::Anchors { after: Some("add".to_string()), before: None },
+        )
+        .build()
+        .unwrap();
+    assert!(check_sequence(&net, &windowed, &[&add, &remove]));
+    assert!(check_sequence(&net, &windowed, &[&remove, &add]));
+}
@@ -20,6 +20,8 @@
 use thiserror::Error;
 
 use super::gml_parser::GmlError;
+use super::graphml_parser::GraphmlError;
+use super::GmlReport;
 
 /// Error for ZooTopology
 #[derive(Debug, Error)]
@@ -27,6 +29,12 @@ pub enum ZooTopologyError {
     /// Gml Parse Error
     #[error("Cannot parse GML file: {0}")]
     GmlParseError(#[from] GmlError),
+    /// GraphML Parse Error
+    #[error("Cannot parse GraphML file: {0}")]
+    GraphmlParseError(#[from] GraphmlError),
+    /// The GML file contains unsupported constructs
+    #[error("Unsupported GML constructs:\n{0}")]
+    UnsupportedGml(GmlReport),
     /// Too few internal routers present in the network to generate the topology
     #[error("Too few internal routers")]
     TooFewInternalRouters,
@@ -39,6 +47,9 @@ pub enum ZooTopologyError {
     /// Too few non-border routers
     #[error("Too few non-boreder routers")]
     TooFewNonBorderRouters,
+    /// The ASes cannot peer as required, since they are not adjacent
+    #[error("Cannot establish the required peerings between the ASes")]
+    NoAsPeering,
     /// Specified name could not be found
     #[error("Name not found: {0}")]
     NameNotFound(String),
@@ -51,4 +62,10 @@ pub enum ZooTopologyError {
     /// Multiple Link weights configured
     #[error("Cannot generate the Configuraiton, as mutliple link weights are configured on the same link")]
     MultipleLinkWeights,
+    /// All internal routers are part of the OSPF backbone
+    #[error("Too few OSPF areas, all internal routers are part of the backbone")]
+    TooFewOspfAreas,
+    /// The gadget cannot be embedded into the topology
+    #[error("Cannot embed the {0} gadget into the topology")]
+    NoGadgetEmbedding(String),
 }
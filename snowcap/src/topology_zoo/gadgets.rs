@@ -0,0 +1,370 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Gadgets embedded into TopologyZoo graphs
+//!
+//! This module embeds the dependency structures of the
+//! [`ChainGadget`](crate::example_networks::ChainGadget) and the
+//! [`CarouselGadget`](crate::example_networks::CarouselGadget) into arbitrary TopologyZoo graphs.
+//! The routers playing the roles of the gadget are chosen automatically. This allows evaluating
+//! strategies on real topologies, while controlling the type of the dependencies.
+
+use super::{NodeIdx, ScenarioParams, ZooTopology, ZooTopologyError};
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::{Config, ConfigExpr::*};
+use crate::netsim::route_map::*;
+use crate::netsim::{AsId, BgpSessionType::*, LinkWeight, Network, Prefix, RouterId};
+use crate::Error;
+
+use log::*;
+use petgraph::algo::astar;
+use petgraph::prelude::*;
+use rand::prelude::*;
+
+/// Number of random role assignments tried when embedding the carousel gadget.
+const CAROUSEL_ATTEMPTS: usize = 32;
+
+impl ZooTopology {
+    /// Embed the dependency structure of the [`ChainGadget`](crate::example_networks::ChainGadget)
+    /// into the topology. The two border routers `b0` and `b1` are chosen such that the shortest
+    /// path between them traverses as many internal routers as possible. These routers form the
+    /// chain. `b0` receives the prefix with local-pref 50, and `b1` with local-pref 100.
+    ///
+    /// Initially, every internal router (except `b1`) has an iBGP session to `b0`, and in the final
+    /// configuration, every internal router (except `b0`) has an additional session to `b1`. Since
+    /// the routers on the chain forward towards `b0` and `b1` in opposite directions, they must be
+    /// reconfigured starting at `b1`, and moving towards `b0`. Routers not on the chain only depend
+    /// on the routers on their path towards `b1`.
+    ///
+    /// This function is called when calling [`ZooTopology::apply_scenario_with_params`] with the
+    /// scenario [`Scenario::EmbeddedChain`](super::Scenario::EmbeddedChain).
+    pub fn apply_chain_scenario(
+        &mut self,
+        mut net: Network,
+        params: &ScenarioParams,
+    ) -> Result<(Network, Config, HardPolicy), Error> {
+        self.scenario_link_weights(params);
+
+        // choose the pair of border routers with the longest chain in between
+        let mut border_routers = self.border_routers();
+        border_routers.shuffle(&mut self.rng);
+        let mut chain: Vec<NodeIdx> = Vec::new();
+        for (i, a) in border_routers.iter().enumerate() {
+            for b in border_routers.iter().skip(i + 1) {
+                match self.internal_shortest_path(*a, *b) {
+                    Some(path) if path.len() > chain.len() => chain = path,
+                    _ => {}
+                }
+            }
+        }
+        if chain.len() < 3 {
+            return Err(ZooTopologyError::NoGadgetEmbedding(String::from("chain")).into());
+        }
+        let (b0, b1) = (chain[0], chain[chain.len() - 1]);
+        let (e0, e1) = (self.external_neighbor(b0), self.external_neighbor(b1));
+        debug!(
+            "Embed chain gadget with {} routers between {} and {}",
+            chain.len() - 2,
+            self.graph.node_weight(b0).unwrap().name,
+            self.graph.node_weight(b1).unwrap().name,
+        );
+
+        // only generate the link weights
+        self.ibgp_roots.drain();
+        self.ibgp_graph.clear_edges();
+        let mut c1 = self.prepare_config(false)?;
+        c1.add(BgpSession { source: self.rid(e0), target: self.rid(b0), session_type: EBgp })?;
+        c1.add(BgpSession { source: self.rid(e1), target: self.rid(b1), session_type: EBgp })?;
+        c1.add(BgpRouteMap {
+            router: self.rid(b0),
+            direction: RouteMapDirection::Incoming,
+            map: RouteMapBuilder::new()
+                .order(10)
+                .allow()
+                .match_neighbor(self.rid(e0))
+                .set_local_pref(50)
+                .build(),
+        })?;
+
+        let others = self.internal_routers().into_iter().filter(|x| *x != b0 && *x != b1);
+        let mut c2 = c1.clone();
+        for r in others {
+            let session = |b| BgpSession { source: self.rid(r), target: b, session_type: IBgpPeer };
+            c1.add(session(self.rid(b0)))?;
+            c2.add(session(self.rid(b0)))?;
+            c2.add(session(self.rid(b1)))?;
+        }
+
+        net.set_config(&c1)?;
+        net.annotate_roles_from_config();
+        for e in [e0, e1].iter() {
+            let as_id = self.graph.node_weight(*e).unwrap().as_id;
+            net.advertise_external_route(
+                self.rid(*e),
+                Prefix(0),
+                vec![as_id, AsId(65200)],
+                None,
+                None,
+            )?;
+        }
+
+        let hard_policy =
+            HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+        Ok((net, c2, hard_policy))
+    }
+
+    /// Embed the dependency structure of the
+    /// [`CarouselGadget`](crate::example_networks::CarouselGadget) into the topology. The top-level
+    /// route reflector `rr` is the border router with the most internal neighbors, the four border
+    /// routers `b1` to `b4` are chosen randomly among the other border routers, and the
+    /// bottom-level route reflectors `r1` to `r4` are chosen close to the border routers. All
+    /// remaining internal routers are clients of `rr`. The iBGP sessions, the prefixes and the local-pref settings are
+    /// the same as in the gadget, i.e., the reconfiguration removes the local-pref 50 on `b2` and
+    /// `b3`.
+    ///
+    /// In contrast to the chain, the link weights of the topology are random, and the carousel
+    /// requires a specific relation between them. Therefore, the roles are assigned repeatedly,
+    /// until both the initial and the final configuration satisfy reachability. If no such
+    /// assignment is found, [`ZooTopologyError::NoGadgetEmbedding`] is returned.
+    ///
+    /// This function is called when calling [`ZooTopology::apply_scenario_with_params`] with the
+    /// scenario [`Scenario::EmbeddedCarousel`](super::Scenario::EmbeddedCarousel).
+    pub fn apply_carousel_scenario(
+        &mut self,
+        net: Network,
+        params: &ScenarioParams,
+    ) -> Result<(Network, Config, HardPolicy), Error> {
+        self.scenario_link_weights(params);
+        self.ibgp_roots.drain();
+        self.ibgp_graph.clear_edges();
+        let base = self.prepare_config(false)?;
+
+        let mut border_routers = self.border_routers();
+        if border_routers.len() < 5 {
+            return Err(ZooTopologyError::TooFewBorderRouters.into());
+        }
+        border_routers.sort_by_key(|x| self.internal_degree(*x));
+        let rr = border_routers.pop().unwrap();
+
+        for _ in 0..CAROUSEL_ATTEMPTS {
+            border_routers.shuffle(&mut self.rng);
+            let b = [border_routers[0], border_routers[1], border_routers[2], border_routers[3]];
+            let r = match self.carousel_reflectors(rr, &b) {
+                Some(r) => r,
+                None => continue,
+            };
+            let e = [
+                self.external_neighbor(b[0]),
+                self.external_neighbor(b[1]),
+                self.external_neighbor(b[2]),
+                self.external_neighbor(b[3]),
+            ];
+            let er = self.external_neighbor(rr);
+            if e.contains(&er) || (1..4).any(|i| e[..i].contains(&e[i])) {
+                continue;
+            }
+
+            let (c1, c2) = self.carousel_configs(&base, rr, er, r, b, e)?;
+            let mut net = net.clone();
+            if net.set_config(&c1).is_err() {
+                continue;
+            }
+            net.annotate_roles_from_config();
+            let advertisements = [
+                (er, vec![Prefix(1), Prefix(2)]),
+                (e[0], vec![Prefix(1)]),
+                (e[1], vec![Prefix(1), Prefix(2)]),
+                (e[2], vec![Prefix(1), Prefix(2)]),
+                (e[3], vec![Prefix(2)]),
+            ];
+            for (ext, prefixes) in advertisements.iter() {
+                let as_id = self.graph.node_weight(*ext).unwrap().as_id;
+                for p in prefixes {
+                    let origin = AsId(65200 + p.0);
+                    net.advertise_external_route(
+                        self.rid(*ext),
+                        *p,
+                        vec![as_id, origin],
+                        None,
+                        None,
+                    )?;
+                }
+            }
+
+            if !Self::is_reachable(&net) {
+                continue;
+            }
+            let mut final_net = net.clone();
+            if final_net.set_config(&c2).is_err() || !Self::is_reachable(&final_net) {
+                continue;
+            }
+
+            debug!(
+                "Embed carousel gadget with route reflector {}",
+                self.graph.node_weight(rr).unwrap().name
+            );
+            let hard_policy =
+                HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+            return Ok((net, c2, hard_policy));
+        }
+
+        Err(ZooTopologyError::NoGadgetEmbedding(String::from("carousel")).into())
+    }
+
+    /// Generate the initial and the final configuration of the carousel gadget, based on the
+    /// configuration containing only the link weights.
+    fn carousel_configs(
+        &self,
+        base: &Config,
+        rr: NodeIdx,
+        er: NodeIdx,
+        r: [NodeIdx; 4],
+        b: [NodeIdx; 4],
+        e: [NodeIdx; 4],
+    ) -> Result<(Config, Config), Error> {
+        let mut c2 = base.clone();
+        // bottom-level route reflectors and their clients, as in the gadget
+        let clients: [&[usize]; 4] = [&[0, 2], &[0, 1, 2], &[1, 2, 3], &[1, 3]];
+        for (i, clients) in clients.iter().enumerate() {
+            c2.add(BgpSession {
+                source: self.rid(rr),
+                target: self.rid(r[i]),
+                session_type: IBgpClient,
+            })?;
+            for j in clients.iter() {
+                c2.add(BgpSession {
+                    source: self.rid(r[i]),
+                    target: self.rid(b[*j]),
+                    session_type: IBgpClient,
+                })?;
+            }
+        }
+        // all other internal routers are clients of rr
+        for x in self.internal_routers() {
+            if x != rr && !r.contains(&x) && !b.contains(&x) {
+                c2.add(BgpSession {
+                    source: self.rid(rr),
+                    target: self.rid(x),
+                    session_type: IBgpClient,
+                })?;
+            }
+        }
+        for (bi, ei) in b.iter().zip(e.iter()).chain(std::iter::once((&rr, &er))) {
+            c2.add(BgpSession {
+                source: self.rid(*bi),
+                target: self.rid(*ei),
+                session_type: EBgp,
+            })?;
+        }
+
+        // the initial configuration sets the local-pref on b2 and b3
+        let mut c1 = c2.clone();
+        for i in [1, 2].iter() {
+            c1.add(BgpRouteMap {
+                router: self.rid(b[*i]),
+                direction: RouteMapDirection::Incoming,
+                map: RouteMapBuilder::new()
+                    .order(10)
+                    .allow()
+                    .match_neighbor(self.rid(e[*i]))
+                    .set_local_pref(50)
+                    .build(),
+            })?;
+        }
+        Ok((c1, c2))
+    }
+
+    /// Choose the four bottom-level route reflectors of the carousel gadget, one for each border
+    /// router. Each one is the first internal router on the path from the border router towards
+    /// `rr`, or the closest unused internal router if this one is already taken.
+    fn carousel_reflectors(&self, rr: NodeIdx, b: &[NodeIdx; 4]) -> Option<[NodeIdx; 4]> {
+        let mut used: Vec<NodeIdx> = b.iter().cloned().chain(std::iter::once(rr)).collect();
+        let mut r = [rr; 4];
+        for i in 0..4 {
+            let mut candidates = self
+                .internal_routers()
+                .into_iter()
+                .filter(|x| !used.contains(x))
+                .filter_map(|x| self.internal_shortest_path(b[i], x).map(|p| (p.len(), x)))
+                .collect::<Vec<_>>();
+            candidates.sort();
+            r[i] = candidates.first()?.1;
+            used.push(r[i]);
+        }
+        Some(r)
+    }
+
+    /// Returns `true` if every router can reach every known prefix.
+    fn is_reachable(net: &Network) -> bool {
+        let mut fw_state = net.get_forwarding_state();
+        let routers = net.get_routers();
+        routers
+            .iter()
+            .all(|r| net.get_known_prefixes().iter().all(|p| fw_state.get_route(*r, *p).is_ok()))
+    }
+
+    /// Returns the shortest path between two internal routers, only traversing internal routers.
+    fn internal_shortest_path(&self, source: NodeIdx, target: NodeIdx) -> Option<Vec<NodeIdx>> {
+        let external = |x: NodeIdx| self.graph.node_weight(x).unwrap().external;
+        astar(
+            &self.graph,
+            source,
+            |x| x == target,
+            |e| {
+                if external(e.source()) || external(e.target()) {
+                    LinkWeight::INFINITY
+                } else {
+                    *e.weight()
+                }
+            },
+            |_| 0.0,
+        )
+        .filter(|(cost, _)| cost.is_finite())
+        .map(|(_, path)| path)
+    }
+
+    /// Returns all internal routers
+    pub(super) fn internal_routers(&self) -> Vec<NodeIdx> {
+        self.graph
+            .node_indices()
+            .filter(|x| !self.graph.node_weight(*x).unwrap().external)
+            .collect()
+    }
+
+    /// Returns all internal routers that have at least one external neighbor.
+    pub(super) fn border_routers(&self) -> Vec<NodeIdx> {
+        self.internal_routers()
+            .into_iter()
+            .filter(|x| {
+                self.graph.neighbors(*x).any(|n| self.graph.node_weight(n).unwrap().external)
+            })
+            .collect()
+    }
+
+    /// Returns the first external neighbor of a border router.
+    fn external_neighbor(&self, border_router: NodeIdx) -> NodeIdx {
+        self.graph
+            .neighbors(border_router)
+            .find(|n| self.graph.node_weight(*n).unwrap().external)
+            .unwrap()
+    }
+
+    /// Returns the router id of the node in the network.
+    pub(super) fn rid(&self, node: NodeIdx) -> RouterId {
+        self.graph.node_weight(node).unwrap().net_idx.unwrap()
+    }
+}
@@ -17,7 +17,8 @@
 
 //! Parses GML files from Topology Zoo
 
-use super::NodeData;
+use super::report::LinkAttributes;
+use super::{DuplicateLabels, GmlIssue, GmlOptions, GmlReport, NodeData, ParallelEdges};
 use crate::netsim::{AsId, LinkWeight};
 
 use petgraph::prelude::*;
@@ -28,21 +29,21 @@ use thiserror::Error;
 /// Parses GML files and returns the resulting graph
 /// The names will remain the same, except the same name occurs twice. In this case, we will append
 /// a _N to the end, where N is a number starting from 1 (_1 is appended to the second occurence,
-/// and _2 is appended to the third occurence, etc...).
+/// and _2 is appended to the third occurence, etc...), unless the `options` tell to merge nodes
+/// with the same label. Nodes without a label are named `node_ID`. Edges have weight 1, unless
+/// they specify a `weight`, or the `options` map their link speed or latency to the weight.
+/// Self-loops are dropped, and parallel edges are handled as specified by the `options`. Every
+/// repaired or dropped construct is added to the `report`.
 pub fn parse_gml_graph(
     filename: impl AsRef<str>,
+    options: &GmlOptions,
+    report: &mut GmlReport,
 ) -> Result<Graph<NodeData, LinkWeight, Undirected, u32>, GmlError> {
-    let mut g: Graph<NodeData, LinkWeight, Undirected, u32> =
-        Graph::<NodeData, LinkWeight, Undirected, u32>::new_undirected();
-
     let gml_str = read_to_string(filename.as_ref())?;
 
     let mut current_state = CurrentState::NotStarted;
 
-    let mut current_as_id: u32 = 65100;
-    let mut as_id_lookup: HashMap<String, AsId> = HashMap::new();
-
-    let mut used_labels: HashMap<String, usize> = HashMap::new();
+    let mut builder = GraphBuilder::new(options, report);
     let mut node_lookup: HashMap<usize, NodeIndex<u32>> = HashMap::new();
 
     for (i, line) in gml_str.lines().enumerate() {
@@ -59,7 +60,7 @@ pub fn parse_gml_graph(
                 if line == "node [" {
                     CurrentState::Node { id: None, name: None, external: None }
                 } else if line == "edge [" {
-                    CurrentState::Edge { source: None, target: None }
+                    CurrentState::Edge { source: None, target: None, attrs: Default::default() }
                 } else {
                     CurrentState::None
                 }
@@ -70,14 +71,7 @@ pub fn parse_gml_graph(
                     CurrentState::Node { id, name, external }
                 } else if line.starts_with("label ") {
                     let len_line: usize = line.len();
-                    let mut name: String = String::from(&line[7..len_line - 1]).replace(" ", "_");
-                    // increment the num_used in the hashmap
-                    let num_used = *used_labels.get(&name).unwrap_or(&0);
-                    used_labels.insert(name.clone(), num_used + 1);
-                    if num_used > 0 {
-                        name.push_str(&format!("_{}", num_used));
-                    }
-                    let name = Some(name);
+                    let name = Some(String::from(&line[7..len_line - 1]).replace(" ", "_"));
                     CurrentState::Node { id, name, external }
                 } else if line.starts_with("Internal ") {
                     let external = if line == "Internal 1" {
@@ -93,64 +87,190 @@ pub fn parse_gml_graph(
                     CurrentState::Node { id, name, external }
                 } else if line == "]" {
                     let ext = external.ok_or(GmlError::NodeMissingInternal(i))?;
-                    let name = name.ok_or(GmlError::NodeMissingLabel(i))?;
-                    let as_id = if !ext {
-                        AsId(65001)
-                    } else if as_id_lookup.contains_key(&name) {
-                        *as_id_lookup.get(&name).unwrap()
-                    } else {
-                        current_as_id += 1;
-                        as_id_lookup.insert(name.clone(), AsId(current_as_id));
-                        AsId(current_as_id)
-                    };
-                    let node_idx =
-                        g.add_node(NodeData { name, external: ext, as_id, net_idx: None });
                     let id = id.ok_or(GmlError::NodeMissingId(i))?;
                     if node_lookup.contains_key(&id) {
                         return Err(GmlError::NodeIdNotUnique(i));
                     }
-                    node_lookup.insert(id, node_idx);
+                    node_lookup.insert(id, builder.add_node(id, name, ext));
                     CurrentState::None
                 } else {
                     CurrentState::Node { id, name, external }
                 }
             }
-            CurrentState::Edge { source, target } => {
+            CurrentState::Edge { source, target, mut attrs } => {
                 if let Some(number) = line.strip_prefix("source ") {
                     let source: Option<usize> = Some(number.parse()?);
-                    CurrentState::Edge { source, target }
+                    CurrentState::Edge { source, target, attrs }
                 } else if let Some(number) = line.strip_prefix("target ") {
                     let target: Option<usize> = Some(number.parse()?);
-                    CurrentState::Edge { source, target }
+                    CurrentState::Edge { source, target, attrs }
                 } else if line == "]" {
                     let source = source.ok_or(GmlError::EdgeMissingSource(i))?;
                     let source_idx =
                         node_lookup.get(&source).ok_or(GmlError::UnknownNodeId(source))?;
                     let target = target.ok_or(GmlError::EdgeMissingTarget(i))?;
                     let target_idx =
-                        node_lookup.get(&target).ok_or(GmlError::UnknownNodeId(source))?;
-                    // check if the edge already exists
-                    if g.contains_edge(*source_idx, *target_idx) {
-                        // ignoring the duplicate link
-                    } else {
-                        g.add_edge(*source_idx, *target_idx, 1.0);
-                    }
+                        node_lookup.get(&target).ok_or(GmlError::UnknownNodeId(target))?;
+                    builder.add_edge(*source_idx, *target_idx, &attrs);
                     CurrentState::None
                 } else {
-                    CurrentState::Edge { source, target }
+                    let mut parts = line.splitn(2, ' ');
+                    if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                        attrs.set(name, value)?;
+                    }
+                    CurrentState::Edge { source, target, attrs }
                 }
             }
         };
     }
 
-    Ok(g)
+    Ok(builder.finish())
+}
+
+/// Builds the graph of a topology file, handling duplicate labels and parallel edges as specified
+/// by the options. This is shared by the GML and the GraphML parser.
+pub(super) struct GraphBuilder<'a> {
+    g: Graph<NodeData, LinkWeight, Undirected, u32>,
+    options: &'a GmlOptions,
+    report: &'a mut GmlReport,
+    current_as_id: u32,
+    as_id_lookup: HashMap<String, AsId>,
+    used_labels: HashMap<String, usize>,
+    label_lookup: HashMap<String, NodeIndex<u32>>,
+}
+
+impl<'a> GraphBuilder<'a> {
+    /// Create a new, empty graph builder.
+    pub fn new(options: &'a GmlOptions, report: &'a mut GmlReport) -> Self {
+        Self {
+            g: Graph::new_undirected(),
+            options,
+            report,
+            current_as_id: 65100,
+            as_id_lookup: HashMap::new(),
+            used_labels: HashMap::new(),
+            label_lookup: HashMap::new(),
+        }
+    }
+
+    /// Add a node with the given label (or `node_ID` if it has no label), and return its index.
+    /// If the node is merged into an existing node with the same label, the index of the existing
+    /// node is returned.
+    pub fn add_node(&mut self, id: usize, name: Option<String>, ext: bool) -> NodeIndex<u32> {
+        let label = match name {
+            Some(label) => label,
+            None => {
+                let renamed = format!("node_{}", id);
+                self.report.push(GmlIssue::MissingLabel { id, renamed: renamed.clone() });
+                renamed
+            }
+        };
+        let existing = match self.options.duplicate_labels {
+            DuplicateLabels::Merge => self.label_lookup.get(&label).copied(),
+            DuplicateLabels::Rename => None,
+        };
+        if let Some(existing) = existing {
+            // the merged node is internal if any of the nodes is internal
+            if !ext && self.g[existing].external {
+                self.g[existing].external = false;
+                self.g[existing].as_id = AsId(65001);
+            }
+            self.report.push(GmlIssue::MergedLabel { label, id });
+            existing
+        } else {
+            let name = unique_name(label.clone(), &mut self.used_labels, self.report);
+            let as_id = if !ext {
+                AsId(65001)
+            } else if self.as_id_lookup.contains_key(&name) {
+                *self.as_id_lookup.get(&name).unwrap()
+            } else {
+                self.current_as_id += 1;
+                self.as_id_lookup.insert(name.clone(), AsId(self.current_as_id));
+                AsId(self.current_as_id)
+            };
+            let node_idx = self.g.add_node(NodeData { name, external: ext, as_id, net_idx: None });
+            self.label_lookup.entry(label).or_insert(node_idx);
+            node_idx
+        }
+    }
+
+    /// Add an edge between the two nodes, where the weight is computed from the attributes.
+    /// Self-loops are dropped, and parallel edges are handled as specified by the options.
+    pub fn add_edge(
+        &mut self,
+        source_idx: NodeIndex<u32>,
+        target_idx: NodeIndex<u32>,
+        attrs: &LinkAttributes,
+    ) {
+        let g = &mut self.g;
+        // check if the edge already exists
+        if source_idx == target_idx {
+            self.report.push(GmlIssue::SelfLoop { node: g[source_idx].name.clone() });
+            return;
+        }
+        let source = g[source_idx].name.clone();
+        let target = g[target_idx].name.clone();
+        let weight = match attrs.weight(self.options.link_weights) {
+            Some(weight) => weight,
+            None => {
+                let weight = attrs.weight.unwrap_or(1.0);
+                self.report.push(GmlIssue::MissingLinkAttribute {
+                    source: source.clone(),
+                    target: target.clone(),
+                    weight,
+                });
+                weight
+            }
+        };
+        if let Some(edge) = g.find_edge(source_idx, target_idx) {
+            let old = g[edge];
+            let merged = match self.options.parallel_edges {
+                ParallelEdges::KeepFirst => None,
+                ParallelEdges::Min => Some(old.min(weight)),
+                ParallelEdges::Max => Some(old.max(weight)),
+                ParallelEdges::Sum => Some(old + weight),
+            };
+            match merged {
+                Some(weight) => {
+                    g[edge] = weight;
+                    self.report.push(GmlIssue::MergedEdge { source, target, weight });
+                }
+                None => self.report.push(GmlIssue::ParallelEdge { source, target }),
+            }
+        } else {
+            g.add_edge(source_idx, target_idx, weight);
+        }
+    }
+
+    /// Returns the resulting graph.
+    pub fn finish(self) -> Graph<NodeData, LinkWeight, Undirected, u32> {
+        self.g
+    }
+}
+
+/// Returns the label, or the label with `_N` appended if it was already used.
+fn unique_name(
+    label: String,
+    used_labels: &mut HashMap<String, usize>,
+    report: &mut GmlReport,
+) -> String {
+    // increment the num_used in the hashmap
+    let num_used = *used_labels.get(&label).unwrap_or(&0);
+    used_labels.insert(label.clone(), num_used + 1);
+    if num_used > 0 {
+        let renamed = format!("{}_{}", label, num_used);
+        report.push(GmlIssue::DuplicateLabel { label, renamed: renamed.clone() });
+        renamed
+    } else {
+        label
+    }
 }
 
 enum CurrentState {
     NotStarted,
     None,
     Node { id: Option<usize>, name: Option<String>, external: Option<bool> },
-    Edge { source: Option<usize>, target: Option<usize> },
+    Edge { source: Option<usize>, target: Option<usize>, attrs: LinkAttributes },
 }
 
 #[derive(Debug, Error)]
@@ -164,6 +284,9 @@ pub enum GmlError {
     /// ParseIntError
     #[error("Cannot parse an integer! {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
+    /// ParseFloatError
+    #[error("Cannot parse a float! {0}")]
+    ParseFloatError(#[from] std::num::ParseFloatError),
     /// Unknown Node Id
     #[error("Unknown node id: {0}")]
     UnknownNodeId(usize),
@@ -194,7 +317,8 @@ mod test {
     #[test]
     fn test_with_switch_gml() {
         let filename = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
-        let g = parse_gml_graph(filename).unwrap();
+        let g =
+            parse_gml_graph(filename, &GmlOptions::default(), &mut GmlReport::default()).unwrap();
 
         // check all indices and node names
         assert_eq!(g.node_weight(00.into()).unwrap().name, "Fribourg");
@@ -0,0 +1,411 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Parses GraphML files from Topology Zoo
+//!
+//! The parser only supports the subset of GraphML used by Topology Zoo and similar datasets: The
+//! attributes are declared with `<key>` elements (optionally with a `<default>` value), and every
+//! `<node>` and `<edge>` contains its attributes as `<data>` elements. Nested graphs, hyperedges
+//! and ports are not supported.
+
+use super::gml_parser::GraphBuilder;
+use super::report::LinkAttributes;
+use super::{GmlOptions, GmlReport, NodeData};
+use crate::netsim::LinkWeight;
+
+use petgraph::prelude::*;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use thiserror::Error;
+
+/// Parses GraphML files and returns the resulting graph. The label of a node is read from the
+/// attribute `label`, and the attribute `Internal` decides if it is an internal or an external
+/// router. Nodes without a label are named `node_N`, where `N` is the position of the node in the
+/// file. Edge attributes are handled the same as in
+/// [`parse_gml_graph`](super::gml_parser::parse_gml_graph): the weight is read from the
+/// attribute `weight` (or from `LinkSpeedRaw`, `Latency` or `delay`, as specified by the
+/// `options`). Every repaired or dropped construct is added to the `report`.
+pub fn parse_graphml_graph(
+    filename: impl AsRef<str>,
+    options: &GmlOptions,
+    report: &mut GmlReport,
+) -> Result<Graph<NodeData, LinkWeight, Undirected, u32>, GraphmlError> {
+    let xml = read_to_string(filename.as_ref())?;
+
+    let mut builder = GraphBuilder::new(options, report);
+    let mut node_lookup: HashMap<String, NodeIndex<u32>> = HashMap::new();
+
+    // attribute declarations: id -> key
+    let mut keys: HashMap<String, Key> = HashMap::new();
+    let mut current_key: Option<String> = None;
+    let mut current_element: Option<Element> = None;
+    let mut current_data: Option<String> = None;
+    let mut num_nodes: usize = 0;
+
+    for (tag, text) in Tags::new(&xml) {
+        let tag = tag?;
+        match (tag.name, tag.kind) {
+            ("key", TagKind::Open) | ("key", TagKind::Empty) => {
+                let id = tag.attr("id")?;
+                let key = Key {
+                    domain: tag.attrs.get("for").cloned().unwrap_or_else(|| String::from("all")),
+                    name: tag.attr("attr.name")?,
+                    default: None,
+                };
+                keys.insert(id.clone(), key);
+                if tag.kind == TagKind::Open {
+                    current_key = Some(id);
+                }
+            }
+            ("key", TagKind::Close) => current_key = None,
+            ("default", TagKind::Close) => {
+                if let Some(key) = current_key.as_ref().and_then(|id| keys.get_mut(id)) {
+                    key.default = Some(unescape(text.trim()));
+                }
+            }
+            ("node", TagKind::Open) | ("node", TagKind::Empty) => {
+                let mut element = Element::new("node", &keys);
+                element.id = Some(tag.attr("id")?);
+                current_element = Some(element);
+            }
+            ("edge", TagKind::Open) | ("edge", TagKind::Empty) => {
+                let mut element = Element::new("edge", &keys);
+                element.source = Some(tag.attr("source")?);
+                element.target = Some(tag.attr("target")?);
+                current_element = Some(element);
+            }
+            ("data", TagKind::Open) => current_data = Some(tag.attr("key")?),
+            ("data", TagKind::Empty) => {}
+            ("data", TagKind::Close) => {
+                let id = current_data.take().ok_or(GraphmlError::UnexpectedTag("/data"))?;
+                let key = keys.get(&id).ok_or_else(|| GraphmlError::UnknownKey(id.clone()))?;
+                if let Some(element) = current_element.as_mut() {
+                    element.data.insert(key.name.clone(), unescape(text.trim()));
+                }
+            }
+            _ => {}
+        }
+
+        // finish the node or edge
+        let finished = match (tag.name, tag.kind) {
+            ("node", TagKind::Close)
+            | ("node", TagKind::Empty)
+            | ("edge", TagKind::Close)
+            | ("edge", TagKind::Empty) => current_element.take(),
+            _ => None,
+        };
+        match finished {
+            Some(Element { id: Some(id), data, .. }) => {
+                let ext = match data.get("Internal").map(|s| s.as_str()) {
+                    Some("1") => false,
+                    Some("0") => true,
+                    Some(x) => return Err(GraphmlError::InvalidInternal(x.to_string())),
+                    None => return Err(GraphmlError::NodeMissingInternal(id)),
+                };
+                if node_lookup.contains_key(&id) {
+                    return Err(GraphmlError::NodeIdNotUnique(id));
+                }
+                let name = data.get("label").map(|label| label.replace(" ", "_"));
+                node_lookup.insert(id, builder.add_node(num_nodes, name, ext));
+                num_nodes += 1;
+            }
+            Some(Element { source: Some(source), target: Some(target), data, .. }) => {
+                let mut attrs = LinkAttributes::default();
+                for (name, value) in data.iter() {
+                    attrs.set(name, value)?;
+                }
+                let source_idx =
+                    node_lookup.get(&source).ok_or(GraphmlError::UnknownNodeId(source))?;
+                let target_idx =
+                    node_lookup.get(&target).ok_or(GraphmlError::UnknownNodeId(target))?;
+                builder.add_edge(*source_idx, *target_idx, &attrs);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+/// Attribute declared by a `<key>` element
+#[derive(Debug)]
+struct Key {
+    /// Element type for which the attribute is declared (`node`, `edge` or `all`)
+    domain: String,
+    /// Name of the attribute
+    name: String,
+    /// Default value of the attribute
+    default: Option<String>,
+}
+
+/// A node or an edge, with all its attributes (by name)
+#[derive(Debug)]
+struct Element {
+    id: Option<String>,
+    source: Option<String>,
+    target: Option<String>,
+    data: HashMap<String, String>,
+}
+
+impl Element {
+    /// Create a new element, with the default value of all attributes declared for its type.
+    fn new(domain: &str, keys: &HashMap<String, Key>) -> Self {
+        let data = keys
+            .values()
+            .filter(|k| k.domain == domain || k.domain == "all")
+            .filter_map(|k| k.default.as_ref().map(|d| (k.name.clone(), d.clone())))
+            .collect();
+        Self { id: None, source: None, target: None, data }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TagKind {
+    /// Opening tag, like `<node id="0">`
+    Open,
+    /// Closing tag, like `</node>`
+    Close,
+    /// Empty-element tag, like `<key id="d0" />`
+    Empty,
+}
+
+/// A single XML tag, where the name is stripped of its namespace prefix.
+#[derive(Debug)]
+struct Tag<'a> {
+    name: &'a str,
+    kind: TagKind,
+    attrs: HashMap<String, String>,
+}
+
+impl<'a> Tag<'a> {
+    /// Returns the value of a required attribute.
+    fn attr(&self, name: &str) -> Result<String, GraphmlError> {
+        self.attrs.get(name).cloned().ok_or_else(|| GraphmlError::MissingAttribute {
+            tag: self.name.to_string(),
+            attr: name.to_string(),
+        })
+    }
+}
+
+/// Iterator over all tags of an XML document, together with the text preceding the tag.
+/// Comments, processing instructions and declarations are skipped.
+struct Tags<'a> {
+    rest: &'a str,
+    attr_re: Regex,
+}
+
+impl<'a> Tags<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self {
+            rest: xml,
+            attr_re: Regex::new(r#"([\w:.-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap(),
+        }
+    }
+
+    fn parse_tag(&self, content: &'a str) -> Result<Tag<'a>, GraphmlError> {
+        let (kind, content) = if let Some(content) = content.strip_prefix('/') {
+            (TagKind::Close, content)
+        } else if let Some(content) = content.strip_suffix('/') {
+            (TagKind::Empty, content)
+        } else {
+            (TagKind::Open, content)
+        };
+        let name_end = content.find(char::is_whitespace).unwrap_or(content.len());
+        let name = &content[..name_end];
+        if name.is_empty() {
+            return Err(GraphmlError::InvalidTag(content.to_string()));
+        }
+        let name = name.rsplit(':').next().unwrap_or(name);
+        let attrs = self
+            .attr_re
+            .captures_iter(&content[name_end..])
+            .map(|c| {
+                let value = c.get(2).or_else(|| c.get(3)).map(|m| m.as_str()).unwrap_or("");
+                (c[1].to_string(), unescape(value))
+            })
+            .collect();
+        Ok(Tag { name, kind, attrs })
+    }
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = (Result<Tag<'a>, GraphmlError>, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.rest.find('<')?;
+            let text = &self.rest[..start];
+            let rest = &self.rest[start..];
+            // skip comments, processing instructions and declarations
+            let end_marker = if rest.starts_with("<!--") {
+                "-->"
+            } else if rest.starts_with("<?") || rest.starts_with("<!") {
+                ">"
+            } else {
+                let end = match rest.find('>') {
+                    Some(end) => end,
+                    None => {
+                        self.rest = "";
+                        return Some((Err(GraphmlError::UnexpectedEof), text));
+                    }
+                };
+                self.rest = &rest[end + 1..];
+                return Some((self.parse_tag(rest[1..end].trim()), text));
+            };
+            self.rest = match rest.find(end_marker) {
+                Some(end) => &rest[end + end_marker.len()..],
+                None => "",
+            };
+        }
+    }
+}
+
+/// Replace the predefined XML entities by their characters.
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[derive(Debug, Error)]
+pub enum GraphmlError {
+    /// Io Error
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// ParseFloatError
+    #[error("Cannot parse a float! {0}")]
+    ParseFloatError(#[from] std::num::ParseFloatError),
+    /// The file ends within a tag
+    #[error("Unexpected end of file")]
+    UnexpectedEof,
+    /// The tag cannot be parsed
+    #[error("Invalid tag: <{0}>")]
+    InvalidTag(String),
+    /// The tag is not expected at this position
+    #[error("Unexpected tag: <{0}>")]
+    UnexpectedTag(&'static str),
+    /// The tag is missing a required attribute
+    #[error("Tag <{tag}> is missing the attribute {attr}")]
+    MissingAttribute { tag: String, attr: String },
+    /// The data refers to an undeclared key
+    #[error("Unknown key: {0}")]
+    UnknownKey(String),
+    /// Unknown Node Id
+    #[error("Unknown node id: {0}")]
+    UnknownNodeId(String),
+    /// Node is missing an internal attribute
+    #[error("Node {0} is missing the Internal attribute!")]
+    NodeMissingInternal(String),
+    /// The internal attribute is neither 0 nor 1
+    #[error("Invalid value of the Internal attribute: {0}")]
+    InvalidInternal(String),
+    /// Duplicate Node Id
+    #[error("Node ID is not unique: {0}")]
+    NodeIdNotUnique(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::gml_parser::parse_gml_graph;
+    use super::super::{GmlIssue, LinkWeights};
+    use super::*;
+
+    fn filename(name: &str) -> String {
+        format!("{}/test_files/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn same_as_gml() {
+        let options = GmlOptions::default();
+        let gml =
+            parse_gml_graph(filename("switch.gml"), &options, &mut GmlReport::default()).unwrap();
+        let graphml =
+            parse_graphml_graph(filename("switch.graphml"), &options, &mut GmlReport::default())
+                .unwrap();
+        let nodes = |g: &Graph<NodeData, LinkWeight, Undirected, u32>| {
+            g.raw_nodes()
+                .iter()
+                .map(|n| (n.weight.name.clone(), n.weight.external, n.weight.as_id))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(nodes(&gml), nodes(&graphml));
+        assert_eq!(gml.edge_count(), graphml.edge_count());
+        for e in gml.edge_indices() {
+            assert_eq!(gml.edge_endpoints(e), graphml.edge_endpoints(e));
+            assert_eq!(gml[e], graphml[e]);
+        }
+    }
+
+    #[test]
+    fn bandwidth() {
+        let options = GmlOptions::default()
+            .link_weights(LinkWeights::Bandwidth { reference: 100_000_000_000 });
+        let mut report = GmlReport::default();
+        let g = parse_graphml_graph(filename("switch.graphml"), &options, &mut report).unwrap();
+        assert!(report.is_empty());
+        let count = |w: LinkWeight| g.edge_indices().filter(|e| g[*e] == w).count();
+        assert_eq!(count(100.0), 41);
+        assert_eq!(count(10.0), 20);
+        assert_eq!(count(5.0), 2);
+
+        // the GML parser reads the same link speeds
+        let gml = parse_gml_graph(filename("switch.gml"), &options, &mut report).unwrap();
+        assert!(g.edge_indices().all(|e| g[e] == gml[e]));
+    }
+
+    #[test]
+    fn latency() {
+        let options = GmlOptions::default().link_weights(LinkWeights::Latency { per_ms: 10 });
+        let mut report = GmlReport::default();
+        let g = parse_graphml_graph(filename("latency.graphml"), &options, &mut report).unwrap();
+        let names: Vec<&str> = g.raw_nodes().iter().map(|n| n.weight.name.as_str()).collect();
+        assert_eq!(names, vec!["Zurich", "Bern", "Geneva", "Upstream_&_Co"]);
+        assert_eq!(
+            g.raw_nodes().iter().map(|n| n.weight.external).collect::<Vec<_>>(),
+            vec![false, false, false, true]
+        );
+        assert_eq!(
+            g.raw_edges().iter().map(|e| e.weight).collect::<Vec<_>>(),
+            vec![12.0, 25.0, 1.0, 5.0]
+        );
+        assert_eq!(
+            report.issues,
+            vec![GmlIssue::MissingLinkAttribute {
+                source: "Zurich".to_string(),
+                target: "Geneva".to_string(),
+                weight: 1.0
+            }]
+        );
+    }
+
+    #[test]
+    fn tags() {
+        let xml = "<?xml version=\"1.0\"?><!-- <node> --><a x=\"1\" y='&lt;2'>text</a><b/>";
+        let tags: Vec<_> = Tags::new(xml).map(|(t, text)| (t.unwrap(), text.to_string())).collect();
+        assert_eq!(tags.len(), 3);
+        assert_eq!((tags[0].0.name, tags[0].0.kind), ("a", TagKind::Open));
+        assert_eq!(tags[0].0.attrs.get("y").unwrap(), "<2");
+        assert_eq!(
+            (tags[1].0.name, tags[1].0.kind, tags[1].1.as_str()),
+            ("a", TagKind::Close, "text")
+        );
+        assert_eq!((tags[2].0.name, tags[2].0.kind), ("b", TagKind::Empty));
+    }
+}
@@ -18,12 +18,16 @@
 //! Import functions for importing topology zoo graphml files
 
 use crate::hard_policies::*;
+use crate::netsim::bgp::RovPolicy;
+use crate::netsim::centrality::GraphAnalysis;
 use crate::netsim::config::{
     Config,
     ConfigExpr::{self, *},
 };
 use crate::netsim::route_map::*;
-use crate::netsim::{AsId, BgpSessionType::*, LinkWeight, Network, NetworkError, Prefix, RouterId};
+use crate::netsim::{
+    AsId, BgpSessionType::*, LinkWeight, Network, NetworkError, OspfArea, Prefix, RouterId,
+};
 use crate::Error;
 
 use itertools::iproduct;
@@ -35,13 +39,31 @@ use std::collections::{HashMap, HashSet, VecDeque};
 mod error;
 pub use error::ZooTopologyError;
 
+mod gadgets;
+
 mod gml_parser;
 
+mod graphml_parser;
+
+mod multi_as;
+
+mod params;
+pub use params::{AsPeering, RrSelection, ScenarioParams};
+
+mod report;
+pub use report::{
+    DuplicateLabels, GmlIssue, GmlOptions, GmlReport, GmlValidation, LinkWeights, ParallelEdges,
+};
+
 type NodeIdx = NodeIndex<u32>;
 
+/// Local-pref of the backup egress in [`Scenario::RouteMapRollout`]
+const BACKUP_LOCAL_PREF: u32 = 50;
+
 /// # ZooTopology
-/// This struct can be used to generate a network from a ZooTopology graphml file. In addition, you
-/// can set (predictably) random link weights, and generate a (predictably) random iBGP topologies.
+/// This struct can be used to generate a network from a ZooTopology GML or GraphML file. In
+/// addition, you can set (predictably) random link weights, and generate a (predictably) random
+/// iBGP topologies.
 ///
 /// ```rust
 /// # use snowcap::topology_zoo::ZooTopology;
@@ -75,7 +97,10 @@ pub struct ZooTopology {
     /// Every node in this set is in its separate network!
     disconnected: HashSet<NodeIdx>,
     maintenance: HashSet<NodeIdx>,
+    /// OSPF area of every internal node. If empty, the IGP is flat.
+    ospf_areas: HashMap<NodeIdx, OspfArea>,
     create_ibgp_peers: bool,
+    report: GmlReport,
 }
 
 impl ZooTopology {
@@ -83,11 +108,47 @@ impl ZooTopology {
     /// it will generate a ZooTopology with 100 prefixes. The iBGP topology is not generated by
     /// default.
     ///
+    /// Unsupported GML constructs are repaired (see [`GmlValidation::Repair`]), and listed in
+    /// [`ZooTopology::report`].
+    ///
     /// # Panics
     /// Panics if somehow, petgraph does not play along and creates nodes in a wierd order.
     pub fn new(gml_filename: impl AsRef<str>, seed: u64) -> Result<Self, ZooTopologyError> {
+        Self::new_validated(gml_filename, seed, GmlValidation::Repair)
+    }
+
+    /// Return a new ZooTopology instance by reading and parsing the provided GML file, handling
+    /// unsupported GML constructs (parallel edges, self-loops, duplicate or missing labels and
+    /// disconnected components) as specified by `validation`. Everything that was dropped or
+    /// repaired is listed in [`ZooTopology::report`].
+    pub fn new_validated(
+        gml_filename: impl AsRef<str>,
+        seed: u64,
+        validation: GmlValidation,
+    ) -> Result<Self, ZooTopologyError> {
+        Self::new_with_options(gml_filename, seed, &GmlOptions::default().validation(validation))
+    }
+
+    /// Return a new ZooTopology instance by reading and parsing the provided GML file, normalizing
+    /// the topology as specified by the `options` (keeping only the largest connected component,
+    /// merging parallel edges, merging or renaming nodes with duplicate labels and mapping link
+    /// speed or latency to link weights). Everything that was dropped or repaired is listed in
+    /// [`ZooTopology::report`].
+    ///
+    /// Files ending with `.graphml` are parsed as GraphML, and all other files as GML.
+    pub fn new_with_options(
+        gml_filename: impl AsRef<str>,
+        seed: u64,
+        options: &GmlOptions,
+    ) -> Result<Self, ZooTopologyError> {
         info!("Parsing the file and reading the graph");
-        let graph = gml_parser::parse_gml_graph(gml_filename.as_ref())?;
+        let mut report = GmlReport::default();
+        let graph = if gml_filename.as_ref().ends_with(".graphml") {
+            graphml_parser::parse_graphml_graph(gml_filename.as_ref(), options, &mut report)?
+        } else {
+            gml_parser::parse_gml_graph(gml_filename.as_ref(), options, &mut report)?
+        };
+        let (graph, report) = report::validate(graph, options.validation, report)?;
         info!("Successfully read the GML file.");
         // clone the nodes of the physical graph and make sure that the nodes indices are always the same
         let mut ibgp_graph: Graph<(), (), Directed, u32> = Graph::new();
@@ -102,12 +163,22 @@ impl ZooTopology {
             ibgp_roots: HashSet::new(),
             disconnected: HashSet::new(),
             maintenance: HashSet::new(),
+            ospf_areas: HashMap::new(),
             create_ibgp_peers: true,
+            report,
         })
     }
 
+    /// Returns the report of all unsupported GML constructs, which were dropped or repaired while
+    /// importing the file.
+    pub fn report(&self) -> &GmlReport {
+        &self.report
+    }
+
     /// This funciton applies a scenario to a topology (including some common configuration). This
-    /// function may panic, if the topology does not support the scenario.
+    /// function may panic, if the topology does not support the scenario. If `random_root` is set,
+    /// the route reflector is chosen at random, and otherwise, the router with the highest degree
+    /// is chosen. Use [`ZooTopology::apply_scenario_with_params`] for more control.
     pub fn apply_scenario(
         &mut self,
         scenario: Scenario,
@@ -115,33 +186,41 @@ impl ZooTopology {
         max_weight: u32,
         num_prefixes: usize,
         prefix_probability: f64,
+    ) -> Result<(Network, Config, HardPolicy), Error> {
+        let params = ScenarioParams::default()
+            .rr_selection(if random_root { RrSelection::Random } else { RrSelection::Degree })
+            .max_weight(max_weight)
+            .prefixes(num_prefixes, prefix_probability);
+        self.apply_scenario_with_params(scenario, &params)
+    }
+
+    /// This funciton applies a scenario to a topology (including some common configuration), where
+    /// the generated problem is parameterized with `params` (number of route reflectors and how
+    /// they are selected, link weights, prefixes, external peers and local-pref values). This
+    /// function may panic, if the topology does not support the scenario.
+    pub fn apply_scenario_with_params(
+        &mut self,
+        scenario: Scenario,
+        params: &ScenarioParams,
     ) -> Result<(Network, Config, HardPolicy), Error> {
         let mut net = self.get_net();
         // build initial config
 
         let (config_a, config_b) = match scenario {
             Scenario::FullMesh2RouteReflector | Scenario::RouteReflector2FullMesh => {
-                self.randomize_link_weights(max_weight);
+                self.scenario_link_weights(params);
                 self.ibgp_full_mesh();
                 let config_a = self.get_config()?;
 
                 // build final config with different iBGP topology
-                if random_root {
-                    self.ibgp_single_route_reflector_random()?;
-                } else {
-                    self.ibgp_single_route_reflector_most_important()?;
-                }
+                self.ibgp_selected_route_reflectors(params)?;
                 let config_b = self.get_config()?;
                 (config_a, config_b)
             }
             Scenario::DoubleIgpWeight | Scenario::HalveIgpWeight => {
-                self.randomize_link_weights(max_weight);
+                self.scenario_link_weights(params);
                 // build initial config
-                if random_root {
-                    self.ibgp_single_route_reflector_random()?;
-                } else {
-                    self.ibgp_single_route_reflector_most_important()?;
-                }
+                self.ibgp_selected_route_reflectors(params)?;
                 let config_a = self.get_config()?;
                 let mut config_b = Config::new();
                 for expr in config_a.iter() {
@@ -158,86 +237,106 @@ impl ZooTopology {
                 (config_a, config_b)
             }
             Scenario::DoubleLocalPref | Scenario::HalveLocalPref => {
-                self.randomize_link_weights(max_weight);
-                if random_root {
-                    self.ibgp_single_route_reflector_random()?;
-                } else {
-                    self.ibgp_single_route_reflector_most_important()?;
-                }
+                self.scenario_link_weights(params);
+                self.ibgp_selected_route_reflectors(params)?;
                 let config_a = self.get_config()?;
                 let mut config_b = config_a.clone();
                 let mut order_id = 1;
-                for expr in config_a.iter() {
-                    let (r_int, r_ext) = match expr {
-                        ConfigExpr::BgpSession { source: r_int, target: r_ext, session_type }
-                            if session_type.is_ebgp()
-                                && net.get_external_routers().contains(r_ext) =>
-                        {
-                            (r_int, r_ext)
-                        }
-                        ConfigExpr::BgpSession { source: r_ext, target: r_int, session_type }
-                            if session_type.is_ebgp()
-                                && net.get_external_routers().contains(r_ext) =>
-                        {
-                            (r_int, r_ext)
-                        }
-                        _ => continue,
-                    };
+                for (r_int, r_ext) in Self::ebgp_sessions(&net, &config_a) {
                     config_b.add(ConfigExpr::BgpRouteMap {
-                        router: *r_int,
+                        router: r_int,
                         direction: RouteMapDirection::Incoming,
                         map: RouteMapBuilder::new()
                             .order(order_id)
                             .allow()
-                            .match_neighbor(*r_ext)
-                            .set_local_pref(200)
+                            .match_neighbor(r_ext)
+                            .set_local_pref(self.random_local_pref(params))
                             .build(),
                     })?;
                     order_id += 1;
                 }
                 (config_a, config_b)
             }
+            Scenario::RouteMapRollout => {
+                self.scenario_link_weights(params);
+                self.ibgp_selected_route_reflectors(params)?;
+                let config_a = self.get_config()?;
+                let mut config_b = config_a.clone();
+                let mut sessions = Self::ebgp_sessions(&net, &config_a);
+                sessions.sort();
+                // every prefix gets a preferred egress, and a depreferred backup egress. The
+                // entries of a border router are ordered by the prefix.
+                let mut next_order: HashMap<RouterId, usize> = HashMap::new();
+                for i in 0..params.num_prefixes {
+                    let prefix = Prefix(i as u32);
+                    let egresses =
+                        sessions.choose_multiple(&mut self.rng, 2).cloned().collect::<Vec<_>>();
+                    for (j, (r_int, r_ext)) in egresses.into_iter().enumerate() {
+                        let local_pref =
+                            if j == 0 { self.random_local_pref(params) } else { BACKUP_LOCAL_PREF };
+                        let order = next_order.entry(r_int).or_insert(0);
+                        *order += 10;
+                        config_b.add(ConfigExpr::BgpRouteMap {
+                            router: r_int,
+                            direction: RouteMapDirection::Incoming,
+                            map: RouteMapBuilder::new()
+                                .order(*order)
+                                .allow()
+                                .match_neighbor(r_ext)
+                                .match_prefix(prefix)
+                                .set_local_pref(local_pref)
+                                .build(),
+                        })?;
+                    }
+                }
+                (config_a, config_b)
+            }
             Scenario::IntroduceSecondRouteReflector | Scenario::RemoveSecondRouteReflector => {
-                self.randomize_link_weights(max_weight);
+                self.scenario_link_weights(params);
                 // set route-reflector topology
-                if random_root {
-                    self.ibgp_single_route_reflector_random()?;
-                } else {
-                    self.ibgp_single_route_reflector_most_important()?;
-                }
+                self.ibgp_selected_route_reflectors(params)?;
                 let config_a = self.get_config()?;
-                let root = self
-                    .get_roots()
-                    .into_iter()
-                    .next()
-                    .expect("exactly one root must exist!")
-                    .to_string();
-
-                if random_root {
-                    self.ibgp_two_route_reflectors_one_named_other_random(root.as_str())?;
-                } else {
-                    self.ibgp_two_route_reflectors_one_named_other_important(root.as_str())?;
-                }
+                // add one more route reflector as backup
+                let mut roots = self.ibgp_roots.iter().cloned().collect::<Vec<_>>();
+                roots.sort();
+                let backup = self.select_route_reflectors(1, params.rr_selection, &roots)?;
+                roots.extend(backup);
+                self.ibgp_route_reflectors(&roots);
+                let config_b = self.get_config()?;
+                (config_a, config_b)
+            }
+            Scenario::MigrateRouteReflector => {
+                self.scenario_link_weights(params);
+                // set route-reflector topology, with the preferred route reflector first
+                let mut roots = self.select_route_reflectors(
+                    params.num_route_reflectors,
+                    params.rr_selection,
+                    &[],
+                )?;
+                self.ibgp_route_reflectors(&roots);
+                let config_a = self.get_config()?;
+                // move the preferred route reflector to the next best candidate. The new root
+                // gets new sessions, its session to the old root changes direction, and the
+                // sessions of the old root are removed.
+                let new_root = self.select_route_reflectors(1, params.rr_selection, &roots)?;
+                roots[0] = new_root[0];
+                self.ibgp_route_reflectors(&roots);
                 let config_b = self.get_config()?;
                 (config_a, config_b)
             }
             Scenario::NetworkAcquisition | Scenario::NetworkSplit => {
-                self.randomize_link_weights(max_weight);
+                self.scenario_link_weights(params);
                 self.acquisition_before(0.1)?;
                 let config_a = self.get_config()?;
                 let mut t_clone = self.clone();
-                t_clone.acquisition_after(0.1, max_weight);
+                t_clone.acquisition_after(0.1, params.max_weight);
                 let config_b = t_clone.get_config()?;
                 (config_a, config_b)
             }
             Scenario::DisconnectRouter | Scenario::ConnectRouter => {
                 // setup weights and the single route-reflector topology
-                self.randomize_link_weights(max_weight);
-                if random_root {
-                    self.ibgp_single_route_reflector_random()?;
-                } else {
-                    self.ibgp_single_route_reflector_most_important()?;
-                }
+                self.scenario_link_weights(params);
+                self.ibgp_selected_route_reflectors(params)?;
                 let config_a = self.get_config()?;
 
                 // select a router that is the most important
@@ -273,10 +372,69 @@ impl ZooTopology {
                 let config_b = self.get_config()?;
                 (config_a, config_b)
             }
+            Scenario::DeployRov | Scenario::RemoveRov => {
+                self.scenario_link_weights(params);
+                self.ibgp_selected_route_reflectors(params)?;
+                let mut config_a = self.get_config()?;
+                let mut config_b = config_a.clone();
+
+                // ROV is deployed on all border routers
+                let border_routers = self
+                    .graph
+                    .node_indices()
+                    .filter(|x| {
+                        !self.graph.node_weight(*x).unwrap().external
+                            && self
+                                .graph
+                                .neighbors(*x)
+                                .any(|n| self.graph.node_weight(n).unwrap().external)
+                    })
+                    .map(|x| self.graph.node_weight(x).unwrap().net_idx.unwrap())
+                    .collect::<Vec<_>>();
+
+                for i in 0..params.num_prefixes {
+                    let prefix = Prefix(i as u32);
+                    let origin = Self::prefix_origin(prefix);
+                    // Some prefixes were moved to a new origin AS, but their ROA was not yet
+                    // updated. The route for this prefix is therefore RPKI-invalid.
+                    let stale_origin = if self.rng.gen_bool(0.5) {
+                        AsId(origin.0 + params.num_prefixes as u32)
+                    } else {
+                        origin
+                    };
+                    for router in border_routers.iter() {
+                        config_a.add(Roa { router: *router, prefix, origin: stale_origin })?;
+                        config_b.add(Roa { router: *router, prefix, origin })?;
+                    }
+                }
+                for router in border_routers {
+                    config_b
+                        .add(ConfigExpr::RovPolicy { router, policy: RovPolicy::RejectInvalid })?;
+                }
+                (config_a, config_b)
+            }
+            Scenario::RenumberOspfArea => {
+                self.scenario_link_weights(params);
+                self.ibgp_selected_route_reflectors(params)?;
+                self.ospf_areas()?;
+                let config_a = self.get_config()?;
+                // move all links of the first area into a new area
+                let new_area = OspfArea(self.ospf_areas.values().map(|a| a.0).max().unwrap() + 1);
+                self.ospf_areas.values_mut().filter(|a| **a == OspfArea(1)).for_each(|a| {
+                    *a = new_area;
+                });
+                let config_b = self.get_config()?;
+                (config_a, config_b)
+            }
+            Scenario::EmbeddedChain => return self.apply_chain_scenario(net, params),
+            Scenario::EmbeddedCarousel => return self.apply_carousel_scenario(net, params),
+            Scenario::MultiAsMerge | Scenario::MultiAsSplit => {
+                return self.apply_multi_as_scenario(net, params, scenario.is_inverse());
+            }
             Scenario::VerifyTransientCondition | Scenario::VerifyTransientConditionReverse => {
                 return self.apply_transient_condition_scenario(
                     net,
-                    max_weight,
+                    params.max_weight,
                     scenario.is_inverse(),
                     None,
                 );
@@ -287,11 +445,17 @@ impl ZooTopology {
         let (config_a, config_b) =
             if scenario.is_inverse() { (config_b, config_a) } else { (config_a, config_b) };
 
-        // apply config A to network
+        // apply config A to network, and annotate the roles of the routers
         net.set_config(&config_a)?;
+        net.annotate_roles_from_config();
 
         // advertise the same prefix on every router
-        self.advertise_prefixes(&mut net, num_prefixes, prefix_probability)?;
+        self.advertise_prefixes_from_peers(
+            &mut net,
+            params.num_prefixes,
+            params.prefix_probability,
+            params.num_external_peers,
+        )?;
 
         // prepare the hard polcies
         let hard_policy = match scenario {
@@ -446,6 +610,7 @@ impl ZooTopology {
             net.set_config(&c1)?;
             c2
         };
+        net.annotate_roles_from_config();
 
         net.advertise_external_route(
             self.graph.node_weight(ext_lp100).unwrap().net_idx.unwrap(),
@@ -491,12 +656,70 @@ impl ZooTopology {
         self
     }
 
+    /// Randomize the link weights for a scenario, unless the parameters tell to keep the link
+    /// weights imported from the topology file.
+    fn scenario_link_weights(&mut self, params: &ScenarioParams) {
+        if !params.keep_link_weights {
+            self.randomize_link_weights(params.max_weight);
+        }
+    }
+
     /// Reset the seed to a known value
     pub fn set_seed(&mut self, seed: u64) -> &mut Self {
         self.rng = StdRng::seed_from_u64(seed);
         self
     }
 
+    /// Split the IGP into multiple OSPF areas. The backbone consists of the internal router with
+    /// the highest degree, and all of its internal neighbors. Every connected component of the
+    /// remaining internal routers forms its own area, attached to the backbone. Links to external
+    /// routers are in the area of the internal router.
+    ///
+    /// Returns an error if all internal routers are part of the backbone.
+    pub fn ospf_areas(&mut self) -> Result<&mut Self, ZooTopologyError> {
+        let internal = |x: &NodeIdx| !self.graph.node_weight(*x).unwrap().external;
+        let core = self
+            .graph
+            .node_indices()
+            .filter(internal)
+            .max_by_key(|x| (self.internal_degree(*x), std::cmp::Reverse(*x)))
+            .ok_or(ZooTopologyError::TooFewInternalRouters)?;
+
+        let mut areas: HashMap<NodeIdx, OspfArea> = HashMap::new();
+        areas.insert(core, OspfArea::BACKBONE);
+        for n in self.graph.neighbors(core).filter(internal) {
+            areas.insert(n, OspfArea::BACKBONE);
+        }
+
+        // every connected component of the remaining routers forms an area
+        let mut next_area = 1;
+        let mut nodes = self.graph.node_indices().filter(internal).collect::<Vec<_>>();
+        nodes.sort();
+        for start in nodes {
+            if areas.contains_key(&start) {
+                continue;
+            }
+            let area = OspfArea(next_area);
+            next_area += 1;
+            let mut queue = VecDeque::from(vec![start]);
+            areas.insert(start, area);
+            while let Some(node) = queue.pop_front() {
+                for n in self.graph.neighbors(node).filter(internal) {
+                    if !areas.contains_key(&n) {
+                        areas.insert(n, area);
+                        queue.push_back(n);
+                    }
+                }
+            }
+        }
+
+        if next_area == 1 {
+            return Err(ZooTopologyError::TooFewOspfAreas);
+        }
+        self.ospf_areas = areas;
+        Ok(self)
+    }
+
     /// Forms an iBGP full mesh between all routers.
     pub fn ibgp_full_mesh(&mut self) -> &mut Self {
         self.ibgp_graph.clear_edges();
@@ -1035,6 +1258,21 @@ impl ZooTopology {
         net: &mut Network,
         num_prefixes: usize,
         probability: f64,
+    ) -> Result<(), NetworkError> {
+        self.advertise_prefixes_from_peers(net, num_prefixes, probability, None)
+    }
+
+    /// # Advertise prefixes from some peers
+    /// Like [`ZooTopology::advertise_prefixes`], but only `num_peers` randomly chosen external
+    /// routers advertise prefixes (if `num_peers` is `None`, all external routers are used). If the
+    /// network is disconnected, prefixes may still be advertised by other external routers, to make
+    /// sure that both components know every prefix.
+    pub fn advertise_prefixes_from_peers(
+        &mut self,
+        net: &mut Network,
+        num_prefixes: usize,
+        probability: f64,
+        num_peers: Option<usize>,
     ) -> Result<(), NetworkError> {
         info!("Advertise {} prefixes in the network", num_prefixes);
         let mut external_nodes: Vec<NodeIdx> = self
@@ -1045,10 +1283,16 @@ impl ZooTopology {
             .collect();
         // sort before shuffle, to guarantee that we always get the same result
         external_nodes.sort();
+        if let Some(num_peers) = num_peers {
+            external_nodes.shuffle(&mut self.rng);
+            external_nodes.truncate(num_peers);
+            external_nodes.sort();
+        }
 
         let mut prefixes: Vec<(Prefix, AsId)> = Vec::with_capacity(num_prefixes);
         for i in 0..num_prefixes {
-            prefixes.push((Prefix(i as u32), AsId(65300 + i as u32)));
+            let prefix = Prefix(i as u32);
+            prefixes.push((prefix, Self::prefix_origin(prefix)));
         }
 
         let mut component_known_prefixes = [HashSet::new(), HashSet::new()];
@@ -1153,6 +1397,12 @@ impl ZooTopology {
         Ok(())
     }
 
+    /// Returns the AS which originates the given prefix, when advertised using
+    /// [`ZooTopology::advertise_prefixes`].
+    fn prefix_origin(prefix: Prefix) -> AsId {
+        AsId(65300 + prefix.0)
+    }
+
     /// Returns the current configuration based on the already prepared data.
     ///
     /// # Panics
@@ -1186,6 +1436,25 @@ impl ZooTopology {
                 .map_err(|_| ZooTopologyError::MultipleLinkWeights)?;
         }
 
+        // then, add the OSPF areas of all links which are not in the backbone
+        for edge_idx in self.graph.edge_indices() {
+            let (src, dst) = self.graph.edge_endpoints(edge_idx).unwrap();
+            let area = match [src, dst]
+                .iter()
+                .filter_map(|x| self.ospf_areas.get(x))
+                .find(|a| !a.is_backbone())
+            {
+                Some(area) => *area,
+                None => continue,
+            };
+            c.add(ConfigExpr::OspfArea {
+                router_a: self.graph.node_weight(src).unwrap().net_idx.unwrap(),
+                router_b: self.graph.node_weight(dst).unwrap().net_idx.unwrap(),
+                area,
+            })
+            .map_err(|_| ZooTopologyError::MultipleLinkWeights)?;
+        }
+
         // then, add all ebgp sessions
         if generate_ebgp {
             for external_router in self
@@ -1259,6 +1528,130 @@ impl ZooTopology {
         Ok(c)
     }
 
+    /// Generates a iBGP route reflector topology with the route reflectors selected as specified in
+    /// the scenario parameters.
+    fn ibgp_selected_route_reflectors(
+        &mut self,
+        params: &ScenarioParams,
+    ) -> Result<&mut Self, ZooTopologyError> {
+        let roots =
+            self.select_route_reflectors(params.num_route_reflectors, params.rr_selection, &[])?;
+        Ok(self.ibgp_route_reflectors(&roots))
+    }
+
+    /// Select `num` internal routers as route reflectors, which are not yet in `existing`.
+    fn select_route_reflectors(
+        &mut self,
+        num: usize,
+        selection: RrSelection,
+        existing: &[NodeIdx],
+    ) -> Result<Vec<NodeIdx>, ZooTopologyError> {
+        let mut nodes = self
+            .graph
+            .node_indices()
+            .into_iter()
+            .filter(|x| !self.graph.node_weight(*x).unwrap().external)
+            .filter(|x| !existing.contains(x))
+            .collect::<Vec<NodeIdx>>();
+        if nodes.len() < num {
+            return Err(ZooTopologyError::TooFewInternalRouters);
+        }
+        match selection {
+            RrSelection::Random => nodes.shuffle(&mut self.rng),
+            RrSelection::Degree => {
+                // on ties, the router with the largest index is preferred.
+                nodes.sort_by_key(|x| self.internal_degree(*x));
+                nodes.reverse();
+            }
+            RrSelection::Closeness | RrSelection::Betweenness => {
+                let analysis = self.internal_analysis();
+                let score = match selection {
+                    RrSelection::Closeness => analysis.closeness(),
+                    _ => analysis.betweenness(),
+                };
+                nodes.sort_by(|a, b| score[a].partial_cmp(&score[b]).unwrap());
+                nodes.reverse();
+            }
+        }
+        nodes.truncate(num);
+        Ok(nodes)
+    }
+
+    /// Returns the number of IGP neighbors of the node, which are internal routers.
+    fn internal_degree(&self, node: NodeIdx) -> usize {
+        self.graph.neighbors(node).filter(|n| !self.graph.node_weight(*n).unwrap().external).count()
+    }
+
+    /// Returns the graph analysis of all internal routers, where every link has weight 1, i.e.,
+    /// distances are hop counts.
+    fn internal_analysis(&self) -> GraphAnalysis {
+        let internal = |x: &NodeIdx| !self.graph.node_weight(*x).unwrap().external;
+        GraphAnalysis::new(
+            self.graph.node_indices().filter(internal),
+            self.graph.edge_indices().map(|e| {
+                let (a, b) = self.graph.edge_endpoints(e).unwrap();
+                (a, b, 1.0)
+            }),
+        )
+    }
+
+    /// Returns all eBGP sessions in the config between an internal and an external router, as
+    /// tuple `(internal, external)`, in the order of the config.
+    fn ebgp_sessions(net: &Network, config: &Config) -> Vec<(RouterId, RouterId)> {
+        let external_routers = net.get_external_routers();
+        config
+            .iter()
+            .filter_map(|expr| match expr {
+                ConfigExpr::BgpSession { source: r_int, target: r_ext, session_type }
+                    if session_type.is_ebgp() && external_routers.contains(r_ext) =>
+                {
+                    Some((*r_int, *r_ext))
+                }
+                ConfigExpr::BgpSession { source: r_ext, target: r_int, session_type }
+                    if session_type.is_ebgp() && external_routers.contains(r_ext) =>
+                {
+                    Some((*r_int, *r_ext))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns a local-pref value from the range of the scenario parameters.
+    fn random_local_pref(&mut self, params: &ScenarioParams) -> u32 {
+        let (min, max) = params.local_pref_range;
+        if min == max {
+            min
+        } else {
+            self.rng.gen_range(min, max + 1)
+        }
+    }
+
+    /// Generates a iBGP route reflector topology, where all roots are route reflectors, connected
+    /// with peer sessions, and all other internal routers are clients of every route reflector.
+    fn ibgp_route_reflectors(&mut self, roots: &[NodeIdx]) -> &mut Self {
+        // clear the already existing information.
+        self.ibgp_roots.drain();
+        self.ibgp_graph.clear_edges();
+        self.ibgp_roots.extend(roots.iter().cloned());
+
+        // iterate over all internal routers, except the roots
+        for node in self
+            .graph
+            .node_indices()
+            .into_iter()
+            .filter(|x| !self.graph.node_weight(*x).unwrap().external)
+            .filter(|x| !roots.contains(x))
+            // we need to collect because we already borrow self inside the filter.
+            .collect::<Vec<_>>()
+        {
+            for root in roots {
+                self.ibgp_graph.add_edge(*root, node, ());
+            }
+        }
+        self
+    }
+
     /// Generates a iBGP route reflector topology, where the root is the only route reflector, and
     /// all other internal routers are connected to it.
     fn ibgp_single_route_reflector(&mut self, root: NodeIdx) -> &mut Self {
@@ -1398,6 +1791,10 @@ pub enum Scenario {
     /// have a session to both reflectors, and the two reflectors are connected with a peer, and end
     /// with a single Route-Reflector, to which all other routers pair.
     RemoveSecondRouteReflector,
+    /// Scenario, where the (first) route reflector is moved to a different router. The new route
+    /// reflector gets sessions to all clients, the old route reflector becomes a client, and its
+    /// sessions to the other clients are removed.
+    MigrateRouteReflector,
     /// Scenario, where we start with two different connected components, both having connection to
     /// the outside world, and we merge them by adding the links in between.
     NetworkAcquisition,
@@ -1418,17 +1815,110 @@ pub enum Scenario {
     /// Test scenario for verifying transient state conditions. This scenario contains only a single
     /// modifier, which adds an eBGP session.
     VerifyTransientConditionReverse,
+    /// Scenario where Route Origin Validation is incrementally deployed on all border routers,
+    /// rejecting RPKI-invalid routes. Initially, some prefixes have a stale ROA with the wrong
+    /// origin AS, which must be fixed before ROV is enabled, in order to not blackhole them.
+    DeployRov,
+    /// Reverse scenario of the ROV deployment, where ROV is disabled on all border routers, and
+    /// some ROAs become stale.
+    RemoveRov,
+    /// Scenario where the dependency structure of the
+    /// [`ChainGadget`](crate::example_networks::ChainGadget) is embedded into the topology (see
+    /// [`ZooTopology::apply_chain_scenario`]).
+    EmbeddedChain,
+    /// Scenario where the dependency structure of the
+    /// [`CarouselGadget`](crate::example_networks::CarouselGadget) is embedded into the topology
+    /// (see [`ZooTopology::apply_carousel_scenario`]).
+    EmbeddedCarousel,
+    /// Scenario where the IGP is split into OSPF areas (see [`ZooTopology::ospf_areas`]), and all
+    /// links of one area are moved into a new area.
+    RenumberOspfArea,
+    /// Scenario where inbound route maps are rolled out on the border routers for egress
+    /// engineering. Every prefix gets a preferred egress (with a local-pref from
+    /// [`ScenarioParams::local_pref_range`]), and a depreferred backup egress (with local-pref
+    /// 50), such that border routers have multiple route-map entries, matching on the neighbor and
+    /// the prefix.
+    RouteMapRollout,
+    /// Scenario where the network is split into [`ScenarioParams::num_ases`] ASes peering with
+    /// eBGP, which are merged into a single AS, by connecting their route reflectors and removing
+    /// the eBGP sessions in between (see [`ZooTopology::apply_multi_as_scenario`]).
+    MultiAsMerge,
+    /// Reverse scenario of the multi-AS merge, where a single AS is split into multiple ASes.
+    MultiAsSplit,
 }
 
 impl Scenario {
+    /// List of all scenarios
+    pub const ALL: &'static [Scenario] = &[
+        Scenario::FullMesh2RouteReflector,
+        Scenario::RouteReflector2FullMesh,
+        Scenario::DoubleIgpWeight,
+        Scenario::HalveIgpWeight,
+        Scenario::IntroduceSecondRouteReflector,
+        Scenario::RemoveSecondRouteReflector,
+        Scenario::MigrateRouteReflector,
+        Scenario::NetworkAcquisition,
+        Scenario::NetworkSplit,
+        Scenario::DisconnectRouter,
+        Scenario::ConnectRouter,
+        Scenario::DoubleLocalPref,
+        Scenario::HalveLocalPref,
+        Scenario::VerifyTransientCondition,
+        Scenario::VerifyTransientConditionReverse,
+        Scenario::DeployRov,
+        Scenario::RemoveRov,
+        Scenario::EmbeddedChain,
+        Scenario::EmbeddedCarousel,
+        Scenario::RenumberOspfArea,
+        Scenario::RouteMapRollout,
+        Scenario::MultiAsMerge,
+        Scenario::MultiAsSplit,
+    ];
+
+    /// Returns a short description of the scenario.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Scenario::FullMesh2RouteReflector => "Replace the iBGP full mesh by a route reflector",
+            Scenario::RouteReflector2FullMesh => "Replace the route reflector by an iBGP full mesh",
+            Scenario::DoubleIgpWeight => "Double every IGP weight",
+            Scenario::HalveIgpWeight => "Halve every IGP weight",
+            Scenario::IntroduceSecondRouteReflector => "Add a second route reflector as backup",
+            Scenario::RemoveSecondRouteReflector => "Remove the backup route reflector",
+            Scenario::MigrateRouteReflector => "Move the route reflector to a different router",
+            Scenario::NetworkAcquisition => "Merge two connected components by adding links",
+            Scenario::NetworkSplit => "Split the network into two connected components",
+            Scenario::DisconnectRouter => "Set all link weights around a router to infinity",
+            Scenario::ConnectRouter => "Connect a router whose links have infinite weight",
+            Scenario::DoubleLocalPref => "Double every local-pref",
+            Scenario::HalveLocalPref => "Halve every local-pref",
+            Scenario::VerifyTransientCondition => "Add a single eBGP session",
+            Scenario::VerifyTransientConditionReverse => "Remove a single eBGP session",
+            Scenario::DeployRov => "Deploy route origin validation on all border routers",
+            Scenario::RemoveRov => "Disable route origin validation on all border routers",
+            Scenario::EmbeddedChain => "Embed the dependencies of the chain gadget",
+            Scenario::EmbeddedCarousel => "Embed the dependencies of the carousel gadget",
+            Scenario::RenumberOspfArea => "Move all links of an OSPF area into a new area",
+            Scenario::RouteMapRollout => "Roll out local-pref route maps for egress engineering",
+            Scenario::MultiAsMerge => "Merge multiple ASes peering with eBGP into a single AS",
+            Scenario::MultiAsSplit => "Split a single AS into multiple ASes peering with eBGP",
+        }
+    }
+
     fn is_inverse(&self) -> bool {
         match self {
             Scenario::FullMesh2RouteReflector
             | Scenario::DoubleIgpWeight
             | Scenario::IntroduceSecondRouteReflector
+            | Scenario::MigrateRouteReflector
             | Scenario::NetworkAcquisition
             | Scenario::DisconnectRouter
             | Scenario::DoubleLocalPref
+            | Scenario::DeployRov
+            | Scenario::EmbeddedChain
+            | Scenario::EmbeddedCarousel
+            | Scenario::RenumberOspfArea
+            | Scenario::RouteMapRollout
+            | Scenario::MultiAsMerge
             | Scenario::VerifyTransientCondition => false,
             Scenario::RouteReflector2FullMesh
             | Scenario::HalveIgpWeight
@@ -1436,6 +1926,8 @@ impl Scenario {
             | Scenario::NetworkSplit
             | Scenario::ConnectRouter
             | Scenario::HalveLocalPref
+            | Scenario::RemoveRov
+            | Scenario::MultiAsSplit
             | Scenario::VerifyTransientConditionReverse => true,
         }
     }
@@ -1457,6 +1949,7 @@ pub struct NodeData {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::netsim::config::{ConfigExprKey, ConfigModifier};
     #[test]
     fn link_weight() {
         let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
@@ -1611,4 +2104,300 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn scenario_params() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+
+        // the default parameters are equal to the non-parameterized scenario
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let (_, config, _) =
+            t.apply_scenario(Scenario::FullMesh2RouteReflector, false, 100, 1, 1.0).unwrap();
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let params = ScenarioParams::default();
+        let (_, config_params, _) =
+            t.apply_scenario_with_params(Scenario::FullMesh2RouteReflector, &params).unwrap();
+        assert_eq!(config, config_params);
+
+        // multiple route reflectors, and one more as backup
+        for selection in vec![
+            RrSelection::Degree,
+            RrSelection::Closeness,
+            RrSelection::Betweenness,
+            RrSelection::Random,
+        ] {
+            let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+            let params = ScenarioParams::default().num_route_reflectors(3).rr_selection(selection);
+            t.apply_scenario_with_params(Scenario::IntroduceSecondRouteReflector, &params).unwrap();
+            assert_eq!(t.ibgp_roots.len(), 4);
+            let num_internal_nodes =
+                t.graph.node_indices().filter(|x| !t.graph[*x].external).count();
+            assert_eq!(t.ibgp_graph.edge_count(), 4 * (num_internal_nodes - 4));
+        }
+    }
+
+    #[test]
+    fn keep_link_weights() {
+        let filename = format!("{}/test_files/switch.graphml", env!("CARGO_MANIFEST_DIR"));
+        let options = GmlOptions::default()
+            .link_weights(LinkWeights::Bandwidth { reference: 10_000_000_000 });
+        let mut t = ZooTopology::new_with_options(&filename, 42, &options).unwrap();
+        let params = ScenarioParams::default().keep_link_weights(true);
+        let (_, config, _) =
+            t.apply_scenario_with_params(Scenario::FullMesh2RouteReflector, &params).unwrap();
+        let weights = config
+            .iter()
+            .filter_map(|e| match e {
+                IgpLinkWeight { weight, .. } => Some(*weight),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(!weights.is_empty());
+        assert!(weights.iter().all(|w| *w == 10.0 || *w == 1.0));
+    }
+
+    #[test]
+    fn route_map_rollout() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let params = ScenarioParams::default().local_pref_range(150, 300).prefixes(4, 1.0);
+        let (net, config_b, _) =
+            t.apply_scenario_with_params(Scenario::RouteMapRollout, &params).unwrap();
+        let config_a = net.current_config();
+        assert!(config_a.iter().all(|e| !matches!(e, ConfigExpr::BgpRouteMap { .. })));
+        let patch = config_a.get_diff(&config_b);
+        assert!(patch.modifiers.iter().all(|m| matches!(m, ConfigModifier::Insert(_))));
+
+        // every prefix has a preferred and a backup egress
+        let mut preferred: HashMap<Prefix, usize> = HashMap::new();
+        let mut backup: HashMap<Prefix, usize> = HashMap::new();
+        for expr in config_b.iter() {
+            if let ConfigExpr::BgpRouteMap { router, map, .. } = expr {
+                assert!(net.get_device(*router).is_internal());
+                let prefix = match map.conds().iter().find_map(|c| match c {
+                    RouteMapMatch::Prefix(RouteMapMatchClause::Equal(p)) => Some(*p),
+                    _ => None,
+                }) {
+                    Some(p) => p,
+                    None => panic!("Route map without prefix: {:?}", map),
+                };
+                match map.actions()[..] {
+                    [RouteMapSet::LocalPref(Some(BACKUP_LOCAL_PREF))] => {
+                        *backup.entry(prefix).or_default() += 1
+                    }
+                    [RouteMapSet::LocalPref(Some(lp))] if (150..=300).contains(&lp) => {
+                        *preferred.entry(prefix).or_default() += 1
+                    }
+                    _ => panic!("Unexpected actions: {:?}", map.actions()),
+                }
+            }
+        }
+        assert_eq!(preferred.len(), 4);
+        assert!(preferred.values().all(|n| *n == 1));
+        assert_eq!(backup.len(), 4);
+        assert!(backup.values().all(|n| *n == 1));
+    }
+
+    #[test]
+    fn route_reflector_migration() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        for num_rr in 1..=2 {
+            let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+            let params = ScenarioParams::default().num_route_reflectors(num_rr);
+            let (net, config, hard_policy) =
+                t.apply_scenario_with_params(Scenario::MigrateRouteReflector, &params).unwrap();
+            assert_eq!(t.ibgp_roots.len(), num_rr);
+            check_embedded(&net, &config, &hard_policy);
+
+            // new sessions are added, the session between both roots changes, and the sessions
+            // of the old root are removed
+            let patch = net.current_config().get_diff(&config);
+            assert!(patch.modifiers.iter().any(|m| matches!(m, ConfigModifier::Insert(_))));
+            assert!(patch.modifiers.iter().any(|m| matches!(m, ConfigModifier::Remove(_))));
+            assert!(patch.modifiers.iter().any(|m| matches!(m, ConfigModifier::Update { .. })));
+            for modifier in patch.modifiers.iter() {
+                assert!(matches!(modifier.key(), ConfigExprKey::BgpSession { .. }));
+            }
+        }
+    }
+
+    #[test]
+    fn ospf_area_renumbering() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let (net, config, hard_policy) =
+            t.apply_scenario(Scenario::RenumberOspfArea, false, 100, 1, 1.0).unwrap();
+        assert!(net
+            .get_routers()
+            .iter()
+            .all(|r| { net.get_known_prefixes().iter().all(|p| net.get_route(*r, *p).is_ok()) }));
+
+        // only the areas of the links are changed
+        let patch = net.current_config().get_diff(&config);
+        assert!(!patch.modifiers.is_empty());
+        for modifier in patch.modifiers.iter() {
+            match modifier {
+                crate::netsim::config::ConfigModifier::Update {
+                    from: ConfigExpr::OspfArea { area: OspfArea(1), .. },
+                    to: ConfigExpr::OspfArea { .. },
+                } => {}
+                m => panic!("Unexpected modifier: {:?}", m),
+            }
+        }
+        check_embedded(&net, &config, &hard_policy);
+    }
+
+    #[test]
+    fn scenario_params_local_pref_and_peers() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let params = ScenarioParams::default()
+            .local_pref_range(150, 300)
+            .prefixes(4, 1.0)
+            .num_external_peers(2);
+        let (net, config, _) =
+            t.apply_scenario_with_params(Scenario::DoubleLocalPref, &params).unwrap();
+        let mut num_route_maps = 0;
+        for expr in config.iter() {
+            if let ConfigExpr::BgpRouteMap { map, .. } = expr {
+                num_route_maps += 1;
+                for set in map.set.iter() {
+                    if let RouteMapSet::LocalPref(Some(lp)) = set {
+                        assert!(*lp >= 150 && *lp <= 300);
+                    }
+                }
+            }
+        }
+        assert!(num_route_maps > 0);
+
+        // only two external routers advertise prefixes
+        let advertising = net
+            .get_external_routers()
+            .into_iter()
+            .filter(|r| !net.get_device(*r).unwrap_external().advertised_prefixes().is_empty())
+            .count();
+        assert_eq!(advertising, 2);
+        assert_eq!(net.get_known_prefixes().len(), 4);
+    }
+
+    /// Check that both the initial and the final configuration satisfy the hard policy.
+    fn check_embedded(net: &Network, config: &Config, hard_policy: &HardPolicy) {
+        let mut hard_policy = hard_policy.clone();
+        let mut net = net.clone();
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state).unwrap();
+        assert!(hard_policy.check());
+        net.set_config(config).unwrap();
+        let mut fw_state = net.get_forwarding_state();
+        hard_policy.step(&mut net, &mut fw_state).unwrap();
+        assert!(hard_policy.check());
+    }
+
+    #[test]
+    fn embedded_chain() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let (net, config, hard_policy) = t
+            .apply_scenario_with_params(Scenario::EmbeddedChain, &ScenarioParams::default())
+            .unwrap();
+        check_embedded(&net, &config, &hard_policy);
+
+        // every internal router except the two border routers gets a new session
+        let modifiers = net.current_config().get_diff(&config).modifiers;
+        assert_eq!(modifiers.len(), net.get_routers().len() - 2);
+        assert_eq!(net.get_known_prefixes().len(), 1);
+    }
+
+    #[test]
+    fn embedded_carousel() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        match t.apply_scenario_with_params(Scenario::EmbeddedCarousel, &ScenarioParams::default()) {
+            Ok((net, config, hard_policy)) => {
+                check_embedded(&net, &config, &hard_policy);
+                // the reconfiguration removes the two local-pref route maps
+                let modifiers = net.current_config().get_diff(&config).modifiers;
+                assert_eq!(modifiers.len(), 2);
+                assert_eq!(net.get_known_prefixes().len(), 2);
+            }
+            Err(Error::ZooTopologyError(ZooTopologyError::NoGadgetEmbedding(_)))
+            | Err(Error::ZooTopologyError(ZooTopologyError::TooFewBorderRouters)) => {}
+            Err(e) => panic!("Unexpected error: {}", e),
+        }
+    }
+
+    /// Returns the number of eBGP sessions between two internal routers.
+    fn inter_as_sessions(net: &Network, config: &Config) -> usize {
+        config
+            .iter()
+            .filter(|e| match e {
+                BgpSession { source, target, session_type: EBgp } => {
+                    net.get_device(*source).is_internal() && net.get_device(*target).is_internal()
+                }
+                _ => false,
+            })
+            .count()
+    }
+
+    #[test]
+    fn multi_as_merge() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let params = ScenarioParams::default().prefixes(2, 1.0);
+        let (net, config, hard_policy) =
+            t.apply_scenario_with_params(Scenario::MultiAsMerge, &params).unwrap();
+        check_embedded(&net, &config, &hard_policy);
+
+        // the ASes peer with eBGP in the initial configuration, but not in the final one
+        let n = inter_as_sessions(&net, &net.current_config());
+        assert!(n > 0);
+        assert_eq!(inter_as_sessions(&net, &config), 0);
+        // every peering has an outgoing and an incoming route map on both ends
+        let route_maps = net
+            .current_config()
+            .iter()
+            .filter(|e| matches!(e, ConfigExpr::BgpRouteMap { .. }))
+            .count();
+        assert_eq!(route_maps, 4 * n);
+        assert!(config.iter().all(|e| !matches!(e, ConfigExpr::BgpRouteMap { .. })));
+        assert_eq!(net.get_known_prefixes().len(), 2);
+    }
+
+    #[test]
+    fn multi_as_split() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let params = ScenarioParams::default().num_ases(3).peering_links(1);
+        match t.apply_scenario_with_params(Scenario::MultiAsSplit, &params) {
+            Ok((net, config, hard_policy)) => {
+                check_embedded(&net, &config, &hard_policy);
+                assert_eq!(inter_as_sessions(&net, &net.current_config()), 0);
+                // at most one link between every pair of ASes
+                let n = inter_as_sessions(&net, &config);
+                assert!((2..=3).contains(&n));
+            }
+            Err(Error::ZooTopologyError(ZooTopologyError::TooFewBorderRouters))
+            | Err(Error::ZooTopologyError(ZooTopologyError::NoAsPeering)) => {}
+            Err(e) => panic!("Unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn multi_as_chain() {
+        let gml_filename: String = format!("{}/test_files/switch.gml", env!("CARGO_MANIFEST_DIR"));
+        let mut t = ZooTopology::new(&gml_filename, 42).unwrap();
+        let params =
+            ScenarioParams::default().num_ases(3).as_peering(AsPeering::Chain).peering_links(1);
+        match t.apply_scenario_with_params(Scenario::MultiAsMerge, &params) {
+            Ok((net, config, hard_policy)) => {
+                check_embedded(&net, &config, &hard_policy);
+                // the middle AS peers with both other ASes, over a single link each
+                assert_eq!(inter_as_sessions(&net, &net.current_config()), 2);
+                assert_eq!(inter_as_sessions(&net, &config), 0);
+            }
+            Err(Error::ZooTopologyError(ZooTopologyError::TooFewBorderRouters))
+            | Err(Error::ZooTopologyError(ZooTopologyError::NoAsPeering)) => {}
+            Err(e) => panic!("Unexpected error: {}", e),
+        }
+    }
 }
@@ -0,0 +1,251 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Multi-AS scenarios on TopologyZoo graphs
+//!
+//! This module splits the internal routers of arbitrary TopologyZoo graphs into two or three
+//! ASes, which peer with eBGP sessions over the links in between. All internal routers of the
+//! simulated network are in the same AS, so the ASes are emulated:
+//! - The IGP weight of the links between two ASes is larger than any path within an AS. Hence,
+//!   traffic within an AS never crosses a different AS, but the next-hops of routes learned from a
+//!   neighboring AS can still be resolved.
+//! - Every AS has its own route reflector, with all other routers of the AS as clients.
+//! - Routes leaving an AS get the AS number prepended, and routes whose AS path already contains
+//!   the own AS number are rejected (like the AS path loop detection of BGP).
+
+use super::{AsPeering, NodeIdx, ScenarioParams, ZooTopology, ZooTopologyError};
+use crate::hard_policies::HardPolicy;
+use crate::netsim::config::{Config, ConfigExpr::*};
+use crate::netsim::route_map::*;
+use crate::netsim::{AsId, BgpSessionType::*, LinkWeight, Network};
+use crate::Error;
+
+use log::*;
+use petgraph::prelude::*;
+use rand::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// AS number of the first emulated AS. The other ASes are numbered consecutively.
+const FIRST_AS: u32 = 65001;
+
+impl ZooTopology {
+    /// Split the internal routers into `params.num_ases` ASes, which peer with eBGP sessions as
+    /// specified by `params.as_peering` and `params.peering_links`, and merge them into a single AS
+    /// (or split the single AS, if `reverse` is set). Every AS is grown from a random border
+    /// router, such that every AS is connected and has external neighbors. Prefixes are advertised
+    /// from random external routers, as specified by the scenario parameters.
+    ///
+    /// In the merged AS, the route reflectors of all ASes are connected with iBGP peer sessions,
+    /// and the links between the ASes have regular IGP weights. Hence, the reconfiguration adds the
+    /// iBGP sessions between the route reflectors, changes the IGP weights of the links between
+    /// the ASes, and removes the eBGP sessions and route maps between the ASes.
+    ///
+    /// This function is called when calling [`ZooTopology::apply_scenario_with_params`] with the
+    /// scenario [`Scenario::MultiAsMerge`](super::Scenario::MultiAsMerge) or
+    /// [`Scenario::MultiAsSplit`](super::Scenario::MultiAsSplit).
+    pub fn apply_multi_as_scenario(
+        &mut self,
+        mut net: Network,
+        params: &ScenarioParams,
+        reverse: bool,
+    ) -> Result<(Network, Config, HardPolicy), Error> {
+        self.scenario_link_weights(params);
+
+        let ases = self.split_ases(params.num_ases)?;
+        let as_of: HashMap<NodeIdx, usize> = ases
+            .iter()
+            .enumerate()
+            .flat_map(|(i, members)| members.iter().map(move |x| (*x, i)))
+            .collect();
+        let peering_links = self.peering_links(&as_of, params)?;
+
+        // every AS has its own route reflector, which is the router with the most internal links
+        self.ibgp_roots.drain();
+        self.ibgp_graph.clear_edges();
+        for members in ases.iter() {
+            let root = *members.iter().max_by_key(|x| self.internal_degree(**x)).unwrap();
+            debug!(
+                "AS with {} routers and route reflector {}",
+                members.len(),
+                self.graph.node_weight(root).unwrap().name
+            );
+            self.ibgp_roots.insert(root);
+            for x in members.iter().filter(|x| **x != root) {
+                self.ibgp_graph.add_edge(root, *x, ());
+            }
+        }
+
+        // the merged AS connects all route reflectors, and uses the current link weights
+        self.create_ibgp_peers = true;
+        let config_merged = self.get_config()?;
+
+        // in the separate ASes, the IGP must never cross the boundary of an AS
+        let inter_as_weight: LinkWeight =
+            self.graph.edge_weights().filter(|w| w.is_finite()).sum::<LinkWeight>() + 1.0;
+        for e in self.graph.edge_indices().collect::<Vec<_>>() {
+            let (a, b) = self.graph.edge_endpoints(e).unwrap();
+            match (as_of.get(&a), as_of.get(&b)) {
+                (Some(i), Some(j)) if i != j => {
+                    self.graph[e] = if peering_links.contains(&e) {
+                        inter_as_weight
+                    } else {
+                        LinkWeight::INFINITY
+                    };
+                }
+                _ => {}
+            }
+        }
+        self.create_ibgp_peers = false;
+        let mut config_split = self.get_config()?;
+        self.create_ibgp_peers = true;
+        let mut next_order: HashMap<NodeIdx, usize> = HashMap::new();
+        for e in peering_links.iter() {
+            let (a, b) = self.graph.edge_endpoints(*e).unwrap();
+            config_split.add(BgpSession {
+                source: self.rid(a),
+                target: self.rid(b),
+                session_type: EBgp,
+            })?;
+            for (x, neighbor) in [(a, b), (b, a)].iter() {
+                let own_as = AsId(FIRST_AS + as_of[x] as u32);
+                let order = next_order.entry(*x).or_insert(0);
+                *order += 10;
+                config_split.add(BgpRouteMap {
+                    router: self.rid(*x),
+                    direction: RouteMapDirection::Outgoing,
+                    map: RouteMapBuilder::new()
+                        .order(*order)
+                        .allow()
+                        .match_neighbor(self.rid(*neighbor))
+                        .prepend_as_path(own_as, 1)
+                        .build(),
+                })?;
+                config_split.add(BgpRouteMap {
+                    router: self.rid(*x),
+                    direction: RouteMapDirection::Incoming,
+                    map: RouteMapBuilder::new()
+                        .order(*order)
+                        .deny()
+                        .match_neighbor(self.rid(*neighbor))
+                        .match_as_path_contains(own_as)
+                        .build(),
+                })?;
+            }
+        }
+
+        let (config_a, config_b) =
+            if reverse { (config_merged, config_split) } else { (config_split, config_merged) };
+
+        net.set_config(&config_a)?;
+        net.annotate_roles_from_config();
+        self.advertise_prefixes_from_peers(
+            &mut net,
+            params.num_prefixes,
+            params.prefix_probability,
+            params.num_external_peers,
+        )?;
+
+        let hard_policy =
+            HardPolicy::reachability(net.get_routers().iter(), net.get_known_prefixes().iter());
+        Ok((net, config_b, hard_policy))
+    }
+
+    /// Split the internal routers into `num` ASes. Every AS is grown from a randomly chosen border
+    /// router, by alternately adding a neighboring internal router to every AS. Internal routers
+    /// that are not connected to any of the chosen border routers are added to the first AS.
+    fn split_ases(&mut self, num: usize) -> Result<Vec<Vec<NodeIdx>>, ZooTopologyError> {
+        let mut seeds = self.border_routers();
+        if seeds.len() < num {
+            return Err(ZooTopologyError::TooFewBorderRouters);
+        }
+        seeds.shuffle(&mut self.rng);
+        let mut ases: Vec<Vec<NodeIdx>> = seeds.into_iter().take(num).map(|x| vec![x]).collect();
+
+        let mut changed: bool = true;
+        while changed {
+            changed = false;
+            for i in 0..num {
+                let others = ases
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .flat_map(|(_, members)| members.iter().cloned())
+                    .collect::<Vec<_>>();
+                if let Some(next) = self.extend_node(&ases[i], &others) {
+                    ases[i].push(next);
+                    changed = true;
+                }
+            }
+        }
+
+        let assigned: HashSet<NodeIdx> = ases.iter().flatten().cloned().collect();
+        let unassigned = self
+            .internal_routers()
+            .into_iter()
+            .filter(|x| !assigned.contains(x))
+            .collect::<Vec<_>>();
+        ases[0].extend(unassigned);
+        Ok(ases)
+    }
+
+    /// Returns all links over which the ASes peer, as specified by the scenario parameters. Returns
+    /// an error if an AS does not peer with any other AS.
+    fn peering_links(
+        &mut self,
+        as_of: &HashMap<NodeIdx, usize>,
+        params: &ScenarioParams,
+    ) -> Result<Vec<EdgeIndex<u32>>, ZooTopologyError> {
+        // all links between two ASes, grouped by the pair of ASes
+        let mut links: BTreeMap<(usize, usize), Vec<EdgeIndex<u32>>> = BTreeMap::new();
+        for e in self.graph.edge_indices() {
+            let (a, b) = self.graph.edge_endpoints(e).unwrap();
+            if let (Some(i), Some(j)) = (as_of.get(&a), as_of.get(&b)) {
+                if i != j {
+                    links.entry(pair(*i, *j)).or_default().push(e);
+                }
+            }
+        }
+
+        let num = params.num_ases;
+        if params.as_peering == AsPeering::Chain {
+            // the AS in the middle of the chain peers with all other ASes
+            let middle = (0..num)
+                .find(|m| (0..num).filter(|x| x != m).all(|x| links.contains_key(&pair(x, *m))))
+                .ok_or(ZooTopologyError::NoAsPeering)?;
+            links = links.into_iter().filter(|((i, j), _)| *i == middle || *j == middle).collect();
+        }
+        if (0..num).any(|x| !links.keys().any(|(i, j)| *i == x || *j == x)) {
+            return Err(ZooTopologyError::NoAsPeering);
+        }
+
+        let mut result = Vec::new();
+        for (_, mut l) in links.into_iter() {
+            if let Some(max) = params.peering_links {
+                l.shuffle(&mut self.rng);
+                l.truncate(max);
+                l.sort();
+            }
+            result.extend(l);
+        }
+        Ok(result)
+    }
+}
+
+/// Returns the pair of ASes, ordered by their index.
+fn pair(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
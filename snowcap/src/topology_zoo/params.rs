@@ -0,0 +1,192 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Parameters for generating scenarios on a topology
+
+/// Policy for selecting the route reflectors of a generated iBGP topology.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RrSelection {
+    /// Choose the routers with the most IGP links to other internal routers.
+    Degree,
+    /// Choose the routers with the highest closeness centrality (the inverse of the average hop
+    /// count to all other internal routers).
+    Closeness,
+    /// Choose the routers with the highest betweenness centrality (the number of shortest paths
+    /// between other internal routers traversing the router).
+    Betweenness,
+    /// Choose the routers at random.
+    Random,
+}
+
+impl Default for RrSelection {
+    fn default() -> Self {
+        Self::Degree
+    }
+}
+
+/// Peering structure between the ASes of the multi-AS scenarios.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsPeering {
+    /// Every pair of adjacent ASes peers with each other.
+    FullMesh,
+    /// The ASes form a chain, i.e., the AS in the middle peers with both other ASes, which do not
+    /// peer with each other. With only two ASes, this is the same as [`AsPeering::FullMesh`].
+    Chain,
+}
+
+impl Default for AsPeering {
+    fn default() -> Self {
+        Self::FullMesh
+    }
+}
+
+/// # Scenario Parameters
+///
+/// Parameters used by [`ZooTopology::apply_scenario_with_params`] to generate a problem from a
+/// [`Scenario`]. The default parameters generate the same problems as
+/// [`ZooTopology::apply_scenario`] with a single prefix, and without a random root.
+///
+/// [`ZooTopology::apply_scenario_with_params`]: super::ZooTopology::apply_scenario_with_params
+/// [`ZooTopology::apply_scenario`]: super::ZooTopology::apply_scenario
+/// [`Scenario`]: super::Scenario
+///
+/// ```rust
+/// # use snowcap::topology_zoo::{RrSelection, ScenarioParams};
+/// let params = ScenarioParams::default()
+///     .num_route_reflectors(2)
+///     .rr_selection(RrSelection::Closeness)
+///     .prefixes(5, 0.5)
+///     .local_pref_range(150, 300);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioParams {
+    /// Number of route reflectors in the iBGP topologies with route reflectors. Scenarios
+    /// introducing (or removing) a backup route reflector use one route reflector more in the
+    /// topology with backup.
+    pub num_route_reflectors: usize,
+    /// Policy for selecting the route reflectors
+    pub rr_selection: RrSelection,
+    /// Maximum IGP link weight, when randomizing the link weights
+    pub max_weight: u32,
+    /// Keep the link weights imported from the topology file (see [`LinkWeights`]) instead of
+    /// randomizing them. Scenarios changing the link weights still change the imported weights.
+    ///
+    /// [`LinkWeights`]: super::LinkWeights
+    pub keep_link_weights: bool,
+    /// Number of prefixes advertised by the external routers
+    pub num_prefixes: usize,
+    /// Probability that an external peer advertises a prefix
+    pub prefix_probability: f64,
+    /// Number of external peers (randomly chosen) which advertise prefixes. If `None`, all external
+    /// routers of the topology advertise prefixes.
+    pub num_external_peers: Option<usize>,
+    /// Range (inclusive) of local-pref values set by the local-pref scenarios. Every eBGP session
+    /// gets a value chosen uniformly at random from this range.
+    pub local_pref_range: (u32, u32),
+    /// Number of ASes in the multi-AS scenarios (2 or 3)
+    pub num_ases: usize,
+    /// Peering structure between the ASes in the multi-AS scenarios
+    pub as_peering: AsPeering,
+    /// Maximum number of links (randomly chosen) over which two ASes peer in the multi-AS
+    /// scenarios. If `None`, the ASes peer over all links in between.
+    pub peering_links: Option<usize>,
+}
+
+impl Default for ScenarioParams {
+    fn default() -> Self {
+        Self {
+            num_route_reflectors: 1,
+            rr_selection: RrSelection::Degree,
+            max_weight: 100,
+            keep_link_weights: false,
+            num_prefixes: 1,
+            prefix_probability: 1.0,
+            num_external_peers: None,
+            local_pref_range: (200, 200),
+            num_ases: 2,
+            as_peering: AsPeering::FullMesh,
+            peering_links: None,
+        }
+    }
+}
+
+impl ScenarioParams {
+    /// Set the number of route reflectors. The number must be at least 1.
+    pub fn num_route_reflectors(mut self, num: usize) -> Self {
+        assert!(num > 0);
+        self.num_route_reflectors = num;
+        self
+    }
+
+    /// Set the policy for selecting the route reflectors.
+    pub fn rr_selection(mut self, rr_selection: RrSelection) -> Self {
+        self.rr_selection = rr_selection;
+        self
+    }
+
+    /// Set the maximum IGP link weight.
+    pub fn max_weight(mut self, max_weight: u32) -> Self {
+        self.max_weight = max_weight;
+        self
+    }
+
+    /// Keep the link weights imported from the topology file instead of randomizing them.
+    pub fn keep_link_weights(mut self, keep: bool) -> Self {
+        self.keep_link_weights = keep;
+        self
+    }
+
+    /// Set the number of prefixes, and the probability that an external peer advertises a prefix.
+    pub fn prefixes(mut self, num_prefixes: usize, probability: f64) -> Self {
+        self.num_prefixes = num_prefixes;
+        self.prefix_probability = probability;
+        self
+    }
+
+    /// Set the number of external peers which advertise prefixes.
+    pub fn num_external_peers(mut self, num: usize) -> Self {
+        self.num_external_peers = Some(num);
+        self
+    }
+
+    /// Set the range (inclusive) of local-pref values set by the local-pref scenarios.
+    pub fn local_pref_range(mut self, min: u32, max: u32) -> Self {
+        assert!(min <= max);
+        self.local_pref_range = (min, max);
+        self
+    }
+
+    /// Set the number of ASes in the multi-AS scenarios. The number must be 2 or 3.
+    pub fn num_ases(mut self, num: usize) -> Self {
+        assert!((2..=3).contains(&num));
+        self.num_ases = num;
+        self
+    }
+
+    /// Set the peering structure between the ASes in the multi-AS scenarios.
+    pub fn as_peering(mut self, as_peering: AsPeering) -> Self {
+        self.as_peering = as_peering;
+        self
+    }
+
+    /// Set the maximum number of links over which two ASes peer. The number must be at least 1.
+    pub fn peering_links(mut self, num: usize) -> Self {
+        assert!(num > 0);
+        self.peering_links = Some(num);
+        self
+    }
+}
@@ -0,0 +1,506 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Validation report and normalization options of the GML import
+//!
+//! Topology Zoo files often contain constructs that cannot be mapped directly to a network, like
+//! parallel edges, duplicate labels or disconnected components. [`GmlReport`] lists everything
+//! that was dropped or repaired while importing the file, and [`GmlOptions`] selects how such
+//! constructs are handled.
+
+use super::{NodeData, ZooTopologyError};
+use crate::netsim::LinkWeight;
+
+use log::*;
+use petgraph::prelude::*;
+use petgraph::visit::Bfs;
+use std::collections::HashSet;
+use std::fmt;
+
+/// How unsupported GML constructs are handled while importing a topology.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GmlValidation {
+    /// Return [`ZooTopologyError::UnsupportedGml`] if any unsupported construct was found.
+    Fail,
+    /// Repair what can be repaired (renaming nodes, dropping parallel edges and self-loops), and
+    /// keep all disconnected components.
+    Repair,
+    /// Repair like [`GmlValidation::Repair`], and keep only the largest connected component.
+    LargestComponent,
+}
+
+impl Default for GmlValidation {
+    fn default() -> Self {
+        Self::Repair
+    }
+}
+
+/// How parallel edges between the same two nodes are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParallelEdges {
+    /// Keep the first edge, and drop all others.
+    KeepFirst,
+    /// Merge the edges into one with the minimum weight.
+    Min,
+    /// Merge the edges into one with the maximum weight.
+    Max,
+    /// Merge the edges into one with the sum of all weights.
+    Sum,
+}
+
+impl Default for ParallelEdges {
+    fn default() -> Self {
+        Self::KeepFirst
+    }
+}
+
+/// How multiple nodes with the same label are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateLabels {
+    /// Keep all nodes, and append `_N` to the label of the N-th duplicate.
+    Rename,
+    /// Merge all nodes with the same label into the first one. The merged node is internal if
+    /// any of the nodes is internal.
+    Merge,
+}
+
+impl Default for DuplicateLabels {
+    fn default() -> Self {
+        Self::Rename
+    }
+}
+
+/// How the link attributes of the topology file are mapped to IGP link weights. If a link does not
+/// have the required attribute, it keeps the weight given in the file (or weight 1), and this is
+/// listed in the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkWeights {
+    /// Ignore link speed and latency, and use the weight given in the file (or weight 1).
+    Ignore,
+    /// The weight is the reference bandwidth divided by the link speed (`LinkSpeedRaw`), both in
+    /// bits per second, rounded up. This is the default cost of OSPF.
+    Bandwidth {
+        /// Reference bandwidth in bits per second
+        reference: u64,
+    },
+    /// The weight is the latency (`Latency` or `delay`) in milliseconds, multiplied by `per_ms`
+    /// and rounded up.
+    Latency {
+        /// Weight of a single millisecond
+        per_ms: u32,
+    },
+}
+
+impl Default for LinkWeights {
+    fn default() -> Self {
+        Self::Ignore
+    }
+}
+
+/// Attributes of a link read from the topology file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct LinkAttributes {
+    /// Explicit weight of the link
+    pub weight: Option<LinkWeight>,
+    /// Link speed in bits per second
+    pub speed: Option<f64>,
+    /// Latency in milliseconds
+    pub latency: Option<f64>,
+}
+
+impl LinkAttributes {
+    /// Set the attribute with the given name (case insensitive). Unknown attributes are ignored.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), std::num::ParseFloatError> {
+        let value = value.trim().trim_matches('"');
+        match name.to_lowercase().as_str() {
+            "weight" => self.weight = Some(value.parse()?),
+            "linkspeedraw" => self.speed = Some(value.parse()?),
+            "latency" | "delay" => self.latency = Some(value.parse()?),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the weight of the link as specified by the mapping, or `None` if the attribute
+    /// required by the mapping is missing.
+    pub fn weight(&self, mapping: LinkWeights) -> Option<LinkWeight> {
+        match mapping {
+            LinkWeights::Ignore => Some(self.weight.unwrap_or(1.0)),
+            LinkWeights::Bandwidth { reference } => self
+                .speed
+                .filter(|speed| *speed > 0.0)
+                .map(|speed| (reference as f64 / speed).ceil().max(1.0)),
+            LinkWeights::Latency { per_ms } => {
+                self.latency.map(|latency| (latency * per_ms as f64).ceil().max(1.0))
+            }
+        }
+    }
+}
+
+/// Normalization options of the GML import, used in [`ZooTopology::new_with_options`].
+///
+/// [`ZooTopology::new_with_options`]: super::ZooTopology::new_with_options
+///
+/// ```rust
+/// # use snowcap::topology_zoo::{GmlOptions, ParallelEdges};
+/// let options = GmlOptions::default().largest_component().parallel_edges(ParallelEdges::Min);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GmlOptions {
+    /// Handling of invalid constructs and disconnected components
+    pub validation: GmlValidation,
+    /// Handling of parallel edges
+    pub parallel_edges: ParallelEdges,
+    /// Handling of duplicate labels
+    pub duplicate_labels: DuplicateLabels,
+    /// Mapping of link attributes to IGP link weights
+    pub link_weights: LinkWeights,
+}
+
+impl GmlOptions {
+    /// Set the validation mode.
+    pub fn validation(mut self, validation: GmlValidation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Keep only the largest connected component (see [`GmlValidation::LargestComponent`]).
+    pub fn largest_component(self) -> Self {
+        self.validation(GmlValidation::LargestComponent)
+    }
+
+    /// Set how parallel edges are handled.
+    pub fn parallel_edges(mut self, parallel_edges: ParallelEdges) -> Self {
+        self.parallel_edges = parallel_edges;
+        self
+    }
+
+    /// Set how duplicate labels are handled.
+    pub fn duplicate_labels(mut self, duplicate_labels: DuplicateLabels) -> Self {
+        self.duplicate_labels = duplicate_labels;
+        self
+    }
+
+    /// Set how link attributes are mapped to IGP link weights.
+    pub fn link_weights(mut self, link_weights: LinkWeights) -> Self {
+        self.link_weights = link_weights;
+        self
+    }
+}
+
+/// A single unsupported construct found in the GML file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GmlIssue {
+    /// The label was already used by another node, and the node was renamed.
+    DuplicateLabel {
+        /// Label in the GML file
+        label: String,
+        /// New name of the node
+        renamed: String,
+    },
+    /// The label was already used by another node, and the node was merged into it.
+    MergedLabel {
+        /// Label in the GML file
+        label: String,
+        /// Id of the merged node in the GML file
+        id: usize,
+    },
+    /// The node has no label, and it was named after its id.
+    MissingLabel {
+        /// Id of the node in the GML file
+        id: usize,
+        /// New name of the node
+        renamed: String,
+    },
+    /// An edge between the two nodes already exists, and the parallel edge was dropped.
+    ParallelEdge {
+        /// Name of the source node
+        source: String,
+        /// Name of the target node
+        target: String,
+    },
+    /// An edge between the two nodes already exists, and the parallel edge was merged into it.
+    MergedEdge {
+        /// Name of the source node
+        source: String,
+        /// Name of the target node
+        target: String,
+        /// Weight of the merged edge
+        weight: LinkWeight,
+    },
+    /// The link has no attribute required by [`LinkWeights`], and it keeps the weight given in the
+    /// file.
+    MissingLinkAttribute {
+        /// Name of the source node
+        source: String,
+        /// Name of the target node
+        target: String,
+        /// Weight of the link
+        weight: LinkWeight,
+    },
+    /// The edge connects a node to itself, and it was dropped.
+    SelfLoop {
+        /// Name of the node
+        node: String,
+    },
+    /// The nodes are not connected to the largest connected component.
+    DisconnectedComponent {
+        /// Names of all nodes in the component
+        nodes: Vec<String>,
+        /// Wether the component was removed from the topology
+        dropped: bool,
+    },
+}
+
+impl GmlIssue {
+    /// Returns `true` if the construct was dropped from the topology, and `false` if it was
+    /// repaired or kept.
+    pub fn is_dropped(&self) -> bool {
+        match self {
+            Self::DuplicateLabel { .. }
+            | Self::MergedLabel { .. }
+            | Self::MissingLabel { .. }
+            | Self::MergedEdge { .. }
+            | Self::MissingLinkAttribute { .. } => false,
+            Self::ParallelEdge { .. } | Self::SelfLoop { .. } => true,
+            Self::DisconnectedComponent { dropped, .. } => *dropped,
+        }
+    }
+}
+
+impl fmt::Display for GmlIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateLabel { label, renamed } => {
+                write!(f, "Duplicate label {}, renamed to {}", label, renamed)
+            }
+            Self::MergedLabel { label, id } => {
+                write!(f, "Duplicate label {}, merged node {} into it", label, id)
+            }
+            Self::MissingLabel { id, renamed } => {
+                write!(f, "Node {} has no label, named it {}", id, renamed)
+            }
+            Self::ParallelEdge { source, target } => {
+                write!(f, "Dropped parallel edge {} -- {}", source, target)
+            }
+            Self::MergedEdge { source, target, weight } => {
+                write!(f, "Merged parallel edge {} -- {} (weight {})", source, target, weight)
+            }
+            Self::MissingLinkAttribute { source, target, weight } => write!(
+                f,
+                "Link {} -- {} has no speed or latency, kept weight {}",
+                source, target, weight
+            ),
+            Self::SelfLoop { node } => write!(f, "Dropped self-loop at {}", node),
+            Self::DisconnectedComponent { nodes, dropped } => write!(
+                f,
+                "{} disconnected component with {} nodes: {}",
+                if *dropped { "Dropped" } else { "Kept" },
+                nodes.len(),
+                nodes.join(", ")
+            ),
+        }
+    }
+}
+
+/// Report of all unsupported constructs found while importing a GML file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GmlReport {
+    /// All issues, in the order they were found.
+    pub issues: Vec<GmlIssue>,
+}
+
+impl GmlReport {
+    /// Returns `true` if the file contained no unsupported constructs.
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns all issues, where the construct was dropped from the topology.
+    pub fn dropped(&self) -> impl Iterator<Item = &GmlIssue> {
+        self.issues.iter().filter(|i| i.is_dropped())
+    }
+
+    /// Returns all issues, where the construct was repaired or kept.
+    pub fn repaired(&self) -> impl Iterator<Item = &GmlIssue> {
+        self.issues.iter().filter(|i| !i.is_dropped())
+    }
+
+    pub(super) fn push(&mut self, issue: GmlIssue) {
+        warn!("{}", issue);
+        self.issues.push(issue);
+    }
+}
+
+impl fmt::Display for GmlReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No unsupported GML constructs found");
+        }
+        for issue in self.issues.iter() {
+            writeln!(f, "- {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the connectivity of the parsed graph, and apply the validation. All nodes not in the
+/// largest connected component are reported (and removed when using
+/// [`GmlValidation::LargestComponent`]). If the validation is [`GmlValidation::Fail`], and the
+/// report is not empty, an error is returned.
+pub(super) fn validate(
+    graph: Graph<NodeData, LinkWeight, Undirected, u32>,
+    validation: GmlValidation,
+    mut report: GmlReport,
+) -> Result<(Graph<NodeData, LinkWeight, Undirected, u32>, GmlReport), ZooTopologyError> {
+    // compute all connected components, ordered by the smallest node index
+    let mut visited: HashSet<NodeIndex<u32>> = HashSet::new();
+    let mut components: Vec<Vec<NodeIndex<u32>>> = Vec::new();
+    for start in graph.node_indices() {
+        if visited.contains(&start) {
+            continue;
+        }
+        let mut component = Vec::new();
+        let mut bfs = Bfs::new(&graph, start);
+        while let Some(node) = bfs.next(&graph) {
+            visited.insert(node);
+            component.push(node);
+        }
+        component.sort();
+        components.push(component);
+    }
+
+    // the first largest component is kept
+    let largest = components
+        .iter()
+        .enumerate()
+        .max_by_key(|(i, c)| (c.len(), std::cmp::Reverse(*i)))
+        .map(|(i, _)| i);
+    let drop = validation == GmlValidation::LargestComponent;
+    let mut dropped_nodes: HashSet<NodeIndex<u32>> = HashSet::new();
+    for (i, component) in components.into_iter().enumerate() {
+        if Some(i) == largest {
+            continue;
+        }
+        let nodes = component.iter().map(|n| graph[*n].name.clone()).collect();
+        report.push(GmlIssue::DisconnectedComponent { nodes, dropped: drop });
+        if drop {
+            dropped_nodes.extend(component);
+        }
+    }
+
+    if validation == GmlValidation::Fail && !report.is_empty() {
+        return Err(ZooTopologyError::UnsupportedGml(report));
+    }
+
+    // filter_map keeps the order of the remaining nodes
+    let graph = graph.filter_map(
+        |n, data| if dropped_nodes.contains(&n) { None } else { Some(data.clone()) },
+        |_, w| Some(*w),
+    );
+    Ok((graph, report))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ZooTopology;
+    use super::*;
+
+    fn filename() -> String {
+        format!("{}/test_files/unsupported.gml", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn repair() {
+        let t = ZooTopology::new(filename(), 42).unwrap();
+        assert_eq!(
+            t.report().issues,
+            vec![
+                GmlIssue::DuplicateLabel {
+                    label: "Zurich".to_string(),
+                    renamed: "Zurich_1".to_string()
+                },
+                GmlIssue::MissingLabel { id: 3, renamed: "node_3".to_string() },
+                GmlIssue::ParallelEdge { source: "Bern".to_string(), target: "Zurich".to_string() },
+                GmlIssue::SelfLoop { node: "Zurich".to_string() },
+                GmlIssue::DisconnectedComponent {
+                    nodes: vec!["Geneva".to_string(), "Lausanne".to_string()],
+                    dropped: false
+                },
+            ]
+        );
+        assert_eq!(t.report().dropped().count(), 2);
+        assert_eq!(t.graph.node_count(), 7);
+        assert_eq!(t.graph.edge_count(), 5);
+    }
+
+    #[test]
+    fn largest_component() {
+        let mut t =
+            ZooTopology::new_validated(filename(), 42, GmlValidation::LargestComponent).unwrap();
+        assert_eq!(t.report().dropped().count(), 3);
+        assert_eq!(t.graph.node_count(), 5);
+        assert_eq!(t.graph.edge_count(), 4);
+        let names: Vec<&str> = t.graph.raw_nodes().iter().map(|n| n.weight.name.as_str()).collect();
+        assert_eq!(names, vec!["Zurich", "Bern", "Zurich_1", "node_3", "Upstream"]);
+        let net = t.get_net();
+        assert_eq!(net.get_routers().len() + net.get_external_routers().len(), 5);
+        assert!(net.get_router_id("Geneva").is_err());
+    }
+
+    #[test]
+    fn merge() {
+        let options = GmlOptions::default()
+            .parallel_edges(ParallelEdges::Sum)
+            .duplicate_labels(DuplicateLabels::Merge);
+        let t = ZooTopology::new_with_options(filename(), 42, &options).unwrap();
+        assert_eq!(
+            t.report().issues[..4],
+            [
+                GmlIssue::MergedLabel { label: "Zurich".to_string(), id: 2 },
+                GmlIssue::MissingLabel { id: 3, renamed: "node_3".to_string() },
+                GmlIssue::MergedEdge {
+                    source: "Bern".to_string(),
+                    target: "Zurich".to_string(),
+                    weight: 5.0
+                },
+                GmlIssue::MergedEdge {
+                    source: "Bern".to_string(),
+                    target: "Zurich".to_string(),
+                    weight: 6.0
+                },
+            ]
+        );
+        assert_eq!(t.graph.node_count(), 6);
+        assert_eq!(t.graph.edge_count(), 4);
+
+        let options = GmlOptions::default().parallel_edges(ParallelEdges::Min);
+        let t = ZooTopology::new_with_options(filename(), 42, &options).unwrap();
+        let zurich = t.graph.node_indices().find(|n| t.graph[*n].name == "Zurich").unwrap();
+        let bern = t.graph.node_indices().find(|n| t.graph[*n].name == "Bern").unwrap();
+        assert_eq!(t.graph[t.graph.find_edge(zurich, bern).unwrap()], 2.0);
+        assert_eq!(t.report().dropped().count(), 1);
+    }
+
+    #[test]
+    fn fail() {
+        match ZooTopology::new_validated(filename(), 42, GmlValidation::Fail) {
+            Err(ZooTopologyError::UnsupportedGml(report)) => assert_eq!(report.issues.len(), 5),
+            r => panic!("Expected UnsupportedGml, got {:?}", r.map(|_| ())),
+        }
+    }
+}
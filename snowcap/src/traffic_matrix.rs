@@ -0,0 +1,205 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Traffic Matrix
+//!
+//! This module contains the [`TrafficMatrix`], describing how much traffic enters the network at
+//! each internal router, destined to each prefix. The traffic matrix can be imported from
+//! aggregated flow records (e.g., summaries exported from sFlow or NetFlow collectors), stored
+//! either as CSV or as JSON. Each record contains the name of the ingress router, the prefix and
+//! the traffic volume (in any unit, as long as it is the same for all records):
+//!
+//! ```text
+//! router,prefix,volume
+//! Zurich,0,1200.5
+//! Geneva,0,300
+//! ```
+//!
+//! ```text
+//! [{"router": "Zurich", "prefix": 0, "volume": 1200.5}, ...]
+//! ```
+//!
+//! Multiple records for the same router and prefix are summed up. Records for prefixes which are
+//! not known in the network are ignored. Once attached to the network with
+//! [`Network::set_traffic_matrix`], soft policies (like
+//! [`MinimizeTrafficShift`](crate::soft_policies::MinimizeTrafficShift)) weight their cost by the
+//! demand, or route it through the network to compute the load of every link (like
+//! [`MinimizeMaxLinkUtilization`](crate::soft_policies::MinimizeMaxLinkUtilization)).
+
+use crate::netsim::{ForwardingState, Network, NetworkDevice, Prefix, RouterId};
+
+use log::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use thiserror::Error;
+
+/// # Traffic Matrix
+/// Amount of traffic entering the network at each internal router, for each prefix.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrafficMatrix {
+    demands: HashMap<(RouterId, Prefix), f64>,
+}
+
+impl TrafficMatrix {
+    /// Create an empty traffic matrix
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add demand from the router towards the prefix. If there already exists some demand, the
+    /// volume is added to it.
+    pub fn add_demand(&mut self, router: RouterId, prefix: Prefix, volume: f64) {
+        *self.demands.entry((router, prefix)).or_insert(0.0) += volume;
+    }
+
+    /// Returns the demand from the router towards the prefix, or 0 if no demand is known.
+    pub fn get_demand(&self, router: RouterId, prefix: Prefix) -> f64 {
+        self.demands.get(&(router, prefix)).copied().unwrap_or(0.0)
+    }
+
+    /// Returns the sum of all demands.
+    pub fn total_demand(&self) -> f64 {
+        self.demands.values().sum()
+    }
+
+    /// Returns an iterator over all demands, as `((router, prefix), volume)`.
+    pub fn iter(&self) -> impl Iterator<Item = (&(RouterId, Prefix), &f64)> {
+        self.demands.iter()
+    }
+
+    /// Returns the number of router-prefix pairs with known demand.
+    pub fn len(&self) -> usize {
+        self.demands.len()
+    }
+
+    /// Returns `true` if the traffic matrix contains no demand.
+    pub fn is_empty(&self) -> bool {
+        self.demands.is_empty()
+    }
+
+    /// Returns the load of every directed link `(source, target)`, when routing the demand in the
+    /// given forwarding state. If a router has multiple equal-cost next hops, its traffic is split
+    /// equally among them. Traffic which is dropped (due to a black hole or a forwarding loop) does
+    /// not contribute to the load of any link.
+    pub fn link_loads(&self, state: &mut ForwardingState) -> HashMap<(RouterId, RouterId), f64> {
+        let mut loads: HashMap<(RouterId, RouterId), f64> = HashMap::new();
+        for ((router, prefix), volume) in self.demands.iter() {
+            for path in state.get_all_routes(*router, *prefix).into_iter().filter_map(|r| r.ok()) {
+                let mut fraction = *volume;
+                for hop in path.windows(2) {
+                    let num_next_hops = state.get_next_hops(hop[0], *prefix).map(|nh| nh.len());
+                    fraction /= num_next_hops.unwrap_or(1).max(1) as f64;
+                    *loads.entry((hop[0], hop[1])).or_insert(0.0) += fraction;
+                }
+            }
+        }
+        loads
+    }
+
+    /// Build the traffic matrix from aggregated flow records, by mapping the router names and
+    /// prefixes onto the network.
+    pub fn from_records(
+        net: &Network,
+        records: impl IntoIterator<Item = FlowRecord>,
+    ) -> Result<Self, TrafficMatrixError> {
+        let mut tm = Self::new();
+        for record in records {
+            let router = net
+                .get_router_id(&record.router)
+                .map_err(|_| TrafficMatrixError::UnknownRouter(record.router.clone()))?;
+            if !matches!(net.get_device(router), NetworkDevice::InternalRouter(_)) {
+                return Err(TrafficMatrixError::ExternalRouter(record.router));
+            }
+            if !(record.volume >= 0.0 && record.volume.is_finite()) {
+                return Err(TrafficMatrixError::InvalidVolume(record.router, record.volume));
+            }
+            let prefix = Prefix(record.prefix);
+            if !net.get_known_prefixes().contains(&prefix) {
+                warn!("Ignoring flow record for unknown prefix {}", prefix.0);
+                continue;
+            }
+            tm.add_demand(router, prefix, record.volume);
+        }
+        Ok(tm)
+    }
+
+    /// Read the traffic matrix from CSV flow records, with the header `router,prefix,volume`.
+    pub fn from_csv(net: &Network, reader: impl Read) -> Result<Self, TrafficMatrixError> {
+        let records = csv::Reader::from_reader(reader)
+            .deserialize::<FlowRecord>()
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_records(net, records)
+    }
+
+    /// Read the traffic matrix from JSON flow records, stored as a list of objects with the fields
+    /// `router`, `prefix` and `volume`.
+    pub fn from_json(net: &Network, reader: impl Read) -> Result<Self, TrafficMatrixError> {
+        let records: Vec<FlowRecord> = serde_json::from_reader(reader)?;
+        Self::from_records(net, records)
+    }
+
+    /// Read the traffic matrix from a file. If the file ends with `.json`, it is parsed as JSON,
+    /// and as CSV otherwise.
+    pub fn from_file(net: &Network, path: impl AsRef<Path>) -> Result<Self, TrafficMatrixError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        if path.extension().map(|e| e == "json").unwrap_or(false) {
+            Self::from_json(net, file)
+        } else {
+            Self::from_csv(net, file)
+        }
+    }
+}
+
+/// # Flow Record
+/// Aggregated flow record, describing the traffic volume entering the network at a router,
+/// destined to a prefix.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FlowRecord {
+    /// Name of the ingress router
+    pub router: String,
+    /// Destination prefix
+    pub prefix: u32,
+    /// Traffic volume
+    pub volume: f64,
+}
+
+/// Error while importing a traffic matrix
+#[derive(Debug, Error)]
+pub enum TrafficMatrixError {
+    /// Cannot read the file
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// Cannot parse the CSV records
+    #[error("CSV Error: {0}")]
+    CsvError(#[from] csv::Error),
+    /// Cannot parse the JSON records
+    #[error("JSON Error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    /// The router name does not exist in the network
+    #[error("Router {0} does not exist in the network")]
+    UnknownRouter(String),
+    /// Traffic can only enter the network at an internal router
+    #[error("Router {0} is an external router")]
+    ExternalRouter(String),
+    /// The volume must be positive and finite
+    #[error("Invalid traffic volume for router {0}: {1}")]
+    InvalidVolume(String, f64),
+}
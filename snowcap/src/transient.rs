@@ -0,0 +1,159 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Probabilistic Transient Violations
+//!
+//! *This module is only available if the `"transient-violation"` feature is enabled!*
+//!
+//! The hard policies are only checked on the converged states. This module estimates how likely
+//! the network violates reachability while it converges after a single modifier, by simulating the
+//! convergence many times, each time with the messages delivered in a different random order.
+
+use crate::netsim::config::ConfigModifier;
+use crate::netsim::{ForwardingState, Network, NetworkError, Prefix, RouterId};
+use crate::Error;
+
+use log::*;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+/// Estimate the probability, that a router cannot reach a prefix (due to a black hole or a
+/// forwarding loop) in any transient state while the network converges after applying `modifier`.
+/// The convergence is simulated `n_iter` times, each time with the messages delivered in a random
+/// order, chosen by `rng` (but messages between the same two routers are always delivered in
+/// order). Only the transient states are checked, and not the state of `net` itself.
+///
+/// The result contains the violation probability for every internal router and every known
+/// prefix. If the messages cannot be reordered, then this function returns
+/// [`NetworkError::NoEventsToReorder`], and if `n_iter` is zero, it returns
+/// [`Error::NoTransientSamples`].
+///
+/// ```
+/// use snowcap::example_networks::*;
+/// use snowcap::transient::estimate_violation_probability;
+///
+/// let net = SimpleNet::net(0);
+/// let final_config = SimpleNet::final_config(&net, 0);
+/// let modifier = &net.current_config().get_diff(&final_config).modifiers[0];
+///
+/// let mut rng = rand::thread_rng();
+/// if let Ok(probs) = estimate_violation_probability(&net, modifier, 100, &mut rng) {
+///     assert!(probs.values().all(|p| (0.0..=1.0).contains(p)));
+/// }
+/// ```
+pub fn estimate_violation_probability<R: Rng>(
+    net: &Network,
+    modifier: &ConfigModifier,
+    n_iter: usize,
+    rng: &mut R,
+) -> Result<HashMap<(RouterId, Prefix), f64>, Error> {
+    let routers = net.get_routers();
+    let prefixes = net.get_known_prefixes().iter().cloned().collect::<Vec<_>>();
+    let mut num_violations: HashMap<(RouterId, Prefix), usize> =
+        routers.iter().flat_map(|r| prefixes.iter().map(move |p| ((*r, *p), 0))).collect();
+
+    let mut violated: HashSet<(usize, RouterId, Prefix)> = HashSet::new();
+    for_each_transient_state(net, modifier, n_iter, rng, |i, fw_state| {
+        for r in routers.iter() {
+            for p in prefixes.iter() {
+                if fw_state.get_route(*r, *p).is_err() {
+                    violated.insert((i, *r, *p));
+                }
+            }
+        }
+    })?;
+    for (_, r, p) in violated {
+        *num_violations.get_mut(&(r, p)).unwrap() += 1;
+    }
+
+    Ok(num_violations.into_iter().map(|(k, n)| (k, n as f64 / n_iter as f64)).collect())
+}
+
+/// Estimate the probability, that `violated` returns `true` for any transient state while the
+/// network converges after applying `modifier`. The convergence is simulated in the same way as in
+/// [`estimate_violation_probability`], and the same errors are returned. This allows checking
+/// arbitrary conditions on the transient states, like a path condition of a router:
+///
+/// ```
+/// use snowcap::example_networks::*;
+/// use snowcap::hard_policies::{Condition, PolicyError};
+/// use snowcap::netsim::Prefix;
+/// use snowcap::transient::estimate_violation_probability_with;
+///
+/// let net = SimpleNet::net(0);
+/// let final_config = SimpleNet::final_config(&net, 0);
+/// let modifier = &net.current_config().get_diff(&final_config).modifiers[0];
+/// let condition = Condition::Reachable(net.get_router_id("r1").unwrap(), Prefix(0), None);
+///
+/// let mut rng = rand::thread_rng();
+/// let result = estimate_violation_probability_with(&net, modifier, 100, &mut rng, |fw_state| {
+///     condition.check(fw_state).is_err()
+/// });
+/// if let Ok(prob) = result {
+///     assert!((0.0..=1.0).contains(&prob));
+/// }
+/// ```
+pub fn estimate_violation_probability_with<R, F>(
+    net: &Network,
+    modifier: &ConfigModifier,
+    n_iter: usize,
+    rng: &mut R,
+    mut violated: F,
+) -> Result<f64, Error>
+where
+    R: Rng,
+    F: FnMut(&mut ForwardingState) -> bool,
+{
+    let mut violated_samples: HashSet<usize> = HashSet::new();
+    for_each_transient_state(net, modifier, n_iter, rng, |i, fw_state| {
+        if !violated_samples.contains(&i) && violated(fw_state) {
+            violated_samples.insert(i);
+        }
+    })?;
+    Ok(violated_samples.len() as f64 / n_iter as f64)
+}
+
+/// Simulate the convergence after applying `modifier` on a copy of `net` `n_iter` times, and call
+/// `f` with the index of the sample and the forwarding state of every transient state.
+fn for_each_transient_state<R, F>(
+    net: &Network,
+    modifier: &ConfigModifier,
+    n_iter: usize,
+    rng: &mut R,
+    mut f: F,
+) -> Result<(), Error>
+where
+    R: Rng,
+    F: FnMut(usize, &mut ForwardingState),
+{
+    if n_iter == 0 {
+        return Err(Error::NoTransientSamples);
+    }
+
+    let mut net = net.clone();
+    for i in 0..n_iter {
+        trace!("Transient sample {}", i);
+        let has_reordered = net.apply_modifier_random_order(modifier, rng, |net| {
+            f(i, &mut net.get_forwarding_state());
+        })?;
+        if !has_reordered {
+            return Err(NetworkError::NoEventsToReorder.into());
+        }
+        net.undo_action()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+graph [
+  label "Unsupported"
+  node [
+    id 0
+    label "Zurich"
+    Internal 1
+  ]
+  node [
+    id 1
+    label "Bern"
+    Internal 1
+  ]
+  node [
+    id 2
+    label "Zurich"
+    Internal 1
+  ]
+  node [
+    id 3
+    Internal 1
+  ]
+  node [
+    id 4
+    label "Upstream"
+    Internal 0
+  ]
+  node [
+    id 5
+    label "Geneva"
+    Internal 1
+  ]
+  node [
+    id 6
+    label "Lausanne"
+    Internal 1
+  ]
+  edge [
+    source 0
+    target 1
+    weight 2
+  ]
+  edge [
+    source 1
+    target 0
+    weight 3
+  ]
+  edge [
+    source 1
+    target 2
+  ]
+  edge [
+    source 2
+    target 3
+  ]
+  edge [
+    source 3
+    target 4
+  ]
+  edge [
+    source 0
+    target 0
+  ]
+  edge [
+    source 5
+    target 6
+  ]
+]
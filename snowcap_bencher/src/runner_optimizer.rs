@@ -28,7 +28,7 @@ use snowcap::{
     },
     optimizers::*,
     soft_policies::{MinimizeTrafficShift, SoftPolicy},
-    synthesize_parallel, Stopper,
+    synthesize_parallel, ControlHandle,
 };
 
 use console::{style, Term};
@@ -356,7 +356,7 @@ fn worker_runner<O: Optimizer<MinimizeTrafficShift>>(
     let mut result = Vec::new();
 
     let (sender, receiver) = channel::<Run>();
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
     let jobs_todo = Arc::new(Mutex::new(iterations));
     let time_budget = Some(Duration::from_secs(max_time));
 
@@ -399,7 +399,7 @@ fn worker<O: Optimizer<MinimizeTrafficShift>>(
     soft_policy: MinimizeTrafficShift,
     time_budget: Option<Duration>,
     sender: Sender<Run>,
-    mut kill: Stopper,
+    mut kill: ControlHandle,
     jobs_todo: Arc<Mutex<usize>>,
 ) {
     let mut rng = thread_rng();
@@ -459,7 +459,7 @@ fn find_global_optimum(
     let modifiers = net.current_config().get_diff(final_config).modifiers;
     let mut optim =
         GlobalOptimizer::new(net, modifiers, hard_policy, soft_policy, Some(max_time)).unwrap();
-    match optim.work(Stopper::new()) {
+    match optim.work(ControlHandle::new()) {
         Ok((_, cost)) => Some(cost),
         Err(_) => None,
     }
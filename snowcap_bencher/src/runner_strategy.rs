@@ -29,7 +29,7 @@ use snowcap::{
     },
     soft_policies::{compute_cost, MinimizeTrafficShift, SoftPolicy},
     strategies::*,
-    synthesize_parallel, Stopper,
+    synthesize_parallel, ControlHandle,
 };
 
 use console::{style, Term};
@@ -263,7 +263,7 @@ fn worker_runner<S: Strategy, SP: SoftPolicy>(
     let mut result = Vec::new();
 
     let (sender, receiver) = channel::<Run>();
-    let abort = Stopper::new();
+    let abort = ControlHandle::new();
     let jobs_todo = Arc::new(Mutex::new(iterations));
     let time_budget = Some(Duration::from_secs(max_time));
 
@@ -304,7 +304,7 @@ fn worker<S: Strategy, SP: SoftPolicy>(
     hard_policy: HardPolicy,
     time_budget: Option<Duration>,
     sender: Sender<Run>,
-    mut kill: Stopper,
+    mut kill: ControlHandle,
     jobs_todo: Arc<Mutex<usize>>,
 ) {
     let mut rng = thread_rng();
@@ -0,0 +1,238 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Batch Mode
+//!
+//! Run the same scenario and strategy on every GML file of a directory, and aggregate the results
+//! in a single summary.
+
+use super::check_config;
+
+use snowcap::permutators::RandomTreePermutator;
+use snowcap::strategies::{PermutationStrategy, Strategy};
+use snowcap::topology_zoo::{Scenario, ZooTopology};
+use snowcap::{synthesize, ControlHandle};
+
+use log::*;
+use serde::Serialize;
+use std::error::Error;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{spawn, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Options of the batch mode, shared by all topologies.
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Scenario to apply to each topology
+    pub scenario: Scenario,
+    /// Random seed, to get reproducable networks
+    pub seed: u64,
+    /// Use 5 prefixes (distributed with a probability of 0.5) instead of a single one
+    pub many_prefixes: bool,
+    /// Use random roots when generating the configuration
+    pub random_root: bool,
+    /// Use the tree strategy instead of the more complex one
+    pub use_tree: bool,
+    /// Time budget for each topology
+    pub time_budget: Duration,
+}
+
+/// Result of a single topology
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResult {
+    /// Name of the topology (file name without extension)
+    pub topology: String,
+    /// Number of internal routers
+    pub num_routers: usize,
+    /// Number of modifiers of the reconfiguration
+    pub num_modifiers: usize,
+    /// Wether a valid sequence was found
+    pub success: bool,
+    /// Error message, if no sequence was found
+    pub error: Option<String>,
+    /// Time (in seconds) used to synthesize the sequence
+    pub time: f64,
+}
+
+/// Summary of the batch run
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchSummary {
+    /// Name of the scenario
+    pub scenario: String,
+    /// Random seed
+    pub seed: u64,
+    /// Number of topologies for which a sequence was found
+    pub num_success: usize,
+    /// Number of topologies for which no sequence was found
+    pub num_failed: usize,
+    /// Results, sorted by topology name
+    pub results: Vec<BatchResult>,
+}
+
+/// Run the scenario on every GML file in `gml_dir` using `n_threads` parallel workers, and write
+/// the summary to `json_filename` (if given).
+pub fn batch(
+    gml_dir: String,
+    options: BatchOptions,
+    n_threads: Option<usize>,
+    json_filename: Option<String>,
+) -> Result<BatchSummary, Box<dyn Error>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&gml_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|e| e == "gml").unwrap_or(false))
+        .collect();
+    files.sort();
+    let n_files = files.len();
+    info!("Running {:?} on {} topologies", options.scenario, n_files);
+
+    let n_threads = n_threads.unwrap_or_else(num_cpus::get).max(1);
+    let queue = Arc::new(Mutex::new(files));
+    let (sender, receiver) = channel::<BatchResult>();
+
+    let workers: Vec<JoinHandle<_>> = (0..n_threads)
+        .map(|_| {
+            let tx = sender.clone();
+            let q = queue.clone();
+            let o = options.clone();
+            spawn(move || batch_thread(o, q, tx))
+        })
+        .collect();
+    drop(sender);
+
+    let mut results: Vec<BatchResult> = Vec::with_capacity(n_files);
+    for result in receiver.iter() {
+        if result.success {
+            info!("{}: success after {:.3}s", result.topology, result.time);
+        } else {
+            warn!(
+                "{}: {}",
+                result.topology,
+                result.error.as_deref().unwrap_or("failed")
+            );
+        }
+        results.push(result);
+    }
+    workers.into_iter().for_each(|w| w.join().unwrap());
+
+    results.sort_by(|a, b| a.topology.cmp(&b.topology));
+    let num_success = results.iter().filter(|r| r.success).count();
+    let summary = BatchSummary {
+        scenario: format!("{:?}", options.scenario),
+        seed: options.seed,
+        num_success,
+        num_failed: results.len() - num_success,
+        results,
+    };
+
+    if let Some(json_filename) = json_filename {
+        std::fs::write(json_filename, serde_json::to_string_pretty(&summary)?)?;
+    }
+
+    Ok(summary)
+}
+
+/// Worker thread, taking files from the queue until it is empty.
+fn batch_thread(options: BatchOptions, queue: Arc<Mutex<Vec<PathBuf>>>, tx: Sender<BatchResult>) {
+    loop {
+        let file = match queue.lock().unwrap().pop() {
+            Some(file) => file,
+            None => break,
+        };
+        let topology = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // A single topology must not stop the entire batch, even if it panics.
+        let result = catch_unwind(AssertUnwindSafe(|| run_topology(&options, &file)))
+            .unwrap_or_else(|_| Err(String::from("panicked")));
+        let result = match result {
+            Ok(result) => BatchResult { topology, ..result },
+            Err(error) => BatchResult {
+                topology,
+                num_routers: 0,
+                num_modifiers: 0,
+                success: false,
+                error: Some(error),
+                time: 0.0,
+            },
+        };
+        if tx.send(result).is_err() {
+            break;
+        }
+    }
+}
+
+/// Generate the scenario for a single topology, and synthesize the sequence.
+fn run_topology(options: &BatchOptions, file: &Path) -> Result<BatchResult, String> {
+    let mut topo =
+        ZooTopology::new(file.to_string_lossy(), options.seed).map_err(|e| e.to_string())?;
+    let (net, final_config, hard_policy) = topo
+        .apply_scenario(
+            options.scenario.clone(),
+            options.random_root,
+            100,
+            if options.many_prefixes { 5 } else { 1 },
+            if options.many_prefixes { 0.5 } else { 1.0 },
+        )
+        .map_err(|e| e.to_string())?;
+
+    let num_routers = net.get_routers().len();
+    let initial_config = net.current_config().clone();
+    let num_modifiers = initial_config.get_diff(&final_config).modifiers.len();
+    let mut result = BatchResult {
+        topology: String::new(),
+        num_routers,
+        num_modifiers,
+        success: false,
+        error: None,
+        time: 0.0,
+    };
+
+    if let Err(e) = check_config(&net, &final_config) {
+        result.error = Some(e.to_string());
+        return Ok(result);
+    }
+
+    let start = Instant::now();
+    let sequence = if options.use_tree {
+        PermutationStrategy::<RandomTreePermutator>::synthesize(
+            net,
+            final_config,
+            hard_policy,
+            Some(options.time_budget),
+            ControlHandle::new(),
+        )
+    } else {
+        synthesize(
+            net,
+            initial_config,
+            final_config,
+            hard_policy,
+            Some(options.time_budget),
+        )
+    };
+    result.time = start.elapsed().as_secs_f64();
+
+    match sequence {
+        Ok(_) => result.success = true,
+        Err(e) => result.error = Some(e.to_string()),
+    }
+    Ok(result)
+}
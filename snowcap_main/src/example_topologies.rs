@@ -15,7 +15,7 @@
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
-use snowcap::example_networks::{self, repetitions::*, ExampleNetwork};
+use snowcap::example_networks::{self, repetitions::*, ExampleNetwork, VariantRange};
 use snowcap::hard_policies::*;
 use snowcap::netsim::{config::Config, Network};
 use std::error::Error;
@@ -94,6 +94,45 @@ impl fmt::Display for Topology {
     }
 }
 
+impl Topology {
+    /// Returns the range of valid initial and final variants, and whether the number of
+    /// repetitions can be chosen.
+    pub fn variants(&self) -> (VariantRange, VariantRange, bool) {
+        fn info<T: ExampleNetwork>(repetitions: bool) -> (VariantRange, VariantRange, bool) {
+            (T::initial_variants(), T::final_variants(), repetitions)
+        }
+        match self {
+            Topology::AbileneNetwork => info::<example_networks::AbileneNetwork>(false),
+            Topology::BipartiteCarouselFusion => {
+                info::<example_networks::BipartiteCarouselFusion>(true)
+            }
+            Topology::BipartiteGadget => info::<example_networks::BipartiteGadget>(true),
+            Topology::CarouselGadget => info::<example_networks::CarouselGadget>(false),
+            Topology::ChainGadget => info::<example_networks::ChainGadget>(true),
+            Topology::DifficultGadgetComplete => {
+                info::<example_networks::DifficultGadgetComplete>(false)
+            }
+            Topology::DifficultGadgetMinimal => {
+                info::<example_networks::DifficultGadgetMinimal>(false)
+            }
+            Topology::DifficultGadgetRepeated => {
+                info::<example_networks::DifficultGadgetRepeated>(true)
+            }
+            Topology::EvilTwinGadget => info::<example_networks::EvilTwinGadget>(false),
+            Topology::FirewallNet => info::<example_networks::FirewallNet>(false),
+            Topology::MediumNet => info::<example_networks::MediumNet>(false),
+            Topology::VariableAbileneNetwork => {
+                info::<example_networks::VariableAbileneNetwork>(true)
+            }
+            Topology::SimpleNet => info::<example_networks::SimpleNet>(false),
+            Topology::SmallNet => info::<example_networks::SmallNet>(false),
+            Topology::StateSpecificChainGadget => {
+                info::<example_networks::StateSpecificChainGadget>(true)
+            }
+        }
+    }
+}
+
 #[derive(Clap, Debug, Clone, PartialEq, Eq, Copy)]
 pub enum Reps {
     #[clap(name = "1")]
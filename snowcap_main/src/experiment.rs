@@ -0,0 +1,238 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Experiment Files
+//!
+//! An experiment file is a TOML file, which describes the command to run, together with all its
+//! options. The file is translated into the same command-line arguments, which would be passed to
+//! the program directly. Options of the command are stored in the table `options`, and the network
+//! selection (for commands which require one) in the table `network`. Flags are enabled with
+//! `true`, and omitted with `false`.
+//!
+//! ```text
+//! command = "synthesize"
+//!
+//! [options]
+//! use-tree = false
+//! estimate = 100
+//!
+//! [network]
+//! selection = "topology-zoo"
+//! gml-file = "topology_zoo/Abilene.gml"
+//! scenario = "FM2RR"
+//! seed = 42
+//! ```
+//!
+//! Every value can be overwritten on the command line with `--set key=value`, where the key is
+//! either `command`, or prefixed by the table name (e.g., `--set network.seed=7`).
+
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use toml::value::{Table, Value};
+
+/// Positional arguments, in the order in which they are expected by the commands.
+const POSITIONALS: [&str; 5] = ["gml-dir", "gml-file", "spec-file", "scenario", "topology"];
+
+/// Experiment, read from a TOML file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Experiment {
+    table: Table,
+}
+
+impl Experiment {
+    /// Read the experiment from a TOML file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Self::parse(&read_to_string(path)?)
+    }
+
+    /// Parse the experiment from a TOML string.
+    pub fn parse(s: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            table: toml::from_str(s)?,
+        })
+    }
+
+    /// Overwrite a single value, given as `key=value`. The value is parsed as TOML, or taken as a
+    /// string if this fails.
+    pub fn set(&mut self, assignment: &str) -> Result<(), Box<dyn Error>> {
+        let (key, value) = match assignment.find('=') {
+            Some(pos) => (assignment[..pos].trim(), assignment[pos + 1..].trim()),
+            None => {
+                return Err(format!("Invalid override (expected key=value): {}", assignment).into())
+            }
+        };
+        let value = match format!("v = {}", value).parse::<Value>() {
+            Ok(Value::Table(mut t)) => t.remove("v").unwrap(),
+            _ => Value::String(value.to_string()),
+        };
+
+        let mut path: Vec<&str> = key.split('.').collect();
+        let last = path.pop().unwrap();
+        let mut table = &mut self.table;
+        for section in path {
+            table = match table
+                .entry(section.to_string())
+                .or_insert_with(|| Value::Table(Table::new()))
+            {
+                Value::Table(t) => t,
+                _ => return Err(format!("{} is not a table", section).into()),
+            };
+        }
+        table.insert(last.to_string(), value);
+        Ok(())
+    }
+
+    /// Translate the experiment into command-line arguments (including the program name).
+    pub fn args(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut args = vec![String::from("snowcap_main")];
+        match self.table.get("command") {
+            Some(Value::String(command)) => args.push(command.clone()),
+            _ => return Err("The experiment must specify a command".into()),
+        }
+        for key in self.table.keys() {
+            if !["command", "options", "network"].contains(&key.as_str()) {
+                return Err(format!("Unknown key in experiment file: {}", key).into());
+            }
+        }
+        if let Some(options) = self.table.get("options") {
+            args.extend(table_args(options, "options")?);
+        }
+        if let Some(network) = self.table.get("network") {
+            let mut network = match network {
+                Value::Table(t) => t.clone(),
+                _ => return Err("network must be a table".into()),
+            };
+            match network.remove("selection") {
+                Some(Value::String(selection)) => args.push(selection),
+                _ => return Err("The network table must specify the selection".into()),
+            }
+            args.extend(table_args(&Value::Table(network), "network")?);
+        }
+        Ok(args)
+    }
+}
+
+/// Translate a table into command-line arguments. Options come first, followed by the positional
+/// arguments.
+fn table_args(table: &Value, name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let table = match table {
+        Value::Table(t) => t,
+        _ => return Err(format!("{} must be a table", name).into()),
+    };
+    let mut args = Vec::new();
+    let mut positionals = Vec::new();
+    for (key, value) in table.iter() {
+        let key = key.replace('_', "-");
+        if let Some(pos) = POSITIONALS.iter().position(|p| *p == key) {
+            positionals.push((pos, value_str(value)?));
+            continue;
+        }
+        match value {
+            Value::Boolean(true) => args.push(format!("--{}", key)),
+            Value::Boolean(false) => {}
+            Value::Array(values) => {
+                for value in values {
+                    args.push(format!("--{}", key));
+                    args.push(value_str(value)?);
+                }
+            }
+            value => {
+                args.push(format!("--{}", key));
+                args.push(value_str(value)?);
+            }
+        }
+    }
+    positionals.sort();
+    args.extend(positionals.into_iter().map(|(_, v)| v));
+    Ok(args)
+}
+
+/// Format a single value as a command-line argument.
+fn value_str(value: &Value) -> Result<String, Box<dyn Error>> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        v => Err(format!("Unsupported value in experiment file: {}", v).into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXPERIMENT: &str = r#"
+        command = "synthesize"
+
+        [options]
+        use_tree = true
+        explain = false
+        estimate = 100
+
+        [network]
+        selection = "topology-zoo"
+        scenario = "FM2RR"
+        gml-file = "Abilene.gml"
+        seed = 42
+    "#;
+
+    #[test]
+    fn args() {
+        let exp = Experiment::parse(EXPERIMENT).unwrap();
+        assert_eq!(
+            exp.args().unwrap(),
+            vec![
+                "snowcap_main",
+                "synthesize",
+                "--estimate",
+                "100",
+                "--use-tree",
+                "topology-zoo",
+                "--seed",
+                "42",
+                "Abilene.gml",
+                "FM2RR"
+            ]
+        );
+    }
+
+    #[test]
+    fn overrides() {
+        let mut exp = Experiment::parse(EXPERIMENT).unwrap();
+        exp.set("network.seed=7").unwrap();
+        exp.set("options.use_tree = false").unwrap();
+        exp.set("network.scenario=RR2FM").unwrap();
+        assert_eq!(
+            exp.args().unwrap(),
+            vec![
+                "snowcap_main",
+                "synthesize",
+                "--estimate",
+                "100",
+                "topology-zoo",
+                "--seed",
+                "7",
+                "Abilene.gml",
+                "RR2FM"
+            ]
+        );
+        assert!(exp.set("network.seed").is_err());
+        assert!(exp.set("command.foo=1").is_err());
+    }
+}
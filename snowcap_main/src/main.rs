@@ -15,25 +15,45 @@
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
+use snowcap::analysis::estimate_search_space;
+use snowcap::diagnostics::profile_problem;
+use snowcap::failure::{explain_failure, SynthesisFailure};
 use snowcap::hard_policies::*;
-use snowcap::netsim::{config::Config, printer, Network, NetworkError};
+use snowcap::heatmap::ViolationHeatmap;
+use snowcap::netsim::{
+    cli::{modifier_to_cli, CliFlavor},
+    config::{Config, ConfigModifier},
+    printer, Network, NetworkError,
+};
+use snowcap::network_spec::NetworkSpec;
 use snowcap::optimizers::*;
 use snowcap::permutators::*;
+use snowcap::plan::{MigrationPlan, ModifierCostModel, TimeEstimator};
+use snowcap::rationale::explain_sequence;
 use snowcap::soft_policies::*;
 use snowcap::strategies::*;
 use snowcap::topology_zoo::{self, ZooTopology};
-use snowcap::{optimize, synthesize, Stopper};
+use snowcap::traffic_matrix::TrafficMatrix;
+use snowcap::{optimize, synthesize, ControlHandle};
 use snowcap_bencher::*;
-use snowcap_runtime::perform_migration;
+use snowcap_runtime::differential::differential_test;
+use snowcap_runtime::driver::RouterKind;
+use snowcap_runtime::hardware::Inventory;
+use snowcap_runtime::{perform_hardware_migration, perform_migration};
 
-use clap::Clap;
+use clap::{ArgEnum, Clap};
 use log::*;
 use rand::prelude::*;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 
+mod batch;
+use batch::{batch, BatchOptions};
 mod example_topologies;
 use example_topologies::*;
+mod experiment;
+use experiment::Experiment;
 mod transient_violation;
 use transient_violation::*;
 
@@ -41,8 +61,34 @@ fn main() -> Result<(), Box<dyn Error>> {
     // run clap
     let args = CommandLineArguments::parse();
 
+    // read the command from the experiment file, if given
+    let cmd = match (args.experiment, args.cmd) {
+        (Some(filename), None) => {
+            let mut experiment = Experiment::from_file(&filename)?;
+            for assignment in args.overrides.iter() {
+                experiment.set(assignment)?;
+            }
+            let exp_args = experiment.args()?;
+            println!(
+                "Running experiment {}: {}",
+                filename,
+                exp_args[1..].join(" ")
+            );
+            match CommandLineArguments::try_parse_from(exp_args)?.cmd {
+                Some(cmd) => cmd,
+                None => return Err("The experiment file does not contain a command".into()),
+            }
+        }
+        (None, Some(cmd)) if args.overrides.is_empty() => cmd,
+        (None, Some(_)) => return Err("Overrides can only be used with an experiment file".into()),
+        (Some(_), Some(_)) => {
+            return Err("Cannot use both an experiment file and a command".into());
+        }
+        (None, None) => return Err("Either an experiment file or a command is required".into()),
+    };
+
     // match on the action
-    match args.cmd {
+    match cmd {
         MainCommand::TransientViolation {
             gml_file,
             seed,
@@ -54,11 +100,25 @@ fn main() -> Result<(), Box<dyn Error>> {
             transient_violation_topologyzoo(gml_file, seed, n_seeds, n_iter, num_threads, reverse)?
         }
         MainCommand::CustomOperation { n_iter, variant } => transient_violation(n_iter, variant)?,
-        MainCommand::Optimize { network, use_tree } => {
+        MainCommand::Optimize {
+            network,
+            use_tree,
+            dep_groups,
+            patience,
+            tolerance,
+            max_search_time,
+            max_groups,
+            traffic_matrix,
+        } => {
             // initialize the env logger
             pretty_env_logger::init();
             // get the network
-            let (net, final_config, hard_policy) = get_topo(network)?;
+            let (mut net, final_config, hard_policy) = get_topo(network)?;
+            if let Some(filename) = traffic_matrix {
+                let tm = TrafficMatrix::from_file(&net, filename)?;
+                info!("Using traffic matrix with {} demands", tm.len());
+                net.set_traffic_matrix(Some(tm));
+            }
             check_config(&net, &final_config)?;
             let initial_config = net.current_config().clone();
 
@@ -79,8 +139,28 @@ fn main() -> Result<(), Box<dyn Error>> {
                     hard_policy,
                     soft_policy,
                     None,
-                    Stopper::new(),
+                    ControlHandle::new(),
+                )?
+            } else if dep_groups {
+                let mut criteria = StoppingCriteria::default()
+                    .patience(patience)
+                    .relative_tolerance(tolerance);
+                if let Some(secs) = max_search_time {
+                    criteria = criteria.max_search_time(std::time::Duration::from_secs(secs));
+                }
+                if let Some(max) = max_groups {
+                    criteria = criteria.max_groups(max);
+                }
+                let modifiers = initial_config.get_diff(&final_config).modifiers;
+                DepGroupsOptimizer::<_>::with_criteria(
+                    net.clone(),
+                    modifiers,
+                    hard_policy,
+                    soft_policy,
+                    None,
+                    criteria,
                 )?
+                .work(ControlHandle::new())?
             } else {
                 optimize::<MinimizeTrafficShift>(
                     net.clone(),
@@ -101,7 +181,17 @@ fn main() -> Result<(), Box<dyn Error>> {
                     .join("\n    "),
             );
         }
-        MainCommand::Synthesize { network, use_tree } => {
+        MainCommand::Synthesize {
+            network,
+            use_tree,
+            explain,
+            estimate,
+            checkpoint_file,
+            checkpoint_interval,
+            cost_model,
+            emit_cli,
+            cli_dir,
+        } => {
             // initialize the env logger
             pretty_env_logger::init();
             // get the network
@@ -114,34 +204,83 @@ fn main() -> Result<(), Box<dyn Error>> {
                 initial_config.get_diff(&final_config).modifiers.len()
             );
 
+            if let Some(num_probes) = estimate {
+                let estimate = estimate_search_space(
+                    &net,
+                    &initial_config.get_diff(&final_config).modifiers,
+                    &hard_policy,
+                    num_probes,
+                    None,
+                )?;
+                info!(
+                    "Estimated search space: {:.0} states (std. dev. {:.0}), {:.0} solutions",
+                    estimate.expected_states, estimate.std_dev, estimate.expected_solutions
+                );
+            }
+
             // generate the update sequence
             info!("Generating the update sequence");
-            let sequence = if use_tree {
+            let sequence = if let Some(path) = checkpoint_file {
+                synthesize_with_checkpoints(
+                    &net,
+                    &final_config,
+                    &hard_policy,
+                    &path,
+                    std::time::Duration::from_secs(checkpoint_interval),
+                )?
+            } else if use_tree {
                 PermutationStrategy::<RandomTreePermutator>::synthesize(
                     net.clone(),
-                    final_config,
-                    hard_policy,
+                    final_config.clone(),
+                    hard_policy.clone(),
                     None,
-                    Stopper::new(),
-                )?
+                    ControlHandle::new(),
+                )
+                .map_err(|e| explain_on_failure(&net, &final_config, &hard_policy, e))?
             } else {
                 synthesize(
                     net.clone(),
                     initial_config,
-                    final_config,
-                    hard_policy,
+                    final_config.clone(),
+                    hard_policy.clone(),
                     Some(std::time::Duration::from_secs(3600)),
-                )?
+                )
+                .map_err(|e| explain_on_failure(&net, &final_config, &hard_policy, e))?
+            };
+
+            let rationale = if explain {
+                info!("Computing the rationale of the update sequence");
+                Some(explain_sequence(&net, &sequence, &hard_policy)?)
+            } else {
+                None
             };
 
             info!(
                 "Update sequence:\n    {}",
-                sequence
-                    .iter()
-                    .map(|m| printer::config_modifier(&net, m).unwrap())
-                    .collect::<Vec<_>>()
-                    .join("\n    "),
+                printer::plan(&net, &sequence, rationale.as_deref())?.join("\n    "),
+            );
+            log_forwarding_diffs(&net, &sequence)?;
+
+            let mut plan = MigrationPlan::new(&net, &sequence)?;
+            plan.annotate(&[&final_config, net.current_config()]);
+            plan.estimate_time(&TimeEstimator::default(), None);
+            info!(
+                "Estimated execution time: {:.1}s",
+                plan.total_time().unwrap_or_default().as_secs_f64()
             );
+            let cost_model: ModifierCostModel = match cost_model {
+                Some(path) => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+                None => ModifierCostModel::default(),
+            };
+            plan.estimate_application(&cost_model);
+            info!(
+                "Estimated maintenance duration: {:.1} min",
+                plan.total_duration().unwrap_or_default().as_secs_f64() / 60.0
+            );
+
+            if let Some(dialect) = emit_cli {
+                write_cli(&net, &sequence, dialect, &cli_dir)?;
+            }
         }
         MainCommand::Runtime {
             network,
@@ -150,11 +289,27 @@ fn main() -> Result<(), Box<dyn Error>> {
             at_once,
             seed,
             json_filename,
+            failover_drill,
+            bird,
+            cisco,
+            bmp,
+            rollback_on_violation,
+            interactive,
+            inventory,
         } => {
             // initialize the env logger
             pretty_env_logger::init();
             // get the network
             let (net, final_config, hard_policy) = get_topo(network)?;
+            let mut router_kinds = HashMap::new();
+            for name in bird.iter() {
+                router_kinds.insert(net.get_router_id(name)?, RouterKind::Bird);
+            }
+            for name in cisco.iter() {
+                router_kinds.insert(net.get_router_id(name)?, RouterKind::CiscoIosvL2);
+            }
+            let conditions =
+                if failover_drill { hard_policy.prop_vars.clone() } else { Vec::new() };
             check_config(&net, &final_config)?;
             let initial_config = net.current_config().clone();
 
@@ -189,26 +344,205 @@ fn main() -> Result<(), Box<dyn Error>> {
                     .join("\n    "),
             );
 
-            perform_migration(
-                &net,
-                &sequence,
+            if let Some(inventory) = inventory {
+                perform_hardware_migration(&net, &sequence, &Inventory::from_file(inventory)?)?;
+            } else {
+                perform_migration(
+                    &net,
+                    &sequence,
+                    persistent_gns_project,
+                    json_filename,
+                    at_once,
+                    &conditions,
+                    &router_kinds,
+                    bmp,
+                    rollback_on_violation,
+                    interactive,
+                )?;
+            }
+        }
+        MainCommand::Differential {
+            seed,
+            n_networks,
+            num_routers,
+            persistent_gns_project,
+            json_filename,
+        } => {
+            // initialize the env logger
+            pretty_env_logger::init();
+            let reports = differential_test(
+                seed..(seed + n_networks),
+                num_routers,
                 persistent_gns_project,
                 json_filename,
-                at_once,
             )?;
+            let inconsistent = reports
+                .iter()
+                .filter(|r| !r.is_consistent())
+                .map(|r| r.seed)
+                .collect::<Vec<_>>();
+            if inconsistent.is_empty() {
+                info!(
+                    "Simulator and runtime agree on all {} networks",
+                    reports.len()
+                );
+            } else {
+                error!(
+                    "Simulator and runtime disagree for the seeds {:?}",
+                    inconsistent
+                );
+            }
+        }
+        MainCommand::Heatmap {
+            network,
+            n_playouts,
+            seed,
+            router_csv,
+            modifier_csv,
+        } => {
+            // initialize the env logger
+            pretty_env_logger::init();
+            // get the network
+            let (net, final_config, hard_policy) = get_topo(network)?;
+            check_config(&net, &final_config)?;
+            let modifiers = net.current_config().get_diff(&final_config).modifiers;
+
+            info!("Playing out {} random orderings", n_playouts);
+            let heatmap =
+                ViolationHeatmap::compute(&net, &modifiers, &hard_policy, n_playouts, seed)?;
+            info!(
+                "{} of {} orderings violate the hard policy",
+                heatmap.num_violations, heatmap.num_playouts
+            );
+
+            heatmap.write_router_csv(&net, std::fs::File::create(&router_csv)?)?;
+            heatmap.write_modifier_csv(&net, &modifiers, std::fs::File::create(&modifier_csv)?)?;
+            info!("Heatmap written to {} and {}", router_csv, modifier_csv);
+        }
+        MainCommand::Diagnose {
+            network,
+            n_playouts,
+            seed,
+        } => {
+            // initialize the env logger
+            pretty_env_logger::init();
+            // get the network
+            let (net, final_config, hard_policy) = get_topo(network)?;
+            check_config(&net, &final_config)?;
+            let modifiers = net.current_config().get_diff(&final_config).modifiers;
+
+            info!("Profiling {} random orderings", n_playouts);
+            let profile = profile_problem(&net, &modifiers, &hard_policy, n_playouts, seed)?;
+            println!("{}", profile);
+            let recommendations = profile.recommendations();
+            if recommendations.is_empty() {
+                println!("No recommendations, the problem looks well balanced");
+            } else {
+                println!("Recommendations:");
+                for r in recommendations {
+                    println!("  - {}", r);
+                }
+            }
         }
         MainCommand::Bencher { network, args } => {
             let scenario = network.repr();
             let (net, final_config, hard_policy) = get_topo(network)?;
             bench(net, final_config, hard_policy, scenario, args)?;
         }
+        MainCommand::List { what } => list(what)?,
+        MainCommand::Batch {
+            gml_dir,
+            seed,
+            many_prefixes,
+            random_root,
+            use_tree,
+            time_budget,
+            num_threads,
+            json_filename,
+            scenario,
+        } => {
+            // initialize the env logger
+            pretty_env_logger::init();
+            let options = BatchOptions {
+                scenario: scenario.into(),
+                seed,
+                many_prefixes,
+                random_root,
+                use_tree,
+                time_budget: std::time::Duration::from_secs(time_budget),
+            };
+            let summary = batch(gml_dir, options, num_threads, json_filename)?;
+            println!(
+                "Found a sequence for {} of {} topologies",
+                summary.num_success,
+                summary.results.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn list(what: ListSelection) -> Result<(), Box<dyn Error>> {
+    match what {
+        ListSelection::Scenarios => {
+            println!("{:<12} {:<32} Description", "Argument", "Scenario");
+            for name in <Scenario as ArgEnum>::VARIANTS {
+                let scenario = <Scenario as ArgEnum>::from_str(name, false)?;
+                let repr = scenario.to_string();
+                let zoo_scenario: topology_zoo::Scenario = scenario.into();
+                println!("{:<12} {:<32} {}", name, repr, zoo_scenario.description());
+            }
+        }
+        ListSelection::Examples => {
+            println!(
+                "{:<26} {:<9} {:<9} Repetitions",
+                "Topology", "Initial", "Final"
+            );
+            for name in <Topology as ArgEnum>::VARIANTS {
+                let topology = <Topology as ArgEnum>::from_str(name, false)?;
+                let (initial, last, repetitions) = topology.variants();
+                println!(
+                    "{:<26} {:<9} {:<9} {}",
+                    name,
+                    initial.to_string(),
+                    last.to_string(),
+                    if repetitions {
+                        <Reps as ArgEnum>::VARIANTS.join(", ")
+                    } else {
+                        "-".to_string()
+                    }
+                );
+            }
+        }
+        ListSelection::Strategies => {
+            for (kind, algorithms) in &[("Strategies", STRATEGIES), ("Optimizers", OPTIMIZERS)] {
+                println!("{}:", kind);
+                for algorithm in algorithms.iter() {
+                    println!(
+                        "  {}{}: {}",
+                        algorithm.name,
+                        if algorithm.exhaustive {
+                            " (exhaustive)"
+                        } else {
+                            ""
+                        },
+                        algorithm.description
+                    );
+                    for (param, description) in algorithm.parameters {
+                        println!("      <{}>: {}", param, description);
+                    }
+                }
+            }
+        }
     }
     Ok(())
 }
 
 fn get_topo(args: NetworkSelection) -> Result<(Network, Config, HardPolicy), Box<dyn Error>> {
     match args {
-        NetworkSelection::CustomNetwork => custom_scenario(),
+        NetworkSelection::CustomNetwork { spec_file } => {
+            Ok(NetworkSpec::from_file(spec_file)?.build()?)
+        }
         NetworkSelection::TopologyZoo {
             gml_file,
             seed,
@@ -225,10 +559,6 @@ fn get_topo(args: NetworkSelection) -> Result<(Network, Config, HardPolicy), Box
     }
 }
 
-fn custom_scenario() -> Result<(Network, Config, HardPolicy), Box<dyn Error>> {
-    todo!()
-}
-
 fn topology_zoo_scenario(
     gml_file: String,
     seed: u64,
@@ -245,6 +575,167 @@ fn topology_zoo_scenario(
     )?)
 }
 
+/// Synthesize the update sequence with [`StrategyTRTA`], writing a checkpoint to `path` every
+/// `interval`. If `path` already exists, the synthesis continues at the stored checkpoint. Once a
+/// solution is found, the checkpoint file is removed.
+fn synthesize_with_checkpoints(
+    net: &Network,
+    final_config: &Config,
+    hard_policy: &HardPolicy,
+    path: &str,
+    interval: std::time::Duration,
+) -> Result<Vec<ConfigModifier>, Box<dyn Error>> {
+    let modifiers = net.current_config().get_diff(final_config).modifiers;
+    let mut strategy = StrategyTRTA::new(
+        net.clone(),
+        modifiers,
+        hard_policy.clone(),
+        Some(std::time::Duration::from_secs(3600)),
+    )?;
+    if std::path::Path::new(path).exists() {
+        info!("Resuming the synthesis from {}", path);
+        let state: StrategyState = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        strategy.resume(state)?;
+    }
+    loop {
+        // stop the strategy after the interval, in order to write the checkpoint
+        let abort = ControlHandle::new();
+        let timer = abort.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(interval);
+            timer.send_stop();
+        });
+        match strategy.work(abort) {
+            Ok(sequence) => {
+                if std::path::Path::new(path).exists() {
+                    std::fs::remove_file(path)?;
+                }
+                return Ok(sequence);
+            }
+            Err(snowcap::Error::Abort) => {
+                debug!("Writing the checkpoint to {}", path);
+                std::fs::write(path, serde_json::to_string(&strategy.checkpoint())?)?;
+            }
+            Err(e) => {
+                if e.is_timeout() {
+                    std::fs::write(path, serde_json::to_string(&strategy.checkpoint())?)?;
+                }
+                return Err(e.into());
+            }
+        }
+    }
+}
+
+/// If no safe ordering was found, log the explanation of the failure. The error is returned.
+fn explain_on_failure(
+    net: &Network,
+    final_config: &Config,
+    hard_policy: &HardPolicy,
+    error: snowcap::Error,
+) -> snowcap::Error {
+    if matches!(
+        error,
+        snowcap::Error::NoSafeOrdering | snowcap::Error::ProbablyNoSafeOrdering
+    ) {
+        info!("Explaining the failure of the synthesis");
+        let modifiers = net.current_config().get_diff(final_config).modifiers;
+        let time_budget = Some(std::time::Duration::from_secs(60));
+        match explain_failure(net, &modifiers, hard_policy, time_budget) {
+            Ok(Some(failure)) => {
+                error!("{}", failure.repr_with_name(net));
+                log_blocked_diffs(net, &failure);
+            }
+            Ok(None) => warn!("A safe ordering exists, but the strategy did not find it"),
+            Err(e) => warn!("Cannot explain the failure: {}", e),
+        }
+    }
+    error
+}
+
+/// In verbose mode (log level `debug`), log the changes in the forwarding state after each step.
+fn log_forwarding_diffs(net: &Network, sequence: &[ConfigModifier]) -> Result<(), Box<dyn Error>> {
+    if !log_enabled!(Level::Debug) {
+        return Ok(());
+    }
+    let mut net = net.clone();
+    let mut before = net.get_forwarding_state();
+    for (i, modifier) in sequence.iter().enumerate() {
+        net.apply_modifier(modifier)?;
+        let after = net.get_forwarding_state();
+        debug!(
+            "Forwarding changes of step {}: {}\n    {}",
+            i,
+            printer::config_modifier(&net, modifier)?,
+            printer::forwarding_diff(&net, &before, &after)?.join("\n    ")
+        );
+        before = after;
+    }
+    Ok(())
+}
+
+/// In verbose mode (log level `debug`), log the changes in the forwarding state caused by every
+/// blocked modifier of the failure.
+fn log_blocked_diffs(net: &Network, failure: &SynthesisFailure) {
+    if !log_enabled!(Level::Debug) {
+        return;
+    }
+    let mut net = net.clone();
+    if failure
+        .prefix
+        .iter()
+        .any(|m| net.apply_modifier(m).is_err())
+    {
+        return;
+    }
+    let before = net.get_forwarding_state();
+    for blocked in failure.blocked.iter() {
+        let mut probe = net.clone();
+        let name = printer::config_modifier(&net, &blocked.modifier).unwrap_or_default();
+        match probe.apply_modifier(&blocked.modifier) {
+            Ok(()) => {
+                match printer::forwarding_diff(&net, &before, &probe.get_forwarding_state()) {
+                    Ok(diff) => debug!(
+                        "Forwarding changes of {}:\n    {}",
+                        name,
+                        diff.join("\n    ")
+                    ),
+                    Err(e) => warn!("Cannot compute the forwarding changes of {}: {}", name, e),
+                }
+            }
+            Err(e) => debug!("{} does not converge: {}", name, e),
+        }
+    }
+}
+
+/// Write the device commands of every step of the sequence into a separate file in `dir`.
+fn write_cli(
+    net: &Network,
+    sequence: &[ConfigModifier],
+    dialect: CliDialect,
+    dir: &str,
+) -> Result<(), Box<dyn Error>> {
+    let flavor = match dialect {
+        CliDialect::Frr => CliFlavor::Frr,
+        CliDialect::Cisco => CliFlavor::CiscoIos,
+    };
+    std::fs::create_dir_all(dir)?;
+    // each step is translated on the network, on which it is applied.
+    let mut net = net.clone();
+    for (i, modifier) in sequence.iter().enumerate() {
+        let path = std::path::Path::new(dir).join(format!("step_{:03}.txt", i + 1));
+        let header = printer::config_modifier(&net, modifier)?;
+        let cmds = modifier_to_cli(&net, modifier, flavor)?;
+        std::fs::write(&path, format!("! {}\n\n{}", header, cmds))?;
+        net.apply_modifier(modifier)?;
+    }
+    info!(
+        "Device commands of {} steps written to {}",
+        sequence.len(),
+        dir
+    );
+    Ok(())
+}
+
 fn check_config(net: &Network, final_config: &Config) -> Result<(), Box<dyn Error>> {
     match net.clone().set_config(final_config) {
         Ok(()) => Ok(()),
@@ -260,9 +751,15 @@ fn check_config(net: &Network, final_config: &Config) -> Result<(), Box<dyn Erro
 #[derive(Clap, Debug)]
 #[clap(name = "Runtime (Binary)", author = "Tibor Schneider")]
 struct CommandLineArguments {
+    /// Read the action and all its options from a TOML experiment file
+    #[clap(short = 'x', long)]
+    experiment: Option<String>,
+    /// Overwrite a value of the experiment file, as `key=value` (e.g., `network.seed=7`)
+    #[clap(long = "set")]
+    overrides: Vec<String>,
     /// Action to perform
     #[clap(subcommand)]
-    cmd: MainCommand,
+    cmd: Option<MainCommand>,
 }
 
 #[derive(Clap, Debug)]
@@ -273,6 +770,29 @@ enum MainCommand {
         /// Use the tree strategy instead of the more complex one
         #[clap(short = 't', long)]
         use_tree: bool,
+        /// Annotate every step with the earlier steps it depends on
+        #[clap(short = 'e', long)]
+        explain: bool,
+        /// Estimate the size of the search space with the given number of random probes first
+        #[clap(long)]
+        estimate: Option<usize>,
+        /// Periodically store the state of the synthesis in this file, and resume from it if it
+        /// exists. The file is removed once a solution is found.
+        #[clap(long)]
+        checkpoint_file: Option<String>,
+        /// Interval (in seconds) in which the checkpoint is written
+        #[clap(long, default_value = "60")]
+        checkpoint_interval: u64,
+        /// JSON file with the time to apply each kind of modifier, used to estimate the duration
+        /// of the maintenance
+        #[clap(long)]
+        cost_model: Option<String>,
+        /// Write the device commands of every step into a file, using the given dialect
+        #[clap(arg_enum, long)]
+        emit_cli: Option<CliDialect>,
+        /// Directory in which the files with the device commands are written
+        #[clap(long, default_value = "cli")]
+        cli_dir: String,
         /// Type of measurement to perform
         #[clap(subcommand)]
         network: NetworkSelection,
@@ -283,6 +803,25 @@ enum MainCommand {
         /// Use the tree strategy instead of the more complex one
         #[clap(short = 't', long)]
         use_tree: bool,
+        /// Use the DepGroupsOptimizer with the stopping criteria below
+        #[clap(short = 'd', long)]
+        dep_groups: bool,
+        /// Number of valid solutions without improvement, after which the DepGroupsOptimizer stops
+        #[clap(long, default_value = "10")]
+        patience: usize,
+        /// Minimal relative cost reduction for a solution to count as an improvement
+        #[clap(long, default_value = "0")]
+        tolerance: f64,
+        /// Stop the DepGroupsOptimizer after the given number of seconds once a solution is known
+        #[clap(long)]
+        max_search_time: Option<u64>,
+        /// Stop the DepGroupsOptimizer after learning the given number of dependency groups
+        #[clap(long)]
+        max_groups: Option<usize>,
+        /// Weight the traffic shifts by the demand read from a CSV or JSON file with aggregated flow
+        /// records.
+        #[clap(long)]
+        traffic_matrix: Option<String>,
         /// Type of measurement to perform
         #[clap(subcommand)]
         network: NetworkSelection,
@@ -308,6 +847,82 @@ enum MainCommand {
         /// Store the result summary in a json file
         #[clap(long = "json")]
         json_filename: Option<String>,
+        /// After the migration, fail every link on the path of a reliability condition in turn,
+        /// and check that the routers still reach the prefix
+        #[clap(long)]
+        failover_drill: bool,
+        /// Name of a router to run on BIRD instead of FRR (can be repeated)
+        #[clap(long)]
+        bird: Vec<String>,
+        /// Name of a router to run on Cisco IOSvL2 instead of FRR (can be repeated)
+        #[clap(long)]
+        cisco: Vec<String>,
+        /// Monitor the RIBs of all FRR routers via BMP, and store them in the json file
+        #[clap(long)]
+        bmp: bool,
+        /// Stop at the first step which violates an invariant, and roll back all modifiers applied
+        /// so far
+        #[clap(long)]
+        rollback_on_violation: bool,
+        /// Pause before every step, show the expected forwarding changes, and wait for the
+        /// operator to apply the step, skip it, or abort the migration
+        #[clap(long)]
+        interactive: bool,
+        /// Push the migration to the real devices of the inventory (YAML file), instead of
+        /// emulating the network in GNS3. All options concerning GNS3 are ignored.
+        #[clap(long)]
+        inventory: Option<String>,
+    },
+    /// Compare the forwarding state of the simulator with the runtime on random networks
+    #[clap(name = "differential")]
+    Differential {
+        /// Seed of the first random network
+        #[clap(short = 's', long, default_value = "42")]
+        seed: u64,
+        /// Number of random networks to compare
+        #[clap(short = 'n', long, default_value = "10")]
+        n_networks: u64,
+        /// Number of internal routers of each network
+        #[clap(short = 'r', long, default_value = "4")]
+        num_routers: usize,
+        /// Leave the gns3 project open after quitting this project
+        #[clap(short = 'p', long)]
+        persistent_gns_project: bool,
+        /// Store the reports in a json file
+        #[clap(long = "json")]
+        json_filename: Option<String>,
+    },
+    /// Count how often routers and modifiers are involved in violations of random orderings
+    #[clap(name = "heatmap")]
+    Heatmap {
+        /// Type of measurement to perform
+        #[clap(subcommand)]
+        network: NetworkSelection,
+        /// Number of random orderings to play out
+        #[clap(short = 'n', long, default_value = "1000")]
+        n_playouts: usize,
+        /// Seed for the random orderings
+        #[clap(short = 's', long)]
+        seed: Option<u64>,
+        /// CSV file to store the heatmap per router and prefix
+        #[clap(long, default_value = "heatmap_routers.csv")]
+        router_csv: String,
+        /// CSV file to store the heatmap per modifier
+        #[clap(long, default_value = "heatmap_modifiers.csv")]
+        modifier_csv: String,
+    },
+    /// Measure how the time is split between simulation, policy checking and search overhead
+    #[clap(name = "diagnose")]
+    Diagnose {
+        /// Type of measurement to perform
+        #[clap(subcommand)]
+        network: NetworkSelection,
+        /// Number of random orderings to play out
+        #[clap(short = 'n', long, default_value = "10")]
+        n_playouts: usize,
+        /// Seed for the random orderings
+        #[clap(short = 's', long)]
+        seed: Option<u64>,
     },
     /// Run the Bencher
     #[clap(name = "bench")]
@@ -340,6 +955,43 @@ enum MainCommand {
         #[clap(long)]
         num_threads: Option<usize>,
     },
+    /// Run the same scenario on every GML file of a directory, and aggregate the results
+    #[clap(name = "batch")]
+    Batch {
+        /// Directory containing the GML files
+        gml_dir: String,
+        /// Random seed, to get reproducable networks
+        #[clap(short = 's', long, default_value = "42")]
+        seed: u64,
+        /// use many prefixes (i.e., 5 prefixes, distributed with a probability of 0.5)
+        #[clap(short = 'm', long)]
+        many_prefixes: bool,
+        /// Use a random roots when generating configuration
+        #[clap(short = 'r', long)]
+        random_root: bool,
+        /// Use the tree strategy instead of the more complex one
+        #[clap(short = 't', long)]
+        use_tree: bool,
+        /// Time budget for each topology, in seconds
+        #[clap(long, default_value = "600")]
+        time_budget: u64,
+        /// Number of parallel execution units
+        #[clap(long)]
+        num_threads: Option<usize>,
+        /// Store the summary in a json file
+        #[clap(long = "json")]
+        json_filename: Option<String>,
+        /// Select the reconfiguration scenario
+        #[clap(arg_enum)]
+        scenario: Scenario,
+    },
+    /// List the available scenarios, example networks or strategies
+    #[clap(name = "list")]
+    List {
+        /// What to list
+        #[clap(subcommand)]
+        what: ListSelection,
+    },
     /// Custom method
     #[clap(name = "custom")]
     CustomOperation {
@@ -357,9 +1009,12 @@ enum MainCommand {
 /// and perform this order on a network simulated inside GNS3 using FRRouting.
 #[derive(Clap, Debug)]
 enum NetworkSelection {
-    /// Use the custom hard-coded network
+    /// Use a custom network, described in a JSON or YAML file (see `snowcap::network_spec`)
     #[clap(name = "custom")]
-    CustomNetwork,
+    CustomNetwork {
+        /// JSON or YAML file describing the network, the configurations and the hard policy
+        spec_file: String,
+    },
     /// Use the network from Topology Zoo
     #[clap(name = "topology-zoo")]
     TopologyZoo {
@@ -396,11 +1051,38 @@ enum NetworkSelection {
     },
 }
 
+/// Dialect of the device commands
+#[derive(Clap, Debug, Clone, Copy)]
+enum CliDialect {
+    /// FRRouting
+    #[clap(name = "frr")]
+    Frr,
+    /// Cisco IOS
+    #[clap(name = "cisco")]
+    Cisco,
+}
+
+/// Selection of what should be listed
+#[derive(Clap, Debug)]
+enum ListSelection {
+    /// List the reconfiguration scenarios for networks from Topology Zoo
+    #[clap(name = "scenarios")]
+    Scenarios,
+    /// List the example networks, with their initial and final variants
+    #[clap(name = "examples", alias = "networks")]
+    Examples,
+    /// List the strategies and optimizers, with their type arguments
+    #[clap(name = "strategies", alias = "optimizers")]
+    Strategies,
+}
+
 impl NetworkSelection {
     /// Stringify the network
     pub fn repr(&self) -> String {
         match self {
-            NetworkSelection::CustomNetwork => "Custom Network".to_string(),
+            NetworkSelection::CustomNetwork { spec_file } => {
+                format!("Custom Network {}", spec_file)
+            }
             NetworkSelection::TopologyZoo {
                 gml_file,
                 many_prefixes,
@@ -481,6 +1163,10 @@ pub enum Scenario {
     /// with a single Route-Reflector, to which all other routers pair.
     #[clap(name = "del2ndRR")]
     RemoveSecondRouteReflector,
+    /// Scenario, where the route reflector is moved to a different router. The new route
+    /// reflector gets sessions to all clients, and the sessions of the old one are removed.
+    #[clap(name = "MoveRR")]
+    MigrateRouteReflector,
     /// Scenario, where we start with two different connected components, both having connection to
     /// the outside world, and we merge them by adding the links in between.
     #[clap(name = "NetAcq")]
@@ -506,6 +1192,36 @@ pub enum Scenario {
     /// modifier, which adds an eBGP session.
     #[clap(name = "TransientRev")]
     VerifyTransientConditionReverse,
+    /// Scenario, where Route Origin Validation is deployed on all border routers. Initially, some
+    /// prefixes have a stale ROA, which must be fixed before ROV is enabled.
+    #[clap(name = "DeployROV")]
+    DeployRov,
+    /// Reverse scenario of the ROV deployment
+    #[clap(name = "RemoveROV")]
+    RemoveRov,
+    /// Scenario, where the dependencies of the chain gadget are embedded into the topology. The
+    /// routers on the longest shortest path between two border routers form the chain.
+    #[clap(name = "Chain")]
+    EmbeddedChain,
+    /// Scenario, where the dependencies of the carousel gadget are embedded into the topology, with
+    /// the roles of the gadget assigned to suitable routers.
+    #[clap(name = "Carousel")]
+    EmbeddedCarousel,
+    /// Scenario, where the IGP is split into OSPF areas, and all links of one area are moved into a
+    /// new area.
+    #[clap(name = "RenumberArea")]
+    RenumberOspfArea,
+    /// Scenario, where inbound route maps are rolled out on the border routers, setting the
+    /// local-pref of a preferred and a backup egress for every prefix.
+    #[clap(name = "RouteMapRollout")]
+    RouteMapRollout,
+    /// Scenario, where the network is split into multiple ASes peering with eBGP, which are merged
+    /// into a single AS.
+    #[clap(name = "MultiAsMerge")]
+    MultiAsMerge,
+    /// Scenario, where a single AS is split into multiple ASes peering with eBGP.
+    #[clap(name = "MultiAsSplit")]
+    MultiAsSplit,
 }
 
 impl fmt::Display for Scenario {
@@ -535,6 +1251,9 @@ impl fmt::Display for Scenario {
             Scenario::RemoveSecondRouteReflector => {
                 write!(f, "RemoveSecondRouteReflector")
             }
+            Scenario::MigrateRouteReflector => {
+                write!(f, "MigrateRouteReflector")
+            }
             Scenario::NetworkAcquisition => {
                 write!(f, "NetworkAcquisition")
             }
@@ -553,6 +1272,30 @@ impl fmt::Display for Scenario {
             Scenario::VerifyTransientConditionReverse => {
                 write!(f, "VerifyTransientConditionReverse")
             }
+            Scenario::DeployRov => {
+                write!(f, "DeployRov")
+            }
+            Scenario::RemoveRov => {
+                write!(f, "RemoveRov")
+            }
+            Scenario::EmbeddedChain => {
+                write!(f, "EmbeddedChain")
+            }
+            Scenario::EmbeddedCarousel => {
+                write!(f, "EmbeddedCarousel")
+            }
+            Scenario::RenumberOspfArea => {
+                write!(f, "RenumberOspfArea")
+            }
+            Scenario::RouteMapRollout => {
+                write!(f, "RouteMapRollout")
+            }
+            Scenario::MultiAsMerge => {
+                write!(f, "MultiAsMerge")
+            }
+            Scenario::MultiAsSplit => {
+                write!(f, "MultiAsSplit")
+            }
         }
     }
 }
@@ -573,6 +1316,7 @@ impl Into<topology_zoo::Scenario> for Scenario {
             Scenario::RemoveSecondRouteReflector => {
                 topology_zoo::Scenario::RemoveSecondRouteReflector
             }
+            Scenario::MigrateRouteReflector => topology_zoo::Scenario::MigrateRouteReflector,
             Scenario::NetworkAcquisition => topology_zoo::Scenario::NetworkAcquisition,
             Scenario::NetworkSplit => topology_zoo::Scenario::NetworkSplit,
             Scenario::DisconnectRouter => topology_zoo::Scenario::DisconnectRouter,
@@ -581,6 +1325,14 @@ impl Into<topology_zoo::Scenario> for Scenario {
             Scenario::VerifyTransientConditionReverse => {
                 topology_zoo::Scenario::VerifyTransientConditionReverse
             }
+            Scenario::DeployRov => topology_zoo::Scenario::DeployRov,
+            Scenario::RemoveRov => topology_zoo::Scenario::RemoveRov,
+            Scenario::EmbeddedChain => topology_zoo::Scenario::EmbeddedChain,
+            Scenario::EmbeddedCarousel => topology_zoo::Scenario::EmbeddedCarousel,
+            Scenario::RenumberOspfArea => topology_zoo::Scenario::RenumberOspfArea,
+            Scenario::RouteMapRollout => topology_zoo::Scenario::RouteMapRollout,
+            Scenario::MultiAsMerge => topology_zoo::Scenario::MultiAsMerge,
+            Scenario::MultiAsSplit => topology_zoo::Scenario::MultiAsSplit,
         }
     }
 }
@@ -22,8 +22,10 @@ use snowcap::netsim::{
     Network, NetworkError, Prefix,
 };
 use snowcap::topology_zoo::*;
+use snowcap::transient::estimate_violation_probability_with;
 
 use core::ops::AddAssign;
+use rand::prelude::*;
 use std::error::Error;
 use std::sync::mpsc::{channel, Sender};
 use std::sync::{Arc, Mutex};
@@ -260,24 +262,33 @@ fn perform_sequence_check_condition(
 
     let command = modifiers.get(0).unwrap();
 
-    let num_correct = match net.apply_modifier_check_transient(&command, transient_conds, n_iter) {
-        Ok(n) => n,
-        Err(NetworkError::NoEventsToReorder) => return Ok(ConditionResult::NothingToReorder),
-        Err(e) => return Err(e.into()),
-    };
-    let fail_prob = (1.0 - (num_correct as f64 / n_iter as f64)) * 100.0;
+    // The only error, we consider, is a violation of the path condition
+    let mut rng = thread_rng();
+    let fail_prob =
+        match estimate_violation_probability_with(&net, command, n_iter, &mut rng, |fw_state| {
+            transient_conds
+                .iter()
+                .any(|c| matches!(c.check(fw_state), Err(PolicyError::PathCondition { .. })))
+        }) {
+            Ok(p) => p * 100.0,
+            Err(snowcap::Error::NetworkError(NetworkError::NoEventsToReorder)) => {
+                return Ok(ConditionResult::NothingToReorder)
+            }
+            Err(e) => return Err(e.into()),
+        };
+    net.apply_modifier(command)?;
 
     let mut fw_state = net.get_forwarding_state();
     policy.step(&mut net, &mut fw_state)?;
     if policy.check() {
-        if num_correct != n_iter {
+        if fail_prob > 0.0 {
             println!("!!!ERROR!!!");
             Ok(ConditionResult::FalsePositive(fail_prob))
         } else {
             Ok(ConditionResult::TruePositive)
         }
     } else {
-        if num_correct != n_iter {
+        if fail_prob > 0.0 {
             Ok(ConditionResult::TrueNegative(fail_prob))
         } else {
             Ok(ConditionResult::FalseNegative)
@@ -0,0 +1,665 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Utilities for telnet interactions to a BIRD Docker Container
+
+use crate::driver::RouterDriver;
+use crate::frr_conn::{RoutingTable, RoutingTableEntry, RteOrigin};
+use crate::physical_network::{BgpSessionInfo, IpAddr, PhysicalRouter};
+
+use log::*;
+use regex::Regex;
+use telnet::{Telnet, TelnetEvent};
+
+use std::thread::sleep;
+
+use std::error::Error;
+use std::net::Ipv4Addr;
+use std::str;
+use std::time::{Duration, SystemTime};
+
+const CMD_WAIT: u64 = 30;
+
+/// Location of the configuration file of BIRD inside the container
+pub const BIRD_CONFIG_FILE: &str = "/etc/bird.conf";
+
+/// # Connection to BIRD
+///
+/// This struct can be used to communicate with a docker container running BIRD 2 inside GNS3,
+/// using telnet. Interfaces are configured using the shell, while the routing protocols are
+/// configured by writing the entire configuration file (see [`bird_config`]) and reloading it with
+/// `birdc configure`. Hence, after every migration step, the configuration file is regenerated
+/// from the [`PhysicalRouter`], which already contains the new configuration.
+///
+/// The routing table is read from the kernel (using `ip route`), since BIRD exports all selected
+/// routes to the kernel.
+pub struct BirdConnection {
+    c: Telnet,
+    prompt_re: Regex,
+    traceroute_re: Regex,
+    logging: bool,
+}
+
+impl std::fmt::Debug for BirdConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BirdConnection")
+    }
+}
+
+impl BirdConnection {
+    /// create a new connection to a device
+    pub fn new(port: u16) -> Result<Self, Box<dyn Error>> {
+        let prompt_re = Regex::new(r"(?m)^[a-zA-Z0-9_\-.@:~/ ]*# \z").unwrap();
+        let traceroute_re =
+            Regex::new(r"^ ?\d{1,2} +(\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}) +\d+\.\d+ ms$").unwrap();
+
+        let mut c = Telnet::connect(("localhost", port), 2048)?;
+        // receive all initial events
+        while let Ok(event) = c.read_timeout(Duration::from_millis(1)) {
+            if matches!(event, TelnetEvent::TimedOut) {
+                break;
+            }
+        }
+
+        c.write("\n".as_bytes())?;
+
+        let now = SystemTime::now();
+
+        let mut result = String::new();
+        loop {
+            let event = c.read_nonblocking()?;
+            match event {
+                telnet::TelnetEvent::NoData => {
+                    if now.elapsed()? > Duration::from_secs(100) {
+                        error!("BIRD is in an invalid state, or did not boot up! Port: {}", port);
+                        return Err("BIRD is in an invalid state, or did not boot up!".into());
+                    }
+                    sleep(Duration::from_millis(10));
+                }
+                telnet::TelnetEvent::Data(d) => result.push_str(str::from_utf8(&d)?),
+                _ => {}
+            }
+            if prompt_re.is_match(&result) {
+                break;
+            }
+        }
+
+        Ok(Self { c, prompt_re, traceroute_re, logging: false })
+    }
+
+    fn send_wait(&mut self, data: impl AsRef<str>) -> Result<String, Box<dyn Error>> {
+        self.c.write(data.as_ref().as_bytes())?;
+        self.receive_until_prompt(CMD_WAIT)
+    }
+
+    fn send(&mut self, data: impl AsRef<str>) -> Result<(), Box<dyn Error>> {
+        self.c.write(data.as_ref().as_bytes())?;
+        Ok(())
+    }
+
+    fn receive_until_prompt(&mut self, wait_secs: u64) -> Result<String, Box<dyn Error>> {
+        let mut result = String::new();
+        let now = SystemTime::now();
+        loop {
+            let event = self.c.read_nonblocking()?;
+            match event {
+                telnet::TelnetEvent::NoData => {
+                    if now.elapsed()? > Duration::from_secs(wait_secs) {
+                        eprintln!("{}", result);
+                        return Err(format!(
+                            "Took longer than {} second to receive an answer!",
+                            wait_secs
+                        )
+                        .into());
+                    }
+                    sleep(Duration::from_millis(10));
+                }
+                telnet::TelnetEvent::Data(d) => {
+                    result.push_str(str::from_utf8(&d)?);
+                    if self.prompt_re.is_match(&result) {
+                        if self.logging {
+                            eprintln!("{}", result);
+                        }
+                        return Ok(result.replace("\r\n", "\n"));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl RouterDriver for BirdConnection {
+    fn initialize_config(&mut self, router: &PhysicalRouter) -> Result<(), Box<dyn Error>> {
+        if router.dual_stack {
+            return Err("Dual-stack routers are only supported on FRR".into());
+        }
+
+        self.send_wait(format!("hostname {}\n", router.name))?;
+
+        // disable reverse path filtering, and enable forwarding
+        self.send_wait("echo 0 > /proc/sys/net/ipv4/conf/all/rp_filter\n")?;
+        self.send_wait("echo 1 > /proc/sys/net/ipv4/ip_forward\n")?;
+
+        // configure the addresses
+        let mut cmds = vec![format!("ip addr add {}/32 dev lo", router.loopback_addr.addr)];
+        for iface in router.ifaces.iter() {
+            let name = &iface.gns_interface.short_name;
+            cmds.push(format!("echo 0 > /proc/sys/net/ipv4/conf/{}/rp_filter", name));
+            if iface.enabled {
+                cmds.push(format!("ip addr add {} dev {}", iface.iface_addr, name));
+                cmds.push(format!("ip link set {} up", name));
+            }
+        }
+        cmds.extend(write_config_cmds(&bird_config(router)?));
+        // start the daemon, if it is not yet running
+        cmds.push(format!("birdc configure || bird -c {}", BIRD_CONFIG_FILE));
+        self.execute(cmds)
+    }
+
+    fn translate(
+        &self,
+        router: &PhysicalRouter,
+        commands: &[String],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        translate_step(router, commands)
+    }
+
+    fn execute(&mut self, commands: Vec<String>) -> Result<(), Box<dyn Error>> {
+        for cmd in commands {
+            let result = self.send_wait(format!("{}\n", cmd))?;
+            if cmd.starts_with("birdc") && result.contains("error") {
+                return Err(format!("Error while applying configuration: \n{}", result).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn set_interface_state(&mut self, iface: &str, up: bool) -> Result<(), Box<dyn Error>> {
+        self.send_wait(format!("ip link set {} {}\n", iface, if up { "up" } else { "down" }))?;
+        Ok(())
+    }
+
+    fn get_routing_table(&mut self) -> Result<RoutingTable, Box<dyn Error>> {
+        let result = self.send_wait("ip -4 route show\n")?;
+        let lines = result.lines().collect::<Vec<_>>();
+        // skip the command and the prompt
+        let num_lines = lines.len();
+        let table = lines.into_iter().take(num_lines.saturating_sub(1)).skip(1);
+        Ok(RoutingTable { entries: table.filter_map(parse_kernel_route).collect() })
+    }
+
+    fn traceroute(&mut self, target: &IpAddr) -> Result<Option<Vec<[u8; 4]>>, Box<dyn Error>> {
+        if target.is_v6() {
+            return Err("IPv6 traceroute is only supported on FRR".into());
+        }
+        self.send(format!("traceroute -n -w 1 -q 1 {}\n", target.addr))?;
+        let result = self.receive_until_prompt(32)?;
+
+        let mut path = Vec::new();
+        // skip the command and the info line
+        for line in result.lines().skip(2) {
+            if self.prompt_re.is_match(line) {
+                break;
+            }
+            match self.traceroute_re.captures(line) {
+                Some(caps) => path.push(caps[1].parse::<Ipv4Addr>()?.octets()),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(path))
+    }
+}
+
+/// Translate the FRR commands of a single migration step into shell commands. Addresses of
+/// interfaces are changed using `ip addr`, while all other changes are applied by regenerating the
+/// configuration file from the `router` (which already contains the new configuration), and
+/// reloading it.
+pub fn translate_step(
+    router: &PhysicalRouter,
+    commands: &[String],
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result = Vec::new();
+    let mut iface: Option<&str> = None;
+    for cmd in commands.iter().map(|c| c.trim()) {
+        if let Some(name) = cmd.strip_prefix("interface ") {
+            iface = Some(name);
+        } else if cmd == "exit" {
+            iface = None;
+        } else if let (Some(name), Some(addr)) = (iface, cmd.strip_prefix("ip address ")) {
+            result.push(format!("ip addr add {} dev {}", addr, name));
+            result.push(format!("ip link set {} up", name));
+        } else if let (Some(name), Some(addr)) = (iface, cmd.strip_prefix("no ip address ")) {
+            result.push(format!("ip addr del {} dev {}", addr, name));
+        }
+    }
+    result.extend(write_config_cmds(&bird_config(router)?));
+    result.push(String::from("birdc configure"));
+    Ok(result)
+}
+
+/// Generate the BIRD configuration file of the router, equivalent to the FRR configuration. Route
+/// maps are translated into one filter per BGP session, where `match peer` is evaluated while
+/// generating the filter. Routes not matched by any route map are accepted.
+///
+/// Returns an error if the router is a dual-stack router, or if a route map cannot be expressed.
+pub fn bird_config(router: &PhysicalRouter) -> Result<String, Box<dyn Error>> {
+    if router.dual_stack {
+        return Err("Dual-stack routers are only supported on FRR".into());
+    }
+
+    let lo = &router.loopback_addr.addr;
+    let mut c = vec![
+        format!("# {}", router.name),
+        format!("router id {};", lo),
+        String::from("protocol device {}"),
+        String::from("protocol direct { ipv4; interface \"*\"; }"),
+        String::from("protocol kernel {"),
+        String::from("  ipv4 { export where source != RTS_DEVICE && proto != \"advertise\"; };"),
+        String::from("}"),
+    ];
+
+    // the advertised network is originated, but not written into the kernel.
+    if let Some(network) = router.advertise_route.as_ref() {
+        c.push(format!(
+            "protocol static advertise {{ ipv4; route {} unreachable; }}",
+            network.get_network()
+        ));
+    }
+
+    if !router.static_routes.is_empty() {
+        c.push(String::from("protocol static {"));
+        c.push(String::from("  ipv4;"));
+        for sr in router.static_routes.iter() {
+            match sr.next_hop.parse::<Ipv4Addr>() {
+                Ok(addr) => c.push(format!("  route {} via {};", sr.addr, addr)),
+                Err(_) => c.push(format!("  route {} via \"{}\";", sr.addr, sr.next_hop)),
+            }
+        }
+        c.push(String::from("}"));
+    }
+
+    // OSPF, redistributing all connected networks
+    if router.ifaces.iter().any(|i| i.cost.is_some()) {
+        c.push(String::from("protocol ospf v2 {"));
+        c.push(String::from("  ipv4 { import all; export where source = RTS_DEVICE; };"));
+        c.push(String::from("  area 0 {"));
+        for iface in router.ifaces.iter().filter(|i| i.enabled) {
+            if let Some(cost) = iface.cost {
+                c.push(format!(
+                    "    interface \"{}\" {{ cost {}; }};",
+                    iface.gns_interface.short_name, cost
+                ));
+            }
+        }
+        c.push(String::from("  };"));
+        c.push(String::from("}"));
+    }
+
+    for session in router.bgp_sessions.iter() {
+        let source = if session.internal_session {
+            lo.clone()
+        } else {
+            router
+                .ifaces
+                .iter()
+                .find(|i| i.neighbor == session.neighbor)
+                .map(|i| i.iface_addr.addr.clone())
+                .ok_or("No direct connection with the neighbor")?
+        };
+        c.push(String::from("protocol bgp {"));
+        c.push(format!("  local {} as {};", source, router.as_id.0));
+        c.push(format!(
+            "  neighbor {} as {};",
+            session.neighbor_addr.addr, session.neighbor_as_id.0
+        ));
+        c.push(String::from("  deterministic med on;"));
+        if router.always_compare_med {
+            c.push(String::from("  med metric on;"));
+        }
+        if session.is_rr_client {
+            c.push(String::from("  rr client;"));
+        }
+        if let Some(cluster_id) = router.cluster_id.as_ref() {
+            c.push(format!("  rr cluster id {};", cluster_id));
+        }
+        c.push(String::from("  ipv4 {"));
+        c.push(format!("    import filter {{ {} }};", bgp_filter(router, session, "in")?));
+        c.push(format!("    export filter {{ {} }};", bgp_filter(router, session, "out")?));
+        c.push(String::from("  };"));
+        c.push(String::from("}"));
+    }
+
+    Ok(c.join("\n"))
+}
+
+/// Generate the body of the filter of a BGP session in the given direction (`in` or `out`).
+fn bgp_filter(
+    router: &PhysicalRouter,
+    session: &BgpSessionInfo,
+    direction: &str,
+) -> Result<String, Box<dyn Error>> {
+    let mut stmts = Vec::new();
+    if direction == "out" {
+        // only export BGP routes and the advertised network
+        stmts.push(String::from("if !(source = RTS_BGP || proto = \"advertise\") then reject;"));
+    }
+
+    let mut route_maps =
+        router.route_maps.iter().filter(|rm| rm.direction == direction).collect::<Vec<_>>();
+    route_maps.sort_by_key(|rm| rm.order);
+    for rm in route_maps {
+        // evaluate the match statements
+        let mut matches = true;
+        for (key, value) in rm.match_statements.iter() {
+            match *key {
+                "peer" => {
+                    matches &= value.split('/').next() == Some(session.neighbor_addr.addr.as_str())
+                }
+                _ => return Err(format!("BIRD does not support `match {} {}`", key, value).into()),
+            }
+        }
+        if !matches {
+            continue;
+        }
+        if rm.state == "deny" {
+            stmts.push(String::from("reject;"));
+            return Ok(stmts.join(" "));
+        }
+        let mut sets = rm.set_statements.iter().collect::<Vec<_>>();
+        sets.sort();
+        for (key, value) in sets {
+            stmts.extend(bird_set(key, value)?);
+        }
+        break;
+    }
+    stmts.push(String::from("accept;"));
+    Ok(stmts.join(" "))
+}
+
+/// Translate a set statement of a route map into BIRD statements.
+fn bird_set(key: &str, value: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let community = |c: &str| -> Result<String, Box<dyn Error>> {
+        let c: u32 = c.parse()?;
+        Ok(format!("bgp_community.add(({}, {}));", c >> 16, c & 0xffff))
+    };
+    Ok(match (key, value) {
+        // adding zero does not change the attribute
+        (_, "+0") => vec![],
+        ("ip next-hop", addr) => vec![format!("bgp_next_hop = {};", addr)],
+        ("local-preference", lp) => vec![format!("bgp_local_pref = {};", lp)],
+        ("metric", med) => vec![format!("bgp_med = {};", med)],
+        ("community", "none") => vec![String::from("bgp_community = -empty-;")],
+        ("community", c) if c.ends_with(" additive") => c
+            .split_whitespace()
+            .filter(|c| *c != "additive")
+            .map(community)
+            .collect::<Result<_, _>>()?,
+        ("community", c) => vec![String::from("bgp_community = -empty-;"), community(c)?],
+        ("as-path prepend", path) => {
+            path.split_whitespace().map(|a| format!("bgp_path.prepend({});", a)).collect()
+        }
+        (key, value) => return Err(format!("BIRD does not support `set {} {}`", key, value).into()),
+    })
+}
+
+/// Shell commands to write the configuration file.
+fn write_config_cmds(config: &str) -> Vec<String> {
+    std::iter::once(format!("echo '' > {}", BIRD_CONFIG_FILE))
+        .chain(config.lines().map(|l| format!("echo '{}' >> {}", l, BIRD_CONFIG_FILE)))
+        .collect()
+}
+
+/// Parse a single line of the output of `ip route show`. Connected routes are written by the
+/// kernel, static routes have the protocol `static`, and all other routes are learned by BIRD. As
+/// the kernel does not know whether BIRD learned the route from OSPF or BGP, these routes have the
+/// origin [`RteOrigin::Table`]. Returns `None` if the line cannot be parsed.
+pub fn parse_kernel_route(line: &str) -> Option<RoutingTableEntry> {
+    let mut tokens = line.split_whitespace().peekable();
+    let rejected = matches!(tokens.peek(), Some(&"unreachable") | Some(&"blackhole"));
+    if rejected {
+        tokens.next();
+    }
+    let network = match tokens.next()? {
+        "default" => IpAddr::new("0.0.0.0", 0),
+        n if n.contains('/') => IpAddr::try_from_str(n).ok()?,
+        n => IpAddr::new(n, 32),
+    };
+
+    let mut entry = RoutingTableEntry {
+        origin: RteOrigin::Table,
+        instance_id: 0,
+        selected: true,
+        fib_route: !rejected,
+        queued: false,
+        rejected,
+        network,
+        administrative_distance: 0,
+        metric: 0,
+        next_hop_addr: None,
+        next_hop_iface: String::new(),
+        learned_from: None,
+    };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "via" => entry.next_hop_addr = Some(tokens.next()?.to_string()),
+            "dev" => entry.next_hop_iface = tokens.next()?.to_string(),
+            "metric" => entry.metric = tokens.next()?.parse().ok()?,
+            "proto" => match tokens.next()? {
+                "kernel" => entry.origin = RteOrigin::Connected,
+                "static" => entry.origin = RteOrigin::Static,
+                _ => {}
+            },
+            "scope" | "src" | "table" | "pref" => {
+                tokens.next();
+            }
+            // flags without a value
+            _ => {}
+        }
+    }
+
+    Some(entry)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::driver::RouterKind;
+    use crate::physical_network::{IfaceInfo, RouteMapInfo, StaticRouteInfo};
+    use gns3::*;
+    use snowcap::netsim::AsId;
+
+    fn iface(neighbor: u32, addr: &str, name: &str, cost: Option<u32>) -> IfaceInfo {
+        IfaceInfo {
+            neighbor: neighbor.into(),
+            neighbor_addr: IpAddr::new(format!("10.{}.0.2", neighbor), 24),
+            iface_addr: IpAddr::new(addr, 24),
+            gns_interface: GNS3Interface {
+                adapter_number: neighbor - 1,
+                port_number: 0,
+                name: name.to_string(),
+                short_name: name.to_string(),
+                link_type: "ethernet".to_string(),
+            },
+            enabled: true,
+            cost,
+            link_id: 0,
+        }
+    }
+
+    fn test_router() -> PhysicalRouter {
+        PhysicalRouter {
+            router_id: 0.into(),
+            name: String::from("node"),
+            kind: RouterKind::Bird,
+            gns_node: GNS3Node {
+                id: String::new(),
+                name: String::from("node"),
+                node_type: String::from("docker"),
+                port: 5000,
+                status: GNS3NodeStatus::Stopped,
+                interfaces: vec![],
+            },
+            loopback_addr: IpAddr::new("10.0.0.1", 24),
+            as_id: AsId(65001),
+            is_internal: true,
+            ifaces: vec![
+                iface(1, "10.1.0.1", "eth0", Some(10)),
+                iface(2, "10.2.0.1", "eth1", None),
+            ],
+            bgp_sessions: vec![
+                BgpSessionInfo {
+                    neighbor: 1.into(),
+                    neighbor_addr: IpAddr::new("10.0.0.2", 24),
+                    neighbor_as_id: AsId(65001),
+                    is_rr_client: true,
+                    internal_session: true,
+                },
+                BgpSessionInfo {
+                    neighbor: 2.into(),
+                    neighbor_addr: IpAddr::new("10.2.0.2", 24),
+                    neighbor_as_id: AsId(65002),
+                    is_rr_client: false,
+                    internal_session: false,
+                },
+            ],
+            route_maps: vec![RouteMapInfo {
+                name: String::from("rm-in"),
+                state: "permit",
+                order: 10,
+                direction: "in",
+                match_statements: vec![("peer", String::from("10.2.0.2/24"))].into_iter().collect(),
+                set_statements: vec![("local-preference", String::from("200"))]
+                    .into_iter()
+                    .collect(),
+                community_lists: Vec::new(),
+            }],
+            static_routes: vec![StaticRouteInfo {
+                addr: IpAddr::new("99.0.1.0", 24),
+                next_hop: String::from("10.2.0.2"),
+            }],
+            advertise_route: Some(IpAddr::new("10.0.0.0", 24)),
+            advertise_route_v6: None,
+            dual_stack: false,
+            always_compare_med: false,
+            cluster_id: None,
+            bmp: None,
+        }
+    }
+
+    #[test]
+    fn config() {
+        let config = bird_config(&test_router()).unwrap();
+        let lines = config.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"router id 10.0.0.1;"));
+        assert!(
+            lines.contains(&"protocol static advertise { ipv4; route 10.0.0.0/24 unreachable; }")
+        );
+        assert!(lines.contains(&"  route 99.0.1.0/24 via 10.2.0.2;"));
+        assert!(lines.contains(&"    interface \"eth0\" { cost 10; };"));
+        assert!(!config.contains("interface \"eth1\""));
+        assert!(lines.contains(&"  local 10.0.0.1 as 65001;"));
+        assert!(lines.contains(&"  neighbor 10.0.0.2 as 65001;"));
+        assert!(lines.contains(&"  rr client;"));
+        assert!(lines.contains(&"  local 10.2.0.1 as 65001;"));
+        assert!(lines.contains(&"  neighbor 10.2.0.2 as 65002;"));
+        assert_eq!(lines.iter().filter(|l| l.starts_with("protocol bgp")).count(), 2);
+        // the route map only matches the external session
+        assert_eq!(lines.iter().filter(|l| **l == "    import filter { accept; };").count(), 1);
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|l| **l == "    import filter { bgp_local_pref = 200; accept; };")
+                .count(),
+            1
+        );
+        assert!(lines.contains(
+            &"    export filter { if !(source = RTS_BGP || proto = \"advertise\") then reject; accept; };"
+        ));
+    }
+
+    #[test]
+    fn config_unsupported() {
+        let mut router = test_router();
+        router.route_maps[0].set_statements.insert("weight", String::from("100"));
+        assert!(bird_config(&router).is_err());
+
+        let mut router = test_router();
+        router.dual_stack = true;
+        assert!(bird_config(&router).is_err());
+    }
+
+    #[test]
+    fn deny_route_map() {
+        let mut router = test_router();
+        router.route_maps[0].state = "deny";
+        router.route_maps[0].set_statements.clear();
+        let config = bird_config(&router).unwrap();
+        assert!(config.contains("    import filter { reject; };"));
+    }
+
+    #[test]
+    fn step() {
+        let commands = vec![
+            String::from("interface eth1"),
+            String::from("ip address 10.2.0.1/24"),
+            String::from("exit"),
+            String::from("router bgp 65001"),
+            String::from("neighbor 10.2.0.2 remote-as 65002"),
+            String::from("exit"),
+        ];
+        let result = translate_step(&test_router(), &commands).unwrap();
+        assert_eq!(result[0], "ip addr add 10.2.0.1/24 dev eth1");
+        assert_eq!(result[1], "ip link set eth1 up");
+        assert_eq!(result[2], format!("echo '' > {}", BIRD_CONFIG_FILE));
+        assert_eq!(result.last().unwrap(), "birdc configure");
+        assert!(result.contains(&format!("echo 'router id 10.0.0.1;' >> {}", BIRD_CONFIG_FILE)));
+    }
+
+    #[test]
+    fn kernel_routes() {
+        let r = parse_kernel_route("10.1.0.0/24 dev eth0 proto kernel scope link src 10.1.0.1")
+            .unwrap();
+        assert_eq!(r.origin, RteOrigin::Connected);
+        assert_eq!(r.network, IpAddr::new("10.1.0.0", 24));
+        assert_eq!(r.next_hop_addr, None);
+        assert_eq!(r.next_hop_iface, "eth0");
+
+        let r = parse_kernel_route("10.0.0.2 via 10.1.0.2 dev eth0 proto bird metric 32").unwrap();
+        assert_eq!(r.origin, RteOrigin::Table);
+        assert_eq!(r.network, IpAddr::new("10.0.0.2", 32));
+        assert_eq!(r.next_hop_addr, Some(String::from("10.1.0.2")));
+        assert_eq!(r.metric, 32);
+        assert!(r.fib_route);
+
+        let r =
+            parse_kernel_route("99.0.1.0/24 via 10.2.0.2 dev eth1 proto static onlink").unwrap();
+        assert_eq!(r.origin, RteOrigin::Static);
+        assert_eq!(r.next_hop_iface, "eth1");
+
+        let r = parse_kernel_route("unreachable 10.0.0.0/24 proto bird metric 32").unwrap();
+        assert!(r.rejected);
+        assert!(!r.fib_route);
+        assert_eq!(r.network, IpAddr::new("10.0.0.0", 24));
+
+        let r = parse_kernel_route("default via 10.1.0.2 dev eth0").unwrap();
+        assert_eq!(r.network, IpAddr::new("0.0.0.0", 0));
+
+        assert_eq!(parse_kernel_route(""), None);
+    }
+}
@@ -0,0 +1,553 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # BGP Monitoring Protocol
+//!
+//! This module contains a minimal collector for the BGP Monitoring Protocol (BMP, RFC 7854). The
+//! FRR routers connect to the collector through a management interface, which is attached to a
+//! GNS3 NAT node (see [`BmpTarget`]), and send the post-policy Adj-RIB-In of every BGP session.
+//! The collector keeps the RIB of every router, identified by the `sysName` of the initiation
+//! message (which is the hostname of the router).
+//!
+//! The collector only understands IPv4 unicast routes. All other messages (statistics reports,
+//! termination, and routes of other address families) are ignored.
+
+use log::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::io::{ErrorKind, Read};
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// TCP port of the collector
+pub const BMP_PORT: u16 = 5000;
+
+/// Address of the collector (the host running snowcap), as seen from the GNS3 NAT node.
+pub const BMP_COLLECTOR_ADDR: &str = "192.168.122.1";
+
+const BMP_VERSION: u8 = 3;
+const COMMON_HEADER_LEN: usize = 6;
+const PER_PEER_HEADER_LEN: usize = 42;
+const BGP_HEADER_LEN: usize = 19;
+
+/// Configuration of a router, to send BMP messages to the collector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BmpTarget {
+    /// Name of the management interface, connected to the NAT node.
+    pub iface: String,
+    /// Address of the collector
+    pub collector_addr: String,
+    /// TCP port of the collector
+    pub port: u16,
+}
+
+impl BmpTarget {
+    /// Create a new target on the management interface, for the default collector.
+    pub fn new(iface: impl Into<String>) -> Self {
+        Self { iface: iface.into(), collector_addr: BMP_COLLECTOR_ADDR.to_string(), port: BMP_PORT }
+    }
+}
+
+/// A route in the Adj-RIB-In of a router, after the import policy was applied.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct RibEntry {
+    /// Address of the BGP peer, from which the route was received
+    pub peer: String,
+    /// Network of the route, as `a.b.c.d/len`
+    pub prefix: String,
+    /// BGP next hop
+    pub next_hop: Option<String>,
+    /// AS path
+    pub as_path: Vec<u32>,
+    /// Local preference
+    pub local_pref: Option<u32>,
+    /// Multi-exit discriminator
+    pub med: Option<u32>,
+}
+
+/// Snapshot of the RIBs of all routers, sorted by the name of the router.
+pub type RibSnapshot = BTreeMap<String, Vec<RibEntry>>;
+
+/// Parsed BMP message
+#[derive(Debug, Clone, PartialEq)]
+pub enum BmpMessage {
+    /// Initiation message, with the `sysName` of the router.
+    Initiation {
+        /// Name of the router
+        sys_name: Option<String>,
+    },
+    /// A BGP session was established.
+    PeerUp {
+        /// Address of the peer
+        peer: String,
+    },
+    /// A BGP session went down. All routes of this peer are removed.
+    PeerDown {
+        /// Address of the peer
+        peer: String,
+    },
+    /// BGP update, received from a peer.
+    RouteMonitoring {
+        /// Address of the peer
+        peer: String,
+        /// Routes which are announced (or replaced)
+        announced: Vec<RibEntry>,
+        /// Networks which are withdrawn
+        withdrawn: Vec<String>,
+    },
+    /// Any other message, with its type.
+    Other(u8),
+}
+
+/// Parse a single BMP message at the start of `buf`. Returns the message and its length, or
+/// `None` if the buffer does not yet contain the entire message.
+pub fn parse_message(buf: &[u8]) -> Result<Option<(BmpMessage, usize)>, Box<dyn Error>> {
+    if buf.len() < COMMON_HEADER_LEN {
+        return Ok(None);
+    }
+    if buf[0] != BMP_VERSION {
+        return Err(format!("Unsupported BMP version: {}", buf[0]).into());
+    }
+    let len = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+    if len < COMMON_HEADER_LEN {
+        return Err(format!("Invalid BMP message length: {}", len).into());
+    }
+    if buf.len() < len {
+        return Ok(None);
+    }
+    let body = &buf[COMMON_HEADER_LEN..len];
+    let msg = match buf[5] {
+        0 => parse_route_monitoring(body)?,
+        2 => BmpMessage::PeerDown { peer: parse_per_peer_header(body)?.0 },
+        3 => BmpMessage::PeerUp { peer: parse_per_peer_header(body)?.0 },
+        4 => {
+            let mut sys_name = None;
+            let mut tlvs = body;
+            while tlvs.len() >= 4 {
+                let tlv_type = u16::from_be_bytes([tlvs[0], tlvs[1]]);
+                let tlv_len = u16::from_be_bytes([tlvs[2], tlvs[3]]) as usize;
+                let value = tlvs.get(4..4 + tlv_len).ok_or("Truncated initiation message")?;
+                if tlv_type == 2 {
+                    sys_name = Some(String::from_utf8_lossy(value).to_string());
+                }
+                tlvs = &tlvs[4 + tlv_len..];
+            }
+            BmpMessage::Initiation { sys_name }
+        }
+        t => BmpMessage::Other(t),
+    };
+    Ok(Some((msg, len)))
+}
+
+/// Parse the per-peer header, and return the address of the peer and whether the AS path uses
+/// 2-byte AS numbers.
+fn parse_per_peer_header(body: &[u8]) -> Result<(String, bool), Box<dyn Error>> {
+    if body.len() < PER_PEER_HEADER_LEN {
+        return Err("Truncated per-peer header".into());
+    }
+    let flags = body[1];
+    let addr = &body[10..26];
+    let peer = if flags & 0x80 != 0 {
+        let mut octets = [0u8; 16];
+        octets.copy_from_slice(addr);
+        Ipv6Addr::from(octets).to_string()
+    } else {
+        Ipv4Addr::new(addr[12], addr[13], addr[14], addr[15]).to_string()
+    };
+    Ok((peer, flags & 0x20 != 0))
+}
+
+/// Parse a route monitoring message, containing a BGP update.
+fn parse_route_monitoring(body: &[u8]) -> Result<BmpMessage, Box<dyn Error>> {
+    let (peer, two_byte_as) = parse_per_peer_header(body)?;
+    let bgp = &body[PER_PEER_HEADER_LEN..];
+    if bgp.len() < BGP_HEADER_LEN || bgp[18] != 2 {
+        return Ok(BmpMessage::Other(0));
+    }
+    let update = &bgp[BGP_HEADER_LEN..];
+
+    let withdrawn_len = read_u16(update, 0)?;
+    let withdrawn = parse_nlri(update.get(2..2 + withdrawn_len).ok_or("Truncated BGP update")?)?;
+    let attr_pos = 2 + withdrawn_len;
+    let attr_len = read_u16(update, attr_pos)?;
+    let attr_end = attr_pos + 2 + attr_len;
+    let mut attrs = update.get(attr_pos + 2..attr_end).ok_or("Truncated BGP update")?;
+    let nlri = parse_nlri(&update[attr_end..])?;
+
+    let mut entry = RibEntry {
+        peer: peer.clone(),
+        prefix: String::new(),
+        next_hop: None,
+        as_path: Vec::new(),
+        local_pref: None,
+        med: None,
+    };
+    while attrs.len() >= 3 {
+        let flags = attrs[0];
+        let attr_type = attrs[1];
+        let (len, start) =
+            if flags & 0x10 != 0 { (read_u16(attrs, 2)?, 4) } else { (attrs[2] as usize, 3) };
+        let value = attrs.get(start..start + len).ok_or("Truncated attribute")?;
+        let as_u32 = |v: &[u8]| -> Option<u32> {
+            if v.len() == 4 {
+                Some(u32::from_be_bytes([v[0], v[1], v[2], v[3]]))
+            } else {
+                None
+            }
+        };
+        match attr_type {
+            2 => entry.as_path = parse_as_path(value, if two_byte_as { 2 } else { 4 })?,
+            3 if len == 4 => {
+                entry.next_hop =
+                    Some(Ipv4Addr::from([value[0], value[1], value[2], value[3]]).to_string())
+            }
+            4 => entry.med = as_u32(value),
+            5 => entry.local_pref = as_u32(value),
+            _ => {}
+        }
+        attrs = &attrs[start + len..];
+    }
+
+    let announced = nlri.into_iter().map(|prefix| RibEntry { prefix, ..entry.clone() }).collect();
+    Ok(BmpMessage::RouteMonitoring { peer, announced, withdrawn })
+}
+
+/// Read a big-endian `u16` at position `pos`.
+fn read_u16(buf: &[u8], pos: usize) -> Result<usize, Box<dyn Error>> {
+    match buf.get(pos..pos + 2) {
+        Some(b) => Ok(u16::from_be_bytes([b[0], b[1]]) as usize),
+        None => Err("Truncated BGP update".into()),
+    }
+}
+
+/// Parse a list of IPv4 prefixes, encoded as length and the significant bytes.
+fn parse_nlri(mut buf: &[u8]) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result = Vec::new();
+    while !buf.is_empty() {
+        let len = buf[0] as usize;
+        let num_bytes = (len + 7) / 8;
+        if len > 32 || buf.len() < 1 + num_bytes {
+            return Err("Invalid NLRI".into());
+        }
+        let mut octets = [0u8; 4];
+        octets[..num_bytes].copy_from_slice(&buf[1..1 + num_bytes]);
+        result.push(format!("{}/{}", Ipv4Addr::from(octets), len));
+        buf = &buf[1 + num_bytes..];
+    }
+    Ok(result)
+}
+
+/// Parse the AS path attribute, where every AS number has `as_size` bytes. All segments (sequences
+/// and sets) are concatenated.
+fn parse_as_path(mut buf: &[u8], as_size: usize) -> Result<Vec<u32>, Box<dyn Error>> {
+    let mut result = Vec::new();
+    while buf.len() >= 2 {
+        let count = buf[1] as usize;
+        let segment = buf.get(2..2 + count * as_size).ok_or("Truncated AS path")?;
+        for asn in segment.chunks(as_size) {
+            result.push(asn.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32));
+        }
+        buf = &buf[2 + count * as_size..];
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Default)]
+struct BmpState {
+    ribs: HashMap<String, BTreeMap<(String, String), RibEntry>>,
+    num_messages: usize,
+    last_update: Option<Instant>,
+}
+
+impl BmpState {
+    fn apply(&mut self, router: &str, msg: BmpMessage) {
+        self.num_messages += 1;
+        let rib = self.ribs.entry(router.to_string()).or_default();
+        match msg {
+            BmpMessage::PeerDown { peer } => rib.retain(|(p, _), _| *p != peer),
+            BmpMessage::RouteMonitoring { peer, announced, withdrawn } => {
+                for prefix in withdrawn {
+                    rib.remove(&(peer.clone(), prefix));
+                }
+                for entry in announced {
+                    rib.insert((peer.clone(), entry.prefix.clone()), entry);
+                }
+            }
+            BmpMessage::Initiation { .. } | BmpMessage::PeerUp { .. } | BmpMessage::Other(_) => {
+                return
+            }
+        }
+        self.last_update = Some(Instant::now());
+    }
+}
+
+/// # BMP Collector
+///
+/// The collector listens on [`BMP_PORT`] in a separate thread, and handles every router in its own
+/// thread. The collector can be cloned, where all clones share the same state. The threads are
+/// stopped with [`BmpCollector::stop`].
+#[derive(Debug, Clone)]
+pub struct BmpCollector {
+    state: Arc<Mutex<BmpState>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl PartialEq for BmpCollector {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl BmpCollector {
+    /// Start the collector on the given port
+    pub fn start(port: u16) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let collector = Self {
+            state: Arc::new(Mutex::new(BmpState::default())),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+        let c = collector.clone();
+        thread::spawn(move || {
+            while !c.stop.load(Relaxed) {
+                match listener.accept() {
+                    Ok((stream, addr)) => {
+                        info!("BMP session from {}", addr);
+                        let c = c.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = c.handle(stream, addr.ip().to_string()) {
+                                warn!("BMP session from {} failed: {}", addr, e);
+                            }
+                        });
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(100))
+                    }
+                    Err(e) => error!("Cannot accept BMP session: {}", e),
+                }
+            }
+        });
+        Ok(collector)
+    }
+
+    /// Receive and process all messages of a single session.
+    fn handle(&self, mut stream: TcpStream, addr: String) -> Result<(), Box<dyn Error>> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+        let mut router = addr;
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        while !self.stop.load(Relaxed) {
+            match stream.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            }
+            let mut pos = 0;
+            while let Some((msg, len)) = parse_message(&buf[pos..])? {
+                pos += len;
+                if let BmpMessage::Initiation { sys_name: Some(name) } = &msg {
+                    router = name.clone();
+                }
+                self.state.lock().unwrap().apply(&router, msg);
+            }
+            buf.drain(..pos);
+        }
+        Ok(())
+    }
+
+    /// Stop the collector and all sessions.
+    pub fn stop(&self) {
+        self.stop.store(true, Relaxed);
+    }
+
+    /// Returns the total number of messages, which were processed.
+    pub fn num_messages(&self) -> usize {
+        self.state.lock().unwrap().num_messages
+    }
+
+    /// Returns the current RIB of every router.
+    pub fn snapshot(&self) -> RibSnapshot {
+        self.state
+            .lock()
+            .unwrap()
+            .ribs
+            .iter()
+            .map(|(router, rib)| (router.clone(), rib.values().cloned().collect()))
+            .collect()
+    }
+
+    /// Wait until no RIB has changed for the duration `quiet`. Returns an error if the RIBs still
+    /// change after `timeout`.
+    pub fn wait_quiet(&self, quiet: Duration, timeout: Duration) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        loop {
+            let last_update = self.state.lock().unwrap().last_update;
+            if last_update.map(|t| t.elapsed() >= quiet).unwrap_or(true) {
+                return Ok(());
+            }
+            if start.elapsed() >= timeout {
+                return Err("The RIBs did not converge!".into());
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn message(msg_type: u8, body: &[u8]) -> Vec<u8> {
+        let mut msg = vec![BMP_VERSION];
+        msg.extend_from_slice(&((body.len() + COMMON_HEADER_LEN) as u32).to_be_bytes());
+        msg.push(msg_type);
+        msg.extend_from_slice(body);
+        msg
+    }
+
+    fn per_peer_header(peer: [u8; 4]) -> Vec<u8> {
+        let mut header = vec![0, 0];
+        header.extend_from_slice(&[0; 8]);
+        header.extend_from_slice(&[0; 12]);
+        header.extend_from_slice(&peer);
+        header.extend_from_slice(&65002u32.to_be_bytes());
+        header.extend_from_slice(&[10, 0, 0, 2]);
+        header.extend_from_slice(&[0; 8]);
+        header
+    }
+
+    fn update(withdrawn: &[u8], attrs: &[u8], nlri: &[u8]) -> Vec<u8> {
+        let mut update = Vec::new();
+        update.extend_from_slice(&(withdrawn.len() as u16).to_be_bytes());
+        update.extend_from_slice(withdrawn);
+        update.extend_from_slice(&(attrs.len() as u16).to_be_bytes());
+        update.extend_from_slice(attrs);
+        update.extend_from_slice(nlri);
+        let mut bgp = vec![0xff; 16];
+        bgp.extend_from_slice(&((update.len() + BGP_HEADER_LEN) as u16).to_be_bytes());
+        bgp.push(2);
+        bgp.extend(update);
+        bgp
+    }
+
+    #[test]
+    fn initiation() {
+        let mut body = vec![0, 1, 0, 3];
+        body.extend_from_slice(b"FRR");
+        body.extend_from_slice(&[0, 2, 0, 2]);
+        body.extend_from_slice(b"r1");
+        let msg = message(4, &body);
+        assert_eq!(
+            parse_message(&msg).unwrap(),
+            Some((BmpMessage::Initiation { sys_name: Some(String::from("r1")) }, msg.len()))
+        );
+        // incomplete messages
+        assert_eq!(parse_message(&msg[..3]).unwrap(), None);
+        assert_eq!(parse_message(&msg[..msg.len() - 1]).unwrap(), None);
+    }
+
+    #[test]
+    fn route_monitoring() {
+        let attrs = [
+            0x40, 1, 1, 0, // origin IGP
+            0x40, 2, 10, 2, 2, 0, 0, 0xfd, 0xea, 0, 0, 0xfd, 0xe9, // AS path 65002 65001
+            0x40, 3, 4, 10, 1, 0, 2, // next hop 10.1.0.2
+            0x80, 4, 4, 0, 0, 0, 20, // MED 20
+            0x40, 5, 4, 0, 0, 0, 100, // local pref 100
+        ];
+        let mut body = per_peer_header([10, 1, 0, 2]);
+        body.extend(update(&[24, 99, 0, 1], &attrs, &[8, 200, 16, 10, 1]));
+        let msg = message(0, &body);
+        let entry = RibEntry {
+            peer: String::from("10.1.0.2"),
+            prefix: String::from("200.0.0.0/8"),
+            next_hop: Some(String::from("10.1.0.2")),
+            as_path: vec![65002, 65001],
+            local_pref: Some(100),
+            med: Some(20),
+        };
+        assert_eq!(
+            parse_message(&msg).unwrap(),
+            Some((
+                BmpMessage::RouteMonitoring {
+                    peer: String::from("10.1.0.2"),
+                    announced: vec![
+                        entry.clone(),
+                        RibEntry { prefix: String::from("10.1.0.0/16"), ..entry }
+                    ],
+                    withdrawn: vec![String::from("99.0.1.0/24")],
+                },
+                msg.len()
+            ))
+        );
+    }
+
+    #[test]
+    fn rib_state() {
+        let entry = RibEntry {
+            peer: String::from("10.0.0.2"),
+            prefix: String::from("200.0.0.0/8"),
+            next_hop: Some(String::from("10.0.0.2")),
+            as_path: vec![65002],
+            local_pref: Some(100),
+            med: None,
+        };
+        let mut state = BmpState::default();
+        state.apply("r1", BmpMessage::Initiation { sys_name: Some(String::from("r1")) });
+        assert!(state.last_update.is_none());
+        state.apply(
+            "r1",
+            BmpMessage::RouteMonitoring {
+                peer: entry.peer.clone(),
+                announced: vec![entry.clone()],
+                withdrawn: vec![],
+            },
+        );
+        assert_eq!(state.ribs["r1"].values().cloned().collect::<Vec<_>>(), vec![entry.clone()]);
+        assert!(state.last_update.is_some());
+
+        state.apply("r1", BmpMessage::PeerDown { peer: entry.peer.clone() });
+        assert!(state.ribs["r1"].is_empty());
+
+        state.apply(
+            "r1",
+            BmpMessage::RouteMonitoring {
+                peer: entry.peer.clone(),
+                announced: vec![entry.clone()],
+                withdrawn: vec![],
+            },
+        );
+        state.apply(
+            "r1",
+            BmpMessage::RouteMonitoring {
+                peer: entry.peer.clone(),
+                announced: vec![],
+                withdrawn: vec![entry.prefix.clone()],
+            },
+        );
+        assert!(state.ribs["r1"].is_empty());
+        assert_eq!(state.num_messages, 5);
+    }
+}
@@ -18,11 +18,14 @@
 //! This module checks the paths if the conditions are ok
 
 use snowcap::hard_policies::*;
-use snowcap::netsim::{Prefix, RouterId};
+use snowcap::netsim::{ForwardingState, Prefix, RouterId};
 
 use log::*;
+use serde::Serialize;
 use std::collections::HashMap;
 
+use super::bmp::RibSnapshot;
+use super::pcap_reader::{AnomalyKind, TransientAnomaly};
 use super::physical_network::{PhysicalNetwork, CLIENT_ID_BASE};
 
 /// Checks if the conditions supplied are satisfied. This function excepts a vector of path
@@ -134,12 +137,236 @@ pub fn check(
             }
             Condition::Reliable(_, _, _) => info!("Skipping reliability condition"),
             Condition::TransientPath(_, _, _) => info!("Skipping transient path condition"),
+            Condition::ValleyFree(_, _) => info!("Skipping valley-free condition"),
+            Condition::PathLengthAtMost(router, prefix, k) => {
+                let client: RouterId = (router.index() as u32 + CLIENT_ID_BASE).into();
+                let router_name = phys_net.router_name(*router);
+                info!(
+                    "Checking condition: {} can reach prefix {} in at most {} hops",
+                    router_name, prefix.0, k
+                );
+                let p = match paths.get(&(client, *prefix)) {
+                    Some(p) => p,
+                    None => {
+                        warn!(
+                            "No packets from router {} to prefix {} were found!",
+                            router_name, prefix.0
+                        );
+                        conds_ok = false;
+                        continue;
+                    }
+                };
+                for (path, count) in p.iter() {
+                    match path {
+                        None => {
+                            warn!("    {} packets dropped!", count);
+                            conds_ok = false;
+                        }
+                        // the path contains the client and the destination, in addition to the
+                        // routers.
+                        Some(path) if path.len() <= k + 3 => {
+                            info!("    {} packets took path {}", count, path_str(phys_net, path))
+                        }
+                        Some(path) => {
+                            conds_ok = false;
+                            warn!("    {} packets took path {}", count, path_str(phys_net, path));
+                        }
+                    }
+                }
+            }
+            Condition::LatencyAtMost(_, _, _) => info!("Skipping latency condition"),
+            Condition::EgressIs(_, _, _) => info!("Skipping egress condition"),
         }
     }
 
     conds_ok
 }
 
+/// # Verdict of a migration step
+/// Summary of the flows measured while applying a single step, compared to the simulation.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepVerdict {
+    /// Position of the step in the migration sequence (starting at 0).
+    pub step: usize,
+    /// String representation of the modifier(s) applied in this step.
+    pub modifier: String,
+    /// No packet was dropped, unless the simulation also has a black hole for the flow.
+    pub no_black_holes: bool,
+    /// Every packet took a path that the simulation predicts either before or after the step.
+    pub matches_simulation: bool,
+    /// All flows which deviated from the simulation.
+    pub deviations: Vec<FlowDeviation>,
+    /// Transient loops and black holes detected in the packet captures.
+    pub anomalies: Vec<AnomalyInformation>,
+    /// Routes which are missing in the RIBs collected via BMP after the step (see
+    /// [`missing_routes`]). Always empty if BMP monitoring is disabled.
+    pub missing_routes: Vec<MissingRoute>,
+}
+
+impl StepVerdict {
+    /// Returns `true` if all invariants of the step hold.
+    pub fn is_ok(&self) -> bool {
+        self.no_black_holes && self.matches_simulation && self.missing_routes.is_empty()
+    }
+}
+
+/// # Deviation of a flow
+/// Packets of a flow, which took a path that is not expected from the simulation.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowDeviation {
+    /// Name of the router at which the flow enters the network.
+    pub router: String,
+    /// Prefix of the flow.
+    pub prefix: u32,
+    /// How the flow deviated from the simulation.
+    pub kind: DeviationKind,
+    /// Path taken by the packets (empty if they were dropped).
+    pub path: Vec<String>,
+    /// Paths of the simulation before and after the step (empty if the simulation has a black
+    /// hole).
+    pub expected: Vec<Vec<String>>,
+    /// Number of packets which took this path.
+    pub count: usize,
+}
+
+/// # Transient anomaly
+/// Transient loop or black hole of a flow, detected in the packet captures (see
+/// [`detect_anomalies`](crate::pcap_reader::detect_anomalies)).
+#[derive(Debug, Clone, Serialize)]
+pub struct AnomalyInformation {
+    /// Name of the router at which the flow enters the network.
+    pub router: String,
+    /// Prefix of the flow.
+    pub prefix: u32,
+    /// Kind of the anomaly.
+    pub kind: AnomalyKind,
+    /// Link of the loop, or router of the black hole.
+    pub location: Vec<String>,
+    /// Number of affected packets.
+    pub num_packets: usize,
+    /// Duration of the anomaly in seconds.
+    pub duration: f64,
+}
+
+/// # Missing route
+/// Router, which has no route for a prefix in its RIB, while the simulation has one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingRoute {
+    /// Name of the router
+    pub router: String,
+    /// Prefix, for which the router has no route
+    pub prefix: u32,
+}
+
+/// # Kind of a flow deviation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DeviationKind {
+    /// Packets were dropped, while the simulation has a path.
+    Dropped,
+    /// Packets took a path which the simulation never chooses.
+    UnexpectedPath,
+}
+
+/// Compare the flows measured during a step with the simulated forwarding state before and after
+/// the step. A path is expected, if it matches the simulated path either before or after the step.
+/// The first and the last node of the measured path (the client and the origin of the prefix) are
+/// not considered. The `anomalies` are added to the verdict, but they do not affect it.
+pub fn step_verdict(
+    step: usize,
+    modifier: String,
+    flows: &HashMap<(RouterId, Prefix), HashMap<Option<Vec<RouterId>>, usize>>,
+    anomalies: &[TransientAnomaly],
+    before: &mut ForwardingState,
+    after: &mut ForwardingState,
+    phys_net: &PhysicalNetwork,
+) -> StepVerdict {
+    let names = |p: &[RouterId]| -> Vec<String> {
+        p.iter().map(|r| phys_net.router_name(*r).to_string()).collect()
+    };
+
+    let mut flows = flows.iter().collect::<Vec<_>>();
+    flows.sort_by_key(|(key, _)| **key);
+
+    let mut deviations = Vec::new();
+    for ((client, prefix), paths) in flows {
+        let router = client_router(*client);
+        let expected =
+            vec![before.get_route(router, *prefix).ok(), after.get_route(router, *prefix).ok()];
+        let mut paths = paths.iter().collect::<Vec<_>>();
+        paths.sort();
+        for (path, count) in paths {
+            let short_path = path.as_ref().map(|p| strip_endpoints(p));
+            if expected.contains(&short_path) {
+                continue;
+            }
+            deviations.push(FlowDeviation {
+                router: phys_net.router_name(router).to_string(),
+                prefix: prefix.0,
+                kind: if short_path.is_none() {
+                    DeviationKind::Dropped
+                } else {
+                    DeviationKind::UnexpectedPath
+                },
+                path: short_path.as_deref().map(names).unwrap_or_default(),
+                expected: expected.iter().flatten().map(|p| names(p)).collect(),
+                count: *count,
+            });
+        }
+    }
+
+    let anomalies = anomalies
+        .iter()
+        .map(|a| AnomalyInformation {
+            router: phys_net.router_name(client_router(a.flow.0)).to_string(),
+            prefix: a.flow.1 .0,
+            kind: a.kind,
+            location: names(&a.location),
+            num_packets: a.num_packets,
+            duration: a.duration,
+        })
+        .collect();
+
+    StepVerdict {
+        step,
+        modifier,
+        no_black_holes: deviations.iter().all(|d| d.kind != DeviationKind::Dropped),
+        matches_simulation: deviations.iter().all(|d| d.kind != DeviationKind::UnexpectedPath),
+        deviations,
+        anomalies,
+        missing_routes: Vec::new(),
+    }
+}
+
+/// Compare the RIBs collected via BMP with the simulated forwarding state after the step. Every
+/// monitored router must have received a route for the network of every IPv4 prefix, for which
+/// the simulation has a route.
+pub fn missing_routes(
+    rib: &RibSnapshot,
+    after: &mut ForwardingState,
+    phys_net: &PhysicalNetwork,
+) -> Vec<MissingRoute> {
+    let mut missing = Vec::new();
+    for router in phys_net.routers.iter().filter(|r| r.bmp.is_some()) {
+        for prefix in phys_net.prefixes() {
+            let network = match phys_net.prefix_network(*prefix) {
+                Some(network) => network.to_string(),
+                None => continue,
+            };
+            if after.get_route(router.router_id, *prefix).is_err() {
+                continue;
+            }
+            let has_route = rib
+                .get(&router.name)
+                .map(|entries| entries.iter().any(|e| e.prefix == network))
+                .unwrap_or(false);
+            if !has_route {
+                missing.push(MissingRoute { router: router.name.clone(), prefix: prefix.0 });
+            }
+        }
+    }
+    missing
+}
+
 /// Print all paths as info logs
 pub fn print_paths(
     flows: &HashMap<(RouterId, Prefix), HashMap<Option<Vec<RouterId>>, usize>>,
@@ -157,6 +384,20 @@ pub fn print_paths(
     }
 }
 
+/// Get the router to which the client is connected.
+fn client_router(client: RouterId) -> RouterId {
+    ((client.index() as u32).saturating_sub(CLIENT_ID_BASE)).into()
+}
+
+/// Remove the client and the origin of the prefix from the measured path.
+fn strip_endpoints(path: &[RouterId]) -> Vec<RouterId> {
+    if path.len() <= 2 {
+        Vec::new()
+    } else {
+        path[1..path.len() - 1].to_vec()
+    }
+}
+
 fn path_str(phys_net: &PhysicalNetwork, path: &Vec<RouterId>) -> String {
     path.iter().map(|r| phys_net.router_name(*r)).collect::<Vec<_>>().join(" -> ")
 }
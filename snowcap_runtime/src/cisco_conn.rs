@@ -0,0 +1,648 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Utilities for telnet interactions to Cisco IOSvL2
+
+use crate::driver::RouterDriver;
+use crate::frr_conn::{RoutingTable, RoutingTableEntry, RteOrigin};
+use crate::physical_network::{IpAddr, PhysicalRouter};
+
+use log::*;
+use regex::Regex;
+use telnet::{Telnet, TelnetEvent};
+
+use std::thread::sleep;
+
+use std::error::Error;
+use std::net::Ipv4Addr;
+use std::str;
+use std::time::{Duration, SystemTime};
+
+const CMD_WAIT: u64 = 30;
+
+/// # Connection to Cisco IOSvL2
+///
+/// This struct can be used to communicate with a Cisco IOSvL2 image running inside GNS3, using
+/// the telnet console. Since the image is a layer-3 switch, IP routing is enabled explicitly, and
+/// all interfaces are configured as routed ports. The commands of every migration step are
+/// translated from FRR syntax line by line (see [`translate_commands`]). Route maps matching on
+/// the BGP peer are not supported.
+///
+/// All BGP sessions send communities, which is the default in FRR.
+pub struct CiscoConnection {
+    c: Telnet,
+    prompt_re: Regex,
+    traceroute_re: Regex,
+    logging: bool,
+}
+
+impl std::fmt::Debug for CiscoConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CiscoConnection")
+    }
+}
+
+impl CiscoConnection {
+    /// create a new connection to a device
+    pub fn new(port: u16) -> Result<Self, Box<dyn Error>> {
+        let prompt_re = Regex::new(r"(?m)^[a-zA-Z0-9_\-.]+(\([a-z0-9\-]+\))?[>#] ?\z").unwrap();
+        let traceroute_re =
+            Regex::new(r"^ *\d{1,2} (\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}) .*\d+ msec").unwrap();
+
+        let mut c = Telnet::connect(("localhost", port), 2048)?;
+        // receive all initial events
+        while let Ok(event) = c.read_timeout(Duration::from_millis(1)) {
+            if matches!(event, TelnetEvent::TimedOut) {
+                break;
+            }
+        }
+
+        c.write("\n".as_bytes())?;
+
+        let now = SystemTime::now();
+
+        let mut result = String::new();
+        loop {
+            let event = c.read_nonblocking()?;
+            match event {
+                telnet::TelnetEvent::NoData => {
+                    if now.elapsed()? > Duration::from_secs(300) {
+                        error!("IOS is in an invalid state, or did not boot up! Port: {}", port);
+                        return Err("IOS is in an invalid state, or did not boot up!".into());
+                    }
+                    sleep(Duration::from_millis(10));
+                }
+                telnet::TelnetEvent::Data(d) => result.push_str(str::from_utf8(&d)?),
+                _ => {}
+            }
+            // skip the initial configuration dialog
+            if result.contains("[yes/no]:") {
+                c.write("no\n".as_bytes())?;
+                result.clear();
+            } else if result.contains("Press RETURN to get started") {
+                c.write("\n".as_bytes())?;
+                result.clear();
+            }
+            if prompt_re.is_match(&result) {
+                break;
+            }
+        }
+
+        let mut s = Self { c, prompt_re, traceroute_re, logging: false };
+
+        s.send_wait("enable\n")?;
+        s.send_wait("terminal length 0\n")?;
+
+        Ok(s)
+    }
+
+    fn config_expr(&mut self, data: impl AsRef<str>) -> Result<(), Box<dyn Error>> {
+        let result = self.send_wait(format!("{}\n", data.as_ref().trim()))?;
+        if result.lines().any(|l| l.trim_start().starts_with('%')) {
+            Err(format!("Error while applying configuration: \n{}", result).into())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn send_wait(&mut self, data: impl AsRef<str>) -> Result<String, Box<dyn Error>> {
+        self.c.write(data.as_ref().as_bytes())?;
+        self.receive_until_prompt(CMD_WAIT)
+    }
+
+    fn send(&mut self, data: impl AsRef<str>) -> Result<(), Box<dyn Error>> {
+        self.c.write(data.as_ref().as_bytes())?;
+        Ok(())
+    }
+
+    fn receive_until_prompt(&mut self, wait_secs: u64) -> Result<String, Box<dyn Error>> {
+        let mut result = String::new();
+        let now = SystemTime::now();
+        loop {
+            let event = self.c.read_nonblocking()?;
+            match event {
+                telnet::TelnetEvent::NoData => {
+                    if now.elapsed()? > Duration::from_secs(wait_secs) {
+                        eprintln!("{}", result);
+                        return Err(format!(
+                            "Took longer than {} second to receive an answer!",
+                            wait_secs
+                        )
+                        .into());
+                    }
+                    sleep(Duration::from_millis(10));
+                }
+                telnet::TelnetEvent::Data(d) => {
+                    result.push_str(str::from_utf8(&d)?);
+                    if self.prompt_re.is_match(&result) {
+                        if self.logging {
+                            eprintln!("{}", result);
+                        }
+                        return Ok(result.replace("\r\n", "\n"));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl RouterDriver for CiscoConnection {
+    fn initialize_config(&mut self, router: &PhysicalRouter) -> Result<(), Box<dyn Error>> {
+        let commands = cisco_config(router)?;
+        self.execute(commands)
+    }
+
+    fn translate(
+        &self,
+        _router: &PhysicalRouter,
+        commands: &[String],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        translate_commands(commands)
+    }
+
+    fn execute(&mut self, commands: Vec<String>) -> Result<(), Box<dyn Error>> {
+        self.send_wait("configure terminal\n")?;
+        for cmd in commands {
+            self.config_expr(cmd)?;
+        }
+        self.send_wait("end\n")?;
+        Ok(())
+    }
+
+    fn set_interface_state(&mut self, iface: &str, up: bool) -> Result<(), Box<dyn Error>> {
+        self.execute(vec![
+            format!("interface {}", iface),
+            if up { "no shutdown" } else { "shutdown" }.to_string(),
+            String::from("exit"),
+        ])
+    }
+
+    fn get_routing_table(&mut self) -> Result<RoutingTable, Box<dyn Error>> {
+        let result = self.send_wait("show ip route\n")?;
+        Ok(parse_routing_table(&result))
+    }
+
+    fn traceroute(&mut self, target: &IpAddr) -> Result<Option<Vec<[u8; 4]>>, Box<dyn Error>> {
+        if target.is_v6() {
+            return Err("IPv6 traceroute is only supported on FRR".into());
+        }
+        self.send(format!("traceroute ip {} numeric timeout 1 probe 1\n", target.addr))?;
+        let result = self.receive_until_prompt(64)?;
+
+        let mut path = Vec::new();
+        // skip the command, and all lines which do not describe a hop.
+        for line in result.lines().skip(1) {
+            if self.prompt_re.is_match(line) {
+                break;
+            }
+            if !line.trim_start().starts_with(|c: char| c.is_ascii_digit()) {
+                continue;
+            }
+            match self.traceroute_re.captures(line) {
+                Some(caps) => path.push(caps[1].parse::<Ipv4Addr>()?.octets()),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(path))
+    }
+}
+
+/// Generate the entire configuration of the router, as commands in the configuration mode. The
+/// configuration is equivalent to the one of FRR (see
+/// [`FrrConnection::initialize_config`](crate::frr_conn::FrrConnection::initialize_config)).
+pub fn cisco_config(router: &PhysicalRouter) -> Result<Vec<String>, Box<dyn Error>> {
+    if router.dual_stack {
+        return Err("Dual-stack routers are only supported on FRR".into());
+    }
+
+    let lo = &router.loopback_addr.addr;
+    let mut c = vec![
+        format!("hostname {}", router.name),
+        String::from("ip routing"),
+        String::from("no ip domain-lookup"),
+        String::from("interface Loopback0"),
+        format!("ip address {} 255.255.255.255", lo),
+        String::from("exit"),
+    ];
+
+    if router.ifaces.iter().any(|i| i.cost.is_some()) {
+        c.push(String::from("router ospf 1"));
+        c.push(format!("router-id {}", lo));
+        c.push(String::from("redistribute connected subnets"));
+        c.push(String::from("exit"));
+    }
+
+    // all interfaces are routed ports, even if they are not yet enabled
+    for iface in router.ifaces.iter() {
+        c.push(format!("interface {}", iface.gns_interface.name));
+        c.push(String::from("no switchport"));
+        if iface.enabled {
+            c.push(format!(
+                "ip address {} {}",
+                iface.iface_addr.addr,
+                iface.iface_addr.repr_mask()
+            ));
+            if let Some(cost) = iface.cost {
+                c.push(String::from("ip ospf 1 area 0"));
+                c.push(format!("ip ospf cost {}", cost));
+            }
+        }
+        c.push(String::from("no shutdown"));
+        c.push(String::from("exit"));
+    }
+
+    let mut frr = Vec::new();
+    for rm in router.route_maps.iter() {
+        frr.extend(rm.community_list_commands());
+        frr.push(format!("route-map {} {} {}", rm.name, rm.state, rm.order));
+        for (key, value) in rm.match_statements.iter() {
+            frr.push(format!("match {} {}", key, value));
+        }
+        for (key, value) in rm.set_statements.iter() {
+            frr.push(format!("set {} {}", key, value));
+        }
+        frr.push(String::from("exit"));
+        frr.push(format!("route-map {} permit 65535", rm.name));
+        frr.push(String::from("exit"));
+    }
+    c.extend(translate_commands(&frr)?);
+
+    c.push(format!("router bgp {}", router.as_id.0));
+    c.push(format!("bgp router-id {}", lo));
+    c.push(String::from("bgp log-neighbor-changes"));
+    c.push(String::from("bgp deterministic-med"));
+    if router.always_compare_med {
+        c.push(String::from("bgp always-compare-med"));
+    }
+    if let Some(cluster_id) = router.cluster_id.as_ref() {
+        c.push(format!("bgp cluster-id {}", cluster_id));
+    }
+    c.push(String::from("bgp bestpath compare-routerid"));
+    for group in &["internal", "external"] {
+        c.push(format!("neighbor {} peer-group", group));
+        c.push(format!("neighbor {} send-community", group));
+    }
+    for session in router.bgp_sessions.iter() {
+        let n_addr = session.neighbor_addr.addr.as_str();
+        let source = if session.internal_session {
+            "Loopback0"
+        } else {
+            router
+                .ifaces
+                .iter()
+                .find(|i| i.neighbor == session.neighbor)
+                .map(|i| i.gns_interface.name.as_str())
+                .ok_or("No direct connection with the neighbor")?
+        };
+        let group = if session.internal_session { "internal" } else { "external" };
+        c.push(format!("neighbor {} remote-as {}", n_addr, session.neighbor_as_id.0));
+        c.push(format!("neighbor {} update-source {}", n_addr, source));
+        c.push(format!("neighbor {} peer-group {}", n_addr, group));
+        if session.is_rr_client {
+            c.push(format!("neighbor {} route-reflector-client", n_addr));
+        }
+    }
+    c.push(String::from("address-family ipv4 unicast"));
+    if let Some(network) = router.advertise_route.as_ref() {
+        c.push(format!("network {} mask {}", network.addr, network.repr_mask()));
+    }
+    for rm in router.route_maps.iter() {
+        c.push(format!("neighbor internal route-map {} {}", rm.name, rm.direction));
+        c.push(format!("neighbor external route-map {} {}", rm.name, rm.direction));
+    }
+    c.push(String::from("exit-address-family"));
+    c.push(String::from("exit"));
+
+    let static_routes = router.static_routes.iter().map(|sr| sr.command()).collect::<Vec<_>>();
+    c.extend(translate_commands(&static_routes)?);
+
+    Ok(c)
+}
+
+/// Translate FRR commands into Cisco IOS commands, line by line. Addresses with a prefix length
+/// are written with a netmask, the loopback interface is called `Loopback0`, and relative changes
+/// of attributes by zero (`+0`) are dropped. Returns an error for commands that have no equivalent
+/// in Cisco IOS, like `match peer` or IPv6 commands.
+pub fn translate_commands(commands: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result = Vec::with_capacity(commands.len());
+    for cmd in commands.iter().map(|c| c.trim()) {
+        let (no, stmt) = match cmd.strip_prefix("no ") {
+            Some(stmt) => ("no ", stmt),
+            None => ("", cmd),
+        };
+        let words = stmt.split_whitespace().collect::<Vec<_>>();
+        if words.iter().any(|w| w.starts_with("ipv6") || w.ends_with("ospf6")) {
+            return Err("Dual-stack routers are only supported on FRR".into());
+        }
+        let translated = match words.as_slice() {
+            ["ip", "address", addr] => {
+                let addr = IpAddr::try_from_str(addr)?;
+                format!("{}ip address {} {}", no, addr.addr, addr.repr_mask())
+            }
+            ["ip", "route", network, next_hop] => {
+                let network = IpAddr::try_from_str(network)?;
+                format!("{}ip route {} {} {}", no, network.addr, network.repr_mask(), next_hop)
+            }
+            ["neighbor", addr, "update-source", "lo"] => {
+                format!("{}neighbor {} update-source Loopback0", no, addr)
+            }
+            // IOS removes the neighbor entirely
+            ["neighbor", addr, "remote-as", _] if !no.is_empty() => format!("no neighbor {}", addr),
+            ["address-family", "ipv4"] => String::from("address-family ipv4 unicast"),
+            ["bgp", "community-list", rest @ ..] => {
+                format!("{}ip community-list {}", no, rest.join(" "))
+            }
+            ["set", _, "+0"] => continue,
+            ["match", "peer", _] => {
+                return Err(format!("Cisco IOS does not support `{}`", cmd).into());
+            }
+            _ => cmd.to_string(),
+        };
+        result.push(translated);
+    }
+    Ok(result)
+}
+
+/// Parse the output of the IOS command `show ip route`. Lines that do not contain a route (like
+/// the legend) are ignored. If a network is written without prefix length, the length is taken
+/// from the previous line `x.x.x.x/l is subnetted`. Local routes (`L`) have the origin
+/// [`RteOrigin::Connected`].
+pub fn parse_routing_table(s: &str) -> RoutingTable {
+    let header_re = Regex::new(r"^\s+\d+\.\d+\.\d+\.\d+/(\d+) is subnetted").unwrap();
+    let route_re = Regex::new(concat!(
+        r"^([A-Za-z])[* ]?(?: ?[A-Z][A-Z0-9]?)?\s+(\d+\.\d+\.\d+\.\d+)(?:/(\d+))?\s+",
+        r"(?:\[(\d+)/(\d+)\] via (\d+\.\d+\.\d+\.\d+)(?:, [0-9:dhmwy]+)?(?:, (\S+))?",
+        r"|is directly connected, (\S+))"
+    ))
+    .unwrap();
+
+    let mut mask = 32;
+    let mut entries = Vec::new();
+    for line in s.lines() {
+        if let Some(caps) = header_re.captures(line) {
+            mask = caps[1].parse().unwrap_or(32);
+            continue;
+        }
+        let caps = match route_re.captures(line) {
+            Some(caps) => caps,
+            None => continue,
+        };
+        let origin = match &caps[1] {
+            "L" | "C" => RteOrigin::Connected,
+            "S" => RteOrigin::Static,
+            "O" => RteOrigin::Ospf,
+            "B" => RteOrigin::Bgp,
+            "R" => RteOrigin::Rip,
+            "i" => RteOrigin::IsIs,
+            "D" => RteOrigin::Eigrip,
+            _ => RteOrigin::Table,
+        };
+        let mask = caps.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(mask);
+        entries.push(RoutingTableEntry {
+            origin,
+            instance_id: 0,
+            selected: true,
+            fib_route: true,
+            queued: false,
+            rejected: false,
+            network: IpAddr::new(&caps[2], mask),
+            administrative_distance: caps.get(4).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
+            metric: caps.get(5).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
+            next_hop_addr: caps.get(6).map(|m| m.as_str().to_string()),
+            next_hop_iface: caps
+                .get(7)
+                .or_else(|| caps.get(8))
+                .map(|m| m.as_str().to_string())
+                .unwrap_or_default(),
+            learned_from: None,
+        });
+    }
+    RoutingTable { entries }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::driver::RouterKind;
+    use crate::physical_network::{BgpSessionInfo, IfaceInfo, RouteMapInfo, StaticRouteInfo};
+    use gns3::*;
+    use snowcap::netsim::AsId;
+
+    fn cmds(commands: &[&str]) -> Vec<String> {
+        commands.iter().map(|c| c.to_string()).collect()
+    }
+
+    fn test_router() -> PhysicalRouter {
+        PhysicalRouter {
+            router_id: 0.into(),
+            name: String::from("node"),
+            kind: RouterKind::CiscoIosvL2,
+            gns_node: GNS3Node {
+                id: String::new(),
+                name: String::from("node"),
+                node_type: String::from("qemu"),
+                port: 5000,
+                status: GNS3NodeStatus::Stopped,
+                interfaces: vec![],
+            },
+            loopback_addr: IpAddr::new("10.0.0.1", 24),
+            as_id: AsId(65001),
+            is_internal: true,
+            ifaces: vec![IfaceInfo {
+                neighbor: 1.into(),
+                neighbor_addr: IpAddr::new("10.1.0.2", 24),
+                iface_addr: IpAddr::new("10.1.0.1", 24),
+                gns_interface: GNS3Interface {
+                    adapter_number: 0,
+                    port_number: 0,
+                    name: "GigabitEthernet0/0".to_string(),
+                    short_name: "Gi0/0".to_string(),
+                    link_type: "ethernet".to_string(),
+                },
+                enabled: true,
+                cost: Some(10),
+                link_id: 0,
+            }],
+            bgp_sessions: vec![
+                BgpSessionInfo {
+                    neighbor: 2.into(),
+                    neighbor_addr: IpAddr::new("10.0.0.2", 24),
+                    neighbor_as_id: AsId(65001),
+                    is_rr_client: true,
+                    internal_session: true,
+                },
+                BgpSessionInfo {
+                    neighbor: 1.into(),
+                    neighbor_addr: IpAddr::new("10.1.0.2", 24),
+                    neighbor_as_id: AsId(65002),
+                    is_rr_client: false,
+                    internal_session: false,
+                },
+            ],
+            route_maps: vec![],
+            static_routes: vec![StaticRouteInfo {
+                addr: IpAddr::new("99.0.1.0", 24),
+                next_hop: String::from("10.1.0.2"),
+            }],
+            advertise_route: Some(IpAddr::new("10.0.0.0", 24)),
+            advertise_route_v6: None,
+            dual_stack: false,
+            always_compare_med: false,
+            cluster_id: None,
+            bmp: None,
+        }
+    }
+
+    #[test]
+    fn config() {
+        let config = cisco_config(&test_router()).unwrap();
+        let pos = |cmd: &str| config.iter().position(|c| c == cmd).unwrap();
+        let has = |cmd: &str| config.iter().any(|c| c == cmd);
+        assert!(pos("interface Loopback0") < pos("ip address 10.0.0.1 255.255.255.255"));
+        assert!(pos("interface GigabitEthernet0/0") < pos("ip address 10.1.0.1 255.255.255.0"));
+        assert!(has("ip ospf cost 10"));
+        assert!(has("router bgp 65001"));
+        assert!(has("neighbor 10.0.0.2 update-source Loopback0"));
+        assert!(has("neighbor 10.0.0.2 route-reflector-client"));
+        assert!(has("neighbor 10.1.0.2 update-source GigabitEthernet0/0"));
+        assert!(has("neighbor 10.1.0.2 peer-group external"));
+        assert!(pos("address-family ipv4 unicast") < pos("network 10.0.0.0 mask 255.255.255.0"));
+        assert!(has("ip route 99.0.1.0 255.255.255.0 10.1.0.2"));
+    }
+
+    #[test]
+    fn config_unsupported() {
+        let mut router = test_router();
+        router.route_maps.push(RouteMapInfo {
+            name: String::from("rm-in"),
+            state: "permit",
+            order: 10,
+            direction: "in",
+            match_statements: vec![("peer", String::from("10.1.0.2/24"))].into_iter().collect(),
+            set_statements: vec![("local-preference", String::from("200"))].into_iter().collect(),
+            community_lists: Vec::new(),
+        });
+        assert!(cisco_config(&router).is_err());
+
+        let mut router = test_router();
+        router.dual_stack = true;
+        assert!(cisco_config(&router).is_err());
+    }
+
+    #[test]
+    fn translate() {
+        assert_eq!(
+            translate_commands(&cmds(&[
+                "interface Gi0/0",
+                "ip address 10.1.0.1/24",
+                "no ip address 10.2.0.1/30",
+                "exit",
+                "ip route 99.0.1.0/24 10.1.0.2",
+                "router bgp 65001",
+                "neighbor 10.0.0.2 update-source lo",
+                "no neighbor 10.0.0.3 remote-as 65001",
+                "address-family ipv4",
+                "neighbor 10.0.0.2 route-map rm-in in",
+                "exit-address-family",
+                "exit",
+                "bgp community-list standard rm-in_CL_del permit 100",
+                "no bgp community-list standard rm-in_CL_match",
+                "route-map rm-in permit 10",
+                "set local-preference +0",
+                "set metric 20",
+                "set comm-list rm-in_CL_del delete",
+                "exit",
+            ]))
+            .unwrap(),
+            cmds(&[
+                "interface Gi0/0",
+                "ip address 10.1.0.1 255.255.255.0",
+                "no ip address 10.2.0.1 255.255.255.252",
+                "exit",
+                "ip route 99.0.1.0 255.255.255.0 10.1.0.2",
+                "router bgp 65001",
+                "neighbor 10.0.0.2 update-source Loopback0",
+                "no neighbor 10.0.0.3",
+                "address-family ipv4 unicast",
+                "neighbor 10.0.0.2 route-map rm-in in",
+                "exit-address-family",
+                "exit",
+                "ip community-list standard rm-in_CL_del permit 100",
+                "no ip community-list standard rm-in_CL_match",
+                "route-map rm-in permit 10",
+                "set metric 20",
+                "set comm-list rm-in_CL_del delete",
+                "exit",
+            ])
+        );
+    }
+
+    #[test]
+    fn translate_unsupported() {
+        assert!(translate_commands(&cmds(&["match peer 10.1.0.2/24"])).is_err());
+        assert!(translate_commands(&cmds(&["ipv6 address fd00::1/64"])).is_err());
+        assert!(translate_commands(&cmds(&["no ipv6 route fd00::/64 eth0"])).is_err());
+        assert!(translate_commands(&cmds(&["router ospf6"])).is_err());
+        assert!(translate_commands(&cmds(&["address-family ipv6 unicast"])).is_err());
+    }
+
+    #[test]
+    fn routing_table() {
+        let table = parse_routing_table(
+            "show ip route
+Codes: L - local, C - connected, S - static, R - RIP, M - mobile, B - BGP
+       D - EIGRP, EX - EIGRP external, O - OSPF, IA - OSPF inter area
+Gateway of last resort is not set
+
+      10.0.0.0/8 is variably subnetted, 4 subnets, 2 masks
+C        10.0.0.1/32 is directly connected, Loopback0
+O        10.0.0.2/32 [110/11] via 10.1.0.2, 00:01:02, GigabitEthernet0/0
+C        10.1.0.0/24 is directly connected, GigabitEthernet0/0
+L        10.1.0.1/32 is directly connected, GigabitEthernet0/0
+      99.0.0.0/24 is subnetted, 1 subnets
+S        99.0.1.0 [1/0] via 10.1.0.2
+B        20.0.0.0/24 [20/0] via 10.1.0.2, 00:00:12
+node#",
+        );
+        assert_eq!(table.entries.len(), 6);
+
+        let e = &table.entries[0];
+        assert_eq!(e.origin, RteOrigin::Connected);
+        assert_eq!(e.network, IpAddr::new("10.0.0.1", 32));
+        assert_eq!(e.next_hop_addr, None);
+        assert_eq!(e.next_hop_iface, "Loopback0");
+
+        let e = &table.entries[1];
+        assert_eq!(e.origin, RteOrigin::Ospf);
+        assert_eq!(e.network, IpAddr::new("10.0.0.2", 32));
+        assert_eq!(e.administrative_distance, 110);
+        assert_eq!(e.metric, 11);
+        assert_eq!(e.next_hop_addr, Some(String::from("10.1.0.2")));
+        assert_eq!(e.next_hop_iface, "GigabitEthernet0/0");
+
+        let e = &table.entries[4];
+        assert_eq!(e.origin, RteOrigin::Static);
+        assert_eq!(e.network, IpAddr::new("99.0.1.0", 24));
+        assert_eq!(e.next_hop_iface, "");
+
+        let e = &table.entries[5];
+        assert_eq!(e.origin, RteOrigin::Bgp);
+        assert_eq!(e.network, IpAddr::new("20.0.0.0", 24));
+        assert_eq!(e.administrative_distance, 20);
+    }
+}
@@ -20,11 +20,13 @@
 use crate::physical_network::*;
 use snowcap::netsim::config::{
     Config,
-    ConfigExpr::{BgpRouteMap, BgpSession, IgpLinkWeight, StaticRoute},
+    ConfigExpr::{
+        BgpAlwaysCompareMed, BgpClusterId, BgpRouteMap, BgpSession, IgpLinkWeight, StaticRoute,
+    },
     ConfigModifier::{self, Insert, Remove, Update},
 };
 use snowcap::netsim::route_map::RouteMapDirection;
-use snowcap::netsim::{BgpSessionType, RouterId};
+use snowcap::netsim::{Afi, BgpSessionType, Prefix, RouterId};
 
 /// Apply an entire configuration on the physical network. This funciton generates no commands to be
 /// executed. If this is needed, use [`parse_modifier`].
@@ -133,27 +135,39 @@ pub fn parse_modifier(
                     && phys_net.routers[target.index()].is_internal
                 {
                     // set the cost
-                    phys_net.routers[source.index()].ifaces[link_idx].cost =
-                        Some(weight.round() as u32);
-                    vec![(
+                    let cost = weight.round() as u32;
+                    phys_net.routers[source.index()].ifaces[link_idx].cost = Some(cost);
+                    let mut cmds = vec![
+                        format!("interface {}", iface_name),
+                        format!("ip address {}", iface_addr),
+                        format!("ip ospf 1 area 0"),
+                        format!("ip ospf cost {}", cost),
+                    ];
+                    cmds.extend(ipv6_iface_cmds(
+                        phys_net,
                         *source,
-                        vec![
-                            format!("interface {}", iface_name),
-                            format!("ip address {}", iface_addr),
-                            format!("ip ospf 1 area 0"),
-                            format!("ip ospf cost {}", weight.round() as u32),
-                        ],
-                    )]
+                        &iface_name,
+                        &iface_addr,
+                        Some(cost),
+                        true,
+                    ));
+                    vec![(*source, cmds)]
                 } else {
                     // enable the interface wihtout setting the cost
                     phys_net.routers[source.index()].ifaces[link_idx].cost = None;
-                    vec![(
+                    let mut cmds = vec![
+                        format!("interface {}", iface_name),
+                        format!("ip address {}", iface_addr),
+                    ];
+                    cmds.extend(ipv6_iface_cmds(
+                        phys_net,
                         *source,
-                        vec![
-                            format!("interface {}", iface_name),
-                            format!("ip address {}", iface_addr),
-                        ],
-                    )]
+                        &iface_name,
+                        &iface_addr,
+                        None,
+                        true,
+                    ));
+                    vec![(*source, cmds)]
                 }
             }
         }
@@ -166,8 +180,9 @@ pub fn parse_modifier(
                 RouteMapInfo::from_route_map(rm_name, *router, *direction, map, &phys_net.routers);
             phys_net.routers[router.index()].route_maps.push(rm.clone());
 
-            // create the updates
-            let mut cmds = vec![format!("route-map {} {} {}", rm.name, rm.state, rm.order)];
+            // create the updates, starting with the community lists used by the route map
+            let mut cmds = rm.community_list_commands();
+            cmds.push(format!("route-map {} {} {}", rm.name, rm.state, rm.order));
             for (key, value) in rm.match_statements {
                 cmds.push(format!("match {} {}", key, value));
             }
@@ -182,6 +197,12 @@ pub fn parse_modifier(
             cmds.push(format!("neighbor internal route-map {} {}", rm.name, rm.direction));
             cmds.push(format!("neighbor external route-map {} {}", rm.name, rm.direction));
             cmds.push(format!("exit"));
+            if phys_net.routers[router.index()].dual_stack {
+                cmds.push(format!("address-family ipv6 unicast"));
+                cmds.push(format!("neighbor internal route-map {} {}", rm.name, rm.direction));
+                cmds.push(format!("neighbor external route-map {} {}", rm.name, rm.direction));
+                cmds.push(format!("exit"));
+            }
             cmds.push(format!("exit"));
 
             vec![(*router, cmds)]
@@ -189,18 +210,37 @@ pub fn parse_modifier(
 
         // insert the static route
         Insert(StaticRoute { router, prefix, target }) => {
-            let link_idx = get_iface_idx(phys_net, *router, *target);
-            let next_hop_addr =
-                phys_net.routers[router.index()].ifaces[link_idx].neighbor_addr.addr.clone();
-            let addr = phys_net.routers[phys_net.prefix_router_id(*prefix).index()]
-                .advertise_route
-                .as_ref()
-                .unwrap()
-                .clone();
-            phys_net.routers[router.index()]
-                .static_routes
-                .push(StaticRouteInfo { addr: addr.clone(), next_hop: next_hop_addr.clone() });
-            vec![(*router, vec![format!("ip route {} {}", addr, next_hop_addr)])]
+            let sr = StaticRouteInfo {
+                addr: get_prefix_network(phys_net, *prefix),
+                next_hop: get_next_hop_addr(phys_net, *router, *target, prefix.afi()),
+            };
+            phys_net.routers[router.index()].static_routes.push(sr.clone());
+            vec![(*router, vec![sr.command()])]
+        }
+
+        // always compare the MED
+        Insert(BgpAlwaysCompareMed { router }) => {
+            phys_net.routers[router.index()].always_compare_med = true;
+            vec![(
+                *router,
+                vec![
+                    format!("router bgp {}", phys_net.routers[router.index()].as_id.0),
+                    format!("bgp always-compare-med"),
+                ],
+            )]
+        }
+
+        // set the cluster id of a route reflector
+        Insert(BgpClusterId { router, cluster_id }) => {
+            let addr = phys_net.routers[cluster_id.index()].loopback_addr.addr.clone();
+            phys_net.routers[router.index()].cluster_id = Some(addr.clone());
+            vec![(
+                *router,
+                vec![
+                    format!("router bgp {}", phys_net.routers[router.index()].as_id.0),
+                    format!("bgp cluster-id {}", addr),
+                ],
+            )]
         }
 
         // remove the existing bgp session!
@@ -243,6 +283,30 @@ pub fn parse_modifier(
             ]
         }
 
+        // only compare the MED of routes from the same neighbor AS
+        Remove(BgpAlwaysCompareMed { router }) => {
+            phys_net.routers[router.index()].always_compare_med = false;
+            vec![(
+                *router,
+                vec![
+                    format!("router bgp {}", phys_net.routers[router.index()].as_id.0),
+                    format!("no bgp always-compare-med"),
+                ],
+            )]
+        }
+
+        // use the router id as cluster id
+        Remove(BgpClusterId { router, .. }) => {
+            phys_net.routers[router.index()].cluster_id = None;
+            vec![(
+                *router,
+                vec![
+                    format!("router bgp {}", phys_net.routers[router.index()].as_id.0),
+                    format!("no bgp cluster-id"),
+                ],
+            )]
+        }
+
         // remove the interface and the ospf cost, but only if the interface is enabled!
         Remove(IgpLinkWeight { source, target, .. }) => {
             let link_idx = get_iface_idx(phys_net, *source, *target);
@@ -255,25 +319,25 @@ pub fn parse_modifier(
                 phys_net.routers[source.index()].ifaces[link_idx].enabled = false;
                 phys_net.routers[source.index()].ifaces[link_idx].cost = None;
 
-                if let Some(old_cost) = old_cost {
-                    vec![(
-                        *source,
-                        vec![
-                            format!("interface {}", iface_name),
-                            format!("no ip ospf cost {}", old_cost),
-                            format!("no ip ospf 1 area 0"),
-                            format!("no ip address {}", old_addr),
-                        ],
-                    )]
+                let mut cmds = if let Some(old_cost) = old_cost {
+                    vec![
+                        format!("interface {}", iface_name),
+                        format!("no ip ospf cost {}", old_cost),
+                        format!("no ip ospf 1 area 0"),
+                        format!("no ip address {}", old_addr),
+                    ]
                 } else {
-                    vec![(
-                        *source,
-                        vec![
-                            format!("interface {}", iface_name),
-                            format!("no ip address {}", old_addr),
-                        ],
-                    )]
-                }
+                    vec![format!("interface {}", iface_name), format!("no ip address {}", old_addr)]
+                };
+                cmds.extend(ipv6_iface_cmds(
+                    phys_net,
+                    *source,
+                    &iface_name,
+                    &old_addr,
+                    old_cost,
+                    false,
+                ));
+                vec![(*source, cmds)]
             } else {
                 // nothing to do, interface is already disabled
                 vec![]
@@ -303,28 +367,34 @@ pub fn parse_modifier(
             cmds.push(format!("no neighbor internal route-map {} {}", rm.name, rm.direction));
             cmds.push(format!("no neighbor external route-map {} {}", rm.name, rm.direction));
             cmds.push(format!("exit"));
+            if phys_net.routers[router.index()].dual_stack {
+                cmds.push(format!("address-family ipv6 unicast"));
+                cmds.push(format!("no neighbor internal route-map {} {}", rm.name, rm.direction));
+                cmds.push(format!("no neighbor external route-map {} {}", rm.name, rm.direction));
+                cmds.push(format!("exit"));
+            }
             cmds.push(format!("exit"));
 
-            // then, delete the route map
+            // then, delete the route map and its community lists
             cmds.push(format!("no route-map {} {} {}", rm.name, rm.state, rm.order));
+            for (list, _) in rm.community_lists.iter() {
+                cmds.push(format!("no bgp community-list standard {}", list));
+            }
 
             vec![(*router, cmds)]
         }
 
         // remove the static route of the prefix, no matter where it points.
         Remove(StaticRoute { router, prefix, .. }) => {
-            let addr = phys_net.routers[phys_net.prefix_router_id(*prefix).index()]
-                .advertise_route
-                .as_ref()
-                .unwrap();
+            let addr = get_prefix_network(phys_net, *prefix);
             // search this entry and remove it from the static routes
             let pos = phys_net.routers[router.index()]
                 .static_routes
                 .iter()
-                .position(|sr| sr.addr == *addr)
+                .position(|sr| sr.addr == addr)
                 .expect("Static route to remove does not exist!");
             let old_sr = phys_net.routers[router.index()].static_routes.remove(pos);
-            vec![(*router, vec![format!("no ip route {} {}", old_sr.addr, old_sr.next_hop)])]
+            vec![(*router, vec![format!("no {}", old_sr.command())])]
         }
 
         // Here, the session can either change from RR->Source to Peer<->Peer, or viceversa. We just
@@ -410,13 +480,14 @@ pub fn parse_modifier(
                     let iface_name = get_interface_from_to(phys_net, *source, *target);
                     phys_net.routers[source.index()].ifaces[link_idx].cost =
                         Some(weight.round() as u32);
-                    vec![(
-                        *source,
-                        vec![
-                            format!("interface {}", iface_name),
-                            format!("ip ospf cost {}", weight.round() as u32),
-                        ],
-                    )]
+                    let mut cmds = vec![
+                        format!("interface {}", iface_name),
+                        format!("ip ospf cost {}", weight.round() as u32),
+                    ];
+                    if phys_net.routers[source.index()].dual_stack {
+                        cmds.push(format!("ipv6 ospf6 cost {}", weight.round() as u32));
+                    }
+                    vec![(*source, cmds)]
                 }
             } else {
                 // link is not enabled! This is the same as inserting a new modifier with the new
@@ -453,9 +524,18 @@ pub fn parse_modifier(
             // add the new rm to the datastructure
             phys_net.routers[router.index()].route_maps.push(new_rm.clone());
 
+            // add the new entries of the community lists first, and remove the old ones only after
+            // the route map is updated.
+            let old_entries = old_rm.community_list_commands();
+            let new_entries = new_rm.community_list_commands();
+
             // create the updates
-            let mut cmds =
-                vec![format!("route-map {} {} {}", new_rm.name, new_rm.state, new_rm.order)];
+            let mut cmds = new_entries
+                .iter()
+                .filter(|e| !old_entries.contains(e))
+                .cloned()
+                .collect::<Vec<_>>();
+            cmds.push(format!("route-map {} {} {}", new_rm.name, new_rm.state, new_rm.order));
 
             // modify the match statements
 
@@ -505,37 +585,40 @@ pub fn parse_modifier(
                 }
             }
 
+            // remove the old entries of the community lists
+            let old_entries =
+                old_entries.into_iter().filter(|e| !new_entries.contains(e)).collect::<Vec<_>>();
+            if !old_entries.is_empty() {
+                cmds.push(format!("exit"));
+                cmds.extend(old_entries.into_iter().map(|e| format!("no {}", e)));
+            }
+
             vec![(*router, cmds)]
         }
 
         // Change the static route to a different location. For this, the router and the prefix must
         // be the same
         Update { from: StaticRoute { router, prefix, .. }, to: StaticRoute { target, .. } } => {
-            let link_idx = get_iface_idx(phys_net, *router, *target);
-            let new_next_hop_addr =
-                phys_net.routers[router.index()].ifaces[link_idx].neighbor_addr.addr.clone();
-            let addr = phys_net.routers[phys_net.prefix_router_id(*prefix).index()]
-                .advertise_route
-                .as_ref()
-                .unwrap()
-                .clone();
+            let new_sr = StaticRouteInfo {
+                addr: get_prefix_network(phys_net, *prefix),
+                next_hop: get_next_hop_addr(phys_net, *router, *target, prefix.afi()),
+            };
             // search this entry and remove it from the static routes
             let pos = phys_net.routers[router.index()]
                 .static_routes
                 .iter()
-                .position(|sr| sr.addr == addr)
+                .position(|sr| sr.addr == new_sr.addr)
                 .expect("Static route to remove does not exist!");
             let old_sr = phys_net.routers[router.index()].static_routes.remove(pos);
-            phys_net.routers[router.index()]
-                .static_routes
-                .push(StaticRouteInfo { addr: addr.clone(), next_hop: new_next_hop_addr.clone() });
-            vec![(
-                *router,
-                vec![
-                    format!("ip route {} {}", addr, new_next_hop_addr),
-                    format!("no ip route {} {}", old_sr.addr, old_sr.next_hop),
-                ],
-            )]
+            phys_net.routers[router.index()].static_routes.push(new_sr.clone());
+            vec![(*router, vec![new_sr.command(), format!("no {}", old_sr.command())])]
+        }
+        // Change the cluster id. The new cluster id simply replaces the old one.
+        Update { from: BgpClusterId { .. }, to: BgpClusterId { router, cluster_id } } => {
+            parse_modifier(
+                phys_net,
+                &Insert(BgpClusterId { router: *router, cluster_id: *cluster_id }),
+            )
         }
         modifier => panic!("Invalid Modifier: {:?}", modifier),
     }
@@ -550,6 +633,56 @@ fn get_interface_from_to(
     phys_net.routers[source.index()].ifaces[iface_idx].gns_interface.name.clone()
 }
 
+/// Returns the commands to enable (or disable) IPv6 on an interface of a dual-stack router, which
+/// must be executed in the interface node. If the `cost` is set, the interface is also added to (or
+/// removed from) OSPFv3. For routers without IPv6, no commands are returned.
+fn ipv6_iface_cmds(
+    phys_net: &PhysicalNetwork,
+    router: RouterId,
+    iface_name: &str,
+    iface_addr: &IpAddr,
+    cost: Option<u32>,
+    enable: bool,
+) -> Vec<String> {
+    if !phys_net.routers[router.index()].dual_stack {
+        return vec![];
+    }
+    let no = if enable { "" } else { "no " };
+    let mut cmds = vec![format!("{}ipv6 address {}", no, iface_addr.to_v6())];
+    if let Some(cost) = cost {
+        cmds.push(format!("{}ipv6 ospf6 cost {}", no, cost));
+        cmds.push(format!("exit"));
+        cmds.push(format!("router ospf6"));
+        cmds.push(format!("{}interface {} area 0.0.0.0", no, iface_name));
+    }
+    cmds
+}
+
+/// Returns the network advertised by the origin router of the prefix.
+fn get_prefix_network(phys_net: &PhysicalNetwork, prefix: Prefix) -> IpAddr {
+    let origin = &phys_net.routers[phys_net.prefix_router_id(prefix).index()];
+    match prefix.afi() {
+        Afi::Ipv4 => origin.advertise_route.clone(),
+        Afi::Ipv6 => origin.advertise_route_v6.clone(),
+    }
+    .expect("The origin router does not advertise the prefix")
+}
+
+/// Returns the address of the neighbor `target`, which is used as next hop for static routes.
+fn get_next_hop_addr(
+    phys_net: &mut PhysicalNetwork,
+    router: RouterId,
+    target: RouterId,
+    afi: Afi,
+) -> String {
+    let link_idx = get_iface_idx(phys_net, router, target);
+    let neighbor_addr = &phys_net.routers[router.index()].ifaces[link_idx].neighbor_addr;
+    match afi {
+        Afi::Ipv4 => neighbor_addr.addr.clone(),
+        Afi::Ipv6 => neighbor_addr.to_v6().addr,
+    }
+}
+
 fn get_iface_idx(phys_net: &mut PhysicalNetwork, source: RouterId, target: RouterId) -> usize {
     phys_net.routers[source.index()]
         .ifaces
@@ -0,0 +1,253 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Differential Testing
+//!
+//! This module compares the converged forwarding state of the simulator (netsim) with the state
+//! of the runtime (FRR inside GNS3). For each seed, it generates a small random network, sets it
+//! up inside GNS3, and compares the path of every internal router towards every prefix (obtained
+//! using traceroute) with the path predicted by netsim. Every mismatch is reported together with
+//! the configuration and the event trace of netsim, such that the situation can be replayed and
+//! debugged in the simulator. Use [`differential_test`] to run the harness on a corpus of seeds.
+
+use crate::physical_network::PhysicalNetwork;
+use snowcap::netsim::config::{Config, ConfigExpr};
+use snowcap::netsim::{printer, AsId, BgpSessionType, Network, NetworkError, Prefix, RouterId};
+
+use log::*;
+use rand::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::error::Error;
+
+/// Result of comparing the simulator and the runtime on a single random network.
+#[derive(Debug, Clone, Serialize)]
+pub struct DifferentialReport {
+    /// Seed used to generate the network
+    pub seed: u64,
+    /// Configuration of the network, formatted as strings
+    pub config: Vec<String>,
+    /// All paths, where the simulator and the runtime disagree
+    pub mismatches: Vec<PathMismatch>,
+    /// Events processed by netsim to reach the converged state, in order
+    pub event_trace: Vec<String>,
+}
+
+impl DifferentialReport {
+    /// Returns `true` if the simulator and the runtime agree on all paths.
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Path from a router to a prefix, on which the simulator and the runtime disagree. A path of
+/// `None` means that the prefix is not reachable from the router.
+#[derive(Debug, Clone, Serialize)]
+pub struct PathMismatch {
+    /// Name of the router
+    pub router: String,
+    /// Destination prefix
+    pub prefix: u32,
+    /// Path computed by netsim
+    pub expected: Option<Vec<String>>,
+    /// Path measured in the runtime
+    pub actual: Option<Vec<String>>,
+}
+
+/// Generate a random network with `num_routers` internal routers (at least 3) and two external
+/// routers, configured with random link weights and either a full iBGP mesh or a single route
+/// reflector. Both external routers advertise prefix 0, and the second one also advertises
+/// prefix 1. The same seed always results in the same network.
+pub fn random_network(seed: u64, num_routers: usize) -> Result<Network, NetworkError> {
+    let num_routers = num_routers.max(3);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut net = Network::new();
+    let mut c = Config::new();
+
+    let routers: Vec<RouterId> =
+        (0..num_routers).map(|i| net.add_router(format!("R{}", i))).collect();
+    let e1 = net.add_external_router("E1", AsId(65101));
+    let e2 = net.add_external_router("E2", AsId(65102));
+
+    // ring of all routers, plus some random chords
+    let mut links: HashSet<(RouterId, RouterId)> = (0..num_routers)
+        .map(|i| (routers[i], routers[(i + 1) % num_routers]))
+        .map(|(a, b)| if a < b { (a, b) } else { (b, a) })
+        .collect();
+    for _ in 0..num_routers / 2 {
+        let mut pair = routers.choose_multiple(&mut rng, 2).cloned().collect::<Vec<_>>();
+        pair.sort();
+        links.insert((pair[0], pair[1]));
+    }
+    let mut links: Vec<(RouterId, RouterId)> = links.into_iter().collect();
+    links.sort();
+    for (a, b) in links {
+        net.add_link(a, b);
+        c.add(ConfigExpr::IgpLinkWeight {
+            source: a,
+            target: b,
+            weight: rng.gen_range(1, 10) as f64,
+        })
+        .unwrap();
+        c.add(ConfigExpr::IgpLinkWeight {
+            source: b,
+            target: a,
+            weight: rng.gen_range(1, 10) as f64,
+        })
+        .unwrap();
+    }
+
+    // connect the external routers to distinct internal routers
+    let border = routers.choose_multiple(&mut rng, 2).cloned().collect::<Vec<_>>();
+    for (b, e) in border.into_iter().zip(vec![e1, e2]) {
+        net.add_link(b, e);
+        c.add(ConfigExpr::IgpLinkWeight { source: b, target: e, weight: 1.0 }).unwrap();
+        c.add(ConfigExpr::IgpLinkWeight { source: e, target: b, weight: 1.0 }).unwrap();
+        c.add(ConfigExpr::BgpSession { source: b, target: e, session_type: BgpSessionType::EBgp })
+            .unwrap();
+    }
+
+    // iBGP sessions
+    if rng.gen_bool(0.5) {
+        for (i, a) in routers.iter().enumerate() {
+            for b in routers.iter().skip(i + 1) {
+                c.add(ConfigExpr::BgpSession {
+                    source: *a,
+                    target: *b,
+                    session_type: BgpSessionType::IBgpPeer,
+                })
+                .unwrap();
+            }
+        }
+    } else {
+        let rr = *routers.choose(&mut rng).unwrap();
+        for r in routers.iter().filter(|r| **r != rr) {
+            c.add(ConfigExpr::BgpSession {
+                source: rr,
+                target: *r,
+                session_type: BgpSessionType::IBgpClient,
+            })
+            .unwrap();
+        }
+    }
+
+    net.set_config(&c)?;
+    let path_len = rng.gen_range(0, 2);
+    net.advertise_external_route(e1, Prefix(0), vec![AsId(65101), AsId(65200)], None, None)?;
+    net.advertise_external_route(
+        e2,
+        Prefix(0),
+        std::iter::once(AsId(65102))
+            .chain(std::iter::repeat(AsId(65102)).take(path_len))
+            .chain(std::iter::once(AsId(65200)))
+            .collect(),
+        None,
+        None,
+    )?;
+    net.advertise_external_route(e2, Prefix(1), vec![AsId(65102), AsId(65201)], None, None)?;
+
+    Ok(net)
+}
+
+/// Compare the converged forwarding state of the network with the paths measured in the runtime.
+/// The physical network must be set up with the same network (see [`PhysicalNetwork::new`]).
+pub fn compare_forwarding_state(
+    net: &Network,
+    phys_net: &PhysicalNetwork,
+) -> Result<Vec<PathMismatch>, Box<dyn Error>> {
+    let mut fw_state = net.get_forwarding_state();
+    let all_paths = phys_net.get_all_paths()?;
+    let internal_routers = net.get_routers();
+    let mut mismatches = Vec::new();
+
+    let mut all_paths = all_paths
+        .into_iter()
+        .filter(|(router, _)| internal_routers.contains(router))
+        .flat_map(|(router, paths)| paths.into_iter().map(move |(p, path)| (router, p, path)))
+        .collect::<Vec<_>>();
+    all_paths.sort_by_key(|(router, prefix, _)| (*router, *prefix));
+
+    for (router, prefix, path) in all_paths {
+        // the last hop of the traceroute is the origin of the prefix, which is not part of netsim
+        let actual = path.map(|p| {
+            let len = p.len();
+            std::iter::once(router).chain(p.into_iter()).take(len).collect::<Vec<_>>()
+        });
+        let expected = fw_state.get_route(router, prefix).ok();
+        if actual != expected {
+            let names = |path: Option<Vec<RouterId>>| {
+                path.map(|p| p.into_iter().map(|r| phys_net.router_name(r).to_string()).collect())
+            };
+            let mismatch = PathMismatch {
+                router: phys_net.router_name(router).to_string(),
+                prefix: prefix.0,
+                expected: names(expected),
+                actual: names(actual),
+            };
+            warn!(
+                "Mismatch at {} for prefix {}: expected {:?}, measured {:?}",
+                mismatch.router, mismatch.prefix, mismatch.expected, mismatch.actual
+            );
+            mismatches.push(mismatch);
+        }
+    }
+
+    Ok(mismatches)
+}
+
+/// Run the differential test for every seed, on a random network with `num_routers` internal
+/// routers (see [`random_network`]). Returns one report for each seed. If `json_filename` is set,
+/// then all reports are also stored in this file.
+pub fn differential_test(
+    seeds: impl IntoIterator<Item = u64>,
+    num_routers: usize,
+    persistent_gns_project: bool,
+    json_filename: Option<String>,
+) -> Result<Vec<DifferentialReport>, Box<dyn Error>> {
+    let mut reports = Vec::new();
+    for seed in seeds {
+        info!("Differential test with seed {}", seed);
+        let net = random_network(seed, num_routers)?;
+        let phys_net = PhysicalNetwork::new(&net, "DifferentialNet", persistent_gns_project)?;
+        let mismatches = compare_forwarding_state(&net, &phys_net)?;
+        if mismatches.is_empty() {
+            info!("Simulator and runtime agree on all paths (seed {})", seed);
+        } else {
+            error!("Found {} mismatches (seed {})", mismatches.len(), seed);
+        }
+
+        let mut config = net
+            .current_config()
+            .iter()
+            .map(|expr| printer::config_expr(&net, expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        config.sort();
+
+        reports.push(DifferentialReport {
+            seed,
+            config,
+            mismatches,
+            event_trace: net.event_trace(),
+        });
+    }
+
+    if let Some(json_filename) = json_filename {
+        std::fs::write(json_filename, serde_json::to_string(&reports)?)?;
+    }
+
+    Ok(reports)
+}
@@ -0,0 +1,163 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Device Drivers
+//!
+//! The physical network may consist of routers from different vendors. Every router is driven by
+//! a [`RouterDriver`], which applies the configuration, translates the commands of every migration
+//! step into the dialect of the device, and scrapes the routing table and the result of
+//! traceroute. The commands of a migration step are always generated in FRR syntax by
+//! [`parse_modifier`](crate::config::parse_modifier), and then translated by the driver.
+//!
+//! Which driver is used for a router is given by its [`RouterKind`]. The following devices are
+//! supported:
+//!
+//! - [`RouterKind::Frr`]: FRRouting, using [`FrrConnection`] (default).
+//! - [`RouterKind::Bird`]: The BIRD Internet Routing Daemon, using [`BirdConnection`]. The entire
+//!   configuration file is regenerated after every step.
+//! - [`RouterKind::CiscoIosvL2`]: Cisco IOSvL2, using [`CiscoConnection`].
+//!
+//! Only FRR supports dual-stack routers. The origin routers are always FRR routers.
+
+use crate::bird_conn::BirdConnection;
+use crate::cisco_conn::CiscoConnection;
+use crate::frr_conn::{FrrConnection, RoutingTable};
+use crate::physical_network::{IpAddr, PhysicalRouter};
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Type of the router image in GNS3, which determines the driver used to configure it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RouterKind {
+    /// FRRouting
+    Frr,
+    /// BIRD Internet Routing Daemon
+    Bird,
+    /// Cisco IOSvL2
+    CiscoIosvL2,
+}
+
+impl Default for RouterKind {
+    fn default() -> Self {
+        Self::Frr
+    }
+}
+
+impl RouterKind {
+    /// Name of the GNS3 template of the router image
+    pub fn template_name(&self) -> &'static str {
+        match self {
+            Self::Frr => "FRR 7.3.1",
+            Self::Bird => "BIRD 2.0.7",
+            Self::CiscoIosvL2 => "Cisco IOSvL2 15.2.1",
+        }
+    }
+
+    /// Connect to the router, running at the given telnet port.
+    pub fn connect(&self, port: u16) -> Result<Box<dyn RouterDriver>, Box<dyn Error>> {
+        Ok(match self {
+            Self::Frr => Box::new(FrrConnection::new(port)?),
+            Self::Bird => Box::new(BirdConnection::new(port)?),
+            Self::CiscoIosvL2 => Box::new(CiscoConnection::new(port)?),
+        })
+    }
+}
+
+impl fmt::Display for RouterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Frr => write!(f, "FRR"),
+            Self::Bird => write!(f, "BIRD"),
+            Self::CiscoIosvL2 => write!(f, "Cisco IOSvL2"),
+        }
+    }
+}
+
+/// Connection to a router of a specific vendor.
+pub trait RouterDriver: fmt::Debug {
+    /// Apply the entire configuration of the router. This is called once, after the router has
+    /// booted.
+    fn initialize_config(&mut self, router: &PhysicalRouter) -> Result<(), Box<dyn Error>>;
+
+    /// Translate the commands of a single migration step, given in FRR syntax, into the commands
+    /// of the device. `router` already contains the configuration after the step. Returns an error
+    /// if the step cannot be expressed on the device.
+    fn translate(
+        &self,
+        router: &PhysicalRouter,
+        commands: &[String],
+    ) -> Result<Vec<String>, Box<dyn Error>>;
+
+    /// Execute the commands, returned by [`RouterDriver::translate`].
+    fn execute(&mut self, commands: Vec<String>) -> Result<(), Box<dyn Error>>;
+
+    /// Translate and execute the commands of a single migration step.
+    fn apply_step(
+        &mut self,
+        router: &PhysicalRouter,
+        commands: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        let commands = self.translate(router, commands)?;
+        self.execute(commands)
+    }
+
+    /// Shut down (or enable, if `up` is set) the interface.
+    fn set_interface_state(&mut self, iface: &str, up: bool) -> Result<(), Box<dyn Error>>;
+
+    /// Returns the current routing table. Only the entries are compared, to check if the network
+    /// has converged.
+    fn get_routing_table(&mut self) -> Result<RoutingTable, Box<dyn Error>>;
+
+    /// Perform a traceroute, and get the result back, as a vector of IPv4 addresses. Returns
+    /// `None` if the target is not reached.
+    fn traceroute(&mut self, target: &IpAddr) -> Result<Option<Vec<[u8; 4]>>, Box<dyn Error>>;
+}
+
+impl RouterDriver for FrrConnection {
+    fn initialize_config(&mut self, router: &PhysicalRouter) -> Result<(), Box<dyn Error>> {
+        FrrConnection::initialize_config(self, router)
+    }
+
+    fn translate(
+        &self,
+        _router: &PhysicalRouter,
+        commands: &[String],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(commands.to_vec())
+    }
+
+    fn execute(&mut self, commands: Vec<String>) -> Result<(), Box<dyn Error>> {
+        self.reconfigure(commands)
+    }
+
+    fn set_interface_state(&mut self, iface: &str, up: bool) -> Result<(), Box<dyn Error>> {
+        self.reconfigure(vec![
+            format!("interface {}", iface),
+            if up { "no shutdown" } else { "shutdown" }.to_string(),
+        ])
+    }
+
+    fn get_routing_table(&mut self) -> Result<RoutingTable, Box<dyn Error>> {
+        FrrConnection::get_routing_table(self)
+    }
+
+    fn traceroute(&mut self, target: &IpAddr) -> Result<Option<Vec<[u8; 4]>>, Box<dyn Error>> {
+        FrrConnection::traceroute(self, target)
+    }
+}
@@ -0,0 +1,202 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Failover Drills
+//!
+//! This module checks the reliability conditions on the running network, after the migration is
+//! complete. Every link on the path of a [`Condition::Reliable`] is failed in turn, the probes are
+//! captured until the network has converged, and the link is restored afterwards. The simulation
+//! only claims reliability, while the drill shows that the routers actually fail over.
+
+use snowcap::hard_policies::Condition;
+use snowcap::netsim::config::{ConfigExpr, ConfigModifier};
+use snowcap::netsim::{Network, NetworkError, Prefix, RouterId};
+
+use log::*;
+use serde::Serialize;
+use std::error::Error;
+
+use super::physical_network::{PhysicalNetwork, CLIENT_ID_BASE};
+
+/// # Result of a failover drill
+/// Outcome of every reliability condition, while a single link was down.
+#[derive(Debug, Clone, Serialize)]
+pub struct DrillReport {
+    /// Names of the two routers of the failed link.
+    pub link: Vec<String>,
+    /// Outcome of every reliability condition.
+    pub outcomes: Vec<DrillOutcome>,
+}
+
+impl DrillReport {
+    /// Returns `true` if every router could reach its prefix after the failure.
+    pub fn is_ok(&self) -> bool {
+        self.outcomes.iter().all(|o| o.reachable)
+    }
+}
+
+/// # Outcome of a reliability condition
+/// Reachability of a prefix from a single router, measured with the probes while the link was down.
+#[derive(Debug, Clone, Serialize)]
+pub struct DrillOutcome {
+    /// Name of the router at which the flow enters the network.
+    pub router: String,
+    /// Prefix of the flow.
+    pub prefix: u32,
+    /// Wether the last probe, sent after the network has converged, reached the prefix.
+    pub reachable: bool,
+    /// Wether the simulation expects the prefix to be reachable while the link is down.
+    pub expected_reachable: bool,
+    /// Path of the last probe (empty if it was dropped).
+    pub path: Vec<String>,
+    /// Number of probes dropped while the network converged.
+    pub num_dropped: usize,
+    /// Number of probes sent while the link was down.
+    pub num_probes: usize,
+}
+
+/// Returns all links between internal routers, which are on the path of any
+/// [`Condition::Reliable`] in the current state of the network. Every link is only returned once,
+/// with the smaller router id first.
+pub fn drill_links(net: &Network, conditions: &[Condition]) -> Vec<(RouterId, RouterId)> {
+    let mut fw_state = net.get_forwarding_state();
+    let mut links = Vec::new();
+    for (r, p) in reliable(conditions) {
+        let path = match fw_state.get_route(r, p) {
+            Ok(path) => path,
+            Err(_) => continue,
+        };
+        for hop in path.windows(2) {
+            if net.get_device(hop[0]).is_internal() && net.get_device(hop[1]).is_internal() {
+                let link = (hop[0].min(hop[1]), hop[0].max(hop[1]));
+                if !links.contains(&link) {
+                    links.push(link);
+                }
+            }
+        }
+    }
+    links
+}
+
+/// Perform the failover drill on the physical network, which must be in the same state as `net`.
+/// For every link returned by [`drill_links`], the link is failed, and the probes are captured
+/// until the network has converged. Afterwards, the link is restored, and the function waits for
+/// the network to converge again before failing the next link.
+pub fn failover_drill(
+    phys_net: &mut PhysicalNetwork,
+    net: &Network,
+    conditions: &[Condition],
+) -> Result<Vec<DrillReport>, Box<dyn Error>> {
+    let mut reports = Vec::new();
+    for (a, b) in drill_links(net, conditions) {
+        let link = vec![phys_net.router_name(a).to_string(), phys_net.router_name(b).to_string()];
+        info!("Failing the link {} -- {}", link[0], link[1]);
+        phys_net.fail_link_wait_convergence_check_flows(a, b)?;
+        let samples = phys_net.read_path_samples()?;
+
+        // simulate the same link failure
+        let mut sim_net = net.clone();
+        for (source, target) in vec![(a, b), (b, a)] {
+            let weight = 1.0;
+            match sim_net.apply_modifier(&ConfigModifier::Remove(ConfigExpr::IgpLinkWeight {
+                source,
+                target,
+                weight,
+            })) {
+                Ok(())
+                | Err(NetworkError::NoConvergence)
+                | Err(NetworkError::ConvergenceLoop(_, _)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let mut fw_state = sim_net.get_forwarding_state();
+
+        let mut outcomes = Vec::new();
+        for (router, prefix) in reliable(conditions) {
+            let client: RouterId = (router.index() as u32 + CLIENT_ID_BASE).into();
+            let probes = samples.get(&(client, prefix)).map(|s| s.as_slice()).unwrap_or_default();
+            let last_path = probes.last().and_then(|s| s.path.clone());
+            let outcome = DrillOutcome {
+                router: phys_net.router_name(router).to_string(),
+                prefix: prefix.0,
+                reachable: last_path.is_some(),
+                expected_reachable: fw_state.get_route(router, prefix).is_ok(),
+                path: last_path
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|r| phys_net.router_name(r).to_string())
+                    .collect(),
+                num_dropped: probes.iter().filter(|s| s.path.is_none()).count(),
+                num_probes: probes.len(),
+            };
+            if !outcome.reachable {
+                warn!(
+                    "{} cannot reach prefix {} while {} -- {} is down",
+                    outcome.router, outcome.prefix, link[0], link[1]
+                );
+            }
+            outcomes.push(outcome);
+        }
+
+        info!("Restoring the link {} -- {}", link[0], link[1]);
+        phys_net.set_link_state(a, b, true)?;
+        phys_net.wait_converge()?;
+
+        reports.push(DrillReport { link, outcomes });
+    }
+    Ok(reports)
+}
+
+/// Returns the router and prefix of every reliability condition.
+fn reliable(conditions: &[Condition]) -> impl Iterator<Item = (RouterId, Prefix)> + '_ {
+    conditions.iter().filter_map(|c| match c {
+        Condition::Reliable(r, p, _) => Some((*r, *p)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use snowcap::example_networks::{ExampleNetwork, SimpleNet};
+
+    #[test]
+    fn links_on_reliable_paths() {
+        let net = SimpleNet::net(0);
+        let p = *net.get_known_prefixes().iter().next().unwrap();
+        let routers = net.get_routers();
+
+        // no reliability condition, no link to fail
+        let reachable =
+            routers.iter().map(|r| Condition::Reachable(*r, p, None)).collect::<Vec<_>>();
+        assert!(drill_links(&net, &reachable).is_empty());
+
+        let reliable = routers.iter().map(|r| Condition::Reliable(*r, p, None)).collect::<Vec<_>>();
+        let links = drill_links(&net, &reliable);
+        let mut fw_state = net.get_forwarding_state();
+        for r in routers.iter() {
+            let path = fw_state.get_route(*r, p).unwrap();
+            for hop in path.windows(2).filter(|h| net.get_device(h[1]).is_internal()) {
+                assert!(links.contains(&(hop[0].min(hop[1]), hop[0].max(hop[1]))));
+            }
+        }
+        for (a, b) in links.iter() {
+            assert!(a < b);
+            assert_eq!(links.iter().filter(|l| **l == (*a, *b)).count(), 1);
+        }
+    }
+}
@@ -26,6 +26,7 @@ use telnet::{Telnet, TelnetEvent};
 use std::thread::sleep;
 
 use std::error::Error;
+use std::net::Ipv6Addr;
 use std::str;
 use std::time::{Duration, SystemTime};
 
@@ -66,7 +67,8 @@ impl FrrConnection {
         let prompt_re = Regex::new(r"(?m)[a-zA-Z0-9_\-.():~/]+# \z").unwrap();
         let root_prompt_re = Regex::new(r"(?m)[a-zA-Z0-9_\-.]+# \z").unwrap();
         let traceroute_re =
-            Regex::new(r"^ ?\d{1,2} +(\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}) +\d+\.\d+ ms$").unwrap();
+            Regex::new(r"^ ?\d{1,2} +(\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}|[0-9a-f:.]+:[0-9a-f:.]*) +\d+\.\d+ ms$")
+                .unwrap();
 
         let mut c = Telnet::connect(("localhost", port), 2048)?;
         // receive all initial events
@@ -131,7 +133,9 @@ impl FrrConnection {
         Ok(())
     }
 
-    /// Perform a traceroute, and get the result back, as a vector of IP addresses.
+    /// Perform a traceroute, and get the result back, as a vector of IP addresses. If the target is
+    /// an IPv6 address of a dual-stack router (see [`IpAddr::to_v6`]), then the IPv4 addresses
+    /// embedded in the IPv6 addresses of all hops are returned.
     pub fn traceroute(&mut self, target: &IpAddr) -> Result<Option<Vec<[u8; 4]>>, Box<dyn Error>> {
         // check that we are in normal mode
         self.check_normal_mode()?;
@@ -176,13 +180,19 @@ impl FrrConnection {
             match self.traceroute_re.captures(line) {
                 Some(caps) => {
                     let addr = caps.get(1).unwrap().as_str();
-                    let mut parts = addr.split('.');
-                    path.push([
-                        parts.next().unwrap().parse::<u8>()?,
-                        parts.next().unwrap().parse::<u8>()?,
-                        parts.next().unwrap().parse::<u8>()?,
-                        parts.next().unwrap().parse::<u8>()?,
-                    ]);
+                    if addr.contains(':') {
+                        // IPv6 addresses embed the IPv4 address in the last 32 bits.
+                        let octets = addr.parse::<Ipv6Addr>()?.octets();
+                        path.push([octets[12], octets[13], octets[14], octets[15]]);
+                    } else {
+                        let mut parts = addr.split('.');
+                        path.push([
+                            parts.next().unwrap().parse::<u8>()?,
+                            parts.next().unwrap().parse::<u8>()?,
+                            parts.next().unwrap().parse::<u8>()?,
+                            parts.next().unwrap().parse::<u8>()?,
+                        ]);
+                    }
                 }
                 None => {
                     // Seems like the traceroute failed
@@ -219,6 +229,21 @@ impl FrrConnection {
             self.send_wait(format!("echo 0 > /proc/sys/net/ipv4/conf/{}/rp_filter\n", iface.name))?;
         }
 
+        // enable ipv6 forwarding
+        if router.dual_stack {
+            self.send_wait("echo 1 > /proc/sys/net/ipv6/conf/all/forwarding\n")?;
+        }
+
+        // get an address on the management interface, and load the BMP module of bgpd
+        if let Some(bmp) = router.bmp.as_ref() {
+            self.send_wait(format!("udhcpc -i {} -n -q\n", bmp.iface))?;
+            self.send_wait(format!("ip route del default dev {}\n", bmp.iface))?;
+            self.send_wait(
+                "sed -i 's/^bgpd_options=\"/bgpd_options=\"-M bmp /' /etc/frr/daemons\n",
+            )?;
+            self.send_wait("/usr/lib/frr/frrinit.sh restart\n")?;
+        }
+
         self.send_wait("vtysh\n")?;
 
         self.send_wait("terminal length 0\n")?;
@@ -232,6 +257,9 @@ impl FrrConnection {
         // configure loopback interface
         self.config_expr("interface lo\n")?;
         self.config_expr(format!("ip address {}/32\n", router.loopback_addr.addr))?;
+        if router.dual_stack {
+            self.config_expr(format!("ipv6 address {}/128\n", router.loopback_addr.to_v6().addr))?;
+        }
         self.config_expr("exit\n")?;
 
         // confgure ospf
@@ -242,6 +270,20 @@ impl FrrConnection {
             self.config_expr("exit\n")?;
         }
 
+        // configure ospfv3 on dual-stack routers
+        if router.dual_stack && router.ifaces.iter().any(|i| i.cost.is_some()) {
+            self.config_expr("router ospf6\n")?;
+            self.config_expr(format!("ospf6 router-id {}\n", router.loopback_addr.addr))?;
+            self.config_expr("redistribute connected\n")?;
+            for iface in router.ifaces.iter().filter(|i| i.enabled && i.cost.is_some()) {
+                self.config_expr(format!(
+                    "interface {} area 0.0.0.0\n",
+                    iface.gns_interface.short_name
+                ))?;
+            }
+            self.config_expr("exit\n")?;
+        }
+
         // configure every interface
         for iface in &router.ifaces {
             // extract the cost from the configuration
@@ -252,12 +294,21 @@ impl FrrConnection {
                     self.config_expr("ip ospf 1 area 0\n")?;
                     self.config_expr(format!("ip ospf cost {}\n", cost))?;
                 }
+                if router.dual_stack {
+                    self.config_expr(format!("ipv6 address {}\n", iface.iface_addr.to_v6()))?;
+                    if let Some(cost) = iface.cost.as_ref() {
+                        self.config_expr(format!("ipv6 ospf6 cost {}\n", cost))?;
+                    }
+                }
                 self.config_expr("exit\n")?;
             }
         }
 
         // configure route maps
         for rm in &router.route_maps {
+            for cmd in rm.community_list_commands() {
+                self.config_expr(format!("{}\n", cmd))?;
+            }
             self.config_expr(format!("route-map {} {} {}\n", rm.name, rm.state, rm.order))?;
             for (key, value) in rm.match_statements.iter() {
                 self.config_expr(format!("match {} {}\n", key, value))?; // exit router bgp
@@ -276,6 +327,14 @@ impl FrrConnection {
         self.config_expr(format!("router bgp {}\n", router.as_id.0))?;
         self.config_expr(format!("bgp router-id {}\n", router.loopback_addr.addr))?;
         self.config_expr("bgp log-neighbor-changes\n")?;
+        // compare the MED within each neighbor AS first, as done by snowcap
+        self.config_expr("bgp deterministic-med\n")?;
+        if router.always_compare_med {
+            self.config_expr("bgp always-compare-med\n")?;
+        }
+        if let Some(cluster_id) = router.cluster_id.as_ref() {
+            self.config_expr(format!("bgp cluster-id {}\n", cluster_id))?;
+        }
         self.config_expr("bgp bestpath compare-routerid\n")?;
         self.config_expr("bgp route-reflector allow-outbound-policy\n")?;
         self.config_expr("neighbor internal peer-group\n")?;
@@ -326,11 +385,42 @@ impl FrrConnection {
             ))?;
         }
         self.config_expr("exit\n")?; // exit address-family
+
+        // enable ipv6 communication on the same sessions
+        if router.dual_stack {
+            self.config_expr("address-family ipv6 unicast\n")?;
+            if let Some(prefix) = router.advertise_route_v6.as_ref() {
+                self.config_expr(format!("network {}\n", prefix))?;
+            }
+            self.config_expr("neighbor internal activate\n")?;
+            self.config_expr("neighbor external activate\n")?;
+            for rm in &router.route_maps {
+                self.config_expr(format!(
+                    "neighbor internal route-map {} {}\n",
+                    rm.name, rm.direction
+                ))?;
+                self.config_expr(format!(
+                    "neighbor external route-map {} {}\n",
+                    rm.name, rm.direction
+                ))?;
+            }
+            self.config_expr("exit\n")?; // exit address-family
+        }
+        // send the post-policy Adj-RIB-In to the collector
+        if let Some(bmp) = router.bmp.as_ref() {
+            self.config_expr("bmp targets snowcap\n")?;
+            self.config_expr("bmp monitor ipv4 unicast post-policy\n")?;
+            self.config_expr(format!(
+                "bmp connect {} port {} min-retry 100 max-retry 1000\n",
+                bmp.collector_addr, bmp.port
+            ))?;
+            self.config_expr("exit\n")?; // exit bmp targets
+        }
         self.config_expr("exit\n")?; // exit router bgp
 
         // configure static routes
         for sr in &router.static_routes {
-            self.config_expr(format!("ip route {} {}\n", sr.addr, sr.next_hop))?;
+            self.config_expr(format!("{}\n", sr.command()))?;
         }
 
         self.config_expr("exit\n")?; // exit config mode
@@ -708,6 +798,7 @@ impl RteOrigin {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::driver::RouterKind;
     use crate::physical_network::{BgpSessionInfo, IfaceInfo, IpAddr, StaticRouteInfo};
     use gns3::*;
     use snowcap::netsim::*;
@@ -895,6 +986,7 @@ B   109.0.0.0/8 [20/0] via 10.1.12.2, eth1, 00:01:42
         let router = PhysicalRouter {
             router_id: 0.into(),
             name: String::from("node"),
+            kind: RouterKind::Frr,
             gns_node: node,
             loopback_addr: IpAddr::new("10.0.0.1", 24),
             as_id: AsId(65001),
@@ -968,6 +1060,11 @@ B   109.0.0.0/8 [20/0] via 10.1.12.2, eth1, 00:01:42
                 next_hop: String::from("eth1"),
             }],
             advertise_route: Some(IpAddr::new(String::from("10.0.0.0"), 24)),
+            advertise_route_v6: None,
+            dual_stack: false,
+            always_compare_med: false,
+            cluster_id: None,
+            bmp: None,
         };
 
         c.initialize_config(&router).unwrap();
@@ -1000,6 +1097,7 @@ interface lo
 router bgp 65001
  bgp router-id 10.0.0.1
  bgp log-neighbor-changes
+ bgp deterministic-med
  bgp route-reflector allow-outbound-policy
  bgp bestpath compare-routerid
  neighbor external peer-group
@@ -0,0 +1,128 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Utilities for gNMI interactions with real devices
+
+use crate::hardware::{DeviceInfo, HardwareConnection};
+
+use log::*;
+
+use std::error::Error;
+use std::process::Command;
+
+/// Path of the next hops of every prefix in the default network instance (OpenConfig AFT model)
+const AFT_PATH: &str = "/network-instances/network-instance[name=default]/afts/ipv4-unicast/\
+ipv4-entry/state/next-hop-group";
+
+/// # Connection to a gNMI target
+///
+/// Every request is performed by calling the `gnmic` binary. The configuration is changed with a
+/// `Set` request, where the CLI commands are sent with the origin `cli` (supported for instance
+/// by Arista EOS). The forwarding table is read with a `Get` request, printed in the flat format
+/// (`path: value`).
+#[derive(Debug)]
+pub struct GnmiConnection {
+    name: String,
+    target: String,
+    username: String,
+    password: Option<String>,
+    skip_verify: bool,
+}
+
+impl GnmiConnection {
+    /// Create a new connection to the gNMI target. No request is sent to the device.
+    pub fn new(device: &DeviceInfo) -> Self {
+        Self {
+            name: device.name.clone(),
+            target: format!("{}:{}", device.address, device.port()),
+            username: device.username.clone(),
+            password: device.password.clone(),
+            skip_verify: device.skip_verify,
+        }
+    }
+
+    /// Call `gnmic` with the arguments, and return its output.
+    fn gnmic(&self, args: &[String]) -> Result<String, Box<dyn Error>> {
+        let mut cmd = Command::new("gnmic");
+        cmd.arg("-a").arg(&self.target).arg("-u").arg(&self.username);
+        // pass the password in the environment, such that it does not show up in the process list
+        if let Some(password) = self.password.as_ref() {
+            cmd.env("GNMIC_PASSWORD", password);
+        }
+        if self.skip_verify {
+            cmd.arg("--skip-verify");
+        }
+        let output = cmd.args(args).output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            error!("gNMI error on {}:\n{}", self.name, stderr);
+            return Err(format!("gNMI error on {}: {}", self.name, stderr.trim()).into());
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+impl HardwareConnection for GnmiConnection {
+    fn push(&mut self, commands: &[String]) -> Result<(), Box<dyn Error>> {
+        self.gnmic(&set_args(commands))?;
+        Ok(())
+    }
+
+    fn forwarding_snapshot(&mut self) -> Result<String, Box<dyn Error>> {
+        let args = ["get", "--path", AFT_PATH, "--encoding", "json_ietf", "--format", "flat"];
+        let output = self.gnmic(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())?;
+        // sort the lines, such that the order of the entries does not matter.
+        let mut lines = output.lines().collect::<Vec<_>>();
+        lines.sort_unstable();
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Arguments of `gnmic` to apply the CLI commands in a single `Set` request.
+pub fn set_args(commands: &[String]) -> Vec<String> {
+    vec![
+        String::from("set"),
+        String::from("--encoding"),
+        String::from("ascii"),
+        String::from("--update-path"),
+        String::from("cli:"),
+        String::from("--update-value"),
+        commands.join("\n"),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_request() {
+        let commands = vec![String::from("router bgp 65001"), String::from("exit")];
+        assert_eq!(
+            set_args(&commands),
+            vec![
+                "set",
+                "--encoding",
+                "ascii",
+                "--update-path",
+                "cli:",
+                "--update-value",
+                "router bgp 65001\nexit"
+            ]
+        );
+    }
+}
@@ -0,0 +1,486 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Real Hardware
+//!
+//! Instead of emulating the network in GNS3, the migration can be pushed to real devices. The
+//! devices are described by an [`Inventory`], which is read from a YAML file:
+//!
+//! ```yaml
+//! convergence:
+//!   poll_interval: 2
+//!   stable_time: 10
+//!   timeout: 300
+//! devices:
+//!   - name: r1
+//!     address: 192.0.2.1
+//!     transport: Netconf
+//!     dialect: CiscoIos
+//!     username: admin
+//!     known_hosts: /etc/snowcap/known_hosts
+//!   - name: r2
+//!     address: 192.0.2.2
+//!     port: 6030
+//!     transport: Gnmi
+//!     dialect: CiscoIos
+//!     username: admin
+//!     password: admin
+//! ```
+//!
+//! The commands of every step are generated by [`snowcap::netsim::cli`], in the dialect of the
+//! device, and pushed as CLI configuration, using either NETCONF (see [`NetconfConnection`]) or
+//! gNMI (see [`GnmiConnection`]). Hence, the addresses of the devices must follow the same
+//! convention as the generated commands. After every step, the forwarding table of every device is
+//! polled using telemetry (the OpenConfig AFT model), until it did not change for some time (see
+//! [`ConvergenceOptions`]).
+
+use crate::gnmi_conn::GnmiConnection;
+use crate::netconf_conn::NetconfConnection;
+
+use snowcap::netsim::cli::{device_commands, CliFlavor};
+use snowcap::netsim::{config::ConfigModifier, printer, Network, RouterId};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Protocol used to push the configuration to the device, and to read the telemetry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Transport {
+    /// NETCONF over SSH (default port 830), using the `ssh` binary.
+    Netconf,
+    /// gNMI (default port 6030), using the `gnmic` binary.
+    Gnmi,
+}
+
+impl Transport {
+    /// Default port of the transport
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::Netconf => 830,
+            Self::Gnmi => 6030,
+        }
+    }
+}
+
+/// Policy for verifying the SSH host key of a device (only for NETCONF)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HostKeyPolicy {
+    /// The host key must already be present in the known hosts file. Unknown or changed keys are
+    /// rejected.
+    Strict,
+    /// The host key of unknown devices is added to the known hosts file, while changed keys are
+    /// rejected. This trusts the first key seen, and should only be used for lab devices.
+    AcceptNew,
+}
+
+impl Default for HostKeyPolicy {
+    fn default() -> Self {
+        Self::Strict
+    }
+}
+
+impl HostKeyPolicy {
+    /// Value of the `StrictHostKeyChecking` option of `ssh`
+    pub fn ssh_option(&self) -> &'static str {
+        match self {
+            Self::Strict => "yes",
+            Self::AcceptNew => "accept-new",
+        }
+    }
+}
+
+/// Management information of a single device in the inventory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    /// Name of the router in the network
+    pub name: String,
+    /// Management address of the device
+    pub address: String,
+    /// Management port of the device. If not given, the default port of the transport is used.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Protocol to push the configuration
+    pub transport: Transport,
+    /// Dialect of the CLI commands, which are pushed to the device.
+    pub dialect: CliFlavor,
+    /// Username to log in
+    pub username: String,
+    /// Password to log in. For NETCONF, the password is passed to `sshpass`, and for gNMI, it is
+    /// passed to `gnmic` in the environment variable `GNMIC_PASSWORD`. If not given, the SSH keys
+    /// are used.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Skip the verification of the TLS certificate (only for gNMI)
+    #[serde(default)]
+    pub skip_verify: bool,
+    /// Policy for verifying the SSH host key (only for NETCONF)
+    #[serde(default)]
+    pub host_key_policy: HostKeyPolicy,
+    /// Known hosts file, against which the SSH host key is verified (only for NETCONF). If not
+    /// given, the default known hosts file of `ssh` is used.
+    #[serde(default)]
+    pub known_hosts: Option<String>,
+}
+
+impl DeviceInfo {
+    /// Management port of the device
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or_else(|| self.transport.default_port())
+    }
+
+    /// Connect to the device
+    pub fn connect(&self) -> Result<Box<dyn HardwareConnection>, Box<dyn Error>> {
+        Ok(match self.transport {
+            Transport::Netconf => Box::new(NetconfConnection::new(self)?),
+            Transport::Gnmi => Box::new(GnmiConnection::new(self)),
+        })
+    }
+}
+
+/// Parameters to detect the convergence of the network, all given in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConvergenceOptions {
+    /// Time between two polls of the forwarding tables
+    #[serde(default = "ConvergenceOptions::default_poll_interval")]
+    pub poll_interval: u64,
+    /// The network has converged once no forwarding table has changed for this time.
+    #[serde(default = "ConvergenceOptions::default_stable_time")]
+    pub stable_time: u64,
+    /// Maximum time to wait for convergence, before the migration is aborted.
+    #[serde(default = "ConvergenceOptions::default_timeout")]
+    pub timeout: u64,
+}
+
+impl ConvergenceOptions {
+    fn default_poll_interval() -> u64 {
+        2
+    }
+
+    fn default_stable_time() -> u64 {
+        10
+    }
+
+    fn default_timeout() -> u64 {
+        300
+    }
+}
+
+impl Default for ConvergenceOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Self::default_poll_interval(),
+            stable_time: Self::default_stable_time(),
+            timeout: Self::default_timeout(),
+        }
+    }
+}
+
+/// Inventory of the real devices
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    /// Parameters for detecting convergence
+    #[serde(default)]
+    pub convergence: ConvergenceOptions,
+    /// All devices that can be reconfigured
+    pub devices: Vec<DeviceInfo>,
+}
+
+impl Inventory {
+    /// Read the inventory from a YAML file
+    pub fn from_file(filename: impl AsRef<str>) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_yaml::from_reader(File::open(filename.as_ref())?)?)
+    }
+
+    /// Returns the device with the given name
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&DeviceInfo> {
+        self.devices.iter().find(|d| d.name == name.as_ref())
+    }
+}
+
+impl FromStr for Inventory {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// Connection to a real device
+pub trait HardwareConnection: fmt::Debug {
+    /// Apply the CLI commands (in configuration mode) on the device.
+    fn push(&mut self, commands: &[String]) -> Result<(), Box<dyn Error>>;
+
+    /// Returns a representation of the current forwarding table of the device. Two
+    /// representations are equal if the forwarding tables are equal.
+    fn forwarding_snapshot(&mut self) -> Result<String, Box<dyn Error>>;
+}
+
+/// # Network of Real Devices
+///
+/// Network of real devices, for all routers in the network which are part of the inventory.
+/// Routers which are not part of the inventory (like external routers) are never reconfigured.
+#[derive(Debug)]
+pub struct HardwareNetwork {
+    net: Network,
+    devices: HashMap<RouterId, (DeviceInfo, Box<dyn HardwareConnection>)>,
+    options: ConvergenceOptions,
+}
+
+impl HardwareNetwork {
+    /// Connect to all devices in the inventory. `net` must be in the current state of the devices.
+    /// Returns an error if the inventory contains a device, which is not part of the network.
+    pub fn new(net: &Network, inventory: &Inventory) -> Result<Self, Box<dyn Error>> {
+        let mut devices = HashMap::new();
+        for device in inventory.devices.iter() {
+            let router = net.get_router_id(&device.name)?;
+            info!("connecting to {} at {}:{}", device.name, device.address, device.port());
+            devices.insert(router, (device.clone(), device.connect()?));
+        }
+        Ok(Self { net: net.clone(), devices, options: inventory.convergence })
+    }
+
+    /// Generate the commands for every device and every step of the sequence, without pushing
+    /// them. Returns an error if a device which must be reconfigured is not part of the inventory,
+    /// or if a step cannot be expressed in the dialect of a device. Hence, this function can be
+    /// used to check the sequence before starting the migration.
+    pub fn prepare(
+        &self,
+        sequence: &[ConfigModifier],
+    ) -> Result<Vec<Vec<(RouterId, Vec<String>)>>, Box<dyn Error>> {
+        let mut net = self.net.clone();
+        let mut steps = Vec::with_capacity(sequence.len());
+        for modifier in sequence {
+            let repr = printer::config_modifier(&net, modifier)?;
+            let mut step = Vec::new();
+            let routers =
+                device_commands(&net, modifier, CliFlavor::Frr)?.into_iter().map(|(r, _)| r);
+            for router in routers {
+                let (device, _) = self.devices.get(&router).ok_or_else(|| {
+                    format!(
+                        "Router {} is not part of the inventory (step: {})",
+                        net.get_router_name(router).unwrap_or("?"),
+                        repr
+                    )
+                })?;
+                let commands = device_commands(&net, modifier, device.dialect)?
+                    .into_iter()
+                    .find(|(r, _)| *r == router)
+                    .map(|(_, c)| c)
+                    .unwrap_or_default();
+                if let Some(c) = commands.iter().find(|c| c.trim_start().starts_with('!')) {
+                    return Err(format!(
+                        "Step cannot be applied on {}: {} ({})",
+                        device.name,
+                        repr,
+                        c.trim_start().trim_start_matches('!').trim()
+                    )
+                    .into());
+                }
+                step.push((router, commands));
+            }
+            net.apply_modifier(modifier)?;
+            steps.push(step);
+        }
+        Ok(steps)
+    }
+
+    /// Push the commands of a single step (see [`HardwareNetwork::prepare`]) to the devices, in
+    /// the given order.
+    pub fn push_step(&mut self, step: &[(RouterId, Vec<String>)]) -> Result<(), Box<dyn Error>> {
+        for (router, commands) in step {
+            let (device, conn) = self.devices.get_mut(router).ok_or("Unknown device")?;
+            debug!("pushing to {}:\n    {}", device.name, commands.join("\n    "));
+            conn.push(commands)?;
+        }
+        Ok(())
+    }
+
+    /// Poll the forwarding tables of all devices, until no table has changed for
+    /// [`ConvergenceOptions::stable_time`]. Returns the time it took until the last change was
+    /// observed.
+    pub fn wait_convergence(&mut self) -> Result<Duration, Box<dyn Error>> {
+        let start = SystemTime::now();
+        let mut last_change = start;
+        let mut snapshots: HashMap<RouterId, String> = HashMap::new();
+        loop {
+            for (router, (_, conn)) in self.devices.iter_mut() {
+                let snapshot = conn.forwarding_snapshot()?;
+                if snapshots.get(router) != Some(&snapshot) {
+                    snapshots.insert(*router, snapshot);
+                    last_change = SystemTime::now();
+                }
+            }
+            if last_change.elapsed()? >= Duration::from_secs(self.options.stable_time) {
+                return Ok(last_change.duration_since(start)?);
+            }
+            if start.elapsed()? >= Duration::from_secs(self.options.timeout) {
+                return Err(format!(
+                    "Network did not converge within {} seconds!",
+                    self.options.timeout
+                )
+                .into());
+            }
+            thread::sleep(Duration::from_secs(self.options.poll_interval));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use snowcap::netsim::config::{Config, ConfigExpr};
+    use snowcap::netsim::{AsId, BgpSessionType};
+
+    #[derive(Debug)]
+    struct Recorder;
+
+    impl HardwareConnection for Recorder {
+        fn push(&mut self, _commands: &[String]) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn forwarding_snapshot(&mut self) -> Result<String, Box<dyn Error>> {
+            Ok(String::new())
+        }
+    }
+
+    const INVENTORY: &str = "
+convergence:
+  stable_time: 5
+devices:
+  - name: r1
+    address: 192.0.2.1
+    transport: Netconf
+    dialect: CiscoIos
+    username: admin
+    known_hosts: /etc/snowcap/known_hosts
+  - name: r2
+    address: 192.0.2.2
+    port: 6031
+    transport: Gnmi
+    dialect: Frr
+    username: admin
+    password: admin
+    skip_verify: true
+";
+
+    #[test]
+    fn inventory() {
+        let inventory: Inventory = INVENTORY.parse().unwrap();
+        assert_eq!(
+            inventory.convergence,
+            ConvergenceOptions { poll_interval: 2, stable_time: 5, timeout: 300 }
+        );
+        assert_eq!(inventory.devices.len(), 2);
+        let r1 = inventory.get("r1").unwrap();
+        assert_eq!(r1.transport, Transport::Netconf);
+        assert_eq!(r1.dialect, CliFlavor::CiscoIos);
+        assert_eq!(r1.port(), 830);
+        assert_eq!(r1.password, None);
+        assert!(!r1.skip_verify);
+        assert_eq!(r1.host_key_policy, HostKeyPolicy::Strict);
+        assert_eq!(r1.known_hosts.as_deref(), Some("/etc/snowcap/known_hosts"));
+        let r2 = inventory.get("r2").unwrap();
+        assert_eq!(r2.transport, Transport::Gnmi);
+        assert_eq!(r2.port(), 6031);
+        assert_eq!(r2.password, Some(String::from("admin")));
+        assert!(inventory.get("r3").is_none());
+    }
+
+    fn hardware_net(devices: &[&str]) -> (HardwareNetwork, [RouterId; 3]) {
+        let mut net = Network::new();
+        let r1 = net.add_router("r1");
+        let r2 = net.add_router("r2");
+        let e1 = net.add_external_router("e1", AsId(65100));
+
+        let mut c = Config::new();
+        for (a, b) in vec![(r1, r2), (r1, e1)] {
+            net.add_link(a, b);
+            c.add(ConfigExpr::IgpLinkWeight { source: a, target: b, weight: 1.0 }).unwrap();
+            c.add(ConfigExpr::IgpLinkWeight { source: b, target: a, weight: 1.0 }).unwrap();
+        }
+        net.set_config(&c).unwrap();
+
+        let inventory: Inventory = INVENTORY.parse().unwrap();
+        let devices = devices
+            .iter()
+            .map(|name| {
+                let conn: Box<dyn HardwareConnection> = Box::new(Recorder);
+                (net.get_router_id(name).unwrap(), (inventory.get(name).unwrap().clone(), conn))
+            })
+            .collect();
+        (HardwareNetwork { net, devices, options: inventory.convergence }, [r1, r2, e1])
+    }
+
+    #[test]
+    fn prepare() {
+        let (hw, [r1, r2, _]) = hardware_net(&["r1", "r2"]);
+        let sequence = vec![
+            ConfigModifier::Update {
+                from: ConfigExpr::IgpLinkWeight { source: r1, target: r2, weight: 1.0 },
+                to: ConfigExpr::IgpLinkWeight { source: r1, target: r2, weight: 5.0 },
+            },
+            ConfigModifier::Update {
+                from: ConfigExpr::IgpLinkWeight { source: r2, target: r1, weight: 1.0 },
+                to: ConfigExpr::IgpLinkWeight { source: r2, target: r1, weight: 5.0 },
+            },
+        ];
+        let steps = hw.prepare(&sequence).unwrap();
+        assert_eq!(steps.len(), 2);
+        // r1 uses the cisco dialect, and r2 uses the FRR dialect
+        assert_eq!(
+            steps[0],
+            vec![(
+                r1,
+                vec![
+                    String::from("interface GigabitEthernet0/0"),
+                    String::from("ip ospf cost 5"),
+                    String::from("exit")
+                ]
+            )]
+        );
+        assert_eq!(
+            steps[1],
+            vec![(
+                r2,
+                vec![
+                    String::from("interface eth0"),
+                    String::from("ip ospf cost 5"),
+                    String::from("exit")
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn prepare_missing_device() {
+        let (hw, [r1, r2, _]) = hardware_net(&["r1"]);
+        let sequence = vec![ConfigModifier::Insert(ConfigExpr::BgpSession {
+            source: r1,
+            target: r2,
+            session_type: BgpSessionType::IBgpPeer,
+        })];
+        assert!(hw.prepare(&sequence).is_err());
+    }
+}
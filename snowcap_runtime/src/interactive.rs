@@ -0,0 +1,143 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Interactive Migration
+//!
+//! This module lets an operator supervise the migration on the runtime network. Before every
+//! modifier, the upcoming change and the changes in the forwarding state, expected from the
+//! simulation, are shown. The operator then decides to apply the step, to skip it, or to abort the
+//! migration (see [`Decision`]).
+
+use snowcap::netsim::config::ConfigModifier;
+use snowcap::netsim::{printer, Network};
+
+use std::error::Error;
+use std::io::{BufRead, Write};
+
+/// Decision of the operator, before a step is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Apply the step.
+    Apply,
+    /// Skip the step, and continue with the next one.
+    Skip,
+    /// Abort the migration, without applying the step.
+    Abort,
+}
+
+impl Decision {
+    /// Parse the answer of the operator. An empty answer applies the step. Returns `None` if the
+    /// answer is not understood.
+    pub fn parse(answer: &str) -> Option<Self> {
+        match answer.trim().to_lowercase().as_str() {
+            "" | "y" | "yes" | "apply" => Some(Self::Apply),
+            "s" | "skip" => Some(Self::Skip),
+            "a" | "abort" => Some(Self::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// Show the upcoming step on `output`, and ask the operator for a [`Decision`] on `input`. The
+/// expected forwarding changes are computed by applying the modifier on a copy of `sim_net`, which
+/// must be in the current state of the runtime network. The question is repeated until the answer
+/// is understood. If `input` is closed, the migration is aborted.
+pub fn ask(
+    sim_net: &Network,
+    step: usize,
+    num_steps: usize,
+    modifier: &ConfigModifier,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<Decision, Box<dyn Error>> {
+    writeln!(
+        output,
+        "Step {}/{}: {}",
+        step + 1,
+        num_steps,
+        printer::config_modifier(sim_net, modifier)?
+    )?;
+    let mut next_net = sim_net.clone();
+    match next_net.apply_modifier(modifier) {
+        Ok(()) => {
+            let diff = printer::forwarding_diff(
+                sim_net,
+                &sim_net.get_forwarding_state(),
+                &next_net.get_forwarding_state(),
+            )?;
+            if diff.is_empty() {
+                writeln!(output, "    No expected change in the forwarding state")?;
+            }
+            for line in diff {
+                writeln!(output, "    {}", line)?;
+            }
+        }
+        Err(e) => writeln!(output, "    The simulation cannot apply the step: {}", e)?,
+    }
+
+    loop {
+        write!(output, "Apply the step? [Y]es / [s]kip / [a]bort: ")?;
+        output.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            return Ok(Decision::Abort);
+        }
+        match Decision::parse(&answer) {
+            Some(decision) => return Ok(decision),
+            None => writeln!(output, "Invalid answer: {}", answer.trim())?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use snowcap::example_networks::{ExampleNetwork, SimpleNet};
+    use std::io::Cursor;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Decision::parse("\n"), Some(Decision::Apply));
+        assert_eq!(Decision::parse("Yes\n"), Some(Decision::Apply));
+        assert_eq!(Decision::parse(" skip "), Some(Decision::Skip));
+        assert_eq!(Decision::parse("a"), Some(Decision::Abort));
+        assert_eq!(Decision::parse("later"), None);
+    }
+
+    #[test]
+    fn ask_until_understood() {
+        let net = SimpleNet::net(0);
+        let final_config = SimpleNet::final_config(&net, 0);
+        let sequence = net.current_config().get_diff(&final_config).modifiers;
+
+        let mut output = Vec::new();
+        let decision =
+            ask(&net, 0, sequence.len(), &sequence[0], &mut Cursor::new("what\ns\n"), &mut output)
+                .unwrap();
+        assert_eq!(decision, Decision::Skip);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with(&format!("Step 1/{}: ", sequence.len())));
+        assert!(output.contains("Invalid answer: what"));
+        assert_eq!(output.matches("Apply the step?").count(), 2);
+
+        // a closed input aborts the migration
+        let decision =
+            ask(&net, 0, sequence.len(), &sequence[0], &mut Cursor::new(""), &mut Vec::new())
+                .unwrap();
+        assert_eq!(decision, Decision::Abort);
+    }
+}
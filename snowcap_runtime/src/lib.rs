@@ -19,18 +19,39 @@
 //!
 //! This system generates a virtual network inside GNS3, configures all nodes and performs the
 //! migration scenario, while monitoring the forwarding state. For simplified usage, check the
-//! function [`perform_migration`].
+//! function [`perform_migration`]. The migration can also be pushed to real devices, using
+//! [`perform_hardware_migration`].
 
 #![deny(missing_docs, missing_debug_implementations)]
 
+pub mod bird_conn;
+pub mod bmp;
 pub mod checker;
+pub mod cisco_conn;
 pub mod config;
+pub mod differential;
+pub mod driver;
+pub mod failover;
 pub mod frr_conn;
+pub mod gnmi_conn;
+pub mod hardware;
+pub mod interactive;
+pub mod netconf_conn;
 pub mod pcap_reader;
 pub mod physical_network;
 pub mod python_conn;
+pub mod rollback;
 
+use bmp::RibSnapshot;
+use checker::StepVerdict;
+use driver::RouterKind;
+use failover::DrillReport;
+use hardware::{HardwareNetwork, Inventory};
+use interactive::Decision;
+use pcap_reader::{path_changes, PathSample};
 use physical_network::PhysicalNetwork;
+use rollback::RollbackReport;
+use snowcap::hard_policies::Condition;
 use snowcap::netsim::{config::ConfigModifier, printer, Network, Prefix, RouterId};
 
 use log::*;
@@ -42,7 +63,8 @@ use std::error::Error;
 ///
 /// Based on the network (in the initial state), the migration sequence and some invariants, perform
 /// perform the migration and check that the invariants are satisfied, on a simulated network using
-/// GNS3 and FRRouting.
+/// GNS3. By default, all routers run FRRouting. Routers in `router_kinds` use a different device
+/// instead (see [`driver::RouterKind`]).
 ///
 /// This funciton does the following:
 ///
@@ -54,16 +76,58 @@ use std::error::Error;
 ///    converge, inject traffic into the network and capture their path. After the network has
 ///    converged, infer the path of each packet by analyzing the traces on the links. Then, check
 ///    the invariants, that every step is correct.
-#[allow(clippy::type_complexity)]
+///
+/// If `json_filename` is given, the measured flows, and a verdict for every step (see
+/// [`checker::step_verdict`]) are stored in the file. For every flow and step, the file also
+/// contains the time series of path changes during convergence, where the time is relative to the
+/// first probe captured in this step.
+///
+/// If `conditions` contain any [`Condition::Reliable`], a failover drill is performed after the
+/// migration is complete: every link on the path of a reliability condition is failed in turn,
+/// and the reachability is measured with the probes (see [`failover::failover_drill`]). The result
+/// of the drill is appended to the json file.
+///
+/// If `bmp_monitoring` is set, the internal FRR routers send their RIBs to a BMP collector (see
+/// [`bmp`]). Then, convergence is also detected on the control plane, and the RIBs of all routers
+/// are compared with the simulation after every step (see [`checker::missing_routes`]). The RIB
+/// snapshot after every step is also stored in the json file.
+///
+/// If `rollback_on_violation` is set, the migration stops at the first step which violates an
+/// invariant (see [`checker::StepVerdict::is_ok`]). Then, all modifiers applied so far are
+/// reversed, in the opposite order, to return the network to its initial state (see
+/// [`rollback::rollback`]). The trace of the rollback is stored in the json file, and the failover
+/// drill is skipped.
+///
+/// If `interactive` is set, the migration pauses before every modifier, shows the upcoming change
+/// and the expected changes in the forwarding state, and waits for the operator to apply the step,
+/// to skip it, or to abort the migration (see [`interactive::ask`]). Skipped steps, and the step at
+/// which the migration was aborted, are stored in the json file. The interactive mode cannot be
+/// combined with `reconfiguration_at_once`.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub fn perform_migration(
     net: &Network,
     migration_sequence: &[ConfigModifier],
     persistent_gns_project: bool,
     json_filename: Option<String>,
     reconfiguration_at_once: bool,
+    conditions: &[Condition],
+    router_kinds: &HashMap<RouterId, RouterKind>,
+    bmp_monitoring: bool,
+    rollback_on_violation: bool,
+    interactive: bool,
 ) -> Result<bool, Box<dyn Error>> {
+    if interactive && reconfiguration_at_once {
+        return Err("The interactive mode cannot apply all modifiers at once!".into());
+    }
+
     info!("Generating the network...");
-    let mut phys_net = PhysicalNetwork::new(&net, "RuntimeNet", persistent_gns_project)?;
+    let mut phys_net = PhysicalNetwork::with_router_kinds(
+        &net,
+        "RuntimeNet",
+        persistent_gns_project,
+        router_kinds,
+        bmp_monitoring,
+    )?;
 
     info!("performing all traceroutes!");
     let all_paths = phys_net.get_all_paths()?;
@@ -98,6 +162,14 @@ pub fn perform_migration(
 
     let mut flows: HashMap<(RouterId, Prefix), Vec<HashMap<Option<Vec<RouterId>>, usize>>> =
         HashMap::new();
+    let mut changes: HashMap<(RouterId, Prefix), Vec<Vec<PathSample>>> = HashMap::new();
+    let mut verdicts: Vec<StepVerdict> = Vec::new();
+    let mut rib_snapshots: Vec<RibSnapshot> = Vec::new();
+    let mut rollback_report: Option<RollbackReport> = None;
+    let mut applied: Vec<ConfigModifier> = Vec::new();
+    let mut skipped_steps: Vec<usize> = Vec::new();
+    let mut aborted_step: Option<usize> = None;
+    let mut sim_net = net.clone();
 
     if reconfiguration_at_once {
         info!("Applying all modifiers...");
@@ -105,49 +177,206 @@ pub fn perform_migration(
             phys_net.apply_all_modifiers_wait_convergence_check_flows(&migration_sequence, 2)?;
         checker::print_paths(&new_flows, &phys_net);
 
+        let mut fw_before = sim_net.get_forwarding_state();
+        for modifier in migration_sequence.iter() {
+            sim_net.apply_modifier(modifier)?;
+        }
+        let mut fw_after = sim_net.get_forwarding_state();
+        let mut verdict = checker::step_verdict(
+            0,
+            migration_sequence
+                .iter()
+                .map(|m| printer::config_modifier(&net, m))
+                .collect::<Result<Vec<_>, _>>()?
+                .join("; "),
+            &new_flows,
+            &phys_net.read_transient_anomalies()?,
+            &mut fw_before,
+            &mut fw_after,
+            &phys_net,
+        );
+        if let Some(rib) = phys_net.rib_snapshot() {
+            verdict.missing_routes = checker::missing_routes(&rib, &mut fw_after, &phys_net);
+            rib_snapshots.push(rib);
+        }
+        let violated = !verdict.is_ok();
+        verdicts.push(verdict);
+
         // append the new flows to the existing ones
         for (key, paths) in new_flows {
             let flow = flows.entry(key).or_default();
             flow.push(paths);
         }
+        append_path_changes(&mut changes, phys_net.read_path_samples()?);
+
+        if rollback_on_violation && violated {
+            warn!("The migration violated an invariant! Rolling back all modifiers...");
+            rollback_report =
+                Some(rollback::rollback(&mut phys_net, &mut sim_net, net, migration_sequence, 0)?);
+        }
     } else {
-        for modifier in migration_sequence.iter() {
+        for (step, modifier) in migration_sequence.iter().enumerate() {
+            if interactive {
+                let stdin = std::io::stdin();
+                let num_steps = migration_sequence.len();
+                match interactive::ask(
+                    &sim_net,
+                    step,
+                    num_steps,
+                    modifier,
+                    &mut stdin.lock(),
+                    &mut std::io::stdout(),
+                )? {
+                    Decision::Apply => {}
+                    Decision::Skip => {
+                        info!("Skipping step {}", step);
+                        skipped_steps.push(step);
+                        continue;
+                    }
+                    Decision::Abort => {
+                        warn!("Migration aborted before step {}", step);
+                        aborted_step = Some(step);
+                        break;
+                    }
+                }
+            }
             info!("Applying the modifier {}", printer::config_modifier(&net, modifier)?);
             let new_flows = phys_net.apply_modifier_wait_convergence_check_flows(modifier)?;
             checker::print_paths(&new_flows, &phys_net);
 
+            let mut fw_before = sim_net.get_forwarding_state();
+            sim_net.apply_modifier(modifier)?;
+            applied.push(modifier.clone());
+            let mut fw_after = sim_net.get_forwarding_state();
+            let mut verdict = checker::step_verdict(
+                step,
+                printer::config_modifier(&net, modifier)?,
+                &new_flows,
+                &phys_net.read_transient_anomalies()?,
+                &mut fw_before,
+                &mut fw_after,
+                &phys_net,
+            );
+            if let Some(rib) = phys_net.rib_snapshot() {
+                verdict.missing_routes = checker::missing_routes(&rib, &mut fw_after, &phys_net);
+                rib_snapshots.push(rib);
+            }
+            let violated = !verdict.is_ok();
+            verdicts.push(verdict);
+
             // append the new flows to the existing ones
             for (key, paths) in new_flows {
                 let flow = flows.entry(key).or_default();
                 flow.push(paths);
             }
+            append_path_changes(&mut changes, phys_net.read_path_samples()?);
+
+            if rollback_on_violation && violated {
+                warn!("Step {} violated an invariant! Rolling back all modifiers...", step);
+                rollback_report =
+                    Some(rollback::rollback(&mut phys_net, &mut sim_net, net, &applied, step)?);
+                break;
+            }
+        }
+    }
+
+    for verdict in verdicts.iter().filter(|v| !v.is_ok()) {
+        warn!(
+            "Step {} deviated from the simulation in {} flows",
+            verdict.step,
+            verdict.deviations.len()
+        );
+    }
+    for verdict in verdicts.iter() {
+        for m in verdict.missing_routes.iter() {
+            warn!("Step {}: {} has no route for prefix {}", verdict.step, m.router, m.prefix);
         }
+        for a in verdict.anomalies.iter() {
+            warn!(
+                "Step {}: transient {:?} of {} for prefix {} at [{}] for {:.2}s ({} packets)",
+                verdict.step,
+                a.kind,
+                a.router,
+                a.prefix,
+                a.location.join(", "),
+                a.duration,
+                a.num_packets
+            );
+        }
+    }
+
+    if let Some(report) = rollback_report.as_ref() {
+        if report.is_ok() {
+            info!("Rollback complete, the initial reachability is restored");
+        } else {
+            warn!("Rollback complete, but the initial reachability is not restored!");
+        }
+    }
+
+    let failover_drills = if rollback_report.is_some() {
+        info!("Skipping the failover drill after the rollback");
+        Vec::new()
+    } else if conditions.iter().any(|c| c.is_reliability()) {
+        info!("Starting the failover drill");
+        failover::failover_drill(&mut phys_net, &sim_net, conditions)?
+    } else {
+        Vec::new()
+    };
+    for report in failover_drills.iter().filter(|r| !r.is_ok()) {
+        warn!(
+            "Failover drill of the link {} -- {} lost reachability for {} flows",
+            report.link[0],
+            report.link[1],
+            report.outcomes.iter().filter(|o| !o.reachable).count()
+        );
     }
 
     if let Some(json_filename) = json_filename {
         // transform the data into the storable format
-        let data = flows
+        let names = |path: Option<Vec<RouterId>>| -> Vec<String> {
+            path.unwrap_or_default()
+                .into_iter()
+                .map(|r| phys_net.router_name(r).to_string())
+                .collect()
+        };
+        let flows = flows
             .into_iter()
             .map(|((router, prefix), paths)| FlowInformation {
                 router: phys_net.router_name(router).to_string(),
                 prefix: prefix.0,
-                paths: paths
+                path_changes: changes
+                    .remove(&(router, prefix))
+                    .unwrap_or_default()
                     .into_iter()
                     .map(|v| {
                         v.into_iter()
-                            .map(|(path, count)| PathInformation {
-                                count,
-                                path: path
-                                    .unwrap_or_default()
-                                    .into_iter()
-                                    .map(|r| phys_net.router_name(r).to_string())
-                                    .collect(),
+                            .map(|s| PathChangeInformation {
+                                seq_num: s.seq_num,
+                                time: s.time,
+                                path: names(s.path),
                             })
                             .collect()
                     })
                     .collect(),
+                paths: paths
+                    .into_iter()
+                    .map(|v| {
+                        v.into_iter()
+                            .map(|(path, count)| PathInformation { count, path: names(path) })
+                            .collect()
+                    })
+                    .collect(),
             })
             .collect::<Vec<_>>();
+        let data = MigrationResult {
+            flows,
+            verdicts,
+            failover_drills,
+            rib_snapshots,
+            rollback: rollback_report,
+            skipped_steps,
+            aborted_step,
+        };
 
         let data_string = serde_json::to_string(&data)?;
         std::fs::write(json_filename, data_string)?;
@@ -156,11 +385,81 @@ pub fn perform_migration(
     Ok(true)
 }
 
+/// # Perform the migration on real devices
+///
+/// Instead of creating a GNS3 project, connect to the devices in the `inventory` (see
+/// [`hardware`]), and push every modifier of the migration sequence. After every step, wait until
+/// the forwarding tables of all devices have converged, before applying the next step. `net` must
+/// be in the current state of the devices.
+///
+/// Before any device is reconfigured, the commands for all steps are generated. If any step cannot
+/// be applied (because a device is not part of the inventory, or the step cannot be expressed in
+/// the dialect of a device), the migration is aborted without changing anything. If a step fails
+/// while it is pushed, the migration stops at this step.
+pub fn perform_hardware_migration(
+    net: &Network,
+    migration_sequence: &[ConfigModifier],
+    inventory: &Inventory,
+) -> Result<(), Box<dyn Error>> {
+    info!("Connecting to the devices...");
+    let mut hw_net = HardwareNetwork::new(net, inventory)?;
+    let steps = hw_net.prepare(migration_sequence)?;
+
+    info!("Waiting for the network to be stable...");
+    hw_net.wait_convergence()?;
+
+    info!("Starting the migration");
+    let mut sim_net = net.clone();
+    for (i, (modifier, step)) in migration_sequence.iter().zip(steps.iter()).enumerate() {
+        info!("Applying the modifier {}", printer::config_modifier(&sim_net, modifier)?);
+        hw_net.push_step(step).map_err(|e| format!("Step {} failed: {}", i, e))?;
+        let time = hw_net.wait_convergence()?;
+        info!("Step {} converged after {:.2}s", i, time.as_secs_f64());
+        sim_net.apply_modifier(modifier)?;
+    }
+
+    info!("Migration complete!");
+    Ok(())
+}
+
+/// Reduce the samples of a single step to the path changes, and append them to `changes`. The
+/// time is made relative to the first probe of the step.
+fn append_path_changes(
+    changes: &mut HashMap<(RouterId, Prefix), Vec<Vec<PathSample>>>,
+    samples: HashMap<(RouterId, Prefix), Vec<PathSample>>,
+) {
+    let start = samples.values().flatten().map(|s| s.time).fold(f64::INFINITY, f64::min);
+    for (key, samples) in samples {
+        let mut step_changes = path_changes(&samples);
+        step_changes.iter_mut().for_each(|s| s.time -= start);
+        changes.entry(key).or_default().push(step_changes);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MigrationResult {
+    flows: Vec<FlowInformation>,
+    verdicts: Vec<StepVerdict>,
+    failover_drills: Vec<DrillReport>,
+    rib_snapshots: Vec<RibSnapshot>,
+    rollback: Option<RollbackReport>,
+    skipped_steps: Vec<usize>,
+    aborted_step: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct FlowInformation {
     router: String,
     prefix: u32,
     paths: Vec<Vec<PathInformation>>,
+    path_changes: Vec<Vec<PathChangeInformation>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PathChangeInformation {
+    seq_num: u32,
+    time: f64,
+    path: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -0,0 +1,235 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Utilities for NETCONF interactions with real devices
+
+use crate::hardware::{DeviceInfo, HardwareConnection};
+
+use log::*;
+
+use std::error::Error;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// End-of-message marker of the NETCONF 1.0 framing
+const EOM: &str = "]]>]]>";
+
+/// Subtree filter for the forwarding table of the default network instance (OpenConfig AFT
+/// model), only selecting the next hops of every prefix.
+const AFT_FILTER: &str =
+    "<network-instances xmlns=\"http://openconfig.net/yang/network-instance\">\
+<network-instance><name>default</name><afts><ipv4-unicast><ipv4-entry><prefix/>\
+<state><next-hop-group/></state></ipv4-entry></ipv4-unicast></afts></network-instance>\
+</network-instances>";
+
+/// # Connection to a NETCONF server
+///
+/// The session is established by the `ssh` binary, using the `netconf` subsystem. If a password is
+/// given in the inventory, `sshpass` is used to enter it. The host key of the device is verified
+/// as specified by [`DeviceInfo::host_key_policy`] and [`DeviceInfo::known_hosts`]. Only the base 1.0 capability is
+/// announced, such that messages are framed with the end-of-message marker `]]>]]>`.
+///
+/// The configuration is changed by sending the CLI commands in an `<edit-config>` RPC (using the
+/// `<cli-config-data>` element, supported by Cisco IOS and IOS-XE), and the forwarding table is
+/// read with a `<get>` RPC.
+pub struct NetconfConnection {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+    message_id: usize,
+}
+
+impl std::fmt::Debug for NetconfConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NetconfConnection({})", self.name)
+    }
+}
+
+impl NetconfConnection {
+    /// Establish a NETCONF session with the device, and exchange the hello messages.
+    pub fn new(device: &DeviceInfo) -> Result<Self, Box<dyn Error>> {
+        let mut cmd = match device.password.as_ref() {
+            Some(password) => {
+                let mut cmd = Command::new("sshpass");
+                cmd.arg("-e").arg("ssh").env("SSHPASS", password);
+                cmd
+            }
+            None => Command::new("ssh"),
+        };
+        cmd.args(&["-o", "ConnectTimeout=10", "-o"])
+            .arg(format!("StrictHostKeyChecking={}", device.host_key_policy.ssh_option()));
+        if let Some(known_hosts) = device.known_hosts.as_ref() {
+            cmd.arg("-o").arg(format!("UserKnownHostsFile={}", known_hosts));
+        }
+        cmd.arg("-p")
+            .arg(device.port().to_string())
+            .arg("-s")
+            .arg(format!("{}@{}", device.username, device.address))
+            .arg("netconf")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped());
+        let mut child = cmd.spawn()?;
+        let stdin = child.stdin.take().ok_or("Cannot open stdin of ssh")?;
+        let stdout = child.stdout.take().ok_or("Cannot open stdout of ssh")?;
+
+        let mut conn = Self { name: device.name.clone(), child, stdin, stdout, message_id: 0 };
+
+        // receive the hello message of the server, and answer it
+        conn.receive()?;
+        conn.send(concat!(
+            "<hello xmlns=\"urn:ietf:params:xml:ns:netconf:base:1.0\"><capabilities>",
+            "<capability>urn:ietf:params:netconf:base:1.0</capability>",
+            "</capabilities></hello>"
+        ))?;
+
+        Ok(conn)
+    }
+
+    fn send(&mut self, msg: &str) -> Result<(), Box<dyn Error>> {
+        self.stdin.write_all(msg.as_bytes())?;
+        self.stdin.write_all(EOM.as_bytes())?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<String, Box<dyn Error>> {
+        let mut result = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !result.ends_with(EOM.as_bytes()) {
+            let n = self.stdout.read(&mut buf)?;
+            if n == 0 {
+                return Err(format!("NETCONF session to {} was closed!", self.name).into());
+            }
+            result.extend_from_slice(&buf[..n]);
+        }
+        result.truncate(result.len() - EOM.len());
+        Ok(String::from_utf8(result)?)
+    }
+
+    /// Send an RPC, and return its reply. Returns an error if the reply contains an `<rpc-error>`.
+    fn rpc(&mut self, body: &str) -> Result<String, Box<dyn Error>> {
+        self.message_id += 1;
+        let msg = rpc_message(self.message_id, body);
+        self.send(&msg)?;
+        let reply = self.receive()?;
+        if reply.contains("rpc-error>") {
+            error!("NETCONF error on {}:\n{}", self.name, reply);
+            return Err(format!("NETCONF error on {}: {}", self.name, reply).into());
+        }
+        Ok(reply)
+    }
+}
+
+impl HardwareConnection for NetconfConnection {
+    fn push(&mut self, commands: &[String]) -> Result<(), Box<dyn Error>> {
+        self.rpc(&edit_config(commands))?;
+        Ok(())
+    }
+
+    fn forwarding_snapshot(&mut self) -> Result<String, Box<dyn Error>> {
+        let reply =
+            self.rpc(&format!("<get><filter type=\"subtree\">{}</filter></get>", AFT_FILTER))?;
+        Ok(reply_data(&reply).to_string())
+    }
+}
+
+impl Drop for NetconfConnection {
+    fn drop(&mut self) {
+        self.message_id += 1;
+        let msg = rpc_message(self.message_id, "<close-session/>");
+        if self.send(&msg).is_ok() {
+            let _ = self.receive();
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Wrap the body into an RPC with the given message ID.
+fn rpc_message(message_id: usize, body: &str) -> String {
+    format!(
+        "<rpc message-id=\"{}\" xmlns=\"urn:ietf:params:xml:ns:netconf:base:1.0\">{}</rpc>",
+        message_id, body
+    )
+}
+
+/// Generate the `<edit-config>` RPC body for the running datastore, containing the CLI commands.
+pub fn edit_config(commands: &[String]) -> String {
+    format!(
+        "<edit-config><target><running/></target><config><cli-config-data>{}</cli-config-data>\
+         </config></edit-config>",
+        commands.iter().map(|c| format!("<cmd>{}</cmd>", escape_xml(c))).collect::<String>()
+    )
+}
+
+/// Escape the special characters of XML.
+pub fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Returns the content of the `<data>` element of the reply, which does not contain the message
+/// ID. If the reply has no data, the entire reply is returned.
+pub fn reply_data(reply: &str) -> &str {
+    match (reply.find("<data"), reply.rfind("</data>")) {
+        (Some(start), Some(end)) if start < end => &reply[start..end],
+        _ => reply,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edit_config_rpc() {
+        let commands = vec![
+            String::from("router bgp 65001"),
+            String::from("neighbor 10.0.0.2 route-map r1-in in"),
+            String::from("exit"),
+        ];
+        assert_eq!(
+            rpc_message(3, &edit_config(&commands)),
+            concat!(
+                "<rpc message-id=\"3\" xmlns=\"urn:ietf:params:xml:ns:netconf:base:1.0\">",
+                "<edit-config><target><running/></target><config><cli-config-data>",
+                "<cmd>router bgp 65001</cmd>",
+                "<cmd>neighbor 10.0.0.2 route-map r1-in in</cmd>",
+                "<cmd>exit</cmd>",
+                "</cli-config-data></config></edit-config></rpc>"
+            )
+        );
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            escape_xml("description <a & \"b\">"),
+            "description &lt;a &amp; &quot;b&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn data() {
+        let reply = concat!(
+            "<rpc-reply message-id=\"7\" xmlns=\"urn:ietf:params:xml:ns:netconf:base:1.0\">",
+            "<data><network-instances/></data></rpc-reply>"
+        );
+        assert_eq!(reply_data(reply), "<data><network-instances/>");
+        assert_eq!(reply_data("<rpc-reply><ok/></rpc-reply>"), "<rpc-reply><ok/></rpc-reply>");
+    }
+}
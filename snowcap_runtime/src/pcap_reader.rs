@@ -16,13 +16,18 @@
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
 //! Reads pcap files and extracts all udp packets with exactly 8 bytes, to interpret them.
+//!
+//! Apart from inferring the path of every packet (see [`path_inference`]), the captures can be
+//! used to detect transient forwarding loops and black holes during convergence (see
+//! [`detect_anomalies`]).
 
 use super::physical_network::CLIENT_ID_BASE;
 use snowcap::netsim::{Prefix, RouterId};
 
-use etherparse::{SlicedPacket, TransportSlice};
+use etherparse::{InternetSlice, SlicedPacket, TransportSlice};
 use pcap::Capture;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 
 /// Read a pcap file, extract all packets, and return which flows and which sequence numbers of
@@ -30,13 +35,47 @@ use std::error::Error;
 pub fn extract_pcap_flows(
     filename: impl AsRef<str>,
 ) -> Result<HashMap<u32, Vec<u32>>, Box<dyn Error>> {
+    Ok(extract_pcap_probes(filename)?
+        .into_iter()
+        .map(|(flow, probes)| {
+            let mut seq_nums = probes.into_iter().map(|p| p.seq_num).collect::<Vec<_>>();
+            // sort every vector (just to be safe. It should already be sorted!!)
+            seq_nums.sort_unstable();
+            (flow, seq_nums)
+        })
+        .collect())
+}
+
+/// Observation of a single probe packet on a link.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeObservation {
+    /// Sequence number of the probe in its flow
+    pub seq_num: u32,
+    /// TTL (or hop limit) of the IP header, when the probe was captured
+    pub ttl: u8,
+    /// Time of the capture, in seconds since the epoch
+    pub time: f64,
+}
+
+/// Read a pcap file, extract all packets, and return every observation of every probe, grouped by
+/// the flow id. In contrast to [`extract_pcap_flows`], this also includes the TTL and the time of
+/// the capture.
+pub fn extract_pcap_probes(
+    filename: impl AsRef<str>,
+) -> Result<HashMap<u32, Vec<ProbeObservation>>, Box<dyn Error>> {
     let mut cap = Capture::from_file(filename.as_ref())?;
-    let mut result = HashMap::new();
+    let mut result: HashMap<u32, Vec<ProbeObservation>> = HashMap::new();
 
     // iterate over all received packets
     while let Ok(packet) = cap.next() {
+        let time = packet.header.ts.tv_sec as f64 + packet.header.ts.tv_usec as f64 * 1e-6;
         let packet = SlicedPacket::from_ethernet(packet.data)?;
         let payload = packet.payload;
+        let ttl = match &packet.ip {
+            Some(InternetSlice::Ipv4(h)) => h.ttl(),
+            Some(InternetSlice::Ipv6(h, _)) => h.hop_limit(),
+            None => 0,
+        };
         let header_correct = match packet.transport {
             Some(TransportSlice::Udp(h)) => {
                 let h = h.to_header();
@@ -57,16 +96,10 @@ pub fn extract_pcap_flows(
                 + ((payload[6] as u32) << 8)
                 + (payload[7] as u32);
 
-            if !result.contains_key(&flow_id) {
-                result.insert(flow_id, Vec::new());
-            }
-            result.get_mut(&flow_id).unwrap().push(seq_num);
+            result.entry(flow_id).or_default().push(ProbeObservation { seq_num, ttl, time });
         }
     }
 
-    // sort every vector (just to be safe. It should already be sorted!!)
-    result.iter_mut().for_each(|(_, v)| v.sort());
-
     Ok(result)
 }
 
@@ -117,47 +150,242 @@ pub fn path_inference(
                 .collect::<Vec<(RouterId, RouterId)>>();
 
             // reconstruct the path.
-            let mut path = vec![*flow_start];
-            let valid_path = loop {
-                let last_node = path.last().unwrap();
-                if last_node != flow_start && last_node.index() >= CLIENT_ID_BASE as usize {
-                    // we reached the end
-                    break true;
+            let path = reconstruct_path(*flow_start, &mut links);
+
+            // store the statistics
+            *result.get_mut(&(*flow_start, *flow_prefix)).unwrap().entry(path).or_insert(0) += 1;
+        }
+    }
+
+    result
+}
+
+/// Reconstruct the path of a single packet, starting at `flow_start`, using all links on which
+/// the packet was captured. If the path cannot be reconstructed unambiguously, or if the packet
+/// does not reach a client, `None` is returned.
+fn reconstruct_path(
+    flow_start: RouterId,
+    links: &mut Vec<(RouterId, RouterId)>,
+) -> Option<Vec<RouterId>> {
+    let mut path = vec![flow_start];
+    loop {
+        let last_node = path.last().unwrap();
+        if *last_node != flow_start && last_node.index() >= CLIENT_ID_BASE as usize {
+            // we reached the end
+            break Some(path);
+        }
+        // search the next link
+        let next_link = links
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (a, b))| {
+                if a == last_node {
+                    Some((i, *b))
+                } else if b == last_node {
+                    Some((i, *a))
+                } else {
+                    None
                 }
-                // search the next link
-                let next_link = links
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(i, (a, b))| {
-                        if a == last_node {
-                            Some((i, *b))
-                        } else if b == last_node {
-                            Some((i, *a))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<Vec<_>>();
-                if next_link.len() != 1 {
-                    // number of next possible links is not exactly 1! abort
-                    break false;
+            })
+            .collect::<Vec<_>>();
+        if next_link.len() != 1 {
+            // number of next possible links is not exactly 1! abort
+            break None;
+        }
+        let (idx, next_link) = next_link[0];
+        links.remove(idx);
+        path.push(next_link);
+    }
+}
+
+/// Path taken by a single probe, at the time when it was sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathSample {
+    /// Sequence number of the probe
+    pub seq_num: u32,
+    /// Time when the probe was first captured, in seconds since the epoch
+    pub time: f64,
+    /// Path of the probe, or `None` if it was dropped (see [`path_inference`])
+    pub path: Option<Vec<RouterId>>,
+}
+
+/// Reconstruct the path of every probe, ordered by the sequence number. In contrast to
+/// [`path_inference`], this keeps the time at which every probe was sent, such that the time
+/// series of path changes during convergence can be analyzed (see [`path_changes`]).
+pub fn path_samples(
+    captures: &[(RouterId, RouterId, HashMap<u32, Vec<ProbeObservation>>)],
+    flows: &HashMap<(RouterId, Prefix), u32>,
+) -> HashMap<(RouterId, Prefix), Vec<PathSample>> {
+    flows
+        .iter()
+        .map(|((flow_start, prefix), flow)| {
+            // collect all links and the earliest time, grouped by the sequence number
+            let mut probes: BTreeMap<u32, (f64, Vec<(RouterId, RouterId)>)> = BTreeMap::new();
+            for (a, b, cap) in captures.iter() {
+                for obs in cap.get(flow).into_iter().flatten() {
+                    let (time, links) =
+                        probes.entry(obs.seq_num).or_insert((f64::INFINITY, Vec::new()));
+                    *time = time.min(obs.time);
+                    if !links.contains(&(*a, *b)) {
+                        links.push((*a, *b));
+                    }
                 }
-                let (idx, next_link) = next_link[0];
-                links.remove(idx);
-                path.push(next_link);
-            };
+            }
 
-            // wrap the path in an option
-            let path = if valid_path { Some(path) } else { None };
+            let samples = probes
+                .into_iter()
+                .map(|(seq_num, (time, mut links))| PathSample {
+                    seq_num,
+                    time,
+                    path: reconstruct_path(*flow_start, &mut links),
+                })
+                .collect();
+            ((*flow_start, *prefix), samples)
+        })
+        .collect()
+}
 
-            // store the statistics
-            *result.get_mut(&(*flow_start, *flow_prefix)).unwrap().entry(path).or_insert(0) += 1;
+/// Reduce the samples to the points in time where the path changes. The first sample is always
+/// part of the result.
+pub fn path_changes(samples: &[PathSample]) -> Vec<PathSample> {
+    let mut result: Vec<PathSample> = Vec::new();
+    for sample in samples {
+        if result.last().map(|s| s.path != sample.path).unwrap_or(true) {
+            result.push(sample.clone());
+        }
+    }
+    result
+}
+
+/// Kind of a transient anomaly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum AnomalyKind {
+    /// Probes are seen multiple times on the same link, with decreasing TTL.
+    Loop,
+    /// Probes enter a router, and never exit.
+    BlackHole,
+}
+
+/// Transient forwarding loop or black hole of a single flow, affecting a sequence of consecutive
+/// probes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransientAnomaly {
+    /// Flow, given as the client and the prefix.
+    pub flow: (RouterId, Prefix),
+    /// Kind of the anomaly.
+    pub kind: AnomalyKind,
+    /// Location of the anomaly. For loops, this is the link on which the probes were seen
+    /// multiple times. For black holes, this is the router where the probes disappear.
+    pub location: Vec<RouterId>,
+    /// Sequence number of the first affected probe.
+    pub first_seq: u32,
+    /// Sequence number of the last affected probe.
+    pub last_seq: u32,
+    /// Number of affected probes.
+    pub num_packets: usize,
+    /// Time (in seconds) between the first and the last affected probe.
+    pub duration: f64,
+}
+
+/// Detect transient loops and black holes using the results from the pcap files.
+///
+/// Every probe is checked individually. A probe is in a loop if it is seen on the same link
+/// multiple times, with a different TTL. Otherwise, the observations are ordered by decreasing TTL
+/// to follow the probe hop by hop. If it never reaches a client (other than the sender), then the
+/// probe ended in a black hole at the last router it reached. Consecutive probes of the same flow
+/// with the same anomaly are merged into a single [`TransientAnomaly`].
+pub fn detect_anomalies(
+    captures: &[(RouterId, RouterId, HashMap<u32, Vec<ProbeObservation>>)],
+    flows: &HashMap<(RouterId, Prefix), u32>,
+) -> Vec<TransientAnomaly> {
+    let mut result = Vec::new();
+
+    let mut flows = flows.iter().map(|(k, flow)| (*flow, *k)).collect::<Vec<_>>();
+    flows.sort();
+
+    for (flow, (flow_start, prefix)) in flows {
+        // collect all observations, grouped by the sequence number
+        let mut probes: BTreeMap<u32, Vec<(RouterId, RouterId, ProbeObservation)>> =
+            BTreeMap::new();
+        for (a, b, cap) in captures.iter() {
+            for obs in cap.get(&flow).into_iter().flatten() {
+                probes.entry(obs.seq_num).or_default().push((*a, *b, *obs));
+            }
+        }
+
+        let mut current: Option<TransientAnomaly> = None;
+        let mut last_time: f64 = 0.0;
+        for (seq_num, mut obs) in probes {
+            let time = obs.iter().map(|(_, _, o)| o.time).fold(f64::INFINITY, f64::min);
+            let anomaly = probe_anomaly(flow_start, &mut obs);
+
+            // check if the current anomaly continues
+            if let Some(cur) = current.as_mut() {
+                if anomaly.as_ref() == Some(&(cur.kind, cur.location.clone())) {
+                    cur.last_seq = seq_num;
+                    cur.num_packets += 1;
+                    cur.duration += time - last_time;
+                    last_time = time;
+                    continue;
+                }
+                result.push(current.take().unwrap());
+            }
+
+            if let Some((kind, location)) = anomaly {
+                current = Some(TransientAnomaly {
+                    flow: (flow_start, prefix),
+                    kind,
+                    location,
+                    first_seq: seq_num,
+                    last_seq: seq_num,
+                    num_packets: 1,
+                    duration: 0.0,
+                });
+                last_time = time;
+            }
         }
+        result.extend(current);
     }
 
     result
 }
 
+/// Check a single probe for an anomaly, returning the kind and the location.
+fn probe_anomaly(
+    flow_start: RouterId,
+    obs: &mut Vec<(RouterId, RouterId, ProbeObservation)>,
+) -> Option<(AnomalyKind, Vec<RouterId>)> {
+    // search for a link on which the probe was seen with different TTLs
+    let mut links: HashMap<(RouterId, RouterId), u8> = HashMap::new();
+    let mut loops: Vec<(RouterId, RouterId)> = Vec::new();
+    for (a, b, o) in obs.iter() {
+        match links.insert((*a, *b), o.ttl) {
+            Some(ttl) if ttl != o.ttl => loops.push((*a, *b)),
+            _ => {}
+        }
+    }
+    if let Some((a, b)) = loops.into_iter().min() {
+        return Some((AnomalyKind::Loop, vec![a, b]));
+    }
+
+    // follow the probe hop by hop
+    obs.sort_by(|(_, _, x), (_, _, y)| y.ttl.cmp(&x.ttl));
+    let mut node = flow_start;
+    for (a, b, _) in obs.iter() {
+        if *a == node {
+            node = *b;
+        } else if *b == node {
+            node = *a;
+        }
+    }
+
+    if node != flow_start && node.index() >= CLIENT_ID_BASE as usize {
+        None
+    } else {
+        Some((AnomalyKind::BlackHole, vec![node]))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -323,4 +551,94 @@ mod test {
             ]
         );
     }
+
+    fn probes(seq: Vec<u32>, ttl: u8) -> Vec<ProbeObservation> {
+        seq.into_iter()
+            .map(|seq_num| ProbeObservation { seq_num, ttl, time: seq_num as f64 })
+            .collect()
+    }
+
+    #[test]
+    fn detect_loop_and_black_hole() {
+        let n1: RouterId = 1000001.into();
+        let n2: RouterId = 2.into();
+        let n3: RouterId = 3.into();
+        let n4: RouterId = 1000004.into();
+
+        let flows = hashmap![(n1, Prefix(0)) => 0];
+
+        // probes 0 and 5 are delivered, 1 and 2 are in a loop between n2 and n3, and 3 and 4 are
+        // dropped at n3.
+        let mut n2_n3 = probes(vec![0, 1, 2, 3, 4, 5], 63);
+        n2_n3.extend(probes(vec![1, 2], 62));
+        n2_n3.extend(probes(vec![1, 2], 61));
+        let captures = vec![
+            (n1, n2, hashmap! { 0 => probes(vec![0, 1, 2, 3, 4, 5], 64) }),
+            (n2, n3, hashmap! { 0 => n2_n3 }),
+            (n3, n4, hashmap! { 0 => probes(vec![0, 5], 62) }),
+        ];
+
+        let result = detect_anomalies(&captures, &flows);
+        assert_eq!(
+            result,
+            vec![
+                TransientAnomaly {
+                    flow: (n1, Prefix(0)),
+                    kind: AnomalyKind::Loop,
+                    location: vec![n2, n3],
+                    first_seq: 1,
+                    last_seq: 2,
+                    num_packets: 2,
+                    duration: 1.0,
+                },
+                TransientAnomaly {
+                    flow: (n1, Prefix(0)),
+                    kind: AnomalyKind::BlackHole,
+                    location: vec![n3],
+                    first_seq: 3,
+                    last_seq: 4,
+                    num_packets: 2,
+                    duration: 1.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn time_series_of_paths() {
+        let n1: RouterId = 1000001.into();
+        let n2: RouterId = 2.into();
+        let n3: RouterId = 3.into();
+        let n4: RouterId = 4.into();
+        let n5: RouterId = 1000005.into();
+
+        let flows = hashmap![(n1, Prefix(0)) => 0];
+
+        // probes 0 and 1 take n3, probe 2 is dropped, and probes 3 and 4 take n4
+        let captures = vec![
+            (n1, n2, hashmap! { 0 => probes(vec![0, 1, 2, 3, 4], 64) }),
+            (n2, n3, hashmap! { 0 => probes(vec![0, 1], 63) }),
+            (n3, n5, hashmap! { 0 => probes(vec![0, 1], 62) }),
+            (n2, n4, hashmap! { 0 => probes(vec![3, 4], 63) }),
+            (n4, n5, hashmap! { 0 => probes(vec![3, 4], 62) }),
+        ];
+
+        let samples = path_samples(&captures, &flows);
+        let samples = samples.get(&(n1, Prefix(0))).unwrap();
+        assert_eq!(samples.len(), 5);
+        assert_eq!(
+            samples.iter().map(|s| s.time).collect::<Vec<_>>(),
+            vec![0.0, 1.0, 2.0, 3.0, 4.0]
+        );
+
+        let changes = path_changes(samples);
+        assert_eq!(
+            changes,
+            vec![
+                PathSample { seq_num: 0, time: 0.0, path: Some(vec![n1, n2, n3, n5]) },
+                PathSample { seq_num: 2, time: 2.0, path: None },
+                PathSample { seq_num: 3, time: 3.0, path: Some(vec![n1, n2, n4, n5]) },
+            ]
+        );
+    }
 }
@@ -26,30 +26,42 @@ use snowcap::netsim::route_map::RouteMap;
 use snowcap::netsim::route_map::*;
 use snowcap::netsim::*;
 
+use crate::bmp::{BmpCollector, BmpTarget, RibSnapshot, BMP_PORT};
 use crate::config::{apply_config, parse_modifier};
-use crate::frr_conn::{FrrConnection, RoutingTable};
-use crate::pcap_reader::{extract_pcap_flows, path_inference};
+use crate::driver::RouterKind;
+use crate::frr_conn::RoutingTable;
+use crate::pcap_reader::{
+    detect_anomalies, extract_pcap_flows, extract_pcap_probes, path_inference, path_samples,
+    PathSample, ProbeObservation, TransientAnomaly,
+};
 use crate::python_conn::PythonConnection;
 
 use log::*;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::error::Error;
 use std::fmt;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const CONVERGE_CHECK_INTERVAL_MS: u64 = 1000;
 const CONVERGE_CHECK_NUM_INVARIANT: usize = 5;
 const WAIT_NETWORK_INITIALIZE_S: u64 = 200;
+/// Maximum number of threads, which connect to the routers or read capture files at the same time.
+const MAX_PARALLEL_JOBS: usize = 16;
 
 /// Start Router-ID, used to internally represent clients (python clients, or VPCS).
 pub const CLIENT_ID_BASE: u32 = 1000000;
 
-const ROUTER_TEMPLATE_NAME: &str = "FRR 7.3.1";
 const CLIENT_TEMPLATE_NAME: &str = "Python, Go, Perl, PHP";
 
+const NAT_TEMPLATE_NAME: &str = "NAT";
+
+const BMP_QUIET_S: u64 = 5;
+const BMP_TIMEOUT_S: u64 = 300;
+
 const PYTHON_SENDER_PROGRAM: &str = "
 import socket, sys, time
 seq = 0
@@ -86,16 +98,34 @@ while True:
 ///   - Loopback Address: `(100 + x).0.0.1/32`
 ///   - Address of the interface towards the client: `(100 + x).0.0.2/24`
 ///   - Address of the client: `(100 + x).0.0.2/24`
-/// - **Origin routers**, with router id `x`, advertising prefix `p`
-///   - Loopback Address: `(200 + x).0.0.1/32`
-///   - Address of the interface towards the client: `(200 + x).0.0.2/24`
-///   - Address of the client: `(200 + x).0.0.2/24`
+/// - **Origin routers**, the `o`-th origin router, advertising prefix `p`
+///   - Loopback Address: `(200 + o).0.0.1/32`
+///   - Address of the interface towards the client: `(200 + o).0.0.2/24`
+///   - Address of the client: `(200 + o).0.0.2/24`
 /// - **Links: Internal --- Internal/External**, with link i 'x', from router `a` to router `b`
 ///   - Address of router a: `10.1.x.1/32`
 ///   - Address of router b: `10.1.x.2/32`
 /// - **Links: External --- Origin**, from external router `x` to origin router `o` with orefix `p`
 ///   - Address of origin router o: `(200 + o).1.x.1/32`
 ///   - Address of external router b: `(200 + o).1.x.2/32`
+///
+/// ## Dual-Stack Networks
+///
+/// If the network knows any IPv6 prefix, then all routers are configured as dual-stack routers.
+/// Every IPv4 address `a.b.c.d/m` from above is then also assigned as the IPv6 address
+/// `fd00::a.b.c.d/(96 + m)` (see [`IpAddr::to_v6`]), OSPFv3 is configured with the same costs as
+/// OSPF, and all BGP sessions exchange both IPv4 and IPv6 routes. The origin router of an IPv6
+/// prefix only advertises its IPv6 network. Paths towards IPv6 prefixes are measured using
+/// traceroute over IPv6, but the clients only send traffic towards IPv4 prefixes.
+///
+/// ## BMP Monitoring
+///
+/// If BMP monitoring is enabled, every internal FRR router gets an additional management interface
+/// (the next free interface after the client), connected to its own GNS3 NAT node. The router
+/// receives its address on this interface via DHCP, and sends the post-policy Adj-RIB-In of all
+/// BGP sessions to the [`BmpCollector`] running on the host (see [`BmpTarget`]). The management
+/// interfaces are not part of `links`, and are never captured. The network is only considered
+/// converged if, additionally to the routing tables, no BMP update was received for 5 seconds.
 #[derive(Debug, Clone, PartialEq)]
 pub struct PhysicalNetwork {
     server: GNS3Server,
@@ -103,6 +133,7 @@ pub struct PhysicalNetwork {
     num_explicit_routers: usize,
     num_origin_routers: usize,
     prefixes: Vec<Prefix>,
+    dual_stack: bool,
     /// Vector of all routers in the network
     pub routers: Vec<PhysicalRouter>,
     /// Vector of all links in the network
@@ -113,17 +144,32 @@ pub struct PhysicalNetwork {
     prefix_router_lookup: HashMap<Prefix, RouterId>,
     reverse_ip_lookup: HashMap<[u8; 4], RouterId>,
     flow_lookup: HashMap<(RouterId, Prefix), u32>,
-    frr_template_id: String,
+    router_template_ids: HashMap<RouterKind, String>,
     client_tempate_id: String,
+    nat_template_id: Option<String>,
+    bmp: Option<BmpCollector>,
     persistent_gns_project: bool,
 }
 
 impl PhysicalNetwork {
-    /// Generate the physical network
+    /// Generate the physical network, where all routers are FRR routers.
     pub fn new(
         net: &Network,
         name: impl AsRef<str>,
         persistent_gns_project: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::with_router_kinds(net, name, persistent_gns_project, &HashMap::new(), false)
+    }
+
+    /// Generate the physical network, where the routers in `router_kinds` use the given device
+    /// (see [`RouterKind`]). All other routers, and all origin routers, are FRR routers. If
+    /// `bmp_monitoring` is set, the internal FRR routers send their RIBs to a BMP collector.
+    pub fn with_router_kinds(
+        net: &Network,
+        name: impl AsRef<str>,
+        persistent_gns_project: bool,
+        router_kinds: &HashMap<RouterId, RouterKind>,
+        bmp_monitoring: bool,
     ) -> Result<Self, Box<dyn Error>> {
         let config = net.current_config();
         let mut server = GNS3Server::new("localhost", 3080)?;
@@ -141,15 +187,27 @@ impl PhysicalNetwork {
         let num_devices = num_explicit_routers + num_origin_routers;
 
         // create routers
-        let mut router_template = None;
+        let kinds = [RouterKind::Frr, RouterKind::Bird, RouterKind::CiscoIosvL2];
+        let mut router_template_ids = HashMap::new();
         let mut client_template = None;
+        let mut nat_template = None;
         server.get_templates()?.into_iter().for_each(|t| {
-            if t.name == ROUTER_TEMPLATE_NAME {
-                router_template = Some(t.id);
+            if let Some(kind) = kinds.iter().find(|k| k.template_name() == t.name) {
+                router_template_ids.insert(*kind, t.id);
             } else if t.name == CLIENT_TEMPLATE_NAME {
                 client_template = Some(t.id);
+            } else if t.name == NAT_TEMPLATE_NAME {
+                nat_template = Some(t.id);
             }
         });
+        for kind in std::iter::once(&RouterKind::Frr).chain(router_kinds.values()) {
+            if !router_template_ids.contains_key(kind) {
+                return Err(format!("GNS3 template {} not found!", kind.template_name()).into());
+            }
+        }
+        if bmp_monitoring && nat_template.is_none() {
+            return Err(format!("GNS3 template {} not found!", NAT_TEMPLATE_NAME).into());
+        }
 
         let mut phys_net = Self {
             server,
@@ -157,6 +215,7 @@ impl PhysicalNetwork {
             num_explicit_routers,
             num_origin_routers,
             prefixes: net.get_known_prefixes().iter().cloned().collect(),
+            dual_stack: net.get_known_prefixes().iter().any(|p| p.afi() == Afi::Ipv6),
             routers: Vec::with_capacity(num_devices),
             links: Vec::with_capacity(net.links_symmetric().count()),
             clients: Vec::with_capacity(num_devices),
@@ -164,16 +223,22 @@ impl PhysicalNetwork {
             ip_lookup: HashMap::new(),
             reverse_ip_lookup: HashMap::new(),
             flow_lookup: HashMap::new(),
-            frr_template_id: router_template.unwrap(),
+            router_template_ids,
             client_tempate_id: client_template.unwrap(),
+            nat_template_id: nat_template,
+            bmp: None,
             persistent_gns_project,
         };
 
-        phys_net.create_routers(net)?;
+        phys_net.create_routers(net, router_kinds)?;
         phys_net.create_origin_routers(net)?;
         phys_net.create_all_links(net)?;
         phys_net.create_links_to_origin(net)?;
         phys_net.create_clients_on_all_routers();
+        if bmp_monitoring {
+            phys_net.create_bmp_links()?;
+            phys_net.bmp = Some(BmpCollector::start(BMP_PORT)?);
+        }
         apply_config(&mut phys_net, config);
         phys_net.setup_ip_lookup();
         phys_net.prepare_flows();
@@ -182,10 +247,10 @@ impl PhysicalNetwork {
         phys_net.server.start_all_nodes()?;
 
         phys_net.setup_clients()?;
-        phys_net.setup_frr_routers()?;
+        phys_net.setup_routers()?;
 
         info!("Network successfully configured! waiting for convergence...");
-        thread::sleep(Duration::from_secs(WAIT_NETWORK_INITIALIZE_S));
+        phys_net.wait_initialized(net)?;
         phys_net.wait_converge()?;
 
         Ok(phys_net)
@@ -205,16 +270,48 @@ impl PhysicalNetwork {
         *self.prefix_router_lookup.get(&prefix).unwrap()
     }
 
+    /// Returns all prefixes of the network
+    pub fn prefixes(&self) -> &[Prefix] {
+        &self.prefixes
+    }
+
+    /// Returns the IPv4 network advertised by the origin router of a given prefix, or `None` if the
+    /// prefix has no origin router, or if it is an IPv6 prefix.
+    pub fn prefix_network(&self, prefix: Prefix) -> Option<&IpAddr> {
+        let origin = self.prefix_router_lookup.get(&prefix)?;
+        self.routers[origin.index()].advertise_route.as_ref()
+    }
+
+    /// Returns the BMP collector, if BMP monitoring is enabled.
+    pub fn bmp(&self) -> Option<&BmpCollector> {
+        self.bmp.as_ref()
+    }
+
+    /// Returns the current RIBs of all monitored routers, or `None` if BMP monitoring is disabled.
+    pub fn rib_snapshot(&self) -> Option<RibSnapshot> {
+        self.bmp.as_ref().map(|bmp| bmp.snapshot())
+    }
+
     /// Create all internal routers
-    fn create_routers(&mut self, net: &Network) -> Result<(), Box<dyn Error>> {
+    fn create_routers(
+        &mut self,
+        net: &Network,
+        router_kinds: &HashMap<RouterId, RouterKind>,
+    ) -> Result<(), Box<dyn Error>> {
         for i in 0..net.num_devices() {
             let router_id = (i as u32).into();
+            let kind = router_kinds.get(&router_id).copied().unwrap_or_default();
+            if self.dual_stack && kind != RouterKind::Frr {
+                return Err("Dual-stack networks are only supported with FRR routers".into());
+            }
+            let template_id = &self.router_template_ids[&kind];
             match net.get_device(router_id) {
                 NetworkDevice::InternalRouter(r) => {
-                    let gns_node = self.server.create_node(r.name(), &self.frr_template_id)?;
+                    let gns_node = self.server.create_node(r.name(), template_id)?;
                     self.routers.push(PhysicalRouter {
                         router_id,
                         name: r.name().to_string(),
+                        kind,
                         as_id: r.as_id(),
                         gns_node,
                         loopback_addr: IpAddr::new(format!("10.0.{}.1", router_id.index()), 24),
@@ -223,14 +320,20 @@ impl PhysicalNetwork {
                         route_maps: Vec::new(),
                         static_routes: Vec::new(),
                         advertise_route: Some(IpAddr::new("10.0.0.0", 8)),
+                        advertise_route_v6: self.v6_of(&IpAddr::new("10.0.0.0", 8)),
                         is_internal: true,
+                        dual_stack: self.dual_stack,
+                        always_compare_med: false,
+                        cluster_id: None,
+                        bmp: None,
                     });
                 }
                 NetworkDevice::ExternalRouter(r) => {
-                    let gns_node = self.server.create_node(r.name(), &self.frr_template_id)?;
+                    let gns_node = self.server.create_node(r.name(), template_id)?;
                     self.routers.push(PhysicalRouter {
                         router_id,
                         name: r.name().to_string(),
+                        kind,
                         as_id: r.as_id(),
                         gns_node,
                         loopback_addr: IpAddr::new(
@@ -245,13 +348,20 @@ impl PhysicalNetwork {
                             format!("{}.0.0.0", router_id.index() + 100),
                             8,
                         )),
+                        advertise_route_v6: self
+                            .v6_of(&IpAddr::new(format!("{}.0.0.0", router_id.index() + 100), 8)),
                         is_internal: false,
+                        dual_stack: self.dual_stack,
+                        always_compare_med: false,
+                        cluster_id: None,
+                        bmp: None,
                     });
                 }
                 _ => unreachable!("Could not find device!"),
             }
             info!(
-                "Create router: {} with ip {}, telnet port: {}",
+                "Create {} router: {} with ip {}, telnet port: {}",
+                kind,
                 self.routers.last().unwrap().name,
                 self.routers.last().unwrap().loopback_addr,
                 self.routers.last().unwrap().gns_node.port
@@ -283,21 +393,37 @@ impl PhysicalNetwork {
             let as_id: AsId = *as_id_iter.next().unwrap();
             assert!(as_id_iter.all(|x| as_id == *x));
 
-            let name = format!("origin{}", prefix.0);
-            let gns_node = self.server.create_node(&name, &self.frr_template_id)?;
+            let name = match prefix.afi() {
+                Afi::Ipv4 => format!("origin{}", prefix.id()),
+                Afi::Ipv6 => format!("origin{}v6", prefix.id()),
+            };
+            let gns_node =
+                self.server.create_node(&name, &self.router_template_ids[&RouterKind::Frr])?;
             let origin_router_id = self.routers.len();
+            let origin_nr = origin_router_id - self.num_explicit_routers;
+            let network = IpAddr::new(format!("{}.0.0.0", origin_nr + 200), 8);
+            let (advertise_route, advertise_route_v6) = match prefix.afi() {
+                Afi::Ipv4 => (Some(network), None),
+                Afi::Ipv6 => (None, Some(network.to_v6())),
+            };
             self.routers.push(PhysicalRouter {
                 router_id: (origin_router_id as u32).into(),
                 name,
+                kind: RouterKind::Frr,
                 as_id,
                 gns_node,
-                loopback_addr: IpAddr::new(format!("{}.0.0.1", prefix.0 + 200), 24),
+                loopback_addr: IpAddr::new(format!("{}.0.0.1", origin_nr + 200), 24),
                 ifaces: Vec::new(),
                 bgp_sessions: Vec::new(),
                 route_maps: Vec::new(),
                 static_routes: Vec::new(),
-                advertise_route: Some(IpAddr::new(format!("{}.0.0.0", prefix.0 + 200), 8)),
+                advertise_route,
+                advertise_route_v6,
                 is_internal: false,
+                dual_stack: self.dual_stack,
+                always_compare_med: false,
+                cluster_id: None,
+                bmp: None,
             });
 
             self.prefix_router_lookup.insert(*prefix, (origin_router_id as u32).into());
@@ -371,7 +497,11 @@ impl PhysicalNetwork {
     // Create the links to the origin routers
     fn create_links_to_origin(&mut self, net: &Network) -> Result<(), Box<dyn Error>> {
         for prefix in self.prefixes.iter() {
-            let origin_router_index = self.get_origin_router_index(*prefix);
+            let origin_router_index = match self.prefix_router_lookup.get(prefix) {
+                Some(origin) => origin.index(),
+                None => continue,
+            };
+            let origin_nr = origin_router_index - self.num_explicit_routers;
             for ext_router_id in
                 Self::get_external_routers_with_prefix(net, *prefix).iter().map(|r| r.router_id())
             {
@@ -393,9 +523,9 @@ impl PhysicalNetwork {
                 )?;
 
                 let origin_addr =
-                    IpAddr::new(format!("{}.1.{}.1", 200 + prefix.0, ext_router_id.index()), 24);
+                    IpAddr::new(format!("{}.1.{}.1", 200 + origin_nr, ext_router_id.index()), 24);
                 let ext_addr =
-                    IpAddr::new(format!("{}.1.{}.2", 200 + prefix.0, ext_router_id.index()), 24);
+                    IpAddr::new(format!("{}.1.{}.2", 200 + origin_nr, ext_router_id.index()), 24);
 
                 self.links.push(PhysicalLink {
                     gns_link,
@@ -508,6 +638,25 @@ impl PhysicalNetwork {
         }
     }
 
+    /// Connect the next free interface of every internal FRR router to a new NAT node, which is used
+    /// to reach the BMP collector on the host.
+    fn create_bmp_links(&mut self) -> Result<(), Box<dyn Error>> {
+        let nat_template_id = self.nat_template_id.as_ref().unwrap();
+        for r in self.routers.iter_mut().filter(|r| r.is_internal) {
+            if r.kind != RouterKind::Frr {
+                warn!("BMP monitoring is not supported on {} ({})", r.name, r.kind);
+                continue;
+            }
+            let iface = r.ifaces.len();
+            assert!(iface < r.gns_node.interfaces.len());
+            let gns_nat = self.server.create_node(format!("{}-nat", r.name), nat_template_id)?;
+            self.server.create_link(&r.gns_node, iface, &gns_nat, 0)?;
+            r.bmp = Some(BmpTarget::new(r.gns_node.interfaces[iface].short_name.clone()));
+            info!("Created BMP management link of {}", r.name);
+        }
+        Ok(())
+    }
+
     /// Setup the reverse IP lookup
     fn setup_ip_lookup(&mut self) {
         for router in self.routers.iter() {
@@ -530,7 +679,8 @@ impl PhysicalNetwork {
         let mut flow_id: u32 = 0;
         for i in 0..self.clients.len() {
             if self.routers[i].is_internal {
-                for p in self.prefixes.iter() {
+                // the clients only send IPv4 traffic
+                for p in self.prefixes.iter().filter(|p| p.afi() == Afi::Ipv4) {
                     self.flow_lookup.insert((self.clients[i].client_id, *p), flow_id);
                     // get the target ip
                     let origin_router = self.prefix_router_lookup.get(p).unwrap();
@@ -613,13 +763,13 @@ impl PhysicalNetwork {
         Ok(())
     }
 
-    fn setup_frr_routers(&mut self) -> Result<(), Box<dyn Error>> {
+    fn setup_routers(&mut self) -> Result<(), Box<dyn Error>> {
         info!("connecting terminals...");
         let mut jobs = Vec::with_capacity(self.routers.len());
         for i in 0..self.routers.len() {
             let r = self.routers.get(i).unwrap().clone();
             jobs.push(thread::spawn(move || {
-                let mut c = match FrrConnection::new(r.gns_node.port) {
+                let mut c = match r.kind.connect(r.gns_node.port) {
                     Ok(c) => c,
                     Err(e) => {
                         error!("Cannot setup a session to the router {}: {}", r.name, e);
@@ -659,11 +809,58 @@ impl PhysicalNetwork {
 
     /// Returns the index of an origin router in the structure
     fn get_origin_router_index(&self, prefix: Prefix) -> usize {
-        self.num_explicit_routers + prefix.0 as usize
+        self.prefix_router_lookup.get(&prefix).unwrap().index()
+    }
+
+    /// Returns the IPv6 address of `addr` if the network is a dual-stack network.
+    fn v6_of(&self, addr: &IpAddr) -> Option<IpAddr> {
+        if self.dual_stack {
+            Some(addr.to_v6())
+        } else {
+            None
+        }
+    }
+
+    /// Wait until every internal router has learned a BGP route for the network of every IPv4
+    /// prefix, for which the simulation has a route. If this takes longer than 200 seconds, a
+    /// warning is printed, and the function returns nevertheless.
+    fn wait_initialized(&self, net: &Network) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        let mut fw_state = net.get_forwarding_state();
+        let mut expected: Vec<(usize, IpAddr)> = Vec::new();
+        for r in self.routers.iter().filter(|r| r.is_internal) {
+            for p in self.prefixes.iter().filter(|p| p.afi() == Afi::Ipv4) {
+                if let Some(network) = self.prefix_network(*p) {
+                    if fw_state.get_route(r.router_id, *p).is_ok() {
+                        expected.push((r.router_id.index(), network.clone()));
+                    }
+                }
+            }
+        }
+        loop {
+            let tables = self.get_routing_tables()?;
+            let missing = expected
+                .iter()
+                .filter(|(i, network)| !tables[*i].entries.iter().any(|e| e.network == *network))
+                .count();
+            if missing == 0 {
+                info!("All routes learned after {} seconds", start.elapsed().as_secs());
+                return Ok(());
+            }
+            if start.elapsed() >= Duration::from_secs(WAIT_NETWORK_INITIALIZE_S) {
+                warn!(
+                    "{} routes are still missing after {} seconds",
+                    missing, WAIT_NETWORK_INITIALIZE_S
+                );
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(CONVERGE_CHECK_INTERVAL_MS));
+        }
     }
 
     /// Wait until the network has converged. We call a network to be converged, if after 10
-    /// consecutive trials (with 3 second delay) are identical.
+    /// consecutive trials (with 3 second delay) are identical. If BMP monitoring is enabled, we also
+    /// wait until no BMP update was received for some time.
     pub fn wait_converge(&self) -> Result<(), Box<dyn Error>> {
         let now = std::time::SystemTime::now();
         // get the initial routing tables
@@ -679,82 +876,87 @@ impl PhysicalNetwork {
                 current_rt = new_rt;
             }
         }
+        if let Some(bmp) = self.bmp.as_ref() {
+            bmp.wait_quiet(Duration::from_secs(BMP_QUIET_S), Duration::from_secs(BMP_TIMEOUT_S))?;
+        }
         info!("Network converged after {} seconds", now.elapsed().unwrap().as_secs());
         Ok(())
     }
 
     /// apply a modifier, wait until everything has converged, and check all flows
-    #[allow(clippy::type_complexity, clippy::needless_collect, clippy::map_collect_result_unit)]
+    #[allow(clippy::type_complexity)]
     pub fn apply_modifier_wait_convergence_check_flows(
         &mut self,
         modifier: &ConfigModifier,
     ) -> Result<HashMap<(RouterId, Prefix), HashMap<Option<Vec<RouterId>>, usize>>, Box<dyn Error>>
     {
-        // start to capture
-        self.start_capture()?;
-
-        // start the flows
-        let stop = Arc::new(AtomicBool::new(false));
-        let handles: Vec<thread::JoinHandle<Result<(), String>>> = self
-            .clients
-            .iter()
-            .filter(|c| !c.flows.is_empty())
-            .map(|c| {
-                let port = c.gns_client.port;
-                let flows = c.flows.clone();
-                let s = stop.clone();
-                thread::spawn(move || {
-                    let mut c = PythonConnection::new(port).map_err(|e| format!("{}", e))?;
-                    c.run_program(format!(
-                        "python3 /root/sender.py {}",
-                        flows
-                            .into_iter()
-                            .map(|(ip, flow_id)| format!(
-                                "{}.{}.{}.{} {}",
-                                ip[0], ip[1], ip[2], ip[3], flow_id
-                            ))
-                            .collect::<Vec<_>>()
-                            .join(" "),
-                    ))
-                    .map_err(|e| format!("{}", e))?;
-                    // wait until the stop command is received
-                    while !s.load(Relaxed) {
-                        thread::sleep(Duration::from_secs(1));
-                    }
-                    // send control-c
-                    c.ctrl_c().map_err(|e| format!("{}", e))?;
-                    Ok(())
-                })
-            })
-            .collect::<Vec<_>>();
+        self.check_flows_during(|phys_net| {
+            // wait 5 seconds until all flows have started sending their packets
+            thread::sleep(Duration::from_secs(5));
 
-        // wait 5 seconds until all flows have started sending their packets
-        thread::sleep(Duration::from_secs(5));
+            // apply the modifier
+            phys_net.apply_modifier(modifier)
+        })
+    }
 
-        // apply the modifier
-        self.apply_modifier(modifier)?;
+    #[allow(clippy::type_complexity)]
+    /// apply a modifier, wait until everything has converged, and check all flows
+    pub fn apply_all_modifiers_wait_convergence_check_flows(
+        &mut self,
+        modifiers: &[ConfigModifier],
+        pause_duration_s: u64,
+    ) -> Result<HashMap<(RouterId, Prefix), HashMap<Option<Vec<RouterId>>, usize>>, Box<dyn Error>>
+    {
+        self.check_flows_during(|phys_net| {
+            for m in modifiers {
+                // wait 5 seconds until all flows have started sending their packets
+                thread::sleep(Duration::from_secs(pause_duration_s));
 
-        info!("waiting for convergence");
-        // wait until convergence
-        self.wait_converge()?;
+                // apply the modifier
+                phys_net.apply_modifier(m)?;
+            }
+            Ok(())
+        })
+    }
 
-        // stop the flows
-        stop.store(true, Relaxed);
-        handles.into_iter().map(|h| h.join().unwrap()).collect::<Result<(), String>>()?;
+    /// Fail the link between `a` and `b`, wait until everything has converged, and check all
+    /// flows. The link stays down until it is restored with [`PhysicalNetwork::set_link_state`].
+    #[allow(clippy::type_complexity)]
+    pub fn fail_link_wait_convergence_check_flows(
+        &mut self,
+        a: RouterId,
+        b: RouterId,
+    ) -> Result<HashMap<(RouterId, Prefix), HashMap<Option<Vec<RouterId>>, usize>>, Box<dyn Error>>
+    {
+        self.check_flows_during(|phys_net| {
+            // wait 5 seconds until all flows have started sending their packets
+            thread::sleep(Duration::from_secs(5));
 
-        // Stop the capturing
-        self.stop_capture()?;
+            // shut down the link
+            phys_net.set_link_state(a, b, false)
+        })
+    }
 
-        // extract all path information
-        self.read_infer_flows()
+    /// Shut down (or enable, if `up` is set) the interfaces of the link between `a` and `b` on
+    /// both routers. This does not wait until the network has converged.
+    pub fn set_link_state(&self, a: RouterId, b: RouterId, up: bool) -> Result<(), Box<dyn Error>> {
+        for (router, neighbor) in [(a, b), (b, a)].iter() {
+            let r = &self.routers[router.index()];
+            let iface = r.ifaces.iter().find(|i| i.neighbor == *neighbor).ok_or_else(|| {
+                format!("No link between {} and {}", self.router_name(a), self.router_name(b))
+            })?;
+            let mut term = r.kind.connect(r.gns_node.port)?;
+            term.set_interface_state(&iface.gns_interface.short_name, up)?;
+        }
+        Ok(())
     }
 
+    /// Start the flows and the capture, perform the action, wait until everything has converged,
+    /// and check all flows.
     #[allow(clippy::type_complexity, clippy::needless_collect, clippy::map_collect_result_unit)]
-    /// apply a modifier, wait until everything has converged, and check all flows
-    pub fn apply_all_modifiers_wait_convergence_check_flows(
+    fn check_flows_during(
         &mut self,
-        modifiers: &[ConfigModifier],
-        pause_duration_s: u64,
+        action: impl FnOnce(&mut Self) -> Result<(), Box<dyn Error>>,
     ) -> Result<HashMap<(RouterId, Prefix), HashMap<Option<Vec<RouterId>>, usize>>, Box<dyn Error>>
     {
         // start to capture
@@ -795,13 +997,7 @@ impl PhysicalNetwork {
             })
             .collect::<Vec<_>>();
 
-        for m in modifiers {
-            // wait 5 seconds until all flows have started sending their packets
-            thread::sleep(Duration::from_secs(pause_duration_s));
-
-            // apply the modifier
-            self.apply_modifier(m)?;
-        }
+        action(self)?;
 
         info!("waiting for convergence");
         // wait until convergence
@@ -822,8 +1018,9 @@ impl PhysicalNetwork {
     fn apply_modifier(&mut self, modifier: &ConfigModifier) -> Result<(), Box<dyn Error>> {
         let commands = parse_modifier(self, modifier);
         for (target, commands) in commands {
-            let mut term = FrrConnection::new(self.routers[target.index()].gns_node.port)?;
-            term.reconfigure(commands)?;
+            let router = &self.routers[target.index()];
+            let mut term = router.kind.connect(router.gns_node.port)?;
+            term.apply_step(router, &commands)?;
         }
         Ok(())
     }
@@ -838,11 +1035,20 @@ impl PhysicalNetwork {
         Ok(())
     }
 
-    /// Start capture on all links
+    /// Stop capture on all links. If the capture file of a link cannot be read locally (because the
+    /// GNS3 server runs on a different machine), it is downloaded into the temporary directory,
+    /// and the capture file path of the link is updated accordingly.
     pub fn stop_capture(&mut self) -> Result<(), Box<dyn Error>> {
         for l in self.links.iter_mut() {
-            let link_id = &l.gns_link.id;
-            l.gns_link = self.server.stop_capture(link_id)?;
+            let link_id = l.gns_link.id.clone();
+            l.gns_link = self.server.stop_capture(&link_id)?;
+            let remote_path = l.gns_link.capture_file_path.as_ref().ok_or("No capture file!")?;
+            if !Path::new(remote_path).is_file() {
+                let local_path = std::env::temp_dir().join(format!("snowcap-{}.pcap", link_id));
+                debug!("Downloading capture of link {} to {:?}", link_id, local_path);
+                self.server.download_capture(&link_id, &local_path)?;
+                l.gns_link.capture_file_path = Some(local_path.to_string_lossy().to_string());
+            }
         }
 
         Ok(())
@@ -855,90 +1061,111 @@ impl PhysicalNetwork {
     ) -> Result<HashMap<(RouterId, Prefix), HashMap<Option<Vec<RouterId>>, usize>>, Box<dyn Error>>
     {
         // extract all capture flow information
-        let captures = self
-            .links
-            .iter()
-            .map(|l| match extract_pcap_flows(l.gns_link.capture_file_path.as_ref().unwrap()) {
-                Ok(cap) => Ok((l.endpoint_a, l.endpoint_b, cap)),
-                Err(e) => Err(e),
-            })
-            .collect::<Result<Vec<(RouterId, RouterId, HashMap<u32, Vec<u32>>)>, Box<dyn Error>>>(
-            )?;
+        let captures = parallel_map(self.capture_jobs(), MAX_PARALLEL_JOBS, |(a, b, file)| {
+            extract_pcap_flows(file).map(|cap| (a, b, cap)).map_err(|e| format!("{}", e))
+        })
+        .into_iter()
+        .collect::<Result<Vec<(RouterId, RouterId, HashMap<u32, Vec<u32>>)>, String>>()?;
 
         // infer all flows from this
         Ok(path_inference(captures, &self.flow_lookup))
     }
 
-    /// Get all routing tables in parallel
-    #[allow(clippy::needless_collect)]
-    fn get_routing_tables(&self) -> Result<Vec<RoutingTable>, Box<dyn Error>> {
-        let jobs = self
-            .routers
+    /// Read all pcap files of the last capture, and detect transient loops and black holes (see
+    /// [`detect_anomalies`]).
+    pub fn read_transient_anomalies(&self) -> Result<Vec<TransientAnomaly>, Box<dyn Error>> {
+        Ok(detect_anomalies(&self.read_probes()?, &self.flow_lookup))
+    }
+
+    /// Read all pcap files of the last capture, and reconstruct the path of every probe, together
+    /// with the time when it was sent (see [`path_samples`]).
+    #[allow(clippy::type_complexity)]
+    pub fn read_path_samples(
+        &self,
+    ) -> Result<HashMap<(RouterId, Prefix), Vec<PathSample>>, Box<dyn Error>> {
+        Ok(path_samples(&self.read_probes()?, &self.flow_lookup))
+    }
+
+    /// Read all probe observations from the pcap files of every link.
+    #[allow(clippy::type_complexity)]
+    fn read_probes(
+        &self,
+    ) -> Result<Vec<(RouterId, RouterId, HashMap<u32, Vec<ProbeObservation>>)>, Box<dyn Error>>
+    {
+        Ok(parallel_map(self.capture_jobs(), MAX_PARALLEL_JOBS, |(a, b, file)| {
+            extract_pcap_probes(file).map(|cap| (a, b, cap)).map_err(|e| format!("{}", e))
+        })
+        .into_iter()
+        .collect::<Result<_, String>>()?)
+    }
+
+    /// Returns both endpoints and the capture file of every link.
+    fn capture_jobs(&self) -> Vec<(RouterId, RouterId, String)> {
+        self.links
             .iter()
-            .map(|r| {
-                let port = r.gns_node.port;
-                let name = r.name.clone();
-                thread::spawn(move || {
-                    let mut c = FrrConnection::new(port).map_err(|e| format!("{}", e))?;
-                    c.get_routing_table()
-                        .map_err(|e| format!("Cannot parse routing table from {}: {}", name, e))
-                })
-            })
-            .collect::<Vec<_>>();
-        jobs.into_iter()
-            .map(|handle| match handle.join().unwrap() {
-                Ok(table) => Ok(table),
-                Err(e) => Err(e.into()),
-            })
-            .collect::<Vec<Result<RoutingTable, Box<dyn Error>>>>()
-            .into_iter()
+            .map(|l| (l.endpoint_a, l.endpoint_b, l.gns_link.capture_file_path.clone().unwrap()))
             .collect()
     }
 
+    /// Get all routing tables in parallel
+    fn get_routing_tables(&self) -> Result<Vec<RoutingTable>, Box<dyn Error>> {
+        let jobs = self.routers.iter().map(|r| (r.gns_node.port, r.kind, r.name.clone())).collect();
+        Ok(parallel_map(jobs, MAX_PARALLEL_JOBS, |(port, kind, name)| {
+            let mut c = kind.connect(port).map_err(|e| format!("{}", e))?;
+            c.get_routing_table()
+                .map_err(|e| format!("Cannot parse routing table from {}: {}", name, e))
+        })
+        .into_iter()
+        .collect::<Result<_, String>>()?)
+    }
+
     /// Get all paths in the network from every explicit router to every prefix. Here, we use the
-    /// traceroute functionality!
-    #[allow(clippy::type_complexity, clippy::needless_collect)]
+    /// traceroute functionality! The routers are handled in parallel (by at most 16 threads), and
+    /// every router performs the traceroutes to all prefixes over a single session.
+    #[allow(clippy::type_complexity)]
     pub fn get_all_paths(
         &self,
     ) -> Result<HashMap<RouterId, HashMap<Prefix, Option<Vec<RouterId>>>>, Box<dyn Error>> {
-        let mut result: HashMap<RouterId, HashMap<Prefix, Option<Vec<RouterId>>>> =
-            (0..self.num_explicit_routers).map(|i| ((i as u32).into(), HashMap::new())).collect();
-
-        for prefix in self.prefixes.iter() {
-            let target_ip =
-                self.routers[self.get_origin_router_index(*prefix)].loopback_addr.clone();
-            let jobs = (0..self.num_explicit_routers)
-                .map(|i| {
-                    let port = self.routers[i].gns_node.port;
-                    let ip = target_ip.clone();
-                    thread::spawn(move || {
-                        let mut c = FrrConnection::new(port).map_err(|e| format!("{}", e))?;
-                        c.traceroute(&ip).map_err(|e| format!("{}", e))
-                    })
-                })
-                .collect::<Vec<_>>();
-
-            // wait until all traceroutes are finished
-            let mut err: Option<String> = None;
-            for (i, handle) in jobs.into_iter().enumerate() {
-                match handle.join() {
-                    Ok(Ok(Some(path))) => {
-                        let path = path
-                            .into_iter()
-                            .map(|ip| *self.reverse_ip_lookup.get(&ip).unwrap())
-                            .collect();
-                        result.get_mut(&(i as u32).into()).unwrap().insert(*prefix, Some(path));
-                    }
-                    Ok(Ok(None)) => {
-                        result.get_mut(&(i as u32).into()).unwrap().insert(*prefix, None);
-                    }
-                    Ok(Err(e)) => err = Some(e),
-                    Err(e) => err = Some(format!("Thread error:  {:?}", e)),
+        let targets = self
+            .prefixes
+            .iter()
+            .map(|prefix| {
+                let origin = &self.routers[self.get_origin_router_index(*prefix)];
+                match prefix.afi() {
+                    Afi::Ipv4 => (*prefix, origin.loopback_addr.clone()),
+                    Afi::Ipv6 => (*prefix, origin.loopback_addr.to_v6()),
                 }
-            }
-
-            if let Some(err) = err {
-                return Err(err.into());
+            })
+            .collect::<Vec<_>>();
+        let jobs = self.routers[..self.num_explicit_routers]
+            .iter()
+            .map(|r| (r.router_id, r.gns_node.port, r.kind, targets.clone()))
+            .collect();
+
+        let paths = parallel_map(
+            jobs,
+            MAX_PARALLEL_JOBS,
+            |(router, port, kind, targets)| -> Result<_, String> {
+                let mut c = kind.connect(port).map_err(|e| format!("{}", e))?;
+                let paths = targets
+                    .into_iter()
+                    .map(|(prefix, ip)| {
+                        Ok((prefix, c.traceroute(&ip).map_err(|e| format!("{}", e))?))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok((router, paths))
+            },
+        );
+
+        let mut result: HashMap<RouterId, HashMap<Prefix, Option<Vec<RouterId>>>> = HashMap::new();
+        for job in paths {
+            let (router, paths) = job?;
+            let router_paths = result.entry(router).or_default();
+            for (prefix, path) in paths {
+                let path = path.map(|p| {
+                    p.into_iter().map(|ip| *self.reverse_ip_lookup.get(&ip).unwrap()).collect()
+                });
+                router_paths.insert(prefix, path);
             }
         }
 
@@ -951,8 +1178,47 @@ impl PhysicalNetwork {
     }
 }
 
+/// Apply `f` to every job, using at most `num_workers` threads at the same time. The results are
+/// returned in the same order as the jobs.
+fn parallel_map<T, R, F>(jobs: Vec<T>, num_workers: usize, f: F) -> Vec<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let num_jobs = jobs.len();
+    let queue = Arc::new(Mutex::new(jobs.into_iter().enumerate().collect::<Vec<_>>()));
+    let f = Arc::new(f);
+    let (tx, rx) = mpsc::channel();
+    let workers = (0..num_workers.min(num_jobs))
+        .map(|_| {
+            let queue = queue.clone();
+            let f = f.clone();
+            let tx = tx.clone();
+            thread::spawn(move || loop {
+                let job = queue.lock().unwrap().pop();
+                match job {
+                    Some((i, job)) => tx.send((i, f(job))).unwrap(),
+                    None => break,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(tx);
+
+    let mut results = rx.iter().collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().expect("Something wierd happened with the threads!");
+    }
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
 impl Drop for PhysicalNetwork {
     fn drop(&mut self) {
+        if let Some(bmp) = self.bmp.as_ref() {
+            bmp.stop();
+        }
         if !self.persistent_gns_project {
             self.server.delete_project(&self.project_id).unwrap();
         }
@@ -994,6 +1260,8 @@ pub struct PhysicalRouter {
     pub router_id: RouterId,
     /// Name of the router
     pub name: String,
+    /// Device of the router, determining the driver used to configure it
+    pub kind: RouterKind,
     /// GNS3 Node Information
     pub gns_node: GNS3Node,
     /// Address of the loopback interface, should have a net mask of `32`!
@@ -1010,8 +1278,21 @@ pub struct PhysicalRouter {
     pub static_routes: Vec<StaticRouteInfo>,
     /// List of all routes that are advertised by this router via BGP
     pub advertise_route: Option<IpAddr>,
+    /// List of all IPv6 routes that are advertised by this router via BGP
+    pub advertise_route_v6: Option<IpAddr>,
     /// Flag, if the router is internal or not
     pub is_internal: bool,
+    /// Flag, if the router is a dual-stack router. Then, the loopback and every interface is also
+    /// assigned the IPv6 address given by [`IpAddr::to_v6`].
+    pub dual_stack: bool,
+    /// Flag, if the MED of routes from different neighbor ASes is compared
+    pub always_compare_med: bool,
+    /// Cluster ID for route reflection (the loopback address of a router). If `None`, the router
+    /// ID is used.
+    pub cluster_id: Option<String>,
+    /// Management interface and collector, to which BMP messages are sent. If `None`, BMP is not
+    /// configured on the router.
+    pub bmp: Option<BmpTarget>,
 }
 
 /// BGP Session Information
@@ -1045,6 +1326,9 @@ pub struct RouteMapInfo {
     pub match_statements: HashMap<&'static str, String>,
     /// Set statements, allowed by [FRR](https://docs.frrouting.org/en/latest/routemap.html)
     pub set_statements: HashMap<&'static str, String>,
+    /// Community lists referenced by the match and set statements, with their name and their
+    /// entries. Every entry permits the routes carrying all communities of the entry.
+    pub community_lists: Vec<(String, Vec<String>)>,
 }
 
 impl RouteMapInfo {
@@ -1056,51 +1340,119 @@ impl RouteMapInfo {
         map: &RouteMap,
         routers: &[PhysicalRouter],
     ) -> Self {
+        let mut community_lists = Vec::new();
+        let match_list = format!("{}_CL_match", name);
+        let del_list = format!("{}_CL_del", name);
+
+        let match_statements: HashMap<&'static str, String> = map
+            .conds()
+            .iter()
+            .filter_map(|cond| match cond {
+                RouteMapMatch::Neighbor(neighbor_id) => Some((
+                    "peer",
+                    routers[neighbor_id.index()]
+                        .ifaces
+                        .iter()
+                        .find(|i| i.neighbor == router_id)
+                        .unwrap()
+                        .iface_addr
+                        .to_string(),
+                )),
+                RouteMapMatch::Prefix(_) => todo!(),
+                RouteMapMatch::AsPath(_) => todo!(),
+                RouteMapMatch::NextHop(_) => todo!(),
+                RouteMapMatch::Community(_) => todo!(),
+                // an empty list of required communities matches every route
+                RouteMapMatch::CommunityList(RouteMapMatchCommunityList::All(l))
+                    if l.is_empty() =>
+                {
+                    None
+                }
+                RouteMapMatch::CommunityList(list) => {
+                    let entries = match list {
+                        RouteMapMatchCommunityList::Any(l) => {
+                            l.iter().map(|c| c.to_string()).collect()
+                        }
+                        RouteMapMatchCommunityList::All(l) => {
+                            vec![l.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")]
+                        }
+                    };
+                    // a list without entries is not configured, and thus matches no route
+                    if !entries.is_empty() {
+                        community_lists.push((match_list.clone(), entries));
+                    }
+                    Some(("community", match_list.clone()))
+                }
+            })
+            .collect();
+
+        let mut set_statements: HashMap<&'static str, String> = map
+            .actions()
+            .iter()
+            .filter_map(|action| match action {
+                RouteMapSet::NextHop(r) => {
+                    Some(("ip next-hop", routers[r.index()].loopback_addr.addr.clone()))
+                }
+                RouteMapSet::LocalPref(Some(lp)) => Some(("local-preference", format!("{}", lp))),
+                RouteMapSet::LocalPref(None) => Some(("local-preference", String::from("+0"))),
+                RouteMapSet::Med(Some(med)) => Some(("metric", format!("{}", med))),
+                RouteMapSet::Med(None) => Some(("metric", String::from("+0"))),
+                RouteMapSet::IgpCost(_) => panic!("IGP const cannot be changed on FRR"),
+                RouteMapSet::Community(Some(c)) => Some(("community", format!("{}", c))),
+                RouteMapSet::Community(None) => Some(("community", String::from("none"))),
+                RouteMapSet::AddCommunity(c) => Some(("community", format!("{} additive", c))),
+                RouteMapSet::AddCommunities(l) => Some((
+                    "community",
+                    format!(
+                        "{} additive",
+                        l.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")
+                    ),
+                )),
+                // deleted communities are collected in a single community list below
+                RouteMapSet::DelCommunity(_) | RouteMapSet::DelCommunities(_) => None,
+                RouteMapSet::PrependAsPath(a, n) => {
+                    Some(("as-path prepend", vec![a.0.to_string(); *n].join(" ")))
+                }
+            })
+            .collect();
+        let deleted = map
+            .actions()
+            .iter()
+            .flat_map(|action| match action {
+                RouteMapSet::DelCommunity(c) => vec![*c],
+                RouteMapSet::DelCommunities(l) => l.iter().cloned().collect(),
+                _ => Vec::new(),
+            })
+            .collect::<BTreeSet<u32>>();
+        if !deleted.is_empty() {
+            community_lists
+                .push((del_list.clone(), deleted.iter().map(|c| c.to_string()).collect()));
+            set_statements.insert("comm-list", format!("{} delete", del_list));
+        }
+
         Self {
             name,
             state: if map.state().is_allow() { "permit" } else { "deny" },
             order: map.order() as u32,
             direction: if direction == RouteMapDirection::Incoming { "in" } else { "out" },
-            match_statements: map
-                .conds()
-                .iter()
-                .map(|cond| match cond {
-                    RouteMapMatch::Neighbor(neighbor_id) => (
-                        "peer",
-                        routers[neighbor_id.index()]
-                            .ifaces
-                            .iter()
-                            .find(|i| i.neighbor == router_id)
-                            .unwrap()
-                            .iface_addr
-                            .to_string(),
-                    ),
-                    RouteMapMatch::Prefix(_) => todo!(),
-                    RouteMapMatch::AsPath(_) => todo!(),
-                    RouteMapMatch::NextHop(_) => todo!(),
-                    RouteMapMatch::Community(_) => todo!(),
-                })
-                .collect(),
-            set_statements: map
-                .actions()
-                .iter()
-                .filter_map(|action| match action {
-                    RouteMapSet::NextHop(r) => {
-                        Some(("ip next-hop", routers[r.index()].loopback_addr.addr.clone()))
-                    }
-                    RouteMapSet::LocalPref(Some(lp)) => {
-                        Some(("local-preference", format!("{}", lp)))
-                    }
-                    RouteMapSet::LocalPref(None) => Some(("local-preference", String::from("+0"))),
-                    RouteMapSet::Med(Some(med)) => Some(("metric", format!("{}", med))),
-                    RouteMapSet::Med(None) => Some(("metric", String::from("+0"))),
-                    RouteMapSet::IgpCost(_) => panic!("IGP const cannot be changed on FRR"),
-                    RouteMapSet::Community(Some(c)) => Some(("community", format!("{}", c))),
-                    RouteMapSet::Community(None) => panic!("Communities are not yet supported"),
-                })
-                .collect(),
+            match_statements,
+            set_statements,
+            community_lists,
         }
     }
+
+    /// Returns the FRR commands to configure the community lists of the route map.
+    pub fn community_list_commands(&self) -> Vec<String> {
+        self.community_lists
+            .iter()
+            .flat_map(|(list, entries)| entries.iter().map(move |e| community_list_entry(list, e)))
+            .collect()
+    }
+}
+
+/// FRR command to configure an entry of a standard community list.
+pub fn community_list_entry(list: &str, entry: &str) -> String {
+    format!("bgp community-list standard {} permit {}", list, entry)
 }
 
 /// Information about the static route
@@ -1112,6 +1464,23 @@ pub struct StaticRouteInfo {
     pub next_hop: String,
 }
 
+impl StaticRouteInfo {
+    /// Returns the FRR command to configure the static route, either `ip route` or `ipv6 route`.
+    ///
+    /// ```
+    /// # use snowcap_runtime::physical_network::{IpAddr, StaticRouteInfo};
+    /// let sr = StaticRouteInfo {
+    ///     addr: IpAddr::new("fd00::200.0.0.0", 104),
+    ///     next_hop: String::from("fd00::10.1.0.2"),
+    /// };
+    /// assert_eq!(sr.command(), "ipv6 route fd00::200.0.0.0/104 fd00::10.1.0.2");
+    /// ```
+    pub fn command(&self) -> String {
+        let ip = if self.addr.is_v6() { "ipv6" } else { "ip" };
+        format!("{} route {} {}", ip, self.addr, self.next_hop)
+    }
+}
+
 /// Interface Information
 #[derive(Debug, Clone, PartialEq)]
 pub struct IfaceInfo {
@@ -1238,6 +1607,25 @@ impl IpAddr {
         ]
     }
 
+    /// Returns the IPv6 address of a dual-stack router, which embeds the IPv4 address in the unique
+    /// local prefix `fd00::/96`.
+    ///
+    /// ```
+    /// # use snowcap_runtime::physical_network::IpAddr;
+    /// let addr = IpAddr::new("10.1.3.1", 24);
+    /// assert_eq!(addr.to_v6(), IpAddr::new("fd00::10.1.3.1", 120));
+    /// assert!(addr.to_v6().is_v6());
+    /// assert!(!addr.is_v6());
+    /// ```
+    pub fn to_v6(&self) -> Self {
+        Self::new(format!("fd00::{}", self.addr), self.mask + 96)
+    }
+
+    /// Returns `true` if the address is an IPv6 address.
+    pub fn is_v6(&self) -> bool {
+        self.addr.contains(':')
+    }
+
     /// create an IP address from a string of the shape X.X.X.X/X
     ///
     /// ```
@@ -1285,4 +1673,68 @@ mod test {
         assert_eq!(&IpAddr::new("", 24).repr_mask(), "255.255.255.0");
         assert_eq!(&IpAddr::new("", 25).repr_mask(), "255.255.255.128");
     }
+
+    #[test]
+    fn route_map_community_lists() {
+        let map = RouteMapBuilder::new()
+            .order(10)
+            .allow()
+            .match_community_any(vec![100, 200])
+            .delete_community(300)
+            .delete_communities(vec![400, 300])
+            .build();
+        let rm = RouteMapInfo::from_route_map(
+            String::from("r0_RM_0"),
+            RouterId::new(0),
+            RouteMapDirection::Incoming,
+            &map,
+            &[],
+        );
+        assert_eq!(rm.match_statements.get("community").unwrap(), "r0_RM_0_CL_match");
+        assert_eq!(rm.set_statements.get("comm-list").unwrap(), "r0_RM_0_CL_del delete");
+        assert_eq!(
+            rm.community_list_commands(),
+            vec![
+                "bgp community-list standard r0_RM_0_CL_match permit 100",
+                "bgp community-list standard r0_RM_0_CL_match permit 200",
+                "bgp community-list standard r0_RM_0_CL_del permit 300",
+                "bgp community-list standard r0_RM_0_CL_del permit 400",
+            ]
+        );
+
+        let map =
+            RouteMapBuilder::new().order(10).deny().match_community_all(vec![100, 200]).build();
+        let rm = RouteMapInfo::from_route_map(
+            String::from("r0_RM_1"),
+            RouterId::new(0),
+            RouteMapDirection::Incoming,
+            &map,
+            &[],
+        );
+        assert!(rm.set_statements.is_empty());
+        assert_eq!(
+            rm.community_list_commands(),
+            vec!["bgp community-list standard r0_RM_1_CL_match permit 100 200"]
+        );
+    }
+
+    #[test]
+    fn parallel_map_keeps_order() {
+        let running = Arc::new(Mutex::new((0, 0)));
+        let r = running.clone();
+        let result = parallel_map((0..50).collect(), 4, move |x: u32| {
+            {
+                let mut r = r.lock().unwrap();
+                r.0 += 1;
+                r.1 = r.1.max(r.0);
+            }
+            thread::sleep(Duration::from_millis(u64::from(x % 3)));
+            r.lock().unwrap().0 -= 1;
+            x * 2
+        });
+        assert_eq!(result, (0..50).map(|x| x * 2).collect::<Vec<_>>());
+        // never more than 4 jobs at the same time
+        assert!(running.lock().unwrap().1 <= 4);
+        assert!(parallel_map(Vec::<u32>::new(), 4, |x| x).is_empty());
+    }
 }
@@ -0,0 +1,168 @@
+// Snowcap: Synthesizing Network-Wide Configuration Updates
+// Copyright (C) 2021  Tibor Schneider
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Rollback
+//!
+//! This module returns the running network to its initial state, after a step of the migration
+//! violated an invariant. All modifiers that were applied are reversed (see
+//! [`ConfigModifier::reverse`]), and applied in the opposite order, while capturing the probes for
+//! every step. Afterwards, the reachability of every prefix is verified with traceroute.
+
+use snowcap::netsim::config::ConfigModifier;
+use snowcap::netsim::{printer, Network};
+
+use log::*;
+use serde::Serialize;
+use std::error::Error;
+
+use super::checker::{self, StepVerdict};
+use super::physical_network::PhysicalNetwork;
+
+/// # Result of a rollback
+/// Trace of the rollback, and the reachability of every prefix afterwards.
+#[derive(Debug, Clone, Serialize)]
+pub struct RollbackReport {
+    /// Position of the step in the migration sequence, which violated an invariant.
+    pub violated_step: usize,
+    /// Verdict of every step of the rollback, in the order in which they were applied.
+    pub steps: Vec<StepVerdict>,
+    /// Reachability of every prefix from every router, after the rollback.
+    pub reachability: Vec<RollbackReachability>,
+}
+
+impl RollbackReport {
+    /// Returns `true` if the reachability after the rollback is the same as in the initial state.
+    pub fn is_ok(&self) -> bool {
+        self.reachability.iter().all(|r| r.reachable == r.expected_reachable)
+    }
+}
+
+/// # Reachability after the rollback
+/// Reachability of a prefix from a single router, measured with traceroute.
+#[derive(Debug, Clone, Serialize)]
+pub struct RollbackReachability {
+    /// Name of the router.
+    pub router: String,
+    /// Prefix, towards which the traceroute was performed.
+    pub prefix: u32,
+    /// Wether the traceroute reached the prefix.
+    pub reachable: bool,
+    /// Wether the prefix is reachable in the initial state of the simulation.
+    pub expected_reachable: bool,
+    /// Path of the traceroute (empty if it did not reach the prefix).
+    pub path: Vec<String>,
+}
+
+/// Returns the sequence that undoes all `applied` modifiers, by reversing every modifier and the
+/// order in which they are applied.
+pub fn rollback_sequence(applied: &[ConfigModifier]) -> Vec<ConfigModifier> {
+    applied.iter().rev().cloned().map(ConfigModifier::reverse).collect()
+}
+
+/// Undo all `applied` modifiers on the physical network, and on `sim_net` (which must be in the
+/// same state as the physical network). Every step of the rollback is checked in the same way as
+/// the migration (see [`checker::step_verdict`]). Afterwards, the reachability of every prefix is
+/// compared to the `initial` network.
+pub fn rollback(
+    phys_net: &mut PhysicalNetwork,
+    sim_net: &mut Network,
+    initial: &Network,
+    applied: &[ConfigModifier],
+    violated_step: usize,
+) -> Result<RollbackReport, Box<dyn Error>> {
+    let mut steps = Vec::new();
+    for (step, modifier) in rollback_sequence(applied).iter().enumerate() {
+        let modifier_repr = printer::config_modifier(sim_net, modifier)?;
+        info!("Rolling back: {}", modifier_repr);
+        let flows = phys_net.apply_modifier_wait_convergence_check_flows(modifier)?;
+
+        let mut fw_before = sim_net.get_forwarding_state();
+        sim_net.apply_modifier(modifier)?;
+        let mut fw_after = sim_net.get_forwarding_state();
+        steps.push(checker::step_verdict(
+            step,
+            modifier_repr,
+            &flows,
+            &phys_net.read_transient_anomalies()?,
+            &mut fw_before,
+            &mut fw_after,
+            phys_net,
+        ));
+    }
+
+    info!("Verifying the reachability after the rollback");
+    let mut fw_state = initial.get_forwarding_state();
+    let mut all_paths = phys_net.get_all_paths()?.into_iter().collect::<Vec<_>>();
+    all_paths.sort_by_key(|(router, _)| *router);
+    let mut reachability = Vec::new();
+    for (router, paths) in all_paths {
+        let mut paths = paths.into_iter().collect::<Vec<_>>();
+        paths.sort_by_key(|(prefix, _)| *prefix);
+        for (prefix, path) in paths {
+            let r = RollbackReachability {
+                router: phys_net.router_name(router).to_string(),
+                prefix: prefix.0,
+                reachable: path.is_some(),
+                expected_reachable: fw_state.get_route(router, prefix).is_ok(),
+                path: path
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|r| phys_net.router_name(r).to_string())
+                    .collect(),
+            };
+            if r.reachable != r.expected_reachable {
+                warn!(
+                    "After the rollback, {} {} prefix {}",
+                    r.router,
+                    if r.reachable { "still reaches" } else { "cannot reach" },
+                    r.prefix
+                );
+            }
+            reachability.push(r);
+        }
+    }
+
+    Ok(RollbackReport { violated_step, steps, reachability })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use snowcap::example_networks::{ExampleNetwork, SimpleNet};
+
+    #[test]
+    fn rollback_restores_initial_state() {
+        let mut net = SimpleNet::net(0);
+        let initial = net.clone();
+        let final_config = SimpleNet::final_config(&net, 0);
+        let sequence = net.current_config().get_diff(&final_config).modifiers;
+        assert!(!sequence.is_empty());
+
+        for modifier in sequence.iter() {
+            net.apply_modifier(modifier).unwrap();
+        }
+        assert_ne!(net.current_config(), initial.current_config());
+
+        let rollback = rollback_sequence(&sequence);
+        assert_eq!(rollback.len(), sequence.len());
+        for modifier in rollback.iter() {
+            net.apply_modifier(modifier).unwrap();
+        }
+        assert_eq!(net.current_config(), initial.current_config());
+        assert_eq!(net.get_forwarding_state(), initial.get_forwarding_state());
+    }
+}